@@ -15,13 +15,27 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::conversions::string_view_array_from_display_iter;
-use crate::{RecordBatchIterator, DEFAULT_BATCH_SIZE};
-use arrow::array::{BinaryArray, Int64Array, RecordBatch};
+use crate::conversions::{
+    geometry_to_wkb, geometry_to_wkb_unclosed, string_view_array_from_display_iter,
+};
+use crate::lineage::{add_lineage, lineage_schema, row_lineage};
+use crate::nulls::{null_rates, NullRates};
+use crate::{RecordBatchIterator, Table, DEFAULT_BATCH_SIZE, GEOMETRY_TYPE_KEY};
+use arrow::array::{
+    ArrayRef, BinaryArray, BooleanArray, Int64Array, RecordBatch, RecordBatchOptions,
+    StringViewArray,
+};
 use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
-use geo::Geometry;
-use geozero::{CoordDimensions, ToWkb};
-use spatialbench::generators::{BuildingGenerator, BuildingGeneratorIterator};
+use geo::Centroid;
+use spatialbench::generators::{
+    Building, BuildingGenerator, BuildingGeneratorIterator, Projection,
+};
+use spatialbench::spatial::crs::{self, Crs};
+use spatialbench::spatial::dimensions::{self, building_height, elevation, Dimensions};
+use spatialbench::spatial::duplicate::{self, DuplicateKind};
+use spatialbench::spatial::invalid::{self, InvalidKind};
+use spatialbench::spatial::GeometryKind;
+use std::collections::HashMap;
 use std::sync::{Arc, LazyLock};
 
 /// Generate [`Building`]s in [`RecordBatch`] format
@@ -41,15 +55,57 @@ use std::sync::{Arc, LazyLock};
 /// let batch = arrow_generator.next().unwrap();
 /// ```
 pub struct BuildingArrow {
+    /// Generates the buildings copied by the first row of a batch
+    generator: BuildingGenerator<'static>,
     inner: BuildingGeneratorIterator<'static>,
     batch_size: usize,
+    projection: Vec<usize>,
+    /// The schema of all the columns
+    table_schema: SchemaRef,
+    schema: SchemaRef,
+    dimensions: Dimensions,
+    crs: Crs,
+    seed: u64,
+    null_rates: NullRates,
+    row_lineage: bool,
+    /// Whether the boundaries may be multi polygons
+    multi_polygons: bool,
+    invalid_rate: f64,
+    duplicate_rate: f64,
 }
 
 impl BuildingArrow {
     pub fn new(generator: BuildingGenerator<'static>) -> Self {
+        // declare the boundaries may be multi polygons, which may only
+        // appear after the first rows
+        let multi_polygons = generator
+            .spatial_generator()
+            .geometry_mix
+            .fraction(GeometryKind::MultiPolygon);
+        let multi_polygons = multi_polygons > 0.0;
+        let invalid_rate = invalid::invalid_rate();
+        let duplicate_rate = duplicate::duplicate_rate();
+        let null_rates = null_rates().clone();
+        let row_lineage = row_lineage();
+        let schema = null_rates.nullable_schema(
+            Table::Building,
+            building_schema(multi_polygons, invalid_rate > 0.0, duplicate_rate > 0.0),
+        );
         Self {
             inner: generator.iter(),
             batch_size: DEFAULT_BATCH_SIZE,
+            projection: (0..schema.fields().len()).collect(),
+            table_schema: schema.clone(),
+            schema: lineage_schema(schema, row_lineage),
+            dimensions: dimensions::dimensions(),
+            crs: crs::crs(),
+            seed: generator.seed(),
+            null_rates,
+            row_lineage,
+            multi_polygons,
+            invalid_rate,
+            duplicate_rate,
+            generator,
         }
     }
 
@@ -58,11 +114,169 @@ impl BuildingArrow {
         self.batch_size = batch_size;
         self
     }
+
+    /// Only generate the columns at the `projection` indices of the schema
+    pub fn with_projection(mut self, projection: Vec<usize>) -> Self {
+        let schema = self
+            .table_schema
+            .project(&projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(Arc::new(schema), self.row_lineage);
+        // the flags after the boundary only depend on the key
+        let columns = projection.iter().map(|&column| match column {
+            0..=2 => column,
+            _ => 0,
+        });
+        self.inner = self.inner.with_projection(Projection::new(columns));
+        self.projection = projection;
+        self
+    }
+
+    /// Make a fraction of the values of the columns of `null_rates` null or
+    /// empty
+    ///
+    /// Defaults to the rates set with [`set_null_rates`](crate::nulls::set_null_rates).
+    pub fn with_null_rates(mut self, null_rates: NullRates) -> Self {
+        self.table_schema = null_rates.nullable_schema(
+            Table::Building,
+            building_schema(
+                self.multi_polygons,
+                self.invalid_rate > 0.0,
+                self.duplicate_rate > 0.0,
+            ),
+        );
+        let schema = self
+            .table_schema
+            .project(&self.projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(Arc::new(schema), self.row_lineage);
+        self.null_rates = null_rates;
+        self
+    }
+
+    /// Add the `_rowgen` column with the lineage of each row
+    ///
+    /// Defaults to the value set with [`set_row_lineage`](crate::lineage::set_row_lineage).
+    pub fn with_row_lineage(mut self, row_lineage: bool) -> Self {
+        self.schema = lineage_schema(Arc::clone(&self.schema), row_lineage);
+        self.row_lineage = row_lineage;
+        self
+    }
+
+    /// Write the boundaries with `dimensions`
+    ///
+    /// The boundaries are extruded to their roof: the Z of every vertex is
+    /// the [`elevation`] of the terrain at the centroid plus the
+    /// [`building_height`]. Defaults to the dimensions set with
+    /// [`dimensions::set_dimensions`].
+    pub fn with_dimensions(mut self, dimensions: Dimensions) -> Self {
+        self.dimensions = dimensions;
+        self
+    }
+
+    /// Project the boundaries to `crs`
+    ///
+    /// Defaults to the CRS set with [`crs::set_crs`].
+    pub fn with_crs(mut self, crs: Crs) -> Self {
+        self.crs = crs;
+        self
+    }
+
+    /// Make the boundaries of a fraction `rate` of the buildings invalid,
+    /// flagged in a `b_isvalid` column
+    ///
+    /// Defaults to the rate set with [`invalid::set_invalid_rate`]. All the
+    /// columns are generated, as the schema gains or loses the flag.
+    pub fn with_invalid_rate(mut self, rate: f64) -> Self {
+        self.invalid_rate = rate;
+        self.reset_schema()
+    }
+
+    /// Make a fraction `rate` of the buildings duplicates of the building
+    /// before them, exact or near, pointed to by the `b_duplicateof` and
+    /// `b_duplicatekind` columns
+    ///
+    /// A duplicate has the name, height and validity of the building it
+    /// copies. Defaults to the rate set with
+    /// [`duplicate::set_duplicate_rate`]. All the columns are generated, as
+    /// the schema gains or loses the columns.
+    pub fn with_duplicate_rate(mut self, rate: f64) -> Self {
+        self.duplicate_rate = rate;
+        self.reset_schema()
+    }
+
+    /// Generate all the columns of the schema of the current rates
+    fn reset_schema(mut self) -> Self {
+        self.table_schema = self.null_rates.nullable_schema(
+            Table::Building,
+            building_schema(
+                self.multi_polygons,
+                self.invalid_rate > 0.0,
+                self.duplicate_rate > 0.0,
+            ),
+        );
+        self.schema = lineage_schema(Arc::clone(&self.table_schema), self.row_lineage);
+        self.projection = (0..self.schema.fields().len()).collect();
+        self.inner = self.inner.with_projection(Projection::default());
+        self
+    }
+
+    /// Return how `building` duplicates the building before it, if it does
+    fn duplicate_kind(&self, building: &Building) -> Option<DuplicateKind> {
+        DuplicateKind::of_row(
+            building.b_buildingkey as u64,
+            self.seed,
+            self.duplicate_rate,
+        )
+    }
+
+    /// Return the key of the building `building` copies, or its own key
+    fn source_key(&self, building: &Building) -> i64 {
+        match self.duplicate_kind(building) {
+            Some(_) => building.b_buildingkey - 1,
+            None => building.b_buildingkey,
+        }
+    }
+
+    /// Return how the boundary of `building` is made invalid, if it is,
+    /// the same as the building it copies
+    fn invalid_kind(&self, building: &Building) -> Option<InvalidKind> {
+        InvalidKind::of_row(
+            self.source_key(building) as u64,
+            self.seed,
+            self.invalid_rate,
+        )
+    }
+
+    /// Convert the boundary of `building` to WKB
+    fn boundary_wkb(&self, building: &Building) -> Vec<u8> {
+        let roof = if self.dimensions.has_z() {
+            let ground = building
+                .b_boundary
+                .centroid()
+                .map_or(0.0, |center| elevation(center.x(), center.y()));
+            ground + building_height(self.source_key(building), self.seed)
+        } else {
+            0.0
+        };
+        match self.invalid_kind(building) {
+            None => geometry_to_wkb(&building.b_boundary, self.crs, self.dimensions, |_| roof),
+            Some(InvalidKind::UnclosedRing) => {
+                geometry_to_wkb_unclosed(&building.b_boundary, self.crs, self.dimensions, |_| roof)
+            }
+            Some(kind) => geometry_to_wkb(
+                &kind.apply(building.b_boundary.clone()),
+                self.crs,
+                self.dimensions,
+                |_| roof,
+            ),
+        }
+    }
 }
 
 impl RecordBatchIterator for BuildingArrow {
     fn schema(&self) -> &SchemaRef {
-        &BUILDING_SCHEMA
+        &self.schema
     }
 }
 
@@ -71,32 +285,72 @@ impl Iterator for BuildingArrow {
 
     fn next(&mut self) -> Option<Self::Item> {
         // Get next rows to convert
-        let rows: Vec<_> = self.inner.by_ref().take(self.batch_size).collect();
+        let mut rows: Vec<_> = self.inner.by_ref().take(self.batch_size).collect();
         if rows.is_empty() {
             return None;
         }
 
-        let buildingkey = Int64Array::from_iter_values(rows.iter().map(|r| r.b_buildingkey));
-        let name = string_view_array_from_display_iter(rows.iter().map(|r| &r.b_name));
-
-        // Convert geo::Polygon to WKB binary format
-        let wkb_array = BinaryArray::from_iter_values(rows.iter().map(|r| {
-            Geometry::Polygon(r.b_boundary.clone())
-                .to_wkb(CoordDimensions::xy())
-                .unwrap()
-        }));
+        // Copy the buildings before the duplicates, which are not duplicates
+        for i in 0..rows.len() {
+            let Some(kind) = self.duplicate_kind(&rows[i]) else {
+                continue;
+            };
+            let key = rows[i].b_buildingkey;
+            let source = match i {
+                0 => self.generator.building(key - 1),
+                _ => rows[i - 1].clone(),
+            };
+            rows[i].b_name = source.b_name;
+            rows[i].b_boundary = kind.apply(source.b_boundary, key as u64, self.seed);
+        }
 
-        let batch = RecordBatch::try_new(
-            Arc::clone(self.schema()),
-            vec![Arc::new(buildingkey), Arc::new(name), Arc::new(wkb_array)],
-        )
-        .unwrap();
+        // Only convert the projected columns
+        let columns = self
+            .projection
+            .iter()
+            .map(|&column| -> ArrayRef {
+                // the columns after the boundary depend on the rates
+                match self.table_schema.field(column).name().as_str() {
+                    "b_buildingkey" => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|r| r.b_buildingkey),
+                    )),
+                    "b_name" => Arc::new(string_view_array_from_display_iter(
+                        rows.iter().map(|r| &r.b_name),
+                    )),
+                    "b_boundary" => Arc::new(BinaryArray::from_iter_values(
+                        rows.iter().map(|r| self.boundary_wkb(r)),
+                    )),
+                    "b_isvalid" => Arc::new(BooleanArray::from(
+                        rows.iter()
+                            .map(|r| self.invalid_kind(r).is_none())
+                            .collect::<Vec<_>>(),
+                    )),
+                    "b_duplicateof" => Arc::new(Int64Array::from_iter(
+                        rows.iter()
+                            .map(|r| self.duplicate_kind(r).map(|_| r.b_buildingkey - 1)),
+                    )),
+                    "b_duplicatekind" => Arc::new(StringViewArray::from_iter(
+                        rows.iter()
+                            .map(|r| self.duplicate_kind(r).map(|kind| kind.to_string())),
+                    )),
+                    _ => unreachable!("projection is checked against the schema"),
+                }
+            })
+            .collect();
+        let keys: Vec<i64> = rows.iter().map(|r| r.b_buildingkey).collect();
+        let columns = self
+            .null_rates
+            .apply(Table::Building, &self.schema, &keys, columns);
+        let columns = add_lineage(columns, &keys, self.seed, self.row_lineage);
+        let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+        let batch = RecordBatch::try_new_with_options(Arc::clone(self.schema()), columns, &options)
+            .unwrap();
         Some(batch)
     }
 }
 
 /// Schema for the Building
-static BUILDING_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_building_schema);
+pub(crate) static BUILDING_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_building_schema);
 fn make_building_schema() -> SchemaRef {
     Arc::new(Schema::new(vec![
         Field::new("b_buildingkey", DataType::Int64, false),
@@ -104,3 +358,26 @@ fn make_building_schema() -> SchemaRef {
         Field::new("b_boundary", DataType::Binary, false),
     ]))
 }
+
+/// Schema for the Building, with a mix of polygons and multi polygons if
+/// `multi_polygons`, the `b_isvalid` flag of the boundaries if `flagged`,
+/// and the `b_duplicateof` and `b_duplicatekind` columns if `duplicates`
+pub(crate) fn building_schema(multi_polygons: bool, flagged: bool, duplicates: bool) -> SchemaRef {
+    let mut fields: Vec<_> = BUILDING_SCHEMA.fields().iter().cloned().collect();
+    if multi_polygons {
+        let metadata = HashMap::from([(GEOMETRY_TYPE_KEY.to_string(), "MultiPolygon".to_string())]);
+        fields[2] = Arc::new(fields[2].as_ref().clone().with_metadata(metadata));
+    }
+    if flagged {
+        fields.push(Arc::new(Field::new("b_isvalid", DataType::Boolean, false)));
+    }
+    if duplicates {
+        fields.push(Arc::new(Field::new("b_duplicateof", DataType::Int64, true)));
+        fields.push(Arc::new(Field::new(
+            "b_duplicatekind",
+            DataType::Utf8View,
+            true,
+        )));
+    }
+    Arc::new(Schema::new(fields))
+}
@@ -0,0 +1,232 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Change data capture (CDC) stream of the trip table
+//!
+//! [`TripCdcArrow`] generates the trips as an ordered stream of insert,
+//! update and delete events, in commits of a fixed number of trips: each
+//! commit inserts the next trips, as they are picked up, without their
+//! dropoff columns, updates the trips inserted by the previous commit with
+//! them, and deletes every [`DELETE_DIVISOR`]th trip of those updated by
+//! the commit before. The commits have increasing timestamps, one
+//! [`COMMIT_INTERVAL_MILLIS`] apart from [`COMMIT_START_MILLIS`].
+
+use crate::{RecordBatchIterator, TripArrow, DEFAULT_BATCH_SIZE};
+use arrow::array::{
+    new_null_array, ArrayRef, AsArray, BooleanArray, Int64Array, RecordBatch, StringViewArray,
+    TimestampMillisecondArray,
+};
+use arrow::compute::filter_record_batch;
+use arrow::datatypes::{DataType, Field, Int64Type, Schema, SchemaRef, TimeUnit};
+use spatialbench::generators::TripGenerator;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+/// Every trip whose key is a multiple of this is deleted
+pub const DELETE_DIVISOR: i64 = 100;
+
+/// Timestamp of the first commit, 2024-01-01 00:00:00 UTC
+pub const COMMIT_START_MILLIS: i64 = 1_704_067_200_000;
+
+/// Time between two commits, one second
+pub const COMMIT_INTERVAL_MILLIS: i64 = 1000;
+
+/// The columns of a trip that are only known at its dropoff
+const DROPOFF_COLUMNS: [&str; 6] = [
+    "t_dropofftime",
+    "t_fare",
+    "t_tip",
+    "t_totalamount",
+    "t_distance",
+    "t_dropoffloc",
+];
+
+/// The operation of a CDC event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CdcOp {
+    /// A new trip, picked up, without its dropoff columns
+    Insert,
+    /// The trip with all its columns, dropped off
+    Update,
+    /// The trip is deleted, with its last columns
+    Delete,
+}
+
+impl fmt::Display for CdcOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CdcOp::Insert => write!(f, "insert"),
+            CdcOp::Update => write!(f, "update"),
+            CdcOp::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+/// Generate the trips as a CDC stream of [`RecordBatch`]es
+///
+/// Each batch has the events of one operation of one commit: the
+/// `_commit` number (from 1), the `_commit_ts` timestamp and the `_op`
+/// (`insert`, `update` or `delete`) of the events, followed by the columns
+/// of the trips. The trips are inserted, updated and deleted in the order
+/// of their keys, and after the last trips are inserted, two more commits
+/// update and delete them.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::TripGenerator;
+/// # use spatialbench_arrow::cdc::TripCdcArrow;
+/// # use arrow::array::{AsArray, RecordBatch};
+/// let cdc = TripCdcArrow::new(TripGenerator::new(0.001, 1, 1)).with_commit_size(1000);
+/// let batches: Vec<_> = cdc.collect();
+/// let op = |batch: &RecordBatch| batch.column(2).as_string_view().value(0).to_string();
+/// let events: Vec<_> = batches.iter().map(|b| (op(b), b.num_rows())).collect();
+/// // the first commit inserts 1000 trips, the second inserts 1000 more and
+/// // updates the first ones, and the third also deletes 10 of them
+/// assert_eq!(events[..6], [
+///     ("insert".to_string(), 1000),
+///     ("insert".to_string(), 1000),
+///     ("update".to_string(), 1000),
+///     ("insert".to_string(), 1000),
+///     ("update".to_string(), 1000),
+///     ("delete".to_string(), 10),
+/// ]);
+/// // 6 commits insert the 6000 trips, and 2 more update and delete the last
+/// assert_eq!(events.len(), 6 + 6 + 6);
+/// ```
+pub struct TripCdcArrow {
+    trips: TripArrow,
+    commit_size: usize,
+    /// Number of the last commit
+    commit: i64,
+    /// The trips inserted by the last commit, updated by the next one
+    inserted: Option<RecordBatch>,
+    /// The trips updated by the last commit, some deleted by the next one
+    updated: Option<RecordBatch>,
+    /// The batches of the last commit not returned yet
+    pending: VecDeque<RecordBatch>,
+    schema: SchemaRef,
+}
+
+impl TripCdcArrow {
+    pub fn new(generator: TripGenerator) -> Self {
+        let trips = TripArrow::new(generator).with_batch_size(DEFAULT_BATCH_SIZE);
+        let schema = cdc_schema(trips.schema());
+        Self {
+            trips,
+            commit_size: DEFAULT_BATCH_SIZE,
+            commit: 0,
+            inserted: None,
+            updated: None,
+            pending: VecDeque::new(),
+            schema,
+        }
+    }
+
+    /// Set the number of trips inserted by each commit
+    pub fn with_commit_size(mut self, commit_size: usize) -> Self {
+        self.trips = self.trips.with_batch_size(commit_size);
+        self.commit_size = commit_size;
+        self
+    }
+
+    /// Return the events of `trips` with `op` in the current commit
+    fn events(&self, op: CdcOp, trips: &RecordBatch) -> RecordBatch {
+        let rows = trips.num_rows();
+        let timestamp = COMMIT_START_MILLIS + (self.commit - 1) * COMMIT_INTERVAL_MILLIS;
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from_value(self.commit, rows)),
+            Arc::new(TimestampMillisecondArray::from_value(timestamp, rows)),
+            Arc::new(StringViewArray::from_iter_values(std::iter::repeat_n(
+                op.to_string(),
+                rows,
+            ))),
+        ];
+        for (field, column) in trips.schema_ref().fields().iter().zip(trips.columns()) {
+            if op == CdcOp::Insert && DROPOFF_COLUMNS.contains(&field.name().as_str()) {
+                columns.push(new_null_array(field.data_type(), rows));
+            } else {
+                columns.push(Arc::clone(column));
+            }
+        }
+        RecordBatch::try_new(Arc::clone(&self.schema), columns)
+            .expect("the columns match the schema")
+    }
+}
+
+impl RecordBatchIterator for TripCdcArrow {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+}
+
+impl Iterator for TripCdcArrow {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            let trips = self.trips.next();
+            if trips.is_none() && self.inserted.is_none() && self.updated.is_none() {
+                return None;
+            }
+            self.commit += 1;
+            if let Some(trips) = &trips {
+                self.pending.push_back(self.events(CdcOp::Insert, trips));
+            }
+            if let Some(inserted) = &self.inserted {
+                self.pending.push_back(self.events(CdcOp::Update, inserted));
+            }
+            if let Some(updated) = &self.updated {
+                let keys = updated.column(0).as_primitive::<Int64Type>();
+                let deleted = BooleanArray::from_iter(
+                    keys.iter()
+                        .map(|key| key.map(|key| key % DELETE_DIVISOR == 0)),
+                );
+                let deleted =
+                    filter_record_batch(updated, &deleted).expect("the mask has the rows");
+                if deleted.num_rows() > 0 {
+                    self.pending.push_back(self.events(CdcOp::Delete, &deleted));
+                }
+            }
+            self.updated = self.inserted.take();
+            self.inserted = trips;
+        }
+        self.pending.pop_front()
+    }
+}
+
+/// Return the schema of the CDC stream of trips with `schema`, with the
+/// dropoff columns nullable
+fn cdc_schema(schema: &SchemaRef) -> SchemaRef {
+    let mut fields = vec![
+        Arc::new(Field::new("_commit", DataType::Int64, false)),
+        Arc::new(Field::new(
+            "_commit_ts",
+            DataType::Timestamp(TimeUnit::Millisecond, None),
+            false,
+        )),
+        Arc::new(Field::new("_op", DataType::Utf8View, false)),
+    ];
+    for field in schema.fields() {
+        if DROPOFF_COLUMNS.contains(&field.name().as_str()) {
+            fields.push(Arc::new(field.as_ref().clone().with_nullable(true)));
+        } else {
+            fields.push(Arc::clone(field));
+        }
+    }
+    Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+}
@@ -17,10 +17,15 @@
 
 //! Routines to convert TPCH types to Arrow types
 
-use arrow::array::{StringViewArray, StringViewBuilder};
+use arrow::array::{ArrayRef, Int64Array, StringViewArray, StringViewBuilder};
+use arrow::datatypes::{DataType, Field};
+use geo::{Coord, Geometry, LineString, Point, Polygon};
 use spatialbench::dates::TPCHDate;
 use spatialbench::decimal::TPCHDecimal;
+use spatialbench::spatial::cell::{geohash, CellSystem};
+use spatialbench::spatial::{CellIndex, Crs, Dimensions};
 use std::fmt::Write;
+use std::sync::Arc;
 
 /// Convert a TPCHDecimal to an Arrow Decimal(15,2)
 #[inline(always)]
@@ -87,6 +92,245 @@ where
     builder.finish()
 }
 
+/// Converts a geometry without measures to little endian ISO WKB with
+/// `dimensions`, ignoring the M dimension
+///
+/// The longitude / latitude coordinates are projected to `crs`. With a Z
+/// dimension, each coordinate has the Z value `z(coord)` of the coordinate
+/// before it is projected, called for the coordinates in the order they
+/// are written.
+pub fn geometry_to_wkb(
+    geometry: &Geometry,
+    crs: Crs,
+    dimensions: Dimensions,
+    z: impl FnMut(Coord) -> f64,
+) -> Vec<u8> {
+    geometry_to_wkb_with_m(geometry, crs, dimensions.without_m(), z, |_| f64::NAN)
+}
+
+/// Converts a geometry to little endian ISO WKB with `dimensions`
+///
+/// As [`geometry_to_wkb`], and with an M dimension the coordinate at index
+/// `i` (in the order they are written) has the M value `m(i)`.
+pub fn geometry_to_wkb_with_m(
+    geometry: &Geometry,
+    crs: Crs,
+    dimensions: Dimensions,
+    mut z: impl FnMut(Coord) -> f64,
+    mut m: impl FnMut(usize) -> f64,
+) -> Vec<u8> {
+    let mut writer = WkbWriter {
+        buffer: Vec::with_capacity(64),
+        crs,
+        dimensions,
+        z: &mut z,
+        m: &mut m,
+        index: 0,
+        close_rings: true,
+    };
+    writer.write_geometry(geometry);
+    writer.buffer
+}
+
+/// Converts a geometry to WKB as [`geometry_to_wkb`], with the rings of the
+/// polygons written without their closing coordinate
+///
+/// The rings are unclosed, so the polygons are invalid.
+pub fn geometry_to_wkb_unclosed(
+    geometry: &Geometry,
+    crs: Crs,
+    dimensions: Dimensions,
+    mut z: impl FnMut(Coord) -> f64,
+) -> Vec<u8> {
+    let mut writer = WkbWriter {
+        buffer: Vec::with_capacity(64),
+        crs,
+        dimensions: dimensions.without_m(),
+        z: &mut z,
+        m: &mut |_| f64::NAN,
+        index: 0,
+        close_rings: false,
+    };
+    writer.write_geometry(geometry);
+    writer.buffer
+}
+
+/// Return the empty geometry of the type and dimensions of the little
+/// endian ISO WKB `wkb`
+///
+/// An empty point has NaN coordinates, and the other geometries have no
+/// coordinates, rings or parts.
+pub fn empty_wkb(wkb: &[u8]) -> Vec<u8> {
+    let code = u32::from_le_bytes(wkb[1..5].try_into().expect("a WKB header"));
+    let mut buffer = wkb[..5].to_vec();
+    if code % 1000 == 1 {
+        // 1000 for Z, 2000 for M and 3000 for both
+        let dimensions = match code / 1000 {
+            0 => 2,
+            1 | 2 => 3,
+            _ => 4,
+        };
+        for _ in 0..dimensions {
+            buffer.extend_from_slice(&f64::NAN.to_le_bytes());
+        }
+    } else {
+        buffer.extend_from_slice(&0u32.to_le_bytes());
+    }
+    buffer
+}
+
+/// Return the fields of the cell index columns of the point `columns`,
+/// the `indexes` of each column in turn
+///
+/// The H3 and S2 cells are Int64 ids and the geohashes strings. They are
+/// nullable, as the cells of null and empty points are null (see
+/// [`NullRates::apply`](crate::nulls::NullRates::apply)).
+pub fn cell_index_fields(columns: &[&str], indexes: &[CellIndex]) -> Vec<Field> {
+    columns
+        .iter()
+        .flat_map(|column| {
+            indexes.iter().map(move |index| {
+                let data_type = match index.system {
+                    CellSystem::Geohash => DataType::Utf8View,
+                    CellSystem::H3 | CellSystem::S2 => DataType::Int64,
+                };
+                Field::new(index.column_name(column), data_type, true)
+            })
+        })
+        .collect()
+}
+
+/// Return the cells of `points` with `index`, in the type of
+/// [`cell_index_fields`]
+pub fn cell_index_array<'a, I>(index: &CellIndex, points: I) -> ArrayRef
+where
+    I: Iterator<Item = &'a Point>,
+{
+    match index.system {
+        CellSystem::Geohash => Arc::new(StringViewArray::from_iter_values(
+            points.map(|point| geohash(point.x(), point.y(), index.resolution)),
+        )),
+        CellSystem::H3 | CellSystem::S2 => Arc::new(Int64Array::from_iter_values(
+            points.map(|point| index.cell_id(point.x(), point.y())),
+        )),
+    }
+}
+
+struct WkbWriter<'a> {
+    buffer: Vec<u8>,
+    crs: Crs,
+    dimensions: Dimensions,
+    z: &'a mut dyn FnMut(Coord) -> f64,
+    m: &'a mut dyn FnMut(usize) -> f64,
+    /// Index of the next coordinate
+    index: usize,
+    /// Whether the rings end with their first coordinate
+    close_rings: bool,
+}
+
+impl WkbWriter<'_> {
+    fn write_geometry(&mut self, geometry: &Geometry) {
+        match geometry {
+            Geometry::Point(point) => {
+                self.write_header(1);
+                self.write_coord(point.0);
+            }
+            Geometry::Line(line) => {
+                self.write_geometry(&Geometry::LineString(LineString::from(*line)))
+            }
+            Geometry::LineString(line) => {
+                self.write_header(2);
+                self.write_coords(line);
+            }
+            Geometry::Polygon(polygon) => self.write_polygon(polygon),
+            Geometry::MultiPoint(points) => {
+                self.write_header(4);
+                self.write_u32(points.0.len());
+                for point in points {
+                    self.write_header(1);
+                    self.write_coord(point.0);
+                }
+            }
+            Geometry::MultiLineString(lines) => {
+                self.write_header(5);
+                self.write_u32(lines.0.len());
+                for line in lines {
+                    self.write_header(2);
+                    self.write_coords(line);
+                }
+            }
+            Geometry::MultiPolygon(polygons) => {
+                self.write_header(6);
+                self.write_u32(polygons.0.len());
+                for polygon in polygons {
+                    self.write_polygon(polygon);
+                }
+            }
+            Geometry::GeometryCollection(geometries) => {
+                self.write_header(7);
+                self.write_u32(geometries.0.len());
+                for geometry in geometries {
+                    self.write_geometry(geometry);
+                }
+            }
+            Geometry::Rect(rect) => self.write_polygon(&rect.to_polygon()),
+            Geometry::Triangle(triangle) => self.write_polygon(&triangle.to_polygon()),
+        }
+    }
+
+    fn write_polygon(&mut self, polygon: &Polygon) {
+        self.write_header(3);
+        // an empty polygon has no rings, rather than an empty one
+        if polygon.exterior().0.is_empty() {
+            self.write_u32(0);
+            return;
+        }
+        self.write_u32(1 + polygon.interiors().len());
+        for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+            if self.close_rings {
+                self.write_coords(ring);
+            } else {
+                let open = &ring.0[..ring.0.len().saturating_sub(1)];
+                self.write_coords(&LineString::new(open.to_vec()));
+            }
+        }
+    }
+
+    /// Write the byte order and the ISO type code of `geometry_type`
+    fn write_header(&mut self, geometry_type: u32) {
+        self.buffer.push(1);
+        let offset = 1000 * self.dimensions.has_z() as u32 + 2000 * self.dimensions.has_m() as u32;
+        self.buffer
+            .extend_from_slice(&(geometry_type + offset).to_le_bytes());
+    }
+
+    fn write_coords(&mut self, line: &LineString) {
+        self.write_u32(line.0.len());
+        for &coord in line {
+            self.write_coord(coord);
+        }
+    }
+
+    fn write_coord(&mut self, coord: Coord) {
+        let projected = self.crs.project(coord);
+        self.buffer.extend_from_slice(&projected.x.to_le_bytes());
+        self.buffer.extend_from_slice(&projected.y.to_le_bytes());
+        if self.dimensions.has_z() {
+            let z = (self.z)(coord);
+            self.buffer.extend_from_slice(&z.to_le_bytes());
+        }
+        if self.dimensions.has_m() {
+            let m = (self.m)(self.index);
+            self.buffer.extend_from_slice(&m.to_le_bytes());
+        }
+        self.index += 1;
+    }
+
+    fn write_u32(&mut self, value: usize) {
+        self.buffer.extend_from_slice(&(value as u32).to_le_bytes());
+    }
+}
+
 // test to ensure that the conversion functions are correct
 #[cfg(test)]
 mod tests {
@@ -110,4 +354,83 @@ mod tests {
         let value = TPCHDate::new(MIN_GENERATE_DATE + 1234, 0, 0, 0);
         assert_eq!(to_arrow_timestamp_millis(value), 800841600000);
     }
+
+    #[test]
+    fn test_geometry_to_wkb() {
+        use geo::{point, polygon, MultiPolygon};
+        use geozero::{CoordDimensions, ToWkb};
+
+        let square =
+            polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 0.0)];
+        // the same as geozero in 2D
+        for geometry in [
+            Geometry::Point(point!(x: 1.5, y: -2.0)),
+            Geometry::MultiPolygon(MultiPolygon::new(vec![square.clone(), square])),
+        ] {
+            assert_eq!(
+                geometry_to_wkb(&geometry, Crs::Wgs84, Dimensions::Xy, |_| unreachable!()),
+                geometry.to_wkb(CoordDimensions::xy()).unwrap()
+            );
+        }
+
+        let wkb = geometry_to_wkb(
+            &Geometry::Point(point!(x: 1.5, y: -2.0)),
+            Crs::Wgs84,
+            Dimensions::Xyz,
+            |coord| coord.x * 2.0,
+        );
+        let mut expected = vec![1, 0xE9, 0x03, 0, 0];
+        for value in [1.5f64, -2.0, 3.0] {
+            expected.extend_from_slice(&value.to_le_bytes());
+        }
+        assert_eq!(wkb, expected);
+
+        // the points have no M
+        let point = Geometry::Point(point!(x: 1.5, y: -2.0));
+        let wkb = geometry_to_wkb(&point, Crs::Wgs84, Dimensions::Xyzm, |coord| coord.x * 2.0);
+        assert_eq!(wkb, expected);
+
+        // linestring ZM (3002) with the index of each coordinate as M
+        let line = Geometry::LineString(vec![(0.0, 1.0), (2.0, 3.0)].into());
+        let wkb =
+            geometry_to_wkb_with_m(&line, Crs::Wgs84, Dimensions::Xyzm, |_| 9.0, |i| i as f64);
+        let mut expected = vec![1, 0xBA, 0x0B, 0, 0, 2, 0, 0, 0];
+        for value in [0.0f64, 1.0, 9.0, 0.0, 2.0, 3.0, 9.0, 1.0] {
+            expected.extend_from_slice(&value.to_le_bytes());
+        }
+        assert_eq!(wkb, expected);
+
+        // the unclosed rings end before their first coordinate
+        let triangle =
+            Geometry::Polygon(polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0)]);
+        let wkb = geometry_to_wkb_unclosed(&triangle, Crs::Wgs84, Dimensions::Xy, |_| 0.0);
+        let closed = geometry_to_wkb(&triangle, Crs::Wgs84, Dimensions::Xy, |_| 0.0);
+        assert_eq!(wkb[9..13], 3u32.to_le_bytes());
+        assert_eq!(wkb[13..], closed[13..closed.len() - 16]);
+
+        // an empty polygon has no rings
+        let empty = Geometry::Polygon(Polygon::new(LineString::new(vec![]), vec![]));
+        let wkb = geometry_to_wkb(&empty, Crs::Wgs84, Dimensions::Xy, |_| unreachable!());
+        assert_eq!(wkb, vec![1, 3, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(empty_wkb(&closed), wkb);
+
+        // an empty point has NaN coordinates, here X, Y and Z
+        let point = Geometry::Point(point!(x: 1.5, y: -2.0));
+        let wkb = geometry_to_wkb(&point, Crs::Wgs84, Dimensions::Xyz, |_| 0.0);
+        let empty = empty_wkb(&wkb);
+        assert_eq!(empty[..5], wkb[..5]);
+        assert_eq!(empty.len(), wkb.len());
+        assert!(empty[5..]
+            .chunks(8)
+            .all(|value| f64::from_le_bytes(value.try_into().unwrap()).is_nan()));
+
+        // projected, with the Z of the longitude / latitude coordinate
+        let point = Geometry::Point(point!(x: 180.0, y: 0.0));
+        let wkb = geometry_to_wkb(&point, Crs::WebMercator, Dimensions::Xyz, |coord| coord.x);
+        let mut expected = vec![1, 0xE9, 0x03, 0, 0];
+        for value in [20_037_508.343f64, 0.0, 180.0] {
+            expected.extend_from_slice(&value.to_le_bytes());
+        }
+        assert_eq!(wkb, expected);
+    }
 }
@@ -16,10 +16,12 @@
 // under the License.
 
 use crate::conversions::string_view_array_from_display_iter;
-use crate::{RecordBatchIterator, DEFAULT_BATCH_SIZE};
-use arrow::array::{Int64Array, RecordBatch, StringViewArray};
+use crate::lineage::{add_lineage, lineage_schema, row_lineage};
+use crate::nulls::{null_rates, NullRates};
+use crate::{RecordBatchIterator, Table, DEFAULT_BATCH_SIZE};
+use arrow::array::{ArrayRef, Int64Array, RecordBatch, RecordBatchOptions, StringViewArray};
 use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
-use spatialbench::generators::{CustomerGenerator, CustomerGeneratorIterator};
+use spatialbench::generators::{CustomerGenerator, CustomerGeneratorIterator, Projection};
 use std::sync::{Arc, LazyLock};
 
 /// Generate [`Customer`]s in [`RecordBatch`] format
@@ -62,13 +64,28 @@ use std::sync::{Arc, LazyLock};
 pub struct CustomerArrow {
     inner: CustomerGeneratorIterator<'static>,
     batch_size: usize,
+    projection: Vec<usize>,
+    schema: SchemaRef,
+    seed: u64,
+    null_rates: NullRates,
+    row_lineage: bool,
 }
 
 impl CustomerArrow {
     pub fn new(generator: CustomerGenerator<'static>) -> Self {
+        let null_rates = null_rates().clone();
+        let row_lineage = row_lineage();
         Self {
             inner: generator.iter(),
             batch_size: DEFAULT_BATCH_SIZE,
+            projection: (0..CUSTOMER_SCHEMA.fields().len()).collect(),
+            schema: lineage_schema(
+                null_rates.nullable_schema(Table::Customer, CUSTOMER_SCHEMA.clone()),
+                row_lineage,
+            ),
+            seed: generator.seed(),
+            null_rates,
+            row_lineage,
         }
     }
 
@@ -77,11 +94,52 @@ impl CustomerArrow {
         self.batch_size = batch_size;
         self
     }
+
+    /// Only generate the columns at the `projection` indices of the schema
+    pub fn with_projection(mut self, projection: Vec<usize>) -> Self {
+        let schema = CUSTOMER_SCHEMA
+            .project(&projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(
+            self.null_rates
+                .nullable_schema(Table::Customer, Arc::new(schema)),
+            self.row_lineage,
+        );
+        let columns = projection.iter().copied();
+        self.inner = self.inner.with_projection(Projection::new(columns));
+        self.projection = projection;
+        self
+    }
+
+    /// Make a fraction of the values of the columns of `null_rates` null or
+    /// empty
+    ///
+    /// Defaults to the rates set with [`set_null_rates`](crate::nulls::set_null_rates).
+    pub fn with_null_rates(mut self, null_rates: NullRates) -> Self {
+        let schema = CUSTOMER_SCHEMA
+            .project(&self.projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(
+            null_rates.nullable_schema(Table::Customer, Arc::new(schema)),
+            self.row_lineage,
+        );
+        self.null_rates = null_rates;
+        self
+    }
+
+    /// Add the `_rowgen` column with the lineage of each row
+    ///
+    /// Defaults to the value set with [`set_row_lineage`](crate::lineage::set_row_lineage).
+    pub fn with_row_lineage(mut self, row_lineage: bool) -> Self {
+        self.schema = lineage_schema(Arc::clone(&self.schema), row_lineage);
+        self.row_lineage = row_lineage;
+        self
+    }
 }
 
 impl RecordBatchIterator for CustomerArrow {
     fn schema(&self) -> &SchemaRef {
-        &CUSTOMER_SCHEMA
+        &self.schema
     }
 }
 
@@ -95,31 +153,48 @@ impl Iterator for CustomerArrow {
             return None;
         }
 
-        let c_custkey = Int64Array::from_iter_values(rows.iter().map(|r| r.c_custkey));
-        let c_name = string_view_array_from_display_iter(rows.iter().map(|r| r.c_name));
-        let c_address = string_view_array_from_display_iter(rows.iter().map(|r| &r.c_address));
-        let c_region = StringViewArray::from_iter_values(rows.iter().map(|r| r.c_region));
-        let c_nation = StringViewArray::from_iter_values(rows.iter().map(|r| r.c_nation));
-        let c_phone = string_view_array_from_display_iter(rows.iter().map(|r| &r.c_phone));
-
-        let batch = RecordBatch::try_new(
-            Arc::clone(self.schema()),
-            vec![
-                Arc::new(c_custkey),
-                Arc::new(c_name),
-                Arc::new(c_address),
-                Arc::new(c_region),
-                Arc::new(c_nation),
-                Arc::new(c_phone),
-            ],
-        )
-        .unwrap();
+        // Only convert the projected columns
+        let columns = self
+            .projection
+            .iter()
+            .map(|&column| -> ArrayRef {
+                match column {
+                    0 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|r| r.c_custkey),
+                    )),
+                    1 => Arc::new(string_view_array_from_display_iter(
+                        rows.iter().map(|r| r.c_name),
+                    )),
+                    2 => Arc::new(string_view_array_from_display_iter(
+                        rows.iter().map(|r| &r.c_address),
+                    )),
+                    3 => Arc::new(StringViewArray::from_iter_values(
+                        rows.iter().map(|r| r.c_region),
+                    )),
+                    4 => Arc::new(StringViewArray::from_iter_values(
+                        rows.iter().map(|r| r.c_nation),
+                    )),
+                    5 => Arc::new(string_view_array_from_display_iter(
+                        rows.iter().map(|r| &r.c_phone),
+                    )),
+                    _ => unreachable!("projection is checked against the schema"),
+                }
+            })
+            .collect();
+        let keys: Vec<i64> = rows.iter().map(|r| r.c_custkey).collect();
+        let columns = self
+            .null_rates
+            .apply(Table::Customer, &self.schema, &keys, columns);
+        let columns = add_lineage(columns, &keys, self.seed, self.row_lineage);
+        let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+        let batch = RecordBatch::try_new_with_options(Arc::clone(self.schema()), columns, &options)
+            .unwrap();
         Some(batch)
     }
 }
 
 /// Schema for the Customer
-static CUSTOMER_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_customer_schema);
+pub(crate) static CUSTOMER_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_customer_schema);
 fn make_customer_schema() -> SchemaRef {
     Arc::new(Schema::new(vec![
         Field::new("c_custkey", DataType::Int64, false),
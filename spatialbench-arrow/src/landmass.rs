@@ -0,0 +1,211 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::conversions::geometry_to_wkb;
+use crate::lineage::{add_lineage, lineage_schema, row_lineage};
+use crate::nulls::{null_rates, NullRates};
+use crate::{RecordBatchIterator, Table, DEFAULT_BATCH_SIZE};
+use arrow::array::{
+    ArrayRef, BinaryArray, Int64Array, RecordBatch, RecordBatchOptions, StringViewArray,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use geo::Geometry;
+use spatialbench::generators::{LandmassGenerator, LandmassGeneratorIterator, Projection};
+use spatialbench::spatial::crs::{self, Crs};
+use spatialbench::spatial::dimensions::{self, elevation, Dimensions};
+use std::sync::{Arc, LazyLock};
+
+/// Generate [`Landmass`]es in [`RecordBatch`] format
+///
+/// [`Landmass`]: spatialbench::generators::Landmass
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::LandmassGenerator;
+/// # use spatialbench_arrow::LandmassArrow;
+///
+/// // Create a SF=1 generator and wrap it in an Arrow generator
+/// let generator = LandmassGenerator::new(1.0, 1, 1);
+/// let mut arrow_generator = LandmassArrow::new(generator)
+///   .with_batch_size(10);
+/// // Read the first batch, with all the continents
+/// let batch = arrow_generator.next().unwrap();
+/// assert_eq!(batch.num_rows(), 8);
+/// ```
+pub struct LandmassArrow {
+    inner: LandmassGeneratorIterator,
+    batch_size: usize,
+    projection: Vec<usize>,
+    schema: SchemaRef,
+    seed: u64,
+    null_rates: NullRates,
+    row_lineage: bool,
+    dimensions: Dimensions,
+    crs: Crs,
+}
+
+impl LandmassArrow {
+    pub fn new(generator: LandmassGenerator) -> Self {
+        let null_rates = null_rates().clone();
+        let row_lineage = row_lineage();
+        Self {
+            inner: generator.iter(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            projection: (0..LANDMASS_SCHEMA.fields().len()).collect(),
+            schema: lineage_schema(
+                null_rates.nullable_schema(Table::Landmass, LANDMASS_SCHEMA.clone()),
+                row_lineage,
+            ),
+            seed: generator.seed(),
+            null_rates,
+            row_lineage,
+            dimensions: dimensions::dimensions(),
+            crs: crs::crs(),
+        }
+    }
+
+    /// Set the batch size
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Only generate the columns at the `projection` indices of the schema
+    pub fn with_projection(mut self, projection: Vec<usize>) -> Self {
+        let schema = LANDMASS_SCHEMA
+            .project(&projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(
+            self.null_rates
+                .nullable_schema(Table::Landmass, Arc::new(schema)),
+            self.row_lineage,
+        );
+        let columns = projection.iter().copied();
+        self.inner = self.inner.with_projection(Projection::new(columns));
+        self.projection = projection;
+        self
+    }
+
+    /// Make a fraction of the values of the columns of `null_rates` null or
+    /// empty
+    ///
+    /// Defaults to the rates set with [`set_null_rates`](crate::nulls::set_null_rates).
+    pub fn with_null_rates(mut self, null_rates: NullRates) -> Self {
+        let schema = LANDMASS_SCHEMA
+            .project(&self.projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(
+            null_rates.nullable_schema(Table::Landmass, Arc::new(schema)),
+            self.row_lineage,
+        );
+        self.null_rates = null_rates;
+        self
+    }
+
+    /// Add the `_rowgen` column with the lineage of each row
+    ///
+    /// Defaults to the value set with [`set_row_lineage`](crate::lineage::set_row_lineage).
+    pub fn with_row_lineage(mut self, row_lineage: bool) -> Self {
+        self.schema = lineage_schema(Arc::clone(&self.schema), row_lineage);
+        self.row_lineage = row_lineage;
+        self
+    }
+
+    /// Write the boundaries with `dimensions`, with the [`elevation`] of the
+    /// terrain as Z
+    ///
+    /// Defaults to the dimensions set with [`dimensions::set_dimensions`].
+    pub fn with_dimensions(mut self, dimensions: Dimensions) -> Self {
+        self.dimensions = dimensions;
+        self
+    }
+
+    /// Project the boundaries to `crs`
+    ///
+    /// Defaults to the CRS set with [`crs::set_crs`].
+    pub fn with_crs(mut self, crs: Crs) -> Self {
+        self.crs = crs;
+        self
+    }
+}
+
+impl RecordBatchIterator for LandmassArrow {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+}
+
+impl Iterator for LandmassArrow {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Get next rows to convert
+        let rows: Vec<_> = self.inner.by_ref().take(self.batch_size).collect();
+        if rows.is_empty() {
+            return None;
+        }
+
+        // Only convert the projected columns
+        let columns = self
+            .projection
+            .iter()
+            .map(|&column| -> ArrayRef {
+                match column {
+                    0 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|row| row.l_landmasskey),
+                    )),
+                    1 => Arc::new(StringViewArray::from_iter_values(
+                        rows.iter().map(|row| row.l_name),
+                    )),
+                    2 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|row| row.l_islands),
+                    )),
+                    3 => Arc::new(BinaryArray::from_iter_values(rows.iter().map(|row| {
+                        geometry_to_wkb(
+                            &Geometry::MultiPolygon(row.l_boundary.clone()),
+                            self.crs,
+                            self.dimensions,
+                            |coord| elevation(coord.x, coord.y),
+                        )
+                    }))),
+                    _ => unreachable!("projection is checked against the schema"),
+                }
+            })
+            .collect();
+        let keys: Vec<i64> = rows.iter().map(|row| row.l_landmasskey).collect();
+        let columns = self
+            .null_rates
+            .apply(Table::Landmass, &self.schema, &keys, columns);
+        let columns = add_lineage(columns, &keys, self.seed, self.row_lineage);
+        let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+        let batch = RecordBatch::try_new_with_options(Arc::clone(self.schema()), columns, &options)
+            .unwrap();
+        Some(batch)
+    }
+}
+
+/// Schema for the Landmass table
+pub(crate) static LANDMASS_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_landmass_schema);
+
+fn make_landmass_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("l_landmasskey", DataType::Int64, false),
+        Field::new("l_name", DataType::Utf8View, false),
+        Field::new("l_islands", DataType::Int64, false),
+        Field::new("l_boundary", DataType::Binary, false),
+    ]))
+}
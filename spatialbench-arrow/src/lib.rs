@@ -21,6 +21,9 @@
 //! Arrow [`RecordBatch`]es. This is significantly faster than generating TBL or CSV
 //! files and then parsing them into Arrow.
 //!
+//! Use [`TableGenerator`] to generate any table by name, or the generator for
+//! a specific table such as [`TripArrow`].
+//!
 //! # Example
 //! ```
 //! # use spatialbench::generators::TripGenerator;
@@ -53,17 +56,45 @@
 //! ```
 
 mod building;
+pub mod cdc;
+mod city;
 pub mod conversions;
+mod country;
 mod customer;
 mod driver;
+mod landmass;
+pub mod lineage;
+pub mod nulls;
+mod poi;
+mod pointcloud;
+#[cfg(feature = "datafusion")]
+mod provider;
+mod raster;
+mod region;
+mod road;
+pub mod schema;
+mod table;
+mod trajectory;
 mod trip;
 mod vehicle;
 
 use arrow::array::RecordBatch;
 use arrow::datatypes::SchemaRef;
 pub use building::BuildingArrow;
+pub use city::CityArrow;
+pub use country::CountryArrow;
 pub use customer::CustomerArrow;
 pub use driver::DriverArrow;
+pub use landmass::LandmassArrow;
+pub use poi::PoiArrow;
+pub use pointcloud::PointCloudArrow;
+#[cfg(feature = "datafusion")]
+pub use provider::{register_tables, SpatialBenchTableProvider};
+pub use raster::RasterArrow;
+pub use region::RegionArrow;
+pub use road::RoadArrow;
+pub use table::{Table, TableGenerator};
+pub use trajectory::TrajectoryArrow;
 pub use trip::TripArrow;
 pub use vehicle::VehicleArrow;
 
@@ -72,5 +103,19 @@ pub trait RecordBatchIterator: Iterator<Item = RecordBatch> + Send {
     fn schema(&self) -> &SchemaRef;
 }
 
+impl RecordBatchIterator for Box<dyn RecordBatchIterator> {
+    fn schema(&self) -> &SchemaRef {
+        self.as_ref().schema()
+    }
+}
+
 /// The default number of rows in each Batch
 pub const DEFAULT_BATCH_SIZE: usize = 8 * 1000;
+
+/// Field metadata key for a geometry type that can hold all the geometries
+/// of a WKB column, e.g. `MultiPolygon` for a mix of polygons and multi
+/// polygons
+///
+/// Writers can use it instead of inferring the type from the first rows,
+/// which may not include the rarer types.
+pub const GEOMETRY_TYPE_KEY: &str = "spatialbench:geometry_type";
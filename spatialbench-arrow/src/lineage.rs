@@ -0,0 +1,259 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The lineage of the generated rows (`_rowgen`)
+//!
+//! With [`set_row_lineage`], every table has a last `_rowgen` column such as
+//! `3:1234:42` with the [`RowGen`] of the row: the chunk of the table it is
+//! in, its index in the chunk and the seed it was generated with. The chunks
+//! are fixed runs of [`CHUNK_ROWS`] rows of the whole table, so the lineage
+//! of a row is the same whatever the parts, batch size and projection, and
+//! a row of the output of one engine can be regenerated on its own to compare
+//! it with the output of another.
+
+use crate::conversions::string_view_array_from_display_iter;
+use crate::TableGenerator;
+use arrow::array::{ArrayRef, RecordBatch};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+/// The name of the lineage column
+pub const ROWGEN_COLUMN: &str = "_rowgen";
+
+/// The number of rows of each chunk of a table
+pub const CHUNK_ROWS: i64 = 100_000;
+
+/// Where a row comes from, written `<chunk>:<row>:<seed>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowGen {
+    /// The chunk of the table, from 0
+    pub chunk: i64,
+    /// The index of the row in the chunk, from 0
+    pub row: i64,
+    /// The seed of the generator, see [`TableGenerator::with_seed`]
+    pub seed: u64,
+}
+
+impl RowGen {
+    /// Return the lineage of the row with key `key` (from 1) generated with
+    /// `seed`
+    pub fn from_key(key: i64, seed: u64) -> Self {
+        Self {
+            chunk: (key - 1) / CHUNK_ROWS,
+            row: (key - 1) % CHUNK_ROWS,
+            seed,
+        }
+    }
+
+    /// Return the key of the row, from 1
+    pub fn key(&self) -> i64 {
+        self.chunk * CHUNK_ROWS + self.row + 1
+    }
+
+    /// Generate only this row with `generator`, which must have the scale
+    /// factor and row counts of the original data
+    ///
+    /// Returns `None` if the table has no such row, or more rows than there
+    /// can be parts. The row is generated as the part of the table of its
+    /// key, with as many parts as rows.
+    pub fn regenerate(&self, generator: TableGenerator) -> Option<RecordBatch> {
+        let generator = generator.with_seed(self.seed).with_part(1, 1);
+        let key = i32::try_from(self.key()).ok()?;
+        let row_count = i32::try_from(generator.row_count()).ok()?;
+        if key > row_count {
+            return None;
+        }
+        generator
+            .with_part(key, row_count)
+            .with_batch_size(1)
+            .batches()
+            .next()
+    }
+}
+
+impl fmt::Display for RowGen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.chunk, self.row, self.seed)
+    }
+}
+
+impl FromStr for RowGen {
+    type Err = String;
+
+    /// Parse a lineage such as `3:1234:42`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid row lineage {s}, expected <chunk>:<row>:<seed>");
+        let mut parts = s.trim().split(':');
+        let (Some(chunk), Some(row), Some(seed), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let rowgen = Self {
+            chunk: chunk.parse().map_err(|_| invalid())?,
+            row: row.parse().map_err(|_| invalid())?,
+            seed: seed.parse().map_err(|_| invalid())?,
+        };
+        if rowgen.chunk < 0 || !(0..CHUNK_ROWS).contains(&rowgen.row) {
+            return Err(invalid());
+        }
+        Ok(rowgen)
+    }
+}
+
+/// Return `schema` with the `_rowgen` column if `enabled`, and without it
+/// otherwise
+pub(crate) fn lineage_schema(schema: SchemaRef, enabled: bool) -> SchemaRef {
+    let has_lineage = schema.index_of(ROWGEN_COLUMN).is_ok();
+    if enabled == has_lineage {
+        return schema;
+    }
+    let mut fields: Vec<Field> = schema
+        .fields()
+        .iter()
+        .filter(|f| f.name() != ROWGEN_COLUMN)
+        .map(|f| f.as_ref().clone())
+        .collect();
+    if enabled {
+        fields.push(Field::new(ROWGEN_COLUMN, DataType::Utf8View, false));
+    }
+    Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+}
+
+/// Append the `_rowgen` column of the rows with `keys` generated with `seed`
+/// to `columns`, if `enabled`
+pub(crate) fn add_lineage(
+    mut columns: Vec<ArrayRef>,
+    keys: &[i64],
+    seed: u64,
+    enabled: bool,
+) -> Vec<ArrayRef> {
+    if enabled {
+        columns.push(Arc::new(string_view_array_from_display_iter(
+            keys.iter().map(|&key| RowGen::from_key(key, seed)),
+        )));
+    }
+    columns
+}
+
+static ROW_LINEAGE: OnceLock<bool> = OnceLock::new();
+
+/// Add the `_rowgen` column to the tables generated by the Arrow generators
+/// created afterwards, unless they override it with `with_row_lineage`
+///
+/// Returns an error if it is already set to the other value.
+pub fn set_row_lineage(enabled: bool) -> Result<(), String> {
+    if *ROW_LINEAGE.get_or_init(|| enabled) != enabled {
+        return Err("the row lineage is already set to the other value".to_string());
+    }
+    Ok(())
+}
+
+/// Return whether the `_rowgen` column is set with [`set_row_lineage`], by
+/// default not
+pub fn row_lineage() -> bool {
+    ROW_LINEAGE.get().copied().unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Table;
+    use arrow::array::{AsArray, Int64Array};
+    use arrow::datatypes::Int64Type;
+
+    #[test]
+    fn test_parse_rowgen() {
+        let rowgen = RowGen::from_key(312_346, 42);
+        assert_eq!(rowgen.to_string(), "3:12345:42");
+        assert_eq!(rowgen.key(), 312_346);
+        assert_eq!("3:12345:42".parse::<RowGen>().unwrap(), rowgen);
+        assert_eq!(RowGen::from_key(1, 0).to_string(), "0:0:0");
+        for s in ["3:12345", "3:12345:42:1", "a:1:2", "1:100000:0", "-1:0:0"] {
+            assert!(s.parse::<RowGen>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_regenerate() {
+        for table in [Table::Trip, Table::Building, Table::City, Table::Trajectory] {
+            let generator = TableGenerator::new(table, 0.01).with_seed(7);
+            let batches: Vec<_> = generator.batches().collect();
+            let keys: Vec<i64> = batches
+                .iter()
+                .flat_map(|batch| {
+                    batch
+                        .column(0)
+                        .as_primitive::<Int64Type>()
+                        .values()
+                        .to_vec()
+                })
+                .collect();
+            let count = keys.len() as i64;
+            for key in [1, (count + 1) / 2, count] {
+                let row = RowGen::from_key(key, 7)
+                    .regenerate(TableGenerator::new(table, 0.01))
+                    .unwrap();
+                assert_eq!(row.num_rows(), 1, "{table} {key}");
+                let expected = batches
+                    .iter()
+                    .flat_map(|batch| (0..batch.num_rows()).map(move |i| batch.slice(i, 1)))
+                    .nth(key as usize - 1)
+                    .unwrap();
+                assert_eq!(row, expected, "{table} {key}");
+                assert_eq!(
+                    row.column(0)
+                        .as_any()
+                        .downcast_ref::<Int64Array>()
+                        .unwrap()
+                        .value(0),
+                    key
+                );
+            }
+        }
+        assert!(RowGen::from_key(1_000_000, 0)
+            .regenerate(TableGenerator::new(Table::City, 0.01))
+            .is_none());
+    }
+
+    #[test]
+    fn test_with_row_lineage() {
+        let generator = TableGenerator::new(Table::City, 0.01)
+            .with_seed(7)
+            .with_row_lineage(true);
+        let schema = generator.schema();
+        assert_eq!(schema.fields().last().unwrap().name(), ROWGEN_COLUMN);
+        let batch = generator.batches().next().unwrap();
+        assert_eq!(batch.schema(), schema);
+        let rowgen = batch.column_by_name(ROWGEN_COLUMN).unwrap();
+        assert_eq!(rowgen.as_string_view().value(0), "0:0:7");
+
+        // the other generators have the default lineage
+        let batch = TableGenerator::new(Table::City, 0.01)
+            .batches()
+            .next()
+            .unwrap();
+        assert!(batch.column_by_name(ROWGEN_COLUMN).is_none());
+
+        // the default can only be set once
+        set_row_lineage(false).unwrap();
+        set_row_lineage(false).unwrap();
+        assert!(set_row_lineage(true).is_err());
+    }
+}
@@ -0,0 +1,455 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Null values and empty geometries in the generated columns
+//!
+//! The generated columns have no nulls. With [`NullRates`], a fraction of
+//! the values of some columns is null, and a fraction of the geometries of
+//! some geometry columns is empty, so the handling of nulls and empty
+//! geometries shows in benchmark results. The rows are chosen from their
+//! key and the column, so the same rows are null in every part, projection
+//! and batch size.
+
+use crate::conversions::empty_wkb;
+use crate::Table;
+use arrow::array::{make_array, Array, ArrayRef, AsArray, BinaryArray};
+use arrow::buffer::NullBuffer;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use spatialbench::spatial::cell::CellIndex;
+use spatialbench::spatial::utils::hash_to_unit_u64;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+/// The fraction of the values of a column that are null or empty, e.g.
+/// `trip.t_dropoffloc=0.02`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnRate {
+    pub table: Table,
+    pub column: String,
+    pub rate: f64,
+}
+
+impl FromStr for ColumnRate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((column, rate)) = s.split_once('=') else {
+            return Err(format!(
+                "expected <table>.<column>=<rate>, e.g. trip.t_dropoffloc=0.02, got '{s}'"
+            ));
+        };
+        let Some((table, column)) = column.trim().split_once('.') else {
+            return Err(format!(
+                "expected <table>.<column>, e.g. trip.t_dropoffloc, got '{}'",
+                column.trim()
+            ));
+        };
+        let table: Table = table.parse()?;
+        let schema = table.schema();
+        if schema.index_of(column).is_err() {
+            let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+            return Err(format!(
+                "table {table} has no column '{column}', expected one of {}",
+                names.join(", ")
+            ));
+        }
+        let rate: f64 = rate
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid rate '{}'", rate.trim()))?;
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(format!("the rate must be in [0, 1], got {rate}"));
+        }
+        Ok(Self {
+            table,
+            column: column.to_string(),
+            rate,
+        })
+    }
+}
+
+/// The null and empty rates of the columns of the tables
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NullRates {
+    /// The table, column, null rate and empty rate of each column with nulls
+    /// or empty geometries
+    columns: Vec<(Table, String, f64, f64)>,
+}
+
+impl NullRates {
+    /// Create the rates from the null rates and the empty rates of columns
+    ///
+    /// The key columns can not be null, only geometry columns can be empty,
+    /// and each column is listed at most once in each.
+    pub fn try_new(nulls: &[ColumnRate], empties: &[ColumnRate]) -> Result<Self, String> {
+        let mut columns: Vec<(Table, String, f64, f64)> = vec![];
+        for (rates, empty) in [(nulls, false), (empties, true)] {
+            for rate in rates {
+                let schema = rate.table.schema();
+                let field = schema
+                    .field_with_name(&rate.column)
+                    .map_err(|e| e.to_string())?;
+                if !empty && schema.index_of(&rate.column).ok() == Some(0) {
+                    return Err(format!(
+                        "{} is the key of table {} and can not be null",
+                        rate.column, rate.table
+                    ));
+                }
+                if empty && field.data_type() != &DataType::Binary {
+                    return Err(format!(
+                        "{} is not a geometry column and can not be empty",
+                        rate.column
+                    ));
+                }
+                let index = match columns
+                    .iter()
+                    .position(|(t, c, _, _)| *t == rate.table && *c == rate.column)
+                {
+                    Some(index) => index,
+                    None => {
+                        columns.push((rate.table, rate.column.clone(), 0.0, 0.0));
+                        columns.len() - 1
+                    }
+                };
+                let column = &mut columns[index];
+                let value = if empty { &mut column.3 } else { &mut column.2 };
+                if *value > 0.0 {
+                    return Err(format!(
+                        "{}.{} is listed more than once",
+                        rate.table, rate.column
+                    ));
+                }
+                *value = rate.rate;
+                if column.2 + column.3 > 1.0 {
+                    return Err(format!(
+                        "the null and empty rates of {}.{} add up to more than 1",
+                        rate.table, rate.column
+                    ));
+                }
+            }
+        }
+        Ok(Self { columns })
+    }
+
+    /// Return whether no column has nulls or empty geometries
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Return the null rate and empty rate of `column` of `table`
+    fn rates(&self, table: Table, column: &str) -> Option<(f64, f64)> {
+        self.columns
+            .iter()
+            .find(|(t, c, _, _)| *t == table && c == column)
+            .map(|&(_, _, null_rate, empty_rate)| (null_rate, empty_rate))
+    }
+
+    /// Return the geometry column of `table` that `column` holds the cells
+    /// of, such as `t_pickuploc` for `t_pickuploc_h3_9`, and its null and
+    /// empty rates, if any
+    fn cell_source(&self, table: Table, column: &str) -> Option<(&str, f64, f64)> {
+        let is_cell_of = |source: &str| {
+            column
+                .strip_prefix(source)
+                .and_then(|suffix| suffix.strip_prefix('_'))
+                .and_then(|index| index.split_once('_'))
+                .is_some_and(|(system, resolution)| {
+                    format!("{system}:{resolution}")
+                        .parse::<CellIndex>()
+                        .is_ok()
+                })
+        };
+        self.columns
+            .iter()
+            .find(|(t, c, _, _)| *t == table && is_cell_of(c))
+            .map(|(_, c, null_rate, empty_rate)| (c.as_str(), *null_rate, *empty_rate))
+    }
+
+    /// Return `schema` of `table` with the columns that have nulls nullable
+    pub fn nullable_schema(&self, table: Table, schema: SchemaRef) -> SchemaRef {
+        if self.is_empty() {
+            return schema;
+        }
+        let fields: Vec<Field> = schema
+            .fields()
+            .iter()
+            .map(|field| match self.rates(table, field.name()) {
+                Some((null_rate, _)) if null_rate > 0.0 => {
+                    field.as_ref().clone().with_nullable(true)
+                }
+                _ => field.as_ref().clone(),
+            })
+            .collect();
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+    }
+
+    /// Replace a fraction of the values of `columns` with nulls and empty
+    /// geometries, for the rows with `keys` of `table` and the columns of
+    /// `schema`
+    ///
+    /// The cell index columns are null where their point is null or empty.
+    pub fn apply(
+        &self,
+        table: Table,
+        schema: &Schema,
+        keys: &[i64],
+        columns: Vec<ArrayRef>,
+    ) -> Vec<ArrayRef> {
+        if self.is_empty() {
+            return columns;
+        }
+        columns
+            .into_iter()
+            .zip(schema.fields())
+            .map(|(array, field)| {
+                if let Some((source, null_rate, empty_rate)) = self.cell_source(table, field.name())
+                {
+                    // the same draws as the point
+                    let salt = column_salt(table, source);
+                    let valid =
+                        NullBuffer::from_iter(keys.iter().map(|&key| {
+                            hash_to_unit_u64(key as u64, salt) >= null_rate + empty_rate
+                        }));
+                    return with_nulls(array, &valid);
+                }
+                let Some((null_rate, empty_rate)) = self.rates(table, field.name()) else {
+                    return array;
+                };
+                // one draw per row: null below the null rate, empty above it
+                let salt = column_salt(table, field.name());
+                let draws: Vec<f64> = keys
+                    .iter()
+                    .map(|&key| hash_to_unit_u64(key as u64, salt))
+                    .collect();
+                let mut array = array;
+                if empty_rate > 0.0 {
+                    let wkb = array.as_binary::<i32>();
+                    let values = draws.iter().enumerate().map(|(row, &u)| {
+                        let value = wkb.value(row);
+                        if u >= null_rate && u < null_rate + empty_rate {
+                            empty_wkb(value)
+                        } else {
+                            value.to_vec()
+                        }
+                    });
+                    array = Arc::new(BinaryArray::from_iter_values(values));
+                }
+                if null_rate > 0.0 {
+                    let valid = NullBuffer::from_iter(draws.iter().map(|&u| u >= null_rate));
+                    array = with_nulls(array, &valid);
+                }
+                array
+            })
+            .collect()
+    }
+}
+
+/// Return `array` with nulls where `valid` is false
+fn with_nulls(array: ArrayRef, valid: &NullBuffer) -> ArrayRef {
+    let nulls = NullBuffer::union(array.nulls(), Some(valid));
+    let data = array.into_data().into_builder().nulls(nulls);
+    make_array(
+        data.build()
+            .expect("the nulls have the length of the array"),
+    )
+}
+
+/// Return the salt of the draws of `column` of `table`, a FNV-1a hash of
+/// its name
+fn column_salt(table: Table, column: &str) -> u64 {
+    format!("{table}.{column}")
+        .bytes()
+        .fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+        })
+}
+
+static NULL_RATES: OnceLock<NullRates> = OnceLock::new();
+
+/// Set the default null and empty rates of the columns generated by the
+/// Arrow generators created afterwards
+///
+/// The rates of a single generator are set with its `with_null_rates`.
+/// The default can only be set once: returns an error if other rates are
+/// already set.
+pub fn set_null_rates(rates: NullRates) -> Result<(), String> {
+    if *NULL_RATES.get_or_init(|| rates.clone()) != rates {
+        return Err("the null and empty rates are already set to other rates".to_string());
+    }
+    Ok(())
+}
+
+/// Return the rates set with [`set_null_rates`], by default none
+pub fn null_rates() -> &'static NullRates {
+    static NONE: NullRates = NullRates {
+        columns: Vec::new(),
+    };
+    NULL_RATES.get().unwrap_or(&NONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trip::trip_schema;
+    use crate::{TableGenerator, TripArrow};
+    use spatialbench::generators::TripGenerator;
+
+    #[test]
+    fn test_parse_rates() {
+        let rate: ColumnRate = "trip.t_dropoffloc=0.02".parse().unwrap();
+        assert_eq!(rate.table, Table::Trip);
+        assert_eq!(rate.column, "t_dropoffloc");
+        for (value, error) in [
+            ("trip.t_dropoffloc", "expected <table>.<column>=<rate>"),
+            ("t_dropoffloc=0.1", "expected <table>.<column>"),
+            ("trip.b_name=0.1", "table trip has no column 'b_name'"),
+            ("trip.t_fare=2", "the rate must be in [0, 1]"),
+        ] {
+            let err = value.parse::<ColumnRate>().unwrap_err();
+            assert!(err.starts_with(error), "{value}: {err}");
+        }
+
+        let rate = |value: &str| value.parse::<ColumnRate>().unwrap();
+        for (nulls, empties, error) in [
+            (
+                vec![rate("trip.t_tripkey=0.1")],
+                vec![],
+                "t_tripkey is the key",
+            ),
+            (
+                vec![],
+                vec![rate("trip.t_fare=0.1")],
+                "t_fare is not a geometry",
+            ),
+            (
+                vec![rate("trip.t_fare=0.1"), rate("trip.t_fare=0.2")],
+                vec![],
+                "trip.t_fare is listed more than once",
+            ),
+            (
+                vec![rate("trip.t_pickuploc=0.6")],
+                vec![rate("trip.t_pickuploc=0.6")],
+                "the null and empty rates of trip.t_pickuploc add up",
+            ),
+        ] {
+            let err = NullRates::try_new(&nulls, &empties).unwrap_err();
+            assert!(err.starts_with(error), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let rates = NullRates::try_new(
+            &[
+                "trip.t_fare=0.1".parse().unwrap(),
+                "trip.t_dropoffloc=0.05".parse().unwrap(),
+            ],
+            &["trip.t_dropoffloc=0.05".parse().unwrap()],
+        )
+        .unwrap();
+        let schema = rates.nullable_schema(Table::Trip, Table::Trip.schema());
+        assert!(schema.field_with_name("t_fare").unwrap().is_nullable());
+        assert!(!schema.field_with_name("t_tip").unwrap().is_nullable());
+
+        let batch = TripArrow::new(TripGenerator::new(0.01, 1, 1))
+            .with_batch_size(10_000)
+            .next()
+            .unwrap();
+        let keys: Vec<i64> = (1..=10_000).collect();
+        let columns = rates.apply(Table::Trip, &schema, &keys, batch.columns().to_vec());
+        let fares = &columns[schema.index_of("t_fare").unwrap()];
+        assert!(
+            (900..=1100).contains(&fares.null_count()),
+            "{}",
+            fares.null_count()
+        );
+        let dropoffs = columns[schema.index_of("t_dropoffloc").unwrap()].as_binary::<i32>();
+        let empty = dropoffs
+            .iter()
+            .flatten()
+            .filter(|wkb| f64::from_le_bytes(wkb[5..13].try_into().unwrap()).is_nan())
+            .count();
+        assert!((400..=600).contains(&dropoffs.null_count()));
+        assert!((400..=600).contains(&empty), "{empty}");
+
+        // the same rows in a smaller batch
+        let head: Vec<ArrayRef> = batch.columns().iter().map(|c| c.slice(0, 100)).collect();
+        let head = rates.apply(Table::Trip, &schema, &keys[..100], head);
+        assert_eq!(
+            head[schema.index_of("t_fare").unwrap()].nulls(),
+            fares.slice(0, 100).nulls()
+        );
+    }
+
+    #[test]
+    fn test_apply_cell_indexes() {
+        let rates = NullRates::try_new(
+            &["trip.t_pickuploc=0.1".parse().unwrap()],
+            &["trip.t_pickuploc=0.05".parse().unwrap()],
+        )
+        .unwrap();
+        let indexes = vec!["h3:7".parse().unwrap()];
+        let schema = rates.nullable_schema(Table::Trip, trip_schema(&indexes));
+        let cells = schema.index_of("t_pickuploc_h3_7").unwrap();
+        assert!(schema.field(cells).is_nullable());
+
+        let batch = TripArrow::new(TripGenerator::new(0.01, 1, 1))
+            .with_cell_indexes(indexes)
+            .with_batch_size(10_000)
+            .next()
+            .unwrap();
+        let keys: Vec<i64> = (1..=10_000).collect();
+        let columns = rates.apply(Table::Trip, &schema, &keys, batch.columns().to_vec());
+        let pickups = columns[schema.index_of("t_pickuploc").unwrap()].as_binary::<i32>();
+        let cells = &columns[cells];
+        assert!((1300..=1700).contains(&cells.null_count()));
+        for (row, pickup) in pickups.iter().enumerate() {
+            let missing = pickup
+                .is_none_or(|wkb| f64::from_le_bytes(wkb[5..13].try_into().unwrap()).is_nan());
+            assert_eq!(cells.is_null(row), missing, "{row}");
+        }
+        // the dropoff cells are untouched
+        let dropoffs = &columns[schema.index_of("t_dropoffloc_h3_7").unwrap()];
+        assert_eq!(dropoffs.null_count(), 0);
+    }
+
+    #[test]
+    fn test_with_null_rates() {
+        let rates = NullRates::try_new(&["trip.t_fare=0.1".parse().unwrap()], &[]).unwrap();
+        let generator = TableGenerator::new(Table::Trip, 0.01)
+            .with_batch_size(10_000)
+            .with_null_rates(rates.clone());
+        let schema = generator.schema();
+        assert!(schema.field_with_name("t_fare").unwrap().is_nullable());
+        let batch = generator.batches().next().unwrap();
+        assert_eq!(batch.schema(), schema);
+        let fares = batch.column_by_name("t_fare").unwrap();
+        assert!((900..=1100).contains(&fares.null_count()));
+
+        // the other generators have the default rates
+        let batch = TableGenerator::new(Table::Trip, 0.01)
+            .batches()
+            .next()
+            .unwrap();
+        assert_eq!(batch.column_by_name("t_fare").unwrap().null_count(), 0);
+
+        // the default can only be set once
+        set_null_rates(NullRates::default()).unwrap();
+        set_null_rates(NullRates::default()).unwrap();
+        assert!(set_null_rates(rates).is_err());
+    }
+}
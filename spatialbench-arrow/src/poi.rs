@@ -0,0 +1,249 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::conversions::{
+    cell_index_array, cell_index_fields, geometry_to_wkb, string_view_array_from_display_iter,
+};
+use crate::lineage::{add_lineage, lineage_schema, row_lineage};
+use crate::nulls::{null_rates, NullRates};
+use crate::{RecordBatchIterator, Table, DEFAULT_BATCH_SIZE};
+use arrow::array::{ArrayRef, BinaryArray, Int64Array, RecordBatch, RecordBatchOptions};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use geo::Geometry;
+use spatialbench::generators::{PoiGenerator, PoiGeneratorIterator, Projection};
+use spatialbench::spatial::cell::{self, CellIndex};
+use spatialbench::spatial::crs::{self, Crs};
+use spatialbench::spatial::dimensions::{self, elevation, Dimensions};
+use std::sync::{Arc, LazyLock};
+
+/// Generate [`Poi`]s in [`RecordBatch`] format
+///
+/// [`Poi`]: spatialbench::generators::Poi
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::PoiGenerator;
+/// # use spatialbench_arrow::PoiArrow;
+///
+/// // Create a SF=0.01 generator and wrap it in an Arrow generator
+/// let generator = PoiGenerator::new(0.01, 1, 1);
+/// let mut arrow_generator = PoiArrow::new(generator)
+///   .with_batch_size(10);
+/// // Read the first batch
+/// let batch = arrow_generator.next().unwrap();
+/// assert_eq!(batch.num_rows(), 10);
+/// ```
+pub struct PoiArrow {
+    inner: PoiGeneratorIterator,
+    batch_size: usize,
+    projection: Vec<usize>,
+    /// The schema of all the columns
+    table_schema: SchemaRef,
+    schema: SchemaRef,
+    seed: u64,
+    null_rates: NullRates,
+    row_lineage: bool,
+    dimensions: Dimensions,
+    crs: Crs,
+    cell_indexes: Vec<CellIndex>,
+}
+
+impl PoiArrow {
+    pub fn new(generator: PoiGenerator) -> Self {
+        let cell_indexes = cell::cell_indexes().to_vec();
+        let null_rates = null_rates().clone();
+        let row_lineage = row_lineage();
+        let schema = null_rates.nullable_schema(Table::Poi, poi_schema(&cell_indexes));
+        Self {
+            inner: generator.iter(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            projection: (0..schema.fields().len()).collect(),
+            table_schema: schema.clone(),
+            schema: lineage_schema(schema, row_lineage),
+            seed: generator.seed(),
+            null_rates,
+            row_lineage,
+            dimensions: dimensions::dimensions(),
+            crs: crs::crs(),
+            cell_indexes,
+        }
+    }
+
+    /// Set the batch size
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Only generate the columns at the `projection` indices of the schema
+    pub fn with_projection(mut self, projection: Vec<usize>) -> Self {
+        let schema = self
+            .table_schema
+            .project(&projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(Arc::new(schema), self.row_lineage);
+        // the cells are computed from the location
+        let columns = projection
+            .iter()
+            .map(|&column| column.min(POI_SCHEMA.fields().len() - 1));
+        self.inner = self.inner.with_projection(Projection::new(columns));
+        self.projection = projection;
+        self
+    }
+
+    /// Make a fraction of the values of the columns of `null_rates` null or
+    /// empty
+    ///
+    /// Defaults to the rates set with [`set_null_rates`](crate::nulls::set_null_rates).
+    pub fn with_null_rates(mut self, null_rates: NullRates) -> Self {
+        self.table_schema = null_rates.nullable_schema(Table::Poi, poi_schema(&self.cell_indexes));
+        let schema = self
+            .table_schema
+            .project(&self.projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(Arc::new(schema), self.row_lineage);
+        self.null_rates = null_rates;
+        self
+    }
+
+    /// Add the `_rowgen` column with the lineage of each row
+    ///
+    /// Defaults to the value set with [`set_row_lineage`](crate::lineage::set_row_lineage).
+    pub fn with_row_lineage(mut self, row_lineage: bool) -> Self {
+        self.schema = lineage_schema(Arc::clone(&self.schema), row_lineage);
+        self.row_lineage = row_lineage;
+        self
+    }
+
+    /// Write the locations with `dimensions`, with the [`elevation`] of the
+    /// terrain as Z
+    ///
+    /// Defaults to the dimensions set with [`dimensions::set_dimensions`].
+    pub fn with_dimensions(mut self, dimensions: Dimensions) -> Self {
+        self.dimensions = dimensions;
+        self
+    }
+
+    /// Project the locations to `crs`
+    ///
+    /// Defaults to the CRS set with [`crs::set_crs`].
+    pub fn with_crs(mut self, crs: Crs) -> Self {
+        self.crs = crs;
+        self
+    }
+
+    /// Add a column with the cells of the location for each of `indexes`
+    ///
+    /// Defaults to the indexes set with [`cell::set_cell_indexes`]. All the
+    /// columns are generated, as the schema gains or loses the columns.
+    pub fn with_cell_indexes(mut self, indexes: Vec<CellIndex>) -> Self {
+        self.table_schema = self
+            .null_rates
+            .nullable_schema(Table::Poi, poi_schema(&indexes));
+        self.schema = lineage_schema(Arc::clone(&self.table_schema), self.row_lineage);
+        self.projection = (0..self.schema.fields().len()).collect();
+        self.inner = self.inner.with_projection(Projection::default());
+        self.cell_indexes = indexes;
+        self
+    }
+}
+
+impl RecordBatchIterator for PoiArrow {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+}
+
+impl Iterator for PoiArrow {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Get next rows to convert
+        let rows: Vec<_> = self.inner.by_ref().take(self.batch_size).collect();
+        if rows.is_empty() {
+            return None;
+        }
+
+        // Only convert the projected columns
+        let columns = self
+            .projection
+            .iter()
+            .map(|&column| -> ArrayRef {
+                match column {
+                    0 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|row| row.p_poikey),
+                    )),
+                    1 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|row| row.p_citykey),
+                    )),
+                    2 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|row| row.p_category),
+                    )),
+                    3 => Arc::new(string_view_array_from_display_iter(
+                        rows.iter().map(|row| &row.p_name),
+                    )),
+                    4 => Arc::new(BinaryArray::from_iter_values(rows.iter().map(|row| {
+                        geometry_to_wkb(
+                            &Geometry::Point(row.p_location),
+                            self.crs,
+                            self.dimensions,
+                            |coord| elevation(coord.x, coord.y),
+                        )
+                    }))),
+                    column => {
+                        let index = &self.cell_indexes[column - POI_SCHEMA.fields().len()];
+                        cell_index_array(index, rows.iter().map(|row| &row.p_location))
+                    }
+                }
+            })
+            .collect();
+        let keys: Vec<i64> = rows.iter().map(|row| row.p_poikey).collect();
+        let columns = self
+            .null_rates
+            .apply(Table::Poi, &self.schema, &keys, columns);
+        let columns = add_lineage(columns, &keys, self.seed, self.row_lineage);
+        let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+        let batch = RecordBatch::try_new_with_options(Arc::clone(self.schema()), columns, &options)
+            .unwrap();
+        Some(batch)
+    }
+}
+
+/// Schema for the Poi table
+pub(crate) static POI_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_poi_schema);
+
+fn make_poi_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("p_poikey", DataType::Int64, false),
+        Field::new("p_citykey", DataType::Int64, false),
+        Field::new("p_category", DataType::Int64, false),
+        Field::new("p_name", DataType::Utf8View, false),
+        Field::new("p_location", DataType::Binary, false),
+    ]))
+}
+
+/// Schema for the Poi table, with the cells of the location for each of
+/// `cell_indexes`
+pub(crate) fn poi_schema(cell_indexes: &[CellIndex]) -> SchemaRef {
+    if cell_indexes.is_empty() {
+        return Arc::clone(&POI_SCHEMA);
+    }
+    let mut fields: Vec<_> = POI_SCHEMA.fields().iter().cloned().collect();
+    let cell_fields = cell_index_fields(&["p_location"], cell_indexes);
+    fields.extend(cell_fields.into_iter().map(Arc::new));
+    Arc::new(Schema::new(fields))
+}
@@ -0,0 +1,199 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::lineage::{add_lineage, lineage_schema, row_lineage};
+use crate::nulls::{null_rates, NullRates};
+use crate::{RecordBatchIterator, Table, DEFAULT_BATCH_SIZE};
+use arrow::array::{
+    ArrayRef, Float64Array, Int16Array, Int32Array, Int64Array, RecordBatch, RecordBatchOptions,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use geo::Coord;
+use spatialbench::generators::{PointCloudGenerator, PointCloudGeneratorIterator, Projection};
+use spatialbench::spatial::crs::{self, Crs};
+use std::sync::{Arc, LazyLock};
+
+/// Generate the [`PointCloudPoint`]s in [`RecordBatch`] format
+///
+/// Unlike the other Arrow generators, the points are not generated as rows
+/// and then converted: the columns of each batch are filled directly with
+/// [`PointCloudGeneratorIterator::next_columns`], which is what makes
+/// billions of points practical.
+///
+/// [`PointCloudPoint`]: spatialbench::generators::PointCloudPoint
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::PointCloudGenerator;
+/// # use spatialbench_arrow::PointCloudArrow;
+///
+/// // Create a SF=0.01 generator and wrap it in an Arrow generator
+/// let generator = PointCloudGenerator::new(0.01, 1, 1);
+/// let mut arrow_generator = PointCloudArrow::new(generator)
+///   .with_batch_size(10);
+/// // Read the first batch
+/// let batch = arrow_generator.next().unwrap();
+/// assert_eq!(batch.num_rows(), 10);
+/// ```
+pub struct PointCloudArrow {
+    inner: PointCloudGeneratorIterator,
+    batch_size: usize,
+    projection: Vec<usize>,
+    schema: SchemaRef,
+    seed: u64,
+    null_rates: NullRates,
+    row_lineage: bool,
+    crs: Crs,
+}
+
+impl PointCloudArrow {
+    pub fn new(generator: PointCloudGenerator) -> Self {
+        let null_rates = null_rates().clone();
+        let row_lineage = row_lineage();
+        Self {
+            inner: generator.iter(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            projection: (0..POINTCLOUD_SCHEMA.fields().len()).collect(),
+            schema: lineage_schema(
+                null_rates.nullable_schema(Table::PointCloud, POINTCLOUD_SCHEMA.clone()),
+                row_lineage,
+            ),
+            seed: generator.seed(),
+            null_rates,
+            row_lineage,
+            crs: crs::crs(),
+        }
+    }
+
+    /// Set the batch size
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Only generate the columns at the `projection` indices of the schema
+    pub fn with_projection(mut self, projection: Vec<usize>) -> Self {
+        let schema = POINTCLOUD_SCHEMA
+            .project(&projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(
+            self.null_rates
+                .nullable_schema(Table::PointCloud, Arc::new(schema)),
+            self.row_lineage,
+        );
+        let columns = projection.iter().copied();
+        self.inner = self.inner.with_projection(Projection::new(columns));
+        self.projection = projection;
+        self
+    }
+
+    /// Make a fraction of the values of the columns of `null_rates` null or
+    /// empty
+    ///
+    /// Defaults to the rates set with [`set_null_rates`](crate::nulls::set_null_rates).
+    pub fn with_null_rates(mut self, null_rates: NullRates) -> Self {
+        let schema = POINTCLOUD_SCHEMA
+            .project(&self.projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(
+            null_rates.nullable_schema(Table::PointCloud, Arc::new(schema)),
+            self.row_lineage,
+        );
+        self.null_rates = null_rates;
+        self
+    }
+
+    /// Add the `_rowgen` column with the lineage of each row
+    ///
+    /// Defaults to the value set with [`set_row_lineage`](crate::lineage::set_row_lineage).
+    pub fn with_row_lineage(mut self, row_lineage: bool) -> Self {
+        self.schema = lineage_schema(Arc::clone(&self.schema), row_lineage);
+        self.row_lineage = row_lineage;
+        self
+    }
+
+    /// Project the `pc_x` and `pc_y` coordinates to `crs`
+    ///
+    /// Defaults to the CRS set with [`crs::set_crs`].
+    pub fn with_crs(mut self, crs: Crs) -> Self {
+        self.crs = crs;
+        self
+    }
+}
+
+impl RecordBatchIterator for PointCloudArrow {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+}
+
+impl Iterator for PointCloudArrow {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut points = self.inner.next_columns(self.batch_size)?;
+        let rows = points.len();
+        if self.crs != Crs::Wgs84 {
+            for (x, y) in points.pc_x.iter_mut().zip(points.pc_y.iter_mut()) {
+                let coord = self.crs.project(Coord { x: *x, y: *y });
+                (*x, *y) = (coord.x, coord.y);
+            }
+        }
+
+        // Only convert the projected columns, moving the vectors to Arrow
+        // without copying them
+        let keys = Arc::new(Int64Array::from(std::mem::take(&mut points.pc_pointkey)));
+        let columns = self
+            .projection
+            .iter()
+            .map(|&column| -> ArrayRef {
+                match column {
+                    0 => keys.clone(),
+                    1 => Arc::new(Float64Array::from(std::mem::take(&mut points.pc_x))),
+                    2 => Arc::new(Float64Array::from(std::mem::take(&mut points.pc_y))),
+                    3 => Arc::new(Float64Array::from(std::mem::take(&mut points.pc_z))),
+                    4 => Arc::new(Int32Array::from(std::mem::take(&mut points.pc_intensity))),
+                    5 => Arc::new(Int16Array::from(std::mem::take(
+                        &mut points.pc_classification,
+                    ))),
+                    _ => unreachable!("projection is checked against the schema"),
+                }
+            })
+            .collect();
+        let columns =
+            self.null_rates
+                .apply(Table::PointCloud, &self.schema, keys.values(), columns);
+        let columns = add_lineage(columns, keys.values(), self.seed, self.row_lineage);
+        let options = RecordBatchOptions::new().with_row_count(Some(rows));
+        let batch = RecordBatch::try_new_with_options(Arc::clone(self.schema()), columns, &options)
+            .unwrap();
+        Some(batch)
+    }
+}
+
+/// Schema for the PointCloud table
+pub(crate) static POINTCLOUD_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_pointcloud_schema);
+
+fn make_pointcloud_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("pc_pointkey", DataType::Int64, false),
+        Field::new("pc_x", DataType::Float64, false),
+        Field::new("pc_y", DataType::Float64, false),
+        Field::new("pc_z", DataType::Float64, false),
+        Field::new("pc_intensity", DataType::Int32, false),
+        Field::new("pc_classification", DataType::Int16, false),
+    ]))
+}
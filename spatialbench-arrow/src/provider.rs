@@ -0,0 +1,244 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! [DataFusion] integration: [`SpatialBenchTableProvider`]
+//!
+//! [DataFusion]: https://datafusion.apache.org/
+
+use crate::{Table, TableGenerator};
+use arrow::datatypes::SchemaRef;
+use async_trait::async_trait;
+use datafusion::catalog::{Session, TableProvider};
+use datafusion::common::stats::Precision;
+use datafusion::common::{Result, Statistics};
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::logical_expr::{Expr, TableType};
+use datafusion::physical_plan::stream::RecordBatchReceiverStreamBuilder;
+use datafusion::physical_plan::streaming::{PartitionStream, StreamingTableExec};
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::prelude::SessionContext;
+use spatialbench::generators::RowCounts;
+use std::any::Any;
+use std::sync::Arc;
+
+/// A DataFusion [`TableProvider`] for a Spatial Bench [`Table`] that
+/// generates the data when it is scanned, rather than reading files
+///
+/// Each scan generates the table in as many partitions as the session's
+/// `target_partitions` (or [`Self::with_partitions`]), which DataFusion runs
+/// in parallel, in batches of the session's `batch_size`. Only the projected
+/// columns are passed to the rest of the plan, and generation stops early
+/// when a `LIMIT` is satisfied.
+///
+/// # Example
+/// ```
+/// # use spatialbench_arrow::{SpatialBenchTableProvider, Table};
+/// # use datafusion::prelude::SessionContext;
+/// # use std::sync::Arc;
+/// # #[tokio::main(flavor = "multi_thread")]
+/// # async fn main() -> datafusion::common::Result<()> {
+/// let ctx = SessionContext::new();
+/// let trip = SpatialBenchTableProvider::new(Table::Trip, 0.01);
+/// ctx.register_table("trip", Arc::new(trip))?;
+///
+/// let batches = ctx
+///     .sql("SELECT count(*), max(t_tripkey) FROM trip")
+///     .await?
+///     .collect()
+///     .await?;
+/// let formatted = arrow::util::pretty::pretty_format_batches(&batches)?.to_string();
+/// assert_eq!(formatted.lines().collect::<Vec<_>>(), vec![
+///     "+----------+---------------------+",
+///     "| count(*) | max(trip.t_tripkey) |",
+///     "+----------+---------------------+",
+///     "| 60000    | 60000               |",
+///     "+----------+---------------------+",
+/// ]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpatialBenchTableProvider {
+    table: Table,
+    scale_factor: f64,
+    seed: u64,
+    row_counts: RowCounts,
+    /// Number of partitions to generate, if not the session's target partitions
+    partitions: Option<usize>,
+}
+
+impl SpatialBenchTableProvider {
+    /// Create a provider for `table` at `scale_factor`
+    pub fn new(table: Table, scale_factor: f64) -> Self {
+        Self {
+            table,
+            scale_factor,
+            seed: 0,
+            row_counts: RowCounts::default(),
+            partitions: None,
+        }
+    }
+
+    /// Generate the table from `seed`, see [`TableGenerator::with_seed`]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Override the number of rows, see [`TableGenerator::with_row_counts`]
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Generate the table in `partitions` partitions when scanned
+    pub fn with_partitions(mut self, partitions: usize) -> Self {
+        self.partitions = Some(partitions);
+        self
+    }
+}
+
+#[async_trait]
+impl TableProvider for SpatialBenchTableProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.table.schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let config = state.config();
+        let num_parts = self.partitions.unwrap_or(config.target_partitions()).max(1);
+        let partitions = (1..=num_parts as i32)
+            .map(|part| {
+                let generator = TableGenerator::new(self.table, self.scale_factor)
+                    .with_part(part, num_parts as i32)
+                    .with_seed(self.seed)
+                    .with_row_counts(self.row_counts)
+                    .with_batch_size(config.batch_size());
+                let schema = generator.schema();
+                Arc::new(GeneratorPartition { schema, generator }) as Arc<dyn PartitionStream>
+            })
+            .collect();
+        Ok(Arc::new(StreamingTableExec::try_new(
+            self.schema(),
+            partitions,
+            projection,
+            None,
+            false,
+            limit,
+        )?))
+    }
+
+    fn statistics(&self) -> Option<Statistics> {
+        let num_rows = TableGenerator::new(self.table, self.scale_factor)
+            .with_row_counts(self.row_counts)
+            .row_count();
+        let statistics = Statistics::new_unknown(&self.schema())
+            .with_num_rows(Precision::Exact(num_rows as usize));
+        Some(statistics)
+    }
+}
+
+/// Register a [`SpatialBenchTableProvider`] for every [`Table`] at
+/// `scale_factor`, using the table names such as `trip`
+pub fn register_tables(ctx: &SessionContext, scale_factor: f64) -> Result<()> {
+    for table in Table::ALL {
+        let provider = SpatialBenchTableProvider::new(table, scale_factor);
+        ctx.register_table(table.name(), Arc::new(provider))?;
+    }
+    Ok(())
+}
+
+/// One partition of a [`SpatialBenchTableProvider`] scan
+#[derive(Debug)]
+struct GeneratorPartition {
+    schema: SchemaRef,
+    generator: TableGenerator,
+}
+
+impl PartitionStream for GeneratorPartition {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let mut builder = RecordBatchReceiverStreamBuilder::new(Arc::clone(&self.schema), 2);
+        let tx = builder.tx();
+        let generator = self.generator;
+        // generating is CPU bound, so run it on a blocking thread
+        builder.spawn_blocking(move || {
+            for batch in generator.batches() {
+                if tx.blocking_send(Ok(batch)).is_err() {
+                    // the stream was dropped, for example when a limit is reached
+                    break;
+                }
+            }
+            Ok(())
+        });
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{AsArray, RecordBatch};
+    use arrow::datatypes::Int64Type;
+
+    async fn query(ctx: &SessionContext, sql: &str) -> Vec<RecordBatch> {
+        ctx.sql(sql).await.unwrap().collect().await.unwrap()
+    }
+
+    fn single_value(batches: &[RecordBatch]) -> i64 {
+        assert_eq!(batches.len(), 1);
+        batches[0].column(0).as_primitive::<Int64Type>().value(0)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_scan() {
+        let ctx = SessionContext::new();
+        register_tables(&ctx, 0.001).unwrap();
+        let trip = SpatialBenchTableProvider::new(Table::Trip, 0.001).with_partitions(3);
+        ctx.register_table("trip3", Arc::new(trip)).unwrap();
+
+        // projection and limit
+        let batches = query(&ctx, "SELECT t_tripkey, t_pickuploc FROM trip LIMIT 5").await;
+        assert_eq!(batches[0].schema().fields().len(), 2);
+        let num_rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        assert_eq!(num_rows, 5);
+
+        // the partitions have every row once, and every trip has a vehicle
+        let expected = TableGenerator::new(Table::Trip, 0.001).row_count();
+        let sql = "SELECT count(DISTINCT t_tripkey) FROM trip3";
+        assert_eq!(single_value(&query(&ctx, sql).await), expected);
+        let sql = "SELECT count(*) FROM trip3 JOIN vehicle ON t_vehiclekey = v_vehiclekey";
+        assert_eq!(single_value(&query(&ctx, sql).await), expected);
+    }
+}
@@ -0,0 +1,241 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Describe the tables for loaders: [`TableSchema`] and [`ColumnSchema`]
+//!
+//! The Arrow schema of a table ([`Table::schema`]) has the names, types and
+//! nullability of its columns. [`TableSchema`] adds what the Arrow types do
+//! not say: which binary columns are WKB geometries and how (geometry type,
+//! CRS, dimensions and edges), the key of the table, and the columns that
+//! reference the keys of other tables.
+//!
+//! # Example
+//! ```
+//! # use spatialbench_arrow::schema::TableSchema;
+//! # use spatialbench_arrow::Table;
+//! let trip = TableSchema::new(Table::Trip);
+//! assert_eq!(trip.primary_key(), "t_tripkey");
+//!
+//! let custkey = trip.column("t_custkey").unwrap();
+//! let references = custkey.references.unwrap();
+//! assert_eq!((references.table, references.column), (Table::Customer, "c_custkey"));
+//!
+//! let pickup = trip.column("t_pickuploc").unwrap();
+//! assert_eq!(pickup.geometry.unwrap().geometry_type, "Point");
+//! ```
+
+use crate::{Table, GEOMETRY_TYPE_KEY};
+use arrow::datatypes::{DataType, SchemaRef};
+use spatialbench::spatial::{crs, dimensions, geography, Crs, Dimensions};
+
+/// The columns and keys of a [`Table`]
+///
+/// The columns are the ones of [`Table::schema`], so they depend on the
+/// same settings, and the geometries have the CRS and dimensions set with
+/// [`crs::set_crs`] and [`dimensions::set_dimensions`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSchema {
+    table: Table,
+    schema: SchemaRef,
+    columns: Vec<ColumnSchema>,
+}
+
+/// A column of a [`TableSchema`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSchema {
+    /// Name of the column, e.g. `t_pickuploc`
+    pub name: String,
+    /// Arrow type of the column
+    pub data_type: DataType,
+    /// Whether the column may have nulls
+    pub nullable: bool,
+    /// How the geometries are written, if the column is a WKB geometry
+    pub geometry: Option<GeometryColumn>,
+    /// The key column this column references, if any
+    pub references: Option<ColumnRef>,
+}
+
+/// How the geometries of a WKB column are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeometryColumn {
+    /// GeoParquet name of a type that holds all the geometries, e.g.
+    /// `Point` or `MultiPolygon`
+    pub geometry_type: &'static str,
+    /// Coordinate reference system of the coordinates
+    pub crs: Crs,
+    /// Dimensions of the coordinates
+    pub dimensions: Dimensions,
+    /// Whether the edges are on the sphere rather than straight lines
+    pub spherical: bool,
+}
+
+/// A column of another table, referenced by a foreign key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnRef {
+    pub table: Table,
+    pub column: &'static str,
+}
+
+impl TableSchema {
+    /// Describe `table`
+    pub fn new(table: Table) -> Self {
+        let schema = table.schema();
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let geometry = matches!(field.data_type(), DataType::Binary).then(|| {
+                    let declared = field.metadata().get(GEOMETRY_TYPE_KEY);
+                    GeometryColumn {
+                        geometry_type: declared
+                            .and_then(|name| GEOMETRY_TYPES.iter().find(|t| *t == name))
+                            .copied()
+                            .unwrap_or_else(|| geometry_type(field.name())),
+                        crs: crs::crs(),
+                        // trajectory paths are the only geometries with M
+                        dimensions: match table {
+                            Table::Trajectory => dimensions::dimensions(),
+                            _ => dimensions::dimensions().without_m(),
+                        },
+                        spherical: geography::geography(),
+                    }
+                });
+                ColumnSchema {
+                    name: field.name().clone(),
+                    data_type: field.data_type().clone(),
+                    nullable: field.is_nullable(),
+                    geometry,
+                    references: references(field.name()),
+                }
+            })
+            .collect();
+        Self {
+            table,
+            schema,
+            columns,
+        }
+    }
+
+    /// Describe all the tables
+    pub fn all() -> Vec<Self> {
+        Table::ALL.into_iter().map(Self::new).collect()
+    }
+
+    /// Return the described table
+    pub fn table(&self) -> Table {
+        self.table
+    }
+
+    /// Return the Arrow schema of the table
+    pub fn arrow_schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Return the columns, in the order they are generated
+    pub fn columns(&self) -> &[ColumnSchema] {
+        &self.columns
+    }
+
+    /// Return the column named `name`, if any
+    pub fn column(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Return the name of the key column, which is unique in the table
+    pub fn primary_key(&self) -> &'static str {
+        match self.table {
+            Table::Vehicle => "v_vehiclekey",
+            Table::Driver => "d_driverkey",
+            Table::Customer => "c_custkey",
+            Table::Trip => "t_tripkey",
+            Table::Building => "b_buildingkey",
+            Table::Trajectory => "tr_tripkey",
+            Table::Road => "r_roadkey",
+            Table::Landmass => "l_landmasskey",
+            Table::Country => "co_countrykey",
+            Table::Region => "re_regionkey",
+            Table::City => "ci_citykey",
+            Table::Poi => "p_poikey",
+            Table::Raster => "rs_cellkey",
+            Table::PointCloud => "pc_pointkey",
+        }
+    }
+}
+
+/// GeoParquet names of the geometry types
+const GEOMETRY_TYPES: [&str; 7] = [
+    "Point",
+    "LineString",
+    "Polygon",
+    "MultiPoint",
+    "MultiLineString",
+    "MultiPolygon",
+    "GeometryCollection",
+];
+
+/// Return the type of the geometries of the WKB column `column`
+fn geometry_type(column: &str) -> &'static str {
+    match column {
+        "t_pickuploc" | "t_dropoffloc" | "p_location" => "Point",
+        "b_boundary" | "co_boundary" | "re_boundary" | "ci_boundary" | "rs_cell" => "Polygon",
+        "tr_path" | "r_line" => "LineString",
+        "l_boundary" => "MultiPolygon",
+        _ => "Geometry",
+    }
+}
+
+/// Return the key column referenced by `column`, if any
+fn references(column: &str) -> Option<ColumnRef> {
+    let (table, key) = match column {
+        "t_custkey" => (Table::Customer, "c_custkey"),
+        "t_driverkey" => (Table::Driver, "d_driverkey"),
+        "t_vehiclekey" => (Table::Vehicle, "v_vehiclekey"),
+        "tr_tripkey" => (Table::Trip, "t_tripkey"),
+        "b_duplicateof" => (Table::Building, "b_buildingkey"),
+        "re_countrykey" => (Table::Country, "co_countrykey"),
+        "ci_regionkey" => (Table::Region, "re_regionkey"),
+        "p_citykey" => (Table::City, "ci_citykey"),
+        _ => return None,
+    };
+    Some(ColumnRef { table, column: key })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_schemas() {
+        for schema in TableSchema::all() {
+            let arrow_schema = schema.table().schema();
+            assert_eq!(schema.columns().len(), arrow_schema.fields().len());
+            assert_eq!(schema.columns()[0].name, schema.primary_key());
+            for column in schema.columns() {
+                let is_binary = column.data_type == DataType::Binary;
+                assert_eq!(column.geometry.is_some(), is_binary, "{}", column.name);
+                if let Some(geometry) = column.geometry {
+                    assert_ne!(geometry.geometry_type, "Geometry", "{}", column.name);
+                }
+                // keys reference the key columns of existing columns
+                if let Some(references) = column.references {
+                    let referenced = TableSchema::new(references.table);
+                    assert_eq!(referenced.primary_key(), references.column);
+                }
+            }
+        }
+    }
+}
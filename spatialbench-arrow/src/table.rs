@@ -0,0 +1,536 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Generate any Spatial Bench table by name: [`Table`] and [`TableGenerator`]
+
+use crate::building::building_schema;
+use crate::city::CITY_SCHEMA;
+use crate::country::COUNTRY_SCHEMA;
+use crate::customer::CUSTOMER_SCHEMA;
+use crate::driver::DRIVER_SCHEMA;
+use crate::landmass::LANDMASS_SCHEMA;
+use crate::lineage;
+use crate::nulls::{self, NullRates};
+use crate::poi::poi_schema;
+use crate::pointcloud::POINTCLOUD_SCHEMA;
+use crate::raster::RASTER_SCHEMA;
+use crate::region::REGION_SCHEMA;
+use crate::road::ROAD_SCHEMA;
+use crate::trajectory::TRAJECTORY_SCHEMA;
+use crate::trip::trip_schema;
+use crate::vehicle::VEHICLE_SCHEMA;
+use crate::{
+    BuildingArrow, CityArrow, CountryArrow, CustomerArrow, DriverArrow, LandmassArrow, PoiArrow,
+    PointCloudArrow, RasterArrow, RecordBatchIterator, RegionArrow, RoadArrow, TrajectoryArrow,
+    TripArrow, VehicleArrow, DEFAULT_BATCH_SIZE,
+};
+use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
+use spatialbench::dates::GenerateUtils;
+use spatialbench::generators::{
+    BuildingGenerator, CityGenerator, CountryGenerator, CustomerGenerator, DriverGenerator,
+    LandmassGenerator, PoiGenerator, PointCloudGenerator, RasterGenerator, RegionGenerator,
+    RoadGenerator, RowCounts, TrajectoryGenerator, TripGenerator, VehicleGenerator,
+};
+use spatialbench::names;
+use spatialbench::skew::{self, KeySkews};
+use spatialbench::spatial::{cell, duplicate, invalid};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The Spatial Bench tables that can be generated as Arrow
+///
+/// The zone table is derived from Overture Maps data rather than generated,
+/// and is only available from the `spatialbench-cli` tool.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Table {
+    Vehicle,
+    Driver,
+    Customer,
+    Trip,
+    Building,
+    /// The paths of the trips, not part of the benchmark queries
+    Trajectory,
+    /// The streets of the road network the trips may snap to, not part of
+    /// the benchmark queries
+    Road,
+    /// The continents and islands of the land mask the trips may stay on,
+    /// not part of the benchmark queries
+    Landmass,
+    /// The countries of the administrative hierarchy, not part of the
+    /// benchmark queries
+    Country,
+    /// The regions of the countries, not part of the benchmark queries
+    Region,
+    /// The cities of the regions, not part of the benchmark queries
+    City,
+    /// Points of interest in the cities, not part of the benchmark queries
+    Poi,
+    /// The cells of a raster of elevation and temperature, not part of the
+    /// benchmark queries
+    Raster,
+    /// The points of a LiDAR style point cloud, not part of the benchmark
+    /// queries
+    PointCloud,
+}
+
+impl Table {
+    /// All the tables
+    pub const ALL: [Table; 14] = [
+        Table::Vehicle,
+        Table::Driver,
+        Table::Customer,
+        Table::Trip,
+        Table::Building,
+        Table::Trajectory,
+        Table::Road,
+        Table::Landmass,
+        Table::Country,
+        Table::Region,
+        Table::City,
+        Table::Poi,
+        Table::Raster,
+        Table::PointCloud,
+    ];
+
+    /// Return the name of the table, such as `trip`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Table::Vehicle => "vehicle",
+            Table::Driver => "driver",
+            Table::Customer => "customer",
+            Table::Trip => "trip",
+            Table::Building => "building",
+            Table::Trajectory => "trajectory",
+            Table::Road => "road",
+            Table::Landmass => "landmass",
+            Table::Country => "country",
+            Table::Region => "region",
+            Table::City => "city",
+            Table::Poi => "poi",
+            Table::Raster => "raster",
+            Table::PointCloud => "pointcloud",
+        }
+    }
+
+    /// Return the Arrow schema of the table
+    ///
+    /// The building table has the `b_isvalid` column if an invalid rate is
+    /// set with [`invalid::set_invalid_rate`], the duplicate columns if a
+    /// duplicate rate is set with [`duplicate::set_duplicate_rate`], and the
+    /// columns with nulls set with [`nulls::set_null_rates`] are nullable.
+    /// The trip and POI tables have the cell index columns set with
+    /// [`cell::set_cell_indexes`]. Every table ends with the `_rowgen` column
+    /// if it is set with [`lineage::set_row_lineage`].
+    pub fn schema(&self) -> SchemaRef {
+        lineage::lineage_schema(
+            nulls::null_rates().nullable_schema(*self, self.columns()),
+            lineage::row_lineage(),
+        )
+    }
+
+    /// Return the schema of the table without nulls or lineage
+    fn columns(&self) -> SchemaRef {
+        match self {
+            Table::Vehicle => Arc::clone(&VEHICLE_SCHEMA),
+            Table::Driver => Arc::clone(&DRIVER_SCHEMA),
+            Table::Customer => Arc::clone(&CUSTOMER_SCHEMA),
+            Table::Trip => trip_schema(cell::cell_indexes()),
+            Table::Building => building_schema(
+                false,
+                invalid::invalid_rate() > 0.0,
+                duplicate::duplicate_rate() > 0.0,
+            ),
+            Table::Trajectory => Arc::clone(&TRAJECTORY_SCHEMA),
+            Table::Road => Arc::clone(&ROAD_SCHEMA),
+            Table::Landmass => Arc::clone(&LANDMASS_SCHEMA),
+            Table::Country => Arc::clone(&COUNTRY_SCHEMA),
+            Table::Region => Arc::clone(&REGION_SCHEMA),
+            Table::City => Arc::clone(&CITY_SCHEMA),
+            Table::Poi => poi_schema(cell::cell_indexes()),
+            Table::Raster => Arc::clone(&RASTER_SCHEMA),
+            Table::PointCloud => Arc::clone(&POINTCLOUD_SCHEMA),
+        }
+    }
+}
+
+impl Display for Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Table {
+    type Err = String;
+
+    /// Returns the table with the given name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Table::ALL
+            .into_iter()
+            .find(|table| table.name() == s)
+            .ok_or_else(|| format!("Invalid table name {s}"))
+    }
+}
+
+/// Generates one part of a Spatial Bench [`Table`] as Arrow [`RecordBatch`]es
+///
+/// This is the same data written by `spatialbench-cli`, so it can be used to
+/// embed data generation in benchmark harnesses or query engines. Each part
+/// is generated independently, so the parts of a table can be generated in
+/// parallel, and together they contain exactly the rows of the whole table.
+///
+/// # Example
+/// ```
+/// # use spatialbench_arrow::{Table, TableGenerator};
+/// // Generate the second of four parts of the SF=0.01 trip table
+/// let generator = TableGenerator::new(Table::Trip, 0.01)
+///     .with_part(2, 4)
+///     .with_batch_size(1000);
+/// assert_eq!(generator.row_count(), 15_000);
+///
+/// let mut num_rows = 0;
+/// for batch in generator.batches() {
+///     assert_eq!(batch.schema(), Table::Trip.schema());
+///     assert!(batch.num_rows() <= 1000);
+///     num_rows += batch.num_rows();
+/// }
+/// assert_eq!(num_rows, 15_000);
+///
+/// // the tables can also be looked up by name
+/// let generator = TableGenerator::new("vehicle".parse().unwrap(), 1.0);
+/// assert_eq!(generator.batches().next().unwrap().num_rows(), 100);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableGenerator {
+    table: Table,
+    scale_factor: f64,
+    part: i32,
+    num_parts: i32,
+    seed: u64,
+    row_counts: RowCounts,
+    null_rates: NullRates,
+    row_lineage: bool,
+    realistic_text: bool,
+    key_skews: KeySkews,
+    batch_size: usize,
+}
+
+impl TableGenerator {
+    /// Create a generator for all of `table` at `scale_factor`
+    pub fn new(table: Table, scale_factor: f64) -> Self {
+        Self {
+            table,
+            scale_factor,
+            part: 1,
+            num_parts: 1,
+            seed: 0,
+            row_counts: RowCounts::default(),
+            null_rates: nulls::null_rates().clone(),
+            row_lineage: lineage::row_lineage(),
+            realistic_text: names::realistic_text(),
+            key_skews: skew::key_skews(),
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    /// Only generate part `part` (1-based) of `num_parts` parts
+    pub fn with_part(mut self, part: i32, num_parts: i32) -> Self {
+        self.part = part;
+        self.num_parts = num_parts;
+        self
+    }
+
+    /// Derive all random values from `seed` instead of the reference seed `0`
+    ///
+    /// The same seed, scale factor and parts always generate the same rows.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Override the number of rows of the tables, see [`RowCounts`]
+    ///
+    /// The counts of the referenced tables also change the foreign keys of
+    /// the trips.
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Make a fraction of the values of the columns of `null_rates` null or
+    /// empty
+    ///
+    /// Defaults to the rates set with [`nulls::set_null_rates`].
+    pub fn with_null_rates(mut self, null_rates: NullRates) -> Self {
+        self.null_rates = null_rates;
+        self
+    }
+
+    /// Add the `_rowgen` column with the lineage of each row
+    ///
+    /// Defaults to the value set with [`lineage::set_row_lineage`].
+    pub fn with_row_lineage(mut self, row_lineage: bool) -> Self {
+        self.row_lineage = row_lineage;
+        self
+    }
+
+    /// Make up realistic names and addresses for the drivers, customers,
+    /// countries, regions, cities and POIs
+    ///
+    /// Defaults to the mode set with [`names::set_realistic_text`].
+    pub fn with_realistic_text(mut self, realistic_text: bool) -> Self {
+        self.realistic_text = realistic_text;
+        self
+    }
+
+    /// Reference the keys of the trips and POIs with `key_skews`
+    ///
+    /// Defaults to the skews set with [`skew::set_key_skews`].
+    pub fn with_key_skews(mut self, key_skews: KeySkews) -> Self {
+        self.key_skews = key_skews;
+        self
+    }
+
+    /// Set the maximum number of rows in each batch
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Return the table being generated
+    pub fn table(&self) -> Table {
+        self.table
+    }
+
+    /// Return the scale factor being generated
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Return the seed being generated
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Return the overridden row counts
+    pub fn row_counts(&self) -> RowCounts {
+        self.row_counts
+    }
+
+    /// Return the null and empty rates of the columns
+    pub fn null_rates(&self) -> &NullRates {
+        &self.null_rates
+    }
+
+    /// Return whether the batches have the `_rowgen` column
+    pub fn row_lineage(&self) -> bool {
+        self.row_lineage
+    }
+
+    /// Return whether the names and addresses are realistic
+    pub fn realistic_text(&self) -> bool {
+        self.realistic_text
+    }
+
+    /// Return the skews of the foreign keys
+    pub fn key_skews(&self) -> KeySkews {
+        self.key_skews
+    }
+
+    /// Return the schema of the generated batches
+    pub fn schema(&self) -> SchemaRef {
+        let schema = self
+            .null_rates
+            .nullable_schema(self.table, self.table.columns());
+        lineage::lineage_schema(schema, self.row_lineage)
+    }
+
+    /// Return the number of rows in this part of the table
+    pub fn row_count(&self) -> i64 {
+        let (sf, part, num_parts) = (self.scale_factor, self.part, self.num_parts);
+        let total_row_count = match self.table {
+            Table::Vehicle => self.row_counts.vehicle_count(sf),
+            Table::Driver => self.row_counts.driver_count(sf),
+            Table::Customer => self.row_counts.customer_count(sf),
+            Table::Trip => self.row_counts.trip_count(sf),
+            Table::Building => self.row_counts.building_count(sf),
+            // one trajectory per trip
+            Table::Trajectory => self.row_counts.trip_count(sf),
+            Table::Road => RoadGenerator::new(sf, 1, 1)
+                .with_seed(self.seed)
+                .road_count(),
+            Table::Landmass => LandmassGenerator::landmass_count(),
+            Table::Country => CountryGenerator::country_count(),
+            Table::Region => RegionGenerator::region_count(),
+            Table::City => CityGenerator::city_count(),
+            Table::Poi => self.row_counts.poi_count(sf),
+            Table::Raster => RasterGenerator::raster_count(sf),
+            Table::PointCloud => self.row_counts.pointcloud_count(sf),
+        };
+        GenerateUtils::part_row_count(total_row_count, part, num_parts)
+    }
+
+    /// Return an iterator over the batches of this part of the table
+    pub fn batches(&self) -> Box<dyn RecordBatchIterator> {
+        let (sf, part, num_parts, seed) = (self.scale_factor, self.part, self.num_parts, self.seed);
+        match self.table {
+            Table::Vehicle => Box::new(
+                VehicleArrow::new(
+                    VehicleGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_row_counts(self.row_counts),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::Driver => Box::new(
+                DriverArrow::new(
+                    DriverGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_realistic_text(self.realistic_text)
+                        .with_row_counts(self.row_counts),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::Customer => Box::new(
+                CustomerArrow::new(
+                    CustomerGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_realistic_text(self.realistic_text)
+                        .with_row_counts(self.row_counts),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::Trip => Box::new(
+                TripArrow::new(
+                    TripGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_key_skews(self.key_skews)
+                        .with_row_counts(self.row_counts),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::Building => Box::new(
+                BuildingArrow::new(
+                    BuildingGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_row_counts(self.row_counts),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::Trajectory => Box::new(
+                TrajectoryArrow::new(
+                    TrajectoryGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_row_counts(self.row_counts),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::Road => Box::new(
+                RoadArrow::new(RoadGenerator::new(sf, part, num_parts).with_seed(seed))
+                    .with_batch_size(self.batch_size)
+                    .with_null_rates(self.null_rates.clone())
+                    .with_row_lineage(self.row_lineage),
+            ),
+            Table::Landmass => Box::new(
+                LandmassArrow::new(LandmassGenerator::new(sf, part, num_parts).with_seed(seed))
+                    .with_batch_size(self.batch_size)
+                    .with_null_rates(self.null_rates.clone())
+                    .with_row_lineage(self.row_lineage),
+            ),
+            Table::Country => Box::new(
+                CountryArrow::new(
+                    CountryGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_realistic_text(self.realistic_text),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::Region => Box::new(
+                RegionArrow::new(
+                    RegionGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_realistic_text(self.realistic_text),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::City => Box::new(
+                CityArrow::new(
+                    CityGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_realistic_text(self.realistic_text),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::Poi => Box::new(
+                PoiArrow::new(
+                    PoiGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_key_skews(self.key_skews)
+                        .with_realistic_text(self.realistic_text)
+                        .with_row_counts(self.row_counts),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+            Table::Raster => Box::new(
+                RasterArrow::new(RasterGenerator::new(sf, part, num_parts).with_seed(seed))
+                    .with_batch_size(self.batch_size)
+                    .with_null_rates(self.null_rates.clone())
+                    .with_row_lineage(self.row_lineage),
+            ),
+            Table::PointCloud => Box::new(
+                PointCloudArrow::new(
+                    PointCloudGenerator::new(sf, part, num_parts)
+                        .with_seed(seed)
+                        .with_row_counts(self.row_counts),
+                )
+                .with_batch_size(self.batch_size)
+                .with_null_rates(self.null_rates.clone())
+                .with_row_lineage(self.row_lineage),
+            ),
+        }
+    }
+}
+
+impl IntoIterator for TableGenerator {
+    type Item = RecordBatch;
+    type IntoIter = Box<dyn RecordBatchIterator>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.batches()
+    }
+}
@@ -15,13 +15,22 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::conversions::{decimal128_array_from_iter, to_arrow_timestamp_millis};
-use crate::{RecordBatchIterator, DEFAULT_BATCH_SIZE};
-use arrow::array::{BinaryArray, Int64Array, RecordBatch, TimestampMillisecondArray};
+use crate::conversions::{
+    cell_index_array, cell_index_fields, decimal128_array_from_iter, geometry_to_wkb,
+    to_arrow_timestamp_millis,
+};
+use crate::lineage::{add_lineage, lineage_schema, row_lineage};
+use crate::nulls::{null_rates, NullRates};
+use crate::{RecordBatchIterator, Table, DEFAULT_BATCH_SIZE};
+use arrow::array::{
+    ArrayRef, BinaryArray, Int64Array, RecordBatch, RecordBatchOptions, TimestampMillisecondArray,
+};
 use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
 use geo::Geometry;
-use geozero::{CoordDimensions, ToWkb};
-use spatialbench::generators::{Trip, TripGenerator, TripGeneratorIterator};
+use spatialbench::generators::{Projection, Trip, TripGenerator, TripGeneratorIterator};
+use spatialbench::spatial::cell::{self, CellIndex};
+use spatialbench::spatial::crs::{self, Crs};
+use spatialbench::spatial::dimensions::{self, elevation, Dimensions};
 use std::sync::{Arc, LazyLock, Mutex};
 
 // Thread-safe wrapper for TripGeneratorIterator
@@ -36,6 +45,13 @@ impl ThreadSafeTripGenerator {
         }
     }
 
+    fn with_projection(self, projection: Projection) -> Self {
+        let generator = self.generator.into_inner().unwrap();
+        Self {
+            generator: Mutex::new(generator.with_projection(projection)),
+        }
+    }
+
     fn next_batch(&self, batch_size: usize) -> Vec<Trip> {
         let mut generator = self.generator.lock().unwrap();
         generator.by_ref().take(batch_size).collect()
@@ -49,15 +65,36 @@ unsafe impl Sync for ThreadSafeTripGenerator {}
 pub struct TripArrow {
     generator: ThreadSafeTripGenerator,
     batch_size: usize,
+    projection: Vec<usize>,
+    /// The schema of all the columns
+    table_schema: SchemaRef,
     schema: SchemaRef,
+    seed: u64,
+    null_rates: NullRates,
+    row_lineage: bool,
+    dimensions: Dimensions,
+    crs: Crs,
+    cell_indexes: Vec<CellIndex>,
 }
 
 impl TripArrow {
     pub fn new(generator: TripGenerator) -> Self {
+        let cell_indexes = cell::cell_indexes().to_vec();
+        let null_rates = null_rates().clone();
+        let row_lineage = row_lineage();
+        let schema = null_rates.nullable_schema(Table::Trip, trip_schema(&cell_indexes));
         Self {
+            seed: generator.seed(),
+            null_rates,
+            row_lineage,
             generator: ThreadSafeTripGenerator::new(generator),
             batch_size: DEFAULT_BATCH_SIZE,
-            schema: TRIP_SCHEMA.clone(),
+            projection: (0..schema.fields().len()).collect(),
+            table_schema: schema.clone(),
+            schema: lineage_schema(schema, row_lineage),
+            dimensions: dimensions::dimensions(),
+            crs: crs::crs(),
+            cell_indexes,
         }
     }
 
@@ -65,6 +102,87 @@ impl TripArrow {
         self.batch_size = batch_size;
         self
     }
+
+    /// Only generate the columns at the `projection` indices of the schema
+    pub fn with_projection(mut self, projection: Vec<usize>) -> Self {
+        let schema = self
+            .table_schema
+            .project(&projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(Arc::new(schema), self.row_lineage);
+        let columns = projection.iter().map(|&column| match column {
+            // the cells of the pickup, then of the dropoff locations
+            column if column >= TRIP_SCHEMA.fields().len() => {
+                let cell = column - TRIP_SCHEMA.fields().len();
+                if cell < self.cell_indexes.len() {
+                    10
+                } else {
+                    11
+                }
+            }
+            column => column,
+        });
+        self.generator = self.generator.with_projection(Projection::new(columns));
+        self.projection = projection;
+        self
+    }
+
+    /// Make a fraction of the values of the columns of `null_rates` null or
+    /// empty
+    ///
+    /// Defaults to the rates set with [`set_null_rates`](crate::nulls::set_null_rates).
+    pub fn with_null_rates(mut self, null_rates: NullRates) -> Self {
+        self.table_schema =
+            null_rates.nullable_schema(Table::Trip, trip_schema(&self.cell_indexes));
+        let schema = self
+            .table_schema
+            .project(&self.projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(Arc::new(schema), self.row_lineage);
+        self.null_rates = null_rates;
+        self
+    }
+
+    /// Add the `_rowgen` column with the lineage of each row
+    ///
+    /// Defaults to the value set with [`set_row_lineage`](crate::lineage::set_row_lineage).
+    pub fn with_row_lineage(mut self, row_lineage: bool) -> Self {
+        self.schema = lineage_schema(Arc::clone(&self.schema), row_lineage);
+        self.row_lineage = row_lineage;
+        self
+    }
+
+    /// Write the locations with `dimensions`, with the [`elevation`] of the
+    /// terrain as Z
+    ///
+    /// Defaults to the dimensions set with [`dimensions::set_dimensions`].
+    pub fn with_dimensions(mut self, dimensions: Dimensions) -> Self {
+        self.dimensions = dimensions;
+        self
+    }
+
+    /// Project the locations to `crs`
+    ///
+    /// Defaults to the CRS set with [`crs::set_crs`].
+    pub fn with_crs(mut self, crs: Crs) -> Self {
+        self.crs = crs;
+        self
+    }
+
+    /// Add a column with the cells of the pickup and dropoff locations for each of `indexes`
+    ///
+    /// Defaults to the indexes set with [`cell::set_cell_indexes`]. All the
+    /// columns are generated, as the schema gains or loses the columns.
+    pub fn with_cell_indexes(mut self, indexes: Vec<CellIndex>) -> Self {
+        self.table_schema = self
+            .null_rates
+            .nullable_schema(Table::Trip, trip_schema(&indexes));
+        self.schema = lineage_schema(Arc::clone(&self.table_schema), self.row_lineage);
+        self.projection = (0..self.schema.fields().len()).collect();
+        self.generator = self.generator.with_projection(Projection::default());
+        self.cell_indexes = indexes;
+        self
+    }
 }
 
 impl RecordBatchIterator for TripArrow {
@@ -83,59 +201,85 @@ impl Iterator for TripArrow {
             return None;
         }
 
-        // Convert column by column
-        let t_tripkey = Int64Array::from_iter_values(rows.iter().map(|row| row.t_tripkey));
-        let t_custkey = Int64Array::from_iter_values(rows.iter().map(|row| row.t_custkey));
-        let t_driverkey = Int64Array::from_iter_values(rows.iter().map(|row| row.t_driverkey));
-        let t_vehiclekey = Int64Array::from_iter_values(rows.iter().map(|row| row.t_vehiclekey));
-        let t_pickuptime = TimestampMillisecondArray::from_iter_values(
-            rows.iter()
-                .map(|row| to_arrow_timestamp_millis(row.t_pickuptime)),
-        );
-        let t_dropofftime = TimestampMillisecondArray::from_iter_values(
-            rows.iter()
-                .map(|row| to_arrow_timestamp_millis(row.t_dropofftime)),
-        );
-        let t_fare = decimal128_array_from_iter(rows.iter().map(|row| row.t_fare));
-        let t_tip = decimal128_array_from_iter(rows.iter().map(|row| row.t_tip));
-        let t_totalamount = decimal128_array_from_iter(rows.iter().map(|row| row.t_totalamount));
-        let t_distance = decimal128_array_from_iter(rows.iter().map(|row| row.t_distance));
-        let t_pickuploc = BinaryArray::from_iter_values(rows.iter().map(|row| {
-            Geometry::Point(row.t_pickuploc)
-                .to_wkb(CoordDimensions::xy())
-                .expect("Failed to convert pickup location to WKB")
-        }));
-        let t_dropoffloc = BinaryArray::from_iter_values(rows.iter().map(|row| {
-            Geometry::Point(row.t_dropoffloc)
-                .to_wkb(CoordDimensions::xy())
-                .expect("Failed to convert dropoff location to WKB")
-        }));
-
-        let batch = RecordBatch::try_new(
-            Arc::clone(&self.schema),
-            vec![
-                Arc::new(t_tripkey),
-                Arc::new(t_custkey),
-                Arc::new(t_driverkey),
-                Arc::new(t_vehiclekey),
-                Arc::new(t_pickuptime),
-                Arc::new(t_dropofftime),
-                Arc::new(t_fare),
-                Arc::new(t_tip),
-                Arc::new(t_totalamount),
-                Arc::new(t_distance),
-                Arc::new(t_pickuploc),
-                Arc::new(t_dropoffloc),
-            ],
-        )
-        .unwrap();
-
+        // Only convert the projected columns
+        let columns = self
+            .projection
+            .iter()
+            .map(|&column| -> ArrayRef {
+                match column {
+                    0 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|row| row.t_tripkey),
+                    )),
+                    1 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|row| row.t_custkey),
+                    )),
+                    2 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|row| row.t_driverkey),
+                    )),
+                    3 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|row| row.t_vehiclekey),
+                    )),
+                    4 => Arc::new(TimestampMillisecondArray::from_iter_values(
+                        rows.iter()
+                            .map(|row| to_arrow_timestamp_millis(row.t_pickuptime)),
+                    )),
+                    5 => Arc::new(TimestampMillisecondArray::from_iter_values(
+                        rows.iter()
+                            .map(|row| to_arrow_timestamp_millis(row.t_dropofftime)),
+                    )),
+                    6 => Arc::new(decimal128_array_from_iter(
+                        rows.iter().map(|row| row.t_fare),
+                    )),
+                    7 => Arc::new(decimal128_array_from_iter(rows.iter().map(|row| row.t_tip))),
+                    8 => Arc::new(decimal128_array_from_iter(
+                        rows.iter().map(|row| row.t_totalamount),
+                    )),
+                    9 => Arc::new(decimal128_array_from_iter(
+                        rows.iter().map(|row| row.t_distance),
+                    )),
+                    10 => Arc::new(BinaryArray::from_iter_values(rows.iter().map(|row| {
+                        geometry_to_wkb(
+                            &Geometry::Point(row.t_pickuploc),
+                            self.crs,
+                            self.dimensions,
+                            |coord| elevation(coord.x, coord.y),
+                        )
+                    }))),
+                    11 => Arc::new(BinaryArray::from_iter_values(rows.iter().map(|row| {
+                        geometry_to_wkb(
+                            &Geometry::Point(row.t_dropoffloc),
+                            self.crs,
+                            self.dimensions,
+                            |coord| elevation(coord.x, coord.y),
+                        )
+                    }))),
+                    // the cells of the pickup, then of the dropoff locations
+                    column => {
+                        let column = column - TRIP_SCHEMA.fields().len();
+                        let index = &self.cell_indexes[column % self.cell_indexes.len()];
+                        if column < self.cell_indexes.len() {
+                            cell_index_array(index, rows.iter().map(|row| &row.t_pickuploc))
+                        } else {
+                            cell_index_array(index, rows.iter().map(|row| &row.t_dropoffloc))
+                        }
+                    }
+                }
+            })
+            .collect();
+        let keys: Vec<i64> = rows.iter().map(|r| r.t_tripkey).collect();
+        let columns = self
+            .null_rates
+            .apply(Table::Trip, &self.schema, &keys, columns);
+        let columns = add_lineage(columns, &keys, self.seed, self.row_lineage);
+        let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+        let batch = RecordBatch::try_new_with_options(Arc::clone(self.schema()), columns, &options)
+            .unwrap();
         Some(batch)
     }
 }
 
 /// Schema for the Trip table
-static TRIP_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_trip_schema);
+pub(crate) static TRIP_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_trip_schema);
 
 fn make_trip_schema() -> SchemaRef {
     Arc::new(Schema::new(vec![
@@ -161,3 +305,15 @@ fn make_trip_schema() -> SchemaRef {
         Field::new("t_dropoffloc", DataType::Binary, false),
     ]))
 }
+
+/// Schema for the Trip table, with the cells of the locations for each of
+/// `cell_indexes`
+pub(crate) fn trip_schema(cell_indexes: &[CellIndex]) -> SchemaRef {
+    if cell_indexes.is_empty() {
+        return Arc::clone(&TRIP_SCHEMA);
+    }
+    let mut fields: Vec<_> = TRIP_SCHEMA.fields().iter().cloned().collect();
+    let cell_fields = cell_index_fields(&["t_pickuploc", "t_dropoffloc"], cell_indexes);
+    fields.extend(cell_fields.into_iter().map(Arc::new));
+    Arc::new(Schema::new(fields))
+}
@@ -16,10 +16,12 @@
 // under the License.
 
 use crate::conversions::string_view_array_from_display_iter;
-use crate::{RecordBatchIterator, DEFAULT_BATCH_SIZE};
-use arrow::array::{Int64Array, RecordBatch, StringViewArray};
+use crate::lineage::{add_lineage, lineage_schema, row_lineage};
+use crate::nulls::{null_rates, NullRates};
+use crate::{RecordBatchIterator, Table, DEFAULT_BATCH_SIZE};
+use arrow::array::{ArrayRef, Int64Array, RecordBatch, RecordBatchOptions, StringViewArray};
 use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
-use spatialbench::generators::{VehicleGenerator, VehicleGeneratorIterator};
+use spatialbench::generators::{Projection, VehicleGenerator, VehicleGeneratorIterator};
 use std::sync::{Arc, LazyLock};
 
 /// Generate [`Vehicle`]s in [`RecordBatch`] format
@@ -62,13 +64,28 @@ use std::sync::{Arc, LazyLock};
 pub struct VehicleArrow {
     inner: VehicleGeneratorIterator<'static>,
     batch_size: usize,
+    projection: Vec<usize>,
+    schema: SchemaRef,
+    seed: u64,
+    null_rates: NullRates,
+    row_lineage: bool,
 }
 
 impl VehicleArrow {
     pub fn new(generator: VehicleGenerator<'static>) -> Self {
+        let null_rates = null_rates().clone();
+        let row_lineage = row_lineage();
         Self {
             inner: generator.iter(),
             batch_size: DEFAULT_BATCH_SIZE,
+            projection: (0..VEHICLE_SCHEMA.fields().len()).collect(),
+            schema: lineage_schema(
+                null_rates.nullable_schema(Table::Vehicle, VEHICLE_SCHEMA.clone()),
+                row_lineage,
+            ),
+            seed: generator.seed(),
+            null_rates,
+            row_lineage,
         }
     }
 
@@ -77,11 +94,52 @@ impl VehicleArrow {
         self.batch_size = batch_size;
         self
     }
+
+    /// Only generate the columns at the `projection` indices of the schema
+    pub fn with_projection(mut self, projection: Vec<usize>) -> Self {
+        let schema = VEHICLE_SCHEMA
+            .project(&projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(
+            self.null_rates
+                .nullable_schema(Table::Vehicle, Arc::new(schema)),
+            self.row_lineage,
+        );
+        let columns = projection.iter().copied();
+        self.inner = self.inner.with_projection(Projection::new(columns));
+        self.projection = projection;
+        self
+    }
+
+    /// Make a fraction of the values of the columns of `null_rates` null or
+    /// empty
+    ///
+    /// Defaults to the rates set with [`set_null_rates`](crate::nulls::set_null_rates).
+    pub fn with_null_rates(mut self, null_rates: NullRates) -> Self {
+        let schema = VEHICLE_SCHEMA
+            .project(&self.projection)
+            .expect("projection out of bounds");
+        self.schema = lineage_schema(
+            null_rates.nullable_schema(Table::Vehicle, Arc::new(schema)),
+            self.row_lineage,
+        );
+        self.null_rates = null_rates;
+        self
+    }
+
+    /// Add the `_rowgen` column with the lineage of each row
+    ///
+    /// Defaults to the value set with [`set_row_lineage`](crate::lineage::set_row_lineage).
+    pub fn with_row_lineage(mut self, row_lineage: bool) -> Self {
+        self.schema = lineage_schema(Arc::clone(&self.schema), row_lineage);
+        self.row_lineage = row_lineage;
+        self
+    }
 }
 
 impl RecordBatchIterator for VehicleArrow {
     fn schema(&self) -> &SchemaRef {
-        &VEHICLE_SCHEMA
+        &self.schema
     }
 }
 
@@ -95,29 +153,45 @@ impl Iterator for VehicleArrow {
             return None;
         }
 
-        let v_vehiclekey = Int64Array::from_iter_values(rows.iter().map(|r| r.v_vehiclekey));
-        let v_mfgr = string_view_array_from_display_iter(rows.iter().map(|r| r.v_mfgr));
-        let v_brand = string_view_array_from_display_iter(rows.iter().map(|r| r.v_brand));
-        let v_type = StringViewArray::from_iter_values(rows.iter().map(|r| r.v_type));
-        let v_license = StringViewArray::from_iter_values(rows.iter().map(|r| r.v_license));
-
-        let batch = RecordBatch::try_new(
-            Arc::clone(self.schema()),
-            vec![
-                Arc::new(v_vehiclekey),
-                Arc::new(v_mfgr),
-                Arc::new(v_brand),
-                Arc::new(v_type),
-                Arc::new(v_license),
-            ],
-        )
-        .unwrap();
+        // Only convert the projected columns
+        let columns = self
+            .projection
+            .iter()
+            .map(|&column| -> ArrayRef {
+                match column {
+                    0 => Arc::new(Int64Array::from_iter_values(
+                        rows.iter().map(|r| r.v_vehiclekey),
+                    )),
+                    1 => Arc::new(string_view_array_from_display_iter(
+                        rows.iter().map(|r| r.v_mfgr),
+                    )),
+                    2 => Arc::new(string_view_array_from_display_iter(
+                        rows.iter().map(|r| r.v_brand),
+                    )),
+                    3 => Arc::new(StringViewArray::from_iter_values(
+                        rows.iter().map(|r| r.v_type),
+                    )),
+                    4 => Arc::new(StringViewArray::from_iter_values(
+                        rows.iter().map(|r| r.v_license),
+                    )),
+                    _ => unreachable!("projection is checked against the schema"),
+                }
+            })
+            .collect();
+        let keys: Vec<i64> = rows.iter().map(|r| r.v_vehiclekey).collect();
+        let columns = self
+            .null_rates
+            .apply(Table::Vehicle, &self.schema, &keys, columns);
+        let columns = add_lineage(columns, &keys, self.seed, self.row_lineage);
+        let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+        let batch = RecordBatch::try_new_with_options(Arc::clone(self.schema()), columns, &options)
+            .unwrap();
         Some(batch)
     }
 }
 
 /// Schema for the Vehicle
-static VEHICLE_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_vehicle_schema);
+pub(crate) static VEHICLE_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(make_vehicle_schema);
 fn make_vehicle_schema() -> SchemaRef {
     Arc::new(Schema::new(vec![
         Field::new("v_vehiclekey", DataType::Int64, false),
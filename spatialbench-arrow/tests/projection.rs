@@ -0,0 +1,123 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Verifies that projected Arrow generators produce the same columns as the
+//! full generators
+
+use spatialbench::generators::{
+    BuildingGenerator, CityGenerator, CountryGenerator, CustomerGenerator, DriverGenerator,
+    LandmassGenerator, PoiGenerator, PointCloudGenerator, RasterGenerator, RegionGenerator,
+    RoadGenerator, TrajectoryGenerator, TripGenerator, VehicleGenerator,
+};
+use spatialbench_arrow::{
+    BuildingArrow, CityArrow, CountryArrow, CustomerArrow, DriverArrow, LandmassArrow, PoiArrow,
+    PointCloudArrow, RasterArrow, RecordBatchIterator, RegionArrow, RoadArrow, Table,
+    TrajectoryArrow, TripArrow, VehicleArrow,
+};
+use std::sync::Arc;
+
+/// Assert that each column of the first batch of `$arrow` is the same when
+/// generated alone, as the generators skip the other columns
+macro_rules! assert_columns_alone {
+    ($arrow:expr) => {
+        let full = $arrow.next().unwrap();
+        for column in 0..full.num_columns() {
+            let projected = $arrow.with_projection(vec![column]).next().unwrap();
+            let name = full.schema().field(column).name().clone();
+            assert_eq!(full.project(&[column]).unwrap(), projected, "{name}");
+        }
+    };
+}
+
+#[test]
+fn test_projection() {
+    let projection = vec![10, 0];
+    let full = TripArrow::new(TripGenerator::new(0.01, 1, 1)).with_batch_size(1000);
+    let projected = TripArrow::new(TripGenerator::new(0.01, 1, 1))
+        .with_batch_size(1000)
+        .with_projection(projection.clone());
+    let schema = Table::Trip.schema().project(&projection).unwrap();
+    assert_eq!(projected.schema(), &Arc::new(schema));
+    let mut num_batches = 0;
+    for (full, projected) in full.zip(projected) {
+        assert_eq!(full.project(&projection).unwrap(), projected);
+        num_batches += 1;
+    }
+    assert_eq!(num_batches, 60);
+
+    let full = BuildingArrow::new(BuildingGenerator::new(1.0, 1, 1))
+        .next()
+        .unwrap();
+    let projected = BuildingArrow::new(BuildingGenerator::new(1.0, 1, 1))
+        .with_projection(vec![2])
+        .next()
+        .unwrap();
+    assert_eq!(full.project(&[2]).unwrap(), projected);
+
+    // the cell index columns follow the columns of the table
+    let indexes = vec!["h3:9".parse().unwrap(), "geohash:7".parse().unwrap()];
+    let full = TripArrow::new(TripGenerator::new(0.001, 1, 1))
+        .with_cell_indexes(indexes.clone())
+        .next()
+        .unwrap();
+    assert_eq!(full.schema().field(15).name(), "t_dropoffloc_geohash_7");
+    let projected = TripArrow::new(TripGenerator::new(0.001, 1, 1))
+        .with_cell_indexes(indexes)
+        .with_projection(vec![15, 12, 0])
+        .next()
+        .unwrap();
+    assert_eq!(full.project(&[15, 12, 0]).unwrap(), projected);
+
+    // the batches keep their row count without any columns
+    let batch = VehicleArrow::new(VehicleGenerator::new(1.0, 1, 1))
+        .with_projection(vec![])
+        .next()
+        .unwrap();
+    assert_eq!((batch.num_rows(), batch.num_columns()), (100, 0));
+}
+
+#[test]
+fn test_projection_pushdown() {
+    assert_columns_alone!(VehicleArrow::new(VehicleGenerator::new(0.01, 1, 1)));
+    assert_columns_alone!(DriverArrow::new(DriverGenerator::new(0.01, 1, 1)));
+    assert_columns_alone!(CustomerArrow::new(CustomerGenerator::new(0.01, 1, 1)));
+    // the roads move the locations, and so the distances and the fares
+    assert_columns_alone!(
+        TripArrow::new(TripGenerator::new(0.01, 1, 1).with_snap_to_roads(true))
+            .with_batch_size(100)
+    );
+    assert_columns_alone!(
+        TripArrow::new(TripGenerator::new(0.01, 1, 1).with_on_land(true)).with_batch_size(100)
+    );
+    assert_columns_alone!(
+        TrajectoryArrow::new(TrajectoryGenerator::new(0.01, 1, 1)).with_batch_size(100)
+    );
+    assert_columns_alone!(BuildingArrow::new(BuildingGenerator::new(0.01, 1, 1))
+        .with_invalid_rate(0.1)
+        .with_duplicate_rate(0.1));
+    assert_columns_alone!(RoadArrow::new(RoadGenerator::new(0.01, 1, 1)));
+    assert_columns_alone!(LandmassArrow::new(LandmassGenerator::new(0.01, 1, 1)));
+    assert_columns_alone!(CountryArrow::new(CountryGenerator::new(0.01, 1, 1)));
+    assert_columns_alone!(RegionArrow::new(RegionGenerator::new(0.01, 1, 1)));
+    assert_columns_alone!(CityArrow::new(CityGenerator::new(0.01, 1, 1)));
+    assert_columns_alone!(PoiArrow::new(PoiGenerator::new(0.01, 1, 1))
+        .with_cell_indexes(vec!["h3:9".parse().unwrap()]));
+    assert_columns_alone!(RasterArrow::new(RasterGenerator::new(0.01, 1, 1)).with_batch_size(100));
+    assert_columns_alone!(
+        PointCloudArrow::new(PointCloudGenerator::new(0.01, 1, 1)).with_batch_size(100)
+    );
+}
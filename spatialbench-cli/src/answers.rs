@@ -0,0 +1,196 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `answers` command: write the expected results of the queries
+
+use crate::generation::GenerationOptions;
+use arrow::array::{Array, AsArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Int64Type};
+use clap::Args;
+use geo::{Geometry, MultiPolygon};
+use geozero::wkb::Wkb;
+use geozero::ToGeo;
+use log::info;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use spatialbench::workload::{Answer, Dialect, JoinCardinality, Query, QueryParameters, Zones};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Write the expected results of the benchmark queries, to validate engines
+#[derive(Debug, Args)]
+#[command(mut_arg("scale_factor", |arg| arg.default_value("0.01")))]
+pub struct AnswersArgs {
+    /// Directory to write `qN.sql` and the expected results `qN.csv` to
+    #[arg(short, long, default_value = ".")]
+    output_dir: PathBuf,
+
+    /// Seed to derive the substitution parameters from, as the `--seed` of
+    /// `queries`
+    ///
+    /// If not specified, the parameters of the published queries are used.
+    #[arg(long)]
+    query_seed: Option<u64>,
+
+    /// SQL dialect of the written queries: sedona, postgis or duckdb
+    ///
+    /// The answers use planar geometry, so they do not apply to the
+    /// `GEOGRAPHY` queries of bigquery.
+    #[arg(short, long, default_value = "sedona")]
+    dialect: Dialect,
+
+    /// Which queries to answer, e.g. `Q1,Q3` (default: all without zones)
+    #[arg(short, long, value_delimiter = ',')]
+    queries: Option<Vec<Query>>,
+
+    /// Parquet file of the zone table generated with the data, to compute the
+    /// cardinalities of the joins with zones
+    #[arg(long)]
+    zones: Option<PathBuf>,
+
+    #[command(flatten)]
+    generation: GenerationOptions,
+}
+
+impl AnswersArgs {
+    /// Compute the answers and write them to the output directory
+    pub fn run(&self) -> io::Result<()> {
+        if self.dialect == Dialect::Bigquery {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Answers use planar geometry and do not apply to --dialect bigquery",
+            ));
+        }
+
+        self.generation.apply()?;
+        let dataset = self.generation.dataset("answers")?;
+
+        let zones = match &self.zones {
+            Some(path) => Some(read_zones(path)?),
+            None => None,
+        };
+        fs::create_dir_all(&self.output_dir)?;
+        let queries = self.queries.as_deref().unwrap_or(&Query::ALL);
+        let mut cardinalities = vec![];
+        for &query in queries {
+            let params = match self.query_seed {
+                Some(seed) => QueryParameters::generate(query, dataset.scale_factor, seed),
+                None => QueryParameters::reference(query),
+            };
+            let start = Instant::now();
+            let answer = Answer::compute(&params, dataset);
+            let cardinality = JoinCardinality::compute(&params, dataset, zones.as_ref());
+            if answer.is_none() && cardinality.is_none() {
+                // only warn when the query was requested explicitly
+                if self.queries.is_some() {
+                    eprintln!("Warning: {query} uses the downloaded zone table and has no answer");
+                }
+                continue;
+            }
+
+            let name = query.to_string().to_lowercase();
+            fs::write(
+                self.output_dir.join(format!("{name}.sql")),
+                params.sql(self.dialect),
+            )?;
+            if let Some(answer) = answer {
+                let path = self.output_dir.join(format!("{name}.csv"));
+                let mut out = BufWriter::new(File::create(&path)?);
+                answer.write_csv(&mut out)?;
+                out.flush()?;
+                info!(
+                    "Wrote {} rows to {} in {:?}",
+                    answer.rows().len(),
+                    path.display(),
+                    start.elapsed()
+                );
+            }
+            cardinalities.extend(cardinality);
+        }
+
+        if !cardinalities.is_empty() {
+            let path = self.output_dir.join("cardinalities.csv");
+            let mut out = BufWriter::new(File::create(&path)?);
+            writeln!(out, "query,join,pairs")?;
+            for cardinality in &cardinalities {
+                writeln!(
+                    out,
+                    "{},\"{}\",{}",
+                    cardinality.query, cardinality.join, cardinality.pairs
+                )?;
+            }
+            out.flush()?;
+            info!(
+                "Wrote the cardinalities of {} joins to {}",
+                cardinalities.len(),
+                path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Read the keys, names and boundaries of the zones of a zone table
+fn read_zones(path: &Path) -> io::Result<Zones> {
+    let invalid = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {message}", path.display()),
+        )
+    };
+    let file = File::open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to open {}: {e}", path.display())))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .map_err(|e| invalid(e.to_string()))?;
+    let mut zones = vec![];
+    for batch in reader {
+        let batch = batch.map_err(|e| invalid(e.to_string()))?;
+        let column = |name: &str, data_type: &DataType| {
+            let column = batch
+                .column_by_name(name)
+                .ok_or_else(|| invalid(format!("no {name} column")))?;
+            cast(column, data_type).map_err(|e| invalid(format!("{name}: {e}")))
+        };
+        let keys = column("z_zonekey", &DataType::Int64)?;
+        let names = column("z_name", &DataType::Utf8)?;
+        let boundaries = column("z_boundary", &DataType::Binary)?;
+        let (keys, names, boundaries) = (
+            keys.as_primitive::<Int64Type>(),
+            names.as_string::<i32>(),
+            boundaries.as_binary::<i32>(),
+        );
+        for i in 0..batch.num_rows() {
+            if boundaries.is_null(i) {
+                continue;
+            }
+            let boundary = match Wkb(boundaries.value(i)).to_geo() {
+                Ok(Geometry::Polygon(polygon)) => MultiPolygon::new(vec![polygon]),
+                Ok(Geometry::MultiPolygon(polygons)) => polygons,
+                // the zones are polygons, other geometries contain no trips
+                Ok(_) => continue,
+                Err(e) => return Err(invalid(format!("invalid z_boundary: {e}"))),
+            };
+            zones.push((keys.value(i), names.value(i).to_string(), boundary));
+        }
+    }
+    let zones = Zones::new(zones);
+    info!("Read {} zones from {}", zones.len(), path.display());
+    Ok(zones)
+}
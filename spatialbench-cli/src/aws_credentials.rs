@@ -0,0 +1,379 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Credentials of the profiles of the AWS shared config files
+//!
+//! The [`object_store`] S3 builder resolves credentials from the `AWS_*`
+//! environment variables, web identity tokens (EKS IRSA), ECS task roles, EKS
+//! Pod Identity and EC2 instance metadata, but not from the profiles of
+//! `~/.aws/config` and `~/.aws/credentials`. [`profile_credentials`] fills
+//! that gap, in the same place as the default chain of the AWS SDKs (after
+//! the environment variables):
+//!
+//! * static keys (`aws_access_key_id` and `aws_secret_access_key`)
+//! * `credential_process`
+//! * IAM Identity Center (SSO) and assumed role profiles, whose credentials
+//!   are obtained with `aws configure export-credentials`, so with the
+//!   session of `aws sso login`
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::debug;
+use object_store::aws::{AwsCredential, AwsCredentialProvider};
+use object_store::{CredentialProvider, StaticCredentialProvider};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Credentials are refreshed this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// The credentials and region of an AWS profile
+#[derive(Debug, Clone)]
+pub struct ProfileCredentials {
+    /// The name of the profile
+    pub profile: String,
+    /// Provides the credentials of the profile, if it has any
+    pub provider: Option<AwsCredentialProvider>,
+    /// The region of the profile
+    pub region: Option<String>,
+}
+
+/// Return the credentials and region of the AWS profile named by
+/// `AWS_PROFILE` (or the `default` profile)
+///
+/// Returns `None` if the shared config files have no such profile, and an
+/// error if `AWS_PROFILE` names a missing profile. The files are read once.
+pub fn profile_credentials() -> io::Result<Option<ProfileCredentials>> {
+    static CREDENTIALS: OnceLock<Result<Option<ProfileCredentials>, String>> = OnceLock::new();
+    CREDENTIALS
+        .get_or_init(|| {
+            let name = std::env::var("AWS_PROFILE").ok();
+            let read = |path: Option<PathBuf>| {
+                path.and_then(|path| std::fs::read_to_string(path).ok())
+                    .unwrap_or_default()
+            };
+            let config = read(shared_file("AWS_CONFIG_FILE", "config"));
+            let credentials = read(shared_file("AWS_SHARED_CREDENTIALS_FILE", "credentials"));
+            match load_profile(name.as_deref(), &config, &credentials) {
+                Some(profile) => Ok(Some(profile.into_credentials())),
+                None => match name {
+                    Some(name) => Err(format!("AWS profile {name} (AWS_PROFILE) not found")),
+                    None => Ok(None),
+                },
+            }
+        })
+        .clone()
+        .map_err(|message| io::Error::new(io::ErrorKind::InvalidInput, message))
+}
+
+/// Return the path of a shared config file, from the environment variable
+/// `env` or in `~/.aws`
+fn shared_file(env: &str, name: &str) -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(env) {
+        return Some(path.into());
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".aws").join(name))
+}
+
+/// Parse the sections of an INI file such as `~/.aws/config`
+fn parse_ini(text: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut sections: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    let mut section = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+            sections.entry(name.clone()).or_default();
+            section = Some(name);
+        } else if let (Some(section), Some((key, value))) = (&section, line.split_once('=')) {
+            sections
+                .get_mut(section)
+                .expect("section exists")
+                .insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+/// How the credentials of a profile are obtained
+#[derive(Debug, PartialEq)]
+enum Source {
+    /// Static keys
+    Static(AwsCredential),
+    /// The output of a command, in the `credential_process` format
+    Process(String),
+    /// No credentials, e.g. a profile only setting the region
+    None,
+}
+
+/// A profile of the shared config files
+#[derive(Debug, PartialEq)]
+struct Profile {
+    name: String,
+    source: Source,
+    region: Option<String>,
+}
+
+impl Profile {
+    fn into_credentials(self) -> ProfileCredentials {
+        let provider = match self.source {
+            Source::Static(credential) => {
+                debug!("Using the static credentials of AWS profile {}", self.name);
+                Some(Arc::new(StaticCredentialProvider::new(credential)) as AwsCredentialProvider)
+            }
+            Source::Process(command) => {
+                debug!("Using the credential process of AWS profile {}", self.name);
+                Some(Arc::new(ProcessCredentialProvider::new(command)) as AwsCredentialProvider)
+            }
+            Source::None => None,
+        };
+        ProfileCredentials {
+            profile: self.name,
+            provider,
+            region: self.region,
+        }
+    }
+}
+
+/// Find the profile `name` (or `default`) in the contents of the config and
+/// credentials files, the latter taking precedence
+fn load_profile(name: Option<&str>, config: &str, credentials: &str) -> Option<Profile> {
+    let name = name.unwrap_or("default");
+    let mut config = parse_ini(config);
+    let mut credentials = parse_ini(credentials);
+    // profiles are `[profile name]` in the config file, except the default
+    let config_section = config.remove(&format!("profile {name}")).or_else(|| {
+        (name == "default")
+            .then(|| config.remove("default"))
+            .flatten()
+    });
+    let credentials_section = credentials.remove(name);
+    if config_section.is_none() && credentials_section.is_none() {
+        return None;
+    }
+    let mut settings = config_section.unwrap_or_default();
+    settings.extend(credentials_section.unwrap_or_default());
+
+    let source = match (
+        settings.get("aws_access_key_id"),
+        settings.get("aws_secret_access_key"),
+    ) {
+        (Some(key_id), Some(secret_key)) => Source::Static(AwsCredential {
+            key_id: key_id.clone(),
+            secret_key: secret_key.clone(),
+            token: settings.get("aws_session_token").cloned(),
+        }),
+        _ => match settings.get("credential_process") {
+            Some(command) => Source::Process(command.clone()),
+            None if ["sso_session", "sso_start_url", "role_arn"]
+                .iter()
+                .any(|key| settings.contains_key(*key)) =>
+            {
+                Source::Process(format!(
+                    "aws configure export-credentials --profile {name} --format process"
+                ))
+            }
+            None => Source::None,
+        },
+    };
+    Some(Profile {
+        name: name.to_string(),
+        source,
+        region: settings.get("region").cloned(),
+    })
+}
+
+/// The output of a `credential_process`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ProcessOutput {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    expiration: Option<DateTime<Utc>>,
+}
+
+/// Provides the credentials printed by a command, refreshing them when they
+/// are about to expire
+#[derive(Debug)]
+struct ProcessCredentialProvider {
+    command: String,
+    /// The last credentials, and when they expire
+    cache: Mutex<Option<CachedCredential>>,
+}
+
+type CachedCredential = (Arc<AwsCredential>, Option<DateTime<Utc>>);
+
+impl ProcessCredentialProvider {
+    fn new(command: String) -> Self {
+        Self {
+            command,
+            cache: Mutex::new(None),
+        }
+    }
+
+    async fn run(&self) -> io::Result<ProcessOutput> {
+        let error = |message: String| {
+            io::Error::other(format!("Credential process {:?} {message}", self.command))
+        };
+        let mut command = if cfg!(windows) {
+            let mut command = tokio::process::Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = tokio::process::Command::new("sh");
+            command.arg("-c");
+            command
+        };
+        let output = command
+            .arg(&self.command)
+            .output()
+            .await
+            .map_err(|e| error(format!("failed: {e}")))?;
+        if !output.status.success() {
+            return Err(error(format!(
+                "failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        serde_json::from_slice(&output.stdout)
+            .map_err(|e| error(format!("printed invalid credentials: {e}")))
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for ProcessCredentialProvider {
+    type Credential = AwsCredential;
+
+    async fn get_credential(&self) -> object_store::Result<Arc<AwsCredential>> {
+        let mut cache = self.cache.lock().await;
+        if let Some((credential, expiration)) = cache.as_ref() {
+            let fresh = expiration.is_none_or(|expiration| {
+                expiration - Utc::now() > chrono::Duration::from_std(EXPIRY_MARGIN).unwrap()
+            });
+            if fresh {
+                return Ok(Arc::clone(credential));
+            }
+        }
+        let output = self.run().await.map_err(|e| object_store::Error::Generic {
+            store: "S3",
+            source: e.into(),
+        })?;
+        let credential = Arc::new(AwsCredential {
+            key_id: output.access_key_id,
+            secret_key: output.secret_access_key,
+            token: output.session_token,
+        });
+        *cache = Some((Arc::clone(&credential), output.expiration));
+        Ok(credential)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "
+[default]
+region = us-west-2
+
+[profile sso]
+sso_session = corp
+sso_account_id = 111122223333
+sso_role_name = Generate
+
+[sso-session corp]
+sso_region = us-east-1
+
+[profile process]
+credential_process = /usr/local/bin/credentials --role generate
+region = eu-central-1
+";
+
+    const CREDENTIALS: &str = "
+# comment
+[default]
+aws_access_key_id = AKIDEXAMPLE
+aws_secret_access_key = secret
+";
+
+    #[test]
+    fn test_load_profile() {
+        assert_eq!(
+            load_profile(None, CONFIG, CREDENTIALS).unwrap(),
+            Profile {
+                name: "default".to_string(),
+                source: Source::Static(AwsCredential {
+                    key_id: "AKIDEXAMPLE".to_string(),
+                    secret_key: "secret".to_string(),
+                    token: None,
+                }),
+                region: Some("us-west-2".to_string()),
+            }
+        );
+        assert_eq!(
+            load_profile(Some("process"), CONFIG, CREDENTIALS).unwrap(),
+            Profile {
+                name: "process".to_string(),
+                source: Source::Process("/usr/local/bin/credentials --role generate".to_string()),
+                region: Some("eu-central-1".to_string()),
+            }
+        );
+        assert_eq!(
+            load_profile(Some("sso"), CONFIG, CREDENTIALS)
+                .unwrap()
+                .source,
+            Source::Process(
+                "aws configure export-credentials --profile sso --format process".to_string()
+            )
+        );
+        assert_eq!(load_profile(Some("missing"), CONFIG, CREDENTIALS), None);
+        assert_eq!(
+            load_profile(None, "[default]\nregion = us-east-1", "")
+                .unwrap()
+                .source,
+            Source::None
+        );
+        assert_eq!(load_profile(None, "", ""), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_credentials() {
+        let provider = ProcessCredentialProvider::new(
+            r#"echo '{"Version": 1, "AccessKeyId": "AKID", "SecretAccessKey": "secret", "SessionToken": "token", "Expiration": "2999-01-01T00:00:00Z"}'"#
+                .to_string(),
+        );
+        let credential = provider.get_credential().await.unwrap();
+        assert_eq!(credential.key_id, "AKID");
+        assert_eq!(credential.token.as_deref(), Some("token"));
+
+        let provider = ProcessCredentialProvider::new("echo expired >&2; exit 1".to_string());
+        let error = provider.get_credential().await.unwrap_err().to_string();
+        assert!(error.contains("expired"), "{error}");
+    }
+}
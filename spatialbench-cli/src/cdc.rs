@@ -0,0 +1,134 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `cdc` command: write the trips as a change data capture stream
+
+use crate::geojson::write_json_lines;
+use crate::throttle::{self, Throttled};
+use clap::{Args, ValueEnum};
+use log::info;
+use parquet::arrow::ArrowWriter;
+use spatialbench::generators::TripGenerator;
+use spatialbench_arrow::cdc::TripCdcArrow;
+use spatialbench_arrow::RecordBatchIterator;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Write the trips as an ordered stream of insert, update and delete
+/// events, to benchmark the ingestion of table formats such as Iceberg,
+/// Delta Lake and Hudi
+#[derive(Debug, Args)]
+pub struct CdcArgs {
+    /// Scale factor of the trips
+    #[arg(short, long, default_value_t = 1.)]
+    scale_factor: f64,
+
+    /// Seed of the trips
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Number of trips inserted by each commit
+    #[arg(long, default_value_t = 10_000)]
+    commit_size: usize,
+
+    /// Write at most this many events per second, e.g. `5000`
+    ///
+    /// The events of each operation of a commit are written at once and
+    /// flushed, so a consumer tailing the jsonl file sees a steady stream
+    /// of commits.
+    #[arg(long)]
+    throttle: Option<f64>,
+
+    /// Directory to write `trip.cdc.parquet` or `trip.cdc.jsonl` to
+    #[arg(short, long, default_value = ".")]
+    output_dir: PathBuf,
+
+    /// Output format: Parquet, or JSON objects, one per line, with the
+    /// geometries as GeoJSON
+    #[arg(short, long, default_value = "parquet")]
+    format: CdcFormat,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum CdcFormat {
+    Parquet,
+    Jsonl,
+}
+
+impl CdcArgs {
+    /// Write the stream to the output directory
+    pub fn run(&self) -> io::Result<()> {
+        if !(self.scale_factor.is_finite() && self.scale_factor > 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--scale-factor must be positive, got {}", self.scale_factor),
+            ));
+        }
+        if self.commit_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--commit-size must be positive",
+            ));
+        }
+        if let Some(events_per_sec) = self.throttle {
+            if !(events_per_sec.is_finite() && events_per_sec > 0.0) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("--throttle must be positive, got {events_per_sec}"),
+                ));
+            }
+            info!("Writing at most {events_per_sec} events per second");
+            throttle::set_throttle(events_per_sec);
+        }
+        fs::create_dir_all(&self.output_dir)?;
+        let generator = TripGenerator::new(self.scale_factor, 1, 1).with_seed(self.seed);
+        let events =
+            Throttled::new(TripCdcArrow::new(generator).with_commit_size(self.commit_size));
+        let mut count = 0;
+        let path = match self.format {
+            CdcFormat::Parquet => {
+                let path = self.output_dir.join("trip.cdc.parquet");
+                let schema = Arc::clone(events.schema());
+                let mut writer = ArrowWriter::try_new(File::create(&path)?, schema, None)?;
+                for batch in events {
+                    count += batch.num_rows();
+                    writer.write(&batch)?;
+                }
+                writer.close()?;
+                path
+            }
+            CdcFormat::Jsonl => {
+                let path = self.output_dir.join("trip.cdc.jsonl");
+                let mut out = BufWriter::new(File::create(&path)?);
+                let mut buffer = vec![];
+                for batch in events {
+                    count += batch.num_rows();
+                    buffer.clear();
+                    write_json_lines(&batch, &mut buffer);
+                    out.write_all(&buffer)?;
+                    out.flush()?;
+                }
+                out.flush()?;
+                path
+            }
+        };
+        info!("Wrote {count} events to {}", path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! * [`Checkpoint`]: records how much of an in progress file has been written
+//! * [`CheckpointSink`]: a [`Sink`] that records a [`Checkpoint`] after each chunk
+//!
+//! Output files are first written to a temporary `.inprogress` file and
+//! renamed when complete, so completed files are never regenerated. For
+//! `tbl`, `csv` and `geojsonl` output with `--resume`, a `.checkpoint` file
+//! next to the in progress file also records which chunks have been written
+//! so that generation of a large file can be resumed part way through
+//! instead of starting over.
+
+use crate::generate::Sink;
+use crate::statistics::WriteStatistics;
+use log::{debug, info};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Progress writing an in progress output file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Description of what is being written, used to detect that the file
+    /// was started with different arguments
+    plan: String,
+    /// Number of buffers (the header and then one per chunk) written
+    buffers: usize,
+    /// Number of bytes of the in progress file holding those buffers
+    bytes: u64,
+}
+
+impl Checkpoint {
+    fn new(plan: impl Into<String>) -> Self {
+        Self {
+            plan: plan.into(),
+            buffers: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Return the path of the checkpoint for the in progress file `temp_path`
+    pub fn path(temp_path: &Path) -> PathBuf {
+        temp_path.with_extension("checkpoint")
+    }
+
+    /// Remove the checkpoint for `temp_path`, if any
+    pub fn remove(temp_path: &Path) -> io::Result<()> {
+        match std::fs::remove_file(Self::path(temp_path)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Read the checkpoint at `path`, returning None if it does not exist or
+    /// can not be parsed
+    fn read(path: &Path) -> Option<Self> {
+        let text = std::fs::read_to_string(path).ok()?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut plan = None;
+        let mut buffers = None;
+        let mut bytes = None;
+        for line in text.lines() {
+            match line.split_once('=')? {
+                ("plan", v) => plan = Some(v.to_string()),
+                ("buffers", v) => buffers = Some(v.parse().ok()?),
+                ("bytes", v) => bytes = Some(v.parse().ok()?),
+                _ => return None,
+            }
+        }
+        Some(Self {
+            plan: plan?,
+            buffers: buffers?,
+            bytes: bytes?,
+        })
+    }
+
+    /// Atomically write the checkpoint to `path`
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let temp_path = path.with_extension("checkpoint.tmp");
+        let text = format!(
+            "plan={}\nbuffers={}\nbytes={}\n",
+            self.plan, self.buffers, self.bytes
+        );
+        std::fs::write(&temp_path, text)?;
+        std::fs::rename(&temp_path, path)
+    }
+}
+
+/// A [`Sink`] that writes to an in progress file, recording a [`Checkpoint`]
+/// after each buffer is durably written
+pub struct CheckpointSink {
+    file: File,
+    checkpoint_path: PathBuf,
+    checkpoint: Checkpoint,
+    /// Number of buffers to discard rather than write (the header, when
+    /// resuming a file that already has one)
+    skip_buffers: usize,
+    statistics: WriteStatistics,
+}
+
+impl CheckpointSink {
+    /// Create a sink that writes `plan` to the in progress file `temp_path`
+    ///
+    /// If `resume` is true and there is a checkpoint for the same `plan`,
+    /// the existing in progress file is truncated to the last checkpoint and
+    /// appended to. Otherwise a new file is created.
+    ///
+    /// Returns the sink and the number of chunks already written, which the
+    /// caller should skip generating.
+    pub fn try_new(temp_path: &Path, plan: &str, resume: bool) -> io::Result<(Self, usize)> {
+        let checkpoint_path = Checkpoint::path(temp_path);
+        if resume {
+            if let Some((sink, chunks)) = Self::try_resume(temp_path, &checkpoint_path, plan)? {
+                return Ok((sink, chunks));
+            }
+        }
+
+        let file = File::create(temp_path)
+            .map_err(|err| io::Error::other(format!("Failed to create {temp_path:?}: {err}")))?;
+        let sink = Self {
+            file,
+            checkpoint_path,
+            checkpoint: Checkpoint::new(plan),
+            skip_buffers: 0,
+            statistics: WriteStatistics::new("buffers"),
+        };
+        Ok((sink, 0))
+    }
+
+    /// Resume writing `temp_path` from its checkpoint, if it has a usable one
+    fn try_resume(
+        temp_path: &Path,
+        checkpoint_path: &Path,
+        plan: &str,
+    ) -> io::Result<Option<(Self, usize)>> {
+        let Some(checkpoint) = Checkpoint::read(checkpoint_path) else {
+            return Ok(None);
+        };
+        if checkpoint.plan != plan || checkpoint.buffers == 0 {
+            debug!("Ignoring checkpoint {checkpoint_path:?} for {plan}: {checkpoint:?}");
+            return Ok(None);
+        }
+        let Ok(mut file) = OpenOptions::new().write(true).open(temp_path) else {
+            return Ok(None);
+        };
+        if file.metadata()?.len() < checkpoint.bytes {
+            debug!("{temp_path:?} is shorter than its checkpoint, starting over");
+            return Ok(None);
+        }
+
+        // discard anything written after the checkpoint
+        file.set_len(checkpoint.bytes)?;
+        file.seek(SeekFrom::End(0))?;
+        let chunks = checkpoint.buffers - 1;
+        info!(
+            "Resuming {temp_path:?} after {chunks} chunks ({} bytes)",
+            checkpoint.bytes
+        );
+        let sink = Self {
+            file,
+            checkpoint_path: checkpoint_path.to_path_buf(),
+            checkpoint,
+            // the header was already written
+            skip_buffers: 1,
+            statistics: WriteStatistics::new("buffers"),
+        };
+        Ok(Some((sink, chunks)))
+    }
+}
+
+impl Sink for CheckpointSink {
+    fn sink(&mut self, buffer: &[u8]) -> Result<(), io::Error> {
+        if self.skip_buffers > 0 {
+            self.skip_buffers -= 1;
+            return Ok(());
+        }
+        self.statistics.increment_chunks(1);
+        self.statistics.increment_bytes(buffer.len());
+        self.file.write_all(buffer)?;
+        // ensure the data is durable before recording it in the checkpoint
+        self.file.sync_data()?;
+        self.checkpoint.buffers += 1;
+        self.checkpoint.bytes += buffer.len() as u64;
+        self.checkpoint.write(&self.checkpoint_path)
+    }
+
+    fn flush(mut self) -> Result<(), io::Error> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::generate_in_chunks;
+    use crate::tbl::VehicleTblSource;
+    use spatialbench::generators::VehicleGenerator;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let checkpoint = Checkpoint {
+            plan: "table trip (SF=1, 10 chunks) to trip.tbl".to_string(),
+            buffers: 4,
+            bytes: 1234,
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trip.checkpoint");
+        checkpoint.write(&path).unwrap();
+        assert_eq!(Checkpoint::read(&path), Some(checkpoint));
+
+        assert_eq!(Checkpoint::parse("plan=x\nbuffers=1\n"), None);
+        assert_eq!(Checkpoint::parse("plan=x\nbuffers=one\nbytes=1\n"), None);
+    }
+
+    /// Writes the first `take` of `num_chunks` vehicle chunks to `temp_path`,
+    /// resuming if requested
+    async fn write_chunks(temp_path: &Path, num_chunks: i32, take: usize, resume: bool) {
+        let (sink, skip) = CheckpointSink::try_new(temp_path, "vehicle", resume).unwrap();
+        let sources = (1..=num_chunks)
+            .map(move |part| VehicleGenerator::new(0.1, part, num_chunks))
+            .map(VehicleTblSource::new)
+            .skip(skip)
+            .take(take - skip);
+        generate_in_chunks(sink, sources, 2).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let expected_path = dir.path().join("expected.inprogress");
+        write_chunks(&expected_path, 5, 5, false).await;
+
+        // write only some chunks, followed by garbage from a partial write
+        let temp_path = dir.path().join("vehicle.inprogress");
+        write_chunks(&temp_path, 5, 2, false).await;
+        let mut file = OpenOptions::new().append(true).open(&temp_path).unwrap();
+        file.write_all(b"partial row|").unwrap();
+        drop(file);
+
+        // resuming writes only the remaining chunks
+        write_chunks(&temp_path, 5, 5, true).await;
+        let checkpoint = Checkpoint::read(&Checkpoint::path(&temp_path)).unwrap();
+        assert_eq!(checkpoint.buffers, 6);
+        assert_eq!(
+            std::fs::read(&temp_path).unwrap(),
+            std::fs::read(&expected_path).unwrap()
+        );
+
+        // without resume, the file is started over
+        write_chunks(&temp_path, 5, 1, false).await;
+        let checkpoint = Checkpoint::read(&Checkpoint::path(&temp_path)).unwrap();
+        assert_eq!(checkpoint.buffers, 2);
+        assert_eq!(
+            std::fs::metadata(&temp_path).unwrap().len(),
+            checkpoint.bytes
+        );
+    }
+}
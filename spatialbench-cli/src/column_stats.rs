@@ -0,0 +1,583 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Statistics of the generated columns (`--stats`)
+//!
+//! [`Profiled`] iterators record, for each column of the batches they
+//! generate, the number of nulls, the minimum and maximum, an estimate of
+//! the number of distinct values and, for the geometry columns, the
+//! bounding box and mean number of vertices. Once the tables are written,
+//! [`TableStats`] are written to `_stats/{table}.json` in the output
+//! directory, a data dictionary with the types of the columns and the
+//! ground truth statistics that query optimizers would otherwise compute
+//! with a full scan.
+
+use crate::geoparquet::{is_wkb_type, wkb_bbox_vertices, wkb_values};
+use crate::manifest::object_url;
+use crate::object_store_writer::{ObjectStoreWriter, UploadOptions};
+use crate::parquet::IntoSize;
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch};
+use arrow::compute::cast;
+use arrow::datatypes::{
+    DataType, Date32Type, Decimal128Type, Float32Type, Float64Type, Int16Type, Int32Type,
+    Int64Type, Int8Type, SchemaRef, TimeUnit, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use chrono::{DateTime, NaiveDate};
+use serde::Serialize;
+use serde_json::Value;
+use spatialbench::spatial::utils::spider_seed_for_index;
+use spatialbench_arrow::{RecordBatchIterator, Table};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use url::Url;
+
+/// Directory of the statistics in the output directory
+pub const STATS_DIR: &str = "_stats";
+
+/// Statistics of the columns of a generated table
+#[derive(Debug, Serialize)]
+pub struct TableStats {
+    table: String,
+    rows: u64,
+    columns: Vec<ColumnStats>,
+}
+
+/// Statistics of a column of [`TableStats`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnStats {
+    name: String,
+    /// The Arrow type, e.g. `Int64` or `Binary` for the WKB geometries
+    #[serde(rename = "type")]
+    data_type: String,
+    nullable: bool,
+    nulls: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<Value>,
+    /// Estimate of the number of distinct non null values
+    ndv: u64,
+    /// `[xmin, ymin, xmax, ymax]` of the geometries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bbox: Option<[f64; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mean_vertices: Option<f64>,
+    #[serde(skip)]
+    arrow_type: DataType,
+    #[serde(skip)]
+    summary: Summary,
+}
+
+impl TableStats {
+    /// Return the statistics of `table` recorded by [`Profiled`] iterators,
+    /// if any of its batches were recorded
+    pub fn of(table: Table) -> Option<Self> {
+        let stats = STATS.get()?.lock().unwrap();
+        let recorded = stats.get(&table)?;
+        let columns = recorded
+            .columns
+            .iter()
+            .map(|column| {
+                let mut column = column.clone();
+                column.finish(recorded.rows);
+                column
+            })
+            .collect();
+        Some(Self {
+            table: table.to_string(),
+            rows: recorded.rows,
+            columns,
+        })
+    }
+
+    /// Return the path of the statistics, relative to the output directory
+    fn path(&self) -> String {
+        format!("{STATS_DIR}/{}.json", self.table)
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("statistics are serializable") + "\n"
+    }
+
+    /// Write the statistics to `_stats/{table}.json` in `output_dir`
+    pub fn write(&self, output_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(output_dir.join(STATS_DIR))?;
+        fs::write(output_dir.join(self.path()), self.to_json())
+    }
+
+    /// Upload the statistics to `_stats/{table}.json` below `url`
+    pub async fn upload(&self, url: &Url, options: UploadOptions) -> io::Result<()> {
+        let url = object_url(url, &self.path())?;
+        let json = self.to_json();
+        tokio::task::spawn_blocking(move || {
+            let mut writer = ObjectStoreWriter::try_new(&url, options)?;
+            writer.write_all(json.as_bytes())?;
+            writer.into_size().map(|_| ())
+        })
+        .await
+        .map_err(|e| io::Error::other(format!("Task Panic: {e}")))?
+    }
+}
+
+impl ColumnStats {
+    fn new(name: &str, data_type: &DataType, nullable: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable,
+            nulls: 0,
+            min: None,
+            max: None,
+            ndv: 0,
+            bbox: None,
+            mean_vertices: None,
+            arrow_type: data_type.clone(),
+            summary: Summary::default(),
+        }
+    }
+
+    /// Return empty statistics of the column of `self`
+    fn empty(&self) -> Self {
+        Self::new(&self.name, &self.arrow_type, self.nullable)
+    }
+
+    /// Add the statistics of the same column of other batches
+    fn merge(&mut self, other: ColumnStats) {
+        self.nulls += other.nulls;
+        let (summary, other) = (&mut self.summary, other.summary);
+        summary.sketch.merge(&other.sketch);
+        summary.range = match (summary.range.take(), other.range) {
+            (Some(Range::Int(a, b)), Some(Range::Int(c, d))) => {
+                Some(Range::Int(a.min(c), b.max(d)))
+            }
+            (Some(Range::Float(a, b)), Some(Range::Float(c, d))) => {
+                Some(Range::Float(a.min(c), b.max(d)))
+            }
+            (Some(Range::Text(a, b)), Some(Range::Text(c, d))) => {
+                Some(Range::Text(a.min(c), b.max(d)))
+            }
+            (range, None) | (None, range) => range,
+            (range, _) => range,
+        };
+        summary.bbox = match (summary.bbox, other.bbox) {
+            (Some(a), Some(b)) => Some(merge_bbox(a, b)),
+            (bbox, None) | (None, bbox) => bbox,
+        };
+        summary.geometries += other.geometries;
+        summary.vertices += other.vertices;
+    }
+
+    /// Add the values of `array`
+    fn update(&mut self, array: &ArrayRef) {
+        self.nulls += array.null_count() as u64;
+        let summary = &mut self.summary;
+        match array.data_type() {
+            data_type if is_wkb_type(data_type) => {
+                for wkb in wkb_values(array).flatten() {
+                    summary.sketch.insert(hash_bytes(wkb));
+                    let Some((bbox, vertices)) = wkb_bbox_vertices(wkb) else {
+                        continue;
+                    };
+                    summary.geometries += 1;
+                    summary.vertices += vertices as u64;
+                    if let Some(bbox) = bbox {
+                        summary.bbox = Some(match summary.bbox {
+                            Some(merged) => merge_bbox(merged, bbox),
+                            None => bbox,
+                        });
+                    }
+                }
+            }
+            DataType::Int8 => update_ints(summary, array.as_primitive::<Int8Type>().iter()),
+            DataType::Int16 => update_ints(summary, array.as_primitive::<Int16Type>().iter()),
+            DataType::Int32 => update_ints(summary, array.as_primitive::<Int32Type>().iter()),
+            DataType::Int64 => update_ints(summary, array.as_primitive::<Int64Type>().iter()),
+            DataType::UInt8 => update_ints(summary, array.as_primitive::<UInt8Type>().iter()),
+            DataType::UInt16 => update_ints(summary, array.as_primitive::<UInt16Type>().iter()),
+            DataType::UInt32 => update_ints(summary, array.as_primitive::<UInt32Type>().iter()),
+            DataType::UInt64 => update_ints(
+                summary,
+                array
+                    .as_primitive::<UInt64Type>()
+                    .iter()
+                    .map(|v| v.map(|v| v as i64)),
+            ),
+            DataType::Date32 => update_ints(summary, array.as_primitive::<Date32Type>().iter()),
+            DataType::Timestamp(_, _) => {
+                let values = cast(array, &DataType::Int64).expect("timestamps are integers");
+                update_ints(summary, values.as_primitive::<Int64Type>().iter())
+            }
+            DataType::Boolean => {
+                update_ints(summary, array.as_boolean().iter().map(|v| v.map(i64::from)))
+            }
+            DataType::Float32 => update_floats(
+                summary,
+                array
+                    .as_primitive::<Float32Type>()
+                    .iter()
+                    .map(|v| v.map(f64::from)),
+            ),
+            DataType::Float64 => update_floats(summary, array.as_primitive::<Float64Type>().iter()),
+            DataType::Decimal128(_, scale) => {
+                let divisor = 10f64.powi(*scale as i32);
+                update_floats(
+                    summary,
+                    array
+                        .as_primitive::<Decimal128Type>()
+                        .iter()
+                        .map(|v| v.map(|v| v as f64 / divisor)),
+                )
+            }
+            DataType::Utf8 => update_strings(summary, array.as_string::<i32>().iter()),
+            DataType::LargeUtf8 => update_strings(summary, array.as_string::<i64>().iter()),
+            DataType::Utf8View => update_strings(summary, array.as_string_view().iter()),
+            // e.g. the lists of the raster values: only the nulls
+            _ => {}
+        }
+    }
+
+    /// Fill in the statistics from the summary of the values of the `rows`
+    /// rows
+    fn finish(&mut self, rows: u64) {
+        let data_type = &self.arrow_type;
+        let summary = &self.summary;
+        self.ndv = summary.sketch.estimate().min(rows - self.nulls);
+        (self.min, self.max) = match &summary.range {
+            Some(Range::Int(min, max)) => (
+                Some(int_value(*min, data_type)),
+                Some(int_value(*max, data_type)),
+            ),
+            Some(Range::Float(min, max)) => (Some(Value::from(*min)), Some(Value::from(*max))),
+            Some(Range::Text(min, max)) => (
+                Some(Value::from(min.as_str())),
+                Some(Value::from(max.as_str())),
+            ),
+            None => (None, None),
+        };
+        self.bbox = summary.bbox;
+        self.mean_vertices =
+            (summary.geometries > 0).then(|| summary.vertices as f64 / summary.geometries as f64);
+    }
+}
+
+/// Return the bounding box covering `a` and `b`
+fn merge_bbox(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    [
+        a[0].min(b[0]),
+        a[1].min(b[1]),
+        a[2].max(b[2]),
+        a[3].max(b[3]),
+    ]
+}
+
+/// Return the JSON value of `value`, the integer representation of a value
+/// of type `data_type`: the timestamps and dates as ISO 8601 strings
+fn int_value(value: i64, data_type: &DataType) -> Value {
+    let timestamp = match data_type {
+        DataType::Timestamp(TimeUnit::Second, _) => DateTime::from_timestamp(value, 0),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => DateTime::from_timestamp_millis(value),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => DateTime::from_timestamp_micros(value),
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => Some(DateTime::from_timestamp_nanos(value)),
+        DataType::Date32 => {
+            let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date");
+            return epoch
+                .checked_add_signed(chrono::Duration::days(value))
+                .map(|date| Value::from(date.to_string()))
+                .unwrap_or(Value::from(value));
+        }
+        DataType::Boolean => return Value::from(value != 0),
+        _ => return Value::from(value),
+    };
+    match timestamp {
+        Some(timestamp) => Value::from(
+            timestamp
+                .naive_utc()
+                .format("%Y-%m-%dT%H:%M:%S%.f")
+                .to_string(),
+        ),
+        None => Value::from(value),
+    }
+}
+
+/// The summary of the non null values of a column, merged across batches
+#[derive(Debug, Clone, Default)]
+struct Summary {
+    range: Option<Range>,
+    sketch: Sketch,
+    bbox: Option<[f64; 4]>,
+    geometries: u64,
+    vertices: u64,
+}
+
+/// The minimum and maximum of the values of a column
+#[derive(Debug, Clone)]
+enum Range {
+    Int(i64, i64),
+    Float(f64, f64),
+    Text(String, String),
+}
+
+fn update_ints<T: Into<i64>>(summary: &mut Summary, values: impl Iterator<Item = Option<T>>) {
+    for value in values.flatten() {
+        let value = value.into();
+        summary
+            .sketch
+            .insert(spider_seed_for_index(value as u64, 0));
+        summary.range = Some(match summary.range.take() {
+            Some(Range::Int(min, max)) => Range::Int(min.min(value), max.max(value)),
+            _ => Range::Int(value, value),
+        });
+    }
+}
+
+fn update_floats(summary: &mut Summary, values: impl Iterator<Item = Option<f64>>) {
+    for value in values.flatten() {
+        // the same hash for 0 and -0
+        summary
+            .sketch
+            .insert(spider_seed_for_index((value + 0.0).to_bits(), 0));
+        if value.is_nan() {
+            continue;
+        }
+        summary.range = Some(match summary.range.take() {
+            Some(Range::Float(min, max)) => Range::Float(min.min(value), max.max(value)),
+            _ => Range::Float(value, value),
+        });
+    }
+}
+
+fn update_strings<'a>(summary: &mut Summary, values: impl Iterator<Item = Option<&'a str>>) {
+    for value in values.flatten() {
+        summary.sketch.insert(hash_bytes(value.as_bytes()));
+        match &mut summary.range {
+            Some(Range::Text(min, max)) => {
+                if value < min.as_str() {
+                    *min = value.to_string();
+                } else if value > max.as_str() {
+                    *max = value.to_string();
+                }
+            }
+            range => *range = Some(Range::Text(value.to_string(), value.to_string())),
+        }
+    }
+}
+
+/// A HyperLogLog sketch of the hashes of the values of a column, which
+/// estimates their number of distinct values within about 1%
+#[derive(Debug, Clone)]
+struct Sketch {
+    registers: Vec<u8>,
+}
+
+/// The number of bits of the hashes indexing the registers of a [`Sketch`]
+const SKETCH_BITS: u32 = 14;
+
+impl Default for Sketch {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; 1 << SKETCH_BITS],
+        }
+    }
+}
+
+impl Sketch {
+    fn insert(&mut self, hash: u64) {
+        let index = (hash >> (64 - SKETCH_BITS)) as usize;
+        let rank = ((hash << SKETCH_BITS) | (1 << (SKETCH_BITS - 1))).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    fn merge(&mut self, other: &Sketch) {
+        for (register, &other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(other);
+        }
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        // linear counting for the small cardinalities
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// Hash `bytes` with FNV-1a, mixed
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let hash = bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+    });
+    spider_seed_for_index(hash, 0)
+}
+
+/// The statistics of the columns of a table recorded so far
+struct Recorded {
+    rows: u64,
+    columns: Vec<ColumnStats>,
+}
+
+/// Statistics of the tables written so far
+static STATS: OnceLock<Mutex<BTreeMap<Table, Recorded>>> = OnceLock::new();
+
+/// Record the statistics of the columns of the batches of [`Profiled`]
+/// iterators, for [`TableStats::of`]
+pub fn record_stats() {
+    let _ = STATS.set(Mutex::new(BTreeMap::new()));
+}
+
+/// A [`RecordBatchIterator`] that records the statistics of the columns of
+/// the batches of the inner iterator, if enabled with [`record_stats`]
+pub struct Profiled<I> {
+    inner: I,
+    /// The table of the batches, found from the names of their columns
+    table: Option<Table>,
+}
+
+impl<I: RecordBatchIterator> Profiled<I> {
+    /// Record the statistics of the batches of `inner`
+    pub fn new(inner: I) -> Self {
+        let table = STATS.get().and_then(|_| table_of(inner.schema()));
+        Self { inner, table }
+    }
+}
+
+/// Return the generated table with the columns of `schema`, the columns of
+/// each table having their own prefix
+fn table_of(schema: &SchemaRef) -> Option<Table> {
+    let column = schema.fields().first()?.name();
+    Table::ALL
+        .into_iter()
+        .find(|table| table.schema().index_of(column).is_ok())
+}
+
+impl<I: RecordBatchIterator> Iterator for Profiled<I> {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = self.inner.next()?;
+        if let (Some(stats), Some(table)) = (STATS.get(), self.table) {
+            // summarize the batch before taking the lock, to not block the
+            // other threads
+            let schema = batch.schema();
+            let columns: Vec<ColumnStats> = schema
+                .fields()
+                .iter()
+                .zip(batch.columns())
+                .map(|(field, array)| {
+                    let mut column =
+                        ColumnStats::new(field.name(), field.data_type(), field.is_nullable());
+                    column.update(array);
+                    column
+                })
+                .collect();
+            let mut stats = stats.lock().unwrap();
+            let recorded = stats.entry(table).or_insert_with(|| Recorded {
+                rows: 0,
+                columns: columns.iter().map(ColumnStats::empty).collect(),
+            });
+            recorded.rows += batch.num_rows() as u64;
+            for (recorded, column) in recorded.columns.iter_mut().zip(columns) {
+                recorded.merge(column);
+            }
+        }
+        Some(batch)
+    }
+}
+
+impl<I: RecordBatchIterator> RecordBatchIterator for Profiled<I> {
+    fn schema(&self) -> &SchemaRef {
+        self.inner.schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spatialbench::generators::TripGenerator;
+    use spatialbench_arrow::TripArrow;
+
+    #[test]
+    fn test_sketch() {
+        for count in [0u64, 10, 1_000, 100_000] {
+            let mut sketch = Sketch::default();
+            let mut other = Sketch::default();
+            for value in 0..count {
+                sketch.insert(spider_seed_for_index(value, 0));
+                // the same values again, merged
+                other.insert(spider_seed_for_index(value, 0));
+            }
+            sketch.merge(&other);
+            let estimate = sketch.estimate() as f64;
+            assert!(
+                (estimate - count as f64).abs() <= 0.05 * count as f64,
+                "{count}: {estimate}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_column_stats() {
+        let schema = Table::Trip.schema();
+        let mut columns: Vec<_> = schema
+            .fields()
+            .iter()
+            .map(|f| ColumnStats::new(f.name(), f.data_type(), f.is_nullable()))
+            .collect();
+        // two batches merged
+        for batch in TripArrow::new(TripGenerator::new(0.001, 1, 1)).with_batch_size(4000) {
+            for (stats, array) in columns.iter_mut().zip(batch.columns()) {
+                let mut batch_stats = stats.empty();
+                batch_stats.update(array);
+                stats.merge(batch_stats);
+            }
+        }
+        for column in &mut columns {
+            column.finish(6000);
+        }
+        let tripkey = &columns[0];
+        assert_eq!(
+            (tripkey.min.clone(), tripkey.max.clone()),
+            (Some(1.into()), Some(6000.into()))
+        );
+        assert!((5900..6100).contains(&tripkey.ndv), "{}", tripkey.ndv);
+        assert_eq!(tripkey.nulls, 0);
+        let pickuptime = &columns[4];
+        assert!(pickuptime
+            .min
+            .as_ref()
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .starts_with("199"));
+        let fare = &columns[6];
+        assert!(fare.min.as_ref().unwrap().as_f64().unwrap() >= 0.0);
+        let pickuploc = &columns[10];
+        assert_eq!(pickuploc.mean_vertices, Some(1.0));
+        let [xmin, ymin, xmax, ymax] = pickuploc.bbox.unwrap();
+        assert!(xmin >= -180.0 && ymin >= -90.0 && xmax <= 180.0 && ymax <= 90.0);
+        assert!(xmin < xmax && ymin < ymax);
+        assert!(pickuploc.min.is_none());
+    }
+}
@@ -0,0 +1,86 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Only generate some of the columns of a table (`--columns`)
+
+use spatialbench_arrow::Table;
+
+/// The columns to generate for one table, see [`parse_columns`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnProjection {
+    /// The projected table
+    pub table: Table,
+    /// Indices of the columns to generate in the schema of the table, in
+    /// output order
+    pub projection: Vec<usize>,
+}
+
+/// Parse a `--columns` value such as `trip:t_tripkey,t_pickuploc`
+pub fn parse_columns(value: &str) -> Result<ColumnProjection, String> {
+    let Some((table, columns)) = value.split_once(':') else {
+        return Err(format!(
+            "expected <table>:<column>,<column>,..., e.g. trip:t_tripkey,t_pickuploc, got '{value}'"
+        ));
+    };
+    let table: Table = table.trim().parse()?;
+    let schema = table.schema();
+    let mut projection = Vec::new();
+    for column in columns.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        let index = schema.index_of(column).map_err(|_| {
+            let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+            format!(
+                "table {table} has no column '{column}', expected one of {}",
+                names.join(", ")
+            )
+        })?;
+        if projection.contains(&index) {
+            return Err(format!("column '{column}' is listed more than once"));
+        }
+        projection.push(index);
+    }
+    if projection.is_empty() {
+        return Err(format!("no columns listed for table {table}"));
+    }
+    Ok(ColumnProjection { table, projection })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_columns() {
+        let columns = parse_columns("trip:t_pickuploc, t_tripkey").unwrap();
+        assert_eq!(columns.table, Table::Trip);
+        assert_eq!(columns.projection, vec![10, 0]);
+
+        for (value, error) in [
+            ("t_tripkey", "expected <table>:<column>"),
+            ("lineitem:l_orderkey", "Invalid table name lineitem"),
+            ("zone:z_zonekey", "Invalid table name zone"),
+            ("trip:t_fare,b_name", "table trip has no column 'b_name'"),
+            (
+                "trip:t_fare,t_fare",
+                "column 't_fare' is listed more than once",
+            ),
+            ("building:", "no columns listed for table building"),
+        ] {
+            let err = parse_columns(value).unwrap_err();
+            assert!(err.starts_with(error), "{value}: {err}");
+        }
+    }
+}
@@ -15,38 +15,95 @@
 // specific language governing permissions and limitations
 // under the License.
 
-//! Implementations of [`Source`] for generating data in TBL format
+//! Implementations of [`Source`] for generating data in CSV format
 use super::generate::Source;
-use spatialbench::csv::{BuildingCsv, CustomerCsv, DriverCsv, TripCsv, VehicleCsv};
+use super::throttle::throttle;
+use spatialbench::csv::{
+    BuildingCsv, CityCsv, CountryCsv, CustomerCsv, DriverCsv, LandmassCsv, PoiCsv,
+    PointCloudPointCsv, RasterCsv, RegionCsv, RoadCsv, TrajectoryCsv, TripCsv, VehicleCsv,
+    DEFAULT_DELIMITER,
+};
 use spatialbench::generators::{
-    BuildingGenerator, CustomerGenerator, DriverGenerator, TripGenerator, VehicleGenerator,
+    BuildingGenerator, CityGenerator, CountryGenerator, CustomerGenerator, DriverGenerator,
+    LandmassGenerator, PoiGenerator, PointCloudGenerator, RasterGenerator, RegionGenerator,
+    RoadGenerator, TrajectoryGenerator, TripGenerator, VehicleGenerator,
 };
 use std::io::Write;
 
+/// Options for writing CSV files
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CsvOptions {
+    /// Character used to separate fields
+    pub delimiter: char,
+    /// Write a header row with the column names
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: DEFAULT_DELIMITER,
+            header: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Return the file extension for files written with these options
+    pub fn extension(&self) -> &'static str {
+        if self.delimiter == '\t' {
+            "tsv"
+        } else {
+            "csv"
+        }
+    }
+}
+
+/// Parse a `--csv-delimiter` value: a single character, or `tab` / `\t`
+pub fn parse_delimiter(value: &str) -> Result<char, String> {
+    match value {
+        "tab" | "\\t" => return Ok('\t'),
+        _ => {}
+    }
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c != '"' && c != '\n' && c != '\r' => Ok(c),
+        _ => Err(format!(
+            "invalid delimiter '{value}', expected a single character other than a quote or newline"
+        )),
+    }
+}
+
 /// Define a Source that writes the table in CSV format
 macro_rules! define_csv_source {
     ($SOURCE_NAME:ident, $GENERATOR_TYPE:ty, $FORMATTER:ty) => {
         pub struct $SOURCE_NAME {
             inner: $GENERATOR_TYPE,
+            options: CsvOptions,
         }
 
         impl $SOURCE_NAME {
-            pub fn new(inner: $GENERATOR_TYPE) -> Self {
-                Self { inner }
+            pub fn new(inner: $GENERATOR_TYPE, options: CsvOptions) -> Self {
+                Self { inner, options }
             }
         }
 
         impl Source for $SOURCE_NAME {
             fn header(&self, buffer: Vec<u8>) -> Vec<u8> {
                 let mut buffer = buffer;
-                writeln!(&mut buffer, "{}", <$FORMATTER>::header())
-                    .expect("writing to memory is infallible");
+                if self.options.header {
+                    // column names never contain the delimiter, so no quoting is needed
+                    let header = <$FORMATTER>::header()
+                        .replace(DEFAULT_DELIMITER, &self.options.delimiter.to_string());
+                    writeln!(&mut buffer, "{header}").expect("writing to memory is infallible");
+                }
                 buffer
             }
 
             fn create(self, mut buffer: Vec<u8>) -> Vec<u8> {
                 for item in self.inner.into_iter() {
-                    let formatter = <$FORMATTER>::new(item);
+                    throttle(1);
+                    let formatter = <$FORMATTER>::new(item).with_delimiter(self.options.delimiter);
                     writeln!(&mut buffer, "{formatter}").expect("writing to memory is infallible");
                 }
                 buffer
@@ -61,3 +118,47 @@ define_csv_source!(DriverCsvSource, DriverGenerator<'static>, DriverCsv);
 define_csv_source!(CustomerCsvSource, CustomerGenerator<'static>, CustomerCsv);
 define_csv_source!(TripCsvSource, TripGenerator, TripCsv);
 define_csv_source!(BuildingCsvSource, BuildingGenerator<'static>, BuildingCsv);
+define_csv_source!(TrajectoryCsvSource, TrajectoryGenerator, TrajectoryCsv);
+define_csv_source!(RoadCsvSource, RoadGenerator, RoadCsv);
+define_csv_source!(LandmassCsvSource, LandmassGenerator, LandmassCsv);
+define_csv_source!(CountryCsvSource, CountryGenerator, CountryCsv);
+define_csv_source!(RegionCsvSource, RegionGenerator, RegionCsv);
+define_csv_source!(CityCsvSource, CityGenerator, CityCsv);
+define_csv_source!(PoiCsvSource, PoiGenerator, PoiCsv);
+define_csv_source!(RasterCsvSource, RasterGenerator, RasterCsv);
+define_csv_source!(PointCloudCsvSource, PointCloudGenerator, PointCloudPointCsv);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_delimiter() {
+        assert_eq!(parse_delimiter(";"), Ok(';'));
+        assert_eq!(parse_delimiter("tab"), Ok('\t'));
+        assert_eq!(parse_delimiter("\\t"), Ok('\t'));
+        assert_eq!(parse_delimiter("\t"), Ok('\t'));
+        assert!(parse_delimiter("").is_err());
+        assert!(parse_delimiter(";;").is_err());
+        assert!(parse_delimiter("\"").is_err());
+    }
+
+    #[test]
+    fn test_tsv_without_header() {
+        let options = CsvOptions {
+            delimiter: '\t',
+            header: false,
+        };
+        let source = VehicleCsvSource::new(VehicleGenerator::new(0.01, 1, 1), options);
+        let header = source.header(Vec::new());
+        assert!(header.is_empty());
+
+        let text = String::from_utf8(source.create(Vec::new())).unwrap();
+        let first = text.lines().next().unwrap();
+        assert_eq!(
+            first,
+            "1\tManufacturer#1\tBrand#13\tPROMO BURNISHED COPPER\t\"ly. slyly ironi\""
+        );
+        assert_eq!(options.extension(), "tsv");
+    }
+}
@@ -0,0 +1,696 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! User defined tables generated next to the built-in ones
+//! (`--custom-tables`)
+//!
+//! The tables are described by a YAML file such as
+//!
+//! ```yaml
+//! tables:
+//!   - name: sensor
+//!     rows: { base: 100, per_sf: 10000 }
+//!     columns:
+//!       - { name: s_sensorkey, type: key }
+//!       - { name: s_buildingkey, type: foreign_key, references: building }
+//!       - { name: s_kind, type: string, values: [air, noise, water], weights: [5, 3, 2] }
+//!       - { name: s_reading, type: float64, min: 0, max: 100, distribution: normal }
+//!       - { name: s_installed, type: date, start: 2020-01-01, end: 2024-12-31 }
+//!       - { name: s_location, type: geometry, kind: point, distribution: hotspots }
+//! ```
+//!
+//! Every value is a hash of the seed, the table, the column and the row, so
+//! the tables are reproducible like the built-in ones.
+
+use crate::spatial_config_file::DistributionPreset;
+use arrow::array::{ArrayRef, BinaryBuilder, Date32Array, Float64Array, Int64Array, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use clap::ValueEnum;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Deserializer};
+use spatialbench::generators::RowCounts;
+use spatialbench::spatial::utils::{
+    build_continent_cdf, hash_to_unit_u64, spider_seed_for_index, WeightedTarget,
+};
+use spatialbench::spatial::{
+    ContinentAffines, Crs, Dimensions, GeomType, SpatialConfig, SpatialGenerator,
+};
+use spatialbench::temporal::parse_timestamp;
+use spatialbench_arrow::conversions::geometry_to_wkb;
+use spatialbench_arrow::{Table, TableGenerator};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// Number of rows of the record batches written to the Parquet files
+const BATCH_ROWS: usize = 8192;
+
+/// Default size of the boxes and polygons, in the unit square of a continent
+const DEFAULT_GEOMETRY_SIZE: f64 = 0.0001;
+
+/// The contents of a `--custom-tables` file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomTablesFile {
+    pub tables: Vec<CustomTable>,
+}
+
+/// A user defined table
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomTable {
+    /// Name of the table and of its `{name}.parquet` file
+    pub name: String,
+    pub rows: RowFormula,
+    pub columns: Vec<CustomColumn>,
+}
+
+/// The number of rows of a table: `base + per_sf * scale_factor`, at least 1
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RowFormula {
+    #[serde(default)]
+    pub base: i64,
+    #[serde(default)]
+    pub per_sf: f64,
+}
+
+impl RowFormula {
+    /// Return the number of rows at `scale_factor`
+    pub fn rows(&self, scale_factor: f64) -> i64 {
+        (self.base + (self.per_sf * scale_factor).round() as i64).max(1)
+    }
+}
+
+/// A column of a user defined table
+#[derive(Debug, Deserialize)]
+pub struct CustomColumn {
+    pub name: String,
+    #[serde(flatten)]
+    pub column_type: ColumnType,
+}
+
+/// The type of a column and the distribution of its values
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ColumnType {
+    /// The row number, from 1
+    Key,
+    /// A key of a built-in table, uniform over its rows at the scale factor
+    ForeignKey {
+        #[serde(deserialize_with = "deserialize_table")]
+        references: Table,
+    },
+    /// An integer between `min` and `max` (inclusive)
+    Int64 {
+        #[serde(default)]
+        min: i64,
+        #[serde(default = "default_int_max")]
+        max: i64,
+        #[serde(default)]
+        distribution: ValueDistribution,
+    },
+    /// A floating point number between `min` and `max`
+    Float64 {
+        #[serde(default)]
+        min: f64,
+        #[serde(default = "default_float_max")]
+        max: f64,
+        #[serde(default)]
+        distribution: ValueDistribution,
+    },
+    /// One of `values`, with optional relative `weights`
+    String {
+        values: Vec<String>,
+        #[serde(default)]
+        weights: Option<Vec<f64>>,
+    },
+    /// A date between `start` and `end` (inclusive), as `YYYY-MM-DD`
+    Date {
+        #[serde(deserialize_with = "deserialize_date")]
+        start: i32,
+        #[serde(deserialize_with = "deserialize_date")]
+        end: i32,
+    },
+    /// A geometry placed on the continents like the built-in geometries
+    Geometry {
+        #[serde(deserialize_with = "deserialize_geometry_kind")]
+        kind: GeomType,
+        #[serde(
+            default = "default_geometry_distribution",
+            deserialize_with = "deserialize_distribution"
+        )]
+        distribution: DistributionPreset,
+        /// Width and height of the boxes, or radius of the polygons, in the
+        /// unit square of a continent
+        #[serde(default = "default_geometry_size")]
+        size: f64,
+    },
+}
+
+/// The distribution of the values of a numeric column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueDistribution {
+    /// Uniform between the minimum and the maximum
+    #[default]
+    Uniform,
+    /// Normal, centered between the minimum and the maximum with a standard
+    /// deviation of a sixth of the range, clamped to the range
+    Normal,
+}
+
+fn default_int_max() -> i64 {
+    1000
+}
+
+fn default_float_max() -> f64 {
+    1.0
+}
+
+fn default_geometry_distribution() -> DistributionPreset {
+    DistributionPreset::Uniform
+}
+
+fn default_geometry_size() -> f64 {
+    DEFAULT_GEOMETRY_SIZE
+}
+
+fn deserialize_table<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Table, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    match name.parse() {
+        Ok(
+            table @ (Table::Vehicle
+            | Table::Driver
+            | Table::Customer
+            | Table::Trip
+            | Table::Building),
+        ) => Ok(table),
+        _ => Err(serde::de::Error::custom(format!(
+            "can not reference table {name}, expected vehicle, driver, customer, trip or building"
+        ))),
+    }
+}
+
+fn deserialize_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+    let date = String::deserialize(deserializer)?;
+    let seconds = parse_timestamp(&date).map_err(serde::de::Error::custom)?;
+    Ok(seconds.div_euclid(86_400) as i32)
+}
+
+fn deserialize_geometry_kind<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<GeomType, D::Error> {
+    match String::deserialize(deserializer)?.to_lowercase().as_str() {
+        "point" => Ok(GeomType::Point),
+        "box" => Ok(GeomType::Box),
+        "polygon" => Ok(GeomType::Polygon),
+        kind => Err(serde::de::Error::custom(format!(
+            "unknown geometry kind {kind}, expected point, box or polygon"
+        ))),
+    }
+}
+
+fn deserialize_distribution<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DistributionPreset, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    DistributionPreset::from_str(&name, true).map_err(serde::de::Error::custom)
+}
+
+impl CustomTablesFile {
+    /// Read and check the tables of `path`
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Failed reading {}: {e}", path.display()),
+            )
+        })?;
+        Self::parse(&text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid custom tables {}: {e}", path.display()),
+            )
+        })
+    }
+
+    /// Parse and check the tables of a YAML document
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let file: Self = serde_yaml::from_str(text).map_err(|e| e.to_string())?;
+        file.check()?;
+        Ok(file)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for table in &self.tables {
+            let name = table.name.as_str();
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!(
+                    "invalid table name '{name}', expected letters, digits and underscores"
+                ));
+            }
+            if name.parse::<Table>().is_ok() || name == "zone" {
+                return Err(format!("table {name} is a built-in table"));
+            }
+            if !names.insert(name) {
+                return Err(format!("table {name} is defined more than once"));
+            }
+            if table.columns.is_empty() {
+                return Err(format!("table {name} has no columns"));
+            }
+            let mut columns = HashSet::new();
+            for column in &table.columns {
+                if !columns.insert(column.name.as_str()) {
+                    return Err(format!(
+                        "column {} of table {name} is defined more than once",
+                        column.name
+                    ));
+                }
+                column
+                    .check()
+                    .map_err(|e| format!("column {} of table {name}: {e}", column.name))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CustomColumn {
+    fn check(&self) -> Result<(), String> {
+        match &self.column_type {
+            ColumnType::Int64 { min, max, .. } if min > max => {
+                Err(format!("min {min} is greater than max {max}"))
+            }
+            ColumnType::Float64 { min, max, .. } if !(min.is_finite() && max.is_finite()) => {
+                Err(format!("min {min} and max {max} must be finite"))
+            }
+            ColumnType::Float64 { min, max, .. } if min > max => {
+                Err(format!("min {min} is greater than max {max}"))
+            }
+            ColumnType::String { values, .. } if values.is_empty() => Err("no values".to_string()),
+            ColumnType::String {
+                values,
+                weights: Some(weights),
+            } => {
+                if weights.len() != values.len() {
+                    return Err(format!(
+                        "{} weights for {} values",
+                        weights.len(),
+                        values.len()
+                    ));
+                }
+                if weights.iter().any(|w| !(w.is_finite() && *w >= 0.0))
+                    || weights.iter().sum::<f64>() <= 0.0
+                {
+                    return Err("the weights must be positive".to_string());
+                }
+                Ok(())
+            }
+            ColumnType::Date { start, end } if start > end => Err("start is after end".to_string()),
+            ColumnType::Geometry { size, .. } if !(size.is_finite() && *size > 0.0) => {
+                Err(format!("size must be positive, got {size}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self.column_type {
+            ColumnType::Key | ColumnType::ForeignKey { .. } | ColumnType::Int64 { .. } => {
+                DataType::Int64
+            }
+            ColumnType::Float64 { .. } => DataType::Float64,
+            ColumnType::String { .. } => DataType::Utf8,
+            ColumnType::Date { .. } => DataType::Date32,
+            ColumnType::Geometry { .. } => DataType::Binary,
+        }
+    }
+}
+
+/// Generates the rows of a [`CustomTable`]
+pub struct CustomTableGenerator<'a> {
+    table: &'a CustomTable,
+    rows: i64,
+    columns: Vec<ColumnGenerator<'a>>,
+}
+
+/// A column with its seed and what its values depend on
+struct ColumnGenerator<'a> {
+    column: &'a CustomColumn,
+    seed: u64,
+    /// The number of rows of the referenced table of a foreign key
+    referenced_rows: i64,
+    spatial: Option<(SpatialGenerator, Vec<WeightedTarget>)>,
+    /// The cumulative weights of the values of a string column
+    cdf: Vec<f64>,
+}
+
+impl<'a> CustomTableGenerator<'a> {
+    /// Create a generator of `table` at `scale_factor`, with the foreign keys
+    /// referencing the built-in tables of `row_counts` (`--rows`)
+    pub fn new(
+        table: &'a CustomTable,
+        scale_factor: f64,
+        seed: u64,
+        row_counts: RowCounts,
+    ) -> Self {
+        let columns = table
+            .columns
+            .iter()
+            .map(|column| {
+                let seed = spider_seed_for_index(
+                    name_hash(&format!("{}.{}", table.name, column.name)),
+                    seed,
+                );
+                let referenced_rows = match column.column_type {
+                    ColumnType::ForeignKey { references } => {
+                        TableGenerator::new(references, scale_factor)
+                            .with_row_counts(row_counts)
+                            .row_count()
+                    }
+                    _ => 0,
+                };
+                let spatial = match &column.column_type {
+                    ColumnType::Geometry {
+                        kind,
+                        distribution,
+                        size,
+                    } => Some(spatial_generator(*kind, *distribution, *size, seed)),
+                    _ => None,
+                };
+                let cdf = match &column.column_type {
+                    ColumnType::String { values, weights } => {
+                        let weights = weights.clone().unwrap_or_else(|| vec![1.0; values.len()]);
+                        let total: f64 = weights.iter().sum();
+                        weights
+                            .iter()
+                            .scan(0.0, |sum, w| {
+                                *sum += w;
+                                Some(*sum / total)
+                            })
+                            .collect()
+                    }
+                    _ => vec![],
+                };
+                ColumnGenerator {
+                    column,
+                    seed,
+                    referenced_rows,
+                    spatial,
+                    cdf,
+                }
+            })
+            .collect();
+        Self {
+            table,
+            rows: table.rows.rows(scale_factor),
+            columns,
+        }
+    }
+
+    /// Return the name of the table
+    pub fn name(&self) -> &str {
+        &self.table.name
+    }
+
+    /// Return the number of rows of the table
+    pub fn row_count(&self) -> i64 {
+        self.rows
+    }
+
+    /// Return the Arrow schema of the table
+    pub fn schema(&self) -> SchemaRef {
+        let fields: Vec<_> = self
+            .table
+            .columns
+            .iter()
+            .map(|column| Field::new(&column.name, column.data_type(), false))
+            .collect();
+        Arc::new(Schema::new(fields))
+    }
+
+    /// Generate the rows `start..end` (from 0)
+    pub fn batch(&self, start: i64, end: i64) -> RecordBatch {
+        let columns: Vec<ArrayRef> = self
+            .columns
+            .iter()
+            .map(|column| column.array(start, end))
+            .collect();
+        RecordBatch::try_new(self.schema(), columns).expect("columns match the schema")
+    }
+
+    /// Write the table to `{name}.parquet` in `output_dir`
+    pub fn write_parquet(&self, output_dir: &Path, compression: Compression) -> io::Result<()> {
+        let path = output_dir.join(format!("{}.parquet", self.table.name));
+        let properties = WriterProperties::builder()
+            .set_compression(compression)
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path)?, self.schema(), Some(properties))
+                .map_err(io::Error::other)?;
+        let mut start = 0;
+        while start < self.rows {
+            let end = (start + BATCH_ROWS as i64).min(self.rows);
+            writer
+                .write(&self.batch(start, end))
+                .map_err(io::Error::other)?;
+            start = end;
+        }
+        writer.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+impl ColumnGenerator<'_> {
+    /// Return the `n`-th uniform number in [0, 1) of the row
+    fn unit(&self, row: i64, n: u64) -> f64 {
+        hash_to_unit_u64(row as u64, spider_seed_for_index(n, self.seed))
+    }
+
+    /// Return a number in [0, 1] drawn from `distribution`
+    fn sample(&self, row: i64, distribution: ValueDistribution) -> f64 {
+        match distribution {
+            ValueDistribution::Uniform => self.unit(row, 0),
+            ValueDistribution::Normal => {
+                // Box-Muller
+                let u1 = self.unit(row, 0).max(f64::MIN_POSITIVE);
+                let u2 = self.unit(row, 1);
+                let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+                (0.5 + z / 6.0).clamp(0.0, 1.0)
+            }
+        }
+    }
+
+    fn array(&self, start: i64, end: i64) -> ArrayRef {
+        let rows = start..end;
+        match &self.column.column_type {
+            ColumnType::Key => Arc::new(Int64Array::from_iter_values(rows.map(|row| row + 1))),
+            ColumnType::ForeignKey { .. } => {
+                let n = self.referenced_rows;
+                Arc::new(Int64Array::from_iter_values(rows.map(|row| {
+                    1 + ((self.unit(row, 0) * n as f64) as i64).min(n - 1)
+                })))
+            }
+            ColumnType::Int64 {
+                min,
+                max,
+                distribution,
+            } => {
+                let range = (max - min) as f64 + 1.0;
+                Arc::new(Int64Array::from_iter_values(rows.map(|row| {
+                    let offset = (self.sample(row, *distribution) * range) as i64;
+                    (min + offset).min(*max)
+                })))
+            }
+            ColumnType::Float64 {
+                min,
+                max,
+                distribution,
+            } => Arc::new(Float64Array::from_iter_values(
+                rows.map(|row| min + self.sample(row, *distribution) * (max - min)),
+            )),
+            ColumnType::String { values, .. } => {
+                let mut builder = StringBuilder::new();
+                for row in rows {
+                    let u = self.unit(row, 0);
+                    let index = self
+                        .cdf
+                        .iter()
+                        .position(|cdf| u < *cdf)
+                        .unwrap_or(values.len() - 1);
+                    builder.append_value(&values[index]);
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnType::Date { start, end } => {
+                let days = (end - start) as f64 + 1.0;
+                Arc::new(Date32Array::from_iter_values(rows.map(|row| {
+                    (start + (self.unit(row, 0) * days) as i32).min(*end)
+                })))
+            }
+            ColumnType::Geometry { .. } => {
+                let (generator, continents) = self.spatial.as_ref().expect("geometry column");
+                let mut builder = BinaryBuilder::new();
+                for row in rows {
+                    // the continent is chosen like the trip pickups
+                    let u = self.unit(row, 2);
+                    let continent = continents
+                        .iter()
+                        .find(|continent| u <= continent.cdf)
+                        .unwrap_or(&continents[continents.len() - 1]);
+                    let geometry = generator.generate(row as u64, &continent.m);
+                    builder.append_value(geometry_to_wkb(
+                        &geometry,
+                        Crs::Wgs84,
+                        Dimensions::Xy,
+                        |_| 0.0,
+                    ));
+                }
+                Arc::new(builder.finish())
+            }
+        }
+    }
+}
+
+/// Return the generator of the geometries of a column, with the cumulative
+/// weights of the continents
+fn spatial_generator(
+    kind: GeomType,
+    distribution: DistributionPreset,
+    size: f64,
+    seed: u64,
+) -> (SpatialGenerator, Vec<WeightedTarget>) {
+    let (dist_type, params) = distribution.distribution();
+    let config = SpatialConfig {
+        dist_type,
+        geom_type: kind,
+        dim: 2,
+        seed: seed as u32,
+        width: size,
+        height: size,
+        minseg: 3,
+        maxseg: 7,
+        polysize: size,
+        params,
+    };
+    let generator = SpatialGenerator::new(config, OnceLock::new(), OnceLock::new());
+    let continents = build_continent_cdf(&ContinentAffines::default())
+        .into_iter()
+        .map(|(_name, m, cdf)| WeightedTarget { m, cdf })
+        .collect();
+    (generator, continents)
+}
+
+/// FNV-1a hash of a name, to derive the seeds of the columns
+fn name_hash(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, BinaryArray, StringArray};
+
+    const SPEC: &str = r#"
+tables:
+  - name: sensor
+    rows: { base: 100, per_sf: 1000 }
+    columns:
+      - { name: s_sensorkey, type: key }
+      - { name: s_buildingkey, type: foreign_key, references: building }
+      - { name: s_kind, type: string, values: [air, noise], weights: [3, 1] }
+      - { name: s_reading, type: int64, min: 10, max: 20, distribution: normal }
+      - { name: s_installed, type: date, start: 2020-01-01, end: 2020-12-31 }
+      - { name: s_location, type: geometry, kind: box, distribution: hotspots }
+"#;
+
+    #[test]
+    fn test_generate() {
+        let file = CustomTablesFile::parse(SPEC).unwrap();
+        let table = &file.tables[0];
+        let generator = CustomTableGenerator::new(table, 0.1, 0, RowCounts::default());
+        assert_eq!(generator.row_count(), 200);
+        let batch = generator.batch(0, 200);
+        assert_eq!(batch.num_rows(), 200);
+        assert_eq!(batch.schema().field(4).data_type(), &DataType::Date32);
+
+        let keys = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(keys.value(0), 1);
+        assert_eq!(keys.value(199), 200);
+        let buildings = TableGenerator::new(Table::Building, 0.1).row_count();
+        let references = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert!(references
+            .iter()
+            .all(|key| (1..=buildings).contains(&key.unwrap())));
+        let kinds = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let air = kinds.iter().filter(|kind| *kind == Some("air")).count();
+        assert!((120..180).contains(&air), "{air} air sensors");
+        let readings = batch
+            .column(3)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert!(readings.iter().all(|r| (10..=20).contains(&r.unwrap())));
+        let locations = batch
+            .column(5)
+            .as_any()
+            .downcast_ref::<BinaryArray>()
+            .unwrap();
+        assert!(locations.iter().all(|wkb| wkb.unwrap().len() > 21));
+
+        // the rows do not depend on the batches
+        assert_eq!(generator.batch(50, 60), batch.slice(50, 10));
+        let seeded = CustomTableGenerator::new(table, 0.1, 7, RowCounts::default());
+        assert_ne!(seeded.batch(0, 200), batch);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let error = |spec: &str| CustomTablesFile::parse(spec).unwrap_err();
+        assert!(error(
+            "tables:\n  - { name: trip, rows: {base: 1}, columns: [{name: a, type: key}] }"
+        )
+        .contains("built-in"));
+        assert!(error("tables:\n  - { name: t, rows: {base: 1}, columns: [{name: a, type: foreign_key, references: zone}] }")
+            .contains("can not reference table zone"));
+        assert!(error("tables:\n  - { name: t, rows: {base: 1}, columns: [{name: a, type: int64, min: 5, max: 1}] }")
+            .contains("column a of table t"));
+        assert!(error("tables:\n  - { name: t, rows: {base: 1}, columns: [{name: a, type: geometry, kind: line}] }")
+            .contains("unknown geometry kind"));
+    }
+}
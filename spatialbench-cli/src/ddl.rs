@@ -0,0 +1,332 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `ddl` command: print the `CREATE TABLE` statements of the tables
+//!
+//! The statements are derived from the Arrow schemas of the generators, so
+//! they always match the generated columns, with the geometry columns typed
+//! for the engine and the spatial indexes it recommends.
+
+use crate::geoparquet::is_wkb_type;
+use crate::postgres::postgres_type;
+use crate::schema::SchemaOptions;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use clap::{Args, ValueEnum};
+use spatialbench::spatial::{dimensions, Crs, Dimensions};
+use spatialbench_arrow::Table;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// Print the `CREATE TABLE` statements of the tables for a SQL engine
+#[derive(Debug, Args)]
+pub struct DdlArgs {
+    /// SQL dialect: postgis, sedona, duckdb, snowflake or bigquery
+    #[arg(short, long, default_value = "postgis")]
+    dialect: DdlDialect,
+
+    /// Which tables to print, e.g. `trip,building` (default: vehicle, driver,
+    /// customer, trip, building and zone)
+    #[arg(short, long, value_delimiter = ',', value_parser = parse_table)]
+    tables: Option<Vec<String>>,
+
+    #[command(flatten)]
+    schema: SchemaOptions,
+}
+
+/// SQL engines to write the DDL for
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum DdlDialect {
+    /// PostgreSQL with PostGIS, as loaded by `--output postgres://...`
+    Postgis,
+    /// Apache Sedona (Spark SQL), with the geometries as WKB
+    Sedona,
+    /// DuckDB with the spatial extension
+    Duckdb,
+    /// Snowflake, with search optimization of the geometry columns
+    Snowflake,
+    /// Google BigQuery, which only has longitude / latitude `GEOGRAPHY`
+    Bigquery,
+}
+
+impl DdlArgs {
+    /// Print the statements of the tables to stdout
+    pub fn run(&self) -> io::Result<()> {
+        if self.dialect == DdlDialect::Bigquery && self.schema.crs != Crs::Wgs84 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "BigQuery geographies are in longitude / latitude, --crs {} is not supported",
+                    self.schema.crs
+                ),
+            ));
+        }
+        self.schema.apply()?;
+
+        let default_tables = ["vehicle", "driver", "customer", "trip", "building", "zone"];
+        let tables = match &self.tables {
+            Some(tables) => tables.iter().map(String::as_str).collect(),
+            None => default_tables.to_vec(),
+        };
+        let mut ddl = String::new();
+        match self.dialect {
+            DdlDialect::Postgis => ddl.push_str("CREATE EXTENSION IF NOT EXISTS postgis;\n\n"),
+            DdlDialect::Duckdb => ddl.push_str("INSTALL spatial;\nLOAD spatial;\n\n"),
+            _ => {}
+        }
+        for (i, table) in tables.into_iter().enumerate() {
+            if i > 0 {
+                ddl.push('\n');
+            }
+            ddl.push_str(&create_table(
+                self.dialect,
+                table,
+                &table_schema(table),
+                geometry_dimensions(table),
+                self.schema.crs.epsg_code(),
+                self.schema.geography,
+            )?);
+        }
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(ddl.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+/// Parse the name of a generated table or `zone`
+fn parse_table(name: &str) -> Result<String, String> {
+    if name != "zone" {
+        name.parse::<Table>()?;
+    }
+    Ok(name.to_string())
+}
+
+/// Return the schema of `table`, a generated table or `zone`
+fn table_schema(table: &str) -> SchemaRef {
+    match table.parse::<Table>() {
+        Ok(table) => table.schema(),
+        // the zone table is written by DataFusion from Overture Maps
+        Err(_) => Arc::new(Schema::new(vec![
+            Field::new("z_zonekey", DataType::Int64, false),
+            Field::new("z_gersid", DataType::Utf8View, false),
+            Field::new("z_country", DataType::Utf8View, false),
+            Field::new("z_region", DataType::Utf8View, false),
+            Field::new("z_name", DataType::Utf8View, false),
+            Field::new("z_subtype", DataType::Utf8View, false),
+            Field::new("z_boundary", DataType::Binary, false),
+        ])),
+    }
+}
+
+/// Return the dimensions of the geometries of `table`
+///
+/// Trajectory paths are the only geometries with M, and the zone boundaries
+/// are always 2D.
+fn geometry_dimensions(table: &str) -> Dimensions {
+    match table {
+        "trajectory" => dimensions::dimensions(),
+        "zone" => Dimensions::Xy,
+        _ => dimensions::dimensions().without_m(),
+    }
+}
+
+/// Return the `CREATE TABLE` statement of `table` in `dialect`, followed by
+/// the statements creating its spatial indexes
+fn create_table(
+    dialect: DdlDialect,
+    table: &str,
+    schema: &Schema,
+    dimensions: Dimensions,
+    srid: u32,
+    geography: bool,
+) -> io::Result<String> {
+    let mut columns = Vec::new();
+    let mut geometry_columns = Vec::new();
+    for field in schema.fields() {
+        let name = field.name();
+        let data_type = column_type(dialect, field.data_type(), dimensions, srid, geography)?;
+        let not_null = if field.is_nullable() { "" } else { " NOT NULL" };
+        let mut column = format!("    {name} {data_type}{not_null}");
+        if is_wkb_type(field.data_type()) {
+            if dialect == DdlDialect::Sedona {
+                let _ = write!(column, " COMMENT 'WKB geometry in EPSG:{srid}'");
+            }
+            geometry_columns.push(name.as_str());
+        }
+        columns.push(column);
+    }
+
+    let mut sql = format!("CREATE TABLE {table} (\n{}\n)", columns.join(",\n"));
+    match dialect {
+        DdlDialect::Sedona => sql.push_str(" USING parquet"),
+        // clustering is what prunes the blocks of spatial filters
+        DdlDialect::Bigquery => {
+            if let Some(column) = geometry_columns.first() {
+                let _ = write!(sql, "\nCLUSTER BY {column}");
+            }
+        }
+        _ => {}
+    }
+    sql.push_str(";\n");
+    for column in geometry_columns {
+        match dialect {
+            DdlDialect::Postgis => {
+                let _ = writeln!(
+                    sql,
+                    "CREATE INDEX {table}_{column}_idx ON {table} USING GIST ({column});"
+                );
+            }
+            DdlDialect::Duckdb => {
+                let _ = writeln!(
+                    sql,
+                    "CREATE INDEX {table}_{column}_idx ON {table} USING RTREE ({column});"
+                );
+            }
+            DdlDialect::Snowflake => {
+                let _ = writeln!(
+                    sql,
+                    "ALTER TABLE {table} ADD SEARCH OPTIMIZATION ON GEO({column});"
+                );
+            }
+            // Spark tables have no indexes, and BigQuery tables are
+            // clustered instead
+            DdlDialect::Sedona | DdlDialect::Bigquery => {}
+        }
+    }
+    Ok(sql)
+}
+
+/// Return the type of a column of Arrow type `data_type` in `dialect`
+fn column_type(
+    dialect: DdlDialect,
+    data_type: &DataType,
+    dimensions: Dimensions,
+    srid: u32,
+    geography: bool,
+) -> io::Result<String> {
+    if dialect == DdlDialect::Postgis {
+        return postgres_type(data_type, dimensions, srid, geography);
+    }
+    let column_type = match (dialect, data_type) {
+        (DdlDialect::Bigquery, DataType::Boolean) => "BOOL".to_string(),
+        (_, DataType::Boolean) => "BOOLEAN".to_string(),
+        (DdlDialect::Bigquery, DataType::Int16 | DataType::Int32 | DataType::Int64) => {
+            "INT64".to_string()
+        }
+        (_, DataType::Int16) => "SMALLINT".to_string(),
+        (DdlDialect::Sedona, DataType::Int32) => "INT".to_string(),
+        (_, DataType::Int32) => "INTEGER".to_string(),
+        (_, DataType::Int64) => "BIGINT".to_string(),
+        (DdlDialect::Bigquery, DataType::Float32 | DataType::Float64) => "FLOAT64".to_string(),
+        (DdlDialect::Duckdb, DataType::Float32) => "REAL".to_string(),
+        (_, DataType::Float32) => "FLOAT".to_string(),
+        (DdlDialect::Snowflake, DataType::Float64) => "FLOAT".to_string(),
+        (_, DataType::Float64) => "DOUBLE".to_string(),
+        (dialect, DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View) => match dialect {
+            DdlDialect::Sedona | DdlDialect::Bigquery => "STRING".to_string(),
+            _ => "VARCHAR".to_string(),
+        },
+        (DdlDialect::Snowflake, DataType::Decimal128(p, s)) => format!("NUMBER({p}, {s})"),
+        (DdlDialect::Bigquery, DataType::Decimal128(p, s)) => format!("NUMERIC({p}, {s})"),
+        (_, DataType::Decimal128(p, s)) => format!("DECIMAL({p}, {s})"),
+        (dialect, DataType::Timestamp(_, tz)) => match (dialect, tz.is_some()) {
+            (DdlDialect::Sedona | DdlDialect::Snowflake, false) => "TIMESTAMP_NTZ".to_string(),
+            (DdlDialect::Snowflake, true) => "TIMESTAMP_TZ".to_string(),
+            (DdlDialect::Bigquery, false) => "DATETIME".to_string(),
+            (DdlDialect::Duckdb, true) => "TIMESTAMPTZ".to_string(),
+            _ => "TIMESTAMP".to_string(),
+        },
+        (_, DataType::Date32) => "DATE".to_string(),
+        (dialect, t) if is_wkb_type(t) => match dialect {
+            DdlDialect::Sedona => "BINARY".to_string(),
+            DdlDialect::Snowflake if geography => "GEOGRAPHY".to_string(),
+            DdlDialect::Bigquery => "GEOGRAPHY".to_string(),
+            _ => "GEOMETRY".to_string(),
+        },
+        (_, t) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Columns of type {t} have no {dialect:?} type"),
+            ))
+        }
+    };
+    Ok(column_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_table() {
+        let schema = Schema::new(vec![
+            Field::new("b_buildingkey", DataType::Int64, false),
+            Field::new("b_name", DataType::Utf8View, true),
+            Field::new("b_boundary", DataType::Binary, false),
+        ]);
+        let sql = |dialect| {
+            create_table(dialect, "building", &schema, Dimensions::Xyz, 3857, false).unwrap()
+        };
+        assert_eq!(
+            sql(DdlDialect::Postgis),
+            "CREATE TABLE building (\n    b_buildingkey bigint NOT NULL,\n    b_name text,\n    \
+             b_boundary geometry(GeometryZ, 3857) NOT NULL\n);\n\
+             CREATE INDEX building_b_boundary_idx ON building USING GIST (b_boundary);\n"
+        );
+        assert!(sql(DdlDialect::Duckdb).contains("b_boundary GEOMETRY NOT NULL\n"));
+        assert!(sql(DdlDialect::Duckdb).ends_with("USING RTREE (b_boundary);\n"));
+        assert!(sql(DdlDialect::Sedona).ends_with(") USING parquet;\n"));
+        assert!(sql(DdlDialect::Snowflake).ends_with("ON GEO(b_boundary);\n"));
+        assert!(sql(DdlDialect::Bigquery).ends_with("\n)\nCLUSTER BY b_boundary;\n"));
+    }
+
+    #[test]
+    fn test_column_types() {
+        let timestamp = DataType::Timestamp(arrow::datatypes::TimeUnit::Millisecond, None);
+        for (dialect, expected) in [
+            (DdlDialect::Postgis, ["timestamp", "numeric(15, 5)", "text"]),
+            (
+                DdlDialect::Sedona,
+                ["TIMESTAMP_NTZ", "DECIMAL(15, 5)", "STRING"],
+            ),
+            (
+                DdlDialect::Duckdb,
+                ["TIMESTAMP", "DECIMAL(15, 5)", "VARCHAR"],
+            ),
+            (
+                DdlDialect::Snowflake,
+                ["TIMESTAMP_NTZ", "NUMBER(15, 5)", "VARCHAR"],
+            ),
+            (
+                DdlDialect::Bigquery,
+                ["DATETIME", "NUMERIC(15, 5)", "STRING"],
+            ),
+        ] {
+            let types = [
+                &timestamp,
+                &DataType::Decimal128(15, 5),
+                &DataType::Utf8View,
+            ]
+            .map(|t| column_type(dialect, t, Dimensions::Xy, 4326, false).unwrap());
+            assert_eq!(types, expected, "{dialect:?}");
+        }
+        let geography =
+            |dialect| column_type(dialect, &DataType::Binary, Dimensions::Xy, 4326, true).unwrap();
+        assert_eq!(geography(DdlDialect::Postgis), "geography(Geometry, 4326)");
+        assert_eq!(geography(DdlDialect::Snowflake), "GEOGRAPHY");
+    }
+}
@@ -0,0 +1,599 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Delta Lake tables
+//!
+//! A Delta table is a directory with the Parquet files of the table and a
+//! `_delta_log` transaction log. Once the Parquet files of a table are
+//! written, [`write_delta_log`] writes the first commit of the log, adding
+//! all the files with their partition values and statistics.
+//!
+//! The statistics are the number of records, and the minimum, maximum and
+//! null count of the numeric columns, including the fields of the GeoParquet
+//! bounding box columns, so engines can skip the files and row groups that
+//! do not intersect a query window.
+
+use crate::hive::DEFAULT_PARTITION;
+use arrow::datatypes::{DataType, Field, Fields, Schema};
+use log::info;
+use parquet::arrow::parquet_to_arrow_schema;
+use parquet::file::metadata::{ParquetMetaData, ParquetMetaDataReader};
+use parquet::file::statistics::Statistics;
+use serde_json::{json, Map, Value};
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, RandomState};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory of the transaction log in the table directory
+pub const DELTA_LOG_DIR: &str = "_delta_log";
+
+/// File of the first commit of the transaction log
+const FIRST_COMMIT: &str = "00000000000000000000.json";
+
+/// A Parquet file of the table
+struct DataFile {
+    /// Path relative to the table directory
+    path: String,
+    /// The `key=value` partition directories of the path (escaped)
+    partitions: Vec<(String, String)>,
+    size: u64,
+    modification_time: u64,
+    metadata: ParquetMetaData,
+}
+
+/// Move `<table>.parquet` from `output_dir` into the `<table>` directory,
+/// for the tables written as a single file by other writers (e.g. zone)
+pub fn move_into_table_dir(output_dir: &Path, table: &str) -> io::Result<()> {
+    let file = output_dir.join(format!("{table}.parquet"));
+    if file.is_file() {
+        let dir = output_dir.join(table);
+        fs::create_dir_all(&dir)?;
+        fs::rename(&file, dir.join(file.file_name().expect("file name")))?;
+    }
+    Ok(())
+}
+
+/// Write the transaction log of the Delta table in `dir`, adding all the
+/// Parquet files in it
+///
+/// Tables that already have a log are left unchanged.
+pub fn write_delta_log(dir: &Path, table: &str) -> io::Result<()> {
+    let log_dir = dir.join(DELTA_LOG_DIR);
+    let commit = log_dir.join(FIRST_COMMIT);
+    if commit.exists() {
+        info!("{} already exists, skipping", commit.display());
+        return Ok(());
+    }
+    let mut paths = vec![];
+    find_parquet_files(dir, &mut paths)?;
+    paths.sort();
+    let files = paths
+        .iter()
+        .map(|path| DataFile::try_new(dir, path))
+        .collect::<io::Result<Vec<_>>>()?;
+    let Some(first) = files.first() else {
+        return Err(io::Error::other(format!(
+            "No Parquet files to add to the Delta table {}",
+            dir.display()
+        )));
+    };
+
+    // the partition columns are not in the files, like in Hive
+    let metadata = first.metadata.file_metadata();
+    let schema = parquet_to_arrow_schema(metadata.schema_descr(), metadata.key_value_metadata())
+        .map_err(io::Error::other)?;
+    let mut fields = schema
+        .fields()
+        .iter()
+        .map(|field| delta_field(field))
+        .collect::<io::Result<Vec<_>>>()?;
+    let partition_columns: Vec<String> = first
+        .partitions
+        .iter()
+        .map(|(key, _)| key.clone())
+        .collect();
+    for (index, column) in partition_columns.iter().enumerate() {
+        let values = files.iter().filter_map(|file| file.partitions.get(index));
+        let values: Vec<_> = values.map(|(_, value)| value.as_str()).collect();
+        fields.push(json!({
+            "name": column,
+            "type": partition_type(&values),
+            "nullable": true,
+            "metadata": {},
+        }));
+    }
+    let delta_schema = json!({"type": "struct", "fields": fields});
+    let timestamp_ntz = schema
+        .fields()
+        .iter()
+        .any(|field| is_timestamp_ntz(field.data_type()));
+
+    let now = millis(SystemTime::now());
+    let mut actions = vec![
+        json!({"commitInfo": {
+            "timestamp": now,
+            "operation": "WRITE",
+            "operationParameters": {
+                "mode": "ErrorIfExists",
+                "partitionBy": serde_json::to_string(&partition_columns).expect("serializable"),
+            },
+            "isBlindAppend": true,
+            "engineInfo": concat!("spatialbench-cli/", env!("CARGO_PKG_VERSION")),
+        }}),
+        if timestamp_ntz {
+            json!({"protocol": {
+                "minReaderVersion": 3,
+                "minWriterVersion": 7,
+                "readerFeatures": ["timestampNtz"],
+                "writerFeatures": ["timestampNtz"],
+            }})
+        } else {
+            json!({"protocol": {"minReaderVersion": 1, "minWriterVersion": 2}})
+        },
+        json!({"metaData": {
+            "id": random_uuid(),
+            "name": table,
+            "format": {"provider": "parquet", "options": {}},
+            "schemaString": delta_schema.to_string(),
+            "partitionColumns": partition_columns,
+            "configuration": {},
+            "createdTime": now,
+        }}),
+    ];
+    for file in &files {
+        if file.partitions.len() != partition_columns.len()
+            || file
+                .partitions
+                .iter()
+                .zip(&partition_columns)
+                .any(|((key, _), column)| key != column)
+        {
+            return Err(io::Error::other(format!(
+                "{} is not partitioned like {}",
+                file.path, first.path
+            )));
+        }
+        let partition_values: Map<String, Value> = file
+            .partitions
+            .iter()
+            .map(|(key, value)| {
+                let value = match value.as_str() {
+                    DEFAULT_PARTITION => Value::Null,
+                    value => Value::String(unescape_path_value(value)),
+                };
+                (key.clone(), value)
+            })
+            .collect();
+        actions.push(json!({"add": {
+            "path": encode_path(&file.path),
+            "partitionValues": partition_values,
+            "size": file.size,
+            "modificationTime": file.modification_time,
+            "dataChange": true,
+            "stats": file_stats(&schema, &file.metadata).to_string(),
+        }}));
+    }
+
+    fs::create_dir_all(&log_dir)?;
+    let mut log = String::new();
+    for action in actions {
+        writeln!(log, "{action}").expect("writing to a String");
+    }
+    // write to a temp file and then rename, as readers may list the log
+    let temp = log_dir.join(format!(".{FIRST_COMMIT}.tmp"));
+    fs::write(&temp, log)?;
+    fs::rename(&temp, &commit)?;
+    info!(
+        "Wrote the Delta log of {} with {} files",
+        dir.display(),
+        files.len()
+    );
+    Ok(())
+}
+
+impl DataFile {
+    fn try_new(dir: &Path, path: &Path) -> io::Result<Self> {
+        let relative = path.strip_prefix(dir).expect("file in the table directory");
+        let components: Vec<_> = relative
+            .iter()
+            .map(|component| component.to_string_lossy().to_string())
+            .collect();
+        let partitions = components[..components.len() - 1]
+            .iter()
+            .filter_map(|directory| {
+                let (key, value) = directory.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect();
+        let file = File::open(path)?;
+        let file_metadata = file.metadata()?;
+        let metadata = ParquetMetaDataReader::new()
+            .parse_and_finish(&file)
+            .map_err(|e| io::Error::other(format!("Invalid Parquet file {path:?}: {e}")))?;
+        Ok(Self {
+            path: components.join("/"),
+            partitions,
+            size: file_metadata.len(),
+            modification_time: millis(file_metadata.modified()?),
+            metadata,
+        })
+    }
+}
+
+/// Append the Parquet files in `dir` and its subdirectories to `paths`,
+/// skipping the hidden files and directories (e.g. `_delta_log`)
+fn find_parquet_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(['_', '.']) {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            find_parquet_files(&path, paths)?;
+        } else if name.ends_with(".parquet") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Return the Delta schema field of an Arrow field
+fn delta_field(field: &Field) -> io::Result<Value> {
+    Ok(json!({
+        "name": field.name(),
+        "type": delta_type(field.data_type())?,
+        "nullable": field.is_nullable(),
+        "metadata": {},
+    }))
+}
+
+/// Return the Delta type of an Arrow type
+fn delta_type(data_type: &DataType) -> io::Result<Value> {
+    let name = match data_type {
+        DataType::Boolean => "boolean",
+        DataType::Int8 => "byte",
+        DataType::Int16 => "short",
+        DataType::Int32 => "integer",
+        DataType::Int64 => "long",
+        DataType::Float32 => "float",
+        DataType::Float64 => "double",
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => "string",
+        DataType::Binary
+        | DataType::LargeBinary
+        | DataType::BinaryView
+        | DataType::FixedSizeBinary(_) => "binary",
+        DataType::Date32 | DataType::Date64 => "date",
+        DataType::Timestamp(_, Some(_)) => "timestamp",
+        DataType::Timestamp(_, None) => "timestamp_ntz",
+        DataType::Decimal128(precision, scale) => {
+            return Ok(json!(format!("decimal({precision},{scale})")))
+        }
+        DataType::Struct(fields) => {
+            let fields = fields
+                .iter()
+                .map(|field| delta_field(field))
+                .collect::<io::Result<Vec<_>>>()?;
+            return Ok(json!({"type": "struct", "fields": fields}));
+        }
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            return Ok(json!({
+                "type": "array",
+                "elementType": delta_type(field.data_type())?,
+                "containsNull": field.is_nullable(),
+            }))
+        }
+        other => {
+            return Err(io::Error::other(format!(
+                "Unsupported type {other} in a Delta table"
+            )))
+        }
+    };
+    Ok(json!(name))
+}
+
+/// Return true if `data_type` is or has a timestamp without a time zone,
+/// which needs the `timestampNtz` table feature
+fn is_timestamp_ntz(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Timestamp(_, None) => true,
+        DataType::Struct(fields) => fields
+            .iter()
+            .any(|field| is_timestamp_ntz(field.data_type())),
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            is_timestamp_ntz(field.data_type())
+        }
+        _ => false,
+    }
+}
+
+/// Return the Delta type of a partition column from its (escaped) values
+fn partition_type(values: &[&str]) -> &'static str {
+    let values: Vec<_> = values
+        .iter()
+        .filter(|value| **value != DEFAULT_PARTITION)
+        .collect();
+    let is_date = |value: &str| {
+        let bytes = value.as_bytes();
+        bytes.len() == 10
+            && bytes.iter().enumerate().all(|(i, b)| match i {
+                4 | 7 => *b == b'-',
+                _ => b.is_ascii_digit(),
+            })
+    };
+    if values.is_empty() {
+        "string"
+    } else if values.iter().all(|value| value.parse::<i64>().is_ok()) {
+        "long"
+    } else if values.iter().all(|value| is_date(value)) {
+        "date"
+    } else {
+        "string"
+    }
+}
+
+/// Return the `stats` of an `add` action: the number of records, and the
+/// minimum, maximum and null count of the numeric columns
+fn file_stats(schema: &Schema, metadata: &ParquetMetaData) -> Value {
+    let mut min_values = Map::new();
+    let mut max_values = Map::new();
+    let mut null_count = Map::new();
+    let row_groups = metadata.row_groups();
+    let num_columns = metadata.file_metadata().schema_descr().num_columns();
+    for column in 0..num_columns {
+        let descriptor = metadata.file_metadata().schema_descr().column(column);
+        let path = descriptor.path().parts();
+        let Some(data_type) = leaf_type(schema.fields(), path) else {
+            continue; // a list, e.g. of GeoArrow coordinates
+        };
+        let statistics: Vec<_> = row_groups
+            .iter()
+            .map(|row_group| row_group.column(column).statistics())
+            .collect();
+        let nulls: Option<u64> = statistics
+            .iter()
+            .map(|statistics| statistics.and_then(Statistics::null_count_opt))
+            .sum();
+        if let Some(nulls) = nulls {
+            insert_path(&mut null_count, path, json!(nulls));
+        }
+        let bounds = statistics
+            .iter()
+            .map(|statistics| numeric_bounds(data_type, (*statistics)?))
+            .collect::<Option<Vec<_>>>();
+        let Some((min, max)) = bounds.and_then(|bounds| {
+            bounds
+                .into_iter()
+                .reduce(|(min, max), (a, b)| (min.min(a), max.max(b)))
+        }) else {
+            continue;
+        };
+        let value = |v: f64| match data_type {
+            DataType::Float32 | DataType::Float64 => json!(v),
+            _ => json!(v as i64),
+        };
+        insert_path(&mut min_values, path, value(min));
+        insert_path(&mut max_values, path, value(max));
+    }
+    json!({
+        "numRecords": metadata.file_metadata().num_rows(),
+        "minValues": min_values,
+        "maxValues": max_values,
+        "nullCount": null_count,
+    })
+}
+
+/// Return the Arrow type of the leaf column at `path`, if it is only nested
+/// in structs
+fn leaf_type<'a>(fields: &'a Fields, path: &[String]) -> Option<&'a DataType> {
+    let (name, rest) = path.split_first()?;
+    let field = fields.iter().find(|field| field.name() == name)?;
+    match (field.data_type(), rest.is_empty()) {
+        (DataType::Struct(fields), false) => leaf_type(fields, rest),
+        (data_type, true) if !data_type.is_nested() => Some(data_type),
+        _ => None,
+    }
+}
+
+/// Return the minimum and maximum of the statistics of an integer or
+/// floating point column, if known
+///
+/// Integers of Delta stats are written as JSON numbers, so `f64` is exact
+/// for the keys of the tables.
+fn numeric_bounds(data_type: &DataType, statistics: &Statistics) -> Option<(f64, f64)> {
+    match (data_type, statistics) {
+        (DataType::Int8 | DataType::Int16 | DataType::Int32, Statistics::Int32(s)) => {
+            Some((*s.min_opt()? as f64, *s.max_opt()? as f64))
+        }
+        (DataType::Int64, Statistics::Int64(s)) => {
+            Some((*s.min_opt()? as f64, *s.max_opt()? as f64))
+        }
+        (DataType::Float32, Statistics::Float(s)) => {
+            Some((*s.min_opt()? as f64, *s.max_opt()? as f64))
+        }
+        (DataType::Float64, Statistics::Double(s)) => Some((*s.min_opt()?, *s.max_opt()?)),
+        _ => None,
+    }
+}
+
+/// Insert `value` at the nested `path` of `map`
+fn insert_path(map: &mut Map<String, Value>, path: &[String], value: Value) {
+    let (name, rest) = path.split_first().expect("non empty path");
+    if rest.is_empty() {
+        map.insert(name.clone(), value);
+        return;
+    }
+    let child = map
+        .entry(name.clone())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Value::Object(child) = child {
+        insert_path(child, rest, value);
+    }
+}
+
+/// Percent encode the characters of a relative path that are not allowed
+/// in the URI paths of the log, including the `%` of escaped partition
+/// values
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~/=".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            write!(encoded, "%{byte:02X}").expect("writing to a String");
+        }
+    }
+    encoded
+}
+
+/// Decode the `%XX` escapes of a Hive partition directory value
+fn unescape_path_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Return milliseconds since the Unix epoch
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Return a random (version 4) UUID, the id of a new table
+fn random_uuid() -> String {
+    let state = RandomState::new();
+    let high = state.hash_one(millis(SystemTime::now()));
+    let low = state.hash_one(high);
+    let bits = ((high as u128) << 64 | low as u128) & !(0xF000 << 64 | 0xC << 60)
+        | 0x4000 << 64
+        | 0x8 << 60;
+    let hex = format!("{bits:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::TimeUnit;
+    use parquet::arrow::ArrowWriter;
+    use spatialbench::generators::TripGenerator;
+    use spatialbench_arrow::{RecordBatchIterator, TripArrow};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_delta_types() {
+        assert_eq!(delta_type(&DataType::Int64).unwrap(), json!("long"));
+        assert_eq!(
+            delta_type(&DataType::Decimal128(15, 5)).unwrap(),
+            json!("decimal(15,5)")
+        );
+        assert_eq!(
+            delta_type(&DataType::Timestamp(TimeUnit::Millisecond, None)).unwrap(),
+            json!("timestamp_ntz")
+        );
+        assert!(delta_type(&DataType::UInt64).is_err());
+        assert_eq!(partition_type(&["2024-01-01", DEFAULT_PARTITION]), "date");
+        assert_eq!(partition_type(&["1", "12"]), "long");
+        assert_eq!(partition_type(&["ASIA", "1"]), "string");
+        assert_eq!(unescape_path_value("a%3Db%2F"), "a=b/");
+        assert_eq!(
+            encode_path("c=a%3Db/part-0.parquet"),
+            "c=a%253Db/part-0.parquet"
+        );
+
+        let uuid = random_uuid();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+    }
+
+    #[test]
+    fn test_write_delta_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let partition = dir.path().join("cell=3");
+        fs::create_dir_all(&partition).unwrap();
+        let batches = TripArrow::new(TripGenerator::new(0.001, 1, 1)).with_batch_size(1000);
+        let schema = Arc::clone(batches.schema());
+        let file = File::create(partition.join("part-0.parquet")).unwrap();
+        let mut writer = ArrowWriter::try_new(file, schema, None).unwrap();
+        for batch in batches {
+            writer.write(&batch).unwrap();
+        }
+        writer.close().unwrap();
+        fs::write(dir.path().join("_SUCCESS.part-0"), "").unwrap();
+
+        write_delta_log(dir.path(), "trip").unwrap();
+        let log = fs::read_to_string(dir.path().join(DELTA_LOG_DIR).join(FIRST_COMMIT)).unwrap();
+        let actions: Vec<Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(actions.len(), 4);
+        assert_eq!(
+            actions[1]["protocol"]["readerFeatures"],
+            json!(["timestampNtz"])
+        );
+        let metadata = &actions[2]["metaData"];
+        assert_eq!(metadata["partitionColumns"], json!(["cell"]));
+        let schema: Value =
+            serde_json::from_str(metadata["schemaString"].as_str().unwrap()).unwrap();
+        assert_eq!(schema["fields"][0]["name"], "t_tripkey");
+        assert_eq!(
+            schema["fields"].as_array().unwrap().last().unwrap()["type"],
+            "long"
+        );
+
+        let add = &actions[3]["add"];
+        assert_eq!(add["path"], "cell=3/part-0.parquet");
+        assert_eq!(add["partitionValues"], json!({"cell": "3"}));
+        let stats: Value = serde_json::from_str(add["stats"].as_str().unwrap()).unwrap();
+        assert_eq!(stats["numRecords"], 6000);
+        assert_eq!(stats["minValues"]["t_tripkey"], 1);
+        assert_eq!(stats["maxValues"]["t_tripkey"], 6000);
+        assert_eq!(stats["nullCount"]["t_pickuploc"], 0);
+        // decimals and timestamps have no bounds
+        assert!(stats["minValues"].get("t_fare").is_none());
+    }
+}
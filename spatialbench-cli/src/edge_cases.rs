@@ -0,0 +1,142 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+//! The `edge-cases` command: write a table of pathological geometries
+
+use crate::geoparquet::{GeoParquetEncoder, GeoParquetVersion};
+use arrow::array::{BinaryArray, Int64Array, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use clap::{Args, ValueEnum};
+use geo::Geometry;
+use log::info;
+use parquet::arrow::ArrowWriter;
+use spatialbench::spatial::edge_cases::{edge_cases, EdgeCase};
+use spatialbench::spatial::{Crs, Dimensions};
+use spatialbench_arrow::conversions::geometry_to_wkb;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Write a table of pathological geometries, to test the robustness of
+/// engines
+#[derive(Debug, Args)]
+pub struct EdgeCasesArgs {
+    /// Directory to write `edge_case.parquet` or `edge_case.csv` to
+    #[arg(short, long, default_value = ".")]
+    output_dir: PathBuf,
+
+    /// Output format: GeoParquet 1.1 with WKB geometries or CSV with WKT
+    /// geometries
+    #[arg(short, long, default_value = "parquet")]
+    format: EdgeCasesFormat,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum EdgeCasesFormat {
+    Parquet,
+    Csv,
+}
+
+impl EdgeCasesArgs {
+    /// Write the edge cases to the output directory
+    pub fn run(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.output_dir)?;
+        let cases = edge_cases();
+        let path = match self.format {
+            EdgeCasesFormat::Parquet => {
+                let path = self.output_dir.join("edge_case.parquet");
+                write_parquet(&cases, File::create(&path)?)?;
+                path
+            }
+            EdgeCasesFormat::Csv => {
+                let path = self.output_dir.join("edge_case.csv");
+                let mut out = BufWriter::new(File::create(&path)?);
+                write_csv(&cases, &mut out)?;
+                out.flush()?;
+                path
+            }
+        };
+        info!("Wrote {} edge cases to {}", cases.len(), path.display());
+        Ok(())
+    }
+}
+
+/// Return the edge cases as a batch, with the geometries as WKB
+fn to_batch(cases: &[EdgeCase]) -> RecordBatch {
+    let schema = Schema::new(vec![
+        Field::new("e_casekey", DataType::Int64, false),
+        Field::new("e_category", DataType::Utf8, false),
+        Field::new("e_name", DataType::Utf8, false),
+        Field::new("e_description", DataType::Utf8, false),
+        Field::new("e_geometry", DataType::Binary, false),
+    ]);
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(Int64Array::from_iter_values(1..=cases.len() as i64)),
+            Arc::new(StringArray::from_iter_values(
+                cases.iter().map(|c| c.category),
+            )),
+            Arc::new(StringArray::from_iter_values(cases.iter().map(|c| c.name))),
+            Arc::new(StringArray::from_iter_values(
+                cases.iter().map(|c| c.description),
+            )),
+            Arc::new(BinaryArray::from_iter_values(cases.iter().map(|c| {
+                geometry_to_wkb(&c.geometry, Crs::Wgs84, Dimensions::Xy, |_| 0.0)
+            }))),
+        ],
+    )
+    .expect("the columns match the schema")
+}
+
+/// Write the edge cases as GeoParquet 1.1
+fn write_parquet(cases: &[EdgeCase], file: File) -> io::Result<()> {
+    let batch = to_batch(cases);
+    let encoder = GeoParquetEncoder::new(batch.schema_ref(), GeoParquetVersion::V1_1);
+    let mut statistics = encoder.new_statistics();
+    let batch = encoder.encode(batch, &mut statistics);
+    let mut writer = ArrowWriter::try_new(file, Arc::clone(encoder.schema()), None)?;
+    writer.write(&batch)?;
+    writer.append_key_value_metadata(encoder.metadata(&statistics));
+    writer.close()?;
+    Ok(())
+}
+
+/// Write the edge cases as CSV with a header, with the geometries as WKT
+fn write_csv(cases: &[EdgeCase], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "e_casekey,e_category,e_name,e_description,e_geometry")?;
+    for (key, case) in (1..).zip(cases) {
+        writeln!(
+            out,
+            "{key},{},{},\"{}\",\"{}\"",
+            case.category,
+            case.name,
+            case.description,
+            wkt(&case.geometry)
+        )?;
+    }
+    Ok(())
+}
+
+/// Return the WKT of `geometry` (the `Debug` format of geo types), with
+/// the empty point that WKB encodes as NaN coordinates
+fn wkt(geometry: &Geometry) -> String {
+    match geometry {
+        Geometry::Point(point) if point.x().is_nan() => "POINT EMPTY".to_string(),
+        geometry => format!("{geometry:?}"),
+    }
+}
@@ -0,0 +1,337 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `estimate` command: predict the size and cost of a run before it
+//!
+//! The row counts are exact. The sizes and the generation time are
+//! extrapolated from a sample of each table: its first rows are generated
+//! and written to Parquet in memory with each codec, timing the generation
+//! and the encoding with the codec of the run.
+
+use crate::object_store_writer::PART_SIZE;
+use crate::plan::GenerationPlan;
+use arrow::array::RecordBatch;
+use clap::{Args, ValueEnum};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde_json::json;
+use spatialbench::distribution::Distributions;
+use spatialbench::text::TextPool;
+use spatialbench_arrow::{Table, TableGenerator};
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Default number of rows of each table generated to estimate its size
+const DEFAULT_SAMPLE_ROWS: i64 = 50_000;
+
+/// Predict the row counts, sizes, generation time and object store requests
+/// of a run
+#[derive(Debug, Args)]
+pub struct EstimateArgs {
+    /// Scale factor of the run
+    #[arg(short, long, default_value_t = 1.)]
+    scale_factor: f64,
+
+    /// Seed of the run
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Which tables to estimate (default: all but the trajectory and road
+    /// tables)
+    ///
+    /// The zone table is downloaded rather than generated, so it can not be
+    /// estimated.
+    #[arg(short = 'T', long = "tables", value_delimiter = ',', value_parser = Table::from_str)]
+    tables: Option<Vec<Table>>,
+
+    /// Number of parts of the run, the files of each table but the vehicle,
+    /// driver and building tables which are not split
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(i32).range(1..))]
+    parts: i32,
+
+    /// Number of threads of the run, defaults to the number of CPUs
+    #[arg(short, long, default_value_t = num_cpus::get())]
+    num_threads: usize,
+
+    /// Parquet compression of the run, which the generation time is
+    /// estimated with
+    #[arg(short = 'c', long, default_value = "SNAPPY")]
+    parquet_compression: Compression,
+
+    /// Number of rows of each table to generate for the estimate
+    ///
+    /// More rows give steadier sizes and rates, at the cost of a slower
+    /// estimate.
+    #[arg(long, default_value_t = DEFAULT_SAMPLE_ROWS, value_parser = clap::value_parser!(i64).range(1..))]
+    sample_rows: i64,
+
+    /// Output format: text or json
+    #[arg(short, long, default_value = "text")]
+    format: EstimateFormat,
+}
+
+/// Formats to print the estimate in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum EstimateFormat {
+    Text,
+    Json,
+}
+
+/// Parquet codecs the sizes are estimated for
+fn codecs() -> [(&'static str, Compression); 5] {
+    [
+        ("uncompressed", Compression::UNCOMPRESSED),
+        ("snappy", Compression::SNAPPY),
+        ("lz4", Compression::LZ4_RAW),
+        ("gzip", Compression::GZIP(GzipLevel::default())),
+        ("zstd", Compression::ZSTD(ZstdLevel::default())),
+    ]
+}
+
+/// The estimate for a table
+#[derive(Debug)]
+struct TableEstimate {
+    table: Table,
+    rows: i64,
+    /// Parquet bytes of the table with each of [`codecs`]
+    bytes: Vec<(&'static str, u64)>,
+    /// Parquet bytes of the table with the codec of the run
+    run_bytes: u64,
+    /// Time to generate and encode the table on one thread
+    time: Duration,
+    /// Files the table is written to
+    files: u64,
+    /// Object store requests to upload the parts of the table
+    requests: u64,
+}
+
+impl EstimateArgs {
+    /// Print the estimate of each table and their total to stdout
+    pub fn run(&self) -> io::Result<()> {
+        if !(self.scale_factor.is_finite() && self.scale_factor > 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--scale-factor must be positive, got {}", self.scale_factor),
+            ));
+        }
+        let tables = self.tables.clone().unwrap_or_else(|| {
+            vec![
+                Table::Vehicle,
+                Table::Driver,
+                Table::Customer,
+                Table::Trip,
+                Table::Building,
+            ]
+        });
+        // as when generating, so they are not charged to the first table
+        Distributions::static_default();
+        TextPool::get_or_init_default();
+        let estimates = tables
+            .iter()
+            .map(|&table| self.estimate(table))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        // the tables are generated one after the other with all the threads
+        let threads = self.num_threads.max(1) as u32;
+        let time = estimates.iter().map(|e| e.time).sum::<Duration>() / threads;
+        let mut stdout = io::stdout().lock();
+        match self.format {
+            EstimateFormat::Json => {
+                let tables: Vec<_> = estimates
+                    .iter()
+                    .map(|e| {
+                        let bytes: serde_json::Map<_, _> = e
+                            .bytes
+                            .iter()
+                            .map(|&(codec, bytes)| (codec.to_string(), json!(bytes)))
+                            .collect();
+                        json!({
+                            "name": e.table.name(),
+                            "rows": e.rows,
+                            "parquet_bytes": bytes,
+                            "bytes": e.run_bytes,
+                            "files": e.files,
+                            "put_requests": e.requests,
+                            "generation_secs": e.time.as_secs_f64() / threads as f64,
+                        })
+                    })
+                    .collect();
+                let json = json!({
+                    "scale_factor": self.scale_factor,
+                    "parts": self.parts,
+                    "num_threads": threads,
+                    "compression": self.parquet_compression.to_string(),
+                    "tables": tables,
+                    "total": {
+                        "rows": estimates.iter().map(|e| e.rows).sum::<i64>(),
+                        "bytes": estimates.iter().map(|e| e.run_bytes).sum::<u64>(),
+                        "put_requests": estimates.iter().map(|e| e.requests).sum::<u64>(),
+                        "generation_secs": time.as_secs_f64(),
+                    },
+                });
+                serde_json::to_writer_pretty(&mut stdout, &json)?;
+                writeln!(stdout)?;
+            }
+            EstimateFormat::Text => {
+                write!(stdout, "{:<10} {:>15}", "table", "rows")?;
+                for (codec, _) in codecs() {
+                    write!(stdout, " {codec:>12}")?;
+                }
+                writeln!(stdout, " {:>12} {:>10}", "PUTs", "time")?;
+                for e in &estimates {
+                    write!(stdout, "{:<10} {:>15}", e.table.name(), e.rows)?;
+                    for (_, bytes) in &e.bytes {
+                        write!(stdout, " {:>12}", size(*bytes))?;
+                    }
+                    let time = e.time / threads;
+                    writeln!(stdout, " {:>12} {:>10}", e.requests, duration(time))?;
+                }
+                writeln!(
+                    stdout,
+                    "\nTotal: {} rows, {} as Parquet ({}), {} object store PUT requests, \
+                     about {} to generate with {threads} threads",
+                    estimates.iter().map(|e| e.rows).sum::<i64>(),
+                    size(estimates.iter().map(|e| e.run_bytes).sum()),
+                    self.parquet_compression,
+                    estimates.iter().map(|e| e.requests).sum::<u64>(),
+                    duration(time),
+                )?;
+            }
+        }
+        stdout.flush()
+    }
+
+    /// Estimate `table` from a sample of its first rows
+    fn estimate(&self, table: Table) -> io::Result<TableEstimate> {
+        let generator = TableGenerator::new(table, self.scale_factor).with_seed(self.seed);
+        let rows = generator.row_count();
+
+        // the first part of enough parts to have about `sample_rows` rows
+        let sample_parts = (rows / self.sample_rows).clamp(1, i32::MAX as i64) as i32;
+        let sample = generator.with_part(1, sample_parts);
+        let start = Instant::now();
+        let batches: Vec<_> = sample.batches().collect();
+        let generation = start.elapsed();
+        let sample_rows: usize = batches.iter().map(RecordBatch::num_rows).sum();
+        // rows of the table per row of the sample
+        let scale = rows as f64 / sample_rows.max(1) as f64;
+
+        let mut bytes = vec![];
+        for (codec, compression) in codecs() {
+            let (sample_bytes, _) = encode(&batches, compression)?;
+            bytes.push((codec, (sample_bytes as f64 * scale) as u64));
+        }
+        let (sample_bytes, encoding) = encode(&batches, self.parquet_compression)?;
+        let run_bytes = (sample_bytes as f64 * scale) as u64;
+        let time = (generation + encoding).mul_f64(scale);
+
+        // the tables that are not split are written to a single file
+        let files = match crate::Table::from_str(table.name()) {
+            Ok(table) if !GenerationPlan::partitioned_table(table) => 1,
+            _ => self.parts as u64,
+        };
+        let requests = put_requests(run_bytes / files) * files;
+        Ok(TableEstimate {
+            table,
+            rows,
+            files,
+            bytes,
+            run_bytes,
+            time,
+            requests,
+        })
+    }
+}
+
+/// Write `batches` to an in memory Parquet file, returning its size and the
+/// time it took
+fn encode(batches: &[RecordBatch], compression: Compression) -> io::Result<(usize, Duration)> {
+    let Some(first) = batches.first() else {
+        return Ok((0, Duration::ZERO));
+    };
+    let properties = WriterProperties::builder()
+        .set_compression(compression)
+        .build();
+    let start = Instant::now();
+    let mut writer = ArrowWriter::try_new(Vec::new(), first.schema(), Some(properties))
+        .map_err(io::Error::other)?;
+    for batch in batches {
+        writer.write(batch).map_err(io::Error::other)?;
+    }
+    let buffer = writer.into_inner().map_err(io::Error::other)?;
+    Ok((buffer.len(), start.elapsed()))
+}
+
+/// Return the number of requests to upload a file of `bytes` bytes
+///
+/// Files of up to one part are uploaded with a single `PUT`, larger files
+/// with a multipart upload: a request to start it, one per part and one to
+/// complete it, which S3 all bills as `PUT` requests.
+fn put_requests(bytes: u64) -> u64 {
+    let part = PART_SIZE as u64;
+    if bytes <= part {
+        1
+    } else {
+        bytes.div_ceil(part) + 2
+    }
+}
+
+/// Format a number of bytes with a binary suffix, e.g. `12.3 MiB`
+fn size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Format a duration as `HH:MM:SS`
+fn duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_requests() {
+        let part = PART_SIZE as u64;
+        assert_eq!(put_requests(0), 1);
+        assert_eq!(put_requests(part), 1);
+        // start, 2 parts and complete
+        assert_eq!(put_requests(part + 1), 4);
+        assert_eq!(put_requests(10 * part), 12);
+    }
+
+    #[test]
+    fn test_size() {
+        assert_eq!(size(512), "512 B");
+        assert_eq!(size(1536), "1.5 KiB");
+        assert_eq!(size(3 << 30), "3.0 GiB");
+    }
+}
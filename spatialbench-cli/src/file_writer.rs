@@ -0,0 +1,217 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! [`FileWriter`]: writes local files with write-behind buffering
+//!
+//! The writer task of [`generate_parquet`] (and of the other binary formats)
+//! assembles the encoded data and writes it on the same blocking thread, so
+//! with a plain [`std::io::BufWriter`] the next row group waits for the disk
+//! every time the buffer is flushed. [`FileWriter`] instead hands each full
+//! buffer to a background task that writes it with tokio's asynchronous file
+//! IO, so that the encoding and the disk IO overlap.
+//!
+//! [`generate_parquet`]: crate::parquet::generate_parquet
+
+use crate::parquet::IntoSize;
+use log::debug;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinHandle;
+
+/// Size of each buffer handed to the background task (32MB)
+const BUFFER_SIZE: usize = 32 * 1024 * 1024;
+
+/// Maximum number of full buffers waiting to be written before writes block
+const MAX_QUEUED_BUFFERS: usize = 2;
+
+/// Writes data to a local file from a blocking thread
+///
+/// Data is buffered in memory as it is written, and each full buffer of
+/// [`BUFFER_SIZE`] bytes is sent to a background task that writes it to the
+/// file. At most [`MAX_QUEUED_BUFFERS`] buffers are queued, so writes block
+/// (rather than use more memory) when the disk can't keep up.
+///
+/// Errors of the background task are reported by the next write or when
+/// the writer is finished (see [`IntoSize`]).
+pub struct FileWriter {
+    path: PathBuf,
+    /// The bytes written since the last buffer was sent
+    buffer: Vec<u8>,
+    /// Total number of bytes written
+    num_bytes: usize,
+    /// Sends full buffers to the task
+    tx: Sender<Vec<u8>>,
+    /// The background task writing the file, until it has failed
+    task: Option<JoinHandle<io::Result<()>>>,
+    /// Handle to the runtime to wait for the task from a blocking thread
+    handle: Handle,
+}
+
+impl FileWriter {
+    /// Create (or truncate) the file at `path`
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = std::fs::File::create(path)
+            .map_err(|err| io::Error::other(format!("Failed to create {path:?}: {err}")))?;
+        let (tx, rx) = mpsc::channel(MAX_QUEUED_BUFFERS);
+        let handle = Handle::current();
+        let task = handle.spawn(write_buffers(tokio::fs::File::from_std(file), rx));
+        Ok(Self {
+            path: path.to_path_buf(),
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+            num_bytes: 0,
+            tx,
+            task: Some(task),
+            handle,
+        })
+    }
+
+    /// Send the buffered data to the background task
+    ///
+    /// Blocks if the task already has [`MAX_QUEUED_BUFFERS`] buffers queued.
+    fn send_buffer(&mut self) -> io::Result<()> {
+        let buffer = std::mem::replace(&mut self.buffer, Vec::with_capacity(BUFFER_SIZE));
+        if self.tx.blocking_send(buffer).is_err() {
+            // the task exited early, so report its error
+            return Err(
+                match self.task.take().map(|task| self.handle.block_on(task)) {
+                    Some(Ok(Err(e))) => e,
+                    Some(Err(e)) => io::Error::other(format!("Write task panic: {e}")),
+                    _ => io::Error::other(format!(
+                        "Writing {} finished unexpectedly",
+                        self.path.display()
+                    )),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Write any remaining data and wait for the file to be written,
+    /// returning the total number of bytes written
+    ///
+    /// This blocks the current thread and so must be called from a blocking
+    /// thread (e.g. within `spawn_blocking`), not from an async task.
+    fn finish(mut self) -> io::Result<usize> {
+        if !self.buffer.is_empty() {
+            self.send_buffer()?;
+        }
+        let Self {
+            path,
+            num_bytes,
+            tx,
+            task,
+            handle,
+            ..
+        } = self;
+        // closing the channel signals the task to finish the file
+        drop(tx);
+        let task =
+            task.ok_or_else(|| io::Error::other(format!("Writing {} failed", path.display())))?;
+        handle
+            .block_on(task)
+            .map_err(|e| io::Error::other(format!("Write task panic: {e}")))??;
+        debug!("Wrote {num_bytes} bytes to {}", path.display());
+        Ok(num_bytes)
+    }
+}
+
+/// Writes the buffers received on `rx` to `file`, in order
+async fn write_buffers(mut file: tokio::fs::File, mut rx: Receiver<Vec<u8>>) -> io::Result<()> {
+    while let Some(buffer) = rx.recv().await {
+        file.write_all(&buffer).await?;
+    }
+    // tokio completes the last write in the background until flushed
+    file.flush().await
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut remaining = buf;
+        while !remaining.is_empty() {
+            let len = (BUFFER_SIZE - self.buffer.len()).min(remaining.len());
+            self.buffer.extend_from_slice(&remaining[..len]);
+            remaining = &remaining[len..];
+            if self.buffer.len() == BUFFER_SIZE {
+                self.send_buffer()?;
+            }
+        }
+        self.num_bytes += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        self.send_buffer()
+    }
+}
+
+impl IntoSize for FileWriter {
+    fn into_size(self) -> Result<usize, io::Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_file_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trip.parquet");
+        let data: Vec<u8> = (0..BUFFER_SIZE * 2 + 100)
+            .map(|i| (i % 251) as u8)
+            .collect();
+
+        let mut writer = FileWriter::create(&path).unwrap();
+        let captured_data = data.clone();
+        let size = tokio::task::spawn_blocking(move || {
+            // write in uneven pieces to span buffer boundaries
+            for chunk in captured_data.chunks(1_000_003) {
+                writer.write_all(chunk)?;
+            }
+            writer.flush()?;
+            writer.write_all(b"footer")?;
+            writer.into_size()
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(size, data.len() + 6);
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!(&written[..data.len()], data.as_slice());
+        assert_eq!(&written[data.len()..], b"footer");
+    }
+
+    #[tokio::test]
+    async fn test_file_writer_create_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = FileWriter::create(&dir.path().join("missing/trip.parquet"))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Failed to create"), "{err}");
+    }
+}
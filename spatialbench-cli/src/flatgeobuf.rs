@@ -0,0 +1,1120 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! FlatGeobuf output format
+//!
+//! Writes [FlatGeobuf] files with a packed Hilbert R-tree spatial index. The
+//! first geometry (WKB) column is the feature geometry and all other columns
+//! are written as feature properties (with any other geometry columns written
+//! as WKB `Binary` properties).
+//!
+//! The spatial index is written before the features and the features must be
+//! sorted in index order, so the encoded features are first spooled to a
+//! temporary file (in the system temporary directory, see [`env::temp_dir`])
+//! and copied to the output once all of them have been generated.
+//!
+//! [FlatGeobuf]: https://flatgeobuf.org
+//! [`env::temp_dir`]: std::env::temp_dir
+
+use crate::geoparquet::{wkb_ordinates, Ordinates};
+use crate::memory::{ChunkEstimate, Reservation};
+use crate::parquet::IntoSize;
+use crate::statistics::WriteStatistics;
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Float64Type, Int64Type, Schema};
+use flatbuffers::{FlatBufferBuilder, TableFinishedWIPOffset, WIPOffset};
+use futures::StreamExt;
+use geo::{Coord, Geometry, Polygon};
+use geozero::wkb::Wkb;
+use geozero::ToGeo;
+use log::debug;
+use spatialbench::spatial::{crs, Dimensions};
+use spatialbench_arrow::RecordBatchIterator;
+use std::cmp::Reverse;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+
+/// Magic bytes at the start of every FlatGeobuf (version 3) file
+const MAGIC: [u8; 8] = [b'f', b'g', b'b', 3, b'f', b'g', b'b', 0];
+
+/// Number of children of each node in the spatial index
+const INDEX_NODE_SIZE: u16 = 16;
+
+/// Size of an encoded spatial index node: a bounding box and an offset
+const NODE_ITEM_BYTES: usize = 40;
+
+/// Vtable slots of the FlatGeobuf flatbuffer tables, from `header.fbs` and
+/// `feature.fbs` in the FlatGeobuf specification
+mod slot {
+    use flatbuffers::VOffsetT;
+
+    const fn slot(field: VOffsetT) -> VOffsetT {
+        4 + 2 * field
+    }
+
+    pub const HEADER_NAME: VOffsetT = slot(0);
+    pub const HEADER_ENVELOPE: VOffsetT = slot(1);
+    pub const HEADER_GEOMETRY_TYPE: VOffsetT = slot(2);
+    pub const HEADER_HAS_Z: VOffsetT = slot(3);
+    pub const HEADER_HAS_M: VOffsetT = slot(4);
+    pub const HEADER_COLUMNS: VOffsetT = slot(7);
+    pub const HEADER_FEATURES_COUNT: VOffsetT = slot(8);
+    pub const HEADER_INDEX_NODE_SIZE: VOffsetT = slot(9);
+    pub const HEADER_CRS: VOffsetT = slot(10);
+
+    pub const CRS_ORG: VOffsetT = slot(0);
+    pub const CRS_CODE: VOffsetT = slot(1);
+
+    pub const COLUMN_NAME: VOffsetT = slot(0);
+    pub const COLUMN_TYPE: VOffsetT = slot(1);
+    pub const COLUMN_NULLABLE: VOffsetT = slot(7);
+
+    pub const FEATURE_GEOMETRY: VOffsetT = slot(0);
+    pub const FEATURE_PROPERTIES: VOffsetT = slot(1);
+
+    pub const GEOMETRY_ENDS: VOffsetT = slot(0);
+    pub const GEOMETRY_XY: VOffsetT = slot(1);
+    pub const GEOMETRY_Z: VOffsetT = slot(2);
+    pub const GEOMETRY_M: VOffsetT = slot(3);
+    pub const GEOMETRY_TYPE: VOffsetT = slot(6);
+    pub const GEOMETRY_PARTS: VOffsetT = slot(7);
+}
+
+/// FlatGeobuf `GeometryType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum GeometryType {
+    Unknown = 0,
+    Point = 1,
+    LineString = 2,
+    Polygon = 3,
+    MultiPoint = 4,
+    MultiLineString = 5,
+    MultiPolygon = 6,
+    GeometryCollection = 7,
+}
+
+/// FlatGeobuf `ColumnType` of the properties that are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum ColumnType {
+    Bool = 2,
+    Long = 7,
+    Double = 10,
+    String = 11,
+    DateTime = 13,
+    Binary = 14,
+}
+
+impl ColumnType {
+    fn new(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Boolean => Self::Bool,
+            data_type if data_type.is_integer() => Self::Long,
+            data_type if data_type.is_numeric() => Self::Double,
+            DataType::Timestamp(_, _) | DataType::Date32 | DataType::Date64 => Self::DateTime,
+            DataType::Binary | DataType::LargeBinary | DataType::BinaryView => Self::Binary,
+            _ => Self::String,
+        }
+    }
+
+    /// The Arrow type that columns are cast to before encoding
+    fn arrow_type(&self) -> DataType {
+        match self {
+            Self::Bool => DataType::Boolean,
+            Self::Long => DataType::Int64,
+            Self::Double => DataType::Float64,
+            Self::String | Self::DateTime => DataType::Utf8,
+            Self::Binary => DataType::Binary,
+        }
+    }
+}
+
+/// A property column
+#[derive(Debug)]
+struct Column {
+    name: String,
+    column_type: ColumnType,
+    nullable: bool,
+    /// Index of the column in the input batches
+    index: usize,
+}
+
+/// How the columns of the input batches are written
+#[derive(Debug)]
+struct FeatureSchema {
+    /// Index of the feature geometry column in the input batches, if any
+    geometry: Option<usize>,
+    properties: Vec<Column>,
+}
+
+impl FeatureSchema {
+    fn new(schema: &Schema) -> Self {
+        let mut geometry = None;
+        let mut properties = vec![];
+        for (index, field) in schema.fields().iter().enumerate() {
+            let column_type = ColumnType::new(field.data_type());
+            if geometry.is_none() && column_type == ColumnType::Binary {
+                geometry = Some(index);
+                continue;
+            }
+            properties.push(Column {
+                name: field.name().clone(),
+                column_type,
+                nullable: field.is_nullable(),
+                index,
+            });
+        }
+        Self {
+            geometry,
+            properties,
+        }
+    }
+}
+
+/// A bounding box: `[min_x, min_y, max_x, max_y]`
+type Bbox = [f64; 4];
+
+const EMPTY_BBOX: Bbox = [
+    f64::INFINITY,
+    f64::INFINITY,
+    f64::NEG_INFINITY,
+    f64::NEG_INFINITY,
+];
+
+fn expand(bbox: &mut Bbox, other: &Bbox) {
+    bbox[0] = bbox[0].min(other[0]);
+    bbox[1] = bbox[1].min(other[1]);
+    bbox[2] = bbox[2].max(other[2]);
+    bbox[3] = bbox[3].max(other[3]);
+}
+
+/// An encoded feature
+#[derive(Debug, Clone, Copy)]
+struct Item {
+    bbox: Bbox,
+    /// Offset of the feature in the spooled features
+    offset: u64,
+    /// Length of the feature in bytes
+    len: u32,
+    /// Hilbert value of the center of `bbox`, used to sort the features
+    hilbert: u32,
+}
+
+/// The encoded features of one [`RecordBatchIterator`]
+#[derive(Debug, Default)]
+struct EncodedFeatures {
+    data: Vec<u8>,
+    /// Features in `data`, with offsets relative to the start of `data`
+    items: Vec<Item>,
+    /// Bitmask of the [`GeometryType`]s in `data`
+    geometry_types: u8,
+    /// Whether the geometries in `data` have Z and M values
+    has_z: bool,
+    has_m: bool,
+}
+
+impl EncodedFeatures {
+    /// Return the memory used by the encoded features
+    fn memory_size(&self) -> usize {
+        self.data.capacity() + self.items.capacity() * std::mem::size_of::<Item>()
+    }
+}
+
+/// Converts a set of RecordBatchIterators into a FlatGeobuf file named `name`
+///
+/// Uses num_threads to encode the features of the iterators in parallel.
+/// With `--memory-limit`, the features of each iterator reserve their memory
+/// until they are spooled (see [`crate::memory`]).
+pub async fn generate_flatgeobuf<W: Write + Send + IntoSize + 'static, I>(
+    writer: W,
+    name: &str,
+    iter_iter: I,
+    num_threads: usize,
+) -> Result<(), io::Error>
+where
+    I: Iterator<Item: RecordBatchIterator> + 'static,
+{
+    debug!("Generating FlatGeobuf with {num_threads} threads");
+    let mut iter_iter = iter_iter.peekable();
+
+    // get schema from the first iterator
+    let Some(first_iter) = iter_iter.peek() else {
+        return Ok(()); // no data shrug
+    };
+    let schema = std::sync::Arc::new(FeatureSchema::new(first_iter.schema()));
+
+    // create a stream that encodes the features of each iterator
+    let estimate = std::sync::Arc::new(ChunkEstimate::new());
+    let mut features_stream = futures::stream::iter(iter_iter)
+        .map(async |iter| {
+            let reservation = estimate.reserve().await;
+            let estimate = std::sync::Arc::clone(&estimate);
+            let schema = std::sync::Arc::clone(&schema);
+            // run on a separate thread
+            tokio::task::spawn(async move {
+                let features = encode_features(&schema, iter);
+                if let Ok(features) = &features {
+                    estimate.update(features.memory_size());
+                }
+                (features, reservation)
+            })
+            .await
+            .expect("Inner task panicked")
+        })
+        .buffered(num_threads); // encode in parallel
+
+    // A blocking task that spools the features to a temporary file and then
+    // writes the output, as it does file IO
+    let (tx, mut rx) =
+        tokio::sync::mpsc::channel::<(io::Result<EncodedFeatures>, Reservation)>(num_threads);
+    let name = name.to_string();
+    let writer_schema = std::sync::Arc::clone(&schema);
+    let writer_task = tokio::task::spawn_blocking(move || {
+        let mut statistics = WriteStatistics::new("parts");
+        let mut spool = BufWriter::new(tempfile::tempfile()?);
+        let mut spool_len = 0;
+        let mut items = vec![];
+        let mut geometry_types = 0;
+        let (mut has_z, mut has_m) = (false, false);
+        while let Some((features, reservation)) = rx.blocking_recv() {
+            let features = features?;
+            spool.write_all(&features.data)?;
+            items.extend(features.items.into_iter().map(|mut item| {
+                item.offset += spool_len;
+                item
+            }));
+            spool_len += features.data.len() as u64;
+            geometry_types |= features.geometry_types;
+            has_z |= features.has_z;
+            has_m |= features.has_m;
+            statistics.increment_chunks(1);
+            drop(reservation);
+        }
+        let spool = spool.into_inner().map_err(|e| e.into_error())?;
+        let writer = write_file(
+            writer,
+            &name,
+            &writer_schema,
+            spool,
+            items,
+            geometry_types,
+            Dimensions::new(has_z, has_m),
+        )?;
+        statistics.increment_bytes(writer.into_size()?);
+        Ok(()) as Result<(), io::Error>
+    });
+
+    // now, drive the input stream and send results to the writer task
+    while let Some(features) = features_stream.next().await {
+        if let Err(e) = tx.send(features).await {
+            debug!("Error sending features to writer: {e}");
+            break; // stop early
+        }
+    }
+    // signal the writer task that we are done
+    drop(tx);
+
+    // Wait for the writer task to finish
+    writer_task.await??;
+    Ok(())
+}
+
+/// Encode all the rows of `iter` as size prefixed FlatGeobuf features
+fn encode_features<I: RecordBatchIterator>(
+    schema: &FeatureSchema,
+    iter: I,
+) -> io::Result<EncodedFeatures> {
+    let mut encoded = EncodedFeatures::default();
+    let mut builder = FlatBufferBuilder::with_capacity(1024);
+    let mut properties = vec![];
+    for batch in iter {
+        let columns = cast_columns(schema, &batch)?;
+        for row in 0..batch.num_rows() {
+            builder.reset();
+            let mut bbox = EMPTY_BBOX;
+            let geometry = match schema.geometry {
+                Some(index) => {
+                    let geometry = read_geometry(batch.column(index).as_ref(), row)?;
+                    geometry.map(|(geometry, ordinates)| {
+                        let mut ordinates = OrdinateValues::new(&ordinates);
+                        let (offset, geometry_type) =
+                            encode_geometry(&mut builder, &geometry, &mut ordinates, &mut bbox);
+                        encoded.geometry_types |= 1 << geometry_type as u8;
+                        encoded.has_z |= ordinates.z.is_some();
+                        encoded.has_m |= ordinates.m.is_some();
+                        offset
+                    })
+                }
+                None => None,
+            };
+
+            properties.clear();
+            encode_properties(&mut properties, schema, &columns, row);
+            let properties = builder.create_vector(&properties);
+
+            let start = builder.start_table();
+            if let Some(geometry) = geometry {
+                builder.push_slot_always(slot::FEATURE_GEOMETRY, geometry);
+            }
+            builder.push_slot_always(slot::FEATURE_PROPERTIES, properties);
+            let feature = builder.end_table(start);
+            builder.finish_size_prefixed(feature, None);
+
+            let data = builder.finished_data();
+            encoded.items.push(Item {
+                bbox,
+                offset: encoded.data.len() as u64,
+                len: data.len() as u32,
+                hilbert: 0,
+            });
+            encoded.data.extend_from_slice(data);
+        }
+    }
+    Ok(encoded)
+}
+
+/// Cast the property columns of `batch` to the types they are encoded from
+fn cast_columns(schema: &FeatureSchema, batch: &RecordBatch) -> io::Result<Vec<ArrayRef>> {
+    schema
+        .properties
+        .iter()
+        .map(|column| {
+            let array = batch.column(column.index);
+            match column.column_type {
+                // geometries can be written as is
+                ColumnType::Binary => Ok(binary_array(array.as_ref())),
+                column_type => cast(array, &column_type.arrow_type()).map_err(io::Error::other),
+            }
+        })
+        .collect()
+}
+
+/// Return `array` as a [`DataType::Binary`] array
+fn binary_array(array: &dyn Array) -> ArrayRef {
+    cast(array, &DataType::Binary).expect("binary types can be cast to binary")
+}
+
+/// Decode the WKB geometry at `row` of `array`, and the Z and M values of
+/// its coordinates if it has any
+fn read_geometry(array: &dyn Array, row: usize) -> io::Result<Option<(Geometry, Ordinates)>> {
+    if array.is_null(row) {
+        return Ok(None);
+    }
+    let wkb = match array.data_type() {
+        DataType::Binary => array.as_binary::<i32>().value(row),
+        DataType::LargeBinary => array.as_binary::<i64>().value(row),
+        _ => array.as_binary_view().value(row),
+    };
+    let geometry = Wkb(wkb)
+        .to_geo()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Some((geometry, wkb_ordinates(wkb).unwrap_or_default())))
+}
+
+/// The Z and M values of the coordinates of a geometry, taken by the parts
+/// of the geometry in the order they are encoded
+struct OrdinateValues<'a> {
+    z: Option<&'a [f64]>,
+    m: Option<&'a [f64]>,
+}
+
+impl<'a> OrdinateValues<'a> {
+    fn new(ordinates: &'a Ordinates) -> Self {
+        Self {
+            z: ordinates.z.as_deref(),
+            m: ordinates.m.as_deref(),
+        }
+    }
+
+    /// Take the Z and M values of the next `n` coordinates
+    fn take(&mut self, n: usize) -> (Option<&'a [f64]>, Option<&'a [f64]>) {
+        let take = |values: &mut Option<&'a [f64]>| {
+            let values = values.as_mut()?;
+            let (taken, rest) = values.split_at(n.min(values.len()));
+            *values = rest;
+            Some(taken)
+        };
+        (take(&mut self.z), take(&mut self.m))
+    }
+}
+
+/// Encode the properties at `row` of `columns` into `buffer`
+///
+/// Each non null value is encoded as its column index (`u16`) followed by
+/// the value, with variable length values prefixed by their length (`u32`).
+fn encode_properties(
+    buffer: &mut Vec<u8>,
+    schema: &FeatureSchema,
+    columns: &[ArrayRef],
+    row: usize,
+) {
+    for (index, (column, array)) in schema.properties.iter().zip(columns).enumerate() {
+        if array.is_null(row) {
+            continue;
+        }
+        buffer.extend_from_slice(&(index as u16).to_le_bytes());
+        match column.column_type {
+            ColumnType::Bool => buffer.push(array.as_boolean().value(row) as u8),
+            ColumnType::Long => {
+                let value = array.as_primitive::<Int64Type>().value(row);
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+            ColumnType::Double => {
+                let value = array.as_primitive::<Float64Type>().value(row);
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+            ColumnType::String | ColumnType::DateTime => {
+                let value = array.as_string::<i32>().value(row);
+                buffer.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                buffer.extend_from_slice(value.as_bytes());
+            }
+            ColumnType::Binary => {
+                let value = array.as_binary::<i32>().value(row);
+                buffer.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                buffer.extend_from_slice(value);
+            }
+        }
+    }
+}
+
+/// Encode a geometry as a FlatGeobuf `Geometry` table, expanding `bbox`
+/// to include it
+fn encode_geometry(
+    builder: &mut FlatBufferBuilder,
+    geometry: &Geometry,
+    ordinates: &mut OrdinateValues,
+    bbox: &mut Bbox,
+) -> (WIPOffset<TableFinishedWIPOffset>, GeometryType) {
+    let mut xy = vec![];
+    let mut ends = vec![];
+    let mut parts = vec![];
+    let geometry_type = match geometry {
+        Geometry::Point(point) => {
+            push_coords(&mut xy, [point.0], bbox);
+            GeometryType::Point
+        }
+        Geometry::Line(line) => {
+            push_coords(&mut xy, [line.start, line.end], bbox);
+            GeometryType::LineString
+        }
+        Geometry::LineString(line) => {
+            push_coords(&mut xy, line.coords().copied(), bbox);
+            GeometryType::LineString
+        }
+        Geometry::Polygon(polygon) => {
+            push_polygon(&mut xy, &mut ends, polygon, bbox);
+            GeometryType::Polygon
+        }
+        Geometry::Rect(rect) => {
+            push_polygon(&mut xy, &mut ends, &rect.to_polygon(), bbox);
+            GeometryType::Polygon
+        }
+        Geometry::Triangle(triangle) => {
+            push_polygon(&mut xy, &mut ends, &triangle.to_polygon(), bbox);
+            GeometryType::Polygon
+        }
+        Geometry::MultiPoint(points) => {
+            push_coords(&mut xy, points.iter().map(|point| point.0), bbox);
+            GeometryType::MultiPoint
+        }
+        Geometry::MultiLineString(lines) => {
+            for line in lines {
+                push_coords(&mut xy, line.coords().copied(), bbox);
+                ends.push((xy.len() / 2) as u32);
+            }
+            GeometryType::MultiLineString
+        }
+        Geometry::MultiPolygon(polygons) => {
+            for polygon in polygons {
+                let mut xy = vec![];
+                let mut ends = vec![];
+                push_polygon(&mut xy, &mut ends, polygon, bbox);
+                let (z, m) = ordinates.take(xy.len() / 2);
+                parts.push(finish_geometry(
+                    builder,
+                    GeometryType::Polygon,
+                    &xy,
+                    z,
+                    m,
+                    &ends,
+                    &[],
+                ));
+            }
+            GeometryType::MultiPolygon
+        }
+        Geometry::GeometryCollection(geometries) => {
+            for geometry in geometries {
+                parts.push(encode_geometry(builder, geometry, ordinates, bbox).0);
+            }
+            GeometryType::GeometryCollection
+        }
+    };
+    let (z, m) = ordinates.take(xy.len() / 2);
+    let offset = finish_geometry(builder, geometry_type, &xy, z, m, &ends, &parts);
+    (offset, geometry_type)
+}
+
+fn push_coords(xy: &mut Vec<f64>, coords: impl IntoIterator<Item = Coord>, bbox: &mut Bbox) {
+    for coord in coords {
+        xy.push(coord.x);
+        xy.push(coord.y);
+        expand(bbox, &[coord.x, coord.y, coord.x, coord.y]);
+    }
+}
+
+fn push_polygon(xy: &mut Vec<f64>, ends: &mut Vec<u32>, polygon: &Polygon, bbox: &mut Bbox) {
+    for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+        push_coords(xy, ring.coords().copied(), bbox);
+        ends.push((xy.len() / 2) as u32);
+    }
+}
+
+fn finish_geometry(
+    builder: &mut FlatBufferBuilder,
+    geometry_type: GeometryType,
+    xy: &[f64],
+    z: Option<&[f64]>,
+    m: Option<&[f64]>,
+    ends: &[u32],
+    parts: &[WIPOffset<TableFinishedWIPOffset>],
+) -> WIPOffset<TableFinishedWIPOffset> {
+    // a single ring or line does not need its end
+    let ends = (ends.len() > 1).then(|| builder.create_vector(ends));
+    let xy = (!xy.is_empty()).then(|| builder.create_vector(xy));
+    let z = z
+        .filter(|z| !z.is_empty())
+        .map(|z| builder.create_vector(z));
+    let m = m
+        .filter(|m| !m.is_empty())
+        .map(|m| builder.create_vector(m));
+    let parts = (!parts.is_empty()).then(|| builder.create_vector(parts));
+
+    let start = builder.start_table();
+    if let Some(ends) = ends {
+        builder.push_slot_always(slot::GEOMETRY_ENDS, ends);
+    }
+    if let Some(xy) = xy {
+        builder.push_slot_always(slot::GEOMETRY_XY, xy);
+    }
+    if let Some(z) = z {
+        builder.push_slot_always(slot::GEOMETRY_Z, z);
+    }
+    if let Some(m) = m {
+        builder.push_slot_always(slot::GEOMETRY_M, m);
+    }
+    if let Some(parts) = parts {
+        builder.push_slot_always(slot::GEOMETRY_PARTS, parts);
+    }
+    builder.push_slot(slot::GEOMETRY_TYPE, geometry_type as u8, 0);
+    builder.end_table(start)
+}
+
+/// Write the FlatGeobuf file: the header, the spatial index and then the
+/// features spooled to `spool` in index order
+fn write_file<W: Write>(
+    mut writer: W,
+    name: &str,
+    schema: &FeatureSchema,
+    mut spool: File,
+    mut items: Vec<Item>,
+    geometry_types: u8,
+    dimensions: Dimensions,
+) -> io::Result<W> {
+    let envelope = items.iter().fold(EMPTY_BBOX, |mut envelope, item| {
+        expand(&mut envelope, &item.bbox);
+        envelope
+    });
+    // the index requires every feature to have a geometry
+    let indexed = schema.geometry.is_some()
+        && !items.is_empty()
+        && items.iter().all(|item| item.bbox[0] <= item.bbox[2]);
+    let geometry_type = match geometry_types.count_ones() {
+        1 => geometry_type_from_bit(geometry_types.trailing_zeros()),
+        _ => GeometryType::Unknown,
+    };
+
+    writer.write_all(&MAGIC)?;
+    let header = encode_header(
+        name,
+        schema,
+        geometry_type,
+        dimensions,
+        indexed.then_some(envelope),
+        items.len() as u64,
+        if indexed { INDEX_NODE_SIZE } else { 0 },
+    );
+    writer.write_all(&header)?;
+
+    if !indexed {
+        spool.seek(SeekFrom::Start(0))?;
+        io::copy(&mut spool, &mut writer)?;
+        return Ok(writer);
+    }
+
+    for item in items.iter_mut() {
+        item.hilbert = hilbert_bbox(&item.bbox, &envelope);
+    }
+    items.sort_by_key(|item| Reverse(item.hilbert));
+    for node in build_index(&items, INDEX_NODE_SIZE as usize) {
+        let (bbox, offset) = node;
+        for value in bbox {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&offset.to_le_bytes())?;
+    }
+
+    let mut buffer = vec![];
+    for item in &items {
+        buffer.resize(item.len as usize, 0);
+        spool.seek(SeekFrom::Start(item.offset))?;
+        spool.read_exact(&mut buffer)?;
+        writer.write_all(&buffer)?;
+    }
+    Ok(writer)
+}
+
+fn geometry_type_from_bit(bit: u32) -> GeometryType {
+    match bit {
+        1 => GeometryType::Point,
+        2 => GeometryType::LineString,
+        3 => GeometryType::Polygon,
+        4 => GeometryType::MultiPoint,
+        5 => GeometryType::MultiLineString,
+        6 => GeometryType::MultiPolygon,
+        7 => GeometryType::GeometryCollection,
+        _ => GeometryType::Unknown,
+    }
+}
+
+/// Encode the size prefixed FlatGeobuf `Header` table
+fn encode_header(
+    name: &str,
+    schema: &FeatureSchema,
+    geometry_type: GeometryType,
+    dimensions: Dimensions,
+    envelope: Option<Bbox>,
+    features_count: u64,
+    index_node_size: u16,
+) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::with_capacity(1024);
+    let columns: Vec<_> = schema
+        .properties
+        .iter()
+        .map(|column| {
+            let name = builder.create_string(&column.name);
+            let start = builder.start_table();
+            builder.push_slot_always(slot::COLUMN_NAME, name);
+            builder.push_slot(slot::COLUMN_TYPE, column.column_type as u8, 0);
+            builder.push_slot(slot::COLUMN_NULLABLE, column.nullable, true);
+            builder.end_table(start)
+        })
+        .collect();
+    let columns = builder.create_vector(&columns);
+    let name = builder.create_string(name);
+    let envelope = envelope.map(|envelope| builder.create_vector(&envelope));
+    // generated coordinates are WGS84 longitude / latitude, unless projected
+    let org = builder.create_string("EPSG");
+    let start = builder.start_table();
+    builder.push_slot_always(slot::CRS_ORG, org);
+    builder.push_slot(slot::CRS_CODE, crs::crs().epsg_code() as i32, 0);
+    let crs = builder.end_table(start);
+
+    let start = builder.start_table();
+    builder.push_slot_always(slot::HEADER_NAME, name);
+    if let Some(envelope) = envelope {
+        builder.push_slot_always(slot::HEADER_ENVELOPE, envelope);
+    }
+    builder.push_slot(slot::HEADER_GEOMETRY_TYPE, geometry_type as u8, 0);
+    builder.push_slot(slot::HEADER_HAS_Z, dimensions.has_z(), false);
+    builder.push_slot(slot::HEADER_HAS_M, dimensions.has_m(), false);
+    builder.push_slot_always(slot::HEADER_COLUMNS, columns);
+    builder.push_slot(slot::HEADER_FEATURES_COUNT, features_count, 0);
+    builder.push_slot(slot::HEADER_INDEX_NODE_SIZE, index_node_size, 16);
+    if schema.geometry.is_some() {
+        builder.push_slot_always(slot::HEADER_CRS, crs);
+    }
+    let header = builder.end_table(start);
+    builder.finish_size_prefixed(header, None);
+    builder.finished_data().to_vec()
+}
+
+/// Build the packed R-tree over `items` (which must already be sorted)
+///
+/// Returns the nodes in the order they are written: the root first and the
+/// leaves (one per item, in order) last. Each node is its bounding box and
+/// the index of its first child node, or for leaves the byte offset of the
+/// feature from the start of the features.
+fn build_index(items: &[Item], node_size: usize) -> Vec<(Bbox, u64)> {
+    // number of nodes in each level, from the leaves up to the root
+    let mut level_sizes = vec![items.len()];
+    let mut n = items.len();
+    loop {
+        n = n.div_ceil(node_size);
+        level_sizes.push(n);
+        if n == 1 {
+            break;
+        }
+    }
+    let num_nodes: usize = level_sizes.iter().sum();
+
+    // start of each level within the nodes, the root level being first
+    let mut level_starts = Vec::with_capacity(level_sizes.len());
+    let mut end = num_nodes;
+    for size in &level_sizes {
+        end -= size;
+        level_starts.push(end);
+    }
+
+    let mut nodes = vec![(EMPTY_BBOX, 0u64); num_nodes];
+    let mut offset = 0;
+    for (node, item) in nodes[level_starts[0]..].iter_mut().zip(items) {
+        *node = (item.bbox, offset);
+        offset += item.len as u64;
+    }
+    for level in 0..level_sizes.len() - 1 {
+        let children = level_starts[level]..level_starts[level] + level_sizes[level];
+        for (parent, first_child) in children.clone().step_by(node_size).enumerate() {
+            let last_child = (first_child + node_size).min(children.end);
+            let mut bbox = EMPTY_BBOX;
+            for (child_bbox, _) in &nodes[first_child..last_child] {
+                expand(&mut bbox, child_bbox);
+            }
+            nodes[level_starts[level + 1] + parent] = (bbox, first_child as u64);
+        }
+    }
+    debug_assert_eq!(
+        nodes.len() * NODE_ITEM_BYTES,
+        index_size(items.len(), node_size)
+    );
+    nodes
+}
+
+/// Size in bytes of the packed R-tree for `num_items` items
+fn index_size(num_items: usize, node_size: usize) -> usize {
+    let mut n = num_items;
+    let mut num_nodes = n;
+    loop {
+        n = n.div_ceil(node_size);
+        num_nodes += n;
+        if n == 1 {
+            break;
+        }
+    }
+    num_nodes * NODE_ITEM_BYTES
+}
+
+/// Hilbert value of the center of `bbox` within `extent`
+pub fn hilbert_bbox(bbox: &Bbox, extent: &Bbox) -> u32 {
+    const HILBERT_MAX: f64 = ((1 << 16) - 1) as f64;
+    let scale = |value: f64, min: f64, max: f64| {
+        let width = max - min;
+        if width > 0.0 {
+            (HILBERT_MAX * (value - min) / width).floor() as u32
+        } else {
+            0
+        }
+    };
+    let x = scale((bbox[0] + bbox[2]) / 2.0, extent[0], extent[2]);
+    let y = scale((bbox[1] + bbox[3]) / 2.0, extent[1], extent[3]);
+    hilbert(x, y)
+}
+
+/// Position of the 16 bit coordinates (`x`, `y`) along a Hilbert curve
+///
+/// Based on <https://github.com/rawrunprotected/hilbert_curves> (public
+/// domain), as used by the reference FlatGeobuf implementations
+fn hilbert(x: u32, y: u32) -> u32 {
+    let mut a = x ^ y;
+    let mut b = 0xFFFF ^ a;
+    let mut c = 0xFFFF ^ (x | y);
+    let mut d = x & (y ^ 0xFFFF);
+
+    let mut aa = a | (b >> 1);
+    let mut bb = (a >> 1) ^ a;
+    let mut cc = ((c >> 1) ^ (b & (d >> 1))) ^ c;
+    let mut dd = ((a & (c >> 1)) ^ (d >> 1)) ^ d;
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    aa = (a & (a >> 2)) ^ (b & (b >> 2));
+    bb = (a & (b >> 2)) ^ (b & ((a ^ b) >> 2));
+    cc ^= (a & (c >> 2)) ^ (b & (d >> 2));
+    dd ^= (b & (c >> 2)) ^ ((a ^ b) & (d >> 2));
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    aa = (a & (a >> 4)) ^ (b & (b >> 4));
+    bb = (a & (b >> 4)) ^ (b & ((a ^ b) >> 4));
+    cc ^= (a & (c >> 4)) ^ (b & (d >> 4));
+    dd ^= (b & (c >> 4)) ^ ((a ^ b) & (d >> 4));
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    cc ^= (a & (c >> 8)) ^ (b & (d >> 8));
+    dd ^= (b & (c >> 8)) ^ ((a ^ b) & (d >> 8));
+
+    a = cc ^ (cc >> 1);
+    b = dd ^ (dd >> 1);
+
+    let mut i0 = x ^ y;
+    let mut i1 = b | (0xFFFF ^ (i0 | a));
+
+    i0 = (i0 | (i0 << 8)) & 0x00FF00FF;
+    i0 = (i0 | (i0 << 4)) & 0x0F0F0F0F;
+    i0 = (i0 | (i0 << 2)) & 0x33333333;
+    i0 = (i0 | (i0 << 1)) & 0x55555555;
+
+    i1 = (i1 | (i1 << 8)) & 0x00FF00FF;
+    i1 = (i1 | (i1 << 4)) & 0x0F0F0F0F;
+    i1 = (i1 | (i1 << 2)) & 0x33333333;
+    i1 = (i1 | (i1 << 1)) & 0x55555555;
+
+    (i1 << 1) | i0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spatialbench::generators::{TrajectoryGenerator, TripGenerator, VehicleGenerator};
+    use spatialbench_arrow::{TrajectoryArrow, TripArrow, VehicleArrow};
+    use std::sync::{Arc, Mutex};
+
+    /// A writer that collects the output in memory
+    #[derive(Clone, Default)]
+    struct MemoryWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for MemoryWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl IntoSize for MemoryWriter {
+        fn into_size(self) -> Result<usize, io::Error> {
+            Ok(self.0.lock().unwrap().len())
+        }
+    }
+
+    /// Minimal flatbuffer table reader, to check the output without a
+    /// FlatGeobuf reader
+    struct Table<'a> {
+        buf: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> Table<'a> {
+        /// The root table of the size prefixed flatbuffer at `pos`
+        fn size_prefixed_root(buf: &'a [u8], pos: usize) -> Self {
+            let root = pos + 4;
+            Self {
+                buf,
+                pos: root + read_u32(buf, root) as usize,
+            }
+        }
+
+        fn field(&self, slot: u16) -> Option<usize> {
+            let soffset = i32::from_le_bytes(self.buf[self.pos..self.pos + 4].try_into().unwrap());
+            let vtable = (self.pos as i64 - soffset as i64) as usize;
+            let vtable_len = read_u16(self.buf, vtable);
+            if slot >= vtable_len {
+                return None;
+            }
+            match read_u16(self.buf, vtable + slot as usize) {
+                0 => None,
+                offset => Some(self.pos + offset as usize),
+            }
+        }
+
+        fn u64(&self, slot: u16, default: u64) -> u64 {
+            self.field(slot).map_or(default, |pos| {
+                u64::from_le_bytes(self.buf[pos..pos + 8].try_into().unwrap())
+            })
+        }
+
+        fn u16(&self, slot: u16, default: u16) -> u16 {
+            self.field(slot)
+                .map_or(default, |pos| read_u16(self.buf, pos))
+        }
+
+        fn u8(&self, slot: u16, default: u8) -> u8 {
+            self.field(slot).map_or(default, |pos| self.buf[pos])
+        }
+
+        /// Return the start and length of the vector in `slot`
+        fn vector(&self, slot: u16) -> Option<(usize, usize)> {
+            let pos = self.field(slot)?;
+            let vector = pos + read_u32(self.buf, pos) as usize;
+            Some((vector + 4, read_u32(self.buf, vector) as usize))
+        }
+    }
+
+    fn read_u32(buf: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap())
+    }
+
+    fn read_u16(buf: &[u8], pos: usize) -> u16 {
+        u16::from_le_bytes(buf[pos..pos + 2].try_into().unwrap())
+    }
+
+    async fn generate<I>(iter_iter: I) -> Vec<u8>
+    where
+        I: Iterator<Item: RecordBatchIterator> + 'static,
+    {
+        let writer = MemoryWriter::default();
+        generate_flatgeobuf(writer.clone(), "test", iter_iter, 2)
+            .await
+            .unwrap();
+        let output = writer.0.lock().unwrap().clone();
+        output
+    }
+
+    #[tokio::test]
+    async fn test_write_with_index() {
+        let sources = (1..=3).map(|part| TripArrow::new(TripGenerator::new(0.001, part, 3)));
+        let output = generate(sources).await;
+        assert_eq!(output[..8], MAGIC);
+
+        let header = Table::size_prefixed_root(&output, 8);
+        let features_count = header.u64(slot::HEADER_FEATURES_COUNT, 0) as usize;
+        assert_eq!(
+            features_count as i64,
+            TripGenerator::calculate_row_count(0.001, 1, 1)
+        );
+        assert_eq!(
+            header.u16(slot::HEADER_INDEX_NODE_SIZE, 16),
+            INDEX_NODE_SIZE
+        );
+        assert_eq!(
+            header.u8(slot::HEADER_GEOMETRY_TYPE, 0),
+            GeometryType::Point as u8
+        );
+        // all columns but the pickup location are properties
+        assert_eq!(header.vector(slot::HEADER_COLUMNS).unwrap().1, 11);
+
+        // the leaves of the index point to each feature, in order
+        let index_start = 12 + read_u32(&output, 8) as usize;
+        let index_size = index_size(features_count, INDEX_NODE_SIZE as usize);
+        let features_start = index_start + index_size;
+        let leaves_start = features_start - features_count * NODE_ITEM_BYTES;
+        let mut feature_offset = 0;
+        for leaf in output[leaves_start..features_start].chunks(NODE_ITEM_BYTES) {
+            let offset = u64::from_le_bytes(leaf[32..].try_into().unwrap());
+            assert_eq!(offset, feature_offset as u64);
+            let feature = Table::size_prefixed_root(&output, features_start + feature_offset);
+            assert!(feature.field(slot::FEATURE_GEOMETRY).is_some());
+            feature_offset += 4 + read_u32(&output, features_start + feature_offset) as usize;
+        }
+        assert_eq!(features_start + feature_offset, output.len());
+    }
+
+    #[tokio::test]
+    async fn test_write_without_geometry() {
+        let sources = std::iter::once(VehicleArrow::new(VehicleGenerator::new(0.1, 1, 1)));
+        let output = generate(sources).await;
+        let header = Table::size_prefixed_root(&output, 8);
+        assert_eq!(header.u64(slot::HEADER_FEATURES_COUNT, 0), 10);
+        assert_eq!(header.u16(slot::HEADER_INDEX_NODE_SIZE, 16), 0);
+        assert_eq!(
+            header.u8(slot::HEADER_GEOMETRY_TYPE, 0),
+            GeometryType::Unknown as u8
+        );
+        assert!(header.field(slot::HEADER_CRS).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_write_with_z() {
+        let generator = TripGenerator::new(0.001, 1, 1);
+        let sources = std::iter::once(TripArrow::new(generator).with_dimensions(Dimensions::Xyz));
+        let output = generate(sources).await;
+        let header = Table::size_prefixed_root(&output, 8);
+        assert_eq!(header.u8(slot::HEADER_HAS_Z, 0), 1);
+
+        // the first feature is a point with a single Z value
+        let features_count = header.u64(slot::HEADER_FEATURES_COUNT, 0) as usize;
+        let features_start = 12
+            + read_u32(&output, 8) as usize
+            + index_size(features_count, INDEX_NODE_SIZE as usize);
+        let feature = Table::size_prefixed_root(&output, features_start);
+        let pos = feature.field(slot::FEATURE_GEOMETRY).unwrap();
+        let geometry = Table {
+            buf: &output,
+            pos: pos + read_u32(&output, pos) as usize,
+        };
+        assert_eq!(geometry.vector(slot::GEOMETRY_XY).unwrap().1, 2);
+        assert_eq!(geometry.vector(slot::GEOMETRY_Z).unwrap().1, 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_with_m() {
+        let generator = TrajectoryGenerator::new(0.001, 1, 1);
+        let sources =
+            std::iter::once(TrajectoryArrow::new(generator).with_dimensions(Dimensions::Xyzm));
+        let output = generate(sources).await;
+        let header = Table::size_prefixed_root(&output, 8);
+        assert_eq!(header.u8(slot::HEADER_HAS_Z, 0), 1);
+        assert_eq!(header.u8(slot::HEADER_HAS_M, 0), 1);
+
+        // each vertex of the first path has a Z and an M value
+        let features_count = header.u64(slot::HEADER_FEATURES_COUNT, 0) as usize;
+        let features_start = 12
+            + read_u32(&output, 8) as usize
+            + index_size(features_count, INDEX_NODE_SIZE as usize);
+        let feature = Table::size_prefixed_root(&output, features_start);
+        let pos = feature.field(slot::FEATURE_GEOMETRY).unwrap();
+        let geometry = Table {
+            buf: &output,
+            pos: pos + read_u32(&output, pos) as usize,
+        };
+        let vertices = geometry.vector(slot::GEOMETRY_XY).unwrap().1 / 2;
+        assert!(vertices > 1);
+        assert_eq!(geometry.vector(slot::GEOMETRY_Z).unwrap().1, vertices);
+        assert_eq!(geometry.vector(slot::GEOMETRY_M).unwrap().1, vertices);
+    }
+
+    #[test]
+    fn test_build_index() {
+        let items: Vec<_> = (0..5)
+            .map(|i| Item {
+                bbox: [i as f64, 0.0, i as f64 + 1.0, 1.0],
+                offset: 0,
+                len: 10,
+                hilbert: 0,
+            })
+            .collect();
+        let nodes = build_index(&items, 2);
+        // 5 leaves, 3 then 2 interior nodes and the root
+        assert_eq!(nodes.len(), 11);
+        assert_eq!(nodes[0], ([0.0, 0.0, 5.0, 1.0], 1));
+        assert_eq!(nodes[1], ([0.0, 0.0, 4.0, 1.0], 3));
+        assert_eq!(nodes[2], ([4.0, 0.0, 5.0, 1.0], 5));
+        assert_eq!(nodes[5], ([4.0, 0.0, 5.0, 1.0], 10));
+        assert_eq!(nodes[6], ([0.0, 0.0, 1.0, 1.0], 0));
+        assert_eq!(nodes[10], ([4.0, 0.0, 5.0, 1.0], 40));
+    }
+}
@@ -20,6 +20,7 @@
 //! These traits and function are used to generate data in parallel and write it to a sink
 //! in streaming fashion (chunks). This is useful for generating large datasets that don't fit in memory.
 
+use crate::memory::{ChunkEstimate, Reservation};
 use futures::StreamExt;
 use log::debug;
 use std::collections::VecDeque;
@@ -40,6 +41,22 @@ pub trait Source: Send {
     /// This will be called before the first call to [`Self::create`] and
     /// exactly once across all [`Source`]es
     fn header(&self, buffer: Vec<u8>) -> Vec<u8>;
+
+    /// Create the end of the output, into the buffer
+    ///
+    /// This is called on the same [`Source`] as [`Self::header`] and is
+    /// written after the output of the last [`Source`]
+    fn footer(&self, buffer: Vec<u8>) -> Vec<u8> {
+        buffer
+    }
+
+    /// Bytes to write between the (non empty) output of consecutive [`Source`]es
+    ///
+    /// This is used for formats such as GeoJSON where records are separated
+    /// with a delimiter rather than terminated by one.
+    fn separator(&self) -> &'static [u8] {
+        b""
+    }
 }
 
 /// Something that can write the contents of a buffer somewhere
@@ -62,6 +79,9 @@ pub trait Sink: Send {
 /// Data is written to the [`Sink`] in the order of the [`Source`]es in
 /// the input iterator.
 ///
+/// With `--memory-limit`, each buffer reserves its memory before it is
+/// generated until it is written (see [`crate::memory`]).
+///
 /// G: Generator
 /// I: Iterator<Item = G>
 /// S: Sink that writes buffers somewhere
@@ -76,6 +96,7 @@ where
     S: Sink + 'static,
 {
     let recycler = BufferRecycler::new();
+    let estimate = Arc::new(ChunkEstimate::new());
     let mut sources = sources.peekable();
 
     // use all cores to make data
@@ -89,26 +110,32 @@ where
         return Ok(()); // no sources
     };
     let header = first.header(Vec::new());
-    tx.send(header)
+    let footer = first.footer(Vec::new());
+    let separator = first.separator();
+    tx.send((header, Reservation::default()))
         .await
         .expect("tx just created, it should not be closed");
 
-    let sources_and_recyclers = sources.map(|generator| (generator, recycler.clone()));
+    let sources_and_recyclers =
+        sources.map(|generator| (generator, recycler.clone(), Arc::clone(&estimate)));
 
     // convert to an async stream to run on tokio
     let mut stream = futures::stream::iter(sources_and_recyclers)
         // each generator writes to a buffer
-        .map(async |(source, recycler)| {
+        .map(async |(source, recycler, estimate)| {
+            let reservation = estimate.reserve().await;
             let buffer = recycler.new_buffer(1024 * 1024 * 8);
             // do the work in a task (on a different thread)
             let mut join_set = JoinSet::new();
             join_set.spawn(async move { source.create(buffer) });
             // wait for the task to be done and return the result
-            join_set
+            let buffer = join_set
                 .join_next()
                 .await
                 .expect("had one item")
-                .expect("join_next join is infallible unless task panics")
+                .expect("join_next join is infallible unless task panics");
+            estimate.update(buffer.capacity());
+            (buffer, reservation)
         })
         // run in parallel
         .buffered(num_threads)
@@ -127,9 +154,23 @@ where
     // runtime. It reads from the channel and writes to the sink (doing File IO)
     let captured_recycler = recycler.clone();
     let writer_task = tokio::task::spawn_blocking(move || {
-        while let Some(buffer) = rx.blocking_recv() {
+        // the first buffer is the header, which is never separated
+        let mut header = true;
+        let mut wrote_data = false;
+        while let Some((mut buffer, reservation)) = rx.blocking_recv() {
+            if !header && !buffer.is_empty() && !separator.is_empty() {
+                if wrote_data {
+                    buffer.splice(0..0, separator.iter().copied());
+                }
+                wrote_data = true;
+            }
+            header = false;
             sink.sink(&buffer)?;
             captured_recycler.return_buffer(buffer);
+            drop(reservation);
+        }
+        if !footer.is_empty() {
+            sink.sink(&footer)?;
         }
         // No more input, flush the sink and return
         sink.flush()
@@ -185,3 +226,66 @@ impl BufferRecycler {
         buffers.push_back(buffer);
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tbl::{BuildingTblSource, CustomerTblSource, TripTblSource};
+    use spatialbench::generators::{BuildingGenerator, CustomerGenerator, TripGenerator};
+
+    /// A [`Sink`] that collects the output in memory
+    #[derive(Clone, Default)]
+    pub(crate) struct MemorySink(pub(crate) Arc<Mutex<Vec<u8>>>);
+
+    impl Sink for MemorySink {
+        fn sink(&mut self, buffer: &[u8]) -> Result<(), io::Error> {
+            self.0.lock().unwrap().extend_from_slice(buffer);
+            Ok(())
+        }
+
+        fn flush(self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    async fn generate<S: Source + 'static>(
+        sources: impl Iterator<Item = S>,
+        num_threads: usize,
+    ) -> Vec<u8> {
+        let sink = MemorySink::default();
+        generate_in_chunks(sink.clone(), sources, num_threads)
+            .await
+            .unwrap();
+        let output = sink.0.lock().unwrap().clone();
+        output
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_chunks_match_single_chunk() {
+        // generating in parallel chunks writes exactly the same bytes as a
+        // single chunk on one thread
+        let serial = generate(
+            std::iter::once(TripTblSource::new(TripGenerator::new(0.001, 1, 1))),
+            1,
+        );
+        let chunked = (1..=7).map(|part| TripTblSource::new(TripGenerator::new(0.001, part, 7)));
+        assert_eq!(generate(chunked, 4).await, serial.await);
+
+        let serial = generate(
+            std::iter::once(CustomerTblSource::new(CustomerGenerator::new(0.01, 1, 1))),
+            1,
+        );
+        let chunked =
+            (1..=5).map(|part| CustomerTblSource::new(CustomerGenerator::new(0.01, part, 5)));
+        assert_eq!(generate(chunked, 3).await, serial.await);
+
+        // buildings scale logarithmically
+        let serial = generate(
+            std::iter::once(BuildingTblSource::new(BuildingGenerator::new(4.0, 1, 1))),
+            1,
+        );
+        let chunked =
+            (1..=3).map(|part| BuildingTblSource::new(BuildingGenerator::new(4.0, part, 3)));
+        assert_eq!(generate(chunked, 2).await, serial.await);
+    }
+}
@@ -0,0 +1,495 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The options of the generated data, shared by the generation and the
+//! commands that derive the same data again, such as `verify`
+
+use crate::key_skew::load_key_skews;
+use crate::rows::{self, RowCount};
+use crate::spatial_config_file::{
+    load_overrides, parse_attribute_distribution, parse_density_raster, parse_distribution,
+    parse_geometry_mix, parse_polygon_vertices, AttributeDistribution, TableDistribution,
+    TableRaster,
+};
+use crate::time_window::load_time_window;
+use clap::Args;
+use log::{info, warn};
+use spatialbench::attributes::SpatialCorrelation;
+use spatialbench::generators::RowCounts;
+use spatialbench::names;
+use spatialbench::skew::ForeignKeySkew;
+use spatialbench::spatial::duplicate::{self, MAX_DUPLICATE_RATE};
+use spatialbench::spatial::{
+    cell, crs, dimensions, geography, invalid, landmass, precision, road, CellIndex,
+    CoordinatePrecision, Crs, Dimensions, GeometryMix,
+};
+use spatialbench::temporal::{parse_timestamp, parse_utc_offset, TimePattern};
+use spatialbench::workload::Dataset;
+use spatialbench_arrow::lineage;
+use spatialbench_arrow::nulls::{self, ColumnRate, NullRates};
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Options that change the generated data, as passed when generating
+#[derive(Debug, Args)]
+pub struct GenerationOptions {
+    /// Scale factor to create
+    ///
+    /// Fractional scale factors such as `0.01` create small datasets for
+    /// smoke tests. Below scale factor 1 row counts are rounded to the
+    /// nearest row, and every table has at least one row.
+    #[arg(short, long, default_value_t = 1.)]
+    pub scale_factor: f64,
+
+    /// Seed all random values of the generated tables are derived from
+    ///
+    /// The default seed `0` generates the reference data. Any other seed
+    /// generates different data with the same distributions, and the same
+    /// seed, scale factor and `--parts` always generate the same rows, with
+    /// any number of threads or output format. The zone table is downloaded
+    /// and does not depend on the seed, unless generated with
+    /// `--zone-source tessellation`.
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Override the number of rows of a table, e.g. `--rows building=10000000`
+    ///
+    /// May be given once per table, and the other tables keep the size of
+    /// the scale factor, for example to generate many buildings and few
+    /// trips. Trips reference the keys of overridden customer, driver and
+    /// vehicle tables, so foreign keys stay consistent. As without this
+    /// option, vehicles, drivers and buildings are only written by `--part 1`.
+    #[arg(long, value_parser = rows::parse_rows)]
+    pub rows: Vec<RowCount>,
+
+    /// YAML file path specifying configs for Trip and Building
+    ///
+    /// When generating, the file may also set any option of the generation
+    /// in a `generate` section, e.g. `scale_factor: 10`, and named
+    /// `profiles` overriding some of them, selected with `--profile`. The
+    /// options given on the command line override those of the file.
+    #[arg(long = "config")]
+    pub config: Option<PathBuf>,
+
+    /// Distribution of the geometries of a table, e.g. `trip=hotspots`
+    ///
+    /// One of `uniform`, `clusters` (Gaussian clusters of similar sizes),
+    /// `hotspots` (Gaussian clusters with power law sizes) or `coastline`
+    /// (along a synthetic coastline), to stress spatial partitioning and
+    /// load balancing. Can be repeated for trip and building. Overrides the
+    /// distribution of `--config`, keeping its geometries.
+    #[arg(long, value_parser = parse_distribution)]
+    pub distribution: Vec<TableDistribution>,
+
+    /// Density raster placing the geometries of a table, e.g.
+    /// `trip=population.asc`
+    ///
+    /// The raster is a grid of weights in longitude / latitude in the ESRI
+    /// ASCII grid format, such as population counts. The geometries are
+    /// placed in its cells in proportion to the weights, instead of on the
+    /// continents. Can be repeated for trip and building.
+    #[arg(long, value_parser = parse_density_raster)]
+    pub density_raster: Vec<TableRaster>,
+
+    /// Number of vertices of the building polygons, e.g. `4..64` or `8`
+    ///
+    /// The number of vertices of each polygon is drawn uniformly from the
+    /// range, to compare engines on simple and highly detailed geometries.
+    /// Defaults to `3..7`, or the `minseg` and `maxseg` of `--config`. The
+    /// zone table is downloaded and keeps its boundaries.
+    #[arg(long, value_parser = parse_polygon_vertices)]
+    pub polygon_vertices: Option<RangeInclusive<i32>>,
+
+    /// Fractions of the types of the building boundaries, e.g.
+    /// `building=polygon:0.7,multipolygon:0.3`
+    ///
+    /// Many engines have separate code paths for multi geometries. Each
+    /// multipolygon is made of the generated polygon and 1 or 2 copies of it
+    /// next to each other. Only the buildings can be mixed: the trip
+    /// locations are points and the zones are downloaded.
+    #[arg(long, value_parser = parse_geometry_mix)]
+    pub geometry_mix: Option<GeometryMix>,
+
+    /// Distribution of the values of an attribute column, e.g.
+    /// `fare=zipf:1.2`
+    ///
+    /// The attributes are `fare` (the fare per mile of the trips, 150 to 300
+    /// cents), `tip` (the tip of the trips, 0 to 30 percent of the fare) and
+    /// `category` (the category of the POIs, 1 to 1000). The distributions
+    /// are `uniform`, `zipf:<s>`, `normal:<mu>,<sigma>` and
+    /// `lognormal:<mu>,<sigma>`, rounded and clamped to the values of the
+    /// attribute, to vary the selectivity of filters and group-bys. Can be
+    /// repeated for each attribute. Overrides the `attributes` of `--config`.
+    #[arg(long, value_parser = parse_attribute_distribution)]
+    pub attribute_distribution: Vec<AttributeDistribution>,
+
+    /// Correlate the fares and tips of the trips with their pickup
+    /// locations, e.g. `0.8` or `0.8,0.05`
+    ///
+    /// The strength, from 0 to 1, is the correlation of the values with a
+    /// smooth random field of the location that varies over the range, in
+    /// degrees (defaults to 0.1, about 10 km), so nearby trips have similar
+    /// fares and tips. The distributions of the values are unchanged.
+    #[arg(long)]
+    pub spatial_correlation: Option<SpatialCorrelation>,
+
+    /// Skew of the references of a foreign key, e.g.
+    /// `t_custkey=hotspot:0.2,0.8`
+    ///
+    /// The foreign keys are `t_custkey`, `t_driverkey`, `t_vehiclekey` and
+    /// `p_citykey`, referenced uniformly by default. The skews are `uniform`,
+    /// `zipf:<s>` and `hotspot:<keys>,<rows>`, where the first `keys`
+    /// fraction of the keys receive the `rows` fraction of the references,
+    /// to benchmark how joins handle skew. Can be repeated for each foreign
+    /// key.
+    #[arg(long)]
+    pub key_skew: Vec<ForeignKeySkew>,
+
+    /// Fraction of the building boundaries made invalid, e.g. `0.01`
+    ///
+    /// The invalid boundaries are bowties, rings without their closing
+    /// coordinate or rings going back to a vertex, in about equal numbers,
+    /// and the building table gains a `b_isvalid` column flagging them. Not
+    /// supported for tbl and csv files.
+    #[arg(long)]
+    pub invalid_rate: Option<f64>,
+
+    /// Fraction of the buildings that duplicate the building before them,
+    /// e.g. `0.02`, at most `0.25`
+    ///
+    /// Half of the duplicates are exact copies and half are near
+    /// duplicates, moved by less than 1e-6 degrees. The building table
+    /// gains `b_duplicateof` and `b_duplicatekind` columns with the key of
+    /// the copied building and `exact` or `near`. Not supported for tbl and
+    /// csv files.
+    #[arg(long)]
+    pub duplicate_rate: Option<f64>,
+
+    /// Fraction of the values of a column that are null, e.g.
+    /// `--null-rate trip.t_dropoffloc=0.02`
+    ///
+    /// Can be repeated for several columns. The null rows are chosen from
+    /// the key of the row, so they are the same in every part and
+    /// projection. The key columns can not be null. Not supported for tbl
+    /// and csv files.
+    #[arg(long, value_parser = ColumnRate::from_str)]
+    pub null_rate: Vec<ColumnRate>,
+
+    /// Fraction of the geometries of a column that are empty, e.g.
+    /// `--empty-rate building.b_boundary=0.01`
+    ///
+    /// Can be repeated for several geometry columns. The empty geometries
+    /// keep their type and dimensions: the points have NaN coordinates,
+    /// the other geometries no coordinates. Not supported for tbl and csv
+    /// files.
+    #[arg(long, value_parser = ColumnRate::from_str)]
+    pub empty_rate: Vec<ColumnRate>,
+
+    /// Cell index of the locations of the point tables, e.g. `h3:9`,
+    /// `s2:13` or `geohash:7`
+    ///
+    /// Can be repeated. The trip and POI tables gain a column with the cell
+    /// of each location for each index, such as `t_pickuploc_h3_9`,
+    /// computed while generating. The H3 and S2 cells are 64 bit integer
+    /// ids and the geohashes strings. Not supported for tbl and csv files.
+    #[arg(long, value_parser = CellIndex::from_str)]
+    pub add_cell_index: Vec<CellIndex>,
+
+    /// Add a `_rowgen` column with the lineage of each row, e.g. `3:1234:42`
+    ///
+    /// The lineage is the chunk of 100,000 rows of the table the row is in,
+    /// the index of the row in the chunk and the seed, the same whatever
+    /// `--parts` and `--part`, so a row that differs between engines can be
+    /// regenerated on its own. Not supported for tbl and csv files.
+    #[arg(long)]
+    pub add_rowgen: bool,
+
+    /// Start of the trip pickup times, e.g. `2024-01-01` or
+    /// `2024-01-01 06:00:00`
+    ///
+    /// With any of `--time-start`, `--time-end`, `--time-pattern` or
+    /// `--time-zone`, the pickup times are drawn from a time window instead
+    /// of the dates of the reference data, from 1992-01-01 to 1998-12-31.
+    #[arg(long, value_parser = parse_timestamp)]
+    pub time_start: Option<i64>,
+
+    /// End of the trip pickup times (excluded), e.g. `2024-02-01`
+    #[arg(long, value_parser = parse_timestamp)]
+    pub time_end: Option<i64>,
+
+    /// Distribution of the trip pickup times in the time window
+    ///
+    /// One of `uniform` (the default), `diurnal` (fewer trips at night and
+    /// rush hours in the morning and evening) or `weekly` (the diurnal
+    /// pattern, with busier Fridays and quieter Sundays).
+    #[arg(long, value_parser = TimePattern::from_str)]
+    pub time_pattern: Option<TimePattern>,
+
+    /// UTC offset of the time window, e.g. `-05:00`
+    ///
+    /// The window and the pattern are in this local time, and the pickup
+    /// times are written in UTC. Defaults to `UTC`. Named time zones are not
+    /// supported since their offsets change with daylight saving time.
+    #[arg(long, allow_hyphen_values = true, value_parser = parse_utc_offset)]
+    pub time_zone: Option<i32>,
+
+    /// Snap the trips to the streets of the `road` table
+    ///
+    /// The trips start and end at the intersection nearest to their pickup
+    /// and dropoff locations, in the town nearest to their pickup, and their
+    /// distance and trajectory follow a shortest route along the streets.
+    #[arg(long, default_value_t = false)]
+    pub snap_to_roads: bool,
+
+    /// Keep the trips on the continents and islands of the `landmass` table
+    ///
+    /// Pickup locations in the water are drawn again or moved inland, and
+    /// trips ending in the water are shortened towards their pickup.
+    #[arg(long, default_value_t = false)]
+    pub on_land: bool,
+
+    /// Make up realistic names and addresses instead of numbered names and
+    /// random characters
+    ///
+    /// The names of the countries, regions, cities and POIs look like
+    /// `Brenford` or `Cedar Bakery`, and the addresses of the drivers and
+    /// customers like `1428 Cedar Lane, Apt 12, Brenford 40213`, for `LIKE`,
+    /// regular expression and tokenization benchmarks.
+    #[arg(long, default_value_t = false)]
+    pub realistic_text: bool,
+
+    /// Dimensions of the coordinates of the geometries, `xy`, `xyz`, `xym`
+    /// or `xyzm`
+    ///
+    /// With a Z, the trip locations, trajectories and roads have the
+    /// elevation of a synthetic terrain as Z (in meters), and the building
+    /// boundaries are extruded to their roof, at the elevation of the ground
+    /// plus the height of the building. The zones keep the 2D boundaries
+    /// they are downloaded with. With an M, the positions of the
+    /// trajectories have their time (in seconds since the Unix epoch) as M;
+    /// the other geometries have no M. Not supported for tbl and csv files.
+    #[arg(long, default_value_t = Dimensions::Xy, value_parser = Dimensions::from_str)]
+    pub dims: Dimensions,
+
+    /// Coordinate reference system of the geometries, e.g. `EPSG:3857` or
+    /// `+proj=utm +zone=33 +datum=WGS84`
+    ///
+    /// The geometries are generated in longitude / latitude and projected to
+    /// this CRS when they are written, which is recorded in the GeoParquet,
+    /// GeoArrow and FlatGeobuf metadata. Only four projections are supported,
+    /// not PROJ in general: WGS84 (`EPSG:4326`), Web Mercator (`EPSG:3857`),
+    /// World Mercator (`EPSG:3395`) and the WGS84 UTM zones (`EPSG:326xx` and
+    /// `EPSG:327xx`). With a UTM zone, the longitudes outside the zone are
+    /// clamped to its edges. Not supported for tbl, csv and GeoJSON files.
+    #[arg(long, default_value_t = Crs::Wgs84, value_parser = Crs::from_str)]
+    pub crs: Crs,
+
+    /// Number of decimal digits of the longitude / latitude of the
+    /// geometries, from 0 to 9 (the default)
+    ///
+    /// The coordinates are rounded as they are generated, before the
+    /// duplicates and the expected answers are derived from them, so the
+    /// data matches an engine that stores coordinates with this precision,
+    /// and points that round to the same location are duplicates. A coarse
+    /// precision can collapse the vertices of small polygons. With `--crs`,
+    /// the coordinates are rounded before they are projected.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=9), conflicts_with = "grid_size")]
+    pub coordinate_precision: Option<u8>,
+
+    /// Snap the longitude / latitude of the geometries to a grid of this size
+    /// in degrees, e.g. `0.0001`, as `--coordinate-precision` rounds them to
+    /// decimal digits
+    #[arg(long, value_parser = precision::parse_grid_size)]
+    pub grid_size: Option<f64>,
+
+    /// Generate the geometries on the sphere rather than on the plane
+    ///
+    /// The placements, sizes and trip distances are computed on the WGS84
+    /// sphere: the geometries are spread by area and keep their shape toward
+    /// the poles, the trips and trajectories follow great circles, and the
+    /// geometries wrap across the antimeridian instead of being clamped to
+    /// it. The GeoParquet and GeoArrow metadata record spherical edges. Only
+    /// supported with longitude / latitude coordinates.
+    #[arg(long, default_value_t = false)]
+    pub geography: bool,
+}
+
+impl GenerationOptions {
+    /// Check the options and set the global settings of the generators
+    ///
+    /// The checks of the options against the output format are left to the
+    /// commands writing files.
+    pub fn apply(&self) -> io::Result<()> {
+        if !(self.scale_factor.is_finite() && self.scale_factor > 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--scale-factor must be positive, got {}", self.scale_factor),
+            ));
+        }
+        load_overrides(
+            self.config.as_deref(),
+            &self.distribution,
+            &self.density_raster,
+            self.polygon_vertices.clone(),
+            self.geometry_mix.clone(),
+            &self.attribute_distribution,
+            self.spatial_correlation,
+        )?;
+        load_key_skews(&self.key_skew)?;
+        load_time_window(
+            self.time_start,
+            self.time_end,
+            self.time_pattern,
+            self.time_zone,
+        )?;
+        if self.snap_to_roads {
+            info!("Snapping the trips to the roads");
+            road::set_snap_to_roads(true);
+        }
+        if self.on_land {
+            info!("Keeping the trips on land");
+            landmass::set_on_land(true);
+        }
+        if let Some(precision) =
+            CoordinatePrecision::from_options(self.coordinate_precision, self.grid_size)
+        {
+            info!("Rounding the coordinates of the geometries to {precision}");
+            precision::set_precision(precision);
+        }
+        if self.realistic_text {
+            info!("Making up realistic names and addresses");
+            names::set_realistic_text(true)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        }
+        if self.dims != Dimensions::Xy {
+            info!("Writing the geometries with {} coordinates", self.dims);
+            dimensions::set_dimensions(self.dims);
+        }
+        if self.crs != Crs::Wgs84 {
+            info!(
+                "Projecting the geometries to {} ({})",
+                self.crs,
+                self.crs.name()
+            );
+            if let Crs::Utm { .. } = self.crs {
+                let lon = self.crs.central_meridian();
+                warn!(
+                    "Clamping the longitudes outside {} ({}° to {}°) to the edges of the zone",
+                    self.crs.name(),
+                    lon - 3.0,
+                    lon + 3.0
+                );
+            }
+            crs::set_crs(self.crs);
+        }
+        if self.geography {
+            if self.crs != Crs::Wgs84 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "--geography is not supported with --crs {}, only with longitude / latitude",
+                        self.crs
+                    ),
+                ));
+            }
+            info!("Generating the geometries on the sphere");
+            geography::set_geography(true);
+        }
+        if let Some(rate) = self.invalid_rate {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("--invalid-rate must be in [0, 1], got {rate}"),
+                ));
+            }
+            info!("Making {rate} of the building boundaries invalid");
+            invalid::set_invalid_rate(rate);
+        }
+        if let Some(rate) = self.duplicate_rate {
+            if !(0.0..=MAX_DUPLICATE_RATE).contains(&rate) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("--duplicate-rate must be in [0, {MAX_DUPLICATE_RATE}], got {rate}"),
+                ));
+            }
+            info!("Making {rate} of the buildings duplicates");
+            duplicate::set_duplicate_rate(rate);
+        }
+        if !self.null_rate.is_empty() || !self.empty_rate.is_empty() {
+            let rates = NullRates::try_new(&self.null_rate, &self.empty_rate)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            info!(
+                "Generating null values in {} and empty geometries in {} columns",
+                self.null_rate.len(),
+                self.empty_rate.len()
+            );
+            nulls::set_null_rates(rates)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        }
+        if !self.add_cell_index.is_empty() {
+            info!(
+                "Adding the cell index columns {}",
+                self.add_cell_index
+                    .iter()
+                    .map(|index| index.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            cell::set_cell_indexes(self.add_cell_index.clone());
+        }
+        if self.add_rowgen {
+            info!("Adding the {} column", lineage::ROWGEN_COLUMN);
+            lineage::set_row_lineage(true)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        }
+        Ok(())
+    }
+
+    /// Return the row counts of the tables, with the `--rows` overrides
+    pub fn row_counts(&self) -> io::Result<RowCounts> {
+        rows::row_counts(&self.rows).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    /// Return the dataset to compute `option` on in memory
+    ///
+    /// The CRS, the invalid and duplicate buildings and the null and empty
+    /// values are only applied when writing the tables, so they are refused.
+    pub fn dataset(&self, option: &str) -> io::Result<Dataset> {
+        let unsupported = if self.crs != Crs::Wgs84 {
+            Some("--crs")
+        } else if self.invalid_rate.is_some() {
+            Some("--invalid-rate")
+        } else if self.duplicate_rate.is_some() {
+            Some("--duplicate-rate")
+        } else if !self.null_rate.is_empty() || !self.empty_rate.is_empty() {
+            Some("--null-rate and --empty-rate")
+        } else {
+            None
+        };
+        if let Some(unsupported) = unsupported {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{option} can not be used with {unsupported}"),
+            ));
+        }
+        Ok(Dataset::new(self.scale_factor)
+            .with_seed(self.seed)
+            .with_row_counts(self.row_counts()?))
+    }
+}
@@ -0,0 +1,626 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Native [GeoArrow] geometry encoding
+//!
+//! * [`GeometryEncoding`]: how geometry columns are written to Parquet
+//! * [`GeoArrowEncoder`]: converts WKB columns to native GeoArrow arrays
+//!
+//! Native GeoArrow columns store coordinates in separated `x` / `y` (and
+//! `z` / `m`) arrays nested in lists of vertices, rings and polygons, so readers
+//! can access them without parsing WKB.
+//!
+//! [GeoArrow]: https://geoarrow.org/format.html
+
+use crate::geoparquet::{is_wkb_type, wkb_ordinates, wkb_values, Ordinates};
+use arrow::array::{Array, ArrayRef, Float64Array, ListArray, RecordBatch, StructArray};
+use arrow::buffer::{NullBuffer, OffsetBuffer};
+use arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use clap::ValueEnum;
+use geo::{Coord, Geometry, LineString, Polygon};
+use geozero::wkb::Wkb;
+use geozero::ToGeo;
+use serde_json::json;
+use spatialbench::spatial::crs::{self, Crs};
+use spatialbench::spatial::geography;
+use spatialbench::spatial::Dimensions;
+use spatialbench_arrow::GEOMETRY_TYPE_KEY;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Field metadata key for the name of an Arrow extension type
+pub const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+
+/// Field metadata key for the metadata of an Arrow extension type
+pub const EXTENSION_METADATA_KEY: &str = "ARROW:extension:metadata";
+
+/// Return the GeoArrow extension metadata: the CRS set with
+/// [`crs::set_crs`], by default WGS84 longitude / latitude, and spherical
+/// edges with [`geography::set_geography`]
+pub fn geoarrow_metadata() -> String {
+    let crs = match crs::crs() {
+        Crs::Wgs84 => "OGC:CRS84".to_string(),
+        crs => crs.to_string(),
+    };
+    let mut metadata = json!({"crs": crs, "crs_type": "authority_code"});
+    if geography::geography() {
+        metadata["edges"] = json!("spherical");
+    }
+    metadata.to_string()
+}
+
+/// How geometry columns are written to Parquet
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum GeometryEncoding {
+    /// Well-known binary (WKB) `Binary` columns
+    Wkb,
+    /// Native GeoArrow columns with separated coordinate arrays
+    Geoarrow,
+}
+
+/// The native GeoArrow geometry types
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GeoArrowType {
+    Point,
+    LineString,
+    Polygon,
+    MultiPoint,
+    MultiLineString,
+    MultiPolygon,
+}
+
+impl GeoArrowType {
+    /// Return the type of `geometry`, or None if it has no native encoding
+    fn of(geometry: &Geometry) -> Option<Self> {
+        Some(match geometry {
+            Geometry::Point(_) => Self::Point,
+            Geometry::LineString(_) => Self::LineString,
+            Geometry::Polygon(_) => Self::Polygon,
+            Geometry::MultiPoint(_) => Self::MultiPoint,
+            Geometry::MultiLineString(_) => Self::MultiLineString,
+            Geometry::MultiPolygon(_) => Self::MultiPolygon,
+            _ => return None,
+        })
+    }
+
+    /// Return the type with the GeoParquet geometry type `name`, e.g.
+    /// `MultiPolygon`
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "Point" => Self::Point,
+            "LineString" => Self::LineString,
+            "Polygon" => Self::Polygon,
+            "MultiPoint" => Self::MultiPoint,
+            "MultiLineString" => Self::MultiLineString,
+            "MultiPolygon" => Self::MultiPolygon,
+            _ => return None,
+        })
+    }
+
+    /// Return a type that can hold geometries of both `self` and `other`, if
+    /// any (e.g. MultiPolygon for Polygon and MultiPolygon)
+    fn union(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (a, b) if a == b => Some(a),
+            (a, b) if a.multi() == b || b.multi() == a => Some(a.multi()),
+            _ => None,
+        }
+    }
+
+    /// Return the multi geometry type for single geometries
+    fn multi(self) -> Self {
+        match self {
+            Self::Point => Self::MultiPoint,
+            Self::LineString => Self::MultiLineString,
+            Self::Polygon => Self::MultiPolygon,
+            multi => multi,
+        }
+    }
+
+    /// Return the name of the GeoArrow extension type
+    pub fn extension_name(&self) -> &'static str {
+        match self {
+            Self::Point => "geoarrow.point",
+            Self::LineString => "geoarrow.linestring",
+            Self::Polygon => "geoarrow.polygon",
+            Self::MultiPoint => "geoarrow.multipoint",
+            Self::MultiLineString => "geoarrow.multilinestring",
+            Self::MultiPolygon => "geoarrow.multipolygon",
+        }
+    }
+
+    /// Return the GeoParquet `encoding` of this type
+    pub fn geoparquet_encoding(&self) -> &'static str {
+        match self {
+            Self::Point => "point",
+            Self::LineString => "linestring",
+            Self::Polygon => "polygon",
+            Self::MultiPoint => "multipoint",
+            Self::MultiLineString => "multilinestring",
+            Self::MultiPolygon => "multipolygon",
+        }
+    }
+
+    /// Return the names of the nested list fields, from outermost to
+    /// innermost
+    fn list_names(&self) -> &'static [&'static str] {
+        match self {
+            Self::Point => &[],
+            Self::LineString => &["vertices"],
+            Self::MultiPoint => &["points"],
+            Self::Polygon => &["rings", "vertices"],
+            Self::MultiLineString => &["linestrings", "vertices"],
+            Self::MultiPolygon => &["polygons", "rings", "vertices"],
+        }
+    }
+
+    /// Return the Arrow data type of the native encoding, with coordinates
+    /// of `dimensions`
+    fn data_type(&self, dimensions: Dimensions) -> DataType {
+        self.list_names().iter().rev().fold(
+            DataType::Struct(coord_fields(dimensions)),
+            |data_type, name| DataType::List(Arc::new(Field::new(*name, data_type, false))),
+        )
+    }
+}
+
+fn coord_fields(dimensions: Dimensions) -> Fields {
+    let names: &[&str] = match dimensions {
+        Dimensions::Xy => &["x", "y"],
+        Dimensions::Xyz => &["x", "y", "z"],
+        Dimensions::Xym => &["x", "y", "m"],
+        Dimensions::Xyzm => &["x", "y", "z", "m"],
+    };
+    names
+        .iter()
+        .map(|name| Field::new(*name, DataType::Float64, false))
+        .collect()
+}
+
+/// Converts the WKB geometry columns of batches to native GeoArrow columns
+///
+/// The native type of each column is inferred from a sample of the data,
+/// and the type declared in the [`GEOMETRY_TYPE_KEY`] field metadata (if
+/// any). Columns whose type can not be determined from the sample (e.g. no
+/// geometries or a mix of points and polygons) are left as WKB. Columns
+/// have a `z` (or `m`) coordinate if the geometries of the sample have a Z
+/// (or an M).
+#[derive(Debug, Clone)]
+pub struct GeoArrowEncoder {
+    /// Indexes of the converted columns, their native types and the
+    /// dimensions of their coordinates
+    columns: Vec<(usize, GeoArrowType, Dimensions)>,
+    /// The schema of the encoded batches
+    schema: SchemaRef,
+}
+
+impl GeoArrowEncoder {
+    /// Create a new encoder for batches with `schema`, inferring the
+    /// geometry types from `sample`
+    pub fn new(schema: &SchemaRef, sample: &[RecordBatch]) -> Self {
+        let mut columns = vec![];
+        for (i, field) in schema.fields().iter().enumerate() {
+            if !is_wkb_type(field.data_type()) {
+                continue;
+            }
+            let declared = field
+                .metadata()
+                .get(GEOMETRY_TYPE_KEY)
+                .and_then(|name| GeoArrowType::from_name(name));
+            let inferred = infer_type(sample.iter().map(|batch| batch.column(i)));
+            let geometry_type = match (declared, inferred) {
+                (Some(declared), Some(inferred)) => declared.union(inferred),
+                (declared, inferred) => declared.or(inferred),
+            };
+            if let Some(geometry_type) = geometry_type {
+                let (mut has_z, mut has_m) = (false, false);
+                for batch in sample {
+                    for ordinates in wkb_values(batch.column(i))
+                        .flatten()
+                        .filter_map(wkb_ordinates)
+                    {
+                        has_z |= ordinates.z.is_some();
+                        has_m |= ordinates.m.is_some();
+                    }
+                }
+                columns.push((i, geometry_type, Dimensions::new(has_z, has_m)));
+            }
+        }
+
+        let mut fields: Vec<_> = schema.fields().iter().cloned().collect();
+        for &(i, geometry_type, dimensions) in &columns {
+            let field = schema.field(i);
+            let mut metadata = field.metadata().clone();
+            metadata.remove(GEOMETRY_TYPE_KEY);
+            metadata.insert(
+                EXTENSION_NAME_KEY.to_string(),
+                geometry_type.extension_name().to_string(),
+            );
+            metadata.insert(EXTENSION_METADATA_KEY.to_string(), geoarrow_metadata());
+            fields[i] = Arc::new(
+                Field::new(
+                    field.name(),
+                    geometry_type.data_type(dimensions),
+                    field.is_nullable(),
+                )
+                .with_metadata(metadata),
+            );
+        }
+        let schema = Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()));
+
+        Self { columns, schema }
+    }
+
+    /// Return the schema of the encoded batches
+    pub fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Return the native type of each converted column, by column name
+    pub fn geometry_types(&self) -> HashMap<String, GeoArrowType> {
+        self.columns
+            .iter()
+            .map(|&(i, geometry_type, _)| (self.schema.field(i).name().clone(), geometry_type))
+            .collect()
+    }
+
+    /// Convert the geometry columns of `batch` to their native encoding
+    ///
+    /// Returns an error if a geometry does not fit the column type, for
+    /// example a polygon in a point column.
+    pub fn encode(&self, batch: RecordBatch) -> Result<RecordBatch, ArrowError> {
+        if self.columns.is_empty() {
+            return Ok(batch);
+        }
+        let mut columns = batch.columns().to_vec();
+        for &(i, geometry_type, dimensions) in &self.columns {
+            let mut builder = NativeBuilder::new(geometry_type, dimensions);
+            for wkb in wkb_values(batch.column(i)) {
+                let geometry = wkb
+                    .map(|wkb| Wkb(wkb).to_geo())
+                    .transpose()
+                    .map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+                let ordinates = wkb
+                    .filter(|_| dimensions != Dimensions::Xy)
+                    .and_then(wkb_ordinates)
+                    .unwrap_or_default();
+                builder
+                    .append(geometry.as_ref(), ordinates)
+                    .map_err(|actual| {
+                        ArrowError::InvalidArgumentError(format!(
+                            "Can not write {actual:?} to {geometry_type:?} column {}",
+                            self.schema.field(i).name()
+                        ))
+                    })?;
+            }
+            columns[i] = builder.finish();
+        }
+        RecordBatch::try_new(Arc::clone(&self.schema), columns)
+    }
+}
+
+/// Return a native type for all the (WKB) geometries in `arrays`, if any
+fn infer_type<'a>(arrays: impl Iterator<Item = &'a ArrayRef>) -> Option<GeoArrowType> {
+    let mut inferred = None;
+    for array in arrays {
+        for wkb in wkb_values(array).flatten() {
+            let geometry_type = GeoArrowType::of(&Wkb(wkb).to_geo().ok()?)?;
+            inferred = match inferred {
+                None => Some(geometry_type),
+                Some(inferred) => Some(inferred.union(geometry_type)?),
+            };
+        }
+    }
+    inferred
+}
+
+/// Builds a native GeoArrow array
+///
+/// The coordinates are stored in `x`, `y`, `z` (for columns with Z values)
+/// and `m` (for columns with M values), and `offsets` has one offset buffer per level of nesting,
+/// outermost first.
+struct NativeBuilder {
+    geometry_type: GeoArrowType,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    z: Option<Vec<f64>>,
+    m: Option<Vec<f64>>,
+    offsets: Vec<Vec<i32>>,
+    validity: Vec<bool>,
+}
+
+impl NativeBuilder {
+    fn new(geometry_type: GeoArrowType, dimensions: Dimensions) -> Self {
+        Self {
+            geometry_type,
+            x: vec![],
+            y: vec![],
+            z: dimensions.has_z().then(Vec::new),
+            m: dimensions.has_m().then(Vec::new),
+            offsets: vec![vec![0]; geometry_type.list_names().len()],
+            validity: vec![],
+        }
+    }
+
+    /// Append a geometry with the Z and M values of its coordinates, or null
+    ///
+    /// Single geometries are appended to multi geometry columns as multi
+    /// geometries with one element. Coordinates without a Z (or M) value
+    /// have a NaN Z (or M) in columns with Z (or M) values. Returns the
+    /// type of the geometry if it does not fit the column.
+    fn append(
+        &mut self,
+        geometry: Option<&Geometry>,
+        ordinates: Ordinates,
+    ) -> Result<(), Option<GeoArrowType>> {
+        let start = self.x.len();
+        self.append_xy(geometry)?;
+        let len = self.x.len();
+        for (values, ordinates) in [(&mut self.z, ordinates.z), (&mut self.m, ordinates.m)] {
+            if let Some(values) = values.as_mut() {
+                match ordinates {
+                    Some(ordinates) if ordinates.len() == len - start => values.extend(ordinates),
+                    _ => values.resize(len, f64::NAN),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn append_xy(&mut self, geometry: Option<&Geometry>) -> Result<(), Option<GeoArrowType>> {
+        use GeoArrowType::*;
+        let Some(geometry) = geometry else {
+            match self.geometry_type {
+                Point => self.push_coords([Coord::zero()].into_iter()),
+                _ => self.close(0),
+            }
+            self.validity.push(false);
+            return Ok(());
+        };
+        match (self.geometry_type, geometry) {
+            (Point, Geometry::Point(point)) => self.push_coords([point.0].into_iter()),
+            (LineString, Geometry::LineString(line)) => self.push_line(line, 0),
+            (MultiPoint, Geometry::Point(point)) => {
+                self.push_coords([point.0].into_iter());
+                self.close(0);
+            }
+            (MultiPoint, Geometry::MultiPoint(points)) => {
+                self.push_coords(points.iter().map(|point| point.0));
+                self.close(0);
+            }
+            (Polygon, Geometry::Polygon(polygon)) => self.push_polygon(polygon, 0),
+            (MultiLineString, Geometry::LineString(line)) => {
+                self.push_line(line, 1);
+                self.close(0);
+            }
+            (MultiLineString, Geometry::MultiLineString(lines)) => {
+                for line in lines {
+                    self.push_line(line, 1);
+                }
+                self.close(0);
+            }
+            (MultiPolygon, Geometry::Polygon(polygon)) => {
+                self.push_polygon(polygon, 1);
+                self.close(0);
+            }
+            (MultiPolygon, Geometry::MultiPolygon(polygons)) => {
+                for polygon in polygons {
+                    self.push_polygon(polygon, 1);
+                }
+                self.close(0);
+            }
+            (_, geometry) => return Err(GeoArrowType::of(geometry)),
+        }
+        self.validity.push(true);
+        Ok(())
+    }
+
+    fn push_coords(&mut self, coords: impl Iterator<Item = Coord>) {
+        for coord in coords {
+            self.x.push(coord.x);
+            self.y.push(coord.y);
+        }
+    }
+
+    /// Push the vertices of `line` as an element of the list at `level`
+    fn push_line(&mut self, line: &LineString, level: usize) {
+        self.push_coords(line.coords().copied());
+        self.close(level);
+    }
+
+    /// Push the rings of `polygon` as an element of the list at `level`
+    fn push_polygon(&mut self, polygon: &Polygon, level: usize) {
+        for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+            self.push_line(ring, level + 1);
+        }
+        self.close(level);
+    }
+
+    /// End the current element of the list at `level`
+    fn close(&mut self, level: usize) {
+        let end = match self.offsets.get(level + 1) {
+            Some(child_offsets) => child_offsets.len() - 1,
+            None => self.x.len(),
+        };
+        self.offsets[level].push(end as i32);
+    }
+
+    fn finish(self) -> ArrayRef {
+        let nulls = NullBuffer::from(self.validity);
+        let nulls = (nulls.null_count() > 0).then_some(nulls);
+        let names = self.geometry_type.list_names();
+        let dimensions = Dimensions::new(self.z.is_some(), self.m.is_some());
+        let mut values: Vec<ArrayRef> = vec![
+            Arc::new(Float64Array::from(self.x)),
+            Arc::new(Float64Array::from(self.y)),
+        ];
+        for ordinates in [self.z, self.m].into_iter().flatten() {
+            values.push(Arc::new(Float64Array::from(ordinates)));
+        }
+        let coords: ArrayRef = Arc::new(StructArray::new(
+            coord_fields(dimensions),
+            values,
+            // points are the outermost level
+            if names.is_empty() {
+                nulls.clone()
+            } else {
+                None
+            },
+        ));
+
+        // nest the coordinates in the lists, from innermost to outermost
+        let mut array = coords;
+        for (level, (name, offsets)) in names.iter().zip(self.offsets).enumerate().rev() {
+            let field = Arc::new(Field::new(*name, array.data_type().clone(), false));
+            let offsets = OffsetBuffer::new(offsets.into());
+            let nulls = if level == 0 { nulls.clone() } else { None };
+            array = Arc::new(ListArray::new(field, offsets, array, nulls));
+        }
+        array
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{AsArray, BinaryArray};
+    use arrow::datatypes::Float64Type;
+    use geo::{point, polygon, MultiPolygon};
+    use geozero::{CoordDimensions, ToWkb};
+
+    fn wkb_batch(geometries: &[Option<Geometry>]) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "geom",
+            DataType::Binary,
+            true,
+        )]));
+        let wkbs: BinaryArray = geometries
+            .iter()
+            .map(|geometry| {
+                geometry
+                    .as_ref()
+                    .map(|geometry| geometry.to_wkb(CoordDimensions::xy()).unwrap())
+            })
+            .collect();
+        RecordBatch::try_new(schema, vec![Arc::new(wkbs)]).unwrap()
+    }
+
+    #[test]
+    fn test_encode_points() {
+        let batch = wkb_batch(&[
+            Some(point!(x: 1.0, y: 2.0).into()),
+            None,
+            Some(point!(x: 3.0, y: 4.0).into()),
+        ]);
+        let encoder = GeoArrowEncoder::new(batch.schema_ref(), std::slice::from_ref(&batch));
+        let encoded = encoder.encode(batch).unwrap();
+
+        let field = encoded.schema().field(0).clone();
+        assert_eq!(
+            field.data_type(),
+            &DataType::Struct(coord_fields(Dimensions::Xy))
+        );
+        assert_eq!(
+            field.metadata().get(EXTENSION_NAME_KEY).unwrap(),
+            "geoarrow.point"
+        );
+        let points = encoded.column(0).as_struct();
+        assert_eq!(points.null_count(), 1);
+        let x = points.column(0).as_primitive::<Float64Type>();
+        assert_eq!(x.values(), &[1.0, 0.0, 3.0]);
+    }
+
+    #[test]
+    fn test_encode_points_with_z() {
+        let wkbs: Vec<_> = [(1.0f64, 2.0f64, 10.0f64), (3.0, 4.0, 20.0)]
+            .iter()
+            .map(|&(x, y, z)| {
+                let mut wkb = vec![1];
+                wkb.extend_from_slice(&1001u32.to_le_bytes());
+                for value in [x, y, z] {
+                    wkb.extend_from_slice(&value.to_le_bytes());
+                }
+                wkb
+            })
+            .collect();
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "geom",
+            DataType::Binary,
+            false,
+        )]));
+        let array = BinaryArray::from_iter_values(wkbs.iter());
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(array)]).unwrap();
+        let encoder = GeoArrowEncoder::new(batch.schema_ref(), std::slice::from_ref(&batch));
+        let encoded = encoder.encode(batch).unwrap();
+
+        let field = encoded.schema().field(0).clone();
+        assert_eq!(
+            field.data_type(),
+            &DataType::Struct(coord_fields(Dimensions::Xyz))
+        );
+        let z = encoded.column(0).as_struct().column(2).clone();
+        assert_eq!(z.as_primitive::<Float64Type>().values(), &[10.0, 20.0]);
+    }
+
+    #[test]
+    fn test_encode_polygons() {
+        let square = polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0)];
+        let multi = MultiPolygon::new(vec![square.clone(), square.clone()]);
+        let batch = wkb_batch(&[Some(square.into()), Some(multi.into())]);
+        let encoder = GeoArrowEncoder::new(batch.schema_ref(), std::slice::from_ref(&batch));
+        assert_eq!(encoder.geometry_types()["geom"], GeoArrowType::MultiPolygon);
+        let encoded = encoder.encode(batch).unwrap();
+
+        // polygons > rings > vertices
+        let polygons = encoded.column(0).as_list::<i32>();
+        assert_eq!(polygons.value_offsets(), &[0, 1, 3]);
+        let rings = polygons.values().as_list::<i32>();
+        assert_eq!(rings.value_offsets(), &[0, 1, 2, 3]);
+        let vertices = rings.values().as_list::<i32>();
+        // rings are closed
+        assert_eq!(vertices.value_offsets(), &[0, 4, 8, 12]);
+
+        // points can not be written to a polygon column
+        let points = wkb_batch(&[Some(point!(x: 1.0, y: 2.0).into())]);
+        assert!(encoder.encode(points).is_err());
+    }
+
+    #[test]
+    fn test_infer_mixed_types() {
+        let square = polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0)];
+        let batch = wkb_batch(&[Some(point!(x: 1.0, y: 2.0).into()), Some(square.into())]);
+        let encoder = GeoArrowEncoder::new(batch.schema_ref(), std::slice::from_ref(&batch));
+        // left as WKB
+        assert!(encoder.geometry_types().is_empty());
+        assert_eq!(encoder.encode(batch.clone()).unwrap(), batch);
+    }
+
+    #[test]
+    fn test_declared_type() {
+        let square = polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0)];
+        let batch = wkb_batch(&[Some(square.into())]);
+        let metadata = HashMap::from([(GEOMETRY_TYPE_KEY.to_string(), "MultiPolygon".to_string())]);
+        let field = batch.schema().field(0).clone().with_metadata(metadata);
+        let schema = Arc::new(Schema::new(vec![field]));
+        // the sample only has polygons
+        let encoder = GeoArrowEncoder::new(&schema, std::slice::from_ref(&batch));
+        assert_eq!(encoder.geometry_types()["geom"], GeoArrowType::MultiPolygon);
+        let field = encoder.schema().field(0);
+        assert!(!field.metadata().contains_key(GEOMETRY_TYPE_KEY));
+    }
+}
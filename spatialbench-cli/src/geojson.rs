@@ -0,0 +1,315 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Implementation of [`Source`] for generating data in GeoJSON format
+//!
+//! Each row of the generated [`RecordBatch`]es is written as a GeoJSON
+//! Feature. The first geometry (WKB) column is the feature `geometry`, and
+//! all other columns are written to the feature `properties`, with any other
+//! geometry columns written as GeoJSON geometry objects.
+//!
+//! Features are either written as a single `FeatureCollection` (`geojson`) or
+//! one feature per line (`geojsonl`, also known as GeoJSONSeq or
+//! newline-delimited GeoJSON).
+
+use crate::generate::Source;
+use arrow::array::{Array, AsArray, RecordBatch};
+use arrow::datatypes::DataType;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use geozero::geojson::GeoJsonWriter;
+use geozero::wkb::Wkb;
+use geozero::{CoordDimensions, GeozeroGeometry};
+use spatialbench_arrow::RecordBatchIterator;
+use std::io::Write;
+
+/// How features are laid out in a GeoJSON file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoJsonLayout {
+    /// A single `FeatureCollection` object
+    FeatureCollection,
+    /// One `Feature` object per line
+    NewlineDelimited,
+}
+
+/// How a column is written to a feature
+enum ColumnKind {
+    /// WKB geometry, written as a GeoJSON geometry object
+    Geometry,
+    /// Written as a JSON number
+    Number,
+    /// Written as a JSON boolean
+    Boolean,
+    /// Written as a JSON string
+    String,
+}
+
+impl ColumnKind {
+    fn new(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Binary | DataType::LargeBinary | DataType::BinaryView => Self::Geometry,
+            data_type if data_type.is_numeric() => Self::Number,
+            DataType::Boolean => Self::Boolean,
+            _ => Self::String,
+        }
+    }
+}
+
+/// A [`Source`] that writes the batches of a [`RecordBatchIterator`] as GeoJSON features
+pub struct GeoJsonSource<I> {
+    inner: I,
+    layout: GeoJsonLayout,
+}
+
+impl<I: RecordBatchIterator> GeoJsonSource<I> {
+    pub fn new(inner: I, layout: GeoJsonLayout) -> Self {
+        Self { inner, layout }
+    }
+}
+
+impl<I: RecordBatchIterator> Source for GeoJsonSource<I> {
+    fn header(&self, mut buffer: Vec<u8>) -> Vec<u8> {
+        if self.layout == GeoJsonLayout::FeatureCollection {
+            buffer.extend_from_slice(b"{\"type\":\"FeatureCollection\",\"features\":[\n");
+        }
+        buffer
+    }
+
+    fn create(self, mut buffer: Vec<u8>) -> Vec<u8> {
+        let mut first = true;
+        for batch in self.inner {
+            let writer = FeatureWriter::new(&batch);
+            for row in 0..batch.num_rows() {
+                if self.layout == GeoJsonLayout::FeatureCollection && !first {
+                    buffer.extend_from_slice(b",\n");
+                }
+                first = false;
+                writer.write(&mut buffer, row);
+                if self.layout == GeoJsonLayout::NewlineDelimited {
+                    buffer.push(b'\n');
+                }
+            }
+        }
+        buffer
+    }
+
+    fn footer(&self, mut buffer: Vec<u8>) -> Vec<u8> {
+        if self.layout == GeoJsonLayout::FeatureCollection {
+            buffer.extend_from_slice(b"\n]}\n");
+        }
+        buffer
+    }
+
+    fn separator(&self) -> &'static [u8] {
+        match self.layout {
+            GeoJsonLayout::FeatureCollection => b",\n",
+            GeoJsonLayout::NewlineDelimited => b"",
+        }
+    }
+}
+
+/// Writes the rows of a [`RecordBatch`] as GeoJSON Feature objects
+struct FeatureWriter<'a> {
+    /// The feature geometry column, if any
+    geometry: Option<&'a dyn Array>,
+    /// The other columns, written as properties
+    properties: Vec<Property<'a>>,
+}
+
+/// A column written as a feature property
+struct Property<'a> {
+    /// The JSON encoded column name
+    name: String,
+    kind: ColumnKind,
+    array: &'a dyn Array,
+    formatter: ArrayFormatter<'a>,
+}
+
+impl<'a> FeatureWriter<'a> {
+    fn new(batch: &'a RecordBatch) -> Self {
+        Self::with_feature_geometry(batch, true)
+    }
+
+    /// Create a writer with the first geometry column as the feature
+    /// geometry if `feature_geometry`, or all the columns as properties
+    fn with_feature_geometry(batch: &'a RecordBatch, feature_geometry: bool) -> Self {
+        let options = FormatOptions::default();
+        let mut geometry = None;
+        let mut properties = vec![];
+        for (field, array) in batch.schema_ref().fields().iter().zip(batch.columns()) {
+            let kind = ColumnKind::new(field.data_type());
+            if feature_geometry && geometry.is_none() && matches!(kind, ColumnKind::Geometry) {
+                geometry = Some(array.as_ref());
+                continue;
+            }
+            let name = serde_json::to_string(field.name()).expect("names can be serialized");
+            let formatter = ArrayFormatter::try_new(array.as_ref(), &options)
+                .expect("all generated types can be formatted");
+            properties.push(Property {
+                name,
+                kind,
+                array: array.as_ref(),
+                formatter,
+            });
+        }
+        Self {
+            geometry,
+            properties,
+        }
+    }
+
+    /// Write `row` as a Feature object
+    fn write(&self, buffer: &mut Vec<u8>, row: usize) {
+        buffer.extend_from_slice(b"{\"type\":\"Feature\",\"geometry\":");
+        match self.geometry {
+            Some(array) => write_geometry(buffer, array, row),
+            None => buffer.extend_from_slice(b"null"),
+        }
+
+        buffer.extend_from_slice(b",\"properties\":");
+        self.write_properties(buffer, row);
+        buffer.push(b'}');
+    }
+
+    /// Write the properties of `row` as a JSON object
+    fn write_properties(&self, buffer: &mut Vec<u8>, row: usize) {
+        buffer.push(b'{');
+        for (i, property) in self.properties.iter().enumerate() {
+            if i > 0 {
+                buffer.push(b',');
+            }
+            buffer.extend_from_slice(property.name.as_bytes());
+            buffer.push(b':');
+            match property.kind {
+                ColumnKind::Geometry => write_geometry(buffer, property.array, row),
+                _ if property.array.is_null(row) => buffer.extend_from_slice(b"null"),
+                ColumnKind::Number | ColumnKind::Boolean => {
+                    write!(buffer, "{}", property.formatter.value(row))
+                        .expect("writing to memory is infallible")
+                }
+                ColumnKind::String => {
+                    let value = property.formatter.value(row).to_string();
+                    serde_json::to_writer(&mut *buffer, &value)
+                        .expect("writing to memory is infallible");
+                }
+            }
+        }
+        buffer.push(b'}');
+    }
+}
+
+/// Write the rows of `batch` as JSON objects, one per line, with the
+/// geometry columns as GeoJSON geometry objects
+pub(crate) fn write_json_lines(batch: &RecordBatch, buffer: &mut Vec<u8>) {
+    let writer = FeatureWriter::with_feature_geometry(batch, false);
+    for row in 0..batch.num_rows() {
+        writer.write_properties(buffer, row);
+        buffer.push(b'\n');
+    }
+}
+
+/// Write the WKB value at `row` of `array` as a GeoJSON geometry object
+fn write_geometry(buffer: &mut Vec<u8>, array: &dyn Array, row: usize) {
+    if array.is_null(row) {
+        buffer.extend_from_slice(b"null");
+        return;
+    }
+    let wkb = match array.data_type() {
+        DataType::Binary => array.as_binary::<i32>().value(row),
+        DataType::LargeBinary => array.as_binary::<i64>().value(row),
+        _ => array.as_binary_view().value(row),
+    };
+    // positions have an elevation if the WKB has a Z
+    let mut writer = GeoJsonWriter::with_dims(&mut *buffer, CoordDimensions::xyz());
+    if Wkb(wkb).process_geom(&mut writer).is_err() {
+        // generated geometries are always valid WKB, but don't write invalid JSON
+        buffer.extend_from_slice(b"null");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::generate_in_chunks;
+    use crate::generate::tests::MemorySink;
+    use spatialbench::generators::TripGenerator;
+    use spatialbench_arrow::TripArrow;
+
+    async fn generate(layout: GeoJsonLayout) -> String {
+        let sink = MemorySink::default();
+        let sources = (1..=3)
+            .map(|part| TripArrow::new(TripGenerator::new(0.001, part, 3)).with_batch_size(10))
+            .map(move |source| GeoJsonSource::new(source, layout));
+        generate_in_chunks(sink.clone(), sources, 2).await.unwrap();
+        let output = sink.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_feature_collection() {
+        let output = generate(GeoJsonLayout::FeatureCollection).await;
+        let collection: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(collection["type"], "FeatureCollection");
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(
+            features.len() as i64,
+            TripGenerator::calculate_row_count(0.001, 1, 1)
+        );
+
+        let feature = &features[0];
+        assert_eq!(feature["type"], "Feature");
+        assert_eq!(feature["geometry"]["type"], "Point");
+        let properties = &feature["properties"];
+        assert_eq!(properties["t_tripkey"], 1);
+        assert!(properties["t_fare"].is_number());
+        assert!(properties["t_pickuptime"].is_string());
+        assert_eq!(properties["t_dropoffloc"]["type"], "Point");
+        assert!(properties.get("t_pickuploc").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_newline_delimited() {
+        let output = generate(GeoJsonLayout::NewlineDelimited).await;
+        let features: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            features.len() as i64,
+            TripGenerator::calculate_row_count(0.001, 1, 1)
+        );
+        assert!(features.iter().all(|feature| feature["type"] == "Feature"));
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let batch = TripArrow::new(TripGenerator::new(0.001, 1, 1))
+            .with_batch_size(10)
+            .next()
+            .unwrap();
+        let mut buffer = vec![];
+        write_json_lines(&batch, &mut buffer);
+        let rows: Vec<serde_json::Value> = String::from_utf8(buffer)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 10);
+        assert_eq!(rows[0]["t_tripkey"], 1);
+        assert_eq!(rows[0]["t_pickuploc"]["type"], "Point");
+        assert_eq!(rows[0]["t_dropoffloc"]["type"], "Point");
+    }
+}
@@ -0,0 +1,933 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! [GeoParquet] metadata and bounding box covering columns
+//!
+//! * [`GeoParquetVersion`]: which version of the specification to write
+//! * [`GeoParquetEncoder`]: adds the covering columns to each batch and
+//!   creates the `geo` file metadata
+//! * [`GeoStatistics`]: bounding boxes and geometry types seen while writing
+//!
+//! All geometry columns in Spatial Bench are WKB encoded `Binary` columns of
+//! longitude / latitude coordinates, so the `crs` is omitted, which means
+//! `OGC:CRS84` according to the specification. Geometries projected with
+//! `--crs` have the PROJJSON of their CRS instead.
+//!
+//! [GeoParquet]: https://geoparquet.org/releases/v1.1.0/
+
+use crate::geoarrow::GeoArrowType;
+use arrow::array::{Array, ArrayRef, AsArray, Float64Builder, RecordBatch, StructArray};
+use arrow::buffer::NullBuffer;
+use arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef};
+use clap::ValueEnum;
+use parquet::format::KeyValue;
+use serde_json::{json, Map, Value};
+use spatialbench::spatial::crs::{self, Crs};
+use spatialbench::spatial::geography;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// Which version of the GeoParquet specification to write
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum GeoParquetVersion {
+    /// GeoParquet 1.0: `geo` file metadata only
+    #[value(name = "1.0")]
+    V1_0,
+    /// GeoParquet 1.1: `geo` file metadata and a `bbox` covering column for
+    /// each geometry column
+    #[value(name = "1.1")]
+    V1_1,
+}
+
+impl GeoParquetVersion {
+    fn version_string(&self) -> &'static str {
+        match self {
+            GeoParquetVersion::V1_0 => "1.0.0",
+            GeoParquetVersion::V1_1 => "1.1.0",
+        }
+    }
+
+    /// Does this version write bounding box covering columns?
+    fn has_covering(&self) -> bool {
+        *self == GeoParquetVersion::V1_1
+    }
+}
+
+/// Adds GeoParquet covering columns to batches and creates the `geo` metadata
+///
+/// The geometry columns are the `Binary` (WKB) columns of the input schema.
+/// For GeoParquet 1.1, a `{column}_bbox` struct column with `xmin`, `ymin`,
+/// `xmax` and `ymax` fields is appended to the schema for each of them.
+#[derive(Debug, Clone)]
+pub struct GeoParquetEncoder {
+    version: GeoParquetVersion,
+    /// Indexes of the geometry columns in the input schema
+    geometry_columns: Vec<usize>,
+    /// The schema of the encoded batches
+    schema: SchemaRef,
+    /// The native GeoArrow type of geometry columns that are not written as WKB
+    native_types: HashMap<String, GeoArrowType>,
+    /// The CRS of the geometries, set with [`crs::set_crs`]
+    crs: Crs,
+    /// Whether the edges are great circles, set with
+    /// [`geography::set_geography`]
+    spherical: bool,
+}
+
+impl GeoParquetEncoder {
+    /// Create a new encoder for batches with `schema`
+    pub fn new(schema: &SchemaRef, version: GeoParquetVersion) -> Self {
+        let geometry_columns: Vec<usize> = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| is_wkb_type(field.data_type()))
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut fields: Vec<_> = schema.fields().iter().cloned().collect();
+        if version.has_covering() {
+            for &i in &geometry_columns {
+                let name = bbox_column_name(schema.field(i).name());
+                fields.push(Arc::new(Field::new(
+                    name,
+                    DataType::Struct(bbox_fields()),
+                    true,
+                )));
+            }
+        }
+        let schema = Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()));
+
+        Self {
+            version,
+            geometry_columns,
+            schema,
+            native_types: HashMap::new(),
+            crs: crs::crs(),
+            spherical: geography::geography(),
+        }
+    }
+
+    /// Set the native GeoArrow types of geometry columns that are written
+    /// with a native encoding instead of WKB (see [`GeoArrowEncoder`])
+    ///
+    /// [`GeoArrowEncoder`]: crate::geoarrow::GeoArrowEncoder
+    pub fn with_native_types(mut self, native_types: HashMap<String, GeoArrowType>) -> Self {
+        self.native_types = native_types;
+        self
+    }
+
+    /// Return the schema of the encoded batches
+    pub fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Return an empty [`GeoStatistics`] for the geometry columns
+    pub fn new_statistics(&self) -> GeoStatistics {
+        GeoStatistics {
+            columns: vec![ColumnStatistics::default(); self.geometry_columns.len()],
+        }
+    }
+
+    /// Update `statistics` with the geometries in `batch` and add the
+    /// covering columns (if any)
+    pub fn encode(&self, batch: RecordBatch, statistics: &mut GeoStatistics) -> RecordBatch {
+        let mut covering: Vec<ArrayRef> = vec![];
+        for (&i, column_statistics) in self.geometry_columns.iter().zip(&mut statistics.columns) {
+            let mut builder = BboxBuilder::with_capacity(batch.num_rows());
+            for wkb in wkb_values(batch.column(i)) {
+                let bbox = wkb.and_then(|wkb| {
+                    let mut bbox = Bbox::new();
+                    let geometry_type = read_wkb(wkb, &mut bbox)?;
+                    column_statistics.geometry_types.insert(geometry_type);
+                    column_statistics.bbox.merge(&bbox);
+                    (!bbox.is_empty()).then_some(bbox)
+                });
+                builder.append(bbox);
+            }
+            if self.version.has_covering() {
+                covering.push(Arc::new(builder.finish()));
+            }
+        }
+        if covering.is_empty() {
+            return batch;
+        }
+
+        let mut columns = batch.columns().to_vec();
+        columns.extend(covering);
+        RecordBatch::try_new(Arc::clone(&self.schema), columns)
+            .expect("covering columns match schema")
+    }
+
+    /// Return the `geo` file metadata describing the geometry columns
+    pub fn metadata(&self, statistics: &GeoStatistics) -> KeyValue {
+        let mut columns = Map::new();
+        for (&i, column_statistics) in self.geometry_columns.iter().zip(&statistics.columns) {
+            let name = self.schema.field(i).name();
+            let mut column = Map::new();
+            let encoding = self
+                .native_types
+                .get(name)
+                .map_or("WKB", |native_type| native_type.geoparquet_encoding());
+            column.insert("encoding".into(), json!(encoding));
+            column.insert(
+                "geometry_types".into(),
+                json!(column_statistics.geometry_types),
+            );
+            if !column_statistics.bbox.is_empty() {
+                let bbox = &column_statistics.bbox;
+                column.insert(
+                    "bbox".into(),
+                    json!([bbox.xmin, bbox.ymin, bbox.xmax, bbox.ymax]),
+                );
+            }
+            if self.version.has_covering() {
+                let bbox_name = bbox_column_name(name);
+                column.insert(
+                    "covering".into(),
+                    json!({
+                        "bbox": {
+                            "xmin": [bbox_name, "xmin"],
+                            "ymin": [bbox_name, "ymin"],
+                            "xmax": [bbox_name, "xmax"],
+                            "ymax": [bbox_name, "ymax"],
+                        }
+                    }),
+                );
+            }
+            if let Some(crs) = projjson(self.crs) {
+                column.insert("crs".into(), crs);
+            }
+            if self.spherical {
+                column.insert("edges".into(), json!("spherical"));
+            }
+            columns.insert(name.clone(), Value::Object(column));
+        }
+
+        let primary_column = self
+            .geometry_columns
+            .first()
+            .map(|&i| self.schema.field(i).name().clone())
+            .unwrap_or_default();
+        let geo = json!({
+            "version": self.version.version_string(),
+            "primary_column": primary_column,
+            "columns": columns,
+        });
+        KeyValue::new("geo".to_string(), geo.to_string())
+    }
+}
+
+/// Merge the `geo` file metadata of files with the same geometry columns,
+/// e.g. of the parts of a table merged into a single file
+///
+/// The bounding box and geometry types of each column are the union of
+/// those of the files, and the rest is the metadata of the first file.
+pub fn merge_metadata<'a>(
+    values: impl IntoIterator<Item = &'a str>,
+) -> Result<Option<String>, serde_json::Error> {
+    let mut merged: Option<Value> = None;
+    for value in values {
+        let geo: Value = serde_json::from_str(value)?;
+        let Some(merged) = merged.as_mut() else {
+            merged = Some(geo);
+            continue;
+        };
+        let (Some(columns), Some(others)) = (
+            merged["columns"].as_object_mut(),
+            geo["columns"].as_object(),
+        ) else {
+            continue;
+        };
+        for (name, column) in columns.iter_mut() {
+            let Some(other) = others.get(name) else {
+                continue;
+            };
+            let geometry_types: BTreeSet<&str> =
+                [&column["geometry_types"], &other["geometry_types"]]
+                    .into_iter()
+                    .filter_map(Value::as_array)
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect();
+            let geometry_types = json!(geometry_types);
+            let mut bbox = json_bbox(column);
+            bbox.merge(&json_bbox(other));
+            column["geometry_types"] = geometry_types;
+            if !bbox.is_empty() {
+                column["bbox"] = json!([bbox.xmin, bbox.ymin, bbox.xmax, bbox.ymax]);
+            }
+        }
+    }
+    Ok(merged.map(|geo| geo.to_string()))
+}
+
+/// Return the `bbox` of a column of the `geo` metadata, empty if it has none
+fn json_bbox(column: &Value) -> Bbox {
+    let values: Option<Vec<f64>> = column["bbox"]
+        .as_array()
+        .and_then(|values| values.iter().map(Value::as_f64).collect());
+    match values.as_deref() {
+        Some(&[xmin, ymin, xmax, ymax]) => Bbox {
+            xmin,
+            ymin,
+            xmax,
+            ymax,
+        },
+        _ => Bbox::new(),
+    }
+}
+
+/// Return the PROJJSON of `crs`, or None for longitude / latitude, which is
+/// the default of GeoParquet
+fn projjson(crs: Crs) -> Option<Value> {
+    let (conversion, method, method_code) = match crs {
+        Crs::Wgs84 => return None,
+        Crs::WebMercator => (
+            "Popular Visualisation Pseudo-Mercator".to_string(),
+            "Popular Visualisation Pseudo Mercator",
+            1024,
+        ),
+        Crs::WorldMercator => ("World Mercator".to_string(), "Mercator (variant A)", 9804),
+        Crs::Utm { zone, south } => (
+            format!("UTM zone {zone}{}", if south { 'S' } else { 'N' }),
+            "Transverse Mercator",
+            9807,
+        ),
+    };
+    let parameter = |name: &str, code: u32, value: f64, unit: &str| {
+        json!({
+            "name": name,
+            "value": value,
+            "unit": unit,
+            "id": {"authority": "EPSG", "code": code},
+        })
+    };
+    let (scale_factor, false_northing) = match crs {
+        Crs::Utm { south, .. } => (0.9996, if south { 10_000_000.0 } else { 0.0 }),
+        _ => (1.0, 0.0),
+    };
+    let mut parameters = vec![
+        parameter("Latitude of natural origin", 8801, 0.0, "degree"),
+        parameter(
+            "Longitude of natural origin",
+            8802,
+            crs.central_meridian(),
+            "degree",
+        ),
+    ];
+    if crs != Crs::WebMercator {
+        parameters.push(parameter(
+            "Scale factor at natural origin",
+            8805,
+            scale_factor,
+            "unity",
+        ));
+    }
+    let false_easting = if matches!(crs, Crs::Utm { .. }) {
+        500_000.0
+    } else {
+        0.0
+    };
+    parameters.push(parameter("False easting", 8806, false_easting, "metre"));
+    parameters.push(parameter("False northing", 8807, false_northing, "metre"));
+
+    Some(json!({
+        "$schema": "https://proj.org/schemas/v0.7/projjson.schema.json",
+        "type": "ProjectedCRS",
+        "name": crs.name(),
+        "base_crs": {
+            "type": "GeographicCRS",
+            "name": "WGS 84",
+            "datum": {
+                "type": "GeodeticReferenceFrame",
+                "name": "World Geodetic System 1984",
+                "ellipsoid": {
+                    "name": "WGS 84",
+                    "semi_major_axis": 6378137,
+                    "inverse_flattening": 298.257223563,
+                },
+            },
+            "coordinate_system": {
+                "subtype": "ellipsoidal",
+                "axis": [
+                    {"name": "Geodetic latitude", "abbreviation": "Lat", "direction": "north", "unit": "degree"},
+                    {"name": "Geodetic longitude", "abbreviation": "Lon", "direction": "east", "unit": "degree"},
+                ],
+            },
+            "id": {"authority": "EPSG", "code": 4326},
+        },
+        "conversion": {
+            "name": conversion,
+            "method": {"name": method, "id": {"authority": "EPSG", "code": method_code}},
+            "parameters": parameters,
+        },
+        "coordinate_system": {
+            "subtype": "Cartesian",
+            "axis": [
+                {"name": "Easting", "abbreviation": "E", "direction": "east", "unit": "metre"},
+                {"name": "Northing", "abbreviation": "N", "direction": "north", "unit": "metre"},
+            ],
+        },
+        "id": {"authority": "EPSG", "code": crs.epsg_code()},
+    }))
+}
+
+/// Bounding boxes and geometry types of the geometry columns written so far
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeoStatistics {
+    columns: Vec<ColumnStatistics>,
+}
+
+impl GeoStatistics {
+    /// Merge the statistics from `other` (e.g. from another row group)
+    pub fn merge(&mut self, other: &GeoStatistics) {
+        if self.columns.is_empty() {
+            self.columns = other.columns.clone();
+            return;
+        }
+        for (column, other) in self.columns.iter_mut().zip(&other.columns) {
+            column.bbox.merge(&other.bbox);
+            column
+                .geometry_types
+                .extend(other.geometry_types.iter().copied());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ColumnStatistics {
+    bbox: Bbox,
+    geometry_types: BTreeSet<&'static str>,
+}
+
+/// A 2D bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bbox {
+    xmin: f64,
+    ymin: f64,
+    xmax: f64,
+    ymax: f64,
+}
+
+impl Default for Bbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bbox {
+    /// Create a new empty bounding box
+    fn new() -> Self {
+        Self {
+            xmin: f64::INFINITY,
+            ymin: f64::INFINITY,
+            xmax: f64::NEG_INFINITY,
+            ymax: f64::NEG_INFINITY,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.xmin > self.xmax
+    }
+
+    fn add_point(&mut self, x: f64, y: f64) {
+        // empty points are encoded as NaN coordinates
+        if x.is_nan() || y.is_nan() {
+            return;
+        }
+        self.xmin = self.xmin.min(x);
+        self.ymin = self.ymin.min(y);
+        self.xmax = self.xmax.max(x);
+        self.ymax = self.ymax.max(y);
+    }
+
+    fn merge(&mut self, other: &Bbox) {
+        self.xmin = self.xmin.min(other.xmin);
+        self.ymin = self.ymin.min(other.ymin);
+        self.xmax = self.xmax.max(other.xmax);
+        self.ymax = self.ymax.max(other.ymax);
+    }
+}
+
+/// Builds a `bbox` covering struct column
+struct BboxBuilder {
+    xmin: Float64Builder,
+    ymin: Float64Builder,
+    xmax: Float64Builder,
+    ymax: Float64Builder,
+    validity: Vec<bool>,
+}
+
+impl BboxBuilder {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            xmin: Float64Builder::with_capacity(capacity),
+            ymin: Float64Builder::with_capacity(capacity),
+            xmax: Float64Builder::with_capacity(capacity),
+            ymax: Float64Builder::with_capacity(capacity),
+            validity: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Append a bounding box, or null for null / empty geometries
+    fn append(&mut self, bbox: Option<Bbox>) {
+        let values = bbox.unwrap_or(Bbox {
+            xmin: 0.0,
+            ymin: 0.0,
+            xmax: 0.0,
+            ymax: 0.0,
+        });
+        self.xmin.append_value(values.xmin);
+        self.ymin.append_value(values.ymin);
+        self.xmax.append_value(values.xmax);
+        self.ymax.append_value(values.ymax);
+        self.validity.push(bbox.is_some());
+    }
+
+    fn finish(mut self) -> StructArray {
+        let nulls = NullBuffer::from(self.validity);
+        let nulls = (nulls.null_count() > 0).then_some(nulls);
+        StructArray::new(
+            bbox_fields(),
+            vec![
+                Arc::new(self.xmin.finish()),
+                Arc::new(self.ymin.finish()),
+                Arc::new(self.xmax.finish()),
+                Arc::new(self.ymax.finish()),
+            ],
+            nulls,
+        )
+    }
+}
+
+fn bbox_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("xmin", DataType::Float64, false),
+        Field::new("ymin", DataType::Float64, false),
+        Field::new("xmax", DataType::Float64, false),
+        Field::new("ymax", DataType::Float64, false),
+    ])
+}
+
+fn bbox_column_name(geometry_column: &str) -> String {
+    format!("{geometry_column}_bbox")
+}
+
+pub fn is_wkb_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView
+    )
+}
+
+/// Return the WKB values of a geometry column
+pub fn wkb_values(array: &ArrayRef) -> Box<dyn Iterator<Item = Option<&[u8]>> + '_> {
+    match array.data_type() {
+        DataType::Binary => Box::new(array.as_binary::<i32>().iter()),
+        DataType::LargeBinary => Box::new(array.as_binary::<i64>().iter()),
+        DataType::BinaryView => Box::new(array.as_binary_view().iter()),
+        other => panic!("Unexpected geometry column type {other}"),
+    }
+}
+
+/// Return the bounding box of a WKB geometry as `[xmin, ymin, xmax, ymax]`,
+/// or None if it is empty or invalid
+pub fn wkb_bbox(wkb: &[u8]) -> Option<[f64; 4]> {
+    let mut bbox = Bbox::new();
+    read_wkb(wkb, &mut bbox)?;
+    (!bbox.is_empty()).then_some([bbox.xmin, bbox.ymin, bbox.xmax, bbox.ymax])
+}
+
+/// Return the center of the bounding box of a WKB geometry, or None if it is
+/// empty or invalid
+pub fn wkb_center(wkb: &[u8]) -> Option<(f64, f64)> {
+    let mut bbox = Bbox::new();
+    read_wkb(wkb, &mut bbox)?;
+    (!bbox.is_empty()).then(|| ((bbox.xmin + bbox.xmax) / 2.0, (bbox.ymin + bbox.ymax) / 2.0))
+}
+
+/// Return the bounding box (None if the geometry is empty) and the number of
+/// vertices of a WKB geometry, or None if it is invalid
+pub fn wkb_bbox_vertices(wkb: &[u8]) -> Option<(Option<[f64; 4]>, usize)> {
+    let mut bbox = Bbox::new();
+    let mut reader = WkbReader::new(wkb);
+    reader.read_geometry(&mut bbox)?;
+    let bbox = (!bbox.is_empty()).then_some([bbox.xmin, bbox.ymin, bbox.xmax, bbox.ymax]);
+    Some((bbox, reader.vertices))
+}
+
+/// Reads a single WKB (ISO or EWKB) geometry, adding its coordinates to
+/// `bbox`
+///
+/// Returns the GeoParquet geometry type name (e.g. `"Point"` or
+/// `"Polygon Z"`), or None if the WKB is invalid.
+fn read_wkb(wkb: &[u8], bbox: &mut Bbox) -> Option<&'static str> {
+    let mut reader = WkbReader::new(wkb);
+    reader.read_geometry(bbox)
+}
+
+/// The Z and M values of the coordinates of a WKB geometry, in the order
+/// they are written, if it has a Z or an M dimension
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ordinates {
+    pub z: Option<Vec<f64>>,
+    pub m: Option<Vec<f64>>,
+}
+
+/// Return the Z and M values of a WKB geometry, or None if it is invalid
+pub fn wkb_ordinates(wkb: &[u8]) -> Option<Ordinates> {
+    let mut reader = WkbReader::new(wkb);
+    reader.read_geometry(&mut Bbox::new())?;
+    Some(Ordinates {
+        z: reader.has_z.then_some(reader.z),
+        m: reader.has_m.then_some(reader.m),
+    })
+}
+
+struct WkbReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    /// Whether any of the geometries read so far has a Z or an M
+    has_z: bool,
+    has_m: bool,
+    /// Z and M values of the coordinates read so far
+    z: Vec<f64>,
+    m: Vec<f64>,
+    /// Number of coordinates read so far
+    vertices: usize,
+}
+
+impl<'a> WkbReader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            has_z: false,
+            has_m: false,
+            z: vec![],
+            m: vec![],
+            vertices: 0,
+        }
+    }
+
+    fn read_geometry(&mut self, bbox: &mut Bbox) -> Option<&'static str> {
+        let little_endian = match self.read_u8()? {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let raw_type = self.read_u32(little_endian)?;
+        // EWKB flags
+        let mut has_z = raw_type & 0x8000_0000 != 0;
+        let mut has_m = raw_type & 0x4000_0000 != 0;
+        if raw_type & 0x2000_0000 != 0 {
+            self.read_u32(little_endian)?; // SRID
+        }
+        // ISO WKB dimensions
+        let iso_type = raw_type & 0x0FFF_FFFF;
+        match iso_type / 1000 {
+            0 => {}
+            1 => has_z = true,
+            2 => has_m = true,
+            3 => (has_z, has_m) = (true, true),
+            _ => return None,
+        }
+        self.has_z |= has_z;
+        self.has_m |= has_m;
+
+        let name = match iso_type % 1000 {
+            1 => {
+                self.read_coords(1, has_z, has_m, little_endian, bbox)?;
+                ["Point", "Point Z"]
+            }
+            2 => {
+                let n = self.read_u32(little_endian)? as usize;
+                self.read_coords(n, has_z, has_m, little_endian, bbox)?;
+                ["LineString", "LineString Z"]
+            }
+            3 => {
+                for _ in 0..self.read_u32(little_endian)? {
+                    let n = self.read_u32(little_endian)? as usize;
+                    self.read_coords(n, has_z, has_m, little_endian, bbox)?;
+                }
+                ["Polygon", "Polygon Z"]
+            }
+            geometry_type @ 4..=7 => {
+                for _ in 0..self.read_u32(little_endian)? {
+                    self.read_geometry(bbox)?;
+                }
+                match geometry_type {
+                    4 => ["MultiPoint", "MultiPoint Z"],
+                    5 => ["MultiLineString", "MultiLineString Z"],
+                    6 => ["MultiPolygon", "MultiPolygon Z"],
+                    _ => ["GeometryCollection", "GeometryCollection Z"],
+                }
+            }
+            _ => return None,
+        };
+        Some(name[has_z as usize])
+    }
+
+    /// Read `n` coordinates, adding the x/y to `bbox` and keeping the z and
+    /// m
+    fn read_coords(
+        &mut self,
+        n: usize,
+        has_z: bool,
+        has_m: bool,
+        little_endian: bool,
+        bbox: &mut Bbox,
+    ) -> Option<()> {
+        self.vertices += n;
+        for _ in 0..n {
+            let x = self.read_f64(little_endian)?;
+            let y = self.read_f64(little_endian)?;
+            if has_z {
+                let z = self.read_f64(little_endian)?;
+                self.z.push(z);
+            }
+            if has_m {
+                let m = self.read_f64(little_endian)?;
+                self.m.push(m);
+            }
+            bbox.add_point(x, y);
+        }
+        Some(())
+    }
+
+    fn read_bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.buf.get(self.pos..self.pos + N)?;
+        self.pos += N;
+        bytes.try_into().ok()
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        self.read_bytes::<1>().map(|b| b[0])
+    }
+
+    fn read_u32(&mut self, little_endian: bool) -> Option<u32> {
+        let bytes = self.read_bytes::<4>()?;
+        Some(match little_endian {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        })
+    }
+
+    fn read_f64(&mut self, little_endian: bool) -> Option<f64> {
+        let bytes = self.read_bytes::<8>()?;
+        Some(match little_endian {
+            true => f64::from_le_bytes(bytes),
+            false => f64::from_be_bytes(bytes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{BinaryArray, Int64Array};
+
+    /// Little endian WKB for a point
+    fn point_wkb(x: f64, y: f64) -> Vec<u8> {
+        let mut wkb = vec![1];
+        wkb.extend_from_slice(&1u32.to_le_bytes());
+        wkb.extend_from_slice(&x.to_le_bytes());
+        wkb.extend_from_slice(&y.to_le_bytes());
+        wkb
+    }
+
+    /// Big endian WKB for a polygon with a single ring
+    fn polygon_wkb(ring: &[(f64, f64)]) -> Vec<u8> {
+        let mut wkb = vec![0];
+        wkb.extend_from_slice(&3u32.to_be_bytes());
+        wkb.extend_from_slice(&1u32.to_be_bytes());
+        wkb.extend_from_slice(&(ring.len() as u32).to_be_bytes());
+        for (x, y) in ring {
+            wkb.extend_from_slice(&x.to_be_bytes());
+            wkb.extend_from_slice(&y.to_be_bytes());
+        }
+        wkb
+    }
+
+    #[test]
+    fn test_read_wkb() {
+        let mut bbox = Bbox::new();
+        assert_eq!(read_wkb(&point_wkb(1.0, 2.0), &mut bbox), Some("Point"));
+        let ring = [(0.0, 0.0), (3.0, -1.0), (2.0, 5.0), (0.0, 0.0)];
+        assert_eq!(read_wkb(&polygon_wkb(&ring), &mut bbox), Some("Polygon"));
+        assert_eq!(
+            bbox,
+            Bbox {
+                xmin: 0.0,
+                ymin: -1.0,
+                xmax: 3.0,
+                ymax: 5.0
+            }
+        );
+
+        // truncated
+        assert_eq!(read_wkb(&point_wkb(1.0, 2.0)[..10], &mut bbox), None);
+
+        // ISO WKB point with a Z
+        let mut wkb = vec![1];
+        wkb.extend_from_slice(&1001u32.to_le_bytes());
+        for value in [1.0f64, 2.0, 350.5] {
+            wkb.extend_from_slice(&value.to_le_bytes());
+        }
+        assert_eq!(read_wkb(&wkb, &mut bbox), Some("Point Z"));
+        let ordinates = wkb_ordinates(&wkb).unwrap();
+        assert_eq!(ordinates.z, Some(vec![350.5]));
+        assert_eq!(ordinates.m, None);
+        assert_eq!(
+            wkb_ordinates(&point_wkb(1.0, 2.0)),
+            Some(Ordinates::default())
+        );
+
+        // with an M, GeoParquet has no geometry type
+        wkb[1..5].copy_from_slice(&2001u32.to_le_bytes());
+        assert_eq!(read_wkb(&wkb, &mut bbox), Some("Point"));
+        assert_eq!(wkb_ordinates(&wkb).unwrap().m, Some(vec![350.5]));
+    }
+
+    fn test_batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("key", DataType::Int64, false),
+            Field::new("loc", DataType::Binary, false),
+        ]));
+        let wkbs = [point_wkb(1.0, 2.0), point_wkb(-3.0, 4.0)];
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(BinaryArray::from_iter_values(wkbs.iter())),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_encode_v1_1() {
+        let batch = test_batch();
+        let encoder = GeoParquetEncoder::new(batch.schema_ref(), GeoParquetVersion::V1_1);
+        let mut statistics = encoder.new_statistics();
+        let encoded = encoder.encode(batch, &mut statistics);
+
+        assert_eq!(encoded.num_columns(), 3);
+        assert_eq!(encoded.schema().field(2).name(), "loc_bbox");
+        let bbox = encoded.column(2).as_struct();
+        let xmin = bbox.column_by_name("xmin").unwrap();
+        assert_eq!(
+            xmin.as_primitive::<arrow::datatypes::Float64Type>()
+                .values(),
+            &[1.0, -3.0]
+        );
+
+        let metadata = encoder.metadata(&statistics);
+        assert_eq!(metadata.key, "geo");
+        let geo: Value = serde_json::from_str(metadata.value.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            geo,
+            json!({
+                "version": "1.1.0",
+                "primary_column": "loc",
+                "columns": {
+                    "loc": {
+                        "encoding": "WKB",
+                        "geometry_types": ["Point"],
+                        "bbox": [-3.0, 2.0, 1.0, 4.0],
+                        "covering": {
+                            "bbox": {
+                                "xmin": ["loc_bbox", "xmin"],
+                                "ymin": ["loc_bbox", "ymin"],
+                                "xmax": ["loc_bbox", "xmax"],
+                                "ymax": ["loc_bbox", "ymax"],
+                            }
+                        }
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode_with_crs() {
+        let batch = test_batch();
+        let mut encoder = GeoParquetEncoder::new(batch.schema_ref(), GeoParquetVersion::V1_0);
+        encoder.crs = Crs::Utm {
+            zone: 33,
+            south: true,
+        };
+        let mut statistics = encoder.new_statistics();
+        encoder.encode(batch, &mut statistics);
+        let metadata = encoder.metadata(&statistics);
+        let geo: Value = serde_json::from_str(metadata.value.as_deref().unwrap()).unwrap();
+        let crs = &geo["columns"]["loc"]["crs"];
+        assert_eq!(crs["type"], "ProjectedCRS");
+        assert_eq!(crs["name"], "WGS 84 / UTM zone 33S");
+        assert_eq!(crs["id"], json!({"authority": "EPSG", "code": 32733}));
+        let parameters = crs["conversion"]["parameters"].as_array().unwrap();
+        assert_eq!(parameters[1]["value"], 15.0);
+        assert_eq!(parameters[4]["value"], 10_000_000.0);
+        assert_eq!(geo["columns"]["loc"].get("edges"), None);
+
+        encoder.spherical = true;
+        let metadata = encoder.metadata(&statistics);
+        let geo: Value = serde_json::from_str(metadata.value.as_deref().unwrap()).unwrap();
+        assert_eq!(geo["columns"]["loc"]["edges"], "spherical");
+    }
+
+    #[test]
+    fn test_encode_v1_0() {
+        let batch = test_batch();
+        let encoder = GeoParquetEncoder::new(batch.schema_ref(), GeoParquetVersion::V1_0);
+        let mut statistics = encoder.new_statistics();
+        let encoded = encoder.encode(batch.clone(), &mut statistics);
+        assert_eq!(encoded, batch);
+
+        // statistics from different row groups are combined
+        let mut combined = GeoStatistics::default();
+        combined.merge(&statistics);
+        let mut other = encoder.new_statistics();
+        encoder.encode(batch, &mut other);
+        combined.merge(&other);
+        assert_eq!(combined, statistics);
+
+        let metadata = encoder.metadata(&combined);
+        let geo: Value = serde_json::from_str(metadata.value.as_deref().unwrap()).unwrap();
+        assert_eq!(geo["version"], "1.0.0");
+        assert_eq!(geo["columns"]["loc"]["bbox"], json!([-3.0, 2.0, 1.0, 4.0]));
+        assert!(geo["columns"]["loc"].get("covering").is_none());
+    }
+
+    #[test]
+    fn test_merge_metadata() {
+        let first = r#"{"version":"1.1.0","primary_column":"loc","columns":{"loc":{"encoding":"WKB","geometry_types":["Point"],"bbox":[0.0,0.0,1.0,1.0]}}}"#;
+        let second = r#"{"version":"1.1.0","primary_column":"loc","columns":{"loc":{"encoding":"WKB","geometry_types":["Polygon","Point"],"bbox":[-1.0,0.5,0.5,2.0]}}}"#;
+        let empty = r#"{"version":"1.1.0","primary_column":"loc","columns":{"loc":{"encoding":"WKB","geometry_types":[]}}}"#;
+        let merged = merge_metadata([first, empty, second]).unwrap().unwrap();
+        let geo: Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(geo["primary_column"], "loc");
+        assert_eq!(geo["columns"]["loc"]["encoding"], "WKB");
+        assert_eq!(
+            geo["columns"]["loc"]["geometry_types"],
+            json!(["Point", "Polygon"])
+        );
+        assert_eq!(geo["columns"]["loc"]["bbox"], json!([-1.0, 0.0, 1.0, 2.0]));
+        assert_eq!(merge_metadata([]).unwrap(), None);
+    }
+}
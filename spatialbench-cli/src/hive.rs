@@ -0,0 +1,815 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Hive-style partitioned Parquet output
+//!
+//! A table is written as a directory with one subdirectory per value of the
+//! partition column, e.g. `trip/t_pickuptime_date=2024-01-01/part-0.parquet`,
+//! which DuckDB, Spark, DataFusion and others read as a single table.
+//!
+//! Timestamp columns are partitioned by day, using the `<column>_date` key,
+//! and are kept in the files. Other columns are partitioned by value and, as
+//! is conventional, removed from the files as the value is part of the path.
+
+use crate::geoarrow::{GeoArrowEncoder, GeometryEncoding};
+use crate::geoparquet::{GeoParquetEncoder, GeoParquetVersion, GeoStatistics};
+use crate::memory::{memory_limit, ChunkEstimate, Reservation};
+use crate::object_store_writer::{ObjectStoreWriter, UploadOptions};
+use crate::parquet::{IntoSize, SampledIterator};
+use crate::statistics::WriteStatistics;
+use arrow::array::{RecordBatch, UInt32Array};
+use arrow::compute::{cast, take_record_batch};
+use arrow::datatypes::{DataType, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use futures::StreamExt;
+use log::debug;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use spatialbench_arrow::RecordBatchIterator;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::hash::{BuildHasher, RandomState};
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+/// Directory name used for null (and empty) partition values, as in Hive
+pub const DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// Maximum number of bytes buffered in unflushed row groups across all the
+/// partitions of a plan (1GB)
+///
+/// Once exceeded, the largest row groups are flushed even if they are smaller
+/// than the target row group size.
+const MAX_BUFFERED_BYTES: usize = 1024 * 1024 * 1024;
+
+/// Size of the write buffer for each local partition file (1MB)
+///
+/// Smaller than the buffer used for single files, as there is one per
+/// partition.
+const FILE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Return the name of the marker file written to the table directory once all
+/// the partitions of `file_name` (e.g. `part-0.parquet`) are complete
+///
+/// Like the other files starting with `_`, it is ignored by readers.
+pub fn success_marker(file_name: &str) -> String {
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    format!("_SUCCESS.{stem}")
+}
+
+/// Assigns the rows of each batch to partitions
+pub trait Partitioner: Send + Sync + 'static {
+    /// Return the schema of the partitioned batches
+    fn schema(&self) -> &SchemaRef;
+
+    /// Return `batch` with the columns of the partitioned batches
+    fn project(&self, batch: &RecordBatch) -> Result<RecordBatch, ArrowError> {
+        Ok(batch.clone())
+    }
+
+    /// Split `batch` by partition, returning the partition directory name
+    /// and rows of each partition
+    fn split(&self, batch: &RecordBatch) -> Result<Vec<(String, RecordBatch)>, ArrowError>;
+}
+
+/// Return the rows of `batch` for each partition
+pub fn take_partitions(
+    batch: RecordBatch,
+    mut partitions: Vec<(String, Vec<u32>)>,
+) -> Result<Vec<(String, RecordBatch)>, ArrowError> {
+    if partitions.len() == 1 {
+        let (partition, _) = partitions.pop().expect("one partition");
+        return Ok(vec![(partition, batch)]);
+    }
+    partitions
+        .into_iter()
+        .map(|(partition, rows)| {
+            let rows = take_record_batch(&batch, &UInt32Array::from(rows))?;
+            Ok((partition, rows))
+        })
+        .collect()
+}
+
+/// Splits batches by the value of the partition column
+#[derive(Debug)]
+pub struct HivePartitioner {
+    /// Index of the partition column
+    column: usize,
+    /// The key of the partition directories (e.g. `t_pickuptime_date`)
+    key: String,
+    /// Partition by the day of a timestamp rather than the value
+    by_day: bool,
+    /// The schema of the partitioned batches
+    schema: SchemaRef,
+}
+
+impl HivePartitioner {
+    /// Create a partitioner for `column` of `schema`
+    ///
+    /// Returns `None` if the schema does not have the column, and an error if
+    /// the column can not be used as a partition key (e.g. a geometry).
+    pub fn try_new(schema: &SchemaRef, column: &str) -> io::Result<Option<Self>> {
+        let Ok(index) = schema.index_of(column) else {
+            return Ok(None);
+        };
+        let by_day = match schema.field(index).data_type() {
+            DataType::Timestamp(_, _) | DataType::Date64 => true,
+            DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Boolean
+            | DataType::Date32
+            | DataType::Utf8
+            | DataType::LargeUtf8
+            | DataType::Utf8View => false,
+            data_type => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Can not partition by column {column} of type {data_type}"),
+                ))
+            }
+        };
+        let (key, schema) = if by_day {
+            (format!("{column}_date"), Arc::clone(schema))
+        } else {
+            let schema = schema
+                .project(&Self::projection(schema.fields().len(), index))
+                .map_err(io::Error::other)?;
+            (column.to_string(), Arc::new(schema))
+        };
+        Ok(Some(Self {
+            column: index,
+            key,
+            by_day,
+            schema,
+        }))
+    }
+
+    /// Indices of all the columns except `column`
+    fn projection(num_columns: usize, column: usize) -> Vec<usize> {
+        (0..num_columns).filter(|&i| i != column).collect()
+    }
+}
+
+impl Partitioner for HivePartitioner {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Return `batch` with the partition column removed, if it is not kept
+    fn project(&self, batch: &RecordBatch) -> Result<RecordBatch, ArrowError> {
+        if self.by_day {
+            Ok(batch.clone())
+        } else {
+            batch.project(&Self::projection(batch.num_columns(), self.column))
+        }
+    }
+
+    /// The partition directory names are `key=value`, e.g.
+    /// `t_pickuptime_date=2024-01-01`
+    fn split(&self, batch: &RecordBatch) -> Result<Vec<(String, RecordBatch)>, ArrowError> {
+        let values = batch.column(self.column);
+        let values = if self.by_day {
+            cast(values, &DataType::Date32)?
+        } else {
+            Arc::clone(values)
+        };
+        let formatter = ArrayFormatter::try_new(values.as_ref(), &FormatOptions::default())?;
+        let mut partitions: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        let mut value = String::new();
+        for row in 0..values.len() {
+            value.clear();
+            if values.is_valid(row) {
+                formatter.value(row).write(&mut value)?;
+            }
+            let partition = if value.is_empty() {
+                DEFAULT_PARTITION.to_string()
+            } else {
+                escape_path_value(&value)
+            };
+            partitions.entry(partition).or_default().push(row as u32);
+        }
+
+        let partitions = partitions
+            .into_iter()
+            .map(|(value, rows)| (format!("{}={value}", self.key), rows))
+            .collect();
+        take_partitions(self.project(batch)?, partitions)
+    }
+}
+
+/// Escape the characters of a partition value that are not allowed in file
+/// names or have special meaning to Hive (e.g. `/` and `=`) as `%XX`
+fn escape_path_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_ascii_control() || "\"#%'*/:=?\\{[]^".contains(c) {
+            write!(escaped, "%{:02X}", c as u32).expect("writing to a String");
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Creates the file of each partition
+pub trait PartitionSink: Send + Sync + 'static {
+    type Writer: Write + Send + IntoSize;
+
+    /// Create the writer for the file in the partition directory `partition`
+    fn create(&self, partition: &str) -> io::Result<Self::Writer>;
+
+    /// Called once the file of `partition` is completely written
+    fn commit(&self, partition: &str) -> io::Result<()>;
+
+    /// Write a small file (e.g. a marker or manifest) to the table directory
+    ///
+    /// This may block, so must be called from a blocking thread.
+    fn write_file(&self, name: &str, contents: &[u8]) -> io::Result<()>;
+}
+
+/// Writes the partition files to a local directory
+///
+/// Each file is written to a temp file that is renamed once it is complete.
+pub struct LocalPartitionSink {
+    /// The table directory
+    dir: PathBuf,
+    /// The name of the file in each partition directory
+    file_name: String,
+}
+
+impl LocalPartitionSink {
+    pub fn new(dir: PathBuf, file_name: String) -> Self {
+        Self { dir, file_name }
+    }
+
+    fn path(&self, partition: &str) -> PathBuf {
+        self.dir.join(partition).join(&self.file_name)
+    }
+}
+
+impl PartitionSink for LocalPartitionSink {
+    type Writer = BufWriter<File>;
+
+    fn create(&self, partition: &str) -> io::Result<Self::Writer> {
+        let path = self.path(partition);
+        let dir = path.parent().expect("partition directory");
+        std::fs::create_dir_all(dir)
+            .map_err(|e| io::Error::other(format!("Error creating directory {dir:?}: {e}")))?;
+        let temp_path = path.with_extension("inprogress");
+        let file = File::create(&temp_path)
+            .map_err(|err| io::Error::other(format!("Failed to create {temp_path:?}: {err}")))?;
+        Ok(BufWriter::with_capacity(FILE_BUFFER_SIZE, file))
+    }
+
+    fn commit(&self, partition: &str) -> io::Result<()> {
+        let path = self.path(partition);
+        rename_temp_file(&path)
+    }
+
+    fn write_file(&self, name: &str, contents: &[u8]) -> io::Result<()> {
+        let path = self.dir.join(name);
+        let temp_path = path.with_extension("inprogress");
+        std::fs::write(&temp_path, contents)
+            .map_err(|err| io::Error::other(format!("Failed to write {temp_path:?}: {err}")))?;
+        rename_temp_file(&path)
+    }
+}
+
+/// Rename the `.inprogress` temp file for `path` to `path`
+fn rename_temp_file(path: &Path) -> io::Result<()> {
+    let temp_path = path.with_extension("inprogress");
+    std::fs::rename(&temp_path, path).map_err(|e| {
+        io::Error::other(format!(
+            "Failed to rename {temp_path:?} to {path:?} file: {e}"
+        ))
+    })
+}
+
+/// Uploads the partition files to an object store
+///
+/// Objects only become visible once their upload completes, so there is
+/// nothing to do on commit.
+pub struct ObjectStorePartitionSink {
+    /// The URL of the table directory
+    url: Url,
+    /// The name of the file in each partition directory
+    file_name: String,
+    options: UploadOptions,
+}
+
+impl ObjectStorePartitionSink {
+    pub fn new(url: Url, file_name: String, options: UploadOptions) -> Self {
+        Self {
+            url,
+            file_name,
+            options,
+        }
+    }
+}
+
+impl ObjectStorePartitionSink {
+    /// Return the URL of `path` (e.g. `{partition}/{file_name}`) within the
+    /// table directory
+    fn url(&self, path: &[&str]) -> io::Result<Url> {
+        let mut url = self.url.clone();
+        // pushing the segments percent encodes the escaped partition value
+        url.path_segments_mut()
+            .map_err(|_| io::Error::other(format!("Invalid object store URL {}", self.url)))?
+            .pop_if_empty()
+            .extend(path);
+        Ok(url)
+    }
+}
+
+impl PartitionSink for ObjectStorePartitionSink {
+    type Writer = ObjectStoreWriter;
+
+    fn create(&self, partition: &str) -> io::Result<Self::Writer> {
+        ObjectStoreWriter::try_new(
+            &self.url(&[partition, &self.file_name])?,
+            self.options.clone(),
+        )
+    }
+
+    fn commit(&self, _partition: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_file(&self, name: &str, contents: &[u8]) -> io::Result<()> {
+        let mut writer = ObjectStoreWriter::try_new(&self.url(&[name])?, self.options.clone())?;
+        writer.write_all(contents)?;
+        writer.into_size()?;
+        Ok(())
+    }
+}
+
+/// Options for writing the Parquet file of each partition
+#[derive(Debug, Clone, Copy)]
+pub struct PartitionedParquetOptions {
+    pub compression: Compression,
+    pub geoparquet_version: Option<GeoParquetVersion>,
+    pub geometry_encoding: GeometryEncoding,
+    /// The data page size limit in bytes, if not the default
+    pub data_page_bytes: Option<usize>,
+    /// Target row group size in bytes
+    pub row_group_bytes: usize,
+    /// Maximum number of rows per row group, if set
+    pub row_group_rows: Option<usize>,
+}
+
+/// The batches of one partition generated from a single iterator
+struct PartitionBatches {
+    partition: String,
+    batches: Vec<RecordBatch>,
+    geo_statistics: GeoStatistics,
+    /// Memory reserved for the iterator the batches were split from,
+    /// released once all its partitions are written
+    reservation: Arc<Reservation>,
+}
+
+/// Converts a set of RecordBatchIterators into one Parquet file per partition
+///
+/// Uses num_threads to generate the batches of the iterators in parallel. The
+/// batches of each partition are written by one of num_threads writer
+/// threads, so that the files are also encoded in parallel.
+///
+/// Unlike [`generate_parquet`], row groups are not aligned to the input
+/// iterators: each partition buffers its rows until the row group reaches the
+/// target size. To bound memory use, the largest row groups are flushed early
+/// once [`MAX_BUFFERED_BYTES`] (or `--memory-limit`, if smaller) are buffered
+/// in total.
+///
+/// [`generate_parquet`]: crate::parquet::generate_parquet
+pub async fn generate_partitioned_parquet<S: PartitionSink, P: Partitioner, I>(
+    sink: Arc<S>,
+    partitioner: P,
+    iter_iter: I,
+    num_threads: usize,
+    options: PartitionedParquetOptions,
+) -> Result<(), io::Error>
+where
+    I: Iterator<Item: RecordBatchIterator> + 'static,
+{
+    debug!("Generating partitioned Parquet with {num_threads} threads");
+    let mut iter_iter = iter_iter.map(SampledIterator::new).peekable();
+    let Some(first_iter) = iter_iter.peek_mut() else {
+        return Ok(()); // no data shrug
+    };
+
+    let schema = Arc::clone(partitioner.schema());
+    let mut geoparquet_encoder = options
+        .geoparquet_version
+        .map(|version| GeoParquetEncoder::new(&schema, version));
+    let schema = match &geoparquet_encoder {
+        Some(encoder) => Arc::clone(encoder.schema()),
+        None => schema,
+    };
+    let geoarrow_encoder = match options.geometry_encoding {
+        GeometryEncoding::Wkb => None,
+        GeometryEncoding::Geoarrow => {
+            let sample = first_iter
+                .sample()
+                .map(|batch| partitioner.project(batch))
+                .transpose()
+                .map_err(io::Error::other)?;
+            let sample: Vec<_> = sample.into_iter().collect();
+            Some(GeoArrowEncoder::new(&schema, &sample))
+        }
+    };
+    let schema = match &geoarrow_encoder {
+        Some(encoder) => {
+            geoparquet_encoder = geoparquet_encoder
+                .map(|geoparquet| geoparquet.with_native_types(encoder.geometry_types()));
+            Arc::clone(encoder.schema())
+        }
+        None => schema,
+    };
+    let partitioner = Arc::new(partitioner);
+    let geoparquet_encoder = geoparquet_encoder.map(Arc::new);
+    let geoarrow_encoder = geoarrow_encoder.map(Arc::new);
+
+    let mut writer_properties = WriterProperties::builder().set_compression(options.compression);
+    if let Some(data_page_bytes) = options.data_page_bytes {
+        writer_properties = writer_properties.set_data_page_size_limit(data_page_bytes);
+    }
+    if let Some(row_group_rows) = options.row_group_rows {
+        writer_properties = writer_properties.set_max_row_group_size(row_group_rows);
+    }
+    let writer_properties = writer_properties.build();
+
+    // create a stream that generates and splits the batches of each iterator
+    let estimate = Arc::new(ChunkEstimate::new());
+    let mut partitions_stream = futures::stream::iter(iter_iter)
+        .map(async |iter| {
+            let reservation = Arc::new(estimate.reserve().await);
+            let estimate = Arc::clone(&estimate);
+            let partitioner = Arc::clone(&partitioner);
+            let geoparquet_encoder = geoparquet_encoder.clone();
+            let geoarrow_encoder = geoarrow_encoder.clone();
+            // run on a separate thread
+            tokio::task::spawn(async move {
+                let partitions = split_partitions(
+                    partitioner.as_ref(),
+                    geoparquet_encoder.as_deref(),
+                    geoarrow_encoder.as_deref(),
+                    iter,
+                    reservation,
+                );
+                if let Ok(partitions) = &partitions {
+                    let batches = partitions.iter().flat_map(|p| &p.batches);
+                    estimate.update(batches.map(|b| b.get_array_memory_size()).sum());
+                }
+                partitions
+            })
+            .await
+            .expect("Inner task panicked")
+        })
+        .buffered(num_threads); // generate in parallel
+
+    // Blocking tasks that write the partitions, each of which handles the
+    // partitions whose name hashes to it
+    let hasher = RandomState::new();
+    let max_buffered_bytes = memory_limit().map_or(MAX_BUFFERED_BYTES, |limit| {
+        MAX_BUFFERED_BYTES.min(limit as usize)
+    });
+    let mut senders = Vec::with_capacity(num_threads);
+    let mut writer_tasks = Vec::with_capacity(num_threads);
+    for _ in 0..num_threads {
+        let (tx, rx) = tokio::sync::mpsc::channel::<PartitionBatches>(num_threads);
+        let mut writer = PartitionWriters {
+            sink: Arc::clone(&sink),
+            schema: Arc::clone(&schema),
+            writer_properties: writer_properties.clone(),
+            geoparquet_encoder: geoparquet_encoder.clone(),
+            row_group_bytes: options.row_group_bytes,
+            max_buffered_bytes: max_buffered_bytes / num_threads,
+            writers: HashMap::new(),
+            buffered_bytes: 0,
+        };
+        writer_tasks.push(tokio::task::spawn_blocking(move || writer.run(rx)));
+        senders.push(tx);
+    }
+
+    // now, drive the input stream and send results to the writer tasks
+    'outer: while let Some(partitions) = partitions_stream.next().await {
+        for partition in partitions.map_err(io::Error::other)? {
+            let writer = hasher.hash_one(&partition.partition) as usize % senders.len();
+            if let Err(e) = senders[writer].send(partition).await {
+                debug!("Error sending partition to writer: {e}");
+                break 'outer; // stop early
+            }
+        }
+    }
+    // signal the writer tasks that we are done
+    drop(senders);
+
+    // Wait for the writer tasks to finish
+    let mut statistics = WriteStatistics::new("files");
+    for task in writer_tasks {
+        let (num_files, num_bytes) = task.await??;
+        statistics.increment_chunks(num_files);
+        statistics.increment_bytes(num_bytes);
+    }
+    Ok(())
+}
+
+/// Generates the batches of `iter` and splits them by partition
+fn split_partitions<P: Partitioner, I: RecordBatchIterator>(
+    partitioner: &P,
+    geoparquet_encoder: Option<&GeoParquetEncoder>,
+    geoarrow_encoder: Option<&GeoArrowEncoder>,
+    iter: I,
+    reservation: Arc<Reservation>,
+) -> Result<Vec<PartitionBatches>, ArrowError> {
+    let mut partitions: BTreeMap<String, PartitionBatches> = BTreeMap::new();
+    for batch in iter {
+        for (partition, batch) in partitioner.split(&batch)? {
+            let entry =
+                partitions
+                    .entry(partition)
+                    .or_insert_with_key(|partition| PartitionBatches {
+                        partition: partition.clone(),
+                        batches: vec![],
+                        geo_statistics: geoparquet_encoder
+                            .map(|encoder| encoder.new_statistics())
+                            .unwrap_or_default(),
+                        reservation: Arc::clone(&reservation),
+                    });
+            let batch = match geoparquet_encoder {
+                Some(encoder) => encoder.encode(batch, &mut entry.geo_statistics),
+                None => batch,
+            };
+            // the statistics are computed from the WKB, so convert afterwards
+            let batch = match geoarrow_encoder {
+                Some(encoder) => encoder.encode(batch)?,
+                None => batch,
+            };
+            entry.batches.push(batch);
+        }
+    }
+    Ok(partitions.into_values().collect())
+}
+
+/// The open file of a partition
+struct PartitionWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    geo_statistics: GeoStatistics,
+}
+
+/// Writes the files of a subset of the partitions
+struct PartitionWriters<S: PartitionSink> {
+    sink: Arc<S>,
+    schema: SchemaRef,
+    writer_properties: WriterProperties,
+    geoparquet_encoder: Option<Arc<GeoParquetEncoder>>,
+    row_group_bytes: usize,
+    max_buffered_bytes: usize,
+    writers: HashMap<String, PartitionWriter<S::Writer>>,
+    /// Total size of the unflushed row groups of all the writers
+    buffered_bytes: usize,
+}
+
+impl<S: PartitionSink> PartitionWriters<S> {
+    /// Write the partitions received on `rx`, returning the number of files
+    /// and bytes written
+    fn run(
+        &mut self,
+        mut rx: tokio::sync::mpsc::Receiver<PartitionBatches>,
+    ) -> io::Result<(usize, usize)> {
+        while let Some(partition) = rx.blocking_recv() {
+            self.write(partition)?;
+        }
+        let mut num_bytes = 0;
+        let num_files = self.writers.len();
+        for (
+            partition,
+            PartitionWriter {
+                mut writer,
+                geo_statistics,
+            },
+        ) in self.writers.drain()
+        {
+            if let Some(encoder) = &self.geoparquet_encoder {
+                writer.append_key_value_metadata(encoder.metadata(&geo_statistics));
+            }
+            num_bytes += writer.into_inner()?.into_size()?;
+            self.sink.commit(&partition)?;
+        }
+        Ok((num_files, num_bytes))
+    }
+
+    fn write(&mut self, partition: PartitionBatches) -> io::Result<()> {
+        let PartitionBatches {
+            partition,
+            batches,
+            geo_statistics,
+            reservation: _reservation,
+        } = partition;
+        let writer = match self.writers.entry(partition) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                let writer = ArrowWriter::try_new(
+                    self.sink.create(entry.key())?,
+                    Arc::clone(&self.schema),
+                    Some(self.writer_properties.clone()),
+                )?;
+                entry.insert(PartitionWriter {
+                    writer,
+                    geo_statistics: GeoStatistics::default(),
+                })
+            }
+        };
+        writer.geo_statistics.merge(&geo_statistics);
+
+        let before = writer.writer.in_progress_size();
+        for batch in batches {
+            writer.writer.write(&batch)?;
+        }
+        if writer.writer.in_progress_size() >= self.row_group_bytes {
+            writer.writer.flush()?;
+        }
+        self.buffered_bytes =
+            (self.buffered_bytes + writer.writer.in_progress_size()).saturating_sub(before);
+
+        if self.buffered_bytes > self.max_buffered_bytes {
+            self.flush_largest()?;
+        }
+        Ok(())
+    }
+
+    /// Flush the largest row groups until at most half of the maximum is
+    /// buffered
+    fn flush_largest(&mut self) -> io::Result<()> {
+        let mut writers: Vec<_> = self
+            .writers
+            .values_mut()
+            .map(|writer| (writer.writer.in_progress_size(), writer))
+            .collect();
+        writers.sort_unstable_by_key(|(size, _)| std::cmp::Reverse(*size));
+        debug!(
+            "Flushing row groups with {} bytes buffered in {} partitions",
+            self.buffered_bytes,
+            writers.len()
+        );
+
+        // recompute the total, as the sizes are estimates and will drift
+        self.buffered_bytes = writers.iter().map(|(size, _)| size).sum();
+        for (size, writer) in writers {
+            if self.buffered_bytes <= self.max_buffered_bytes / 2 {
+                break;
+            }
+            writer.writer.flush()?;
+            self.buffered_bytes -= size;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, StringArray, TimestampMillisecondArray};
+    use arrow::datatypes::{Field, Schema, TimeUnit};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use spatialbench::generators::TripGenerator;
+    use spatialbench_arrow::TripArrow;
+
+    #[test]
+    fn test_split_by_day() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new(
+                "time",
+                DataType::Timestamp(TimeUnit::Millisecond, None),
+                true,
+            ),
+        ]));
+        let day = 24 * 60 * 60 * 1000;
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3, 4])),
+                Arc::new(TimestampMillisecondArray::from(vec![
+                    Some(day + 1),
+                    None,
+                    Some(1),
+                    Some(day + 2),
+                ])),
+            ],
+        )
+        .unwrap();
+        let partitioner = HivePartitioner::try_new(&schema, "time").unwrap().unwrap();
+        assert_eq!(partitioner.schema(), &schema);
+
+        let partitions: Vec<_> = partitioner
+            .split(&batch)
+            .unwrap()
+            .into_iter()
+            .map(|(partition, batch)| {
+                let ids = batch.column(0).as_any().downcast_ref::<Int64Array>();
+                (partition, ids.unwrap().values().to_vec())
+            })
+            .collect();
+        assert_eq!(
+            partitions,
+            vec![
+                ("time_date=1970-01-01".to_string(), vec![3]),
+                ("time_date=1970-01-02".to_string(), vec![1, 4]),
+                (format!("time_date={DEFAULT_PARTITION}"), vec![2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_by_value() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["a/b", "c", "a/b"])),
+            ],
+        )
+        .unwrap();
+        let partitioner = HivePartitioner::try_new(&schema, "name").unwrap().unwrap();
+        // the partition column is removed
+        assert_eq!(partitioner.schema().fields().len(), 1);
+
+        let partitions = partitioner.split(&batch).unwrap();
+        let names: Vec<_> = partitions.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["name=a%2Fb", "name=c"]);
+        assert_eq!(partitions[0].1.num_columns(), 1);
+        assert_eq!(partitions[0].1.num_rows(), 2);
+
+        assert!(HivePartitioner::try_new(&schema, "missing")
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_partitioned() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = LocalPartitionSink::new(dir.path().to_path_buf(), "part-0.parquet".into());
+        let sink = Arc::new(sink);
+        let sources: Vec<_> = (1..=3)
+            .map(|part| TripArrow::new(TripGenerator::new(0.001, part, 3)))
+            .collect();
+        let partitioner = HivePartitioner::try_new(sources[0].schema(), "t_pickuptime")
+            .unwrap()
+            .unwrap();
+        let options = PartitionedParquetOptions {
+            compression: Compression::SNAPPY,
+            geoparquet_version: None,
+            geometry_encoding: GeometryEncoding::Wkb,
+            data_page_bytes: None,
+            row_group_bytes: 1024,
+            row_group_rows: None,
+        };
+        generate_partitioned_parquet(sink, partitioner, sources.into_iter(), 2, options)
+            .await
+            .unwrap();
+
+        let mut rows = 0;
+        for entry in std::fs::read_dir(dir.path()).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            assert!(name.starts_with("t_pickuptime_date="), "{name}");
+            let file = File::open(path.join("part-0.parquet")).unwrap();
+            let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+                .unwrap()
+                .build()
+                .unwrap();
+            rows += reader.map(|batch| batch.unwrap().num_rows()).sum::<usize>();
+        }
+        assert_eq!(rows as i64, TripGenerator::calculate_row_count(0.001, 1, 1));
+    }
+}
@@ -0,0 +1,264 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Arrow IPC (Feather) output format
+//!
+//! Geometry (WKB) columns are marked with the [GeoArrow] `geoarrow.wkb`
+//! extension type so that readers such as GeoPandas, GDAL and SedonaDB
+//! recognize them as geometries.
+//!
+//! [GeoArrow]: https://geoarrow.org/extension-types
+
+use crate::geoarrow::{geoarrow_metadata, EXTENSION_METADATA_KEY, EXTENSION_NAME_KEY};
+use crate::memory::{ChunkEstimate, Reservation};
+use crate::parquet::IntoSize;
+use crate::statistics::WriteStatistics;
+use arrow::array::RecordBatch;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::{FileWriter, StreamWriter};
+use clap::ValueEnum;
+use futures::StreamExt;
+use log::debug;
+use spatialbench_arrow::RecordBatchIterator;
+use std::io;
+use std::io::Write;
+use std::sync::Arc;
+
+/// The Arrow IPC format to write
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ArrowIpcFormat {
+    /// IPC file format (Feather V2), which supports random access and memory mapping
+    File,
+    /// IPC streaming format, which can be read without seeking
+    Stream,
+}
+
+impl ArrowIpcFormat {
+    /// Return the file extension for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArrowIpcFormat::File => "arrow",
+            ArrowIpcFormat::Stream => "arrows",
+        }
+    }
+}
+
+/// Return `schema` with the geometry (WKB) columns marked with the
+/// `geoarrow.wkb` extension type
+pub fn geoarrow_schema(schema: &Schema) -> SchemaRef {
+    let fields: Vec<Field> = schema
+        .fields()
+        .iter()
+        .map(|field| match field.data_type() {
+            DataType::Binary | DataType::LargeBinary | DataType::BinaryView => {
+                let mut metadata = field.metadata().clone();
+                metadata.insert(EXTENSION_NAME_KEY.to_string(), "geoarrow.wkb".to_string());
+                metadata.insert(EXTENSION_METADATA_KEY.to_string(), geoarrow_metadata());
+                field.as_ref().clone().with_metadata(metadata)
+            }
+            _ => field.as_ref().clone(),
+        })
+        .collect();
+    Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+}
+
+/// Either kind of Arrow IPC writer
+enum IpcWriter<W: Write> {
+    File(FileWriter<W>),
+    Stream(StreamWriter<W>),
+}
+
+impl<W: Write> IpcWriter<W> {
+    fn try_new(writer: W, schema: &Schema, format: ArrowIpcFormat) -> Result<Self, ArrowError> {
+        Ok(match format {
+            ArrowIpcFormat::File => Self::File(FileWriter::try_new(writer, schema)?),
+            ArrowIpcFormat::Stream => Self::Stream(StreamWriter::try_new(writer, schema)?),
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), ArrowError> {
+        match self {
+            Self::File(writer) => writer.write(batch),
+            Self::Stream(writer) => writer.write(batch),
+        }
+    }
+
+    /// Write the footer (or end of stream marker) and return the inner writer
+    fn into_inner(self) -> Result<W, ArrowError> {
+        match self {
+            Self::File(mut writer) => {
+                writer.finish()?;
+                writer.into_inner()
+            }
+            Self::Stream(mut writer) => {
+                writer.finish()?;
+                writer.into_inner()
+            }
+        }
+    }
+}
+
+/// Converts a set of RecordBatchIterators into an Arrow IPC file
+///
+/// Uses num_threads to generate the batches of the iterators in parallel.
+/// With `--memory-limit`, the batches of each iterator reserve their memory
+/// until they are written (see [`crate::memory`]).
+pub async fn generate_arrow_ipc<W: Write + Send + IntoSize + 'static, I>(
+    writer: W,
+    iter_iter: I,
+    num_threads: usize,
+    format: ArrowIpcFormat,
+) -> Result<(), io::Error>
+where
+    I: Iterator<Item: RecordBatchIterator> + 'static,
+{
+    debug!("Generating Arrow IPC {format:?} with {num_threads} threads");
+    let mut iter_iter = iter_iter.peekable();
+
+    // get schema from the first iterator
+    let Some(first_iter) = iter_iter.peek() else {
+        return Ok(()); // no data shrug
+    };
+    let schema = geoarrow_schema(first_iter.schema());
+
+    // create a stream that generates the batches of each iterator
+    let estimate = Arc::new(ChunkEstimate::new());
+    let mut batches_stream = futures::stream::iter(iter_iter)
+        .map(async |iter| {
+            let reservation = estimate.reserve().await;
+            let estimate = Arc::clone(&estimate);
+            let schema = Arc::clone(&schema);
+            // run on a separate thread
+            tokio::task::spawn(async move {
+                let batches = iter
+                    .map(|batch| batch.with_schema(Arc::clone(&schema)))
+                    .collect::<Result<Vec<_>, _>>();
+                if let Ok(batches) = &batches {
+                    estimate.update(batches.iter().map(|b| b.get_array_memory_size()).sum());
+                }
+                (batches, reservation)
+            })
+            .await
+            .expect("Inner task panicked")
+        })
+        .buffered(num_threads); // generate in parallel
+
+    // A blocking task that writes the batches, as it does file IO
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<(
+        Result<Vec<RecordBatch>, ArrowError>,
+        Reservation,
+    )>(num_threads);
+    let writer_schema = Arc::clone(&schema);
+    let writer_task = tokio::task::spawn_blocking(move || {
+        let mut statistics = WriteStatistics::new("parts");
+        let mut writer =
+            IpcWriter::try_new(writer, &writer_schema, format).map_err(io::Error::other)?;
+        while let Some((batches, reservation)) = rx.blocking_recv() {
+            for batch in batches.map_err(io::Error::other)? {
+                writer.write(&batch).map_err(io::Error::other)?;
+            }
+            statistics.increment_chunks(1);
+            drop(reservation);
+        }
+        let size = writer.into_inner().map_err(io::Error::other)?.into_size()?;
+        statistics.increment_bytes(size);
+        Ok(()) as Result<(), io::Error>
+    });
+
+    // now, drive the input stream and send results to the writer task
+    while let Some(batches) = batches_stream.next().await {
+        if let Err(e) = tx.send(batches).await {
+            debug!("Error sending batches to writer: {e}");
+            break; // stop early
+        }
+    }
+    // signal the writer task that we are done
+    drop(tx);
+
+    // Wait for the writer task to finish
+    writer_task.await??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::ipc::reader::{FileReader, StreamReader};
+    use spatialbench::generators::TripGenerator;
+    use spatialbench_arrow::TripArrow;
+    use std::io::Cursor;
+    use std::sync::Mutex;
+
+    /// A writer that collects the output in memory
+    #[derive(Clone, Default)]
+    struct MemoryWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for MemoryWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl IntoSize for MemoryWriter {
+        fn into_size(self) -> Result<usize, io::Error> {
+            Ok(self.0.lock().unwrap().len())
+        }
+    }
+
+    async fn generate(format: ArrowIpcFormat) -> Vec<u8> {
+        let writer = MemoryWriter::default();
+        let sources = (1..=3).map(|part| TripArrow::new(TripGenerator::new(0.001, part, 3)));
+        generate_arrow_ipc(writer.clone(), sources, 2, format)
+            .await
+            .unwrap();
+        let output = writer.0.lock().unwrap().clone();
+        output
+    }
+
+    fn assert_geoarrow(schema: &Schema) {
+        let field = schema.field_with_name("t_pickuploc").unwrap();
+        assert_eq!(
+            field.metadata().get(EXTENSION_NAME_KEY).unwrap(),
+            "geoarrow.wkb"
+        );
+        let field = schema.field_with_name("t_tripkey").unwrap();
+        assert!(field.metadata().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_file() {
+        let output = generate(ArrowIpcFormat::File).await;
+        let reader = FileReader::try_new(Cursor::new(output), None).unwrap();
+        assert_geoarrow(&reader.schema());
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows as i64, TripGenerator::calculate_row_count(0.001, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let output = generate(ArrowIpcFormat::Stream).await;
+        let reader = StreamReader::try_new(Cursor::new(output), None).unwrap();
+        assert_geoarrow(&reader.schema());
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows as i64, TripGenerator::calculate_row_count(0.001, 1, 1));
+    }
+}
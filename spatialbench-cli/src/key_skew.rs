@@ -0,0 +1,46 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Skew of the foreign keys (`--key-skew`)
+
+use log::info;
+use spatialbench::skew::{set_key_skews, ForeignKeySkew, KeySkews};
+use std::io;
+
+/// Set the skews of the foreign keys from the `--key-skew` arguments
+///
+/// Each foreign key can only be given once.
+pub fn load_key_skews(skews: &[ForeignKeySkew]) -> io::Result<()> {
+    if skews.is_empty() {
+        return Ok(());
+    }
+    let mut key_skews = KeySkews::default();
+    for (i, skew) in skews.iter().enumerate() {
+        if skews[..i].iter().any(|other| other.key == skew.key) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--key-skew is given more than once for {}", skew.key),
+            ));
+        }
+        info!(
+            "Referencing the keys of {} with the {} skew",
+            skew.key, skew.skew
+        );
+        key_skews.set(skew.key, skew.skew);
+    }
+    set_key_skews(key_skews).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
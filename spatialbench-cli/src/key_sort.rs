@@ -0,0 +1,838 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Sorting the rows of each file by a column (`--sort-by`), along a space
+//! filling curve (`--spatial-sort`) or shuffling them (`--shuffle`)
+//!
+//! The rows of a whole file are sorted, with an external merge sort:
+//!
+//! 1. the chunks of the file are generated and each is sorted into a run,
+//!    `num_threads` chunks at a time
+//! 2. the runs are kept in memory up to [`SORT_MEMORY_BYTES`], and the
+//!    following runs are spilled to temporary Arrow IPC files
+//! 3. the runs are merged, and the merged rows are split into chunks of the
+//!    same sizes as the generated chunks, so a Parquet file has the same row
+//!    groups as without sorting
+//!
+//! The merge reads one batch of each run at a time, so a file larger than
+//! memory is sorted with about [`SORT_MEMORY_BYTES`] plus one batch per run.
+//!
+//! Sorting along a curve sorts the rows by the curve value of the center of
+//! their geometry (see [`SpatialOrder`]), so the row groups and pages of a
+//! file cover small areas. Shuffling sorts the rows the same way, by a random
+//! key derived from a seed and the position of each row in the generated
+//! chunks, so the rows are written in an order uncorrelated with their keys
+//! and locations, and the same order for the same seed.
+
+use crate::generate::Sink;
+use crate::hive::PartitionSink;
+use crate::parquet::IntoSize;
+use crate::spatial_sort::{curve_values, SpatialOrder};
+use arrow::array::{ArrayRef, RecordBatch, UInt64Array};
+use arrow::compute::{concat_batches, interleave_record_batch, sort_to_indices, take_record_batch};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::ipc::reader::StreamReader;
+use arrow::ipc::writer::StreamWriter;
+use arrow::row::{RowConverter, Rows, SortField};
+use log::debug;
+use spatialbench::spatial::utils::spider_seed_for_index;
+use spatialbench_arrow::{RecordBatchIterator, Table};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Seek, Write};
+use std::sync::{Arc, Mutex};
+
+/// Size of the sorted runs of a file kept in memory, the following runs are
+/// spilled to temporary files
+pub const SORT_MEMORY_BYTES: usize = 512 * 1024 * 1024;
+
+/// The order to write the rows of a file in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowOrder {
+    /// Sorted by a column
+    Column(String),
+    /// Sorted along a space filling curve, by the first geometry column
+    Curve(SpatialOrder),
+    /// A random permutation, derived from a seed
+    Shuffled(u64),
+}
+
+impl RowOrder {
+    /// Return the seed of the shuffle of a part of a table, so each file is
+    /// shuffled differently but the same for each run with the global `seed`
+    pub fn shuffled(seed: u64, table: &str, part: i32) -> Self {
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        for byte in table.bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        let seed = spider_seed_for_index(seed ^ hash, 0);
+        RowOrder::Shuffled(spider_seed_for_index(seed ^ part as u64, 0))
+    }
+}
+
+/// The column to sort the files of one table by, see [`parse_sort_by`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub table: Table,
+    pub column: String,
+}
+
+/// Parse a `--sort-by` value such as `trip:t_pickuptime`
+pub fn parse_sort_by(value: &str) -> Result<SortKey, String> {
+    let Some((table, column)) = value.split_once(':') else {
+        return Err(format!(
+            "expected <table>:<column>, e.g. trip:t_pickuptime, got '{value}'"
+        ));
+    };
+    let table: Table = table.trim().parse()?;
+    let column = column.trim();
+    let schema = table.schema();
+    let field = schema.field_with_name(column).map_err(|_| {
+        let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        format!(
+            "table {table} has no column '{column}', expected one of {}",
+            names.join(", ")
+        )
+    })?;
+    if !is_sortable(field.data_type()) {
+        return Err(format!(
+            "column '{column}' of type {} can not be sorted by, use --spatial-sort for geometries",
+            field.data_type()
+        ));
+    }
+    Ok(SortKey {
+        table,
+        column: column.to_string(),
+    })
+}
+
+/// Return true for the types of columns the files can be sorted by: not
+/// binary geometries, nested types or the raster tiles
+fn is_sortable(data_type: &DataType) -> bool {
+    data_type.is_primitive()
+        || matches!(
+            data_type,
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View | DataType::Boolean
+        )
+}
+
+/// An iterator of the chunks of a file, with the rows of all the chunks
+/// sorted by a column or shuffled
+///
+/// If no order is set, the chunks are passed through unchanged. Otherwise,
+/// all the chunks are generated and sorted on the first call to `next` (see
+/// the module documentation), and each chunk is then returned with the
+/// same number of rows as the chunk generated at its position.
+///
+/// If sorting fails (e.g. spilling a run), no more chunks are returned and
+/// the error is recorded in the [`SortError`] of the iterator, so the sink
+/// of the file fails instead of completing a truncated file.
+pub struct KeySorted<S> {
+    sources: S,
+    /// The order of the rows, if sorting
+    order: Option<RowOrder>,
+    num_threads: usize,
+    /// The merge of the runs, and the number of rows of each chunk, once
+    /// the runs are sorted
+    merge: Option<(Merge, std::vec::IntoIter<usize>)>,
+    error: SortError,
+}
+
+impl<S> KeySorted<S>
+where
+    S: Iterator<Item: RecordBatchIterator>,
+{
+    /// Sort the rows of `sources` in `order`, if set, generating up to
+    /// `num_threads` of the sources at a time
+    pub fn new(sources: S, order: Option<RowOrder>, num_threads: usize) -> Self {
+        Self {
+            sources,
+            order,
+            num_threads: num_threads.max(1),
+            merge: None,
+            error: SortError::default(),
+        }
+    }
+
+    /// Record the error of sorting in `error`
+    pub fn with_error(mut self, error: SortError) -> Self {
+        self.error = error;
+        self
+    }
+
+    /// Generate the sources and sort each of them into a run
+    fn sort_runs(&mut self, order: &RowOrder) -> io::Result<(Merge, Vec<usize>)> {
+        let mut runs = vec![];
+        let mut chunk_rows = vec![];
+        let mut schema = None;
+        let mut batch_size = 0;
+        let mut memory = 0;
+        loop {
+            let sources: Vec<_> = self.sources.by_ref().take(self.num_threads).collect();
+            if sources.is_empty() {
+                break;
+            }
+            let first_chunk = chunk_rows.len();
+            let sorted: Vec<_> = std::thread::scope(|scope| {
+                let handles: Vec<_> = (first_chunk..)
+                    .zip(sources)
+                    .map(|(chunk, source)| scope.spawn(move || sort_run(source, order, chunk)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("sorting a chunk panicked"))
+                    .collect()
+            });
+            for sorted in sorted {
+                let (run_schema, run, first_batch_rows) = sorted?;
+                let rows = run.as_ref().map_or(0, |batch| batch.num_rows());
+                schema.get_or_insert(run_schema);
+                if batch_size == 0 {
+                    batch_size = first_batch_rows;
+                }
+                chunk_rows.push(rows);
+                let Some(run) = run else {
+                    continue;
+                };
+                memory += run.get_array_memory_size();
+                if memory <= SORT_MEMORY_BYTES {
+                    runs.push(Run::Memory(Some(run)));
+                } else {
+                    runs.push(Run::spill(&run, batch_size.max(1))?);
+                }
+            }
+        }
+        let spilled = runs
+            .iter()
+            .filter(|run| matches!(run, Run::Spilled(_)))
+            .count();
+        debug!(
+            "Merging {} sorted runs by {order:?}, {spilled} of them spilled",
+            runs.len()
+        );
+        let merge = match schema {
+            Some(schema) => Merge::try_new(schema, order, runs, batch_size.max(1))?,
+            None => Merge::empty(),
+        };
+        Ok((merge, chunk_rows))
+    }
+}
+
+/// Generate the batches of `source`, the chunk at position `chunk` of the
+/// file, and sort them in `order`
+///
+/// Returns the schema, the sorted rows (if any) and the number of rows of
+/// the first batch. When sorting along a curve or shuffling, the sorted rows
+/// end with the key column.
+fn sort_run(
+    mut source: impl RecordBatchIterator,
+    order: &RowOrder,
+    chunk: usize,
+) -> io::Result<(SchemaRef, Option<RecordBatch>, usize)> {
+    let schema = Arc::clone(source.schema());
+    let batches: Vec<_> = source.by_ref().collect();
+    let first_batch_rows = batches.first().map_or(0, |batch| batch.num_rows());
+    if batches.is_empty() {
+        return Ok((schema, None, 0));
+    }
+    let mut batch = concat_batches(&schema, &batches).map_err(io::Error::other)?;
+    drop(batches);
+    let key = match order {
+        RowOrder::Column(column) => {
+            let index = schema.index_of(column).map_err(io::Error::other)?;
+            Arc::clone(batch.column(index))
+        }
+        RowOrder::Curve(order) => {
+            let keys = curve_values(&batch, *order);
+            batch = with_key_column(&batch, CURVE_COLUMN, &keys)?;
+            keys
+        }
+        RowOrder::Shuffled(seed) => {
+            let keys = shuffle_keys(*seed, chunk, batch.num_rows());
+            batch = with_key_column(&batch, SHUFFLE_COLUMN, &keys)?;
+            keys
+        }
+    };
+    let indices = sort_to_indices(&key, None, None).map_err(io::Error::other)?;
+    let batch = take_record_batch(&batch, &indices).map_err(io::Error::other)?;
+    Ok((schema, Some(batch), first_batch_rows))
+}
+
+/// Name of the curve value column the runs sorted along a curve are sorted by
+const CURVE_COLUMN: &str = "_curve";
+
+/// Name of the random key column the shuffled runs are sorted by
+const SHUFFLE_COLUMN: &str = "_shuffle";
+
+/// Return `batch` with the `keys` column `name` appended
+fn with_key_column(batch: &RecordBatch, name: &str, keys: &ArrayRef) -> io::Result<RecordBatch> {
+    let mut fields: Vec<_> = batch.schema().fields().iter().cloned().collect();
+    fields.push(Arc::new(Field::new(name, DataType::UInt64, false)));
+    let mut columns = batch.columns().to_vec();
+    columns.push(Arc::clone(keys));
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map_err(io::Error::other)
+}
+
+/// Return the random keys of the `rows` rows of the chunk at position
+/// `chunk` of a file shuffled with `seed`
+fn shuffle_keys(seed: u64, chunk: usize, rows: usize) -> ArrayRef {
+    let seed = spider_seed_for_index(seed, 0);
+    let keys: UInt64Array = (0..rows as u64)
+        .map(|row| spider_seed_for_index(seed ^ ((chunk as u64) << 40 | row), 0))
+        .collect();
+    Arc::new(keys)
+}
+
+impl<S> Iterator for KeySorted<S>
+where
+    S: Iterator<Item: RecordBatchIterator>,
+{
+    type Item = SortedChunk<S::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(order) = self.order.clone() else {
+            return self.sources.next().map(SortedChunk::Unsorted);
+        };
+        if self.merge.is_none() {
+            let (merge, chunk_rows) = match self.sort_runs(&order) {
+                Ok(sorted) => sorted,
+                Err(e) => {
+                    self.error.set(order_error(&order, e));
+                    (Merge::empty(), vec![])
+                }
+            };
+            self.merge = Some((merge, chunk_rows.into_iter()));
+        }
+        let (merge, chunk_rows) = self.merge.as_mut().expect("merge");
+        let rows = chunk_rows.next()?;
+        let batches = match merge.take(rows) {
+            Ok(batches) => batches,
+            Err(e) => {
+                self.error.set(order_error(&order, e));
+                *chunk_rows = vec![].into_iter();
+                return None;
+            }
+        };
+        Some(SortedChunk::Sorted(
+            Arc::clone(merge.schema()),
+            batches.into_iter(),
+        ))
+    }
+}
+
+/// Return `error` of sorting in `order`
+fn order_error(order: &RowOrder, error: io::Error) -> io::Error {
+    io::Error::new(
+        error.kind(),
+        format!("Failed sorting by {order:?}: {error}"),
+    )
+}
+
+/// The error of a [`KeySorted`] iterator, if sorting failed
+///
+/// The iterator ends early when sorting fails, so the sink of the file checks
+/// this error before completing it (see [`SortChecked`]).
+#[derive(Debug, Clone, Default)]
+pub struct SortError(Arc<Mutex<Option<io::Error>>>);
+
+impl SortError {
+    /// Record `error`, unless an earlier error was recorded
+    fn set(&self, error: io::Error) {
+        self.0.lock().unwrap().get_or_insert(error);
+    }
+
+    /// Return the recorded error, if any
+    pub fn check(&self) -> io::Result<()> {
+        match self.0.lock().unwrap().as_ref() {
+            Some(error) => Err(io::Error::new(error.kind(), error.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A writer or [`Sink`] that fails instead of completing its output when
+/// the sorting of its rows failed
+pub struct SortChecked<W> {
+    inner: W,
+    error: SortError,
+}
+
+impl<W> SortChecked<W> {
+    /// Check `error` before completing `inner`
+    pub fn new(inner: W, error: &SortError) -> Self {
+        Self {
+            inner,
+            error: error.clone(),
+        }
+    }
+}
+
+impl<W: Write> Write for SortChecked<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + IntoSize> IntoSize for SortChecked<W> {
+    fn into_size(self) -> Result<usize, io::Error> {
+        self.error.check()?;
+        self.inner.into_size()
+    }
+}
+
+impl<S: Sink> Sink for SortChecked<S> {
+    fn sink(&mut self, buffer: &[u8]) -> Result<(), io::Error> {
+        self.inner.sink(buffer)
+    }
+
+    fn flush(self) -> Result<(), io::Error> {
+        self.error.check()?;
+        self.inner.flush()
+    }
+}
+
+impl<S: PartitionSink> PartitionSink for SortChecked<S> {
+    type Writer = S::Writer;
+
+    fn create(&self, partition: &str) -> io::Result<Self::Writer> {
+        self.inner.create(partition)
+    }
+
+    fn commit(&self, partition: &str) -> io::Result<()> {
+        self.error.check()?;
+        self.inner.commit(partition)
+    }
+
+    fn write_file(&self, name: &str, contents: &[u8]) -> io::Result<()> {
+        self.error.check()?;
+        self.inner.write_file(name, contents)
+    }
+}
+
+/// A chunk of a [`KeySorted`] file
+pub enum SortedChunk<I> {
+    /// The chunk as generated, when not sorting
+    Unsorted(I),
+    /// The sorted rows of the chunk
+    Sorted(SchemaRef, std::vec::IntoIter<RecordBatch>),
+}
+
+impl<I: RecordBatchIterator> Iterator for SortedChunk<I> {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SortedChunk::Unsorted(inner) => inner.next(),
+            SortedChunk::Sorted(_, batches) => batches.next(),
+        }
+    }
+}
+
+impl<I: RecordBatchIterator> RecordBatchIterator for SortedChunk<I> {
+    fn schema(&self) -> &SchemaRef {
+        match self {
+            SortedChunk::Unsorted(inner) => inner.schema(),
+            SortedChunk::Sorted(schema, _) => schema,
+        }
+    }
+}
+
+/// A sorted run of rows
+enum Run {
+    /// The rows, until the merge reads them
+    Memory(Option<RecordBatch>),
+    /// The batches of the rows in a temporary file
+    Spilled(StreamReader<BufReader<File>>),
+}
+
+impl Run {
+    /// Write the rows of `run` to a temporary file, in batches of
+    /// `batch_size` rows
+    fn spill(run: &RecordBatch, batch_size: usize) -> io::Result<Self> {
+        let mut file = tempfile::tempfile()?;
+        {
+            let mut writer = StreamWriter::try_new(BufWriter::new(&mut file), run.schema_ref())
+                .map_err(io::Error::other)?;
+            for offset in (0..run.num_rows()).step_by(batch_size) {
+                let len = batch_size.min(run.num_rows() - offset);
+                writer
+                    .write(&run.slice(offset, len))
+                    .map_err(io::Error::other)?;
+            }
+            writer.finish().map_err(io::Error::other)?;
+        }
+        file.rewind()?;
+        let reader = StreamReader::try_new(BufReader::new(file), None).map_err(io::Error::other)?;
+        Ok(Run::Spilled(reader))
+    }
+
+    /// Return the next batch of the run, if any
+    fn next_batch(&mut self) -> io::Result<Option<RecordBatch>> {
+        match self {
+            Run::Memory(batch) => Ok(batch.take()),
+            Run::Spilled(reader) => reader.next().transpose().map_err(io::Error::other),
+        }
+    }
+}
+
+/// The current batch of a [`Run`] being merged
+struct Cursor {
+    run: Run,
+    /// The current batch, its sort keys and the position of its next row
+    batch: Option<(RecordBatch, Rows, usize)>,
+    /// Index of the current batch in [`Merge::batches`]
+    slot: usize,
+}
+
+/// A k-way merge of sorted runs
+struct Merge {
+    schema: SchemaRef,
+    /// Index of the sort key column of the runs, after the columns of the
+    /// schema when sorting along a curve or shuffling
+    column: usize,
+    converter: Option<RowConverter>,
+    cursors: Vec<Cursor>,
+    /// The batches the rows of the next output batch are taken from
+    batches: Vec<RecordBatch>,
+    batch_size: usize,
+}
+
+impl Merge {
+    fn try_new(
+        schema: SchemaRef,
+        order: &RowOrder,
+        runs: Vec<Run>,
+        batch_size: usize,
+    ) -> io::Result<Self> {
+        let (column, data_type) = match order {
+            RowOrder::Column(column) => {
+                let column = schema.index_of(column).map_err(io::Error::other)?;
+                (column, schema.field(column).data_type().clone())
+            }
+            RowOrder::Curve(_) | RowOrder::Shuffled(_) => (schema.fields().len(), DataType::UInt64),
+        };
+        let converter =
+            RowConverter::new(vec![SortField::new(data_type)]).map_err(io::Error::other)?;
+        let mut merge = Self {
+            schema,
+            column,
+            converter: Some(converter),
+            cursors: vec![],
+            batches: vec![],
+            batch_size,
+        };
+        for run in runs {
+            let mut cursor = Cursor {
+                run,
+                batch: None,
+                slot: 0,
+            };
+            merge.advance(&mut cursor)?;
+            merge.cursors.push(cursor);
+        }
+        Ok(merge)
+    }
+
+    fn empty() -> Self {
+        Self {
+            schema: Arc::new(arrow::datatypes::Schema::empty()),
+            column: 0,
+            converter: None,
+            cursors: vec![],
+            batches: vec![],
+            batch_size: 1,
+        }
+    }
+
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Load the next non empty batch of the run of `cursor`, if any
+    fn advance(&mut self, cursor: &mut Cursor) -> io::Result<()> {
+        cursor.batch = None;
+        while let Some(batch) = cursor.run.next_batch()? {
+            if batch.num_rows() == 0 {
+                continue;
+            }
+            let converter = self.converter.as_ref().expect("converter");
+            let rows = converter
+                .convert_columns(&[Arc::clone(batch.column(self.column))])
+                .map_err(io::Error::other)?;
+            cursor.slot = self.batches.len();
+            self.batches.push(batch.clone());
+            cursor.batch = Some((batch, rows, 0));
+            break;
+        }
+        Ok(())
+    }
+
+    /// Return the next `rows` merged rows, in batches of at most the batch
+    /// size
+    fn take(&mut self, rows: usize) -> io::Result<Vec<RecordBatch>> {
+        let mut output = vec![];
+        let mut remaining = rows;
+        while remaining > 0 {
+            let len = remaining.min(self.batch_size);
+            output.push(self.next_batch(len)?);
+            remaining -= len;
+        }
+        Ok(output)
+    }
+
+    /// Merge the next `len` rows into a batch
+    fn next_batch(&mut self, len: usize) -> io::Result<RecordBatch> {
+        // only the current batches of the runs are referenced so far
+        self.batches.clear();
+        for cursor in &mut self.cursors {
+            if let Some((batch, _, _)) = &cursor.batch {
+                cursor.slot = self.batches.len();
+                self.batches.push(batch.clone());
+            }
+        }
+
+        let mut indices = Vec::with_capacity(len);
+        let mut cursors = std::mem::take(&mut self.cursors);
+        while indices.len() < len {
+            // the first run with the smallest key, so equal keys keep the
+            // order of the chunks
+            let smallest = cursors
+                .iter()
+                .enumerate()
+                .filter_map(|(i, cursor)| {
+                    let (_, rows, pos) = cursor.batch.as_ref()?;
+                    Some((rows.row(*pos), i))
+                })
+                .min()
+                .map(|(_, i)| i);
+            let Some(i) = smallest else {
+                break;
+            };
+            let cursor = &mut cursors[i];
+            let (batch, _, pos) = cursor.batch.as_mut().expect("smallest has a batch");
+            indices.push((cursor.slot, *pos));
+            *pos += 1;
+            if *pos == batch.num_rows() {
+                self.advance(cursor)?;
+            }
+        }
+        self.cursors = cursors;
+        if indices.len() < len {
+            return Err(io::Error::other(format!(
+                "the sorted runs have {} rows less than generated",
+                len - indices.len()
+            )));
+        }
+
+        let batches: Vec<_> = self.batches.iter().collect();
+        let batch = interleave_record_batch(&batches, &indices).map_err(io::Error::other)?;
+        if batch.num_columns() == self.schema.fields().len() {
+            return Ok(batch);
+        }
+        // drop the key column of the runs sorted along a curve or shuffled
+        RecordBatch::try_new(
+            Arc::clone(&self.schema),
+            batch.columns()[..self.schema.fields().len()].to_vec(),
+        )
+        .map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{AsArray, TimestampMillisecondArray};
+    use arrow::datatypes::{Int64Type, TimestampMillisecondType, UInt64Type};
+    use spatialbench::generators::TripGenerator;
+    use spatialbench_arrow::TripArrow;
+
+    #[test]
+    fn test_parse_sort_by() {
+        let key = parse_sort_by("trip: t_pickuptime").unwrap();
+        assert_eq!(key.table, Table::Trip);
+        assert_eq!(key.column, "t_pickuptime");
+
+        for (value, error) in [
+            ("t_tripkey", "expected <table>:<column>"),
+            ("trip:b_name", "table trip has no column 'b_name'"),
+            (
+                "trip:t_pickuploc",
+                "column 't_pickuploc' of type Binary can not be sorted by",
+            ),
+        ] {
+            let err = parse_sort_by(value).unwrap_err();
+            assert!(err.starts_with(error), "{value}: {err}");
+        }
+    }
+
+    fn chunks(num_parts: i32) -> impl Iterator<Item = TripArrow> {
+        (1..=num_parts).map(move |part| {
+            TripArrow::new(TripGenerator::new(0.001, part, num_parts)).with_batch_size(100)
+        })
+    }
+
+    fn pickup_time() -> RowOrder {
+        RowOrder::Column("t_pickuptime".to_string())
+    }
+
+    fn pickup_times(batches: &[RecordBatch]) -> Vec<i64> {
+        batches
+            .iter()
+            .flat_map(|batch| {
+                let column = batch.column_by_name("t_pickuptime").unwrap();
+                let times: &TimestampMillisecondArray =
+                    column.as_primitive::<TimestampMillisecondType>();
+                times.values().to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_key_sorted() {
+        let unsorted: Vec<_> = chunks(4).flatten().collect();
+        let sorted: Vec<_> = KeySorted::new(chunks(4), Some(pickup_time()), 3)
+            .map(|chunk| chunk.collect::<Vec<_>>())
+            .collect();
+
+        // the chunks keep their sizes
+        assert_eq!(sorted.len(), 4);
+        for (part, chunk) in (1..).zip(&sorted) {
+            let rows: usize = chunk.iter().map(|batch| batch.num_rows()).sum();
+            assert_eq!(rows as i64, TripGenerator::new(0.001, part, 4).row_count());
+            assert!(chunk.iter().all(|batch| batch.num_rows() <= 100));
+        }
+
+        let sorted: Vec<_> = sorted.into_iter().flatten().collect();
+        let times = pickup_times(&sorted);
+        assert!(times.is_sorted());
+        let mut expected = pickup_times(&unsorted);
+        expected.sort();
+        assert_eq!(times, expected);
+
+        // without a column the chunks are unchanged
+        let unchanged: Vec<_> = KeySorted::new(chunks(4), None, 3).flatten().collect();
+        assert_eq!(unchanged, unsorted);
+    }
+
+    #[test]
+    fn test_merge_spilled_runs() {
+        let runs: Vec<_> = chunks(3)
+            .map(|chunk| {
+                let (_, run, _) = sort_run(chunk, &pickup_time(), 0).unwrap();
+                Run::spill(&run.unwrap(), 7).unwrap()
+            })
+            .collect();
+        let schema = Table::Trip.schema();
+        let mut merge = Merge::try_new(schema, &pickup_time(), runs, 50).unwrap();
+        let batches = merge.take(6000).unwrap();
+        assert_eq!(batches.len(), 120);
+        assert!(pickup_times(&batches).is_sorted());
+        assert!(merge.take(1).is_err());
+    }
+
+    #[test]
+    fn test_sort_error() {
+        let error = SortError::default();
+        let order = RowOrder::Column("t_missing".to_string());
+        let chunks = KeySorted::new(chunks(2), Some(order), 2)
+            .with_error(error.clone())
+            .count();
+        assert_eq!(chunks, 0);
+        let err = error.check().unwrap_err();
+        assert!(err.to_string().starts_with("Failed sorting by"), "{err}");
+
+        // the sink of the file fails instead of completing it
+        let sink = SortChecked::new(crate::WriterSink::new(vec![]), &error);
+        assert!(sink.flush().is_err());
+        assert!(SortError::default().check().is_ok());
+    }
+
+    #[test]
+    fn test_curve_sorted() {
+        let order = RowOrder::Curve(SpatialOrder::Hilbert);
+        let sorted: Vec<_> = KeySorted::new(chunks(4), Some(order), 3)
+            .map(|chunk| chunk.collect::<Vec<_>>())
+            .collect();
+        assert_eq!(sorted.len(), 4);
+        for (part, chunk) in (1..).zip(&sorted) {
+            let rows: usize = chunk.iter().map(|batch| batch.num_rows()).sum();
+            assert_eq!(rows as i64, TripGenerator::new(0.001, part, 4).row_count());
+            // the curve value column is dropped
+            assert_eq!(chunk[0].schema(), Table::Trip.schema());
+        }
+
+        // the values are sorted across the chunks, not only within each
+        let values: Vec<u64> = sorted
+            .concat()
+            .iter()
+            .flat_map(|batch| {
+                let values = curve_values(batch, SpatialOrder::Hilbert);
+                values.as_primitive::<UInt64Type>().values().to_vec()
+            })
+            .collect();
+        assert!(values.is_sorted());
+    }
+
+    fn trip_keys(batches: &[RecordBatch]) -> Vec<i64> {
+        batches
+            .iter()
+            .flat_map(|batch| {
+                let column = batch.column_by_name("t_tripkey").unwrap();
+                column.as_primitive::<Int64Type>().values().to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shuffled() {
+        let shuffle = |seed, num_threads| -> Vec<Vec<RecordBatch>> {
+            let order = RowOrder::shuffled(seed, "trip", 1);
+            KeySorted::new(chunks(4), Some(order), num_threads)
+                .map(|chunk| chunk.collect())
+                .collect()
+        };
+        let shuffled = shuffle(0, 3);
+        assert_eq!(shuffled.len(), 4);
+        for (part, chunk) in (1..).zip(&shuffled) {
+            let rows: usize = chunk.iter().map(|batch| batch.num_rows()).sum();
+            assert_eq!(rows as i64, TripGenerator::new(0.001, part, 4).row_count());
+            // the random key column is dropped
+            assert_eq!(chunk[0].schema(), Table::Trip.schema());
+        }
+
+        let keys = trip_keys(&shuffled.concat());
+        assert!(!keys.is_sorted());
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(sorted, trip_keys(&chunks(4).flatten().collect::<Vec<_>>()));
+
+        // the same seed shuffles the same way, whatever the number of threads
+        assert_eq!(trip_keys(&shuffle(0, 1).concat()), keys);
+        assert_ne!(trip_keys(&shuffle(1, 3).concat()), keys);
+        assert_ne!(
+            RowOrder::shuffled(0, "trip", 1),
+            RowOrder::shuffled(0, "trip", 2)
+        );
+        assert_ne!(
+            RowOrder::shuffled(0, "trip", 1),
+            RowOrder::shuffled(0, "building", 1)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `knn` command: print kNN queries with their exact neighbors
+
+use crate::generation::GenerationOptions;
+use clap::{Args, ValueEnum};
+use spatialbench::workload::{Dialect, KnnGroundTruth, KnnStream, KnnTable};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Print reproducible kNN queries, optionally with their exact neighbors
+#[derive(Debug, Args)]
+pub struct KnnArgs {
+    /// Table to search: trip (pickup locations) or building
+    #[arg(short, long, default_value_t = KnnTable::Building, value_parser = KnnTable::from_str)]
+    table: KnnTable,
+
+    /// Number of queries to generate
+    #[arg(short, long, default_value_t = 1)]
+    count: u64,
+
+    /// Number of neighbors of every query (default: drawn between 1 and 100)
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    k: Option<u32>,
+
+    /// Seed of the stream of queries
+    #[arg(long, default_value_t = 0)]
+    query_seed: u64,
+
+    /// Add the keys and planar distances of the exact neighbors of each
+    /// query, computed by generating the table in memory with the options of
+    /// the generation
+    #[arg(long, default_value_t = false)]
+    ground_truth: bool,
+
+    /// Output format for the queries
+    #[arg(short, long, default_value = "jsonl")]
+    format: KnnFormat,
+
+    /// SQL dialect for `--format sql`: sedona, postgis, duckdb or bigquery
+    #[arg(short, long, default_value = "sedona")]
+    dialect: Dialect,
+
+    #[command(flatten)]
+    generation: GenerationOptions,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum KnnFormat {
+    /// One JSON object per line, with the center as WKT
+    Jsonl,
+    /// CSV with a header, with the center as WKT and the neighbors separated
+    /// by spaces
+    Csv,
+    /// The queries, separated by empty lines
+    Sql,
+}
+
+impl KnnArgs {
+    /// Print the queries to stdout
+    pub fn run(&self) -> io::Result<()> {
+        self.generation.apply()?;
+        if self.ground_truth && self.format == KnnFormat::Sql {
+            eprintln!("Warning: --ground-truth is not printed with --format sql");
+        }
+        if self.format != KnnFormat::Sql && self.dialect != Dialect::Sedona {
+            eprintln!("Warning: --dialect is only used with --format sql");
+        }
+
+        let mut stream = KnnStream::new(self.table, self.generation.scale_factor, self.query_seed);
+        if let Some(k) = self.k {
+            stream = stream.with_k(k as usize);
+        }
+        let ground_truth = if self.ground_truth && self.format != KnnFormat::Sql {
+            let dataset = self.generation.dataset("--ground-truth")?;
+            Some(KnnGroundTruth::new(self.table, dataset))
+        } else {
+            None
+        };
+
+        let mut stdout = io::stdout().lock();
+        let result = self.write(&mut stdout, stream, ground_truth.as_ref());
+        match result {
+            // e.g. piped to `head`
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        }
+    }
+
+    fn write(
+        &self,
+        out: &mut impl Write,
+        stream: KnnStream,
+        ground_truth: Option<&KnnGroundTruth>,
+    ) -> io::Result<()> {
+        if self.format == KnnFormat::Csv {
+            let neighbors = if ground_truth.is_some() {
+                ",neighbors,distances"
+            } else {
+                ""
+            };
+            writeln!(out, "table,index,center,k{neighbors}")?;
+        }
+        for (index, query) in stream.take(self.count as usize).enumerate() {
+            let center = format!("POINT ({:.4} {:.4})", query.center.x(), query.center.y());
+            let neighbors = ground_truth.map(|truth| truth.neighbors(&query));
+            match self.format {
+                KnnFormat::Jsonl => {
+                    let mut json = serde_json::json!({
+                        "table": self.table.name(),
+                        "index": index,
+                        "center": center,
+                        "k": query.k,
+                    });
+                    if let Some(neighbors) = neighbors {
+                        let (keys, distances): (Vec<_>, Vec<_>) = neighbors.into_iter().unzip();
+                        json["neighbors"] = keys.into();
+                        json["distances"] = distances.into();
+                    }
+                    writeln!(out, "{json}")?;
+                }
+                KnnFormat::Csv => {
+                    write!(out, "{},{index},\"{center}\",{}", self.table, query.k)?;
+                    if let Some(neighbors) = neighbors {
+                        let keys: Vec<_> =
+                            neighbors.iter().map(|(key, _)| key.to_string()).collect();
+                        let distances: Vec<_> =
+                            neighbors.iter().map(|(_, d)| d.to_string()).collect();
+                        write!(out, ",{},{}", keys.join(" "), distances.join(" "))?;
+                    }
+                    writeln!(out)?;
+                }
+                KnnFormat::Sql => {
+                    if index > 0 {
+                        writeln!(out)?;
+                    }
+                    write!(out, "{}", query.sql(self.dialect))?;
+                }
+            }
+        }
+        out.flush()
+    }
+}
@@ -0,0 +1,571 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! * [`Manifest`]: the `_spatialbench_manifest.json` describing a dataset
+//! * [`Checksummed`]: a writer or [`Sink`] recording the [`Checksum`] of the
+//!   file it writes for the manifest
+//! * [`Bounded`]: a [`RecordBatchIterator`] recording the bounding boxes of
+//!   the geometry columns for the manifest
+//!
+//! The manifest records how a dataset in an output directory was generated
+//! (generator version, scale factor, seed and the settings of the
+//! geometries) and what it contains: the row count of each table, its files
+//! with their sizes and SHA-256 checksums, and the bounding box of each of
+//! its geometry columns.
+//!
+//! The checksums are computed from the bytes as they are written (or
+//! uploaded, see [`ObjectStoreWriter`]), so that `verify --checksums` detects
+//! files corrupted after they left the generator. Local files not written
+//! through a [`Checksummed`] writer (e.g. resumed files or Hive partitions)
+//! are read back when the manifest is written.
+
+use crate::generate::Sink;
+use crate::geoparquet::{is_wkb_type, wkb_bbox, wkb_values};
+use crate::object_store_writer::{object_store_url, ObjectStoreWriter, UploadOptions};
+use crate::output_plan::OutputLocation;
+use crate::parquet::IntoSize;
+use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use spatialbench::generators::RowCounts;
+use spatialbench::spatial::{crs, dimensions, geography, precision};
+use spatialbench_arrow::{RecordBatchIterator, TableGenerator};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use url::Url;
+
+/// Name of the manifest in the output directory
+pub const MANIFEST_FILE: &str = "_spatialbench_manifest.json";
+
+/// Description of a generated dataset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    generator: String,
+    version: String,
+    scale_factor: f64,
+    seed: u64,
+    format: String,
+    crs: String,
+    dimensions: String,
+    geography: bool,
+    /// Precision of the longitude / latitude, e.g. `9 digits`
+    #[serde(default)]
+    coordinate_precision: String,
+    tables: BTreeMap<String, TableManifest>,
+}
+
+/// Description of a table of a [`Manifest`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableManifest {
+    /// Number of rows, unless the table is not generated (zone)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rows: Option<i64>,
+    /// `[xmin, ymin, xmax, ymax]` of each geometry column written
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    bbox: BTreeMap<String, [f64; 4]>,
+    files: Vec<FileManifest>,
+}
+
+/// A file of a [`TableManifest`]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FileManifest {
+    /// Path relative to the output directory, with `/` separators
+    pub path: String,
+    #[serde(flatten)]
+    pub checksum: Checksum,
+}
+
+/// Size and SHA-256 checksum of a file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Checksum {
+    pub bytes: u64,
+    /// Lowercase hex digest
+    pub sha256: String,
+}
+
+impl Checksum {
+    /// Compute the checksum of the file at `path`
+    pub fn of_file(path: &Path) -> io::Result<Self> {
+        let mut hasher = Checksummed::new(io::sink(), None);
+        io::copy(&mut File::open(path)?, &mut hasher)?;
+        Ok(hasher.checksum())
+    }
+}
+
+impl Manifest {
+    /// Create a manifest of a dataset generated in `format` with the
+    /// current geometry settings
+    pub fn new(scale_factor: f64, seed: u64, format: &str) -> Self {
+        Self {
+            generator: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            scale_factor,
+            seed,
+            format: format.to_string(),
+            crs: crs::crs().to_string(),
+            dimensions: dimensions::dimensions().to_string(),
+            geography: geography::geography(),
+            coordinate_precision: precision::precision().to_string(),
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// Parse a manifest written by [`Self::write`]
+    pub fn parse(json: &[u8]) -> io::Result<Self> {
+        serde_json::from_slice(json).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid {MANIFEST_FILE}: {e}"),
+            )
+        })
+    }
+
+    /// Return the tables and their files
+    pub fn tables(&self) -> impl Iterator<Item = (&str, &[FileManifest])> {
+        self.tables
+            .iter()
+            .map(|(name, table)| (name.as_str(), table.files.as_slice()))
+    }
+
+    /// Add `table` with `rows` rows, with its files in `output_dir` and the
+    /// bounding boxes recorded by [`Bounded`]
+    ///
+    /// The files of a table are `{table}.{extension}` and all the files in
+    /// the `{table}` directory.
+    pub fn add_table(
+        &mut self,
+        output_dir: &Path,
+        table: &str,
+        rows: Option<i64>,
+    ) -> io::Result<()> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(output_dir)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str());
+            if path.is_dir() && name == Some(table) {
+                list_files(&path, &mut paths)?;
+            } else if name
+                .and_then(|name| name.split_once('.'))
+                .map(|(stem, _)| stem)
+                == Some(table)
+            {
+                paths.push(path);
+            }
+        }
+        paths.retain(|path| {
+            !matches!(
+                path.extension().and_then(|extension| extension.to_str()),
+                Some("inprogress" | "checkpoint")
+            )
+        });
+        paths.sort();
+        let files = paths
+            .iter()
+            .map(|path| file_manifest(output_dir, path))
+            .collect::<io::Result<_>>()?;
+        self.insert_table(table, rows, files);
+        Ok(())
+    }
+
+    /// Add `table` with `rows` rows, with the files uploaded below `url` by
+    /// [`ObjectStoreWriter`]s and the bounding boxes recorded by [`Bounded`]
+    pub fn add_uploaded_table(&mut self, url: &Url, table: &str, rows: Option<i64>) {
+        let base = format!("{}/", url.as_str().trim_end_matches('/'));
+        let checksums = CHECKSUMS.lock().unwrap();
+        let files = checksums
+            .iter()
+            .filter_map(|(location, checksum)| {
+                let path = location.strip_prefix(&base)?;
+                let in_table = path.strip_prefix(table)?;
+                (in_table.starts_with('/') || in_table.starts_with('.')).then(|| FileManifest {
+                    path: path.to_string(),
+                    checksum: checksum.clone(),
+                })
+            })
+            .collect();
+        drop(checksums);
+        self.insert_table(table, rows, files);
+    }
+
+    /// Add `table` with `rows` rows, with the `files` planned below
+    /// `output_dir` (e.g. with `--path-template`), local files or uploaded
+    /// objects, and the bounding boxes recorded by [`Bounded`]
+    pub fn add_planned_files(
+        &mut self,
+        output_dir: &Path,
+        table: &str,
+        rows: Option<i64>,
+        files: &[OutputLocation],
+    ) -> io::Result<()> {
+        let base = object_store_url(output_dir)
+            .map(|url| format!("{}/", url.as_str().trim_end_matches('/')));
+        let mut manifests = vec![];
+        for file in files {
+            match file {
+                OutputLocation::File(path) => manifests.push(file_manifest(output_dir, path)?),
+                OutputLocation::ObjectStore(url) => {
+                    let checksum = CHECKSUMS.lock().unwrap().get(url.as_str()).cloned();
+                    let checksum = checksum
+                        .ok_or_else(|| io::Error::other(format!("{url} was not uploaded")))?;
+                    let location = url.as_str();
+                    let path = base
+                        .as_deref()
+                        .and_then(|base| location.strip_prefix(base))
+                        .unwrap_or(location);
+                    manifests.push(FileManifest {
+                        path: path.to_string(),
+                        checksum,
+                    });
+                }
+                _ => {}
+            }
+        }
+        manifests.sort_by(|a, b| a.path.cmp(&b.path));
+        self.insert_table(table, rows, manifests);
+        Ok(())
+    }
+
+    fn insert_table(&mut self, table: &str, rows: Option<i64>, files: Vec<FileManifest>) {
+        let bbox = match table.parse::<spatialbench_arrow::Table>() {
+            Ok(generated) => {
+                let bounds = BOUNDS.get().map(|bounds| bounds.lock().unwrap().clone());
+                let bounds = bounds.unwrap_or_default();
+                generated
+                    .schema()
+                    .fields()
+                    .iter()
+                    .filter_map(|field| Some((field.name().clone(), *bounds.get(field.name())?)))
+                    .collect()
+            }
+            Err(_) => BTreeMap::new(),
+        };
+        self.tables
+            .insert(table.to_string(), TableManifest { rows, bbox, files });
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("manifest is serializable") + "\n"
+    }
+
+    /// Write the manifest to [`MANIFEST_FILE`] in `output_dir`
+    pub fn write(&self, output_dir: &Path) -> io::Result<()> {
+        fs::write(output_dir.join(MANIFEST_FILE), self.to_json())
+    }
+
+    /// Upload the manifest to [`MANIFEST_FILE`] below `url`
+    pub async fn upload(&self, url: &Url, options: UploadOptions) -> io::Result<()> {
+        let url = object_url(url, MANIFEST_FILE)?;
+        let json = self.to_json();
+        tokio::task::spawn_blocking(move || {
+            let mut writer = ObjectStoreWriter::try_new(&url, options)?;
+            writer.write_all(json.as_bytes())?;
+            writer.into_size().map(|_| ())
+        })
+        .await
+        .map_err(|e| io::Error::other(format!("Task Panic: {e}")))?
+    }
+}
+
+/// Return the URL of the file at `path` (with `/` separators) below `url`
+pub fn object_url(url: &Url, path: &str) -> io::Result<Url> {
+    Url::parse(&format!("{}/{path}", url.as_str().trim_end_matches('/')))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Return the number of rows of a generated table, or of part `part` of
+/// `num_parts` of it
+pub fn table_rows(
+    table: &str,
+    scale_factor: f64,
+    seed: u64,
+    row_counts: RowCounts,
+    part: Option<(i32, i32)>,
+) -> Option<i64> {
+    let table = table.parse().ok()?;
+    let generator = TableGenerator::new(table, scale_factor)
+        .with_seed(seed)
+        .with_row_counts(row_counts);
+    let generator = match part {
+        Some((part, num_parts)) => generator.with_part(part, num_parts),
+        None => generator,
+    };
+    Some(generator.row_count())
+}
+
+/// Add the files in `dir` and its subdirectories to `paths`
+fn list_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Return the path (relative to `output_dir`) and checksum of the file at
+/// `path`, reading it unless its checksum was recorded as it was written
+fn file_manifest(output_dir: &Path, path: &Path) -> io::Result<FileManifest> {
+    let relative = path.strip_prefix(output_dir).unwrap_or(path);
+    let components: Vec<_> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    let recorded = CHECKSUMS
+        .lock()
+        .unwrap()
+        .get(&path.display().to_string())
+        .cloned();
+    let checksum = match recorded {
+        Some(checksum) => checksum,
+        None => Checksum::of_file(path)?,
+    };
+    Ok(FileManifest {
+        path: components.join("/"),
+        checksum,
+    })
+}
+
+/// Checksums of the files written so far, by path or URL
+static CHECKSUMS: Mutex<BTreeMap<String, Checksum>> = Mutex::new(BTreeMap::new());
+
+/// Record the checksum of the file or object at `location`, written in full
+pub fn record_checksum(location: String, checksum: Checksum) {
+    CHECKSUMS.lock().unwrap().insert(location, checksum);
+}
+
+/// A writer or [`Sink`] computing the [`Checksum`] of the bytes written
+/// through it
+///
+/// Once the inner writer or sink is finished, the checksum is recorded for
+/// `location` (if any) with [`record_checksum`].
+pub struct Checksummed<W> {
+    inner: W,
+    location: Option<String>,
+    hasher: Sha256,
+    bytes: u64,
+}
+
+impl<W> Checksummed<W> {
+    /// Compute the checksum of the bytes written to `inner`, the whole file
+    /// or object at `location`
+    pub fn new(inner: W, location: Option<String>) -> Self {
+        Self {
+            inner,
+            location,
+            hasher: Sha256::new(),
+            bytes: 0,
+        }
+    }
+
+    fn update(&mut self, buf: &[u8]) {
+        self.hasher.update(buf);
+        self.bytes += buf.len() as u64;
+    }
+
+    /// Return the checksum of the bytes written so far
+    pub fn checksum(&self) -> Checksum {
+        Checksum {
+            bytes: self.bytes,
+            sha256: format!("{:x}", self.hasher.clone().finalize()),
+        }
+    }
+
+    /// Finish `inner` with `finish`, then record the checksum
+    fn finish<T>(self, finish: impl FnOnce(W) -> io::Result<T>) -> io::Result<T> {
+        let checksum = self.checksum();
+        let result = finish(self.inner)?;
+        if let Some(location) = self.location {
+            record_checksum(location, checksum);
+        }
+        Ok(result)
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + IntoSize> IntoSize for Checksummed<W> {
+    fn into_size(self) -> Result<usize, io::Error> {
+        self.finish(W::into_size)
+    }
+}
+
+impl<S: Sink> Sink for Checksummed<S> {
+    fn sink(&mut self, buffer: &[u8]) -> Result<(), io::Error> {
+        self.update(buffer);
+        self.inner.sink(buffer)
+    }
+
+    fn flush(self) -> Result<(), io::Error> {
+        self.finish(S::flush)
+    }
+}
+
+/// Bounding boxes of the geometry columns written so far, by column name
+static BOUNDS: OnceLock<Mutex<BTreeMap<String, [f64; 4]>>> = OnceLock::new();
+
+/// Record the bounding boxes of the geometry columns of the batches of
+/// [`Bounded`] iterators, for the manifest
+pub fn record_bounds() {
+    let _ = BOUNDS.set(Mutex::new(BTreeMap::new()));
+}
+
+/// A [`RecordBatchIterator`] that records the bounding boxes of the geometry
+/// columns of the batches of the inner iterator, if enabled with
+/// [`record_bounds`]
+pub struct Bounded<I> {
+    inner: I,
+}
+
+impl<I: RecordBatchIterator> Bounded<I> {
+    /// Record the bounding boxes of the batches of `inner`
+    pub fn new(inner: I) -> Self {
+        Self { inner }
+    }
+}
+
+impl<I: RecordBatchIterator> Iterator for Bounded<I> {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = self.inner.next()?;
+        if let Some(bounds) = BOUNDS.get() {
+            let schema = batch.schema();
+            for (field, column) in schema.fields().iter().zip(batch.columns()) {
+                if !is_wkb_type(field.data_type()) {
+                    continue;
+                }
+                let Some(bbox) = wkb_values(column)
+                    .flatten()
+                    .filter_map(wkb_bbox)
+                    .reduce(merge)
+                else {
+                    continue;
+                };
+                let mut bounds = bounds.lock().unwrap();
+                bounds
+                    .entry(field.name().clone())
+                    .and_modify(|bounds| *bounds = merge(*bounds, bbox))
+                    .or_insert(bbox);
+            }
+        }
+        Some(batch)
+    }
+}
+
+impl<I: RecordBatchIterator> RecordBatchIterator for Bounded<I> {
+    fn schema(&self) -> &SchemaRef {
+        self.inner.schema()
+    }
+}
+
+/// Return the bounding box covering `a` and `b`
+fn merge(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    [
+        a[0].min(b[0]),
+        a[1].min(b[1]),
+        a[2].max(b[2]),
+        a[3].max(b[3]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spatialbench::generators::TripGenerator;
+    use spatialbench_arrow::TripArrow;
+
+    #[test]
+    fn test_bounded() {
+        record_bounds();
+        let batches = Bounded::new(TripArrow::new(TripGenerator::new(0.001, 1, 1)));
+        let num_rows: usize = batches.map(|batch| batch.num_rows()).sum();
+        assert_eq!(num_rows, 6000);
+
+        let bounds = BOUNDS.get().unwrap().lock().unwrap();
+        let [xmin, ymin, xmax, ymax] = bounds["t_pickuploc"];
+        assert!(-180.0 <= xmin && xmin < xmax && xmax <= 180.0);
+        assert!(-90.0 <= ymin && ymin < ymax && ymax <= 90.0);
+        assert!(bounds.contains_key("t_dropoffloc"));
+    }
+
+    #[test]
+    fn test_add_table() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("vehicle.tbl"), "abc").unwrap();
+        fs::create_dir(dir.path().join("trip")).unwrap();
+        fs::write(dir.path().join("trip").join("trip.1.tbl"), "").unwrap();
+        fs::write(dir.path().join("trip").join("trip.2.inprogress"), "").unwrap();
+        fs::write(dir.path().join("vehicles.tbl"), "").unwrap();
+
+        let mut manifest = Manifest::new(1.0, 0, "tbl");
+        manifest
+            .add_table(dir.path(), "vehicle", Some(100))
+            .unwrap();
+        manifest.add_table(dir.path(), "trip", None).unwrap();
+        assert_eq!(
+            manifest.tables["vehicle"].files,
+            vec![FileManifest {
+                path: "vehicle.tbl".to_string(),
+                checksum: Checksum {
+                    bytes: 3,
+                    sha256: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+                        .to_string(),
+                },
+            }]
+        );
+        let trip_files: Vec<_> = manifest.tables["trip"]
+            .files
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect();
+        assert_eq!(trip_files, ["trip/trip.1.tbl"]);
+    }
+
+    #[test]
+    fn test_checksummed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vehicle.tbl");
+        let location = path.display().to_string();
+        let file = io::BufWriter::new(File::create(&path).unwrap());
+        let mut writer = Checksummed::new(file, Some(location.clone()));
+        writer.write_all(b"a").unwrap();
+        writer.write_all(b"bc").unwrap();
+        writer.into_size().unwrap();
+
+        // the recorded checksum is the one of the written file
+        let recorded = CHECKSUMS.lock().unwrap()[&location].clone();
+        assert_eq!(recorded, Checksum::of_file(&path).unwrap());
+        assert_eq!(recorded.bytes, 3);
+    }
+}
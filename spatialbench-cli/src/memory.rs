@@ -0,0 +1,159 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Bounding the memory of the generated data with `--memory-limit`
+//!
+//! Each table is generated in chunks (a buffer of text, a Parquet row group,
+//! ...) by up to num_threads tasks, and the generated chunks are queued
+//! until the writer has written them. With many tables, threads or slow
+//! writers, these chunks can exceed the memory of a small container.
+//!
+//! With a limit, the chunks of all the tables share one budget: a chunk
+//! reserves its size before it is generated and releases it once written,
+//! so the generators wait for the writers when the budget is used up.
+//!
+//! The size of a chunk is only known once it is generated, so each writer
+//! pipeline reserves the size of the largest chunk it has generated so far
+//! (see [`ChunkEstimate`]). The first chunk of a pipeline, and any chunk
+//! larger than the limit, reserves the whole budget and so is generated on
+//! its own.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// The budget is counted in permits of this many bytes
+const PERMIT_BYTES: u64 = 1024;
+
+/// Memory shared by the chunks in flight of all the tables
+#[derive(Debug)]
+struct MemoryBudget {
+    limit: u64,
+    /// one permit per [`PERMIT_BYTES`] of the limit
+    semaphore: Arc<Semaphore>,
+    permits: u32,
+}
+
+impl MemoryBudget {
+    fn new(limit: u64) -> Self {
+        let permits = limit.div_ceil(PERMIT_BYTES).clamp(1, u32::MAX as u64) as u32;
+        Self {
+            limit,
+            semaphore: Arc::new(Semaphore::new(permits as usize)),
+            permits,
+        }
+    }
+
+    /// Wait until `bytes` are available, at most the whole budget
+    ///
+    /// The semaphore is fair, so a large reservation is not starved by
+    /// smaller ones made after it.
+    async fn reserve(&self, bytes: usize) -> Reservation {
+        let permits = (bytes as u64)
+            .div_ceil(PERMIT_BYTES)
+            .clamp(1, self.permits as u64) as u32;
+        let permit = Arc::clone(&self.semaphore)
+            .acquire_many_owned(permits)
+            .await
+            .expect("memory budget semaphore is never closed");
+        Reservation {
+            _permit: Some(permit),
+        }
+    }
+}
+
+static BUDGET: OnceLock<MemoryBudget> = OnceLock::new();
+
+/// Limit the chunks in flight to `bytes` in total
+///
+/// Only the first call has an effect.
+pub fn set_memory_limit(bytes: u64) {
+    let _ = BUDGET.set(MemoryBudget::new(bytes));
+}
+
+/// Return the limit set with [`set_memory_limit`], if any
+pub fn memory_limit() -> Option<u64> {
+    BUDGET.get().map(|budget| budget.limit)
+}
+
+/// Memory reserved for a chunk, released when dropped
+///
+/// The default reservation reserves nothing, e.g. for the header of a file.
+#[derive(Debug, Default)]
+pub struct Reservation {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// The size of the chunks of one writer pipeline, to reserve before
+/// generating the next chunk
+#[derive(Debug, Default)]
+pub struct ChunkEstimate {
+    /// the largest chunk so far, 0 before the first chunk
+    bytes: AtomicUsize,
+}
+
+impl ChunkEstimate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait until the budget has room for the next chunk, if limited with
+    /// [`set_memory_limit`]
+    pub async fn reserve(&self) -> Reservation {
+        let Some(budget) = BUDGET.get() else {
+            return Reservation::default();
+        };
+        match self.bytes.load(Ordering::Relaxed) {
+            0 => budget.reserve(usize::MAX).await,
+            bytes => budget.reserve(bytes).await,
+        }
+    }
+
+    /// Record the size of a generated chunk
+    pub fn update(&self, bytes: usize) {
+        self.bytes.fetch_max(bytes, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_budget_backpressure() {
+        let budget = MemoryBudget::new(10 * 1024);
+        let first = budget.reserve(6 * 1024).await;
+        let second = budget.reserve(4 * 1024).await;
+        // the budget is used up, so the next reservation waits
+        let third = budget.reserve(1);
+        tokio::pin!(third);
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut third)
+            .await
+            .is_err());
+        drop(first);
+        tokio::time::timeout(Duration::from_secs(1), third)
+            .await
+            .expect("reservation after release");
+        drop(second);
+        // a reservation larger than the budget takes all of it
+        let all = budget.reserve(usize::MAX).await;
+        assert_eq!(budget.semaphore.available_permits(), 0);
+        drop(all);
+        assert_eq!(budget.semaphore.available_permits(), 10);
+    }
+}
@@ -0,0 +1,237 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! `--merge`: merge the parts of each table into a single file
+//!
+//! With `--parts`, `--mb-per-file` or `--target-file-size`, the parts of a
+//! table are generated in parallel to `{table}/{table}.{part}.{extension}`.
+//! For consumers that require exactly one file per table, the parts are
+//! then merged, in order, into `{table}.{extension}` (as written without
+//! parts) and removed.
+//!
+//! Parquet row groups are copied as they are, without decoding and
+//! re-encoding them, and the GeoParquet metadata covers all the parts (see
+//! [`geoparquet::merge_metadata`]). Text parts are concatenated, without the
+//! header of all but the first CSV part. Other formats have a header or
+//! footer describing the whole file and so can not be merged this way.
+
+use crate::geoparquet;
+use crate::OutputFormat;
+use log::info;
+use parquet::column::writer::ColumnCloseResult;
+use parquet::file::metadata::{KeyValue, ParquetMetaDataReader};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Returns true if the parts of `format` can be merged
+pub fn supports(format: OutputFormat) -> bool {
+    matches!(
+        format,
+        OutputFormat::Parquet | OutputFormat::Tbl | OutputFormat::Csv | OutputFormat::Geojsonl
+    )
+}
+
+/// Merge the parts of `table` in `dir` into `{table}.{extension}`
+///
+/// `csv_header` is true if each (CSV) part starts with a header line. Does
+/// nothing if the table has no parts, e.g. it was not generated.
+pub fn merge_table(
+    dir: &Path,
+    table: &str,
+    extension: &str,
+    format: OutputFormat,
+    csv_header: bool,
+) -> io::Result<()> {
+    let table_dir = dir.join(table);
+    let parts = parts(&table_dir, table, extension)?;
+    if parts.is_empty() {
+        return Ok(());
+    }
+    let path = dir.join(format!("{table}.{extension}"));
+    // write to a temp file and then rename to avoid partial files
+    let temp_path = path.with_extension("inprogress");
+    let file = File::create(&temp_path)
+        .map_err(|err| io::Error::other(format!("Failed to create {temp_path:?}: {err}")))?;
+    match format {
+        OutputFormat::Parquet => merge_parquet(&parts, file)?,
+        OutputFormat::Csv if csv_header => merge_text(&parts, file, true)?,
+        _ => merge_text(&parts, file, false)?,
+    }
+    fs::rename(&temp_path, &path).map_err(|e| {
+        io::Error::other(format!(
+            "Failed to rename {temp_path:?} to {path:?} file: {e}"
+        ))
+    })?;
+    for part in &parts {
+        fs::remove_file(part)?;
+    }
+    // the directory may also hold other files, e.g. of a previous run
+    if fs::read_dir(&table_dir)?.next().is_none() {
+        fs::remove_dir(&table_dir)?;
+    }
+    info!("Merged {} parts into {}", parts.len(), path.display());
+    Ok(())
+}
+
+/// Return the parts `{table}.{part}.{extension}` in `table_dir`, in order
+fn parts(table_dir: &Path, table: &str, extension: &str) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(table_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let prefix = format!("{table}.");
+    let suffix = format!(".{extension}");
+    let mut parts = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        let part = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(&prefix)?.strip_suffix(&suffix))
+            .and_then(|part| part.parse::<u32>().ok());
+        if let Some(part) = part {
+            parts.push((part, path));
+        }
+    }
+    parts.sort();
+    Ok(parts.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Concatenate the text `parts`, without the first line of all but the
+/// first part if `skip_header`
+fn merge_text(parts: &[PathBuf], file: File, skip_header: bool) -> io::Result<()> {
+    let mut writer = BufWriter::with_capacity(32 * 1024 * 1024, file); // 32MB buffer
+    for (i, part) in parts.iter().enumerate() {
+        let mut reader = BufReader::new(File::open(part)?);
+        if skip_header && i > 0 {
+            reader.read_until(b'\n', &mut vec![])?;
+        }
+        io::copy(&mut reader, &mut writer)?;
+    }
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// Copy the row groups of the Parquet `parts` into `file`, in order
+fn merge_parquet(parts: &[PathBuf], file: File) -> io::Result<()> {
+    let inputs = parts
+        .iter()
+        .map(|path| {
+            let reader = File::open(path)?;
+            let metadata = ParquetMetaDataReader::new()
+                .parse_and_finish(&reader)
+                .map_err(|e| io::Error::other(format!("Failed to read {path:?}: {e}")))?;
+            Ok((reader, metadata))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let first = inputs[0].1.file_metadata();
+    for (path, (_, metadata)) in parts.iter().zip(&inputs).skip(1) {
+        if metadata.file_metadata().schema() != first.schema() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{path:?} does not have the schema of {:?}", parts[0]),
+            ));
+        }
+    }
+
+    // The other metadata (e.g. the Arrow schema) is the same in all the parts
+    let mut key_value_metadata: Vec<KeyValue> = first
+        .key_value_metadata()
+        .into_iter()
+        .flatten()
+        .filter(|kv| kv.key != "geo")
+        .cloned()
+        .collect();
+    let geo = inputs.iter().filter_map(|(_, metadata)| {
+        let key_value_metadata = metadata.file_metadata().key_value_metadata()?;
+        let geo = key_value_metadata.iter().find(|kv| kv.key == "geo")?;
+        geo.value.as_deref()
+    });
+    let geo = geoparquet::merge_metadata(geo).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid GeoParquet metadata: {e}"),
+        )
+    })?;
+    if let Some(geo) = geo {
+        key_value_metadata.push(KeyValue::new("geo".to_string(), geo));
+    }
+    let properties = WriterProperties::builder()
+        .set_key_value_metadata(Some(key_value_metadata))
+        .build();
+
+    let schema = first.schema_descr().root_schema_ptr();
+    let writer = BufWriter::with_capacity(32 * 1024 * 1024, file); // 32MB buffer
+    let mut writer = SerializedFileWriter::new(writer, schema, Arc::new(properties))
+        .map_err(io::Error::other)?;
+    for (reader, metadata) in &inputs {
+        for row_group in metadata.row_groups() {
+            let mut row_group_writer = writer.next_row_group().map_err(io::Error::other)?;
+            // the column chunks are copied as they are, without their page
+            // indexes
+            for column in row_group.columns() {
+                let result = ColumnCloseResult {
+                    bytes_written: column.compressed_size() as u64,
+                    rows_written: row_group.num_rows() as u64,
+                    metadata: column.clone(),
+                    bloom_filter: None,
+                    column_index: None,
+                    offset_index: None,
+                };
+                row_group_writer
+                    .append_column(reader, result)
+                    .map_err(io::Error::other)?;
+            }
+            row_group_writer.close().map_err(io::Error::other)?;
+        }
+    }
+    let writer = writer.into_inner().map_err(io::Error::other)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let table_dir = dir.path().join("vehicle");
+        fs::create_dir(&table_dir).unwrap();
+        // part 10 sorts after part 2
+        for (part, rows) in [(1, "1\n2\n"), (10, "5\n"), (2, "3\n4\n")] {
+            let path = table_dir.join(format!("vehicle.{part}.csv"));
+            fs::write(path, format!("v_vehiclekey\n{rows}")).unwrap();
+        }
+        fs::write(table_dir.join("notes.txt"), "not a part").unwrap();
+
+        merge_table(dir.path(), "vehicle", "csv", OutputFormat::Csv, true).unwrap();
+        let merged = fs::read_to_string(dir.path().join("vehicle.csv")).unwrap();
+        assert_eq!(merged, "v_vehiclekey\n1\n2\n3\n4\n5\n");
+        // only the parts are removed
+        let remaining: Vec<_> = fs::read_dir(&table_dir).unwrap().collect();
+        assert_eq!(remaining.len(), 1);
+
+        // tables without parts are left alone
+        merge_table(dir.path(), "trip", "csv", OutputFormat::Csv, true).unwrap();
+        assert!(!dir.path().join("trip.csv").exists());
+    }
+}
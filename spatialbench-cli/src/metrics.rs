@@ -0,0 +1,248 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Prometheus metrics of the generation with `--metrics-port`
+//!
+//! The rows and bytes of the tables are the counts of [`crate::progress`].
+//! The object store writer counts the bytes it uploads and the requests it
+//! retries with [`add_uploaded_bytes`] and [`add_upload_retry`]. [`serve`]
+//! answers `GET /metrics` with all of them in the Prometheus text format.
+
+use crate::progress::{self, TableStatus};
+use log::{debug, info};
+use std::fmt::Write as _;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Bytes uploaded to object stores so far
+static UPLOADED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// WebHDFS requests retried so far
+static UPLOAD_RETRIES: AtomicU64 = AtomicU64::new(0);
+
+/// Count `bytes` more bytes uploaded to an object store
+pub fn add_uploaded_bytes(bytes: u64) {
+    UPLOADED_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Count one more retried WebHDFS request
+pub fn add_upload_retry() {
+    UPLOAD_RETRIES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Serve the metrics on `port` of all the interfaces until the process exits
+pub async fn serve(port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)))
+        .await
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to listen on port {port}: {e}")))?;
+    info!("Serving Prometheus metrics on http://0.0.0.0:{port}/metrics");
+    tokio::spawn(accept(listener));
+    Ok(())
+}
+
+/// Answer the connections to `listener`
+async fn accept(listener: TcpListener) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(async move {
+                    if let Err(e) = respond(stream).await {
+                        debug!("Failed to answer a metrics request: {e}");
+                    }
+                });
+            }
+            Err(e) => debug!("Failed to accept a metrics connection: {e}"),
+        }
+    }
+}
+
+/// Answer the HTTP request on `stream` and close it
+async fn respond(mut stream: TcpStream) -> io::Result<()> {
+    // only the request line matters, so read until the end of the headers
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let n = stream.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..n]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut words = request.split_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render(&progress::statuses())),
+        _ => ("404 Not Found", "Not Found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Name, type, help and value of a metric of each table
+type TableMetric = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&TableStatus) -> f64,
+);
+
+/// Return the metrics of the tables and the process in the Prometheus text
+/// format
+fn render(statuses: &[TableStatus]) -> String {
+    let mut out = String::new();
+    let per_table: [TableMetric; 5] = [
+        (
+            "spatialbench_rows_generated_total",
+            "counter",
+            "Rows generated",
+            |s| s.rows as f64,
+        ),
+        (
+            "spatialbench_rows",
+            "gauge",
+            "Rows of the table once generated",
+            |s| s.total_rows as f64,
+        ),
+        (
+            "spatialbench_written_bytes_total",
+            "counter",
+            "Bytes written",
+            |s| s.bytes as f64,
+        ),
+        (
+            "spatialbench_rows_per_second",
+            "gauge",
+            "Rows generated per second since the table started",
+            TableStatus::rows_per_sec,
+        ),
+        (
+            "spatialbench_written_bytes_per_second",
+            "gauge",
+            "Bytes written per second since the table started",
+            TableStatus::bytes_per_sec,
+        ),
+    ];
+    for (name, kind, help, value) in per_table {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        for status in statuses {
+            let _ = writeln!(
+                out,
+                "{name}{{table=\"{}\"}} {}",
+                status.table.name(),
+                value(status)
+            );
+        }
+    }
+
+    let totals = [
+        (
+            "spatialbench_uploaded_bytes_total",
+            "Bytes uploaded to object stores",
+            UPLOADED_BYTES.load(Ordering::Relaxed),
+        ),
+        (
+            "spatialbench_upload_retries_total",
+            "WebHDFS requests retried",
+            UPLOAD_RETRIES.load(Ordering::Relaxed),
+        ),
+    ];
+    for (name, help, value) in totals {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        let _ = writeln!(out, "{name} {value}");
+    }
+
+    if let Some(bytes) = resident_memory_bytes() {
+        let name = "process_resident_memory_bytes";
+        let _ = writeln!(out, "# HELP {name} Resident memory size in bytes");
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        let _ = writeln!(out, "{name} {bytes}");
+    }
+    out
+}
+
+/// Return the resident memory of the process, where `/proc` reports it
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    // e.g. `VmRSS:     1234 kB`
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Table;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render() {
+        let status = TableStatus {
+            table: Table::Trip,
+            total_rows: 6_000_000,
+            rows: 1_500_000,
+            bytes: 300 << 20,
+            elapsed: Duration::from_secs(10),
+            done: false,
+        };
+        let metrics = render(&[status]);
+        for line in [
+            "# TYPE spatialbench_rows_generated_total counter",
+            "spatialbench_rows_generated_total{table=\"trip\"} 1500000",
+            "spatialbench_rows{table=\"trip\"} 6000000",
+            "spatialbench_written_bytes_total{table=\"trip\"} 314572800",
+            "spatialbench_rows_per_second{table=\"trip\"} 150000",
+            "spatialbench_written_bytes_per_second{table=\"trip\"} 31457280",
+            "# TYPE spatialbench_upload_retries_total counter",
+        ] {
+            assert!(metrics.lines().any(|l| l == line), "{line} in {metrics}");
+        }
+        assert!(metrics.contains("\nspatialbench_uploaded_bytes_total "));
+    }
+
+    #[tokio::test]
+    async fn test_respond() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(accept(listener));
+
+        let get = |path: &'static str| async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+        let metrics = get("/metrics").await;
+        assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"), "{metrics}");
+        assert!(metrics.contains("# TYPE spatialbench_uploaded_bytes_total counter"));
+        assert!(get("/").await.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
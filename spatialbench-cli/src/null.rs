@@ -0,0 +1,134 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! `--output null`: generate the rows without serializing or writing them,
+//! to measure the throughput of the generators alone
+//!
+//! The rows are generated in parallel exactly as for the other outputs, but
+//! the [`Source`]s of this module drop each row rather than formatting it.
+
+use super::generate::Source;
+use super::progress::TableStatus;
+use super::throttle::throttle;
+use spatialbench::generators::{
+    BuildingGenerator, CityGenerator, CountryGenerator, CustomerGenerator, DriverGenerator,
+    LandmassGenerator, PoiGenerator, PointCloudGenerator, RasterGenerator, RegionGenerator,
+    RoadGenerator, TrajectoryGenerator, TripGenerator, VehicleGenerator,
+};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Returns true if `--output` is `null`
+pub fn is_null_output(path: &Path) -> bool {
+    path.as_os_str() == "null"
+}
+
+/// Define a Source that generates the rows of a table and drops them
+macro_rules! define_null_source {
+    ($SOURCE_NAME:ident, $GENERATOR_TYPE:ty) => {
+        pub struct $SOURCE_NAME {
+            inner: $GENERATOR_TYPE,
+        }
+
+        impl $SOURCE_NAME {
+            pub fn new(inner: $GENERATOR_TYPE) -> Self {
+                Self { inner }
+            }
+        }
+
+        impl Source for $SOURCE_NAME {
+            fn header(&self, buffer: Vec<u8>) -> Vec<u8> {
+                buffer
+            }
+
+            fn create(self, buffer: Vec<u8>) -> Vec<u8> {
+                for item in self.inner.iter() {
+                    throttle(1);
+                    // don't let the compiler optimize the generation away
+                    std::hint::black_box(item);
+                }
+                buffer
+            }
+        }
+    };
+}
+
+define_null_source!(VehicleNullSource, VehicleGenerator<'static>);
+define_null_source!(DriverNullSource, DriverGenerator<'static>);
+define_null_source!(CustomerNullSource, CustomerGenerator<'static>);
+define_null_source!(TripNullSource, TripGenerator);
+define_null_source!(BuildingNullSource, BuildingGenerator<'static>);
+define_null_source!(TrajectoryNullSource, TrajectoryGenerator);
+define_null_source!(RoadNullSource, RoadGenerator);
+define_null_source!(LandmassNullSource, LandmassGenerator);
+define_null_source!(CountryNullSource, CountryGenerator);
+define_null_source!(RegionNullSource, RegionGenerator);
+define_null_source!(CityNullSource, CityGenerator);
+define_null_source!(PoiNullSource, PoiGenerator);
+define_null_source!(RasterNullSource, RasterGenerator);
+define_null_source!(PointCloudNullSource, PointCloudGenerator);
+
+/// Return the throughput of the generators of the tables of `statuses`,
+/// which ran on `num_threads` threads
+pub fn report(statuses: &[TableStatus], num_threads: usize) -> String {
+    let mut report = String::new();
+    for status in statuses {
+        let rate = status.rows_per_sec();
+        writeln!(
+            report,
+            "{}: {} rows in {:.3}s, {:.0} rows/s, {:.0} rows/s per thread ({num_threads} threads)",
+            status.table,
+            status.rows,
+            status.elapsed.as_secs_f64(),
+            rate,
+            rate / num_threads.max(1) as f64,
+        )
+        .expect("writing to a string is infallible");
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Table;
+    use std::time::Duration;
+
+    #[test]
+    fn test_report() {
+        let status = TableStatus {
+            table: Table::Trip,
+            total_rows: 6000,
+            rows: 6000,
+            bytes: 0,
+            elapsed: Duration::from_secs(2),
+            done: true,
+        };
+        assert_eq!(
+            report(&[status], 4),
+            "trip: 6000 rows in 2.000s, 3000 rows/s, 750 rows/s per thread (4 threads)\n"
+        );
+    }
+
+    #[test]
+    fn test_null_source() {
+        let source = TripNullSource::new(TripGenerator::new(0.001, 1, 1));
+        assert!(source.create(vec![]).is_empty());
+        assert!(is_null_output(Path::new("null")));
+        assert!(!is_null_output(Path::new("null.parquet")));
+    }
+}
@@ -0,0 +1,1277 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! [`ObjectStoreWriter`]: writes generated data to any [`object_store`]
+//! supported location (S3, Azure, HTTP, local files, ...)
+
+use crate::aws_credentials;
+use crate::generate::Sink;
+use crate::manifest::{record_checksum, Checksum};
+use crate::metrics;
+use crate::parquet::IntoSize;
+use crate::webhdfs::{self, WebHdfs};
+use clap::ValueEnum;
+use futures::{stream, FutureExt, StreamExt};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use log::{debug, info, warn};
+use object_store::aws::AmazonS3Builder;
+use object_store::azure::MicrosoftAzureBuilder;
+use object_store::http::HttpBuilder;
+use object_store::path::Path;
+use object_store::{
+    Attribute, AttributeValue, Attributes, BackoffConfig, ClientOptions, MultipartUpload,
+    ObjectStore, ObjectStoreScheme, PutMultipartOptions, PutOptions, PutPayload, RetryConfig,
+    TagSet,
+};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::hash::{BuildHasher, RandomState};
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempPath};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// Size of each part of the multipart upload (32MB)
+///
+/// S3 requires parts to be at least 5MB, and it is convenient for memory
+/// accounting to use the same buffer size as the local writers.
+pub const PART_SIZE: usize = 32 * 1024 * 1024;
+
+/// Maximum number of complete parts waiting to be uploaded before writes block
+///
+/// Parts staged on disk (see [`UploadOptions::staging_dir`]) are only
+/// bounded by the free space of the staging directory.
+const MAX_QUEUED_PARTS: usize = 2;
+
+/// Default number of parts uploaded concurrently for each object
+pub const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+
+/// Default number of attempts for each object store request
+pub const DEFAULT_UPLOAD_MAX_ATTEMPTS: usize = 5;
+
+/// Options for uploading to an object store
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadOptions {
+    /// Maximum number of parts of each object to upload at once
+    pub concurrency: usize,
+    /// How failed requests are retried
+    pub retry: RetryPolicy,
+    /// Settings of the objects written to S3
+    pub s3: S3Options,
+    /// Local directory to spill the parts to until they are uploaded,
+    /// rather than buffering them in memory
+    pub staging_dir: Option<PathBuf>,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_UPLOAD_CONCURRENCY,
+            retry: RetryPolicy::default(),
+            s3: S3Options::default(),
+            staging_dir: None,
+        }
+    }
+}
+
+impl UploadOptions {
+    /// Maximum number of complete parts of an object waiting to be uploaded
+    fn max_queued_parts(&self) -> usize {
+        match self.staging_dir {
+            Some(_) => Semaphore::MAX_PERMITS,
+            None => MAX_QUEUED_PARTS,
+        }
+    }
+}
+
+/// Server-side encryption of the objects written to S3
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum S3Encryption {
+    /// Keys managed by S3 (SSE-S3)
+    S3,
+    /// Keys managed by AWS KMS (SSE-KMS), with the given key (ID or ARN) or
+    /// the default key of the bucket
+    Kms { key_id: Option<String> },
+}
+
+/// Settings of S3 and of the objects written to it
+///
+/// Many corporate buckets require object settings (e.g. a bucket policy
+/// rejecting unencrypted `PUT`s), and S3 compatible stores such as MinIO,
+/// Ceph RGW and Cloudflare R2 require connection settings. These override
+/// the `AWS_*` environment variables, and are ignored for other object
+/// stores.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct S3Options {
+    /// Server-side encryption, or the default encryption of the bucket
+    pub encryption: Option<S3Encryption>,
+    /// Storage class (e.g. `STANDARD_IA` or `INTELLIGENT_TIERING`)
+    pub storage_class: Option<String>,
+    /// Canned ACL (e.g. `bucket-owner-full-control`)
+    pub acl: Option<String>,
+    /// Tags, as `(key, value)` pairs
+    pub tags: Vec<(String, String)>,
+    /// Endpoint of an S3 compatible store (e.g. `http://localhost:9000`)
+    pub endpoint: Option<String>,
+    /// Address buckets as `endpoint/bucket` rather than `bucket.endpoint`
+    pub path_style: bool,
+    /// Agree to pay for the requests to requester pays buckets
+    pub requester_pays: bool,
+    /// Accept invalid TLS certificates, e.g. self-signed ones
+    pub skip_tls_verify: bool,
+}
+
+/// The `--s3-sse` server-side encryption types
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum S3SseType {
+    /// SSE-S3
+    #[value(name = "AES256")]
+    Aes256,
+    /// SSE-KMS
+    #[value(name = "aws:kms")]
+    AwsKms,
+}
+
+/// The canned ACLs of S3
+pub const S3_CANNED_ACLS: [&str; 7] = [
+    "private",
+    "public-read",
+    "public-read-write",
+    "authenticated-read",
+    "aws-exec-read",
+    "bucket-owner-read",
+    "bucket-owner-full-control",
+];
+
+/// Parse a `--s3-tag` value such as `team=geo`
+pub fn parse_s3_tag(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("Invalid tag {value:?}, expected KEY=VALUE")),
+    }
+}
+
+impl S3Options {
+    /// Return true if no settings are given, so objects get the defaults
+    /// of the bucket
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Return the [`AmazonS3Builder`] configuration for these settings
+    fn config(&self) -> Vec<(String, String)> {
+        let mut config = vec![];
+        match &self.encryption {
+            None => {}
+            Some(S3Encryption::S3) => {
+                config.push(("aws_server_side_encryption".into(), "AES256".into()));
+            }
+            Some(S3Encryption::Kms { key_id }) => {
+                config.push(("aws_server_side_encryption".into(), "aws:kms".into()));
+                if let Some(key_id) = key_id {
+                    config.push(("aws_sse_kms_key_id".into(), key_id.clone()));
+                }
+            }
+        }
+        if let Some(endpoint) = &self.endpoint {
+            config.push(("aws_endpoint".into(), endpoint.clone()));
+            if endpoint.starts_with("http://") {
+                config.push(("aws_allow_http".into(), "true".into()));
+            }
+        }
+        if self.path_style {
+            config.push(("aws_virtual_hosted_style_request".into(), "false".into()));
+        }
+        if self.requester_pays {
+            config.push(("aws_request_payer".into(), "true".into()));
+        }
+        if self.skip_tls_verify {
+            config.push(("allow_invalid_certificates".into(), "true".into()));
+        }
+        config
+    }
+
+    /// Return the default headers of the requests for these settings
+    fn headers(&self) -> io::Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        if let Some(acl) = &self.acl {
+            let value = HeaderValue::from_str(acl).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid ACL {acl:?}"))
+            })?;
+            headers.insert(HeaderName::from_static("x-amz-acl"), value);
+        }
+        Ok(headers)
+    }
+
+    fn tag_set(&self) -> TagSet {
+        let mut tags = TagSet::default();
+        for (key, value) in &self.tags {
+            tags.push(key, value);
+        }
+        tags
+    }
+
+    fn attributes(&self) -> Attributes {
+        self.storage_class
+            .iter()
+            .map(|class| (Attribute::StorageClass, AttributeValue::from(class.clone())))
+            .collect()
+    }
+
+    /// Return the options of single `put` requests
+    fn put_options(&self) -> PutOptions {
+        PutOptions {
+            tags: self.tag_set(),
+            attributes: self.attributes(),
+            ..Default::default()
+        }
+    }
+
+    /// Return the options of multipart uploads
+    fn multipart_options(&self) -> PutMultipartOptions {
+        PutMultipartOptions {
+            tags: self.tag_set(),
+            attributes: self.attributes(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Retries failed object store requests with jittered exponential backoff
+///
+/// The [`object_store`] HTTP clients of the S3, Azure and HTTP stores retry
+/// each individual request, including the parts of multipart uploads (see
+/// [`Self::client_config`]). Only the requests to [`WebHdfs`], whose client
+/// does not retry, are retried with [`Self::retry`], so each request is
+/// attempted at most [`Self::max_attempts`] times either way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: usize,
+    /// Backoff before the first retry, doubled for each subsequent retry
+    pub initial_backoff: Duration,
+    /// Maximum backoff between retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_UPLOAD_MAX_ATTEMPTS,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Return how long to wait before retrying after `attempt` attempts
+    ///
+    /// The exponential backoff is randomly jittered between 50% and 100% so
+    /// that concurrent uploads do not retry in lockstep.
+    fn backoff(&self, attempt: usize) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16) as u32;
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        let jitter = (RandomState::new().hash_one(attempt) % 1000) as u32;
+        backoff / 2 + backoff / 2 * jitter / 1000
+    }
+
+    /// Return the retry configuration of the [`object_store`] HTTP client
+    fn client_config(&self) -> RetryConfig {
+        RetryConfig {
+            backoff: BackoffConfig {
+                init_backoff: self.initial_backoff,
+                max_backoff: self.max_backoff,
+                base: 2.0,
+            },
+            max_retries: self.max_attempts - 1,
+            ..RetryConfig::default()
+        }
+    }
+
+    /// Run `f` until it succeeds, fails with an error that is not
+    /// [transient](webhdfs::is_transient), or [`Self::max_attempts`]
+    /// attempts have been made
+    async fn retry<T, F, Fut>(&self, what: &str, mut f: F) -> object_store::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = object_store::Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match f().await {
+                Ok(v) => return Ok(v),
+                Err(e) => self.wait_for_retry(what, &mut attempt, e).await?,
+            }
+        }
+    }
+
+    /// Called after attempt number `attempt` of `what` failed with `e`
+    ///
+    /// If the request should be retried, waits for the backoff and increments
+    /// `attempt`, otherwise returns `e`.
+    async fn wait_for_retry(
+        &self,
+        what: &str,
+        attempt: &mut usize,
+        e: object_store::Error,
+    ) -> object_store::Result<()> {
+        if *attempt >= self.max_attempts || !webhdfs::is_transient(&e) {
+            return Err(e);
+        }
+        let backoff = self.backoff(*attempt);
+        metrics::add_upload_retry();
+        warn!(
+            "{what} failed (attempt {attempt}/{}), retrying in {backoff:?}: {e}",
+            self.max_attempts
+        );
+        tokio::time::sleep(backoff).await;
+        *attempt += 1;
+        Ok(())
+    }
+}
+
+/// Prefixes of environment variables that are passed to the object store
+/// builders as configuration (e.g. `AWS_REGION` becomes `aws_region`)
+const ENV_PREFIXES: &[&str] = &["AWS_", "AZURE_", "GOOGLE_"];
+
+/// Returns the URL if `output_dir` refers to an object store location
+/// (e.g. `s3://bucket/path`, `az://container/path` or `hdfs://namenode/path`)
+/// rather than a local directory
+pub fn object_store_url(output_dir: &std::path::Path) -> Option<Url> {
+    let url = Url::parse(output_dir.to_str()?).ok()?;
+    (ObjectStoreScheme::parse(&url).is_ok() || webhdfs::is_webhdfs_url(&url)).then_some(url)
+}
+
+/// Writes data to a single object.
+///
+/// Data is buffered in memory as it is written, and each complete part of
+/// [`PART_SIZE`] bytes is sent to a background task that uploads it as part
+/// of a multipart upload. Up to [`UploadOptions::concurrency`] parts are
+/// uploaded at once and at most [`MAX_QUEUED_PARTS`] more are queued, so
+/// memory use is bounded regardless of the size of the output. Objects
+/// smaller than a single part are uploaded with a single `put` when the
+/// writer is finished (see [`IntoSize`] and [`Sink::flush`]).
+///
+/// With [`UploadOptions::staging_dir`], the parts are written to temporary
+/// files there instead, and read back just before they are uploaded, so
+/// writes never wait for the network as long as the directory has space.
+/// The files are deleted once uploaded.
+///
+/// Requests are retried according to [`UploadOptions::retry`] by the
+/// [`object_store`] HTTP client (see [`RetryPolicy::client_config`]). As
+/// the WebHDFS client does not retry, starting, completing and single `put`
+/// requests to it are retried by the writer instead. Its parts can not be
+/// retried this way, as [`MultipartUpload::put_part`] assigns the part number
+/// when it is called. If the upload fails (or the writer is dropped before
+/// it is finished) the multipart upload is aborted so incomplete uploads are
+/// not left behind.
+///
+/// The SHA-256 checksum of the data is computed as it is written, and
+/// recorded for the URL with [`record_checksum`] once the upload completes.
+///
+/// The store is resolved from the URL with [`object_store::parse_url_opts`].
+/// Credentials and other settings are read from the standard `AWS_*` and
+/// `AZURE_*` environment variables (e.g. `AWS_REGION` or
+/// `AZURE_STORAGE_ACCOUNT_NAME`). S3 credentials are also read from the
+/// profile of the AWS shared config files (see [`aws_credentials`]), web
+/// identity tokens, and container and instance metadata.
+pub struct ObjectStoreWriter {
+    store: Arc<dyn ObjectStore>,
+    path: Path,
+    /// The URL the checksum is recorded for
+    location: String,
+    /// Checksum of the bytes written so far
+    hasher: Sha256,
+    /// The bytes written so far that do not yet form a complete part, if any
+    buffer: Option<PartBuffer>,
+    /// Total number of bytes written
+    num_bytes: usize,
+    options: UploadOptions,
+    /// The background upload, started when the first part is complete
+    upload: Option<Upload>,
+    /// Handle to the runtime to do the async upload from a blocking thread
+    handle: Handle,
+}
+
+/// A multipart upload running in a background task
+struct Upload {
+    /// Sends parts to the upload task
+    tx: Sender<Part>,
+    /// Set before the channel is closed if the upload should be completed
+    /// rather than aborted
+    finished: Arc<AtomicBool>,
+    /// The upload task, which returns the number of parts uploaded
+    task: JoinHandle<io::Result<usize>>,
+}
+
+/// The data of the part being written
+enum PartBuffer {
+    Memory(Vec<u8>),
+    /// Spilled to a temporary file of the staging directory
+    Staged {
+        file: BufWriter<NamedTempFile>,
+        len: usize,
+    },
+}
+
+impl PartBuffer {
+    /// Start a part in memory, or in a temporary file of `staging_dir`
+    fn new(staging_dir: Option<&std::path::Path>) -> io::Result<Self> {
+        let Some(dir) = staging_dir else {
+            return Ok(Self::Memory(Vec::with_capacity(PART_SIZE)));
+        };
+        let file = tempfile::Builder::new()
+            .prefix(".spatialbench-part-")
+            .tempfile_in(dir)
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Failed to stage part in {}: {e}", dir.display()),
+                )
+            })?;
+        Ok(Self::Staged {
+            file: BufWriter::new(file),
+            len: 0,
+        })
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Memory(data) => data.len(),
+            Self::Staged { len, .. } => *len,
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Memory(data) => data.extend_from_slice(buf),
+            Self::Staged { file, len } => {
+                file.write_all(buf)?;
+                *len += buf.len();
+            }
+        }
+        Ok(())
+    }
+
+    /// Complete the part
+    fn into_part(self) -> io::Result<Part> {
+        match self {
+            Self::Memory(data) => Ok(Part::Memory(PutPayload::from(data))),
+            Self::Staged { file, .. } => {
+                let file = file.into_inner().map_err(|e| e.into_error())?;
+                Ok(Part::Staged(file.into_temp_path()))
+            }
+        }
+    }
+}
+
+/// A complete part waiting to be uploaded
+enum Part {
+    Memory(PutPayload),
+    /// A file of the staging directory, deleted once dropped
+    Staged(TempPath),
+}
+
+impl Part {
+    /// Return the data of the part, reading it back if it is staged
+    async fn read(self) -> io::Result<PutPayload> {
+        match self {
+            Self::Memory(payload) => Ok(payload),
+            Self::Staged(path) => tokio::fs::read(&path)
+                .await
+                .map(PutPayload::from)
+                .map_err(|e| staged_read_error(&path, e)),
+        }
+    }
+
+    /// Like [`Self::read`], from a blocking thread
+    fn read_blocking(self) -> io::Result<PutPayload> {
+        match self {
+            Self::Memory(payload) => Ok(payload),
+            Self::Staged(path) => std::fs::read(&path)
+                .map(PutPayload::from)
+                .map_err(|e| staged_read_error(&path, e)),
+        }
+    }
+}
+
+fn staged_read_error(path: &TempPath, e: io::Error) -> io::Error {
+    io::Error::new(
+        e.kind(),
+        format!("Failed to read staged part {}: {e}", path.display()),
+    )
+}
+
+impl ObjectStoreWriter {
+    /// Create a new writer for the given URL
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn try_new(url: &Url, mut options: UploadOptions) -> io::Result<Self> {
+        let (store, path) = parse_url(url, &options)?;
+        if !webhdfs::is_webhdfs_url(url) {
+            // the HTTP client of the store already retries the requests
+            options.retry.max_attempts = 1;
+        }
+        debug!("Writing {url} to object store path {path}");
+        let mut writer = Self::new(store, path, options);
+        writer.location = url.to_string();
+        Ok(writer)
+    }
+
+    /// Create a new writer for `path` in `store`
+    ///
+    /// Must be called from within a tokio runtime.
+    fn new(store: Arc<dyn ObjectStore>, path: Path, options: UploadOptions) -> Self {
+        assert!(options.concurrency > 0);
+        assert!(options.retry.max_attempts > 0);
+        Self {
+            store,
+            location: path.to_string(),
+            path,
+            hasher: Sha256::new(),
+            buffer: None,
+            num_bytes: 0,
+            options,
+            upload: None,
+            handle: Handle::current(),
+        }
+    }
+
+    /// Returns true if the target object already exists
+    pub async fn exists(&self) -> bool {
+        self.store.head(&self.path).await.is_ok()
+    }
+
+    /// Send the buffered data to the upload task as the next part, starting
+    /// the upload if needed
+    ///
+    /// Blocks if the upload task already has [`MAX_QUEUED_PARTS`] parts queued.
+    fn send_part(&mut self) -> io::Result<()> {
+        let part = self.buffer.take().expect("part started").into_part()?;
+        let upload = self.upload.get_or_insert_with(|| {
+            let (tx, rx) = mpsc::channel(self.options.max_queued_parts());
+            let finished = Arc::new(AtomicBool::new(false));
+            let task = self.handle.spawn(upload_parts(
+                Arc::clone(&self.store),
+                self.path.clone(),
+                rx,
+                Arc::clone(&finished),
+                self.options.clone(),
+            ));
+            Upload { tx, finished, task }
+        });
+        if upload.tx.blocking_send(part).is_err() {
+            // the upload task exited early, so report its error
+            let upload = self.upload.take().expect("upload started");
+            return match self.handle.block_on(upload.task) {
+                Ok(Err(e)) => Err(e),
+                Ok(Ok(_)) => Err(io::Error::other(format!(
+                    "Upload to {} finished unexpectedly",
+                    self.path
+                ))),
+                Err(e) => Err(io::Error::other(format!("Upload task panic: {e}"))),
+            };
+        }
+        Ok(())
+    }
+
+    /// Upload any remaining data and complete the upload, returning the total
+    /// number of bytes written
+    ///
+    /// This blocks the current thread and so must be called from a blocking
+    /// thread (e.g. within `spawn_blocking`), not from an async task.
+    fn finish(mut self) -> io::Result<usize> {
+        let num_bytes = self.num_bytes;
+        let path = self.path.clone();
+        let checksum = Checksum {
+            bytes: num_bytes as u64,
+            sha256: format!("{:x}", std::mem::take(&mut self.hasher).finalize()),
+        };
+        let location = std::mem::take(&mut self.location);
+        let Some(upload) = self.upload.take() else {
+            // small enough for a single request
+            let payload = match self.buffer.take() {
+                Some(buffer) => buffer.into_part()?.read_blocking()?,
+                None => PutPayload::new(),
+            };
+            let store = &self.store;
+            self.handle
+                .block_on(self.options.retry.retry(&format!("Upload of {path}"), || {
+                    store.put_opts(&path, payload.clone(), self.options.s3.put_options())
+                }))
+                .map_err(|e| io::Error::other(format!("Failed to upload {path}: {e}")))?;
+            metrics::add_uploaded_bytes(num_bytes as u64);
+            info!("Uploaded {num_bytes} bytes to {path}");
+            record_checksum(location, checksum);
+            return Ok(num_bytes);
+        };
+
+        let Upload { tx, finished, task } = upload;
+        let mut result = Ok(());
+        if let Some(buffer) = self.buffer.take() {
+            let part = buffer.into_part()?;
+            // on error the task has exited and its result has the details
+            result = tx.blocking_send(part);
+        }
+        // closing the channel signals the task to complete the upload
+        finished.store(true, Ordering::Release);
+        drop(tx);
+        let num_parts = self
+            .handle
+            .block_on(task)
+            .map_err(|e| io::Error::other(format!("Upload task panic: {e}")))??;
+        result.map_err(|_| io::Error::other(format!("Upload to {path} finished unexpectedly")))?;
+        info!("Uploaded {num_bytes} bytes in {num_parts} parts to {path}");
+        record_checksum(location, checksum);
+        Ok(num_bytes)
+    }
+}
+
+/// Return the store and the path within it for `url`
+///
+/// Credentials and other settings are read from the standard `AWS_*`,
+/// `AZURE_*` and `GOOGLE_*` environment variables, and S3 stores are
+/// configured with the `s3` settings.
+fn parse_url(url: &Url, options: &UploadOptions) -> io::Result<(Arc<dyn ObjectStore>, Path)> {
+    let env_options = std::env::vars().filter_map(|(key, value)| {
+        ENV_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+            .then(|| (key.to_ascii_lowercase(), value))
+    });
+    if webhdfs::is_webhdfs_url(url) {
+        let (store, path) = WebHdfs::try_new(url)
+            .map_err(|e| io::Error::other(format!("Failed to configure store for {url}: {e}")))?;
+        return Ok((Arc::new(store), path));
+    }
+    let configure_error = |e: object_store::Error| {
+        io::Error::other(format!("Failed to configure store for {url}: {e}"))
+    };
+    let (scheme, path) = ObjectStoreScheme::parse(url).map_err(|e| configure_error(e.into()))?;
+    // like `parse_url_opts`, with the retries of `options`
+    let retry = options.retry.client_config();
+    match scheme {
+        ObjectStoreScheme::AmazonS3 => {}
+        ObjectStoreScheme::MicrosoftAzure => {
+            let builder = MicrosoftAzureBuilder::new()
+                .with_url(url.to_string())
+                .with_retry(retry);
+            let store = env_options
+                .fold(builder, |builder, (key, value)| match key.parse() {
+                    Ok(key) => builder.with_config(key, value),
+                    Err(_) => builder,
+                })
+                .build()
+                .map_err(configure_error)?;
+            return Ok((Arc::new(store), object_path(url, path)?));
+        }
+        ObjectStoreScheme::Http => {
+            let builder = HttpBuilder::new()
+                .with_url(&url[..url::Position::BeforePath])
+                .with_retry(retry);
+            let store = env_options
+                .fold(builder, |builder, (key, value)| match key.parse() {
+                    Ok(key) => builder.with_config(key, value),
+                    Err(_) => builder,
+                })
+                .build()
+                .map_err(configure_error)?;
+            return Ok((Arc::new(store), path));
+        }
+        _ => {
+            let (store, path) =
+                object_store::parse_url_opts(url, env_options).map_err(configure_error)?;
+            return Ok((Arc::from(store), object_path(url, path)?));
+        }
+    }
+    // like `parse_url_opts`, with the headers of the S3 settings
+    let s3 = &options.s3;
+    let client_options = ClientOptions::new().with_default_headers(s3.headers()?);
+    let mut builder = AmazonS3Builder::new()
+        .with_url(url.to_string())
+        .with_client_options(client_options)
+        .with_retry(retry);
+    // as in the default chain of the AWS SDKs, the credentials of the
+    // environment come first, then those of the profile, then the web
+    // identity, container and instance credentials of the builder
+    if std::env::var_os("AWS_ACCESS_KEY_ID").is_none() {
+        if let Some(profile) = aws_credentials::profile_credentials()? {
+            debug!("Using AWS profile {}", profile.profile);
+            if let Some(region) = profile.region {
+                builder = builder.with_region(region);
+            }
+            if let Some(provider) = profile.provider {
+                builder = builder.with_credentials(provider);
+            }
+        }
+    }
+    let builder = env_options
+        .chain(s3.config())
+        .fold(builder, |builder, (key, value)| match key.parse() {
+            Ok(key) => builder.with_config(key, value),
+            Err(_) => builder,
+        });
+    let store = builder.build().map_err(configure_error)?;
+    Ok((Arc::new(store), path))
+}
+
+/// Download the object at `url`, passing each chunk of it to `f` as it is
+/// received
+pub async fn read_object(url: &Url, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+    let (store, path) = parse_url(url, &UploadOptions::default())?;
+    let result = store
+        .get(&path)
+        .await
+        .map_err(|e| io::Error::other(format!("Failed to read {url}: {e}")))?;
+    let mut stream = result.into_stream();
+    while let Some(bytes) = stream.next().await {
+        let bytes = bytes.map_err(|e| io::Error::other(format!("Failed to read {url}: {e}")))?;
+        f(&bytes);
+    }
+    Ok(())
+}
+
+/// Uploads the parts received on `rx` as a multipart upload to `path`
+///
+/// Once the channel is closed, the upload is completed if `finished` is set
+/// and aborted otherwise. The upload is also aborted if any request fails.
+///
+/// Returns the number of parts uploaded
+async fn upload_parts(
+    store: Arc<dyn ObjectStore>,
+    path: Path,
+    rx: Receiver<Part>,
+    finished: Arc<AtomicBool>,
+    options: UploadOptions,
+) -> io::Result<usize> {
+    let mut upload = options
+        .retry
+        .retry(&format!("Starting upload to {path}"), || {
+            store.put_multipart_opts(&path, options.s3.multipart_options())
+        })
+        .await
+        .map_err(|e| io::Error::other(format!("Failed to start upload to {path}: {e}")))?;
+
+    let result = upload_and_complete(upload.as_mut(), &path, rx, &finished, &options).await;
+    if result.is_err() {
+        // don't leak the parts uploaded so far
+        if let Err(e) = upload.abort().await {
+            warn!("Failed to abort upload to {path}: {e}");
+        }
+    }
+    result
+}
+
+/// Uploads the parts received on `rx` with up to
+/// [`UploadOptions::concurrency`] parts in flight and completes `upload`
+async fn upload_and_complete(
+    upload: &mut dyn MultipartUpload,
+    path: &Path,
+    rx: Receiver<Part>,
+    finished: &AtomicBool,
+    options: &UploadOptions,
+) -> io::Result<usize> {
+    // The part number is assigned when `put_part` is called, so the parts
+    // can complete in any order
+    let parts = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|part| (part, rx))
+    });
+    // staged parts are read back as they are uploaded, so at most
+    // `concurrency` of them are in memory
+    let mut uploads = parts
+        .then(Part::read)
+        .map(|part| {
+            let part = match part {
+                Ok(part) => part,
+                Err(e) => return futures::future::ready(Err(e)).boxed(),
+            };
+            let bytes = part.content_length() as u64;
+            upload
+                .put_part(part)
+                .map(move |result| {
+                    result.map(|()| bytes).map_err(|e| {
+                        io::Error::other(format!("Failed to upload part to {path}: {e}"))
+                    })
+                })
+                .boxed()
+        })
+        .buffer_unordered(options.concurrency)
+        .boxed();
+    let mut num_parts = 0;
+    while let Some(result) = uploads.next().await {
+        let bytes = result?;
+        metrics::add_uploaded_bytes(bytes);
+        num_parts += 1;
+    }
+    drop(uploads);
+
+    if !finished.load(Ordering::Acquire) {
+        return Err(io::Error::other(format!(
+            "Upload to {path} was not finished"
+        )));
+    }
+    // `complete` borrows the upload, so can't use `RetryPolicy::retry`
+    let what = format!("Completing upload to {path}");
+    let mut attempt = 1;
+    while let Err(e) = upload.complete().await {
+        options
+            .retry
+            .wait_for_retry(&what, &mut attempt, e)
+            .await
+            .map_err(|e| io::Error::other(format!("Failed to complete upload to {path}: {e}")))?;
+    }
+    Ok(num_parts)
+}
+
+/// Return the path within the store for `url`
+///
+/// [`ObjectStoreScheme`] treats `az://` URLs as `az://account/container/path`
+/// whereas the Azure builder (and fsspec) treat them as
+/// `az://container/path`, so use the URL path directly in that case.
+fn object_path(url: &Url, path: Path) -> io::Result<Path> {
+    if url.scheme() == "az" {
+        Path::from_url_path(url.path()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    } else {
+        Ok(path)
+    }
+}
+
+impl Write for ObjectStoreWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut remaining = buf;
+        while !remaining.is_empty() {
+            let buffer = match &mut self.buffer {
+                Some(buffer) => buffer,
+                None => self
+                    .buffer
+                    .insert(PartBuffer::new(self.options.staging_dir.as_deref())?),
+            };
+            let len = (PART_SIZE - buffer.len()).min(remaining.len());
+            buffer.write_all(&remaining[..len])?;
+            remaining = &remaining[len..];
+            if buffer.len() == PART_SIZE {
+                self.send_part()?;
+            }
+        }
+        self.num_bytes += buf.len();
+        self.hasher.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // parts are uploaded as soon as they are complete, and the final
+        // part can only be uploaded when the writer is finished
+        Ok(())
+    }
+}
+
+impl IntoSize for ObjectStoreWriter {
+    fn into_size(self) -> Result<usize, io::Error> {
+        self.finish()
+    }
+}
+
+impl Sink for ObjectStoreWriter {
+    fn sink(&mut self, buffer: &[u8]) -> Result<(), io::Error> {
+        self.write_all(buffer)
+    }
+
+    fn flush(self) -> Result<(), io::Error> {
+        self.finish().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_object_store_url() {
+        for dir in [
+            "s3://bucket/sf1",
+            "az://container/sf1",
+            "abfss://container@account.dfs.core.windows.net/sf1",
+            "file:///tmp/sf1",
+            "hdfs://namenode/sf1",
+        ] {
+            let url = object_store_url(std::path::Path::new(dir)).unwrap();
+            assert_eq!(url.as_str(), dir);
+        }
+        assert_eq!(object_store_url(std::path::Path::new("sf1-parquet")), None);
+        assert_eq!(object_store_url(std::path::Path::new("/tmp/sf1")), None);
+    }
+
+    #[test]
+    fn test_object_path() {
+        let cases = [
+            ("s3://bucket/sf1/trip.parquet", "sf1/trip.parquet"),
+            ("az://container/sf1/trip.parquet", "sf1/trip.parquet"),
+            (
+                "abfss://container@account.dfs.core.windows.net/sf1/trip.parquet",
+                "sf1/trip.parquet",
+            ),
+        ];
+        for (url, expected) in cases {
+            let url = Url::parse(url).unwrap();
+            let (_, path) = ObjectStoreScheme::parse(&url).unwrap();
+            assert_eq!(object_path(&url, path).unwrap().as_ref(), expected);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_write_multipart() {
+        let store: Arc<dyn ObjectStore> = Arc::new(object_store::memory::InMemory::new());
+        let path = Path::from("sf1/trip.tbl");
+        let data: Vec<u8> = (0..PART_SIZE * 2 + 100).map(|i| i as u8).collect();
+
+        let writer =
+            ObjectStoreWriter::new(Arc::clone(&store), path.clone(), UploadOptions::default());
+        assert!(!writer.exists().await);
+        let captured_data = data.clone();
+        let size = tokio::task::spawn_blocking(move || {
+            let mut writer = writer;
+            // write in uneven pieces to span part boundaries
+            for chunk in captured_data.chunks(1_000_003) {
+                writer.write_all(chunk)?;
+            }
+            writer.into_size()
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(size, data.len());
+        let written = store.get(&path).await.unwrap().bytes().await.unwrap();
+        assert_eq!(written.as_ref(), data.as_slice());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_write_staged() {
+        let store: Arc<dyn ObjectStore> = Arc::new(object_store::memory::InMemory::new());
+        let staging_dir = tempfile::tempdir().unwrap();
+        let options = UploadOptions {
+            staging_dir: Some(staging_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let data: Vec<u8> = (0..PART_SIZE * 3 + 100).map(|i| (i % 251) as u8).collect();
+        for (path, size) in [("small.tbl", 100), ("large.tbl", data.len())] {
+            let path = Path::from(path);
+            let mut writer =
+                ObjectStoreWriter::new(Arc::clone(&store), path.clone(), options.clone());
+            let captured_data = data[..size].to_vec();
+            tokio::task::spawn_blocking(move || {
+                for chunk in captured_data.chunks(1_000_003) {
+                    writer.write_all(chunk)?;
+                }
+                writer.into_size()
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+            let written = store.get(&path).await.unwrap().bytes().await.unwrap();
+            assert_eq!(written.as_ref(), &data[..size]);
+        }
+        // the staged parts are deleted once uploaded
+        assert_eq!(std::fs::read_dir(staging_dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_write_small() {
+        let store: Arc<dyn ObjectStore> = Arc::new(object_store::memory::InMemory::new());
+        let path = Path::from("vehicle.csv");
+
+        let writer =
+            ObjectStoreWriter::new(Arc::clone(&store), path.clone(), UploadOptions::default());
+        tokio::task::spawn_blocking(move || {
+            let mut writer = writer;
+            writer.sink(b"v_vehiclekey\n1\n")?;
+            Sink::flush(writer)
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        let written = store.get(&path).await.unwrap().bytes().await.unwrap();
+        assert_eq!(written.as_ref(), b"v_vehiclekey\n1\n");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_write_s3_options() {
+        let store: Arc<dyn ObjectStore> = Arc::new(object_store::memory::InMemory::new());
+        let options = UploadOptions {
+            s3: S3Options {
+                storage_class: Some("STANDARD_IA".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for (path, size) in [("small.tbl", 10), ("large.tbl", PART_SIZE + 10)] {
+            let path = Path::from(path);
+            let mut writer =
+                ObjectStoreWriter::new(Arc::clone(&store), path.clone(), options.clone());
+            tokio::task::spawn_blocking(move || {
+                writer.write_all(&vec![0; size])?;
+                writer.into_size()
+            })
+            .await
+            .unwrap()
+            .unwrap();
+            let result = store.get(&path).await.unwrap();
+            assert_eq!(
+                result
+                    .attributes
+                    .get(&Attribute::StorageClass)
+                    .map(AsRef::as_ref),
+                Some("STANDARD_IA"),
+            );
+        }
+    }
+
+    #[test]
+    fn test_s3_options() {
+        let options = S3Options {
+            encryption: Some(S3Encryption::Kms {
+                key_id: Some("arn:aws:kms:us-east-1:111122223333:key/abc".to_string()),
+            }),
+            storage_class: None,
+            acl: Some("bucket-owner-full-control".to_string()),
+            tags: vec![
+                ("team".to_string(), "geo".to_string()),
+                ("cost center".to_string(), "42".to_string()),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.config(),
+            [
+                (
+                    "aws_server_side_encryption".to_string(),
+                    "aws:kms".to_string()
+                ),
+                (
+                    "aws_sse_kms_key_id".to_string(),
+                    "arn:aws:kms:us-east-1:111122223333:key/abc".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            options.headers().unwrap()["x-amz-acl"],
+            "bucket-owner-full-control"
+        );
+        assert_eq!(options.tag_set().encoded(), "team=geo&cost+center=42");
+        assert!(!options.is_empty());
+        assert!(S3Options::default().is_empty());
+
+        // the store is configured without contacting S3
+        let url = Url::parse("s3://bucket/sf1/trip.parquet").unwrap();
+        let options = UploadOptions {
+            s3: options,
+            ..Default::default()
+        };
+        let (_, path) = parse_url(&url, &options).unwrap();
+        assert_eq!(path.as_ref(), "sf1/trip.parquet");
+    }
+
+    #[test]
+    fn test_s3_compatible_options() {
+        let options = S3Options {
+            endpoint: Some("http://localhost:9000".to_string()),
+            path_style: true,
+            requester_pays: true,
+            skip_tls_verify: true,
+            ..Default::default()
+        };
+        let config = options.config();
+        for (key, value) in [
+            ("aws_endpoint", "http://localhost:9000"),
+            ("aws_allow_http", "true"),
+            ("aws_virtual_hosted_style_request", "false"),
+            ("aws_request_payer", "true"),
+            ("allow_invalid_certificates", "true"),
+        ] {
+            assert!(
+                config.contains(&(key.to_string(), value.to_string())),
+                "{key}"
+            );
+        }
+        // the builder knows every key
+        for (key, _) in &config {
+            key.parse::<object_store::aws::AmazonS3ConfigKey>().unwrap();
+        }
+        let url = Url::parse("s3://bucket/sf1/trip.parquet").unwrap();
+        let options = UploadOptions {
+            s3: options,
+            ..Default::default()
+        };
+        parse_url(&url, &options).unwrap();
+    }
+
+    #[test]
+    fn test_parse_s3_tag() {
+        assert_eq!(
+            parse_s3_tag("team=geo").unwrap(),
+            ("team".to_string(), "geo".to_string())
+        );
+        assert_eq!(
+            parse_s3_tag("empty=").unwrap(),
+            ("empty".to_string(), String::new())
+        );
+        assert!(parse_s3_tag("team").is_err());
+        assert!(parse_s3_tag("=geo").is_err());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+        for (attempt, max) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (9, 1000)] {
+            let backoff = policy.backoff(attempt);
+            let max = Duration::from_millis(max);
+            assert!(
+                backoff >= max / 2 && backoff <= max,
+                "{attempt}: {backoff:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_config() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+        let config = policy.client_config();
+        assert_eq!(config.max_retries, 2);
+        assert_eq!(config.backoff.init_backoff, Duration::from_millis(100));
+        assert_eq!(config.backoff.max_backoff, Duration::from_secs(1));
+
+        // every store of an upload is configured with it
+        let options = UploadOptions {
+            retry: policy,
+            ..Default::default()
+        };
+        for url in [
+            "s3://bucket/sf1/trip.parquet",
+            "abfss://container@account.dfs.core.windows.net/sf1/trip.parquet",
+            "https://example.com/sf1/trip.parquet",
+        ] {
+            let url = Url::parse(url).unwrap();
+            let (_, path) = parse_url(&url, &options).unwrap();
+            assert_eq!(path.as_ref(), "sf1/trip.parquet", "{url}");
+            // so the writer does not retry them again
+            let writer = ObjectStoreWriter::try_new(&url, options.clone()).unwrap();
+            assert_eq!(writer.options.retry.max_attempts, 1, "{url}");
+        }
+
+        // unlike the WebHDFS client
+        let url = Url::parse("webhdfs://namenode/sf1/trip.parquet").unwrap();
+        let writer = ObjectStoreWriter::try_new(&url, options).unwrap();
+        assert_eq!(writer.options.retry, policy);
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+        let path = Path::from("trip.tbl");
+        let error = |status| webhdfs::status_error(&path, "CREATE", status, "");
+        let generic = || error(StatusCode::SERVICE_UNAVAILABLE);
+
+        // succeeds after transient failures
+        let mut attempts = 0;
+        let result = policy
+            .retry("test", || {
+                attempts += 1;
+                let result = if attempts < 3 {
+                    Err(generic())
+                } else {
+                    Ok(attempts)
+                };
+                async move { result }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        // gives up after max_attempts
+        let mut attempts = 0;
+        let result: object_store::Result<()> = policy
+            .retry("test", || {
+                attempts += 1;
+                let e = generic();
+                async move { Err(e) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        // does not retry permanent errors
+        for status in [StatusCode::BAD_REQUEST, StatusCode::FORBIDDEN] {
+            let mut attempts = 0;
+            let result: object_store::Result<()> = policy
+                .retry("test", || {
+                    attempts += 1;
+                    let e = error(status);
+                    async move { Err(e) }
+                })
+                .await;
+            assert!(result.is_err());
+            assert_eq!(attempts, 1);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_drop_aborts_upload() {
+        let store: Arc<dyn ObjectStore> = Arc::new(object_store::memory::InMemory::new());
+        let path = Path::from("trip.tbl");
+
+        let writer =
+            ObjectStoreWriter::new(Arc::clone(&store), path.clone(), UploadOptions::default());
+        let task = tokio::task::spawn_blocking(move || {
+            let mut writer = writer;
+            writer.write_all(&vec![0; PART_SIZE + 1])?;
+            // simulate dropping the writer, keeping the task to check its result
+            let Upload { tx, task, .. } = writer.upload.take().unwrap();
+            drop(writer);
+            drop(tx);
+            Ok(task) as io::Result<JoinHandle<io::Result<usize>>>
+        });
+        let upload_task = task.await.unwrap().unwrap();
+        assert!(upload_task.await.unwrap().is_err());
+        assert!(store.head(&path).await.is_err());
+    }
+}
@@ -19,22 +19,43 @@
 //! * [`OutputPlan`]: an output file that will be generated
 //! * [`OutputPlanGenerator`]: plans the output files to be generated
 
-use crate::plan::GenerationPlan;
+use crate::columns::ColumnProjection;
+use crate::csv::CsvOptions;
+use crate::geoarrow::GeometryEncoding;
+use crate::geoparquet::GeoParquetVersion;
+use crate::ipc::ArrowIpcFormat;
+use crate::key_sort::{RowOrder, SortKey};
+use crate::null::is_null_output;
+use crate::object_store_writer::{object_store_url, UploadOptions};
+use crate::path_template::{PathTemplate, PathValues};
+use crate::plan::{GenerationPlan, DEFAULT_PARQUET_ROW_GROUP_BYTES};
+use crate::postgres::{self, postgres_url};
+use crate::spatial_partition::SpatialScheme;
+use crate::spatial_sort::SpatialOrder;
+use crate::wkb_variant::WkbVariant;
 use crate::{OutputFormat, Table};
-use log::debug;
+use log::{debug, info};
 use parquet::basic::Compression;
+use spatialbench::generators::RowCounts;
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use url::Url;
 
 /// Where a partition will be output
 #[derive(Debug, Clone, PartialEq)]
 pub enum OutputLocation {
     /// Output to a file
     File(PathBuf),
+    /// Output to an object store (e.g. S3 or Azure)
+    ObjectStore(Url),
+    /// Output to a table in a PostgreSQL database
+    Postgres(Url),
     /// Output to stdout
     Stdout,
+    /// The rows are generated and dropped (`--output null`)
+    Null,
 }
 
 impl Display for OutputLocation {
@@ -47,7 +68,10 @@ impl Display for OutputLocation {
                 // Display the file name only, not the full path
                 write!(f, "{}", file.to_string_lossy())
             }
+            OutputLocation::ObjectStore(url) => write!(f, "{url}"),
+            OutputLocation::Postgres(url) => write!(f, "{}", postgres::redacted(url)),
             OutputLocation::Stdout => write!(f, "Stdout"),
+            OutputLocation::Null => write!(f, "null"),
         }
     }
 }
@@ -59,12 +83,47 @@ pub struct OutputPlan {
     table: Table,
     /// The scale factor
     scale_factor: f64,
+    /// The global seed of the random values
+    seed: u64,
+    /// The overridden row counts of the tables
+    row_counts: RowCounts,
+    /// The indices of the columns to generate, if not all of them
+    projection: Option<Vec<usize>>,
     /// The output format (TODO don't depend back on something in main)
     output_format: OutputFormat,
     /// If the output is parquet, what compression level to use
     parquet_compression: Compression,
+    /// If the output is parquet, the data page size limit in bytes (if not the default)
+    parquet_data_page_bytes: Option<usize>,
+    /// If the output is parquet, which version of GeoParquet to write (if any)
+    geoparquet_version: Option<GeoParquetVersion>,
+    /// If the output is parquet, how to encode the geometry columns
+    geometry_encoding: GeometryEncoding,
+    /// If the output is parquet or arrow, the variant of WKB to write
+    wkb_variant: WkbVariant,
+    /// If the output is parquet, the column to Hive partition the output by (if any)
+    partition_by: Option<String>,
+    /// If the output is parquet, how to spatially partition the output (if at all)
+    spatial_partition: Option<SpatialScheme>,
+    /// If the output is generated from Arrow, the order to write the rows of
+    /// each file in (if not as generated)
+    row_order: Option<RowOrder>,
+    /// If the output is Hive partitioned, the target row group size in bytes
+    parquet_row_group_bytes: i64,
+    /// If the output is Hive partitioned, the maximum number of rows per row group (if any)
+    parquet_row_group_rows: Option<i64>,
+    /// If the output is csv, how to format it
+    csv_options: CsvOptions,
+    /// If the output is arrow, which IPC format to write
+    arrow_ipc_format: ArrowIpcFormat,
     /// Where to output
     output_location: OutputLocation,
+    /// Other locations to write copies of the output to
+    mirrors: Vec<OutputLocation>,
+    /// If the output is an object store, how to upload to it
+    upload_options: UploadOptions,
+    /// Resume a previously interrupted in progress file, if possible
+    resume: bool,
     /// Plan for generating the table
     generation_plan: GenerationPlan,
 }
@@ -76,18 +135,135 @@ impl OutputPlan {
         output_format: OutputFormat,
         parquet_compression: Compression,
         output_location: OutputLocation,
+        upload_options: UploadOptions,
         generation_plan: GenerationPlan,
     ) -> Self {
         Self {
             table,
             scale_factor,
+            seed: 0,
+            row_counts: RowCounts::default(),
+            projection: None,
             output_format,
             parquet_compression,
             output_location,
+            mirrors: Vec::new(),
+            upload_options,
+            resume: false,
+            geoparquet_version: None,
+            geometry_encoding: GeometryEncoding::Wkb,
+            wkb_variant: WkbVariant::default(),
+            partition_by: None,
+            spatial_partition: None,
+            row_order: None,
+            parquet_row_group_bytes: DEFAULT_PARQUET_ROW_GROUP_BYTES,
+            parquet_row_group_rows: None,
+            parquet_data_page_bytes: None,
+            csv_options: CsvOptions::default(),
+            arrow_ipc_format: ArrowIpcFormat::File,
             generation_plan,
         }
     }
 
+    /// Set the options for csv output
+    pub fn with_csv_options(mut self, csv_options: CsvOptions) -> Self {
+        self.csv_options = csv_options;
+        self
+    }
+
+    /// Set the IPC format to write for arrow output
+    pub fn with_arrow_ipc_format(mut self, format: ArrowIpcFormat) -> Self {
+        self.arrow_ipc_format = format;
+        self
+    }
+
+    /// Set the version of GeoParquet to write for parquet output
+    pub fn with_geoparquet_version(mut self, version: Option<GeoParquetVersion>) -> Self {
+        self.geoparquet_version = version;
+        self
+    }
+
+    /// Set how to encode the geometry columns for parquet output
+    pub fn with_geometry_encoding(mut self, encoding: GeometryEncoding) -> Self {
+        self.geometry_encoding = encoding;
+        self
+    }
+
+    /// Set the variant of WKB to write the geometries as, for parquet and
+    /// arrow output
+    pub fn with_wkb_variant(mut self, variant: WkbVariant) -> Self {
+        self.wkb_variant = variant;
+        self
+    }
+
+    /// Set the data page size limit in bytes for parquet output
+    pub fn with_parquet_data_page_bytes(mut self, data_page_bytes: Option<usize>) -> Self {
+        self.parquet_data_page_bytes = data_page_bytes;
+        self
+    }
+
+    /// Set the column to Hive partition parquet output by
+    pub fn with_partition_by(mut self, column: Option<String>) -> Self {
+        self.partition_by = column;
+        self
+    }
+
+    /// Set how to spatially partition parquet output
+    pub fn with_spatial_partition(mut self, scheme: Option<SpatialScheme>) -> Self {
+        self.spatial_partition = scheme;
+        self
+    }
+
+    /// Set the order to write the rows of the whole file in, sorted by a
+    /// column, along a space filling curve or shuffled
+    pub fn with_row_order(mut self, order: Option<RowOrder>) -> Self {
+        self.row_order = order;
+        self
+    }
+
+    /// Set the row group size for Hive partitioned parquet output
+    ///
+    /// Unpartitioned output has one row group per chunk of the generation plan.
+    pub fn with_parquet_row_group_size(
+        mut self,
+        row_group_bytes: i64,
+        row_group_rows: Option<i64>,
+    ) -> Self {
+        self.parquet_row_group_bytes = row_group_bytes;
+        self.parquet_row_group_rows = row_group_rows;
+        self
+    }
+
+    /// Set whether to resume a previously interrupted in progress file
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Set other locations to write copies of the output to
+    pub fn with_mirrors(mut self, mirrors: Vec<OutputLocation>) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    /// Set the global seed of the random values
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the overridden row counts of the tables
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Only generate the columns at these indices of the table schema
+    pub fn with_projection(mut self, projection: Option<Vec<usize>>) -> Self {
+        self.projection = projection;
+        self
+    }
+
     /// Return the table this partition is for
     pub fn table(&self) -> Table {
         self.table
@@ -98,6 +274,21 @@ impl OutputPlan {
         self.scale_factor
     }
 
+    /// Return the global seed of the random values for this partition
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Return the overridden row counts of the tables
+    pub fn row_counts(&self) -> RowCounts {
+        self.row_counts
+    }
+
+    /// Return the indices of the columns to generate, if not all of them
+    pub fn projection(&self) -> Option<&[usize]> {
+        self.projection.as_deref()
+    }
+
     /// Return the output format for this partition
     pub fn output_format(&self) -> OutputFormat {
         self.output_format
@@ -108,11 +299,88 @@ impl OutputPlan {
         &self.output_location
     }
 
+    /// Return the other locations to write copies of the output to
+    pub fn mirrors(&self) -> &[OutputLocation] {
+        &self.mirrors
+    }
+
+    /// Return the options for uploading to an object store
+    pub fn upload_options(&self) -> UploadOptions {
+        self.upload_options.clone()
+    }
+
+    /// Return true if a previously interrupted in progress file should be resumed
+    pub fn resume(&self) -> bool {
+        self.resume
+    }
+
     /// Return the parquet compression level for this partition
     pub fn parquet_compression(&self) -> Compression {
         self.parquet_compression
     }
 
+    /// Return the version of GeoParquet to write, if any
+    pub fn geoparquet_version(&self) -> Option<GeoParquetVersion> {
+        self.geoparquet_version
+    }
+
+    /// Return how to encode the geometry columns
+    pub fn geometry_encoding(&self) -> GeometryEncoding {
+        self.geometry_encoding
+    }
+
+    /// Return the variant of WKB to write the geometries as
+    pub fn wkb_variant(&self) -> WkbVariant {
+        self.wkb_variant
+    }
+
+    /// Return the data page size limit in bytes, if not the default
+    pub fn parquet_data_page_bytes(&self) -> Option<usize> {
+        self.parquet_data_page_bytes
+    }
+
+    /// Return the column to Hive partition the output by, if any
+    pub fn partition_by(&self) -> Option<&str> {
+        self.partition_by.as_deref()
+    }
+
+    /// Return how to spatially partition the output, if at all
+    pub fn spatial_partition(&self) -> Option<SpatialScheme> {
+        self.spatial_partition
+    }
+
+    /// Return the order to write the rows of the whole file in, if not as
+    /// generated
+    pub fn row_order(&self) -> Option<RowOrder> {
+        self.row_order.clone()
+    }
+
+    /// Return true if the output is Hive or spatially partitioned
+    pub fn is_partitioned(&self) -> bool {
+        self.partition_by.is_some() || self.spatial_partition.is_some()
+    }
+
+    /// Return the target row group size in bytes for Hive partitioned output
+    pub fn parquet_row_group_bytes(&self) -> i64 {
+        self.parquet_row_group_bytes
+    }
+
+    /// Return the maximum number of rows per row group for Hive partitioned
+    /// output, if set
+    pub fn parquet_row_group_rows(&self) -> Option<i64> {
+        self.parquet_row_group_rows
+    }
+
+    /// Return the options for csv output
+    pub fn csv_options(&self) -> CsvOptions {
+        self.csv_options
+    }
+
+    /// Return the IPC format to write for arrow output
+    pub fn arrow_ipc_format(&self) -> ArrowIpcFormat {
+        self.arrow_ipc_format
+    }
+
     /// Return the number of chunks part(ition) count (the number of data chunks
     /// in the underlying generation plan)
     pub fn chunk_count(&self) -> usize {
@@ -127,11 +395,21 @@ impl OutputPlan {
 
 impl Display for OutputPlan {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "table {} (SF={}, ", self.table, self.scale_factor)?;
+        // the seed, row counts and columns are part of the resume checkpoint,
+        // see `CheckpointSink`
+        if self.seed != 0 {
+            write!(f, "seed={}, ", self.seed)?;
+        }
+        if self.row_counts != RowCounts::default() {
+            write!(f, "rows={}, ", self.row_counts)?;
+        }
+        if let Some(projection) = &self.projection {
+            write!(f, "columns={projection:?}, ")?;
+        }
         write!(
             f,
-            "table {} (SF={}, {} chunks) to {}",
-            self.table,
-            self.scale_factor,
+            "{} chunks) to {}",
             self.chunk_count(),
             self.output_location
         )
@@ -142,10 +420,36 @@ impl Display for OutputPlan {
 pub struct OutputPlanGenerator {
     format: OutputFormat,
     scale_factor: f64,
+    seed: u64,
+    row_counts: RowCounts,
+    columns: Vec<ColumnProjection>,
     parquet_compression: Compression,
     parquet_row_group_bytes: i64,
+    parquet_row_group_rows: Option<i64>,
+    parquet_data_page_bytes: Option<usize>,
+    geoparquet_version: Option<GeoParquetVersion>,
+    geometry_encoding: GeometryEncoding,
+    wkb_variant: WkbVariant,
+    partition_by: Option<String>,
+    spatial_partition: Option<SpatialScheme>,
+    spatial_sort: Option<SpatialOrder>,
+    sort_keys: Vec<SortKey>,
+    /// Shuffle the rows of the files of the tables not sorted by a column
+    shuffle: bool,
+    csv_options: CsvOptions,
+    arrow_ipc_format: ArrowIpcFormat,
     stdout: bool,
     output_dir: PathBuf,
+    /// If set, write the single output to this file instead of `output_dir`
+    output_file: Option<PathBuf>,
+    upload_options: UploadOptions,
+    resume: bool,
+    /// Write each table to its own directory, as a Delta table
+    delta: bool,
+    /// Other directories or object store URLs to write copies of the files to
+    mirror_dirs: Vec<PathBuf>,
+    /// The path of each file in the output directory, if not the default
+    path_template: Option<PathTemplate>,
     /// The generated output plans
     output_plans: Vec<OutputPlan>,
     /// Output directories that have been created so far
@@ -161,19 +465,175 @@ impl OutputPlanGenerator {
         parquet_row_group_bytes: i64,
         stdout: bool,
         output_dir: PathBuf,
+        upload_options: UploadOptions,
     ) -> Self {
         Self {
             format,
             scale_factor,
+            seed: 0,
+            row_counts: RowCounts::default(),
+            columns: Vec::new(),
             parquet_compression,
             parquet_row_group_bytes,
+            parquet_row_group_rows: None,
             stdout,
             output_dir,
+            output_file: None,
+            upload_options,
+            resume: false,
+            delta: false,
+            geoparquet_version: None,
+            geometry_encoding: GeometryEncoding::Wkb,
+            wkb_variant: WkbVariant::default(),
+            partition_by: None,
+            spatial_partition: None,
+            spatial_sort: None,
+            sort_keys: Vec::new(),
+            shuffle: false,
+            parquet_data_page_bytes: None,
+            csv_options: CsvOptions::default(),
+            arrow_ipc_format: ArrowIpcFormat::File,
+            mirror_dirs: Vec::new(),
+            path_template: None,
             output_plans: Vec::new(),
             created_directories: HashSet::new(),
         }
     }
 
+    /// Set whether to resume previously interrupted in progress files
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Also write a copy of every file to each of `mirror_dirs`, local
+    /// directories or object store URLs, with the same relative path as in
+    /// the output directory
+    pub fn with_mirrors(mut self, mirror_dirs: Vec<PathBuf>) -> Self {
+        self.mirror_dirs = mirror_dirs;
+        self
+    }
+
+    /// Write each file to the path of `path_template` in the output
+    /// directory (and mirrors), instead of the default layout
+    pub fn with_path_template(mut self, path_template: Option<PathTemplate>) -> Self {
+        self.path_template = path_template;
+        self
+    }
+
+    /// Set whether to write each table to its own directory, as a Delta table
+    pub fn with_delta(mut self, delta: bool) -> Self {
+        self.delta = delta;
+        self
+    }
+
+    /// Set the global seed the random values are derived from
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Override the number of rows of the tables
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Only generate some of the columns of the given tables
+    pub fn with_columns(mut self, columns: Vec<ColumnProjection>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Write the output to `output_file` instead of a file in the output
+    /// directory
+    ///
+    /// Only one table and part may be generated.
+    pub fn with_output_file(mut self, output_file: Option<PathBuf>) -> Self {
+        self.output_file = output_file;
+        self
+    }
+
+    /// Set the maximum number of rows per parquet row group
+    ///
+    /// If set, the row group size in bytes is ignored.
+    pub fn with_parquet_row_group_rows(mut self, row_group_rows: Option<i64>) -> Self {
+        self.parquet_row_group_rows = row_group_rows;
+        self
+    }
+
+    /// Set the version of GeoParquet to write for parquet output
+    pub fn with_geoparquet_version(mut self, version: Option<GeoParquetVersion>) -> Self {
+        self.geoparquet_version = version;
+        self
+    }
+
+    /// Set how to encode the geometry columns for parquet output
+    pub fn with_geometry_encoding(mut self, encoding: GeometryEncoding) -> Self {
+        self.geometry_encoding = encoding;
+        self
+    }
+
+    /// Set the variant of WKB to write the geometries as, for parquet and
+    /// arrow output
+    pub fn with_wkb_variant(mut self, variant: WkbVariant) -> Self {
+        self.wkb_variant = variant;
+        self
+    }
+
+    /// Set the data page size limit in bytes for parquet output
+    pub fn with_parquet_data_page_bytes(mut self, data_page_bytes: Option<usize>) -> Self {
+        self.parquet_data_page_bytes = data_page_bytes;
+        self
+    }
+
+    /// Set the column to Hive partition parquet output by
+    ///
+    /// Each table is then written to a directory (see [`Self::output_location`])
+    pub fn with_partition_by(mut self, column: Option<String>) -> Self {
+        self.partition_by = column;
+        self
+    }
+
+    /// Set how to spatially partition parquet output
+    ///
+    /// Each table is then written to a directory (see [`Self::output_location`])
+    pub fn with_spatial_partition(mut self, scheme: Option<SpatialScheme>) -> Self {
+        self.spatial_partition = scheme;
+        self
+    }
+
+    /// Set the space filling curve to sort the rows of each file along
+    pub fn with_spatial_sort(mut self, order: Option<SpatialOrder>) -> Self {
+        self.spatial_sort = order;
+        self
+    }
+
+    /// Set the columns to sort the files of the given tables by
+    pub fn with_sort_keys(mut self, sort_keys: Vec<SortKey>) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// Set whether to shuffle the rows of each file in an order derived from
+    /// the seed
+    pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Set the options for csv output
+    pub fn with_csv_options(mut self, csv_options: CsvOptions) -> Self {
+        self.csv_options = csv_options;
+        self
+    }
+
+    /// Set the IPC format to write for arrow output
+    pub fn with_arrow_ipc_format(mut self, format: ArrowIpcFormat) -> Self {
+        self.arrow_ipc_format = format;
+        self
+    }
+
     /// Generate the output plans for the given table and partition options
     pub fn generate_plans(
         &mut self,
@@ -202,6 +662,27 @@ impl OutputPlanGenerator {
                 debug!("Generating single partition for table {table}");
                 self.generate_plan_inner(table, Some(1), Some(1))?;
             }
+        } else if let (Some(part), false) = (cli_part, GenerationPlan::partitioned_table(table)) {
+            // When each machine generates one part of a distributed run, the
+            // tables that are not split are written only by the first part,
+            // so the union of the parts has each row exactly once
+            if part == 1 {
+                self.generate_plan_inner(table, cli_part, calculated_part_count)?;
+            } else {
+                // still report invalid --part and --parts options
+                GenerationPlan::try_new(
+                    table,
+                    self.format,
+                    self.scale_factor,
+                    self.row_counts,
+                    cli_part,
+                    calculated_part_count,
+                    self.parquet_row_group_bytes,
+                    self.parquet_row_group_rows,
+                )
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                info!("Skipping table {table} for --part {part}, it is written by --part 1");
+            }
         } else {
             self.generate_plan_inner(table, cli_part, calculated_part_count)?;
         }
@@ -215,8 +696,10 @@ impl OutputPlanGenerator {
         let output_size = OutputSize::new(
             table,
             self.scale_factor,
+            self.row_counts,
             self.format,
             self.parquet_row_group_bytes,
+            self.parquet_row_group_rows,
         );
 
         let total_size_bytes = output_size.total_size_bytes();
@@ -244,13 +727,37 @@ impl OutputPlanGenerator {
             table,
             self.format,
             self.scale_factor,
+            self.row_counts,
             cli_part,
             cli_part_count,
             self.parquet_row_group_bytes,
+            self.parquet_row_group_rows,
         )
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
 
         let output_location = self.output_location(table, cli_part)?;
+        let mirrors = self
+            .mirror_dirs
+            .clone()
+            .iter()
+            .map(|dir| self.file_location(dir, table, cli_part))
+            .collect::<io::Result<_>>()?;
+        let projection = self
+            .columns
+            .iter()
+            .find(|columns| columns.table.name() == table.name())
+            .map(|columns| columns.projection.clone());
+        let sort_by = self
+            .sort_keys
+            .iter()
+            .find(|key| key.table.name() == table.name())
+            .map(|key| RowOrder::Column(key.column.clone()));
+        let row_order = sort_by
+            .or_else(|| self.spatial_sort.map(RowOrder::Curve))
+            .or_else(|| {
+                self.shuffle
+                    .then(|| RowOrder::shuffled(self.seed, table.name(), cli_part.unwrap_or(1)))
+            });
 
         let plan = OutputPlan::new(
             table,
@@ -258,8 +765,24 @@ impl OutputPlanGenerator {
             self.format,
             self.parquet_compression,
             output_location,
+            self.upload_options.clone(),
             generation_plan,
-        );
+        )
+        .with_resume(self.resume)
+        .with_mirrors(mirrors)
+        .with_seed(self.seed)
+        .with_row_counts(self.row_counts)
+        .with_projection(projection)
+        .with_geoparquet_version(self.geoparquet_version)
+        .with_geometry_encoding(self.geometry_encoding)
+        .with_wkb_variant(self.wkb_variant)
+        .with_parquet_data_page_bytes(self.parquet_data_page_bytes)
+        .with_partition_by(self.partition_by.clone())
+        .with_spatial_partition(self.spatial_partition)
+        .with_row_order(row_order)
+        .with_parquet_row_group_size(self.parquet_row_group_bytes, self.parquet_row_group_rows)
+        .with_csv_options(self.csv_options)
+        .with_arrow_ipc_format(self.arrow_ipc_format);
 
         self.output_plans.push(plan);
         Ok(())
@@ -272,28 +795,118 @@ impl OutputPlanGenerator {
     /// * if part is Some(part), then the output location
     ///   will be `{output_dir}/{table}/{table}table.{part}.{extension}`
     ///   (e.g. orders/orders.1.tbl, orders/orders.2.tbl, etc.)
+    ///
+    /// * if the output is Hive or spatially partitioned, the output location is
+    ///   `{output_dir}/{table}/part-{part}.{extension}` (`part-0` if part is
+    ///   None), and the file of each partition is written with the same name
+    ///   in the partition directory, e.g. `trip/t_pickuptime_date=2024-01-01/part-0.parquet`
+    ///
+    /// * if a path template is set (see [`Self::with_path_template`]), the
+    ///   output location is `{output_dir}/{path}`, with the path rendered
+    ///   for the table and part (part 1 if None)
+    ///
+    /// * if writing to stdout or an output file (see [`Self::with_output_file`]),
+    ///   that is the output location regardless of the table and part, and
+    ///   if it is a `postgres://` URL, every table is written to the database
+    ///   (and if it is `null`, every table is generated and dropped)
     fn output_location(&mut self, table: Table, part: Option<i32>) -> io::Result<OutputLocation> {
         if self.stdout {
             Ok(OutputLocation::Stdout)
+        } else if let Some(output_file) = self.output_file.clone() {
+            if let Some(url) = postgres_url(&output_file) {
+                return Ok(OutputLocation::Postgres(url));
+            }
+            if is_null_output(&output_file) {
+                return Ok(OutputLocation::Null);
+            }
+            if let Some(url) = object_store_url(&output_file) {
+                return Ok(OutputLocation::ObjectStore(url));
+            }
+            if let Some(parent) = output_file.parent().filter(|p| !p.as_os_str().is_empty()) {
+                self.ensure_directory_exists(&parent.to_path_buf())?;
+            }
+            Ok(OutputLocation::File(output_file))
         } else {
-            let extension = match self.format {
-                OutputFormat::Tbl => "tbl",
-                OutputFormat::Csv => "csv",
-                OutputFormat::Parquet => "parquet",
-            };
+            let output_dir = self.output_dir.clone();
+            self.file_location(&output_dir, table, part)
+        }
+    }
 
-            let mut output_path = self.output_dir.clone();
-            if let Some(part) = part {
-                // If a partition is specified, create a subdirectory for it
-                output_path.push(table.to_string());
-                self.ensure_directory_exists(&output_path)?;
-                output_path.push(format!("{table}.{part}.{extension}"));
+    /// Return the location of the file of `table` and `part` in `dir`, a
+    /// local directory or an object store URL (see [`Self::output_location`])
+    fn file_location(
+        &mut self,
+        dir: &Path,
+        table: Table,
+        part: Option<i32>,
+    ) -> io::Result<OutputLocation> {
+        let extension = match self.format {
+            OutputFormat::Tbl => "tbl",
+            OutputFormat::Csv => self.csv_options.extension(),
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Geojson => "geojson",
+            OutputFormat::Geojsonl => "geojsonl",
+            OutputFormat::Flatgeobuf => "fgb",
+            OutputFormat::Arrow => self.arrow_ipc_format.extension(),
+        };
+
+        let templated = self.path_template.as_ref().map(|template| {
+            template.render(PathValues {
+                table: &table.to_string(),
+                scale_factor: self.scale_factor,
+                part: part.unwrap_or(1),
+                seed: self.seed,
+                extension,
+            })
+        });
+
+        if let Some(base) = object_store_url(dir) {
+            // object stores have no directories to create
+            let base = base.as_str().trim_end_matches('/');
+            let url = if let Some(path) = templated {
+                format!("{base}/{path}")
+            } else if self.is_partitioned() {
+                format!("{base}/{table}/part-{}.{extension}", part.unwrap_or(0))
+            } else if let Some(part) = part {
+                format!("{base}/{table}/{table}.{part}.{extension}")
             } else {
-                // No partition specified, output to a single file
-                output_path.push(format!("{table}.{extension}"));
+                format!("{base}/{table}.{extension}")
+            };
+            let url =
+                Url::parse(&url).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            return Ok(OutputLocation::ObjectStore(url));
+        }
+
+        let mut output_path = dir.to_path_buf();
+        if let Some(path) = templated {
+            output_path.extend(path.split('/'));
+            if let Some(parent) = output_path.parent() {
+                self.ensure_directory_exists(&parent.to_path_buf())?;
             }
-            Ok(OutputLocation::File(output_path))
+        } else if self.is_partitioned() {
+            output_path.push(table.to_string());
+            self.ensure_directory_exists(&output_path)?;
+            output_path.push(format!("part-{}.{extension}", part.unwrap_or(0)));
+        } else if let Some(part) = part {
+            // If a partition is specified, create a subdirectory for it
+            output_path.push(table.to_string());
+            self.ensure_directory_exists(&output_path)?;
+            output_path.push(format!("{table}.{part}.{extension}"));
+        } else if self.delta {
+            // Delta tables are directories, even of a single file
+            output_path.push(table.to_string());
+            self.ensure_directory_exists(&output_path)?;
+            output_path.push(format!("{table}.{extension}"));
+        } else {
+            // No partition specified, output to a single file
+            output_path.push(format!("{table}.{extension}"));
         }
+        Ok(OutputLocation::File(output_path))
+    }
+
+    /// Return true if the tables are written to partitioned directories
+    fn is_partitioned(&self) -> bool {
+        self.partition_by.is_some() || self.spatial_partition.is_some()
     }
 
     /// Ensure the output directory exists, creating it if necessary
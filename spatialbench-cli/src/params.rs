@@ -0,0 +1,211 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `params` command: print streams of query substitution parameters
+
+use clap::{Args, ValueEnum};
+use spatialbench::workload::{Dialect, ParameterStream, ParameterValue, Query, QueryParameters};
+use std::io::{self, Write};
+
+/// Print reproducible substitution parameters for many variants of a query
+#[derive(Debug, Args)]
+pub struct ParamsArgs {
+    /// Query to generate parameters for, e.g. `Q3`
+    #[arg(short, long)]
+    query: Query,
+
+    /// Number of parameter sets to generate
+    #[arg(short, long, default_value_t = 1)]
+    count: u64,
+
+    /// Seed of the stream of parameters
+    ///
+    /// The same seed, query and scale factor always generate the same
+    /// parameters, in the same order.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Scale factor of the data the queries are run against
+    #[arg(short, long, default_value_t = 1.)]
+    scale_factor: f64,
+
+    /// Fractions of the trips each query variant should select (e.g.
+    /// `0.001`), or `low` (0.1%), `medium` (1%) and `high` (10%)
+    ///
+    /// Chooses the radius (Q1), query window (Q3, Q6) or number of top trips
+    /// (Q4) so each variant selects about this fraction of the trip table,
+    /// estimated from a sample of the trips. Other queries have no filter to
+    /// resize and ignore it. If not specified, the sizes of the published
+    /// queries are used.
+    ///
+    /// With several selectivities, e.g. `low,medium,high`, `--count`
+    /// variants are printed for each of them, with the same centers, and
+    /// each variant has a `selectivity` field.
+    #[arg(long, value_delimiter = ',', value_parser = parse_selectivity)]
+    selectivity: Vec<f64>,
+
+    /// Output format for the parameters
+    #[arg(short, long, default_value = "jsonl")]
+    format: ParamsFormat,
+
+    /// SQL dialect for `--format sql`: sedona, postgis, duckdb or bigquery
+    #[arg(short, long, default_value = "sedona")]
+    dialect: Dialect,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum ParamsFormat {
+    /// One JSON object per line, with points and windows as WKT and
+    /// distances in meters
+    Jsonl,
+    /// CSV with a header, with points and windows as WKT and distances in
+    /// meters
+    Csv,
+    /// The query with the parameters substituted, separated by empty lines
+    Sql,
+}
+
+impl ParamsArgs {
+    /// Print the parameters to stdout
+    pub fn run(&self) -> io::Result<()> {
+        if !(self.scale_factor.is_finite() && self.scale_factor > 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--scale-factor must be positive, got {}", self.scale_factor),
+            ));
+        }
+
+        let stream = ParameterStream::new(self.query, self.scale_factor, self.seed);
+        let mut streams = vec![];
+        for &selectivity in &self.selectivity {
+            if !(selectivity > 0.0 && selectivity <= 1.0) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("--selectivity must be in (0, 1], got {selectivity}"),
+                ));
+            }
+            streams.push((
+                Some(selectivity),
+                stream.clone().with_selectivity(selectivity),
+            ));
+        }
+        if streams.is_empty() {
+            streams.push((None, stream));
+        } else if !self.query.has_selectivity() {
+            eprintln!(
+                "Warning: --selectivity has no effect for {}, which has no filter to resize",
+                self.query
+            );
+        }
+        if self.format != ParamsFormat::Sql && self.dialect != Dialect::Sedona {
+            eprintln!("Warning: --dialect is only used with --format sql");
+        }
+
+        let mut stdout = io::stdout().lock();
+        let result = self.write(&mut stdout, streams);
+        match result {
+            // e.g. piped to `head`
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Write `count` parameters of each stream, tagged with its selectivity
+    /// if there are several
+    fn write(
+        &self,
+        out: &mut impl Write,
+        streams: Vec<(Option<f64>, ParameterStream)>,
+    ) -> io::Result<()> {
+        let names: Vec<_> = QueryParameters::reference(self.query)
+            .values()
+            .iter()
+            .map(|(name, _)| *name)
+            .collect();
+        let tagged = streams.len() > 1;
+        if self.format == ParamsFormat::Csv {
+            let selectivity = if tagged { "selectivity," } else { "" };
+            writeln!(out, "query,index,{selectivity}{}", names.join(","))?;
+        }
+
+        let mut first = true;
+        for (selectivity, stream) in streams {
+            let selectivity = selectivity.filter(|_| tagged);
+            for (index, params) in stream.take(self.count as usize).enumerate() {
+                match self.format {
+                    ParamsFormat::Jsonl => {
+                        // written by hand to keep the parameters in query order
+                        write!(out, "{{\"query\":\"{}\",\"index\":{index}", self.query)?;
+                        if let Some(selectivity) = selectivity {
+                            write!(out, ",\"selectivity\":{selectivity}")?;
+                        }
+                        for &(name, value) in params.values() {
+                            write!(out, ",\"{name}\":{}", json_value(value))?;
+                        }
+                        writeln!(out, "}}")?;
+                    }
+                    ParamsFormat::Csv => {
+                        write!(out, "{},{index}", self.query)?;
+                        if let Some(selectivity) = selectivity {
+                            write!(out, ",{selectivity}")?;
+                        }
+                        for &(_, value) in params.values() {
+                            match json_value(value) {
+                                serde_json::Value::String(wkt) => write!(out, ",\"{wkt}\"")?,
+                                number => write!(out, ",{number}")?,
+                            }
+                        }
+                        writeln!(out)?;
+                    }
+                    ParamsFormat::Sql => {
+                        if !first {
+                            writeln!(out)?;
+                        }
+                        if let Some(selectivity) = selectivity {
+                            writeln!(out, "-- selectivity {selectivity}")?;
+                        }
+                        write!(out, "{}", params.sql(self.dialect))?;
+                    }
+                }
+                first = false;
+            }
+        }
+        out.flush()
+    }
+}
+
+/// Parse a selectivity: a fraction or one of the named levels
+fn parse_selectivity(s: &str) -> Result<f64, String> {
+    match s.to_lowercase().as_str() {
+        "low" => Ok(0.001),
+        "medium" => Ok(0.01),
+        "high" => Ok(0.1),
+        _ => s
+            .parse()
+            .map_err(|_| format!("expected a fraction or one of low, medium and high, got {s}")),
+    }
+}
+
+/// Points and windows as WKT, distances in meters (to the centimeter) and
+/// counts as numbers
+fn json_value(value: ParameterValue) -> serde_json::Value {
+    match value {
+        ParameterValue::Point(_) | ParameterValue::Window(_) => value.to_string().into(),
+        ParameterValue::Distance(meters) => ((meters * 100.0).round() / 100.0).into(),
+        ParameterValue::Count(count) => count.into(),
+    }
+}
@@ -17,12 +17,16 @@
 
 //! Parquet output format
 
+use crate::geoarrow::{GeoArrowEncoder, GeometryEncoding};
+use crate::geoparquet::{GeoParquetEncoder, GeoParquetVersion, GeoStatistics};
+use crate::memory::{ChunkEstimate, Reservation};
 use crate::statistics::WriteStatistics;
+use arrow::array::RecordBatch;
 use arrow::datatypes::SchemaRef;
 use futures::StreamExt;
 use log::debug;
 use parquet::arrow::arrow_writer::{compute_leaves, get_column_writers, ArrowColumnChunk};
-use parquet::arrow::ArrowSchemaConverter;
+use parquet::arrow::{add_encoded_arrow_schema_to_metadata, ArrowSchemaConverter};
 use parquet::basic::Compression;
 use parquet::file::properties::WriterProperties;
 use parquet::file::writer::SerializedFileWriter;
@@ -31,7 +35,9 @@ use spatialbench_arrow::RecordBatchIterator;
 use std::io;
 use std::io::Write;
 use std::sync::Arc;
-use tokio::sync::mpsc::{Receiver, Sender};
+
+/// The encoded columns of a row group, and its geometry statistics
+type EncodedRowGroup = (Vec<ArrowColumnChunk>, GeoStatistics);
 
 pub trait IntoSize {
     /// Convert the object into a size
@@ -44,11 +50,26 @@ pub trait IntoSize {
 ///
 /// Note the input is an iterator of [`RecordBatchIterator`]; The batches
 /// produced by each iterator is encoded as its own row group.
+///
+/// If `geoparquet_version` is set, the file is written as GeoParquet (see
+/// [`GeoParquetEncoder`]). If `geometry_encoding` is
+/// [`GeometryEncoding::Geoarrow`], geometry columns are written as native
+/// GeoArrow columns (see [`GeoArrowEncoder`]), with the types inferred from the
+/// first batch.
+///
+/// If `data_page_bytes` is set, it is the data page size limit, otherwise the
+/// Parquet default is used.
+///
+/// With `--memory-limit`, each row group reserves its memory before it is
+/// encoded until it is written (see [`crate::memory`]).
 pub async fn generate_parquet<W: Write + Send + IntoSize + 'static, I>(
     writer: W,
     iter_iter: I,
     num_threads: usize,
     parquet_compression: Compression,
+    geoparquet_version: Option<GeoParquetVersion>,
+    geometry_encoding: GeometryEncoding,
+    data_page_bytes: Option<usize>,
 ) -> Result<(), io::Error>
 where
     I: Iterator<Item: RecordBatchIterator> + 'static,
@@ -57,18 +78,48 @@ where
         "Generating Parquet with {num_threads} threads, using {parquet_compression} compression"
     );
     // Based on example in https://docs.rs/parquet/latest/parquet/arrow/arrow_writer/struct.ArrowColumnWriter.html
-    let mut iter_iter = iter_iter.peekable();
+    let mut iter_iter = iter_iter.map(SampledIterator::new).peekable();
 
     // get schema from the first iterator
-    let Some(first_iter) = iter_iter.peek() else {
+    let Some(first_iter) = iter_iter.peek_mut() else {
         return Ok(()); // no data shrug
     };
     let schema = Arc::clone(first_iter.schema());
+    let mut geoparquet_encoder =
+        geoparquet_version.map(|version| GeoParquetEncoder::new(&schema, version));
+    // the covering columns (if any) are added to the written schema
+    let schema = match &geoparquet_encoder {
+        Some(encoder) => Arc::clone(encoder.schema()),
+        None => schema,
+    };
+    let geoarrow_encoder = match geometry_encoding {
+        GeometryEncoding::Wkb => None,
+        GeometryEncoding::Geoarrow => {
+            let sample: Vec<_> = first_iter.sample().cloned().into_iter().collect();
+            Some(Arc::new(GeoArrowEncoder::new(&schema, &sample)))
+        }
+    };
+    // the native geometry columns (if any) replace the WKB columns
+    let schema = match &geoarrow_encoder {
+        Some(encoder) => {
+            geoparquet_encoder = geoparquet_encoder
+                .map(|geoparquet| geoparquet.with_native_types(encoder.geometry_types()));
+            Arc::clone(encoder.schema())
+        }
+        None => schema,
+    };
+    let geoparquet_encoder = geoparquet_encoder.map(Arc::new);
 
     // Compute the parquet schema
-    let writer_properties = WriterProperties::builder()
-        .set_compression(parquet_compression)
-        .build();
+    let mut writer_properties = WriterProperties::builder().set_compression(parquet_compression);
+    if let Some(data_page_bytes) = data_page_bytes {
+        writer_properties = writer_properties.set_data_page_size_limit(data_page_bytes);
+    }
+    let mut writer_properties = writer_properties.build();
+    // embed the Arrow schema so readers see the GeoArrow extension types
+    if geoarrow_encoder.is_some() {
+        add_encoded_arrow_schema_to_metadata(&schema, &mut writer_properties);
+    }
     let writer_properties = Arc::new(writer_properties);
     let parquet_schema = Arc::new(
         ArrowSchemaConverter::new()
@@ -78,14 +129,28 @@ where
     );
 
     // create a stream that computes the data for each row group
+    let estimate = Arc::new(ChunkEstimate::new());
     let mut row_group_stream = futures::stream::iter(iter_iter)
         .map(async |iter| {
+            let reservation = estimate.reserve().await;
+            let estimate = Arc::clone(&estimate);
             let parquet_schema = Arc::clone(&parquet_schema);
             let writer_properties = Arc::clone(&writer_properties);
             let schema = Arc::clone(&schema);
+            let geoparquet_encoder = geoparquet_encoder.clone();
+            let geoarrow_encoder = geoarrow_encoder.clone();
             // run on a separate thread
             tokio::task::spawn(async move {
-                encode_row_group(parquet_schema, writer_properties, schema, iter)
+                let (row_group, bytes) = encode_row_group(
+                    parquet_schema,
+                    writer_properties,
+                    schema,
+                    geoparquet_encoder.as_deref(),
+                    geoarrow_encoder.as_deref(),
+                    iter,
+                );
+                estimate.update(bytes);
+                (row_group, reservation)
             })
             .await
             .expect("Inner task panicked")
@@ -99,16 +164,16 @@ where
     // Now, read each completed row group and write it to the file
     let root_schema = parquet_schema.root_schema_ptr();
     let writer_properties_captured = Arc::clone(&writer_properties);
-    let (tx, mut rx): (
-        Sender<Vec<ArrowColumnChunk>>,
-        Receiver<Vec<ArrowColumnChunk>>,
-    ) = tokio::sync::mpsc::channel(num_threads);
+    let geoparquet_encoder_captured = geoparquet_encoder.clone();
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<(EncodedRowGroup, Reservation)>(num_threads);
     let writer_task = tokio::task::spawn_blocking(move || {
         // Create parquet writer
         let mut writer =
             SerializedFileWriter::new(writer, root_schema, writer_properties_captured).unwrap();
 
-        while let Some(chunks) = rx.blocking_recv() {
+        let mut geo_statistics = GeoStatistics::default();
+        while let Some(((chunks, row_group_geo_statistics), reservation)) = rx.blocking_recv() {
+            geo_statistics.merge(&row_group_geo_statistics);
             // Start row group
             let mut row_group_writer = writer.next_row_group().unwrap();
 
@@ -118,6 +183,10 @@ where
             }
             row_group_writer.close().unwrap();
             statistics.increment_chunks(1);
+            drop(reservation);
+        }
+        if let Some(encoder) = geoparquet_encoder_captured {
+            writer.append_key_value_metadata(encoder.metadata(&geo_statistics));
         }
         let size = writer.into_inner()?.into_size()?;
         statistics.increment_bytes(size);
@@ -146,34 +215,93 @@ where
 /// Note at the moment it does not use multiple tasks/threads but it could
 /// potentially encode multiple columns with different threads .
 ///
-/// Returns an array of [`ArrowColumnChunk`]
+/// Returns an array of [`ArrowColumnChunk`] and the [`GeoStatistics`] of the
+/// row group (empty if `geoparquet_encoder` is None), and the memory used by
+/// the encoded row group
 fn encode_row_group<I>(
     parquet_schema: SchemaDescPtr,
     writer_properties: Arc<WriterProperties>,
     schema: SchemaRef,
+    geoparquet_encoder: Option<&GeoParquetEncoder>,
+    geoarrow_encoder: Option<&GeoArrowEncoder>,
     iter: I,
-) -> Vec<ArrowColumnChunk>
+) -> (EncodedRowGroup, usize)
 where
     I: RecordBatchIterator,
 {
     // Create writers for each of the leaf columns
     let mut col_writers = get_column_writers(&parquet_schema, &writer_properties, &schema).unwrap();
 
+    let mut geo_statistics = geoparquet_encoder
+        .map(|encoder| encoder.new_statistics())
+        .unwrap_or_default();
+
     // generate the data and send it to the tasks (via the sender channels)
     for batch in iter {
+        let batch = match geoparquet_encoder {
+            Some(encoder) => encoder.encode(batch, &mut geo_statistics),
+            None => batch,
+        };
+        // the statistics are computed from the WKB, so convert afterwards
+        let batch = match geoarrow_encoder {
+            Some(encoder) => encoder.encode(batch).unwrap(),
+            None => batch,
+        };
         let columns = batch.columns().iter();
-        let col_writers = col_writers.iter_mut();
+        // nested columns (e.g. the GeoParquet bbox) have one writer per leaf
+        let mut col_writers = col_writers.iter_mut();
         let fields = schema.fields().iter();
 
-        for ((col_writer, field), arr) in col_writers.zip(fields).zip(columns) {
+        for (field, arr) in fields.zip(columns) {
             for leaves in compute_leaves(field.as_ref(), arr).unwrap() {
+                let col_writer = col_writers.next().expect("one writer per leaf");
                 col_writer.write(&leaves).unwrap();
             }
         }
     }
     // finish the writers and create the column chunks
-    col_writers
+    let bytes = col_writers.iter().map(|w| w.memory_size()).sum();
+    let chunks = col_writers
         .into_iter()
         .map(|col_writer| col_writer.close().unwrap())
-        .collect()
+        .collect();
+    ((chunks, geo_statistics), bytes)
+}
+
+/// A [`RecordBatchIterator`] whose first batch can be inspected before
+/// iterating, e.g. to infer the geometry types
+pub struct SampledIterator<I> {
+    sample: Option<RecordBatch>,
+    inner: I,
+}
+
+impl<I: RecordBatchIterator> SampledIterator<I> {
+    pub fn new(inner: I) -> Self {
+        Self {
+            sample: None,
+            inner,
+        }
+    }
+
+    /// Return the first batch (if any) without consuming it
+    pub fn sample(&mut self) -> Option<&RecordBatch> {
+        if self.sample.is_none() {
+            self.sample = self.inner.next();
+        }
+        self.sample.as_ref()
+    }
+}
+
+impl<I: RecordBatchIterator> Iterator for SampledIterator<I> {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.sample.take().or_else(|| self.inner.next())
+    }
+}
+
+impl<I: RecordBatchIterator> RecordBatchIterator for SampledIterator<I> {
+    fn schema(&self) -> &SchemaRef {
+        self.inner.schema()
+    }
 }
@@ -0,0 +1,200 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! `--path-template`: the path of each output file, relative to the output
+//! directory, e.g. `{table}/sf={sf}/part-{part:05}.parquet`
+//!
+//! The placeholders are:
+//!
+//! * `{table}`: the name of the table (required)
+//! * `{sf}`: the scale factor, e.g. `1` or `0.1`
+//! * `{part}`: the part of the table, from 1 (1 for tables not split into
+//!   parts)
+//! * `{seed}`: the seed of the random values
+//! * `{ext}`: the extension of the output format, e.g. `parquet`
+//!
+//! `{part}` and `{seed}` can be zero padded to a width, e.g. `{part:05}`.
+//! `/` separates the directories, both in local directories and object
+//! store URLs.
+
+use std::fmt::Write;
+
+/// A parsed `--path-template`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Table,
+    ScaleFactor,
+    /// The part, zero padded to the width
+    Part(usize),
+    /// The seed, zero padded to the width
+    Seed(usize),
+    Extension,
+}
+
+/// The values of the placeholders for one output file
+#[derive(Debug, Clone, Copy)]
+pub struct PathValues<'a> {
+    pub table: &'a str,
+    pub scale_factor: f64,
+    pub part: i32,
+    pub seed: u64,
+    pub extension: &'a str,
+}
+
+/// Parse a path template such as `{table}/sf={sf}/part-{part:05}.parquet`
+pub fn parse_path_template(value: &str) -> Result<PathTemplate, String> {
+    let mut segments = vec![];
+    let mut rest = value;
+    while let Some(start) = rest.find('{') {
+        if rest[..start].contains('}') {
+            return Err(format!("unmatched '}}' in path template '{value}'"));
+        }
+        if start > 0 {
+            segments.push(Segment::Literal(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed '{{' in path template '{value}'"))?;
+        let placeholder = &rest[start + 1..start + end];
+        let (name, width) = match placeholder.split_once(':') {
+            Some((name, width)) => {
+                let width = width
+                    .strip_prefix('0')
+                    .and_then(|width| width.parse::<usize>().ok())
+                    .ok_or_else(|| {
+                        format!("invalid width '{{{placeholder}}}' in path template, expected e.g. {{part:05}}")
+                    })?;
+                (name, Some(width))
+            }
+            None => (placeholder, None),
+        };
+        let segment = match (name, width) {
+            ("table", None) => Segment::Table,
+            ("sf", None) => Segment::ScaleFactor,
+            ("ext", None) => Segment::Extension,
+            ("part", width) => Segment::Part(width.unwrap_or(0)),
+            ("seed", width) => Segment::Seed(width.unwrap_or(0)),
+            _ => {
+                return Err(format!(
+                    "unknown placeholder '{{{placeholder}}}' in path template, expected {{table}}, {{sf}}, {{part}}, {{seed}} or {{ext}}"
+                ))
+            }
+        };
+        segments.push(segment);
+        rest = &rest[start + end + 1..];
+    }
+    if rest.contains('}') {
+        return Err(format!("unmatched '}}' in path template '{value}'"));
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+
+    if !segments.contains(&Segment::Table) {
+        return Err(format!(
+            "path template '{value}' must contain {{table}}, so the tables are written to different files"
+        ));
+    }
+    // the files must stay within the output directory
+    if value.starts_with('/')
+        || value.ends_with('/')
+        || value
+            .split('/')
+            .any(|component| matches!(component, "" | "." | ".."))
+    {
+        return Err(format!(
+            "path template '{value}' must be a relative path of a file, without '.' or '..'"
+        ));
+    }
+    Ok(PathTemplate { segments })
+}
+
+impl PathTemplate {
+    /// Return true if the template contains `{part}`, so each part of a
+    /// table is written to a different file
+    pub fn has_part(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Part(_)))
+    }
+
+    /// Return the path of the file with `values`, with `/` separators
+    pub fn render(&self, values: PathValues) -> String {
+        let mut path = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => path.push_str(literal),
+                Segment::Table => path.push_str(values.table),
+                Segment::ScaleFactor => write!(path, "{}", values.scale_factor).unwrap(),
+                Segment::Part(width) => write!(path, "{:0width$}", values.part).unwrap(),
+                Segment::Seed(width) => write!(path, "{:0width$}", values.seed).unwrap(),
+                Segment::Extension => path.push_str(values.extension),
+            }
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_path_template() {
+        let template = parse_path_template("{table}/sf={sf}/part-{part:05}.{ext}").unwrap();
+        let values = PathValues {
+            table: "trip",
+            scale_factor: 0.1,
+            part: 3,
+            seed: 0,
+            extension: "parquet",
+        };
+        assert!(template.has_part());
+        assert_eq!(template.render(values), "trip/sf=0.1/part-00003.parquet");
+
+        let template = parse_path_template("seed={seed}/{table}.csv").unwrap();
+        assert!(!template.has_part());
+        assert_eq!(template.render(values), "seed=0/trip.csv");
+    }
+
+    #[test]
+    fn test_parse_path_template_errors() {
+        for (template, error) in [
+            ("sf={sf}/part-{part}.parquet", "must contain {table}"),
+            (
+                "{table}/{partition}.parquet",
+                "unknown placeholder '{partition}'",
+            ),
+            ("{table}/{part:5}.parquet", "invalid width"),
+            ("{table}/{part.parquet", "unclosed"),
+            ("{table}}.parquet", "unmatched"),
+            ("sf=}/{table}.parquet", "unmatched"),
+            ("/data/{table}.parquet", "relative path"),
+            ("../{table}.parquet", "relative path"),
+            ("{table}/", "relative path"),
+        ] {
+            let err = parse_path_template(template).unwrap_err();
+            assert!(err.contains(error), "{template}: {err}");
+        }
+    }
+}
@@ -17,10 +17,12 @@
 
 //! * [`GenerationPlan`]: how to generate a specific Spatial Bench dataset.
 
+use crate::throttle;
 use crate::{OutputFormat, Table};
 use log::debug;
 use spatialbench::generators::{
-    BuildingGenerator, CustomerGenerator, DriverGenerator, TripGenerator, VehicleGenerator,
+    CityGenerator, CountryGenerator, LandmassGenerator, RasterGenerator, RegionGenerator,
+    RoadGenerator, RowCounts,
 };
 use std::fmt::Display;
 use std::ops::RangeInclusive;
@@ -35,7 +37,7 @@ use std::ops::RangeInclusive;
 /// A "part" is a logical partition of a particular output table. Each data
 /// generator can create parts individually.
 ///
-/// For example, the parameters to [`TripGenerator::new`] `scale_factor,
+/// For example, the parameters to [`spatialbench::generators::TripGenerator::new`] `scale_factor,
 /// `part_count` and `part_count` together define a partition of the `Trip`
 /// table.
 ///
@@ -81,16 +83,22 @@ impl GenerationPlan {
     /// Returns a GenerationPlan number of parts to generate
     ///
     /// # Arguments
+    /// * `row_counts`: overridden row counts of the tables, `--rows` CLI argument
     /// * `cli_part`: optional part number to generate (1-based), `--part` CLI argument
     /// * `cli_part_count`: optional total number of parts, `--parts` CLI argument
-    /// * `parquet_row_group_size`: optional parquet row group size, `--parquet-row-group-size` CLI argument
+    /// * `parquet_row_group_bytes`: target parquet row group size in bytes, `--parquet-row-group-bytes` CLI argument
+    /// * `parquet_row_group_rows`: optional maximum parquet row group size in rows, `--parquet-row-group-rows` CLI argument.
+    ///   If set, `parquet_row_group_bytes` is ignored
+    #[allow(clippy::too_many_arguments)]
     pub fn try_new(
         table: Table,
         format: OutputFormat,
         scale_factor: f64,
+        row_counts: RowCounts,
         cli_part: Option<i32>,
         cli_part_count: Option<i32>,
         parquet_row_group_bytes: i64,
+        parquet_row_group_rows: Option<i64>,
     ) -> Result<Self, String> {
         // If a single part is specified, split it into chunks to enable parallel generation.
         match (cli_part, cli_part_count) {
@@ -108,32 +116,51 @@ impl GenerationPlan {
                 table,
                 format,
                 scale_factor,
+                row_counts,
                 part,
                 part_count,
                 parquet_row_group_bytes,
+                parquet_row_group_rows,
+            ),
+            (None, None) => Self::try_new_without_parts(
+                table,
+                format,
+                scale_factor,
+                row_counts,
+                parquet_row_group_bytes,
+                parquet_row_group_rows,
             ),
-            (None, None) => {
-                Self::try_new_without_parts(table, format, scale_factor, parquet_row_group_bytes)
-            }
         }
     }
 
     /// Return true if the tables is unpartitionable (not parameterized by part
     /// count)
     pub fn partitioned_table(table: Table) -> bool {
-        table != Table::Vehicle && table != Table::Driver && table != Table::Building
+        !matches!(
+            table,
+            Table::Vehicle
+                | Table::Driver
+                | Table::Building
+                | Table::Landmass
+                | Table::Country
+                | Table::Region
+                | Table::City
+        )
     }
 
     /// Returns a new `GenerationPlan` when partitioning
     ///
     /// See [`GenerationPlan::try_new`] for argument documentation.
+    #[allow(clippy::too_many_arguments)]
     fn try_new_with_parts(
         table: Table,
         format: OutputFormat,
         scale_factor: f64,
+        row_counts: RowCounts,
         cli_part: i32,
         cli_part_count: i32,
         parquet_row_group_bytes: i64,
+        parquet_row_group_rows: Option<i64>,
     ) -> Result<Self, String> {
         if cli_part < 1 {
             return Err(format!(
@@ -161,9 +188,16 @@ impl GenerationPlan {
 
         // scale down the row count by the number of partitions being generated
         // so that the output is consistent with the original part count
-        let num_chunks = OutputSize::new(table, scale_factor, format, parquet_row_group_bytes)
-            .with_scaled_row_count(cli_part_count)
-            .part_count();
+        let num_chunks = OutputSize::new(
+            table,
+            scale_factor,
+            row_counts,
+            format,
+            parquet_row_group_bytes,
+            parquet_row_group_rows,
+        )
+        .with_scaled_row_count(cli_part_count)
+        .part_count();
 
         // The new total number of partitions is the original number of
         // partitions multiplied by the number of chunks.
@@ -196,9 +230,18 @@ impl GenerationPlan {
         table: Table,
         format: OutputFormat,
         scale_factor: f64,
+        row_counts: RowCounts,
         parquet_row_group_bytes: i64,
+        parquet_row_group_rows: Option<i64>,
     ) -> Result<Self, String> {
-        let output_size = OutputSize::new(table, scale_factor, format, parquet_row_group_bytes);
+        let output_size = OutputSize::new(
+            table,
+            scale_factor,
+            row_counts,
+            format,
+            parquet_row_group_bytes,
+            parquet_row_group_rows,
+        );
         let num_parts = output_size.part_count();
 
         Ok(Self {
@@ -241,6 +284,8 @@ pub struct OutputSize {
     row_count: i64,
     /// output target chunk size in bytes
     target_chunk_size_bytes: i64,
+    /// output maximum chunk size in rows, if any (overrides `target_chunk_size_bytes`)
+    target_chunk_rows: Option<i64>,
     /// maximum part count, if any
     max_part_count: Option<i64>,
 }
@@ -249,10 +294,12 @@ impl OutputSize {
     pub fn new(
         table: Table,
         scale_factor: f64,
+        row_counts: RowCounts,
         format: OutputFormat,
         parquet_row_group_bytes: i64,
+        parquet_row_group_rows: Option<i64>,
     ) -> Self {
-        let row_count = Self::row_count_for_table(table, scale_factor);
+        let row_count = Self::row_count_for_table(table, scale_factor, row_counts);
 
         // The average row size in bytes for each table in the SpatialBench schema
         // this was determined by sampling the data
@@ -263,6 +310,15 @@ impl OutputSize {
                 Table::Customer => 84,
                 Table::Trip => 144,
                 Table::Building => 212,
+                Table::Trajectory => 2722,
+                Table::Road => 592,
+                Table::Landmass => 170607,
+                Table::Country => 1268,
+                Table::Region => 479,
+                Table::City => 168,
+                Table::Poi => 61,
+                Table::Raster => 166,
+                Table::PointCloud => 48,
                 Table::Zone => 115,
             },
             // Average row size in bytes for each table at scale factor 1.0
@@ -270,12 +326,78 @@ impl OutputSize {
             // ```shell
             // datafusion-cli -c "datafusion-cli -c "select row_group_id, count(*), min(row_group_bytes)::float/min(row_group_num_rows)::float as bytes_per_row from parquet_metadata('zone.parquet') GROUP BY 1 ORDER BY 1""
             // ```
+            // Measured from the geojsonl output
+            OutputFormat::Geojson | OutputFormat::Geojsonl => match table {
+                Table::Vehicle => 174,
+                Table::Driver => 205,
+                Table::Customer => 206,
+                Table::Trip => 405,
+                Table::Building => 297,
+                Table::Trajectory => 3055,
+                Table::Road => 743,
+                Table::Landmass => 183474,
+                Table::Country => 1467,
+                Table::Region => 629,
+                Table::City => 291,
+                Table::Poi => 181,
+                Table::Raster => 314,
+                Table::PointCloud => 167,
+                // the zone table is only written as parquet
+                Table::Zone => 0,
+            },
+            // Measured from the flatgeobuf output
+            OutputFormat::Flatgeobuf => match table {
+                Table::Vehicle => 122,
+                Table::Driver => 140,
+                Table::Customer => 141,
+                Table::Trip => 283,
+                Table::Building => 229,
+                Table::Trajectory => 1780,
+                Table::Road => 483,
+                Table::Landmass => 102457,
+                Table::Country => 940,
+                Table::Region => 428,
+                Table::City => 235,
+                Table::Poi => 179,
+                Table::Raster => 243,
+                Table::PointCloud => 88,
+                // the zone table is only written as parquet
+                Table::Zone => 0,
+            },
+            // Measured from the arrow output
+            OutputFormat::Arrow => match table {
+                Table::Vehicle => 133,
+                Table::Driver => 148,
+                Table::Customer => 147,
+                Table::Trip => 164,
+                Table::Building => 137,
+                Table::Trajectory => 1643,
+                Table::Road => 382,
+                Table::Landmass => 102452,
+                Table::Country => 893,
+                Table::Region => 349,
+                Table::City => 150,
+                Table::Poi => 81,
+                Table::Raster => 138,
+                Table::PointCloud => 39,
+                // the zone table is only written as parquet
+                Table::Zone => 0,
+            },
             OutputFormat::Parquet => match table {
                 Table::Vehicle => 54,
                 Table::Driver => 84,
                 Table::Customer => 87,
                 Table::Trip => 69,
                 Table::Building => 109,
+                Table::Trajectory => 1640,
+                Table::Road => 276,
+                Table::Landmass => 102361,
+                Table::Country => 545,
+                Table::Region => 182,
+                Table::City => 47,
+                Table::Poi => 38,
+                Table::Raster => 37,
+                Table::PointCloud => 24,
                 Table::Zone => {
                     // Scale based on zone subtype count for the scale factor
                     match scale_factor {
@@ -289,38 +411,62 @@ impl OutputSize {
         };
 
         let target_chunk_size_bytes = match format {
-            // for tbl/csv target chunks, this value does not affect the output
+            // for tbl/csv/geojson/flatgeobuf/arrow target chunks, this value does not affect the output
             // file. Use 15MB, slightly smaller than the 16MB buffer size,  to
             // ensure small overages don't exceed the buffer size and require a
             // reallocation
-            OutputFormat::Tbl | OutputFormat::Csv => 15 * 1024 * 1024,
+            OutputFormat::Tbl
+            | OutputFormat::Csv
+            | OutputFormat::Geojson
+            | OutputFormat::Geojsonl
+            | OutputFormat::Flatgeobuf
+            | OutputFormat::Arrow => 15 * 1024 * 1024,
             OutputFormat::Parquet => parquet_row_group_bytes,
         };
 
+        // only parquet files have row groups, the other files are written
+        // chunk by chunk, in chunks of one second of rows when throttled
+        let target_chunk_rows = match format {
+            OutputFormat::Parquet => parquet_row_group_rows,
+            _ => throttle::chunk_rows(),
+        };
+
         // parquet files can have at most 32767 row groups so cap the number of parts at that number
         let max_part_count = match format {
-            OutputFormat::Tbl | OutputFormat::Csv => None,
+            OutputFormat::Tbl
+            | OutputFormat::Csv
+            | OutputFormat::Geojson
+            | OutputFormat::Geojsonl
+            | OutputFormat::Flatgeobuf
+            | OutputFormat::Arrow => None,
             OutputFormat::Parquet => Some(32767),
         };
 
         debug!(
             "Output size for table {table:?} with scale factor {scale_factor}: \
                 avg_row_size_bytes={avg_row_size_bytes}, row_count={row_count} \
-                target_chunk_size_bytes={target_chunk_size_bytes}, max_part_count={max_part_count:?}",
+                target_chunk_size_bytes={target_chunk_size_bytes}, target_chunk_rows={target_chunk_rows:?}, \
+                max_part_count={max_part_count:?}",
         );
 
         OutputSize {
             avg_row_size_bytes,
             row_count,
             target_chunk_size_bytes,
+            target_chunk_rows,
             max_part_count,
         }
     }
 
     /// Return the number of parts to generate
     pub fn part_count(&self) -> i32 {
-        let mut num_parts =
-            ((self.row_count * self.avg_row_size_bytes) / self.target_chunk_size_bytes) + 1; // +1 to ensure we have at least one part
+        let mut num_parts = match self.target_chunk_rows {
+            // round up, so that no part has more than the target number of rows
+            Some(target_chunk_rows) => {
+                ((self.row_count + target_chunk_rows - 1) / target_chunk_rows).max(1)
+            }
+            None => ((self.row_count * self.avg_row_size_bytes) / self.target_chunk_size_bytes) + 1, // +1 to ensure we have at least one part
+        };
 
         if let Some(max_part_count) = self.max_part_count {
             // if the max part count is set, cap the number of parts at that number
@@ -346,18 +492,30 @@ impl OutputSize {
             avg_row_size_bytes: self.avg_row_size_bytes,
             row_count: scaled_row_count,
             target_chunk_size_bytes: self.target_chunk_size_bytes,
+            target_chunk_rows: self.target_chunk_rows,
             max_part_count: self.max_part_count,
         }
     }
 
-    fn row_count_for_table(table: Table, scale_factor: f64) -> i64 {
+    fn row_count_for_table(table: Table, scale_factor: f64, row_counts: RowCounts) -> i64 {
         //let (avg_row_size_bytes, row_count) = match table {
         match table {
-            Table::Vehicle => VehicleGenerator::calculate_row_count(scale_factor, 1, 1),
-            Table::Driver => DriverGenerator::calculate_row_count(scale_factor, 1, 1),
-            Table::Customer => CustomerGenerator::calculate_row_count(scale_factor, 1, 1),
-            Table::Trip => TripGenerator::calculate_row_count(scale_factor, 1, 1),
-            Table::Building => BuildingGenerator::calculate_row_count(scale_factor, 1, 1),
+            Table::Vehicle => row_counts.vehicle_count(scale_factor),
+            Table::Driver => row_counts.driver_count(scale_factor),
+            Table::Customer => row_counts.customer_count(scale_factor),
+            Table::Trip => row_counts.trip_count(scale_factor),
+            Table::Building => row_counts.building_count(scale_factor),
+            // one trajectory per trip
+            Table::Trajectory => row_counts.trip_count(scale_factor),
+            // the number of roads also depends on the seed, slightly
+            Table::Road => RoadGenerator::new(scale_factor, 1, 1).road_count(),
+            Table::Landmass => LandmassGenerator::landmass_count(),
+            Table::Country => CountryGenerator::country_count(),
+            Table::Region => RegionGenerator::region_count(),
+            Table::City => CityGenerator::city_count(),
+            Table::Poi => row_counts.poi_count(scale_factor),
+            Table::Raster => RasterGenerator::raster_count(scale_factor),
+            Table::PointCloud => row_counts.pointcloud_count(scale_factor),
             Table::Zone => todo!(),
         }
     }
@@ -486,6 +644,37 @@ mod tests {
         // }
     }
 
+    // Test plans of tables with overridden row counts (`--rows`)
+    mod row_counts {
+        use super::*;
+        #[test]
+        fn parquet_sf1_building_10m_rows() {
+            Test::new()
+                .with_table(Table::Building)
+                .with_format(OutputFormat::Parquet)
+                .with_scale_factor(1.0)
+                .with_row_counts(RowCounts {
+                    building: Some(10_000_000),
+                    ..Default::default()
+                })
+                .assert(9, 1..=9)
+        }
+
+        #[test]
+        fn parquet_sf1_trip_other_table_rows() {
+            // the row counts of other tables do not change the plan
+            Test::new()
+                .with_table(Table::Trip)
+                .with_format(OutputFormat::Parquet)
+                .with_scale_factor(1.0)
+                .with_row_counts(RowCounts {
+                    building: Some(10_000_000),
+                    ..Default::default()
+                })
+                .assert(4, 1..=4)
+        }
+    }
+
     // Test plans with CLI parts and partition counts
     mod partitions {
         use super::*;
@@ -756,6 +945,37 @@ mod tests {
                     // parquet is limited to no more than 32k actual row groups in a parquet file
                     .assert(32767, 1..=32767);
             }
+
+            #[test]
+            fn parquet_sf1_lineitem_row_group_rows() {
+                Test::new()
+                    .with_table(Table::Trip)
+                    .with_format(OutputFormat::Parquet)
+                    .with_scale_factor(1.0)
+                    // 6M rows, so 5 row groups of 1.2M rows
+                    .with_parquet_row_group_rows(1_200_000)
+                    .assert(5, 1..=5);
+            }
+
+            #[test]
+            fn parquet_sf1_lineitem_row_group_rows_round_up() {
+                Test::new()
+                    .with_table(Table::Trip)
+                    .with_format(OutputFormat::Parquet)
+                    .with_scale_factor(1.0)
+                    .with_parquet_row_group_rows(1_000_001)
+                    .assert(6, 1..=6);
+            }
+
+            #[test]
+            fn tbl_sf1_lineitem_row_group_rows_ignored() {
+                Test::new()
+                    .with_table(Table::Trip)
+                    .with_format(OutputFormat::Tbl)
+                    .with_scale_factor(1.0)
+                    .with_parquet_row_group_rows(1_200_000)
+                    .assert(55, 1..=55);
+            }
         }
     }
 
@@ -765,9 +985,11 @@ mod tests {
         table: Table,
         format: OutputFormat,
         scale_factor: f64,
+        row_counts: RowCounts,
         cli_part: Option<i32>,
         cli_part_count: Option<i32>,
         parquet_row_group_bytes: i64,
+        parquet_row_group_rows: Option<i64>,
     }
 
     impl Test {
@@ -782,9 +1004,11 @@ mod tests {
                 self.table,
                 self.format,
                 self.scale_factor,
+                self.row_counts,
                 self.cli_part,
                 self.cli_part_count,
                 self.parquet_row_group_bytes,
+                self.parquet_row_group_rows,
             )
             .unwrap();
             assert_eq!(plan.part_count, expected_part_count);
@@ -797,9 +1021,11 @@ mod tests {
                 self.table,
                 self.format,
                 self.scale_factor,
+                self.row_counts,
                 self.cli_part,
                 self.cli_part_count,
                 self.parquet_row_group_bytes,
+                self.parquet_row_group_rows,
             )
             .unwrap_err();
             assert_eq!(actual_error, expected_error);
@@ -823,6 +1049,12 @@ mod tests {
             self
         }
 
+        /// Set overridden row counts
+        fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+            self.row_counts = row_counts;
+            self
+        }
+
         /// Set CLI part
         fn with_cli_part(mut self, cli_part: i32) -> Self {
             self.cli_part = Some(cli_part);
@@ -840,6 +1072,12 @@ mod tests {
             self.parquet_row_group_bytes = parquet_row_group_bytes;
             self
         }
+
+        /// Set parquet row group size in rows
+        fn with_parquet_row_group_rows(mut self, parquet_row_group_rows: i64) -> Self {
+            self.parquet_row_group_rows = Some(parquet_row_group_rows);
+            self
+        }
     }
 
     impl Default for Test {
@@ -848,9 +1086,11 @@ mod tests {
                 table: Table::Trip,
                 format: OutputFormat::Tbl,
                 scale_factor: 1.0,
+                row_counts: RowCounts::default(),
                 cli_part: None,
                 cli_part_count: None,
                 parquet_row_group_bytes: DEFAULT_PARQUET_ROW_GROUP_BYTES,
+                parquet_row_group_rows: None,
             }
         }
     }
@@ -0,0 +1,231 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `preview` command: print the first rows of a table
+//!
+//! The rows are generated in memory, exactly as the first rows of the first
+//! part of the table, and printed as aligned columns with the geometries as
+//! WKT, so a configuration can be checked before generating the full data.
+
+use crate::geoparquet::{is_wkb_type, wkb_values};
+use crate::rows::{self, RowCount};
+use crate::spatial_config_file::{load_overrides, parse_distribution, TableDistribution};
+use arrow::array::{Array, ArrayRef, RecordBatch};
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use clap::Args;
+use geozero::wkb::Wkb;
+use geozero::ToGeo;
+use spatialbench_arrow::{Table, TableGenerator};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Print a sample of the rows of a table
+#[derive(Debug, Args)]
+pub struct PreviewArgs {
+    /// Table to preview
+    #[arg(short, long, default_value_t = Table::Trip, value_parser = Table::from_str)]
+    table: Table,
+
+    /// Number of rows to print
+    #[arg(short, long, default_value_t = 10)]
+    rows: usize,
+
+    /// Scale factor the table is generated at
+    #[arg(short, long, default_value_t = 1.)]
+    scale_factor: f64,
+
+    /// Seed all random values are derived from
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Overridden row counts of the tables, e.g. `--row-count building=10000000`
+    #[arg(long, value_parser = rows::parse_rows)]
+    row_count: Vec<RowCount>,
+
+    /// YAML file path specifying configs for Trip and Building
+    #[arg(long = "config")]
+    config: Option<PathBuf>,
+
+    /// Distribution of the geometries of a table, e.g. `trip=hotspots`
+    #[arg(long, value_parser = parse_distribution)]
+    distribution: Vec<TableDistribution>,
+
+    /// Maximum number of characters of a value, longer values such as the
+    /// WKT of polygons are truncated with `...`
+    #[arg(long, default_value_t = 40, value_parser = clap::value_parser!(u16).range(4..))]
+    max_width: u16,
+}
+
+impl PreviewArgs {
+    /// Print the rows to stdout
+    pub fn run(&self) -> io::Result<()> {
+        if !(self.scale_factor.is_finite() && self.scale_factor > 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--scale-factor must be positive, got {}", self.scale_factor),
+            ));
+        }
+        load_overrides(
+            self.config.as_deref(),
+            &self.distribution,
+            &[],
+            None,
+            None,
+            &[],
+            None,
+        )?;
+
+        let row_counts = rows::row_counts(&self.row_count)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let generator = TableGenerator::new(self.table, self.scale_factor)
+            .with_seed(self.seed)
+            .with_row_counts(row_counts)
+            .with_batch_size(self.rows.max(1));
+        let batch = generator.batches().next();
+        let batch = match batch {
+            Some(batch) => batch.slice(0, batch.num_rows().min(self.rows)),
+            None => RecordBatch::new_empty(generator.schema()),
+        };
+
+        let mut stdout = io::stdout().lock();
+        let result = write_table(&mut stdout, &batch, self.max_width as usize);
+        match result {
+            // e.g. piped to `head`
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// Write the rows of `batch` as columns aligned with spaces, under a header
+/// of the column names
+///
+/// Numbers are aligned to the right and other values to the left.
+fn write_table(out: &mut impl Write, batch: &RecordBatch, max_width: usize) -> io::Result<()> {
+    let schema = batch.schema();
+    let columns: Vec<(Vec<String>, bool)> = batch
+        .columns()
+        .iter()
+        .map(|array| {
+            let cells = cells(array, max_width)?;
+            Ok((cells, array.data_type().is_numeric()))
+        })
+        .collect::<io::Result<_>>()?;
+    let widths: Vec<usize> = schema
+        .fields()
+        .iter()
+        .zip(&columns)
+        .map(|(field, (cells, _))| {
+            cells
+                .iter()
+                .map(|cell| cell.chars().count())
+                .chain([field.name().chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut line = String::new();
+    for (field, width) in schema.fields().iter().zip(&widths) {
+        line.push_str(&format!("{:<width$}  ", field.name()));
+    }
+    writeln!(out, "{}", line.trim_end())?;
+    let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    writeln!(out, "{}", rule.join("  "))?;
+    for row in 0..batch.num_rows() {
+        line.clear();
+        for ((cells, numeric), width) in columns.iter().zip(&widths) {
+            if *numeric {
+                line.push_str(&format!("{:>width$}  ", cells[row]));
+            } else {
+                line.push_str(&format!("{:<width$}  ", cells[row]));
+            }
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    out.flush()
+}
+
+/// Return the values of a column as text of at most `max_width` characters,
+/// with WKB geometries as WKT
+fn cells(array: &ArrayRef, max_width: usize) -> io::Result<Vec<String>> {
+    let cells: Vec<Option<String>> = if is_wkb_type(array.data_type()) {
+        wkb_values(array).map(|wkb| wkb.map(wkt)).collect()
+    } else {
+        let options = FormatOptions::default();
+        let formatter = ArrayFormatter::try_new(array, &options).map_err(io::Error::other)?;
+        (0..array.len())
+            .map(|i| array.is_valid(i).then(|| formatter.value(i).to_string()))
+            .collect()
+    };
+    Ok(cells
+        .into_iter()
+        .map(|cell| truncate(cell.as_deref().unwrap_or("NULL"), max_width))
+        .collect())
+}
+
+/// Return the WKT of a WKB geometry, or its bytes as hex if it is not a
+/// geometry, e.g. the tiles of the raster table
+fn wkt(wkb: &[u8]) -> String {
+    match Wkb(wkb).to_geo() {
+        Ok(geometry) => format!("{geometry:?}"),
+        Err(_) => wkb.iter().map(|byte| format!("{byte:02x}")).collect(),
+    }
+}
+
+/// Truncate `value` to at most `max_width` characters, ending with `...` if
+/// it is truncated
+fn truncate(value: &str, max_width: usize) -> String {
+    if value.chars().count() <= max_width {
+        return value.to_string();
+    }
+    let mut truncated: String = value.chars().take(max_width - 3).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("POINT(1 2)", 10), "POINT(1 2)");
+        assert_eq!(truncate("POINT(10 20)", 10), "POINT(1...");
+        assert_eq!(truncate("ééééé", 4), "é...");
+    }
+
+    #[test]
+    fn test_write_table() {
+        let batch = TableGenerator::new(Table::Trip, 0.01)
+            .with_batch_size(3)
+            .batches()
+            .next()
+            .unwrap();
+        let mut out = Vec::new();
+        write_table(&mut out, &batch, 20).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("t_tripkey  t_custkey"));
+        assert!(lines[1].starts_with("---------  ---------"));
+        // the keys are aligned to the right, under the header
+        assert!(lines[2].starts_with("        1  "));
+        assert!(lines[2].contains("POINT("));
+    }
+}
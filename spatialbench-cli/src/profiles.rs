@@ -0,0 +1,199 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Generation options read from the `--config` file, with `--profile`s
+//!
+//! Besides the spider configuration of the trip and building tables, the
+//! config file may set any option of the generation in a `generate` section,
+//! and named `profiles` overriding some of them:
+//!
+//! ```yaml
+//! generate:
+//!   scale_factor: 10
+//!   format: parquet
+//!   rows: [building=1000000]
+//! profiles:
+//!   smoke:
+//!     scale_factor: 0.01
+//!     output_dir: smoke
+//! ```
+//!
+//! The options given on the command line override those of the file.
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command};
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+
+/// The generation options of a config file
+#[derive(Debug, Default, Deserialize)]
+struct RunConfig {
+    #[serde(default)]
+    generate: Mapping,
+    #[serde(default)]
+    profiles: BTreeMap<String, Mapping>,
+}
+
+/// Options which can not be set in the config file
+const RESERVED: [&str; 4] = ["config", "profile", "help", "version"];
+
+/// Return the arguments of the options of the `generate` section of the config
+/// file at `path`, overridden by those of `profile`, except the options
+/// given in `matches` (the command line)
+pub fn config_args(
+    command: &Command,
+    matches: &ArgMatches,
+    path: &Path,
+    profile: Option<&str>,
+) -> io::Result<Vec<OsString>> {
+    let invalid = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid config {}: {message}", path.display()),
+        )
+    };
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Failed reading {}: {e}", path.display()),
+        )
+    })?;
+    let config: RunConfig = serde_yaml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+
+    let mut options = config.generate;
+    if let Some(profile) = profile {
+        let Some(overrides) = config.profiles.get(profile) else {
+            let profiles: Vec<_> = config.profiles.keys().map(String::as_str).collect();
+            return Err(invalid(format!(
+                "no profile {profile}, expected one of: {}",
+                profiles.join(", ")
+            )));
+        };
+        for (key, value) in overrides {
+            options.insert(key.clone(), value.clone());
+        }
+    }
+
+    let mut args = vec![];
+    for (key, value) in &options {
+        let key = key
+            .as_str()
+            .ok_or_else(|| invalid(format!("option {key:?} is not a string")))?;
+        let id = key.replace('-', "_");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == id.as_str() && !RESERVED.contains(&id.as_str()))
+            .ok_or_else(|| invalid(format!("unknown option {key}")))?;
+        if matches.value_source(&id) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let long = arg.get_long().expect("options have a long name");
+        let takes_values = arg.get_action().takes_values();
+        let values = match value {
+            Value::Sequence(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            let value = match value {
+                Value::Null => continue,
+                Value::Bool(flag) if !takes_values => {
+                    if *flag {
+                        args.push(format!("--{long}").into());
+                    }
+                    continue;
+                }
+                Value::Bool(flag) => flag.to_string(),
+                Value::Number(number) => number.to_string(),
+                Value::String(text) => text.clone(),
+                _ => return Err(invalid(format!("invalid value of option {key}"))),
+            };
+            args.push(format!("--{long}={value}").into());
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, ArgAction};
+    use std::io::Write;
+
+    fn command() -> Command {
+        Command::new("test")
+            .arg(Arg::new("scale_factor").long("scale-factor"))
+            .arg(
+                Arg::new("verbose")
+                    .long("verbose")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(Arg::new("rows").long("rows").action(ArgAction::Append))
+            .arg(Arg::new("config").long("config"))
+    }
+
+    fn args(cli: &[&str], config: &str, profile: Option<&str>) -> io::Result<Vec<OsString>> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(config.as_bytes()).unwrap();
+        let matches =
+            command().get_matches_from(std::iter::once("test").chain(cli.iter().copied()));
+        config_args(&command(), &matches, file.path(), profile)
+    }
+
+    #[test]
+    fn test_config_args() {
+        let config = "
+generate:
+  scale_factor: 10
+  verbose: true
+  rows: [building=5, trip=7]
+profiles:
+  small:
+    scale_factor: 0.01
+    rows: []
+";
+        assert_eq!(
+            args(&[], config, None).unwrap(),
+            [
+                "--scale-factor=10",
+                "--verbose",
+                "--rows=building=5",
+                "--rows=trip=7"
+            ]
+        );
+        assert_eq!(
+            args(&[], config, Some("small")).unwrap(),
+            ["--scale-factor=0.01", "--verbose"]
+        );
+        // the command line overrides the file
+        assert_eq!(
+            args(&["--scale-factor", "2", "--rows", "trip=1"], config, None).unwrap(),
+            ["--verbose"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let error = |config: &str, profile| args(&[], config, profile).unwrap_err().to_string();
+        assert!(error("generate: {scale: 1}", None).contains("unknown option scale"));
+        assert!(error("generate: {config: other.yml}", None).contains("unknown option config"));
+        assert!(error("profiles: {small: {}}", Some("large")).contains("no profile large"));
+    }
+}
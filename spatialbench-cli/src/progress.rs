@@ -0,0 +1,553 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Reporting the progress of the generation with `--progress`
+//!
+//! Each table has a [`TableProgress`] that counts the rows generated and the
+//! bytes written for it. The rows are counted per chunk by [`Progressed`]
+//! (batches) and [`ProgressedSource`] (buffers), and the bytes by
+//! [`Counted`] around the writers. Each [`OutputPlan`] adds its rows to the
+//! total rows of its table up front with a [`PlanProgress`], so the percent
+//! complete and the ETA are known from the start, and counts the rows it
+//! skips (existing files and resumed chunks) as done once it is complete.
+//!
+//! A [`Reporter`] thread reports the counts to stderr, either as one bar per
+//! table or as JSON lines.
+
+use crate::generate::{Sink, Source};
+use crate::hive::PartitionSink;
+use crate::manifest::table_rows;
+use crate::output_plan::OutputPlan;
+use crate::parquet::IntoSize;
+use crate::Table;
+use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
+use clap::ValueEnum;
+use serde_json::json;
+use spatialbench_arrow::RecordBatchIterator;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How to report the progress of the generation
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ProgressFormat {
+    /// A bar per table if stderr is a terminal, otherwise nothing
+    Auto,
+    /// A bar per table, redrawn in place
+    Bar,
+    /// A JSON object per table and line, every second
+    Json,
+    /// Do not report the progress
+    None,
+}
+
+/// The rows and bytes of a table generated so far
+#[derive(Debug)]
+pub struct TableProgress {
+    table: Table,
+    /// Rows of all the plans of the table
+    total_rows: AtomicU64,
+    rows: AtomicU64,
+    bytes: AtomicU64,
+    /// Number of the plans of the table that are not complete
+    plans: AtomicUsize,
+    /// When the first plan of the table started
+    start: OnceLock<Instant>,
+    /// When the last plan of the table completed
+    end: OnceLock<Instant>,
+}
+
+/// A snapshot of a [`TableProgress`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStatus {
+    pub table: Table,
+    pub total_rows: u64,
+    pub rows: u64,
+    pub bytes: u64,
+    pub elapsed: Duration,
+    /// Whether all the plans of the table are complete
+    pub done: bool,
+}
+
+impl TableProgress {
+    /// Count `rows` more generated rows
+    pub fn add_rows(&self, rows: u64) {
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+    }
+
+    /// Count `bytes` more written bytes
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Return the current counts, if the table started
+    fn status(&self) -> Option<TableStatus> {
+        let start = self.start.get()?;
+        let end = self.end.get();
+        Some(TableStatus {
+            table: self.table,
+            total_rows: self.total_rows.load(Ordering::Relaxed),
+            rows: self.rows.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            elapsed: end.copied().unwrap_or_else(Instant::now) - *start,
+            done: end.is_some(),
+        })
+    }
+}
+
+static TABLES: Mutex<BTreeMap<Table, Arc<TableProgress>>> = Mutex::new(BTreeMap::new());
+
+/// Return the progress of `table`
+pub fn table(table: Table) -> Arc<TableProgress> {
+    let mut tables = TABLES.lock().unwrap();
+    let progress = tables.entry(table).or_insert_with(|| {
+        Arc::new(TableProgress {
+            table,
+            total_rows: AtomicU64::new(0),
+            rows: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            plans: AtomicUsize::new(0),
+            start: OnceLock::new(),
+            end: OnceLock::new(),
+        })
+    });
+    Arc::clone(progress)
+}
+
+/// Return the current counts of the tables that started
+pub fn statuses() -> Vec<TableStatus> {
+    TABLES
+        .lock()
+        .unwrap()
+        .values()
+        .filter_map(|table| table.status())
+        .collect()
+}
+
+/// The rows of an [`OutputPlan`] generated so far
+#[derive(Debug)]
+pub struct PlanProgress {
+    table: Arc<TableProgress>,
+    /// Rows of the chunks of the plan
+    total_rows: u64,
+    rows: AtomicU64,
+}
+
+impl PlanProgress {
+    /// Add the rows of `plan` to the total rows of its table
+    pub fn new(plan: &OutputPlan) -> Arc<Self> {
+        let name = plan.table().name();
+        let total_rows = plan
+            .generation_plan()
+            .clone()
+            .into_iter()
+            .filter_map(|part| {
+                table_rows(
+                    name,
+                    plan.scale_factor(),
+                    plan.seed(),
+                    plan.row_counts(),
+                    Some(part),
+                )
+            })
+            .sum::<i64>() as u64;
+        let table = table(plan.table());
+        table.total_rows.fetch_add(total_rows, Ordering::Relaxed);
+        table.plans.fetch_add(1, Ordering::Relaxed);
+        Arc::new(Self {
+            table,
+            total_rows,
+            rows: AtomicU64::new(0),
+        })
+    }
+
+    /// Called when the plan starts running
+    pub fn start(&self) {
+        self.table.start.get_or_init(Instant::now);
+    }
+
+    /// Count `rows` more generated rows
+    pub fn add_rows(&self, rows: u64) {
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+        self.table.add_rows(rows);
+    }
+
+    /// Called once the plan is complete, to count the rows that were not
+    /// generated because their output already existed
+    pub fn finish(&self) {
+        let rows = self.rows.load(Ordering::Relaxed);
+        self.add_rows(self.total_rows.saturating_sub(rows));
+        if self.table.plans.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.table.end.get_or_init(Instant::now);
+        }
+    }
+}
+
+/// A [`RecordBatchIterator`] that counts the rows of the batches of the
+/// inner iterator in a [`PlanProgress`]
+pub struct Progressed<I> {
+    inner: I,
+    progress: Arc<PlanProgress>,
+}
+
+impl<I: RecordBatchIterator> Progressed<I> {
+    /// Count the rows of `inner`
+    pub fn new(inner: I, progress: Arc<PlanProgress>) -> Self {
+        Self { inner, progress }
+    }
+}
+
+impl<I: RecordBatchIterator> Iterator for Progressed<I> {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = self.inner.next()?;
+        self.progress.add_rows(batch.num_rows() as u64);
+        Some(batch)
+    }
+}
+
+impl<I: RecordBatchIterator> RecordBatchIterator for Progressed<I> {
+    fn schema(&self) -> &SchemaRef {
+        self.inner.schema()
+    }
+}
+
+/// A [`Source`] that counts its `rows` in a [`PlanProgress`] once it is
+/// created
+pub struct ProgressedSource<S> {
+    inner: S,
+    rows: u64,
+    progress: Arc<PlanProgress>,
+}
+
+impl<S: Source> ProgressedSource<S> {
+    /// Count the `rows` of `inner`
+    pub fn new(inner: S, rows: i64, progress: Arc<PlanProgress>) -> Self {
+        Self {
+            inner,
+            rows: rows as u64,
+            progress,
+        }
+    }
+}
+
+impl<S: Source> Source for ProgressedSource<S> {
+    fn create(self, buffer: Vec<u8>) -> Vec<u8> {
+        let buffer = self.inner.create(buffer);
+        self.progress.add_rows(self.rows);
+        buffer
+    }
+
+    fn header(&self, buffer: Vec<u8>) -> Vec<u8> {
+        self.inner.header(buffer)
+    }
+
+    fn footer(&self, buffer: Vec<u8>) -> Vec<u8> {
+        self.inner.footer(buffer)
+    }
+
+    fn separator(&self) -> &'static [u8] {
+        self.inner.separator()
+    }
+}
+
+/// A writer or [`Sink`] that counts the bytes written to it in a
+/// [`TableProgress`]
+pub struct Counted<W> {
+    inner: W,
+    progress: Arc<TableProgress>,
+}
+
+impl<W> Counted<W> {
+    /// Count the bytes written to `inner`
+    pub fn new(inner: W, progress: &Arc<TableProgress>) -> Self {
+        Self {
+            inner,
+            progress: Arc::clone(progress),
+        }
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.progress.add_bytes(written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + IntoSize> IntoSize for Counted<W> {
+    fn into_size(self) -> Result<usize, io::Error> {
+        self.inner.into_size()
+    }
+}
+
+impl<S: Sink> Sink for Counted<S> {
+    fn sink(&mut self, buffer: &[u8]) -> Result<(), io::Error> {
+        self.inner.sink(buffer)?;
+        self.progress.add_bytes(buffer.len() as u64);
+        Ok(())
+    }
+
+    fn flush(self) -> Result<(), io::Error> {
+        self.inner.flush()
+    }
+}
+
+impl<S: PartitionSink> PartitionSink for Counted<S> {
+    type Writer = Counted<S::Writer>;
+
+    fn create(&self, partition: &str) -> io::Result<Self::Writer> {
+        Ok(Counted::new(self.inner.create(partition)?, &self.progress))
+    }
+
+    fn commit(&self, partition: &str) -> io::Result<()> {
+        self.inner.commit(partition)
+    }
+
+    fn write_file(&self, name: &str, contents: &[u8]) -> io::Result<()> {
+        self.inner.write_file(name, contents)
+    }
+}
+
+/// Reports the progress of the tables to stderr on a thread until
+/// [`Self::finish`] is called
+pub struct Reporter {
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl Reporter {
+    /// Start reporting in `format`, if it reports anything
+    pub fn start(format: ProgressFormat) -> Option<Self> {
+        let json = match format {
+            ProgressFormat::Auto if io::stderr().is_terminal() => false,
+            ProgressFormat::Auto | ProgressFormat::None => return None,
+            ProgressFormat::Bar => false,
+            ProgressFormat::Json => true,
+        };
+        let (stop, stopped) = mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let interval = if json {
+                Duration::from_secs(1)
+            } else {
+                Duration::from_millis(200)
+            };
+            let mut drawn = 0;
+            let mut reported = BTreeSet::new();
+            loop {
+                let done = !matches!(
+                    stopped.recv_timeout(interval),
+                    Err(RecvTimeoutError::Timeout)
+                );
+                let statuses = statuses();
+                // stderr is only used for warnings meanwhile, so ignore errors
+                let mut stderr = io::stderr().lock();
+                if json {
+                    // complete tables are only reported once
+                    for status in &statuses {
+                        if !reported.contains(&status.table) {
+                            let _ = writeln!(stderr, "{}", status.to_json());
+                        }
+                        if status.done {
+                            reported.insert(status.table);
+                        }
+                    }
+                } else {
+                    // move back up to redraw the bars in place
+                    let mut out = String::new();
+                    if drawn > 0 {
+                        let _ = write!(out, "\x1b[{drawn}A");
+                    }
+                    for status in &statuses {
+                        let _ = writeln!(out, "\x1b[2K{}", status.to_bar());
+                    }
+                    let _ = stderr.write_all(out.as_bytes());
+                    drawn = statuses.len();
+                }
+                let _ = stderr.flush();
+                if done {
+                    return;
+                }
+            }
+        });
+        Some(Self { stop, thread })
+    }
+
+    /// Report the final counts and stop reporting
+    pub fn finish(self) {
+        let _ = self.stop.send(());
+        let _ = self.thread.join();
+    }
+}
+
+/// Width of the bars, in characters
+const BAR_WIDTH: usize = 30;
+
+impl TableStatus {
+    /// Fraction of the rows generated, between 0 and 1
+    fn fraction(&self) -> f64 {
+        if self.total_rows == 0 {
+            1.0
+        } else {
+            (self.rows as f64 / self.total_rows as f64).min(1.0)
+        }
+    }
+
+    pub fn rows_per_sec(&self) -> f64 {
+        self.rows as f64 / self.elapsed.as_secs_f64().max(1e-3)
+    }
+
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64().max(1e-3)
+    }
+
+    /// Estimated time until all the rows are generated at the current rate
+    fn eta(&self) -> Option<Duration> {
+        let remaining = self.total_rows.saturating_sub(self.rows);
+        if self.done || remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rows_per_sec();
+        (rate > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / rate))
+    }
+
+    /// Return the status as a JSON line
+    fn to_json(&self) -> String {
+        let eta = self.eta().map(|eta| eta.as_secs_f64());
+        json!({
+            "table": self.table.name(),
+            "rows": self.rows,
+            "total_rows": self.total_rows,
+            "bytes": self.bytes,
+            "percent": 100.0 * self.fraction(),
+            "rows_per_sec": self.rows_per_sec().round(),
+            "bytes_per_sec": self.bytes_per_sec().round(),
+            "elapsed_secs": self.elapsed.as_secs_f64(),
+            "eta_secs": eta,
+            "done": self.done,
+        })
+        .to_string()
+    }
+
+    /// Return the status as a line with a bar
+    fn to_bar(&self) -> String {
+        let filled = (self.fraction() * BAR_WIDTH as f64) as usize;
+        let bar = format!("{}{}", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled));
+        let eta = match self.eta() {
+            _ if self.done => format!("done in {}", duration(self.elapsed)),
+            Some(eta) => format!("ETA {}", duration(eta)),
+            None => "ETA -".to_string(),
+        };
+        format!(
+            "{:<10} [{bar}] {:>5.1}% {}/{} rows {} rows/s {}/s {eta}",
+            self.table.name(),
+            100.0 * self.fraction(),
+            count(self.rows as f64),
+            count(self.total_rows as f64),
+            count(self.rows_per_sec()),
+            bytes(self.bytes_per_sec()),
+        )
+    }
+}
+
+/// Format a number of things with a K, M or G suffix, e.g. `1.5M`
+fn count(n: f64) -> String {
+    match n {
+        n if n >= 1e9 => format!("{:.2}G", n / 1e9),
+        n if n >= 1e6 => format!("{:.2}M", n / 1e6),
+        n if n >= 1e3 => format!("{:.1}K", n / 1e3),
+        n => format!("{n:.0}"),
+    }
+}
+
+/// Format a number of bytes with a binary suffix, e.g. `12.3 MiB`
+fn bytes(n: f64) -> String {
+    const KIB: f64 = 1024.0;
+    match n {
+        n if n >= KIB * KIB * KIB => format!("{:.1} GiB", n / (KIB * KIB * KIB)),
+        n if n >= KIB * KIB => format!("{:.1} MiB", n / (KIB * KIB)),
+        n if n >= KIB => format!("{:.1} KiB", n / KIB),
+        n => format!("{n:.0} B"),
+    }
+}
+
+/// Format a duration as `HH:MM:SS`
+fn duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn status(rows: u64) -> TableStatus {
+        TableStatus {
+            table: Table::Trip,
+            total_rows: 6_000_000,
+            rows,
+            bytes: 300 << 20,
+            elapsed: Duration::from_secs(10),
+            done: rows == 6_000_000,
+        }
+    }
+
+    #[test]
+    fn test_status() {
+        let status = status(1_500_000);
+        assert_eq!(status.fraction(), 0.25);
+        assert_eq!(status.eta(), Some(Duration::from_secs(30)));
+        assert_eq!(
+            status.to_bar(),
+            format!(
+                "trip       [{}{}]  25.0% 1.50M/6.00M rows 150.0K rows/s 30.0 MiB/s ETA 00:00:30",
+                "#".repeat(7),
+                "-".repeat(23)
+            )
+        );
+
+        let json: Value = serde_json::from_str(&status.to_json()).unwrap();
+        assert_eq!(json["table"], "trip");
+        assert_eq!(json["percent"], 25.0);
+        assert_eq!(json["rows_per_sec"], 150000.0);
+        assert_eq!(json["eta_secs"], 30.0);
+        assert_eq!(json["done"], false);
+    }
+
+    #[test]
+    fn test_done() {
+        let done = status(6_000_000);
+        assert_eq!(done.eta(), Some(Duration::ZERO));
+        assert!(done.to_bar().ends_with("done in 00:00:10"));
+        // no rows yet, so no rate to estimate with
+        assert_eq!(status(0).eta(), None);
+        assert!(status(0).to_bar().ends_with("ETA -"));
+    }
+}
@@ -0,0 +1,73 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `queries` command: print the benchmark queries
+
+use clap::Args;
+use spatialbench::workload::{Dialect, Query, QueryParameters};
+use std::io::{self, Write};
+
+/// Print the benchmark queries with their substitution parameters
+#[derive(Debug, Args)]
+pub struct QueriesArgs {
+    /// SQL dialect: sedona, postgis, duckdb or bigquery
+    #[arg(short, long, default_value = "sedona")]
+    dialect: Dialect,
+
+    /// Scale factor of the data the queries are run against
+    #[arg(short, long, default_value_t = 1.)]
+    scale_factor: f64,
+
+    /// Seed to derive the substitution parameters from
+    ///
+    /// Query windows and center points are moved to locations with data, and
+    /// row counts and distances are varied, the same way for the same seed
+    /// and scale factor. If not specified, the parameters of the published
+    /// queries are used.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Which queries to print, e.g. `Q1,Q3` (default: all)
+    #[arg(short, long, value_delimiter = ',')]
+    queries: Option<Vec<Query>>,
+}
+
+impl QueriesArgs {
+    /// Print the queries to stdout, separated by empty lines
+    pub fn run(&self) -> io::Result<()> {
+        if !(self.scale_factor.is_finite() && self.scale_factor > 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--scale-factor must be positive, got {}", self.scale_factor),
+            ));
+        }
+
+        let queries = self.queries.as_deref().unwrap_or(&Query::ALL);
+        let mut stdout = io::stdout().lock();
+        for (i, &query) in queries.iter().enumerate() {
+            let params = match self.seed {
+                Some(seed) => QueryParameters::generate(query, self.scale_factor, seed),
+                None => QueryParameters::reference(query),
+            };
+            if i > 0 {
+                writeln!(stdout)?;
+            }
+            write!(stdout, "{}", params.sql(self.dialect))?;
+        }
+        stdout.flush()
+    }
+}
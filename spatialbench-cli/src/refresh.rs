@@ -0,0 +1,168 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `refresh` command: write refresh sets of trips to insert and delete
+
+use arrow::array::{Int64Array, RecordBatch};
+use arrow::datatypes::{DataType, Field, Schema};
+use clap::{Args, ValueEnum};
+use log::info;
+use parquet::arrow::ArrowWriter;
+use spatialbench::csv::TripCsv;
+use spatialbench::generators::{RowCounts, TripGenerator};
+use spatialbench::refresh::{RefreshSet, MAX_REFRESHES};
+use spatialbench_arrow::{RecordBatchIterator, TripArrow};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Write refresh sets of the trip table, trips to insert and the keys of
+/// trips to delete, like the refresh functions RF1 and RF2 of TPC-H
+#[derive(Debug, Args)]
+pub struct RefreshArgs {
+    /// Scale factor of the dataset the refresh sets apply to
+    #[arg(short, long, default_value_t = 1.)]
+    scale_factor: f64,
+
+    /// Seed the dataset was generated with
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Number of refresh sets, at most 1000
+    #[arg(short, long, default_value_t = 1)]
+    count: i64,
+
+    /// Directory to write `trip.insert.<n>` and `trip.delete.<n>` files to
+    #[arg(short, long, default_value = ".")]
+    output_dir: PathBuf,
+
+    /// Output format: Parquet or CSV with a header
+    #[arg(short, long, default_value = "parquet")]
+    format: RefreshFormat,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum RefreshFormat {
+    Parquet,
+    Csv,
+}
+
+impl RefreshFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            RefreshFormat::Parquet => "parquet",
+            RefreshFormat::Csv => "csv",
+        }
+    }
+}
+
+impl RefreshArgs {
+    /// Write the refresh sets to the output directory
+    pub fn run(&self) -> io::Result<()> {
+        if !(self.scale_factor.is_finite() && self.scale_factor > 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--scale-factor must be positive, got {}", self.scale_factor),
+            ));
+        }
+        if !(1..=MAX_REFRESHES).contains(&self.count) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "--count must be in [1, {MAX_REFRESHES}], got {}",
+                    self.count
+                ),
+            ));
+        }
+        fs::create_dir_all(&self.output_dir)?;
+        let trip_count = RowCounts::default().trip_count(self.scale_factor);
+        for refresh in 1..=self.count {
+            let set = RefreshSet::new(trip_count, refresh);
+            let extension = self.format.extension();
+            let inserts = self
+                .output_dir
+                .join(format!("trip.insert.{refresh}.{extension}"));
+            let deletes = self
+                .output_dir
+                .join(format!("trip.delete.{refresh}.{extension}"));
+            self.write_inserts(&set, &inserts)?;
+            self.write_deletes(&set, &deletes)?;
+            info!(
+                "Wrote refresh set {refresh} of {} trips to {} and {}",
+                set.size(),
+                inserts.display(),
+                deletes.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the generator of the trips inserted by `set`
+    fn inserted_trips(&self, set: &RefreshSet) -> TripGenerator {
+        TripGenerator::new(self.scale_factor, 1, 1)
+            .with_seed(self.seed)
+            .with_keys(set.inserted_keys())
+    }
+
+    fn write_inserts(&self, set: &RefreshSet, path: &Path) -> io::Result<()> {
+        let generator = self.inserted_trips(set);
+        match self.format {
+            RefreshFormat::Parquet => {
+                let batches = TripArrow::new(generator);
+                let schema = Arc::clone(batches.schema());
+                let mut writer = ArrowWriter::try_new(File::create(path)?, schema, None)?;
+                for batch in batches {
+                    writer.write(&batch)?;
+                }
+                writer.close()?;
+            }
+            RefreshFormat::Csv => {
+                let mut out = BufWriter::new(File::create(path)?);
+                writeln!(out, "{}", TripCsv::header())?;
+                for trip in generator.iter() {
+                    writeln!(out, "{}", TripCsv::new(trip))?;
+                }
+                out.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_deletes(&self, set: &RefreshSet, path: &Path) -> io::Result<()> {
+        match self.format {
+            RefreshFormat::Parquet => {
+                let schema = Schema::new(vec![Field::new("t_tripkey", DataType::Int64, false)]);
+                let keys = Int64Array::from_iter_values(set.deleted_keys());
+                let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(keys)])
+                    .expect("the column matches the schema");
+                let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), None)?;
+                writer.write(&batch)?;
+                writer.close()?;
+            }
+            RefreshFormat::Csv => {
+                let mut out = BufWriter::new(File::create(path)?);
+                writeln!(out, "t_tripkey")?;
+                for key in set.deleted_keys() {
+                    writeln!(out, "{key}")?;
+                }
+                out.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
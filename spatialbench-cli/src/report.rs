@@ -0,0 +1,359 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `report` command: score the results of `run`
+//!
+//! Each run is summarized by the median latency of each query and scored
+//! with the same metrics, so published numbers are comparable:
+//!
+//! * the geometric mean of the median latencies of the valid queries
+//! * the power, `3600 * SF / geometric mean in seconds`: the queries per
+//!   hour of a single stream, scaled by the size of the data as in TPC-H
+//! * the throughput, `3600 * SF * timed runs / their total seconds`, the
+//!   seconds from the start to the end of the streams of a throughput test
+//!
+//! A query is valid if it ran without error and returned the expected rows.
+
+use crate::driver::{QueryResult, RunResults};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Score the results of one or more runs, side by side
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// Results JSON files written by `run`
+    #[arg(required = true)]
+    results: Vec<PathBuf>,
+
+    /// Output format: markdown, html or json
+    #[arg(short, long, default_value = "markdown")]
+    format: ReportFormat,
+
+    /// File to write the report to (default: stdout)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Formats to write the report in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+/// The summary of a run
+#[derive(Debug, Serialize)]
+struct RunSummary {
+    /// The results file
+    file: String,
+    engine: String,
+    dialect: String,
+    scale_factor: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    queries: Vec<QuerySummary>,
+    /// Number of valid queries, the ones the scores are computed from
+    valid_queries: usize,
+    geomean_ms: Option<f64>,
+    power: Option<f64>,
+    throughput: Option<f64>,
+}
+
+/// The summary of a query of a run
+#[derive(Debug, Serialize)]
+struct QuerySummary {
+    query: String,
+    median_ms: Option<f64>,
+    min_ms: Option<f64>,
+    max_ms: Option<f64>,
+    valid: bool,
+}
+
+impl ReportArgs {
+    /// Write the report of the results
+    pub fn run(&self) -> io::Result<()> {
+        let summaries = self
+            .results
+            .iter()
+            .map(|path| {
+                let json = fs::read_to_string(path).map_err(|e| {
+                    io::Error::new(e.kind(), format!("Failed to read {}: {e}", path.display()))
+                })?;
+                let results: RunResults = serde_json::from_str(&json).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} is not a results file of run: {e}", path.display()),
+                    )
+                })?;
+                Ok(summarize(path.display().to_string(), &results))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let report = match self.format {
+            ReportFormat::Markdown => markdown(&summaries),
+            ReportFormat::Html => html(&summaries),
+            ReportFormat::Json => serde_json::to_string_pretty(&summaries)? + "\n",
+        };
+        match &self.output {
+            Some(path) => fs::write(path, report),
+            None => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(report.as_bytes())?;
+                stdout.flush()
+            }
+        }
+    }
+}
+
+/// Summarize and score `results`
+fn summarize(file: String, results: &RunResults) -> RunSummary {
+    let queries: Vec<_> = results.queries.iter().map(summarize_query).collect();
+    let medians: Vec<f64> = queries
+        .iter()
+        .filter(|q| q.valid)
+        .filter_map(|q| q.median_ms)
+        .collect();
+    let geomean_ms = geometric_mean(&medians);
+    let power = geomean_ms.map(|ms| 3600.0 * results.scale_factor / (ms / 1000.0));
+
+    let timed: Vec<f64> = results
+        .queries
+        .iter()
+        .filter(|q| q.is_valid())
+        .flat_map(|q| q.latencies_ms.iter().copied())
+        .collect();
+    // the streams of a throughput test run at the same time
+    let total_secs = match &results.throughput {
+        Some(throughput) => throughput.elapsed_ms / 1000.0,
+        None => timed.iter().sum::<f64>() / 1000.0,
+    };
+    let throughput =
+        (total_secs > 0.0).then(|| 3600.0 * results.scale_factor * timed.len() as f64 / total_secs);
+
+    RunSummary {
+        file,
+        engine: results.engine.clone(),
+        dialect: results.dialect.clone(),
+        scale_factor: results.scale_factor,
+        seed: results.seed,
+        valid_queries: medians.len(),
+        queries,
+        geomean_ms,
+        power,
+        throughput,
+    }
+}
+
+fn summarize_query(result: &QueryResult) -> QuerySummary {
+    let mut latencies = result.latencies_ms.clone();
+    latencies.sort_by(f64::total_cmp);
+    QuerySummary {
+        query: result.query.clone(),
+        median_ms: median(&latencies),
+        min_ms: latencies.first().copied(),
+        max_ms: latencies.last().copied(),
+        valid: result.is_valid() && !latencies.is_empty(),
+    }
+}
+
+/// Return the median of the sorted `values`
+fn median(values: &[f64]) -> Option<f64> {
+    let n = values.len();
+    match n {
+        0 => None,
+        _ if n % 2 == 1 => Some(values[n / 2]),
+        _ => Some((values[n / 2 - 1] + values[n / 2]) / 2.0),
+    }
+}
+
+/// Return the geometric mean of the positive `values`
+fn geometric_mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() || values.iter().any(|&v| v <= 0.0) {
+        return None;
+    }
+    let log_sum: f64 = values.iter().map(|v| v.ln()).sum();
+    Some((log_sum / values.len() as f64).exp())
+}
+
+/// Return the rows of the report: a header, one row per query in the order
+/// they first appear in the runs, and the scores
+fn rows(summaries: &[RunSummary]) -> Vec<Vec<String>> {
+    let mut header = vec!["".to_string()];
+    header.extend(
+        summaries
+            .iter()
+            .map(|s| format!("{} ({}, SF {})", s.engine, s.dialect, s.scale_factor)),
+    );
+    let mut rows = vec![header];
+
+    let mut queries: Vec<&str> = vec![];
+    for summary in summaries {
+        for query in &summary.queries {
+            if !queries.contains(&query.query.as_str()) {
+                queries.push(&query.query);
+            }
+        }
+    }
+    for name in queries {
+        let mut row = vec![name.to_string()];
+        for summary in summaries {
+            let cell = match summary.queries.iter().find(|q| q.query == name) {
+                None => "".to_string(),
+                Some(QuerySummary {
+                    median_ms: Some(ms),
+                    valid: true,
+                    ..
+                }) => format!("{ms:.1} ms"),
+                Some(_) => "invalid".to_string(),
+            };
+            row.push(cell);
+        }
+        rows.push(row);
+    }
+
+    let score = |name: &str, value: fn(&RunSummary) -> String| {
+        let mut row = vec![name.to_string()];
+        row.extend(summaries.iter().map(value));
+        row
+    };
+    rows.push(score("Valid queries", |s| {
+        format!("{} / {}", s.valid_queries, s.queries.len())
+    }));
+    rows.push(score("Geometric mean", |s| {
+        s.geomean_ms
+            .map_or("".to_string(), |ms| format!("{ms:.1} ms"))
+    }));
+    rows.push(score("Power", |s| {
+        s.power.map_or("".to_string(), |p| format!("{p:.1}"))
+    }));
+    rows.push(score("Throughput", |s| {
+        s.throughput.map_or("".to_string(), |t| format!("{t:.1}"))
+    }));
+    rows
+}
+
+/// Return the report as a Markdown table
+fn markdown(summaries: &[RunSummary]) -> String {
+    let rows = rows(summaries);
+    let mut out = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        let _ = writeln!(out, "| {} |", row.join(" | "));
+        if i == 0 {
+            let _ = writeln!(out, "|{}", "---|".repeat(row.len()));
+        }
+    }
+    out
+}
+
+/// Return the report as an HTML table
+fn html(summaries: &[RunSummary]) -> String {
+    let rows = rows(summaries);
+    let mut out = String::from("<table>\n");
+    for (i, row) in rows.iter().enumerate() {
+        let tag = if i == 0 { "th" } else { "td" };
+        out.push_str("  <tr>");
+        for cell in row {
+            let _ = write!(out, "<{tag}>{}</{tag}>", escape(cell));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+    out
+}
+
+/// Escape `text` for HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(query: &str, latencies_ms: Vec<f64>, error: Option<&str>) -> QueryResult {
+        QueryResult {
+            query: query.to_string(),
+            latencies_ms,
+            rows: Some(1),
+            expected_rows: None,
+            error: error.map(str::to_string),
+        }
+    }
+
+    fn results() -> RunResults {
+        RunResults {
+            engine: "postgres".to_string(),
+            dialect: "postgis".to_string(),
+            scale_factor: 10.0,
+            seed: None,
+            version: "0.1.0".to_string(),
+            started: 0,
+            queries: vec![
+                query("Q1", vec![300.0, 100.0, 200.0], None),
+                query("Q2", vec![400.0, 400.0], None),
+                query("Q3", vec![], Some("syntax error")),
+            ],
+            streams: vec![],
+            throughput: None,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let summary = summarize("results.json".to_string(), &results());
+        assert_eq!(summary.queries[0].median_ms, Some(200.0));
+        assert_eq!(summary.queries[0].min_ms, Some(100.0));
+        assert!(!summary.queries[2].valid);
+        assert_eq!(summary.valid_queries, 2);
+        // sqrt(200 * 400)
+        let geomean = summary.geomean_ms.unwrap();
+        assert!((geomean - 282.84).abs() < 0.01, "{geomean}");
+        let power = summary.power.unwrap();
+        assert!((power - 3600.0 * 10.0 / (geomean / 1000.0)).abs() < 0.01);
+        // 5 runs in 1.4 seconds
+        let throughput = summary.throughput.unwrap();
+        assert!((throughput - 3600.0 * 10.0 * 5.0 / 1.4).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_markdown() {
+        let summary = summarize("results.json".to_string(), &results());
+        let report = markdown(&[summary]);
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines[0], "|  | postgres (postgis, SF 10) |");
+        assert_eq!(lines[1], "|---|---|");
+        assert_eq!(lines[2], "| Q1 | 200.0 ms |");
+        assert_eq!(lines[4], "| Q3 | invalid |");
+        assert_eq!(lines[5], "| Valid queries | 2 / 3 |");
+        assert_eq!(lines[6], "| Geometric mean | 282.8 ms |");
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[1.0, 2.0, 10.0]), Some(2.0));
+        assert_eq!(median(&[1.0, 2.0, 4.0, 10.0]), Some(3.0));
+    }
+}
@@ -0,0 +1,137 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Override the number of rows of a table (`--rows`)
+
+use spatialbench::generators::RowCounts;
+use spatialbench_arrow::Table;
+
+/// The number of rows to generate for one table, see [`parse_rows`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowCount {
+    /// The overridden table
+    pub table: Table,
+    /// The number of rows of the whole table
+    pub rows: i64,
+}
+
+/// Parse a `--rows` value such as `building=10000000`
+///
+/// `_` may be used to separate thousands, e.g. `building=10_000_000`.
+pub fn parse_rows(value: &str) -> Result<RowCount, String> {
+    let Some((table, rows)) = value.split_once('=') else {
+        return Err(format!(
+            "expected <table>=<rows>, e.g. building=10000000, got '{value}'"
+        ));
+    };
+    let table: Table = table.trim().parse()?;
+    match table {
+        Table::Trajectory => {
+            return Err("table trajectory has one row per trip, use --rows trip=<rows>".to_string())
+        }
+        Table::Road | Table::Raster => {
+            return Err(format!(
+                "the number of rows of table {table} only depends on the scale factor"
+            ))
+        }
+        Table::Landmass => return Err("table landmass has one row per continent".to_string()),
+        Table::Country | Table::Region | Table::City => {
+            return Err(format!(
+                "the number of rows of table {table} is fixed by the administrative hierarchy"
+            ))
+        }
+        _ => {}
+    }
+    let rows: i64 = rows
+        .trim()
+        .replace('_', "")
+        .parse()
+        .map_err(|_| format!("invalid number of rows for table {table}: '{rows}'"))?;
+    if rows < 1 {
+        return Err(format!(
+            "table {table} must have at least one row, got {rows}"
+        ));
+    }
+    Ok(RowCount { table, rows })
+}
+
+/// Combine the `--rows` of several tables, each table may only be listed once
+pub fn row_counts(rows: &[RowCount]) -> Result<RowCounts, String> {
+    let mut row_counts = RowCounts::default();
+    for row_count in rows {
+        let count = match row_count.table {
+            Table::Vehicle => &mut row_counts.vehicle,
+            Table::Driver => &mut row_counts.driver,
+            Table::Customer => &mut row_counts.customer,
+            Table::Trip => &mut row_counts.trip,
+            Table::Building => &mut row_counts.building,
+            Table::Poi => &mut row_counts.poi,
+            Table::PointCloud => &mut row_counts.pointcloud,
+            Table::Trajectory
+            | Table::Road
+            | Table::Landmass
+            | Table::Country
+            | Table::Region
+            | Table::City
+            | Table::Raster => {
+                unreachable!("checked by parse_rows")
+            }
+        };
+        if count.replace(row_count.rows).is_some() {
+            return Err(format!(
+                "--rows is given more than once for table {}",
+                row_count.table
+            ));
+        }
+    }
+    Ok(row_counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rows() {
+        let rows = [
+            parse_rows("building=10_000_000").unwrap(),
+            parse_rows("trip = 1000000").unwrap(),
+        ];
+        assert_eq!(
+            row_counts(&rows).unwrap(),
+            RowCounts {
+                trip: Some(1_000_000),
+                building: Some(10_000_000),
+                ..Default::default()
+            }
+        );
+        let err = row_counts(&[rows[0], rows[0]]).unwrap_err();
+        assert_eq!(err, "--rows is given more than once for table building");
+
+        for (value, error) in [
+            ("building", "expected <table>=<rows>"),
+            ("zone=10", "Invalid table name zone"),
+            ("trajectory=10", "table trajectory has one row per trip"),
+            ("road=10", "the number of rows of table road only"),
+            ("trip=many", "invalid number of rows for table trip: 'many'"),
+            ("trip=0", "table trip must have at least one row, got 0"),
+        ] {
+            let err = parse_rows(value).unwrap_err();
+            assert!(err.starts_with(error), "{value}: {err}");
+        }
+    }
+}
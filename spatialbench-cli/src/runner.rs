@@ -17,21 +17,48 @@
 
 //! [`PlanRunner`] for running [`OutputPlan`]s.
 
+use crate::checkpoint::{Checkpoint, CheckpointSink};
+use crate::column_stats::Profiled;
 use crate::csv::*;
-use crate::generate::{generate_in_chunks, Source};
+use crate::file_writer::FileWriter;
+use crate::flatgeobuf::generate_flatgeobuf;
+use crate::generate::{generate_in_chunks, Sink, Source};
+use crate::geojson::{GeoJsonLayout, GeoJsonSource};
+use crate::hive::{
+    generate_partitioned_parquet, success_marker, HivePartitioner, LocalPartitionSink,
+    ObjectStorePartitionSink, PartitionSink, PartitionedParquetOptions, Partitioner,
+};
+use crate::ipc::generate_arrow_ipc;
+use crate::key_sort::{KeySorted, RowOrder, SortChecked, SortError};
+use crate::manifest::{Bounded, Checksummed};
+use crate::null::*;
+use crate::object_store_writer::ObjectStoreWriter;
 use crate::output_plan::{OutputLocation, OutputPlan};
 use crate::parquet::generate_parquet;
+use crate::postgres::{self, PostgresSink, PostgresSource};
+use crate::progress::{self, Counted, PlanProgress, Progressed, ProgressedSource};
+use crate::spatial_partition::{SpatialPartitioner, MANIFEST_FILE};
 use crate::tbl::*;
+use crate::tee::{self, Tee};
+use crate::throttle::Throttled;
+use crate::wkb_variant::{WkbEncoded, WkbVariant};
 use crate::{OutputFormat, Table, WriterSink};
 use log::{debug, info};
 use spatialbench::generators::{
-    BuildingGenerator, CustomerGenerator, DriverGenerator, TripGenerator, VehicleGenerator,
+    BuildingGenerator, CityGenerator, CountryGenerator, CustomerGenerator, DriverGenerator,
+    LandmassGenerator, PoiGenerator, PointCloudGenerator, RasterGenerator, RegionGenerator,
+    RoadGenerator, RowCounts, TrajectoryGenerator, TripGenerator, VehicleGenerator,
 };
+use spatialbench::spatial::dimensions::dimensions;
 use spatialbench_arrow::{
-    BuildingArrow, CustomerArrow, DriverArrow, RecordBatchIterator, TripArrow, VehicleArrow,
+    BuildingArrow, CityArrow, CountryArrow, CustomerArrow, DriverArrow, LandmassArrow, PoiArrow,
+    PointCloudArrow, RasterArrow, RecordBatchIterator, RegionArrow, RoadArrow, TrajectoryArrow,
+    TripArrow, VehicleArrow,
 };
 use std::io;
 use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
 use tokio::task::{JoinError, JoinSet};
 
 /// Runs multiple [`OutputPlan`]s in parallel, managing the number of threads
@@ -66,11 +93,19 @@ impl PlanRunner {
             let b_cnt = b.chunk_count();
             a_cnt.cmp(&b_cnt)
         });
+        // count the rows of all the plans before any of them run
+        let mut plans: Vec<_> = plans
+            .into_iter()
+            .map(|plan| {
+                let progress = PlanProgress::new(&plan);
+                (plan, progress)
+            })
+            .collect();
 
         // Do the actual work in parallel, using a worker queue
         let mut worker_queue = WorkerQueue::new(num_threads);
-        while let Some(plan) = plans.pop() {
-            worker_queue.schedule_plan(plan).await?;
+        while let Some((plan, progress)) = plans.pop() {
+            worker_queue.schedule_plan(plan, progress).await?;
         }
         worker_queue.join_all().await
     }
@@ -116,7 +151,11 @@ impl WorkerQueue {
     /// Note this algorithm does not guarantee that all threads are always busy,
     /// but it should be good enough for most cases. For best thread utilization
     /// spawn the largest plans first.
-    pub async fn schedule_plan(&mut self, plan: OutputPlan) -> io::Result<()> {
+    pub async fn schedule_plan(
+        &mut self,
+        plan: OutputPlan,
+        progress: Arc<PlanProgress>,
+    ) -> io::Result<()> {
         debug!("scheduling plan {plan}");
         loop {
             if self.available_threads == 0 {
@@ -151,7 +190,7 @@ impl WorkerQueue {
             debug!("Spawning plan {plan} with {num_plan_threads} threads");
 
             self.join_set
-                .spawn(async move { run_plan(plan, num_plan_threads).await });
+                .spawn(async move { run_plan(plan, num_plan_threads, progress).await });
             self.available_threads -= num_plan_threads;
             return Ok(());
         }
@@ -173,29 +212,71 @@ fn task_result<T>(result: Result<io::Result<T>, JoinError>) -> io::Result<T> {
     result.map_err(|e| io::Error::other(format!("Task Panic: {e}")))?
 }
 
-/// Run a single [`OutputPlan`]
-async fn run_plan(plan: OutputPlan, num_threads: usize) -> io::Result<usize> {
-    match plan.table() {
-        Table::Building => run_building_plan(plan, num_threads).await,
-        Table::Vehicle => run_vehicle_plan(plan, num_threads).await,
-        Table::Driver => run_driver_plan(plan, num_threads).await,
-        Table::Customer => run_customer_plan(plan, num_threads).await,
-        Table::Trip => run_trip_plan(plan, num_threads).await,
+/// Run a single [`OutputPlan`], counting its rows in `progress`
+async fn run_plan(
+    plan: OutputPlan,
+    num_threads: usize,
+    progress: Arc<PlanProgress>,
+) -> io::Result<usize> {
+    progress.start();
+    let p = Arc::clone(&progress);
+    let threads = match plan.table() {
+        Table::Building => run_building_plan(plan, num_threads, p).await,
+        Table::Vehicle => run_vehicle_plan(plan, num_threads, p).await,
+        Table::Driver => run_driver_plan(plan, num_threads, p).await,
+        Table::Customer => run_customer_plan(plan, num_threads, p).await,
+        Table::Trip => run_trip_plan(plan, num_threads, p).await,
+        Table::Trajectory => run_trajectory_plan(plan, num_threads, p).await,
+        Table::Road => run_road_plan(plan, num_threads, p).await,
+        Table::Landmass => run_landmass_plan(plan, num_threads, p).await,
+        Table::Country => run_country_plan(plan, num_threads, p).await,
+        Table::Region => run_region_plan(plan, num_threads, p).await,
+        Table::City => run_city_plan(plan, num_threads, p).await,
+        Table::Poi => run_poi_plan(plan, num_threads, p).await,
+        Table::Raster => run_raster_plan(plan, num_threads, p).await,
+        Table::PointCloud => run_pointcloud_plan(plan, num_threads, p).await,
         Table::Zone => todo!("Zone table is not supported in PlanRunner"),
-    }
+    }?;
+    progress.finish();
+    Ok(threads)
 }
 
 /// Writes a CSV/TSV output from the sources
-async fn write_file<I>(plan: OutputPlan, num_threads: usize, sources: I) -> Result<(), io::Error>
+/// Writes `sources` to `sink`, a new file that will be renamed to `path`,
+/// recording its checksum and copying it to the mirrors of `plan`
+async fn write_new_file<S, I>(
+    plan: &OutputPlan,
+    path: &Path,
+    sink: S,
+    sources: I,
+    num_threads: usize,
+    sort_error: &SortError,
+) -> Result<(), io::Error>
+where
+    S: Sink + 'static,
+    I: Iterator<Item: Source> + 'static,
+{
+    let sink = Checksummed::new(sink, Some(path.display().to_string()));
+    let sink = SortChecked::new(Tee::new(sink, tee::mirrors(plan)?), sort_error);
+    generate_in_chunks(sink, sources, num_threads).await
+}
+
+async fn write_file<I>(
+    plan: OutputPlan,
+    num_threads: usize,
+    sources: I,
+    sort_error: &SortError,
+) -> Result<(), io::Error>
 where
     I: Iterator<Item: Source> + 'static,
 {
+    let progress = progress::table(plan.table());
     // Since generate_in_chunks already buffers, there is no need to buffer
     // again (aka don't use BufWriter here)
     match plan.output_location() {
         OutputLocation::Stdout => {
-            let sink = WriterSink::new(io::stdout());
-            generate_in_chunks(sink, sources, num_threads).await
+            let sink = Counted::new(WriterSink::new(io::stdout()), &progress);
+            generate_in_chunks(SortChecked::new(sink, sort_error), sources, num_threads).await
         }
         OutputLocation::File(path) => {
             // if the output already exists, skip running
@@ -205,11 +286,108 @@ where
             }
             // write to a temp file and then rename to avoid partial files
             let temp_path = path.with_extension("inprogress");
-            let file = std::fs::File::create(&temp_path).map_err(|err| {
-                io::Error::other(format!("Failed to create {temp_path:?}: {err}"))
+            // chunks separated by a delimiter (e.g. GeoJSON) can not be resumed
+            // as whether a separator is needed depends on the earlier chunks
+            let mut sources = sources.peekable();
+            let resume = plan.resume()
+                && sources
+                    .peek()
+                    .is_some_and(|source| source.separator().is_empty());
+            if resume {
+                // record progress so that generation can be resumed
+                let description = format!("{plan} ({})", plan.generation_plan());
+                let (sink, completed_chunks) =
+                    CheckpointSink::try_new(&temp_path, &description, true)?;
+                let sink = Counted::new(sink, &progress);
+                let sources = sources.skip(completed_chunks);
+                if completed_chunks == 0 {
+                    write_new_file(&plan, path, sink, sources, num_threads, sort_error).await?;
+                } else {
+                    // the manifest reads back the checksum of a resumed file
+                    let sink = SortChecked::new(sink, sort_error);
+                    generate_in_chunks(sink, sources, num_threads).await?;
+                }
+            } else {
+                let file = std::fs::File::create(&temp_path).map_err(|err| {
+                    io::Error::other(format!("Failed to create {temp_path:?}: {err}"))
+                })?;
+                let sink = Counted::new(WriterSink::new(file), &progress);
+                write_new_file(&plan, path, sink, sources, num_threads, sort_error).await?;
+            }
+            // rename the temp file to the final path
+            std::fs::rename(&temp_path, path).map_err(|e| {
+                io::Error::other(format!(
+                    "Failed to rename {temp_path:?} to {path:?} file: {e}"
+                ))
             })?;
-            let sink = WriterSink::new(file);
-            generate_in_chunks(sink, sources, num_threads).await?;
+            Checkpoint::remove(&temp_path)
+        }
+        OutputLocation::Postgres(_) => {
+            unreachable!("PostgreSQL output is written by write_postgres")
+        }
+        OutputLocation::Null => unreachable!("null output is written by write_null"),
+        OutputLocation::ObjectStore(url) => {
+            // objects only become visible once the upload completes, so there
+            // is no need for a temp file
+            let sink = ObjectStoreWriter::try_new(url, plan.upload_options())?;
+            if sink.exists().await {
+                info!("{url} already exists, skipping generation");
+                return Ok(());
+            }
+            let sink = Tee::new(Counted::new(sink, &progress), tee::mirrors(&plan)?);
+            generate_in_chunks(SortChecked::new(sink, sort_error), sources, num_threads).await
+        }
+    }
+}
+
+/// Generates an output parquet file from the sources
+async fn write_parquet<I>(
+    plan: OutputPlan,
+    num_threads: usize,
+    sources: I,
+    sort_error: &SortError,
+) -> Result<(), io::Error>
+where
+    I: Iterator<Item: RecordBatchIterator> + 'static,
+{
+    let progress = progress::table(plan.table());
+    match plan.output_location() {
+        OutputLocation::Stdout => {
+            let writer = BufWriter::with_capacity(32 * 1024 * 1024, io::stdout()); // 32MB buffer
+            let writer = SortChecked::new(Counted::new(writer, &progress), sort_error);
+            generate_parquet(
+                writer,
+                sources,
+                num_threads,
+                plan.parquet_compression(),
+                plan.geoparquet_version(),
+                plan.geometry_encoding(),
+                plan.parquet_data_page_bytes(),
+            )
+            .await
+        }
+        OutputLocation::File(path) => {
+            // if the output already exists, skip running
+            if path.exists() {
+                info!("{} already exists, skipping generation", path.display());
+                return Ok(());
+            }
+            // write to a temp file and then rename to avoid partial files
+            let temp_path = path.with_extension("inprogress");
+            let writer = FileWriter::create(&temp_path)?; // 32MB write-behind buffers
+            let writer = Counted::new(writer, &progress);
+            let writer = Checksummed::new(writer, Some(path.display().to_string()));
+            let writer = SortChecked::new(Tee::new(writer, tee::mirrors(&plan)?), sort_error);
+            generate_parquet(
+                writer,
+                sources,
+                num_threads,
+                plan.parquet_compression(),
+                plan.geoparquet_version(),
+                plan.geometry_encoding(),
+                plan.parquet_data_page_bytes(),
+            )
+            .await?;
             // rename the temp file to the final path
             std::fs::rename(&temp_path, path).map_err(|e| {
                 io::Error::other(format!(
@@ -218,18 +396,214 @@ where
             })?;
             Ok(())
         }
+        OutputLocation::Postgres(_) => {
+            unreachable!("PostgreSQL output is written by write_postgres")
+        }
+        OutputLocation::Null => unreachable!("null output is written by write_null"),
+        OutputLocation::ObjectStore(url) => {
+            // ObjectStoreWriter already buffers, so no BufWriter is needed
+            let writer = ObjectStoreWriter::try_new(url, plan.upload_options())?;
+            if writer.exists().await {
+                info!("{url} already exists, skipping generation");
+                return Ok(());
+            }
+            let writer = Tee::new(Counted::new(writer, &progress), tee::mirrors(&plan)?);
+            let writer = SortChecked::new(writer, sort_error);
+            generate_parquet(
+                writer,
+                sources,
+                num_threads,
+                plan.parquet_compression(),
+                plan.geoparquet_version(),
+                plan.geometry_encoding(),
+                plan.parquet_data_page_bytes(),
+            )
+            .await
+        }
     }
 }
 
-/// Generates an output parquet file from the sources
-async fn write_parquet<I>(plan: OutputPlan, num_threads: usize, sources: I) -> Result<(), io::Error>
+/// Generates Hive or spatially partitioned output parquet files from the
+/// sources
+///
+/// The output location is the file name to use in each partition directory
+/// of the table directory. If the table does not have the partition column
+/// (or a geometry column), the output location is written as a single
+/// unpartitioned file.
+///
+/// The cells of spatially partitioned output are built from the first batch
+/// of each of the `samples`.
+async fn write_partitioned_parquet<I, S>(
+    plan: OutputPlan,
+    num_threads: usize,
+    sources: I,
+    samples: S,
+    sort_error: &SortError,
+) -> Result<(), io::Error>
+where
+    I: Iterator<Item: RecordBatchIterator> + 'static,
+    S: Iterator<Item: RecordBatchIterator>,
+{
+    let mut sources = sources.peekable();
+    let Some(first) = sources.peek() else {
+        return Ok(()); // no data shrug
+    };
+    let schema = Arc::clone(first.schema());
+    if let Some(column) = plan.partition_by() {
+        let Some(partitioner) = HivePartitioner::try_new(&schema, column)? else {
+            info!(
+                "Table {} has no column {column}, writing it unpartitioned",
+                plan.table()
+            );
+            return write_parquet(plan, num_threads, sources, sort_error).await;
+        };
+        return write_partitions(plan, num_threads, partitioner, sources, None, sort_error).await;
+    }
+
+    let scheme = plan.spatial_partition().expect("partitioned plan");
+    let sample = samples.filter_map(|mut iter| iter.next());
+    let Some(partitioner) = SpatialPartitioner::try_new(&schema, scheme, sample) else {
+        info!(
+            "Table {} has no geometry column, writing it unpartitioned",
+            plan.table()
+        );
+        return write_parquet(plan, num_threads, sources, sort_error).await;
+    };
+    let manifest = partitioner.manifest();
+    write_partitions(
+        plan,
+        num_threads,
+        partitioner,
+        sources,
+        Some(manifest),
+        sort_error,
+    )
+    .await
+}
+
+/// Writes the partitions of `partitioner` to the table directory, and
+/// `manifest` (if any) to [`MANIFEST_FILE`] in it
+async fn write_partitions<P, I>(
+    plan: OutputPlan,
+    num_threads: usize,
+    partitioner: P,
+    sources: I,
+    manifest: Option<String>,
+    sort_error: &SortError,
+) -> Result<(), io::Error>
 where
+    P: Partitioner,
     I: Iterator<Item: RecordBatchIterator> + 'static,
 {
+    let options = PartitionedParquetOptions {
+        compression: plan.parquet_compression(),
+        geoparquet_version: plan.geoparquet_version(),
+        geometry_encoding: plan.geometry_encoding(),
+        data_page_bytes: plan.parquet_data_page_bytes(),
+        row_group_bytes: plan.parquet_row_group_bytes() as usize,
+        row_group_rows: plan.parquet_row_group_rows().map(|rows| rows as usize),
+    };
+    match plan.output_location() {
+        OutputLocation::Stdout => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Partitioned output can not be written to stdout",
+        )),
+        OutputLocation::File(path) => {
+            let dir = path.parent().expect("table directory").to_path_buf();
+            let file_name = path.file_name().expect("file name").to_string_lossy();
+            // the partitions are complete once the marker exists
+            let marker = dir.join(success_marker(&file_name));
+            if marker.exists() {
+                info!("{} already exists, skipping generation", marker.display());
+                return Ok(());
+            }
+            let sink = LocalPartitionSink::new(dir, file_name.to_string());
+            let sink = Counted::new(sink, &progress::table(plan.table()));
+            let sink = Arc::new(SortChecked::new(sink, sort_error));
+            if let Some(manifest) = manifest {
+                write_table_file(Arc::clone(&sink), MANIFEST_FILE.into(), manifest.into()).await?;
+            }
+            generate_partitioned_parquet(
+                Arc::clone(&sink),
+                partitioner,
+                sources,
+                num_threads,
+                options,
+            )
+            .await?;
+            write_table_file(sink, success_marker(&file_name), vec![]).await
+        }
+        OutputLocation::Postgres(_) => {
+            unreachable!("PostgreSQL output is written by write_postgres")
+        }
+        OutputLocation::Null => unreachable!("null output is written by write_null"),
+        OutputLocation::ObjectStore(url) => {
+            let invalid = || io::Error::other(format!("Invalid object store URL {url}"));
+            let file_name = url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .ok_or_else(invalid)?
+                .to_string();
+            let mut dir = url.clone();
+            dir.path_segments_mut().map_err(|_| invalid())?.pop();
+            let mut marker_url = dir.clone();
+            marker_url
+                .path_segments_mut()
+                .map_err(|_| invalid())?
+                .push(&success_marker(&file_name));
+            let marker = ObjectStoreWriter::try_new(&marker_url, plan.upload_options())?;
+            if marker.exists().await {
+                info!("{marker_url} already exists, skipping generation");
+                return Ok(());
+            }
+            let marker_name = success_marker(&file_name);
+            let sink = ObjectStorePartitionSink::new(dir, file_name, plan.upload_options());
+            let sink = Counted::new(sink, &progress::table(plan.table()));
+            let sink = Arc::new(SortChecked::new(sink, sort_error));
+            if let Some(manifest) = manifest {
+                write_table_file(Arc::clone(&sink), MANIFEST_FILE.into(), manifest.into()).await?;
+            }
+            generate_partitioned_parquet(
+                Arc::clone(&sink),
+                partitioner,
+                sources,
+                num_threads,
+                options,
+            )
+            .await?;
+            write_table_file(sink, marker_name, vec![]).await
+        }
+    }
+}
+
+/// Writes a small file to the table directory of `sink` on a blocking thread
+async fn write_table_file<S: PartitionSink>(
+    sink: Arc<S>,
+    name: String,
+    contents: Vec<u8>,
+) -> io::Result<()> {
+    tokio::task::spawn_blocking(move || sink.write_file(&name, &contents))
+        .await
+        .map_err(|e| io::Error::other(format!("Task Panic: {e}")))?
+}
+
+/// Generates an output FlatGeobuf file from the sources
+async fn write_flatgeobuf<I>(
+    plan: OutputPlan,
+    num_threads: usize,
+    sources: I,
+    sort_error: &SortError,
+) -> Result<(), io::Error>
+where
+    I: Iterator<Item: RecordBatchIterator> + 'static,
+{
+    let name = plan.table().to_string();
+    let progress = progress::table(plan.table());
     match plan.output_location() {
         OutputLocation::Stdout => {
             let writer = BufWriter::with_capacity(32 * 1024 * 1024, io::stdout()); // 32MB buffer
-            generate_parquet(writer, sources, num_threads, plan.parquet_compression()).await
+            let writer = SortChecked::new(Counted::new(writer, &progress), sort_error);
+            generate_flatgeobuf(writer, &name, sources, num_threads).await
         }
         OutputLocation::File(path) => {
             // if the output already exists, skip running
@@ -239,11 +613,68 @@ where
             }
             // write to a temp file and then rename to avoid partial files
             let temp_path = path.with_extension("inprogress");
-            let file = std::fs::File::create(&temp_path).map_err(|err| {
-                io::Error::other(format!("Failed to create {temp_path:?}: {err}"))
+            let writer = FileWriter::create(&temp_path)?; // 32MB write-behind buffers
+            let writer = Counted::new(writer, &progress);
+            let writer = Checksummed::new(writer, Some(path.display().to_string()));
+            let writer = SortChecked::new(Tee::new(writer, tee::mirrors(&plan)?), sort_error);
+            generate_flatgeobuf(writer, &name, sources, num_threads).await?;
+            // rename the temp file to the final path
+            std::fs::rename(&temp_path, path).map_err(|e| {
+                io::Error::other(format!(
+                    "Failed to rename {temp_path:?} to {path:?} file: {e}"
+                ))
             })?;
-            let writer = BufWriter::with_capacity(32 * 1024 * 1024, file); // 32MB buffer
-            generate_parquet(writer, sources, num_threads, plan.parquet_compression()).await?;
+            Ok(())
+        }
+        OutputLocation::Postgres(_) => {
+            unreachable!("PostgreSQL output is written by write_postgres")
+        }
+        OutputLocation::Null => unreachable!("null output is written by write_null"),
+        OutputLocation::ObjectStore(url) => {
+            // ObjectStoreWriter already buffers, so no BufWriter is needed
+            let writer = ObjectStoreWriter::try_new(url, plan.upload_options())?;
+            if writer.exists().await {
+                info!("{url} already exists, skipping generation");
+                return Ok(());
+            }
+            let writer = Tee::new(Counted::new(writer, &progress), tee::mirrors(&plan)?);
+            let writer = SortChecked::new(writer, sort_error);
+            generate_flatgeobuf(writer, &name, sources, num_threads).await
+        }
+    }
+}
+
+/// Generates an output Arrow IPC file from the sources
+async fn write_arrow_ipc<I>(
+    plan: OutputPlan,
+    num_threads: usize,
+    sources: I,
+    sort_error: &SortError,
+) -> Result<(), io::Error>
+where
+    I: Iterator<Item: RecordBatchIterator> + 'static,
+{
+    let format = plan.arrow_ipc_format();
+    let progress = progress::table(plan.table());
+    match plan.output_location() {
+        OutputLocation::Stdout => {
+            let writer = BufWriter::with_capacity(32 * 1024 * 1024, io::stdout()); // 32MB buffer
+            let writer = SortChecked::new(Counted::new(writer, &progress), sort_error);
+            generate_arrow_ipc(writer, sources, num_threads, format).await
+        }
+        OutputLocation::File(path) => {
+            // if the output already exists, skip running
+            if path.exists() {
+                info!("{} already exists, skipping generation", path.display());
+                return Ok(());
+            }
+            // write to a temp file and then rename to avoid partial files
+            let temp_path = path.with_extension("inprogress");
+            let writer = FileWriter::create(&temp_path)?; // 32MB write-behind buffers
+            let writer = Counted::new(writer, &progress);
+            let writer = Checksummed::new(writer, Some(path.display().to_string()));
+            let writer = SortChecked::new(Tee::new(writer, tee::mirrors(&plan)?), sort_error);
+            generate_arrow_ipc(writer, sources, num_threads, format).await?;
             // rename the temp file to the final path
             std::fs::rename(&temp_path, path).map_err(|e| {
                 io::Error::other(format!(
@@ -252,9 +683,69 @@ where
             })?;
             Ok(())
         }
+        OutputLocation::Postgres(_) => {
+            unreachable!("PostgreSQL output is written by write_postgres")
+        }
+        OutputLocation::Null => unreachable!("null output is written by write_null"),
+        OutputLocation::ObjectStore(url) => {
+            // ObjectStoreWriter already buffers, so no BufWriter is needed
+            let writer = ObjectStoreWriter::try_new(url, plan.upload_options())?;
+            if writer.exists().await {
+                info!("{url} already exists, skipping generation");
+                return Ok(());
+            }
+            let writer = Tee::new(Counted::new(writer, &progress), tee::mirrors(&plan)?);
+            let writer = SortChecked::new(writer, sort_error);
+            generate_arrow_ipc(writer, sources, num_threads, format).await
+        }
     }
 }
 
+/// Copies the rows of the sources into a PostgreSQL table, creating it if
+/// needed
+async fn write_postgres<I>(
+    plan: OutputPlan,
+    num_threads: usize,
+    sources: I,
+    sort_error: &SortError,
+) -> io::Result<()>
+where
+    I: Iterator<Item: RecordBatchIterator> + 'static,
+{
+    let mut sources = sources.peekable();
+    let Some(first) = sources.peek() else {
+        return Ok(()); // no sources
+    };
+    let schema = Arc::clone(first.schema());
+    let table = plan.table().to_string();
+    // trajectory paths are the only geometries with M
+    let dimensions = match plan.table() {
+        Table::Trajectory => dimensions(),
+        _ => dimensions().without_m(),
+    };
+    postgres::create_table(&table, &schema, dimensions)?;
+    let sink = PostgresSink::try_new(&table, &schema)?;
+    let sink = Counted::new(sink, &progress::table(plan.table()));
+    let sink = SortChecked::new(sink, sort_error);
+    generate_in_chunks(sink, sources.map(PostgresSource::new), num_threads).await
+}
+
+/// Generates the rows of the sources and drops them (`--output null`)
+async fn write_null<I>(plan: OutputPlan, num_threads: usize, sources: I) -> Result<(), io::Error>
+where
+    I: Iterator<Item: Source> + 'static,
+{
+    let sink = Counted::new(WriterSink::new(io::sink()), &progress::table(plan.table()));
+    generate_in_chunks(sink, sources, num_threads).await
+}
+
+/// Number of parts of a table whose first batch is sampled to build the cells
+/// of spatially partitioned output
+///
+/// The same parts are sampled for every part of the output, so all the parts
+/// have the same cells.
+const SPATIAL_SAMPLE_PARTS: i32 = 16;
+
 /// macro to create a function for generating a part of a particular able
 ///
 /// Arguments:
@@ -262,12 +753,19 @@ where
 /// $GENERATOR: The generator type to use
 /// $TBL_SOURCE: The [`Source`] type to use for TBL format
 /// $CSV_SOURCE: The [`Source`] type to use for CSV format
-/// $PARQUET_SOURCE: The [`RecordBatchIterator`] type to use for Parquet format
+/// $NULL_SOURCE: The [`Source`] type to use for `--output null`
+/// $PARQUET_SOURCE: The [`RecordBatchIterator`] type to use for Parquet, GeoJSON, FlatGeobuf and Arrow formats
 macro_rules! define_run {
-    ($FUN_NAME:ident, $GENERATOR:ident, $TBL_SOURCE:ty, $CSV_SOURCE:ty, $PARQUET_SOURCE:ty) => {
-        async fn $FUN_NAME(plan: OutputPlan, num_threads: usize) -> io::Result<usize> {
+    ($FUN_NAME:ident, $GENERATOR:ident, $TBL_SOURCE:ty, $CSV_SOURCE:ty, $NULL_SOURCE:ty, $PARQUET_SOURCE:ty) => {
+        async fn $FUN_NAME(
+            plan: OutputPlan,
+            num_threads: usize,
+            progress: Arc<PlanProgress>,
+        ) -> io::Result<usize> {
             use crate::GenerationPlan;
             let scale_factor = plan.scale_factor();
+            let seed = plan.seed();
+            let row_counts = plan.row_counts();
             info!("Writing {plan} using {num_threads} threads");
 
             /// These interior functions are used to tell the compiler that the lifetime is 'static
@@ -281,49 +779,307 @@ macro_rules! define_run {
             fn tbl_sources(
                 generation_plan: &GenerationPlan,
                 scale_factor: f64,
+                seed: u64,
+                row_counts: RowCounts,
+                progress: Arc<PlanProgress>,
             ) -> impl Iterator<Item: Source> + 'static {
                 generation_plan
                     .clone()
                     .into_iter()
-                    .map(move |(part, num_parts)| $GENERATOR::new(scale_factor, part, num_parts))
-                    .map(<$TBL_SOURCE>::new)
+                    .map(move |(part, num_parts)| {
+                        $GENERATOR::new(scale_factor, part, num_parts)
+                            .with_seed(seed)
+                            .with_row_counts(row_counts)
+                    })
+                    .map(move |generator| {
+                        let rows = generator.row_count();
+                        let source = <$TBL_SOURCE>::new(generator);
+                        ProgressedSource::new(source, rows, Arc::clone(&progress))
+                    })
+            }
+
+            fn null_sources(
+                generation_plan: &GenerationPlan,
+                scale_factor: f64,
+                seed: u64,
+                row_counts: RowCounts,
+                progress: Arc<PlanProgress>,
+            ) -> impl Iterator<Item: Source> + 'static {
+                generation_plan
+                    .clone()
+                    .into_iter()
+                    .map(move |(part, num_parts)| {
+                        $GENERATOR::new(scale_factor, part, num_parts)
+                            .with_seed(seed)
+                            .with_row_counts(row_counts)
+                    })
+                    .map(move |generator| {
+                        let rows = generator.row_count();
+                        let source = <$NULL_SOURCE>::new(generator);
+                        ProgressedSource::new(source, rows, Arc::clone(&progress))
+                    })
             }
 
             fn csv_sources(
                 generation_plan: &GenerationPlan,
                 scale_factor: f64,
+                seed: u64,
+                row_counts: RowCounts,
+                options: CsvOptions,
+                progress: Arc<PlanProgress>,
             ) -> impl Iterator<Item: Source> + 'static {
                 generation_plan
                     .clone()
                     .into_iter()
-                    .map(move |(part, num_parts)| $GENERATOR::new(scale_factor, part, num_parts))
-                    .map(<$CSV_SOURCE>::new)
+                    .map(move |(part, num_parts)| {
+                        $GENERATOR::new(scale_factor, part, num_parts)
+                            .with_seed(seed)
+                            .with_row_counts(row_counts)
+                    })
+                    .map(move |generator| {
+                        let rows = generator.row_count();
+                        let source = <$CSV_SOURCE>::new(generator, options);
+                        ProgressedSource::new(source, rows, Arc::clone(&progress))
+                    })
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            fn geojson_sources(
+                generation_plan: &GenerationPlan,
+                scale_factor: f64,
+                seed: u64,
+                row_counts: RowCounts,
+                projection: Option<Vec<usize>>,
+                layout: GeoJsonLayout,
+                row_order: Option<RowOrder>,
+                num_threads: usize,
+                progress: Arc<PlanProgress>,
+                sort_error: &SortError,
+            ) -> impl Iterator<Item: Source> + 'static {
+                let sources = generation_plan
+                    .clone()
+                    .into_iter()
+                    .map(move |(part, num_parts)| {
+                        $GENERATOR::new(scale_factor, part, num_parts)
+                            .with_seed(seed)
+                            .with_row_counts(row_counts)
+                    })
+                    .map(move |generator| {
+                        projected(<$PARQUET_SOURCE>::new(generator), &projection)
+                    });
+                let sorted =
+                    KeySorted::new(sources, row_order, num_threads).with_error(sort_error.clone());
+                sorted.map(move |batches| {
+                    let batches = Throttled::new(Profiled::new(Bounded::new(batches)));
+                    GeoJsonSource::new(Progressed::new(batches, Arc::clone(&progress)), layout)
+                })
             }
 
+            /// The first batch of each of these is the sample for spatial partitioning
+            fn sample_sources(
+                scale_factor: f64,
+                seed: u64,
+                row_counts: RowCounts,
+                projection: Option<Vec<usize>>,
+            ) -> impl Iterator<Item: RecordBatchIterator> {
+                (1..=SPATIAL_SAMPLE_PARTS)
+                    .map(move |part| {
+                        $GENERATOR::new(scale_factor, part, SPATIAL_SAMPLE_PARTS)
+                            .with_seed(seed)
+                            .with_row_counts(row_counts)
+                    })
+                    .map(move |generator| projected(<$PARQUET_SOURCE>::new(generator), &projection))
+            }
+
+            #[allow(clippy::too_many_arguments)]
             fn parquet_sources(
                 generation_plan: &GenerationPlan,
                 scale_factor: f64,
+                seed: u64,
+                row_counts: RowCounts,
+                projection: Option<Vec<usize>>,
+                row_order: Option<RowOrder>,
+                wkb_variant: WkbVariant,
+                num_threads: usize,
+                progress: Arc<PlanProgress>,
+                sort_error: &SortError,
             ) -> impl Iterator<Item: RecordBatchIterator> + 'static {
-                generation_plan
+                let sources = generation_plan
                     .clone()
                     .into_iter()
-                    .map(move |(part, num_parts)| $GENERATOR::new(scale_factor, part, num_parts))
-                    .map(<$PARQUET_SOURCE>::new)
+                    .map(move |(part, num_parts)| {
+                        $GENERATOR::new(scale_factor, part, num_parts)
+                            .with_seed(seed)
+                            .with_row_counts(row_counts)
+                    })
+                    .map(move |generator| {
+                        projected(<$PARQUET_SOURCE>::new(generator), &projection)
+                    });
+                let sorted =
+                    KeySorted::new(sources, row_order, num_threads).with_error(sort_error.clone());
+                sorted.map(move |batches| {
+                    let batches = Throttled::new(Profiled::new(Bounded::new(batches)));
+                    Progressed::new(WkbEncoded::new(batches, wkb_variant), Arc::clone(&progress))
+                })
+            }
+
+            /// Only convert the columns of `projection` (if any) to Arrow
+            fn projected(
+                batches: $PARQUET_SOURCE,
+                projection: &Option<Vec<usize>>,
+            ) -> $PARQUET_SOURCE {
+                match projection {
+                    Some(projection) => batches.with_projection(projection.clone()),
+                    None => batches,
+                }
             }
 
             // Dispatch to the appropriate output format
+            let projection = plan.projection().map(<[usize]>::to_vec);
+            let sort_error = SortError::default();
             match plan.output_format() {
+                // the rows are dropped whatever the format
+                _ if matches!(plan.output_location(), OutputLocation::Null) => {
+                    let gens = null_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        progress,
+                    );
+                    write_null(plan, num_threads, gens).await?
+                }
                 OutputFormat::Tbl => {
-                    let gens = tbl_sources(plan.generation_plan(), scale_factor);
-                    write_file(plan, num_threads, gens).await?
+                    let gens = tbl_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        progress,
+                    );
+                    write_file(plan, num_threads, gens, &sort_error).await?
                 }
                 OutputFormat::Csv => {
-                    let gens = csv_sources(plan.generation_plan(), scale_factor);
-                    write_file(plan, num_threads, gens).await?
+                    let gens = csv_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        plan.csv_options(),
+                        progress,
+                    );
+                    write_file(plan, num_threads, gens, &sort_error).await?
+                }
+                OutputFormat::Geojson => {
+                    let layout = GeoJsonLayout::FeatureCollection;
+                    let gens = geojson_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        projection,
+                        layout,
+                        plan.row_order(),
+                        num_threads,
+                        progress,
+                        &sort_error,
+                    );
+                    write_file(plan, num_threads, gens, &sort_error).await?
+                }
+                OutputFormat::Geojsonl => {
+                    let layout = GeoJsonLayout::NewlineDelimited;
+                    let gens = geojson_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        projection,
+                        layout,
+                        plan.row_order(),
+                        num_threads,
+                        progress,
+                        &sort_error,
+                    );
+                    write_file(plan, num_threads, gens, &sort_error).await?
+                }
+                OutputFormat::Parquet
+                    if matches!(plan.output_location(), OutputLocation::Postgres(_)) =>
+                {
+                    let gens = parquet_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        projection,
+                        plan.row_order(),
+                        WkbVariant::default(),
+                        num_threads,
+                        progress,
+                        &sort_error,
+                    );
+                    write_postgres(plan, num_threads, gens, &sort_error).await?
+                }
+                OutputFormat::Parquet if plan.is_partitioned() => {
+                    let samples =
+                        sample_sources(scale_factor, seed, row_counts, projection.clone());
+                    let gens = parquet_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        projection,
+                        plan.row_order(),
+                        plan.wkb_variant(),
+                        num_threads,
+                        progress,
+                        &sort_error,
+                    );
+                    write_partitioned_parquet(plan, num_threads, gens, samples, &sort_error).await?
                 }
                 OutputFormat::Parquet => {
-                    let gens = parquet_sources(plan.generation_plan(), scale_factor);
-                    write_parquet(plan, num_threads, gens).await?
+                    let gens = parquet_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        projection,
+                        plan.row_order(),
+                        plan.wkb_variant(),
+                        num_threads,
+                        progress,
+                        &sort_error,
+                    );
+                    write_parquet(plan, num_threads, gens, &sort_error).await?
+                }
+                OutputFormat::Flatgeobuf => {
+                    let gens = parquet_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        projection,
+                        plan.row_order(),
+                        WkbVariant::default(),
+                        num_threads,
+                        progress,
+                        &sort_error,
+                    );
+                    write_flatgeobuf(plan, num_threads, gens, &sort_error).await?
+                }
+                OutputFormat::Arrow => {
+                    let gens = parquet_sources(
+                        plan.generation_plan(),
+                        scale_factor,
+                        seed,
+                        row_counts,
+                        projection,
+                        plan.row_order(),
+                        plan.wkb_variant(),
+                        num_threads,
+                        progress,
+                        &sort_error,
+                    );
+                    write_arrow_ipc(plan, num_threads, gens, &sort_error).await?
                 }
             };
             Ok(num_threads)
@@ -336,14 +1092,97 @@ define_run!(
     TripGenerator,
     TripTblSource,
     TripCsvSource,
+    TripNullSource,
     TripArrow
 );
 
+define_run!(
+    run_trajectory_plan,
+    TrajectoryGenerator,
+    TrajectoryTblSource,
+    TrajectoryCsvSource,
+    TrajectoryNullSource,
+    TrajectoryArrow
+);
+
+define_run!(
+    run_road_plan,
+    RoadGenerator,
+    RoadTblSource,
+    RoadCsvSource,
+    RoadNullSource,
+    RoadArrow
+);
+
+define_run!(
+    run_landmass_plan,
+    LandmassGenerator,
+    LandmassTblSource,
+    LandmassCsvSource,
+    LandmassNullSource,
+    LandmassArrow
+);
+
+define_run!(
+    run_country_plan,
+    CountryGenerator,
+    CountryTblSource,
+    CountryCsvSource,
+    CountryNullSource,
+    CountryArrow
+);
+
+define_run!(
+    run_region_plan,
+    RegionGenerator,
+    RegionTblSource,
+    RegionCsvSource,
+    RegionNullSource,
+    RegionArrow
+);
+
+define_run!(
+    run_city_plan,
+    CityGenerator,
+    CityTblSource,
+    CityCsvSource,
+    CityNullSource,
+    CityArrow
+);
+
+define_run!(
+    run_poi_plan,
+    PoiGenerator,
+    PoiTblSource,
+    PoiCsvSource,
+    PoiNullSource,
+    PoiArrow
+);
+
+define_run!(
+    run_raster_plan,
+    RasterGenerator,
+    RasterTblSource,
+    RasterCsvSource,
+    RasterNullSource,
+    RasterArrow
+);
+
+define_run!(
+    run_pointcloud_plan,
+    PointCloudGenerator,
+    PointCloudTblSource,
+    PointCloudCsvSource,
+    PointCloudNullSource,
+    PointCloudArrow
+);
+
 define_run!(
     run_building_plan,
     BuildingGenerator,
     BuildingTblSource,
     BuildingCsvSource,
+    BuildingNullSource,
     BuildingArrow
 );
 
@@ -352,6 +1191,7 @@ define_run!(
     VehicleGenerator,
     VehicleTblSource,
     VehicleCsvSource,
+    VehicleNullSource,
     VehicleArrow
 );
 
@@ -360,6 +1200,7 @@ define_run!(
     DriverGenerator,
     DriverTblSource,
     DriverCsvSource,
+    DriverNullSource,
     DriverArrow
 );
 
@@ -368,5 +1209,6 @@ define_run!(
     CustomerGenerator,
     CustomerTblSource,
     CustomerCsvSource,
+    CustomerNullSource,
     CustomerArrow
 );
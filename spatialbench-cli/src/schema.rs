@@ -0,0 +1,281 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `schema` command: describe the columns and keys of the tables
+//!
+//! The descriptions come from [`TableSchema`], either as JSON or as an empty
+//! Arrow IPC stream whose schema carries the keys as metadata.
+
+use crate::ipc::geoarrow_schema;
+use arrow::datatypes::{Field, Schema};
+use arrow::ipc::writer::StreamWriter;
+use clap::{Args, ValueEnum};
+use serde_json::{json, Map, Value};
+use spatialbench::spatial::{
+    cell, crs, dimensions, duplicate, geography, invalid, CellIndex, Crs, Dimensions,
+};
+use spatialbench_arrow::lineage;
+use spatialbench_arrow::nulls::{self, ColumnRate, NullRates};
+use spatialbench_arrow::schema::TableSchema;
+use spatialbench_arrow::Table;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Schema metadata key of the key column of the table
+const PRIMARY_KEY_KEY: &str = "spatialbench:primary_key";
+
+/// Field metadata key of the column a foreign key references, e.g.
+/// `customer.c_custkey`
+const REFERENCES_KEY: &str = "spatialbench:references";
+
+/// Describe the columns, geometry encodings and keys of the tables
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    /// Which tables to describe, e.g. `trip,building` (default: all the
+    /// generated tables)
+    #[arg(short, long = "table", visible_alias = "tables", value_delimiter = ',', value_parser = Table::from_str)]
+    tables: Option<Vec<Table>>,
+
+    /// Output format: json, or arrow for an Arrow IPC stream with the schema
+    /// of a single table and no rows
+    #[arg(short, long, default_value = "json")]
+    format: SchemaFormat,
+
+    #[command(flatten)]
+    schema: SchemaOptions,
+}
+
+/// Formats to describe the tables in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum SchemaFormat {
+    Json,
+    Arrow,
+}
+
+/// Options that change the schemas of the tables, as passed when generating
+#[derive(Debug, Args)]
+pub struct SchemaOptions {
+    /// Dimensions of the coordinates the tables are generated with
+    #[arg(long, default_value_t = Dimensions::Xy, value_parser = Dimensions::from_str)]
+    pub dims: Dimensions,
+
+    /// Coordinate reference system the tables are generated with, the SRID
+    /// of the PostGIS geometry columns
+    #[arg(long, default_value_t = Crs::Wgs84, value_parser = Crs::from_str)]
+    pub crs: Crs,
+
+    /// The tables are generated on the sphere, so the geometries are
+    /// geography columns in PostGIS and Snowflake
+    #[arg(long, default_value_t = false)]
+    pub geography: bool,
+
+    /// Fraction of the building boundaries made invalid, which adds
+    /// `b_isvalid`
+    #[arg(long, default_value_t = 0.0)]
+    pub invalid_rate: f64,
+
+    /// Fraction of the buildings that duplicate the building before them,
+    /// which adds the duplicate columns
+    #[arg(long, default_value_t = 0.0)]
+    pub duplicate_rate: f64,
+
+    /// Fractions of the values of columns that are null, e.g.
+    /// `trip.t_dropoffloc=0.02`, which makes the columns nullable
+    #[arg(long, value_parser = ColumnRate::from_str)]
+    pub null_rate: Vec<ColumnRate>,
+
+    /// Cell indexes of the locations of the point tables, e.g. `h3:9`,
+    /// which add the cell index columns
+    #[arg(long, value_parser = CellIndex::from_str)]
+    pub add_cell_index: Vec<CellIndex>,
+
+    /// Add the `_rowgen` lineage column to every table
+    #[arg(long)]
+    pub add_rowgen: bool,
+}
+
+impl SchemaOptions {
+    /// Check the options and set the settings the schemas depend on
+    pub fn apply(&self) -> io::Result<()> {
+        if self.geography && self.crs != Crs::Wgs84 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "--geography is not supported with --crs {}, only with longitude / latitude",
+                    self.crs
+                ),
+            ));
+        }
+        dimensions::set_dimensions(self.dims);
+        crs::set_crs(self.crs);
+        geography::set_geography(self.geography);
+        invalid::set_invalid_rate(self.invalid_rate);
+        duplicate::set_duplicate_rate(self.duplicate_rate);
+        cell::set_cell_indexes(self.add_cell_index.clone());
+        lineage::set_row_lineage(self.add_rowgen)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let rates = NullRates::try_new(&self.null_rate, &[])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        nulls::set_null_rates(rates).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(())
+    }
+}
+
+impl SchemaArgs {
+    /// Print the descriptions of the tables to stdout
+    pub fn run(&self) -> io::Result<()> {
+        self.schema.apply()?;
+        let tables = self.tables.as_deref().unwrap_or(&Table::ALL);
+        let schemas: Vec<_> = tables
+            .iter()
+            .map(|&table| TableSchema::new(table))
+            .collect();
+
+        let mut stdout = io::stdout().lock();
+        match self.format {
+            SchemaFormat::Json => {
+                let tables: Vec<_> = schemas.iter().map(table_json).collect();
+                serde_json::to_writer_pretty(&mut stdout, &json!({ "tables": tables }))?;
+                writeln!(stdout)?;
+            }
+            SchemaFormat::Arrow => {
+                let [schema] = schemas.as_slice() else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "--format arrow describes a single table, pass it with --table",
+                    ));
+                };
+                let mut writer = StreamWriter::try_new(&mut stdout, &arrow_schema(schema))
+                    .map_err(io::Error::other)?;
+                writer.finish().map_err(io::Error::other)?;
+            }
+        }
+        stdout.flush()
+    }
+}
+
+/// Return the JSON description of a table
+fn table_json(schema: &TableSchema) -> Value {
+    let columns: Vec<_> = schema
+        .columns()
+        .iter()
+        .map(|column| {
+            let mut json = Map::new();
+            json.insert("name".into(), json!(column.name));
+            json.insert("type".into(), json!(column.data_type.to_string()));
+            json.insert("nullable".into(), json!(column.nullable));
+            if let Some(geometry) = column.geometry {
+                let edges = if geometry.spherical {
+                    "spherical"
+                } else {
+                    "planar"
+                };
+                json.insert(
+                    "geometry".into(),
+                    json!({
+                        "encoding": "WKB",
+                        "geometry_type": geometry.geometry_type,
+                        "crs": geometry.crs.to_string(),
+                        "dimensions": geometry.dimensions.to_string(),
+                        "edges": edges,
+                    }),
+                );
+            }
+            if let Some(references) = column.references {
+                json.insert(
+                    "references".into(),
+                    json!({
+                        "table": references.table.name(),
+                        "column": references.column,
+                    }),
+                );
+            }
+            Value::Object(json)
+        })
+        .collect();
+    json!({
+        "name": schema.table().name(),
+        "primary_key": schema.primary_key(),
+        "columns": columns,
+    })
+}
+
+/// Return the Arrow schema of a table with the geometry columns marked as
+/// GeoArrow WKB and the keys in the metadata
+fn arrow_schema(schema: &TableSchema) -> Schema {
+    let arrow_schema = geoarrow_schema(schema.arrow_schema());
+    let fields: Vec<Field> = arrow_schema
+        .fields()
+        .iter()
+        .zip(schema.columns())
+        .map(|(field, column)| match column.references {
+            Some(references) => {
+                let mut metadata = field.metadata().clone();
+                metadata.insert(
+                    REFERENCES_KEY.to_string(),
+                    format!("{}.{}", references.table.name(), references.column),
+                );
+                field.as_ref().clone().with_metadata(metadata)
+            }
+            None => field.as_ref().clone(),
+        })
+        .collect();
+    let mut metadata = arrow_schema.metadata().clone();
+    metadata.insert(
+        PRIMARY_KEY_KEY.to_string(),
+        schema.primary_key().to_string(),
+    );
+    Schema::new_with_metadata(fields, metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_json() {
+        let json = table_json(&TableSchema::new(Table::Trip));
+        assert_eq!(json["name"], "trip");
+        assert_eq!(json["primary_key"], "t_tripkey");
+        let column = |name: &str| {
+            json["columns"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|column| column["name"] == name)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            column("t_custkey")["references"],
+            json!({"table": "customer", "column": "c_custkey"})
+        );
+        assert_eq!(column("t_pickuploc")["geometry"]["encoding"], "WKB");
+        assert_eq!(column("t_pickuploc")["geometry"]["geometry_type"], "Point");
+        assert!(column("t_tripkey").get("geometry").is_none());
+    }
+
+    #[test]
+    fn test_arrow_schema() {
+        let schema = arrow_schema(&TableSchema::new(Table::Trajectory));
+        assert_eq!(schema.metadata()[PRIMARY_KEY_KEY], "tr_tripkey");
+        let tripkey = schema.field_with_name("tr_tripkey").unwrap();
+        assert_eq!(tripkey.metadata()[REFERENCES_KEY], "trip.t_tripkey");
+        let path = schema.field_with_name("tr_path").unwrap();
+        assert_eq!(path.metadata()["ARROW:extension:name"], "geoarrow.wkb");
+    }
+}
@@ -16,13 +16,26 @@
 // under the License.
 
 use anyhow::Result;
+use clap::ValueEnum;
+use log::info;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer};
+use spatialbench::attributes::{
+    set_attribute_distributions, Attribute, AttributeDistributions, SpatialCorrelation,
+    ValueDistribution,
+};
+use spatialbench::spatial::overrides::{set_overrides, SpatialOverrides};
 use spatialbench::spatial::{
-    DistributionParams, DistributionType, GeomType, SpatialConfig, SpatialGenerator,
+    DensityRaster, DistributionParams, DistributionType, GeomType, GeometryMix, SpatialConfig,
+    SpatialDefaults, SpatialGenerator,
 };
+use spatialbench_arrow::Table;
+use std::collections::BTreeMap;
 use std::fmt;
-use std::sync::OnceLock;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 
 // Deserializer for DistributionType
 fn deserialize_distribution_type<'de, D>(deserializer: D) -> Result<DistributionType, D::Error>
@@ -50,6 +63,7 @@ where
                 "sierpinski" => Ok(DistributionType::Sierpinski),
                 "thomas" => Ok(DistributionType::Thomas),
                 "hierarchicalthomas" => Ok(DistributionType::HierarchicalThomas),
+                "coastline" => Ok(DistributionType::Coastline),
                 _ => Err(E::custom(format!("unknown distribution type: {}", value))),
             }
         }
@@ -92,6 +106,8 @@ where
 pub struct SpatialConfigFile {
     pub trip: Option<InlineSpatialConfig>,
     pub building: Option<InlineSpatialConfig>,
+    /// Distributions of the attribute columns, such as `fare: zipf:1.2`
+    pub attributes: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Deserialize)]
@@ -106,11 +122,17 @@ pub struct InlineSpatialConfig {
     pub width: f64,
     pub height: f64,
     // geometry = polygon
+    #[serde(default = "default_minseg")]
+    pub minseg: i32,
     pub maxseg: i32,
     pub polysize: f64,
     pub params: InlineParams,
 }
 
+fn default_minseg() -> i32 {
+    3
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum InlineParams {
@@ -151,6 +173,10 @@ pub enum InlineParams {
         pareto_alpha_sub: f64,  // Pareto tail for subcluster weights (within a city)
         pareto_xm_sub: f64,     // Pareto scale (xmin) for subcluster weights
     },
+
+    Coastline {
+        sigma: f64, // spread of the points on both sides of the coastline
+    },
 }
 
 impl InlineSpatialConfig {
@@ -210,6 +236,7 @@ impl InlineSpatialConfig {
                 pareto_alpha_sub: *pareto_alpha_sub, // Pareto tail for subcluster weights (within a city)
                 pareto_xm_sub: *pareto_xm_sub,       // Pareto scale (xmin) for subcluster weights
             },
+            InlineParams::Coastline { sigma } => DistributionParams::Coastline { sigma: *sigma },
         };
 
         let cfg = SpatialConfig {
@@ -219,6 +246,7 @@ impl InlineSpatialConfig {
             seed: self.seed,
             width: self.width,
             height: self.height,
+            minseg: self.minseg,
             maxseg: self.maxseg,
             polysize: self.polysize,
             params,
@@ -231,3 +259,482 @@ pub fn parse_yaml(text: &str) -> Result<SpatialConfigFile> {
     log::info!("Default spider config is being overridden by user-provided configuration");
     Ok(serde_yaml::from_str::<SpatialConfigFile>(text)?)
 }
+
+/// Parse a `--polygon-vertices` value: a range such as `4..64` or a single
+/// number of vertices such as `8`
+pub fn parse_polygon_vertices(value: &str) -> Result<RangeInclusive<i32>, String> {
+    let parse = |v: &str| {
+        v.trim()
+            .parse::<i32>()
+            .map_err(|_| format!("invalid number of vertices '{v}'"))
+    };
+    let (min, max) = match value.split_once("..") {
+        Some((min, max)) => (parse(min)?, parse(max.strip_prefix('=').unwrap_or(max))?),
+        None => (parse(value)?, parse(value)?),
+    };
+    if min < 3 {
+        return Err(format!("polygons have at least 3 vertices, got {min}"));
+    }
+    if max < min {
+        return Err(format!("empty range of vertices {min}..{max}"));
+    }
+    if max > MAX_POLYGON_VERTICES {
+        return Err(format!(
+            "polygons have at most {MAX_POLYGON_VERTICES} vertices, got {max}"
+        ));
+    }
+    Ok(min..=max)
+}
+
+/// Limits the size of a single polygon (10000 vertices are 160KB of WKB)
+const MAX_POLYGON_VERTICES: i32 = 10_000;
+
+/// Parse a `--geometry-mix` value such as
+/// `building=polygon:0.7,multipolygon:0.3`
+///
+/// Only the building boundaries can be mixed: the trip locations are points
+/// and the zones are downloaded.
+pub fn parse_geometry_mix(value: &str) -> Result<GeometryMix, String> {
+    let Some((table, mix)) = value.split_once('=') else {
+        return Err(format!(
+            "expected <table>=<type>:<fraction>,..., e.g. building=polygon:0.7,multipolygon:0.3, got '{value}'"
+        ));
+    };
+    match table.trim() {
+        "building" => mix.parse(),
+        "zone" => Err("the zone boundaries are downloaded and can not be mixed".to_string()),
+        "trip" => Err("the trip locations are points and can not be mixed".to_string()),
+        table => Err(format!(
+            "table {table} has no generated geometries, only building can be mixed"
+        )),
+    }
+}
+
+/// The distributions of `--distribution`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DistributionPreset {
+    /// Uniform over the continents
+    Uniform,
+    /// 1000 Gaussian clusters of similar sizes on each continent
+    Clusters,
+    /// 1000 Gaussian clusters with power law sizes on each continent, a few
+    /// of which hold most of the geometries
+    Hotspots,
+    /// Along a synthetic coastline crossing each continent
+    Coastline,
+}
+
+impl DistributionPreset {
+    /// Return the built-in distribution and its parameters
+    pub fn distribution(&self) -> (DistributionType, DistributionParams) {
+        match self {
+            Self::Uniform => (DistributionType::Uniform, DistributionParams::None),
+            Self::Clusters => (
+                DistributionType::Thomas,
+                DistributionParams::Thomas {
+                    parents: 1000,
+                    mean_offspring: 1.0,
+                    sigma: 0.005,
+                    // a large shape gives weights close to the scale
+                    pareto_alpha: 100.0,
+                    pareto_xm: 1.0,
+                },
+            ),
+            Self::Hotspots => (
+                DistributionType::Thomas,
+                DistributionParams::Thomas {
+                    parents: 1000,
+                    mean_offspring: 1.0,
+                    sigma: 0.005,
+                    pareto_alpha: 0.8,
+                    pareto_xm: 1.0,
+                },
+            ),
+            Self::Coastline => (
+                DistributionType::Coastline,
+                DistributionParams::Coastline { sigma: 0.01 },
+            ),
+        }
+    }
+}
+
+/// The distribution of the geometries of one table, see
+/// [`parse_distribution`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableDistribution {
+    /// The trip or building table
+    pub table: Table,
+    pub preset: DistributionPreset,
+}
+
+/// Parse a `--distribution` value such as `trip=hotspots`
+pub fn parse_distribution(value: &str) -> Result<TableDistribution, String> {
+    let Some((table, preset)) = value.split_once('=') else {
+        return Err(format!(
+            "expected <table>=<distribution>, e.g. trip=hotspots, got '{value}'"
+        ));
+    };
+    let table = parse_spatial_table(table)?;
+    let preset = DistributionPreset::from_str(preset.trim(), true)?;
+    Ok(TableDistribution { table, preset })
+}
+
+/// Parse the name of a table with generated geometries
+fn parse_spatial_table(table: &str) -> Result<Table, String> {
+    match table.trim() {
+        "trip" => Ok(Table::Trip),
+        "building" => Ok(Table::Building),
+        "zone" => Err("the zone boundaries are downloaded".to_string()),
+        table => Err(format!(
+            "table {table} has no generated geometries, expected trip or building"
+        )),
+    }
+}
+
+/// The density raster of one table, see [`parse_density_raster`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRaster {
+    /// The trip or building table
+    pub table: Table,
+    /// The ESRI ASCII grid file of the raster
+    pub path: PathBuf,
+}
+
+/// Parse a `--density-raster` value such as `trip=population.asc`
+///
+/// The file is read by [`load_overrides`].
+pub fn parse_density_raster(value: &str) -> Result<TableRaster, String> {
+    let Some((table, path)) = value.split_once('=') else {
+        return Err(format!(
+            "expected <table>=<path>, e.g. trip=population.asc, got '{value}'"
+        ));
+    };
+    let table = parse_spatial_table(table)?;
+    if path.is_empty() {
+        return Err(format!("missing raster file of {table}"));
+    }
+    Ok(TableRaster {
+        table,
+        path: PathBuf::from(path),
+    })
+}
+
+/// The distribution of one attribute column, see
+/// [`parse_attribute_distribution`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttributeDistribution {
+    pub attribute: Attribute,
+    pub distribution: ValueDistribution,
+}
+
+/// Parse an `--attribute-distribution` value such as `fare=zipf:1.2`
+pub fn parse_attribute_distribution(value: &str) -> Result<AttributeDistribution, String> {
+    let Some((attribute, distribution)) = value.split_once('=') else {
+        return Err(format!(
+            "expected <attribute>=<distribution>, e.g. fare=zipf:1.2, got '{value}'"
+        ));
+    };
+    Ok(AttributeDistribution {
+        attribute: attribute.parse()?,
+        distribution: distribution.parse()?,
+    })
+}
+
+/// Read the density raster of `path`
+fn read_density_raster(path: &Path) -> io::Result<DensityRaster> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Failed reading {}: {e}", path.display()),
+        )
+    })?;
+    DensityRaster::from_ascii_grid(&text).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Failed parsing the density raster {}: {e}", path.display()),
+        )
+    })
+}
+
+/// Return `config`, or `spatialbench-config.yml` in the current directory if
+/// it exists
+pub fn config_path(config: Option<&Path>) -> Option<PathBuf> {
+    if let Some(path) = config {
+        // Use explicitly provided config path
+        Some(path.to_path_buf())
+    } else {
+        // Look for default config file in current directory
+        let default_config = PathBuf::from("spatialbench-config.yml");
+        if default_config.exists() {
+            Some(default_config)
+        } else {
+            None
+        }
+    }
+}
+
+/// Load the spatial overrides from `config`, or from `spatialbench-config.yml`
+/// in the current directory if it exists
+///
+/// `distributions` (`--distribution`) and `rasters` (`--density-raster`)
+/// override the distributions of the tables, `polygon_vertices` (`--polygon-vertices`) the number of vertices
+/// of the building polygons, and `geometry_mix` (`--geometry-mix`) their
+/// types, of the configuration file or the defaults. `attributes`
+/// (`--attribute-distribution`) override the distributions of the attribute
+/// columns of its `attributes` section, and `spatial_correlation`
+/// (`--spatial-correlation`) correlates them with the locations.
+pub fn load_overrides(
+    config: Option<&Path>,
+    distributions: &[TableDistribution],
+    rasters: &[TableRaster],
+    polygon_vertices: Option<RangeInclusive<i32>>,
+    geometry_mix: Option<GeometryMix>,
+    attributes: &[AttributeDistribution],
+    spatial_correlation: Option<SpatialCorrelation>,
+) -> io::Result<()> {
+    let config_path = config_path(config);
+
+    let mut overrides = SpatialOverrides::default();
+    let mut attribute_distributions = AttributeDistributions::default();
+    if let Some(path) = config_path {
+        let text = std::fs::read_to_string(&path).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Failed reading {}: {e}", path.display()),
+            )
+        })?;
+
+        match parse_yaml(&text) {
+            Ok(file_cfg) => {
+                overrides.trip = file_cfg.trip.as_ref().map(|c| c.to_generator());
+                overrides.building = file_cfg.building.as_ref().map(|c| c.to_generator());
+                for (attribute, distribution) in file_cfg.attributes.iter().flatten() {
+                    let parsed =
+                        parse_attribute_distribution(&format!("{attribute}={distribution}"))
+                            .map_err(|e| {
+                                io::Error::new(
+                                    io::ErrorKind::InvalidInput,
+                                    format!(
+                                        "Failed parsing the attributes of {}: {e}",
+                                        path.display()
+                                    ),
+                                )
+                            })?;
+                    attribute_distributions.set(parsed.attribute, parsed.distribution);
+                }
+                info!("Loaded spider configuration from {}", path.display());
+            }
+            Err(e) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Failed parsing spider-config YAML: {e}"),
+                ));
+            }
+        }
+    } else {
+        info!("Using default spider configuration from spider_defaults.rs");
+    }
+
+    for (i, distribution) in distributions.iter().enumerate() {
+        if distributions[..i]
+            .iter()
+            .any(|other| other.table == distribution.table)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "--distribution is given more than once for {}",
+                    distribution.table
+                ),
+            ));
+        }
+        let (dist_type, params) = distribution.preset.distribution();
+        let (generator, default): (_, fn() -> SpatialGenerator) = match distribution.table {
+            Table::Trip => (&mut overrides.trip, SpatialDefaults::trip_default),
+            _ => (&mut overrides.building, SpatialDefaults::building_default),
+        };
+        let spatial_gen = generator.take().unwrap_or_else(default);
+        *generator = Some(spatial_gen.with_distribution_type(dist_type, params));
+    }
+    for (i, raster) in rasters.iter().enumerate() {
+        if rasters[..i].iter().any(|other| other.table == raster.table) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "--density-raster is given more than once for {}",
+                    raster.table
+                ),
+            ));
+        }
+        if distributions.iter().any(|d| d.table == raster.table) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "--density-raster and --distribution are both given for {}",
+                    raster.table
+                ),
+            ));
+        }
+        let density = read_density_raster(&raster.path)?;
+        info!("Loaded density raster from {}", raster.path.display());
+        let (generator, default): (_, fn() -> SpatialGenerator) = match raster.table {
+            Table::Trip => (&mut overrides.trip, SpatialDefaults::trip_default),
+            _ => (&mut overrides.building, SpatialDefaults::building_default),
+        };
+        let spatial_gen = generator.take().unwrap_or_else(default);
+        *generator = Some(spatial_gen.with_distribution(Arc::new(density)));
+    }
+    if let Some(vertices) = polygon_vertices {
+        let building = overrides
+            .building
+            .take()
+            .unwrap_or_else(SpatialDefaults::building_default);
+        if !matches!(building.config.geom_type, GeomType::Polygon) {
+            eprintln!("Warning: --polygon-vertices has no effect, the buildings are not polygons");
+        }
+        overrides.building = Some(building.with_polygon_vertices(vertices));
+    }
+    if let Some(mix) = geometry_mix {
+        let building = overrides
+            .building
+            .take()
+            .unwrap_or_else(SpatialDefaults::building_default);
+        if matches!(building.config.geom_type, GeomType::Point) {
+            eprintln!("Warning: --geometry-mix has no effect, the buildings are points");
+        }
+        overrides.building = Some(building.with_geometry_mix(mix));
+    }
+    for (i, attribute) in attributes.iter().enumerate() {
+        if attributes[..i]
+            .iter()
+            .any(|other| other.attribute == attribute.attribute)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "--attribute-distribution is given more than once for {}",
+                    attribute.attribute
+                ),
+            ));
+        }
+        attribute_distributions.set(attribute.attribute, attribute.distribution);
+    }
+    for attribute in Attribute::ALL {
+        if let Some(distribution) = attribute_distributions.get(attribute) {
+            info!("Drawing the {attribute} values from the {distribution} distribution");
+        }
+    }
+    if let Some(correlation) = spatial_correlation {
+        info!(
+            "Correlating the fares and tips with the pickup locations, strength {} over {} degrees",
+            correlation.strength, correlation.range
+        );
+        attribute_distributions.spatial_correlation = Some(correlation);
+    }
+    set_overrides(overrides);
+    set_attribute_distributions(attribute_distributions);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_polygon_vertices() {
+        assert_eq!(parse_polygon_vertices("4..64"), Ok(4..=64));
+        assert_eq!(parse_polygon_vertices("4..=64"), Ok(4..=64));
+        assert_eq!(parse_polygon_vertices("8"), Ok(8..=8));
+        for (value, error) in [
+            ("2..8", "polygons have at least 3 vertices, got 2"),
+            ("8..4", "empty range of vertices 8..4"),
+            (
+                "4..100000",
+                "polygons have at most 10000 vertices, got 100000",
+            ),
+            ("many", "invalid number of vertices 'many'"),
+        ] {
+            assert_eq!(parse_polygon_vertices(value), Err(error.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_parse_distribution() {
+        assert_eq!(
+            parse_distribution("trip=Hotspots"),
+            Ok(TableDistribution {
+                table: Table::Trip,
+                preset: DistributionPreset::Hotspots
+            })
+        );
+        for (value, error) in [
+            ("hotspots", "expected <table>=<distribution>"),
+            ("zone=uniform", "the zone boundaries are downloaded"),
+            ("driver=uniform", "table driver has no generated geometries"),
+            ("building=normal", "invalid variant: normal"),
+        ] {
+            let err = parse_distribution(value).unwrap_err();
+            assert!(err.starts_with(error), "{value}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_parse_density_raster() {
+        assert_eq!(
+            parse_density_raster("building=data/population.asc"),
+            Ok(TableRaster {
+                table: Table::Building,
+                path: PathBuf::from("data/population.asc")
+            })
+        );
+        for (value, error) in [
+            ("population.asc", "expected <table>=<path>"),
+            ("trip=", "missing raster file of trip"),
+            ("zone=population.asc", "the zone boundaries are downloaded"),
+        ] {
+            let err = parse_density_raster(value).unwrap_err();
+            assert!(err.starts_with(error), "{value}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_parse_attribute_distribution() {
+        assert_eq!(
+            parse_attribute_distribution("fare=lognormal:5.4,0.2"),
+            Ok(AttributeDistribution {
+                attribute: Attribute::Fare,
+                distribution: ValueDistribution::LogNormal {
+                    mu: 5.4,
+                    sigma: 0.2
+                }
+            })
+        );
+        for (value, error) in [
+            ("zipf:1.2", "expected <attribute>=<distribution>"),
+            ("tags=uniform", "Invalid attribute tags"),
+            ("category=pareto", "Invalid distribution pareto"),
+        ] {
+            let err = parse_attribute_distribution(value).unwrap_err();
+            assert!(err.starts_with(error), "{value}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_parse_geometry_mix() {
+        let mix = parse_geometry_mix("building=polygon:0.7,multipolygon:0.3").unwrap();
+        assert_eq!(mix, "polygon:0.7,multipolygon:0.3".parse().unwrap());
+        for (value, error) in [
+            ("polygon:1", "expected <table>=<type>:<fraction>"),
+            ("zone=polygon:1", "the zone boundaries are downloaded"),
+            ("trip=point:1", "the trip locations are points"),
+            (
+                "vehicle=polygon:1",
+                "table vehicle has no generated geometries",
+            ),
+            ("building=polygon:0.7", "the fractions must add up to 1"),
+        ] {
+            let err = parse_geometry_mix(value).unwrap_err();
+            assert!(err.starts_with(error), "{value}: {err}");
+        }
+    }
+}
@@ -0,0 +1,367 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Sidecar spatial indexes of the Parquet row groups (`--spatial-index`)
+//!
+//! Once the tables are written, [`SpatialIndex::build`] reads the first
+//! geometry column of the Parquet files of a table and packs the bounding
+//! box of each row group into a Hilbert R-tree, written in the
+//! [Flatbush](https://github.com/mourner/flatbush) format to
+//! `_index/{table}.flatbush` in the output directory. The items of the tree
+//! are described by `_index/{table}.json`, in the same order: the file, row
+//! group, row count and bounding box of each, so harnesses can prune files
+//! and row groups with the index without building it themselves.
+
+use crate::flatgeobuf::hilbert_bbox;
+use crate::geoparquet::{is_wkb_type, wkb_bbox, wkb_values};
+use crate::manifest::object_url;
+use crate::object_store_writer::{ObjectStoreWriter, UploadOptions};
+use crate::parquet::IntoSize;
+use crate::verify::table_files;
+use parquet::arrow::arrow_reader::{ArrowReaderMetadata, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::ProjectionMask;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use url::Url;
+
+/// Directory of the indexes in the output directory
+pub const INDEX_DIR: &str = "_index";
+
+/// Number of children of each node of the tree, the Flatbush default
+const NODE_SIZE: u16 = 16;
+
+/// First byte of a Flatbush index
+const FLATBUSH_MAGIC: u8 = 0xfb;
+
+/// Version of the Flatbush format written
+const FLATBUSH_VERSION: u8 = 3;
+
+/// Flatbush code of the type of the coordinates, `Float64Array`
+const FLATBUSH_FLOAT64: u8 = 8;
+
+/// Spatial index of the row groups of the Parquet files of a table
+#[derive(Debug, Serialize)]
+pub struct SpatialIndex {
+    table: String,
+    /// Name of the geometry column the bounding boxes are of
+    column: String,
+    node_size: u16,
+    /// The items of the tree, in the order of their indexes in the tree
+    items: Vec<IndexItem>,
+}
+
+/// A row group of a [`SpatialIndex`]
+#[derive(Debug, Serialize, PartialEq)]
+pub struct IndexItem {
+    /// Path relative to the output directory, with `/` separators
+    path: String,
+    row_group: usize,
+    rows: i64,
+    /// `[xmin, ymin, xmax, ymax]` of the geometries of the row group
+    bbox: [f64; 4],
+}
+
+impl SpatialIndex {
+    /// Index the row groups of the Parquet files of the table `name` in
+    /// `output_dir`
+    ///
+    /// Returns None if the table has no geometry column or no geometries.
+    /// Row groups without geometries (all null or empty) are not indexed.
+    pub fn build(output_dir: &Path, name: &str) -> io::Result<Option<Self>> {
+        let mut column = None;
+        let mut items = vec![];
+        for path in table_files(output_dir, name)? {
+            let file = File::open(&path)?;
+            let metadata = ArrowReaderMetadata::load(&file, Default::default())?;
+            let Some(index) = metadata
+                .schema()
+                .fields()
+                .iter()
+                .position(|field| is_wkb_type(field.data_type()))
+            else {
+                continue;
+            };
+            column.get_or_insert_with(|| metadata.schema().field(index).name().clone());
+            let relative = path
+                .strip_prefix(output_dir)
+                .unwrap_or(&path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let num_row_groups = metadata.metadata().num_row_groups();
+            for row_group in 0..num_row_groups {
+                let mask = ProjectionMask::roots(metadata.parquet_schema(), [index]);
+                let reader = ParquetRecordBatchReaderBuilder::new_with_metadata(
+                    file.try_clone()?,
+                    metadata.clone(),
+                )
+                .with_row_groups(vec![row_group])
+                .with_projection(mask)
+                .build()?;
+                let mut bbox: Option<[f64; 4]> = None;
+                for batch in reader {
+                    let batch = batch.map_err(io::Error::other)?;
+                    for other in wkb_values(batch.column(0)).flatten().filter_map(wkb_bbox) {
+                        bbox = Some(match bbox {
+                            Some(bbox) => union(&bbox, &other),
+                            None => other,
+                        });
+                    }
+                }
+                if let Some(bbox) = bbox {
+                    items.push(IndexItem {
+                        path: relative.clone(),
+                        row_group,
+                        rows: metadata.metadata().row_group(row_group).num_rows(),
+                        bbox,
+                    });
+                }
+            }
+        }
+        Ok(column.filter(|_| !items.is_empty()).map(|column| Self {
+            table: name.to_string(),
+            column,
+            node_size: NODE_SIZE,
+            items,
+        }))
+    }
+
+    /// Return the paths of the index and of its items, relative to the
+    /// output directory
+    fn paths(&self) -> [String; 2] {
+        [
+            format!("{INDEX_DIR}/{}.flatbush", self.table),
+            format!("{INDEX_DIR}/{}.json", self.table),
+        ]
+    }
+
+    fn contents(&self) -> [Vec<u8>; 2] {
+        let boxes: Vec<_> = self.items.iter().map(|item| item.bbox).collect();
+        let json = serde_json::to_string_pretty(self).expect("indexes are serializable") + "\n";
+        [flatbush(&boxes, NODE_SIZE), json.into_bytes()]
+    }
+
+    /// Write the index to `_index/{table}.flatbush` and its items to
+    /// `_index/{table}.json` in `output_dir`
+    pub fn write(&self, output_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(output_dir.join(INDEX_DIR))?;
+        for (path, contents) in self.paths().iter().zip(self.contents()) {
+            fs::write(output_dir.join(path), contents)?;
+        }
+        Ok(())
+    }
+
+    /// Upload the index and its items to `_index/` below `url`
+    pub async fn upload(&self, url: &Url, options: UploadOptions) -> io::Result<()> {
+        for (path, contents) in self.paths().iter().zip(self.contents()) {
+            let url = object_url(url, path)?;
+            let options = options.clone();
+            tokio::task::spawn_blocking(move || {
+                let mut writer = ObjectStoreWriter::try_new(&url, options)?;
+                writer.write_all(&contents)?;
+                writer.into_size().map(|_| ())
+            })
+            .await
+            .map_err(|e| io::Error::other(format!("Task Panic: {e}")))??;
+        }
+        Ok(())
+    }
+}
+
+fn union(bbox: &[f64; 4], other: &[f64; 4]) -> [f64; 4] {
+    [
+        bbox[0].min(other[0]),
+        bbox[1].min(other[1]),
+        bbox[2].max(other[2]),
+        bbox[3].max(other[3]),
+    ]
+}
+
+/// Return the Flatbush index of `boxes`, a packed Hilbert R-tree
+///
+/// The index of each box in the tree is its position in `boxes`. The nodes
+/// are written from the leaves (sorted along the Hilbert curve) up to the
+/// root, as the reference implementation does, so its `Flatbush.from` can
+/// load the index. `boxes` must not be empty.
+fn flatbush(boxes: &[[f64; 4]], node_size: u16) -> Vec<u8> {
+    let num_items = boxes.len();
+    let node_size = node_size as usize;
+
+    // end of each level within the nodes, from the leaves up to the root
+    let mut level_ends = vec![num_items];
+    let mut n = num_items;
+    loop {
+        n = n.div_ceil(node_size);
+        level_ends.push(level_ends.last().unwrap() + n);
+        if n == 1 {
+            break;
+        }
+    }
+    let num_nodes = *level_ends.last().unwrap();
+
+    let extent = boxes.iter().fold(
+        [
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ],
+        |extent, bbox| union(&extent, bbox),
+    );
+    let mut order: Vec<usize> = (0..num_items).collect();
+    // a single node needs no sorting
+    if num_items > node_size {
+        order.sort_by_key(|&i| hilbert_bbox(&boxes[i], &extent));
+    }
+
+    let mut nodes: Vec<([f64; 4], u32)> = Vec::with_capacity(num_nodes);
+    nodes.extend(order.iter().map(|&i| (boxes[i], i as u32)));
+    let mut start = 0;
+    for &end in &level_ends[..level_ends.len() - 1] {
+        for first_child in (start..end).step_by(node_size) {
+            let last_child = (first_child + node_size).min(end);
+            let bbox = nodes[first_child..last_child]
+                .iter()
+                .fold(nodes[first_child].0, |bbox, (child, _)| union(&bbox, child));
+            // nodes point to their first child by the position of its box in
+            // the coordinates
+            nodes.push((bbox, (first_child * 4) as u32));
+        }
+        start = end;
+    }
+    debug_assert_eq!(nodes.len(), num_nodes);
+
+    let small_indexes = num_nodes < 16384;
+    let index_bytes = if small_indexes { 2 } else { 4 };
+    let mut buffer = Vec::with_capacity(8 + num_nodes * (32 + index_bytes));
+    buffer.push(FLATBUSH_MAGIC);
+    buffer.push((FLATBUSH_VERSION << 4) + FLATBUSH_FLOAT64);
+    buffer.extend_from_slice(&(node_size as u16).to_le_bytes());
+    buffer.extend_from_slice(&(num_items as u32).to_le_bytes());
+    for (bbox, _) in &nodes {
+        for value in bbox {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    for (_, index) in &nodes {
+        if small_indexes {
+            buffer.extend_from_slice(&(*index as u16).to_le_bytes());
+        } else {
+            buffer.extend_from_slice(&index.to_le_bytes());
+        }
+    }
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Search a Flatbush index as the reference implementation does,
+    /// returning the indexes of the boxes intersecting `query`
+    fn search(index: &[u8], query: [f64; 4]) -> Vec<usize> {
+        assert_eq!(index[0], FLATBUSH_MAGIC);
+        assert_eq!(index[1], 0x38);
+        let node_size = u16::from_le_bytes([index[2], index[3]]) as usize;
+        let num_items = u32::from_le_bytes(index[4..8].try_into().unwrap()) as usize;
+        let mut level_ends = vec![num_items * 4];
+        let mut n = num_items;
+        loop {
+            n = n.div_ceil(node_size);
+            level_ends.push(level_ends.last().unwrap() + n * 4);
+            if n == 1 {
+                break;
+            }
+        }
+        let num_nodes = level_ends.last().unwrap() / 4;
+        let value = |pos: usize| {
+            let start = 8 + pos * 8;
+            f64::from_le_bytes(index[start..start + 8].try_into().unwrap())
+        };
+        let indexes = 8 + num_nodes * 32;
+        let node_index = |pos: usize| {
+            if num_nodes < 16384 {
+                let start = indexes + pos / 4 * 2;
+                u16::from_le_bytes([index[start], index[start + 1]]) as usize
+            } else {
+                let start = indexes + pos / 4 * 4;
+                u32::from_le_bytes(index[start..start + 4].try_into().unwrap()) as usize
+            }
+        };
+        assert_eq!(
+            index.len(),
+            indexes + num_nodes * if num_nodes < 16384 { 2 } else { 4 }
+        );
+
+        let mut results = vec![];
+        let mut queue = vec![(num_nodes - 1) * 4];
+        while let Some(node) = queue.pop() {
+            let level_end = *level_ends.iter().find(|&&end| end > node).unwrap();
+            let end = (node + node_size * 4).min(level_end);
+            for pos in (node..end).step_by(4) {
+                if query[2] < value(pos)
+                    || query[3] < value(pos + 1)
+                    || query[0] > value(pos + 2)
+                    || query[1] > value(pos + 3)
+                {
+                    continue;
+                }
+                if node >= num_items * 4 {
+                    queue.push(node_index(pos));
+                } else {
+                    results.push(node_index(pos));
+                }
+            }
+        }
+        results.sort();
+        results
+    }
+
+    #[test]
+    fn test_flatbush() {
+        let boxes: Vec<[f64; 4]> = (0..1000)
+            .map(|i| {
+                let (x, y) = ((i % 40) as f64, (i / 40) as f64);
+                [x, y, x + 0.5, y + 0.5]
+            })
+            .collect();
+        let index = flatbush(&boxes, NODE_SIZE);
+        let query = [10.2, 3.2, 12.7, 4.7];
+        let expected: Vec<usize> = (0..boxes.len())
+            .filter(|&i| {
+                let bbox = boxes[i];
+                bbox[0] <= query[2]
+                    && bbox[1] <= query[3]
+                    && bbox[2] >= query[0]
+                    && bbox[3] >= query[1]
+            })
+            .collect();
+        assert_eq!(expected, [130, 131, 132, 170, 171, 172]);
+        assert_eq!(search(&index, query), expected);
+        assert_eq!(search(&index, [-1.0, -1.0, 100.0, 100.0]).len(), 1000);
+        assert!(search(&index, [100.0, 100.0, 101.0, 101.0]).is_empty());
+
+        // a single node
+        let index = flatbush(&boxes[..3], NODE_SIZE);
+        assert_eq!(index.len(), 8 + 4 * 32 + 4 * 2);
+        assert_eq!(search(&index, [0.0, 0.0, 1.2, 0.2]), [0, 1]);
+    }
+}
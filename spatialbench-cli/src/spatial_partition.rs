@@ -0,0 +1,589 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Spatially partitioned Parquet output
+//!
+//! Rows are assigned to cells by the center of the bounding box of their
+//! geometry, and each cell is written to its own partition directory (e.g.
+//! `trip/cell=3/part-0.parquet`) using the writers in [`crate::hive`].
+//!
+//! The cells tile the whole world, so every geometry falls in exactly one
+//! cell, and are built from a sample of the table so that they are the same
+//! for every part of a table. The bounds of each cell are written to a
+//! `_cells.json` manifest in the table directory for engines that prune
+//! partitions by their bounds.
+
+use crate::geoparquet::{is_wkb_type, wkb_center, wkb_values};
+use crate::hive::{take_partitions, Partitioner, DEFAULT_PARTITION};
+use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
+use arrow::error::ArrowError;
+use log::debug;
+use serde::Serialize;
+use spatialbench::spatial::crs;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// Name of the manifest with the bounds of each cell
+pub const MANIFEST_FILE: &str = "_cells.json";
+
+/// Maximum number of cells of any scheme
+const MAX_CELLS: usize = 65536;
+
+/// Default number of cells of a KDB-tree
+const DEFAULT_KDB_CELLS: usize = 16;
+
+/// Return the extent of the coordinates in the CRS set with
+/// [`crs::set_crs`], which the cells tile
+fn world() -> Rect {
+    let [xmin, ymin, xmax, ymax] = crs::crs().bounds();
+    Rect {
+        xmin,
+        ymin,
+        xmax,
+        ymax,
+    }
+}
+
+/// How to spatially partition the rows of a table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpatialScheme {
+    /// A uniform grid of `columns` x `rows` cells over the extent of the data
+    Grid { columns: usize, rows: usize },
+    /// A quadtree at most `depth` levels deep, splitting only the nodes with
+    /// more than their share of the data
+    Quadtree { depth: u32 },
+    /// A KDB-tree with `cells` cells of roughly equal numbers of rows
+    Kdb { cells: usize },
+}
+
+impl Display for SpatialScheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpatialScheme::Grid { columns, rows } => write!(f, "grid:{columns}x{rows}"),
+            SpatialScheme::Quadtree { depth } => write!(f, "quadtree:{depth}"),
+            SpatialScheme::Kdb { cells } => write!(f, "kdb:{cells}"),
+        }
+    }
+}
+
+/// Parse a spatial partitioning scheme: `grid:NxM`, `quadtree:DEPTH` or
+/// `kdb[:CELLS]`
+pub fn parse_spatial_scheme(value: &str) -> Result<SpatialScheme, String> {
+    let invalid = || {
+        format!("invalid spatial partitioning '{value}', expected grid:NxM, quadtree:DEPTH or kdb[:CELLS]")
+    };
+    let (name, arg) = match value.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (value, None),
+    };
+    let scheme = match (name, arg) {
+        ("grid", Some(arg)) => {
+            let (columns, rows) = arg.split_once(['x', 'X']).ok_or_else(invalid)?;
+            SpatialScheme::Grid {
+                columns: columns.parse().map_err(|_| invalid())?,
+                rows: rows.parse().map_err(|_| invalid())?,
+            }
+        }
+        ("quadtree", Some(arg)) => SpatialScheme::Quadtree {
+            depth: arg.parse().map_err(|_| invalid())?,
+        },
+        ("kdb", None) => SpatialScheme::Kdb {
+            cells: DEFAULT_KDB_CELLS,
+        },
+        ("kdb", Some(arg)) => SpatialScheme::Kdb {
+            cells: arg.parse().map_err(|_| invalid())?,
+        },
+        _ => return Err(invalid()),
+    };
+    let max_cells = match scheme {
+        SpatialScheme::Grid { columns, rows } => columns.checked_mul(rows),
+        SpatialScheme::Quadtree { depth } => 4usize.checked_pow(depth),
+        SpatialScheme::Kdb { cells } => Some(cells),
+    };
+    match max_cells {
+        Some(1..=MAX_CELLS) => Ok(scheme),
+        _ => Err(format!(
+            "invalid spatial partitioning '{value}', must have between 1 and {MAX_CELLS} cells"
+        )),
+    }
+}
+
+/// An axis aligned rectangle
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct Rect {
+    xmin: f64,
+    ymin: f64,
+    xmax: f64,
+    ymax: f64,
+}
+
+impl Rect {
+    /// Return the extent of `points`, or None if there are none
+    fn extent(points: &[(f64, f64)]) -> Option<Rect> {
+        let (&(x, y), rest) = points.split_first()?;
+        let mut rect = Rect {
+            xmin: x,
+            ymin: y,
+            xmax: x,
+            ymax: y,
+        };
+        for &(x, y) in rest {
+            rect.xmin = rect.xmin.min(x);
+            rect.ymin = rect.ymin.min(y);
+            rect.xmax = rect.xmax.max(x);
+            rect.ymax = rect.ymax.max(y);
+        }
+        Some(rect)
+    }
+
+    /// Split the rectangle at `value` along `axis`, returning the low and
+    /// high sides
+    fn split(&self, axis: Axis, value: f64) -> (Rect, Rect) {
+        match axis {
+            Axis::X => (
+                Rect {
+                    xmax: value,
+                    ..*self
+                },
+                Rect {
+                    xmin: value,
+                    ..*self
+                },
+            ),
+            Axis::Y => (
+                Rect {
+                    ymax: value,
+                    ..*self
+                },
+                Rect {
+                    ymin: value,
+                    ..*self
+                },
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    X,
+    Y,
+}
+
+impl Axis {
+    fn coord(&self, (x, y): (f64, f64)) -> f64 {
+        match self {
+            Axis::X => x,
+            Axis::Y => y,
+        }
+    }
+}
+
+/// A node of the binary space partitioning tree that all schemes are built as
+#[derive(Debug)]
+enum Node {
+    /// A cell, identified by its index
+    Leaf(usize),
+    /// Points with `axis` coordinate less than `value` are in `low`, the
+    /// others in `high`
+    Split {
+        axis: Axis,
+        value: f64,
+        low: Box<Node>,
+        high: Box<Node>,
+    },
+}
+
+/// The cells of a spatial partitioning
+#[derive(Debug)]
+pub struct SpatialPartitioning {
+    scheme: SpatialScheme,
+    root: Node,
+    /// The bounds of each cell
+    cells: Vec<Rect>,
+}
+
+impl SpatialPartitioning {
+    /// Build the cells of `scheme` for the distribution of `sample`, the
+    /// centers of a sample of the geometries
+    pub fn new(scheme: SpatialScheme, mut sample: Vec<(f64, f64)>) -> Self {
+        let mut builder = TreeBuilder { cells: vec![] };
+        // the splits are placed within the extent of the sample, but the
+        // outermost cells extend to cover the world
+        let world = world();
+        let extent = Rect::extent(&sample).unwrap_or(world);
+        let root = match scheme {
+            SpatialScheme::Grid { columns, rows } => {
+                builder.grid(world, extent, (0, columns), (0, rows), (columns, rows))
+            }
+            SpatialScheme::Quadtree { depth } => {
+                let capacity = sample.len() >> (2 * depth);
+                builder.quadtree(world, extent, &sample, depth, capacity)
+            }
+            SpatialScheme::Kdb { cells } => builder.kdb(world, &mut sample, cells),
+        };
+        Self {
+            scheme,
+            root,
+            cells: builder.cells,
+        }
+    }
+
+    /// Return the number of cells
+    pub fn num_cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Return the index of the cell containing `point`
+    pub fn cell(&self, point: (f64, f64)) -> usize {
+        let mut node = &self.root;
+        loop {
+            match node {
+                Node::Leaf(cell) => return *cell,
+                Node::Split {
+                    axis,
+                    value,
+                    low,
+                    high,
+                } => {
+                    node = if axis.coord(point) < *value {
+                        low
+                    } else {
+                        high
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the JSON manifest with the bounds of each cell, for the
+    /// partitions of `geometry_column`
+    pub fn manifest(&self, geometry_column: &str) -> String {
+        #[derive(Serialize)]
+        struct Manifest<'a> {
+            scheme: String,
+            geometry_column: &'a str,
+            cells: Vec<Cell>,
+        }
+        #[derive(Serialize)]
+        struct Cell {
+            id: usize,
+            partition: String,
+            bbox: [f64; 4],
+        }
+        let manifest = Manifest {
+            scheme: self.scheme.to_string(),
+            geometry_column,
+            cells: self
+                .cells
+                .iter()
+                .enumerate()
+                .map(|(id, rect)| Cell {
+                    id,
+                    partition: cell_partition(id),
+                    bbox: [rect.xmin, rect.ymin, rect.xmax, rect.ymax],
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&manifest).expect("manifest is serializable")
+    }
+}
+
+/// Return the partition directory name of cell `id`
+fn cell_partition(id: usize) -> String {
+    format!("cell={id}")
+}
+
+/// Builds the tree of a [`SpatialPartitioning`], numbering the cells in the
+/// order they are created
+struct TreeBuilder {
+    cells: Vec<Rect>,
+}
+
+impl TreeBuilder {
+    fn leaf(&mut self, bounds: Rect) -> Node {
+        self.cells.push(bounds);
+        Node::Leaf(self.cells.len() - 1)
+    }
+
+    fn split(&mut self, axis: Axis, value: f64, low: Node, high: Node) -> Node {
+        Node::Split {
+            axis,
+            value,
+            low: Box::new(low),
+            high: Box::new(high),
+        }
+    }
+
+    /// Build the cells for `columns` x `rows` of a grid of `size` cells
+    /// over `extent`, which are within `bounds`
+    fn grid(
+        &mut self,
+        bounds: Rect,
+        extent: Rect,
+        columns: (usize, usize),
+        rows: (usize, usize),
+        size: (usize, usize),
+    ) -> Node {
+        let (axis, (start, end), count, min, max) = if columns.1 - columns.0 > 1 {
+            (Axis::X, columns, size.0, extent.xmin, extent.xmax)
+        } else if rows.1 - rows.0 > 1 {
+            (Axis::Y, rows, size.1, extent.ymin, extent.ymax)
+        } else {
+            return self.leaf(bounds);
+        };
+        let mid = (start + end) / 2;
+        let value = min + (max - min) * mid as f64 / count as f64;
+        let (low_bounds, high_bounds) = bounds.split(axis, value);
+        let (low, high) = match axis {
+            Axis::X => (
+                self.grid(low_bounds, extent, (start, mid), rows, size),
+                self.grid(high_bounds, extent, (mid, end), rows, size),
+            ),
+            Axis::Y => (
+                self.grid(low_bounds, extent, columns, (start, mid), size),
+                self.grid(high_bounds, extent, columns, (mid, end), size),
+            ),
+        };
+        self.split(axis, value, low, high)
+    }
+
+    /// Build a quadtree node for `extent` (within `bounds`), splitting it
+    /// into quadrants if it has more than `capacity` of the `points`
+    fn quadtree(
+        &mut self,
+        bounds: Rect,
+        extent: Rect,
+        points: &[(f64, f64)],
+        depth: u32,
+        capacity: usize,
+    ) -> Node {
+        if depth == 0 || points.len() <= capacity.max(1) {
+            return self.leaf(bounds);
+        }
+        let cx = (extent.xmin + extent.xmax) / 2.0;
+        let cy = (extent.ymin + extent.ymax) / 2.0;
+        let (west, east) = bounds.split(Axis::X, cx);
+        let (west_extent, east_extent) = extent.split(Axis::X, cx);
+        let (west_points, east_points): (Vec<_>, Vec<_>) =
+            points.iter().partition(|(x, _)| *x < cx);
+
+        let mut half = |bounds: Rect, extent: Rect, points: Vec<(f64, f64)>| {
+            let (south, north) = bounds.split(Axis::Y, cy);
+            let (south_extent, north_extent) = extent.split(Axis::Y, cy);
+            let (south_points, north_points): (Vec<_>, Vec<_>) =
+                points.into_iter().partition(|(_, y)| *y < cy);
+            let low = self.quadtree(south, south_extent, &south_points, depth - 1, capacity);
+            let high = self.quadtree(north, north_extent, &north_points, depth - 1, capacity);
+            self.split(Axis::Y, cy, low, high)
+        };
+        let low = half(west, west_extent, west_points);
+        let high = half(east, east_extent, east_points);
+        self.split(Axis::X, cx, low, high)
+    }
+
+    /// Build a KDB-tree node with `cells` cells within `bounds`, splitting
+    /// the `points` at the quantile along their longest axis
+    fn kdb(&mut self, bounds: Rect, points: &mut [(f64, f64)], cells: usize) -> Node {
+        let Some(extent) = Rect::extent(points) else {
+            return self.leaf(bounds);
+        };
+        if cells <= 1 || points.len() < 2 {
+            return self.leaf(bounds);
+        }
+        let axis = if extent.xmax - extent.xmin >= extent.ymax - extent.ymin {
+            Axis::X
+        } else {
+            Axis::Y
+        };
+        points.sort_unstable_by(|a, b| axis.coord(*a).total_cmp(&axis.coord(*b)));
+        let low_cells = cells / 2;
+        let split = points.len() * low_cells / cells;
+        let value = axis.coord(points[split]);
+        // all the points with the split value go to the high side
+        let split = points.partition_point(|point| axis.coord(*point) < value);
+        if split == 0 {
+            // too many duplicates to split here
+            return self.leaf(bounds);
+        }
+        let (low_bounds, high_bounds) = bounds.split(axis, value);
+        let (low_points, high_points) = points.split_at_mut(split);
+        let low = self.kdb(low_bounds, low_points, low_cells);
+        let high = self.kdb(high_bounds, high_points, cells - low_cells);
+        self.split(axis, value, low, high)
+    }
+}
+
+/// Splits batches by the cell of the first geometry column
+#[derive(Debug)]
+pub struct SpatialPartitioner {
+    /// Index of the geometry column
+    column: usize,
+    schema: SchemaRef,
+    partitioning: SpatialPartitioning,
+}
+
+impl SpatialPartitioner {
+    /// Create a partitioner for the first geometry column of `schema`, with
+    /// the cells of `scheme` built from the geometries of `sample`
+    ///
+    /// Returns `None` if the schema has no geometry column.
+    pub fn try_new(
+        schema: &SchemaRef,
+        scheme: SpatialScheme,
+        sample: impl Iterator<Item = RecordBatch>,
+    ) -> Option<Self> {
+        let column = schema
+            .fields()
+            .iter()
+            .position(|field| is_wkb_type(field.data_type()))?;
+        let sample = sample
+            .flat_map(|batch| {
+                wkb_values(batch.column(column))
+                    .filter_map(|wkb| wkb.and_then(wkb_center))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let partitioning = SpatialPartitioning::new(scheme, sample);
+        debug!(
+            "Built {} cells of {scheme} for {}",
+            partitioning.num_cells(),
+            schema.field(column).name()
+        );
+        Some(Self {
+            column,
+            schema: Arc::clone(schema),
+            partitioning,
+        })
+    }
+
+    /// Return the JSON manifest with the bounds of each cell
+    pub fn manifest(&self) -> String {
+        let geometry_column = self.schema.field(self.column).name();
+        self.partitioning.manifest(geometry_column)
+    }
+}
+
+impl Partitioner for SpatialPartitioner {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// The partition directory names are `cell={id}`, with null and empty
+    /// geometries in the default partition
+    fn split(&self, batch: &RecordBatch) -> Result<Vec<(String, RecordBatch)>, ArrowError> {
+        let mut cells: BTreeMap<Option<usize>, Vec<u32>> = BTreeMap::new();
+        for (row, wkb) in wkb_values(batch.column(self.column)).enumerate() {
+            let cell = wkb
+                .and_then(wkb_center)
+                .map(|point| self.partitioning.cell(point));
+            cells.entry(cell).or_default().push(row as u32);
+        }
+        let partitions = cells
+            .into_iter()
+            .map(|(cell, rows)| {
+                let partition = match cell {
+                    Some(cell) => cell_partition(cell),
+                    None => format!("cell={DEFAULT_PARTITION}"),
+                };
+                (partition, rows)
+            })
+            .collect();
+        take_partitions(batch.clone(), partitions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid of `n` x `n` points in [0, 1) x [0, 1)
+    fn uniform(n: usize) -> Vec<(f64, f64)> {
+        (0..n * n)
+            .map(|i| ((i % n) as f64 / n as f64, (i / n) as f64 / n as f64))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_scheme() {
+        assert_eq!(
+            parse_spatial_scheme("grid:4x2"),
+            Ok(SpatialScheme::Grid {
+                columns: 4,
+                rows: 2
+            })
+        );
+        assert_eq!(
+            parse_spatial_scheme("quadtree:3"),
+            Ok(SpatialScheme::Quadtree { depth: 3 })
+        );
+        assert_eq!(
+            parse_spatial_scheme("kdb"),
+            Ok(SpatialScheme::Kdb { cells: 16 })
+        );
+        assert!(parse_spatial_scheme("grid:0x2").is_err());
+        assert!(parse_spatial_scheme("quadtree:9").is_err());
+        assert!(parse_spatial_scheme("hilbert").is_err());
+    }
+
+    #[test]
+    fn test_grid() {
+        let scheme = SpatialScheme::Grid {
+            columns: 4,
+            rows: 2,
+        };
+        let partitioning = SpatialPartitioning::new(scheme, uniform(8));
+        assert_eq!(partitioning.num_cells(), 8);
+        // the grid covers the extent of the sample ([0, 0.875]), but the
+        // outer cells extend to the world bounds
+        let cell = partitioning.cell((-100.0, -50.0));
+        assert_eq!(partitioning.cells[cell].xmin, -180.0);
+        assert_eq!(partitioning.cells[cell].ymin, -90.0);
+        assert_eq!(partitioning.cells[cell].xmax, 0.875 / 4.0);
+        assert_eq!(partitioning.cells[cell].ymax, 0.875 / 2.0);
+        // every cell has the same number of points
+        let mut counts = vec![0; 8];
+        for point in uniform(8) {
+            counts[partitioning.cell(point)] += 1;
+        }
+        assert_eq!(counts, vec![8; 8]);
+    }
+
+    #[test]
+    fn test_quadtree_and_kdb_balance_skewed_data() {
+        // most points are in one corner
+        let mut sample = uniform(32);
+        sample.extend(uniform(64).into_iter().map(|(x, y)| (x / 10.0, y / 10.0)));
+
+        let quadtree =
+            SpatialPartitioning::new(SpatialScheme::Quadtree { depth: 3 }, sample.clone());
+        assert!(quadtree.num_cells() > 4 && quadtree.num_cells() < 64);
+
+        let kdb = SpatialPartitioning::new(SpatialScheme::Kdb { cells: 8 }, sample.clone());
+        assert_eq!(kdb.num_cells(), 8);
+        let mut counts = [0usize; 8];
+        for point in &sample {
+            counts[kdb.cell(*point)] += 1;
+        }
+        let expected = sample.len() / 8;
+        assert!(counts
+            .iter()
+            .all(|&count| count.abs_diff(expected) <= expected / 10));
+    }
+}
@@ -0,0 +1,152 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Sorting rows along a space filling curve
+//!
+//! Rows are ordered by the Hilbert or Z-order (Morton) value of the center of
+//! the bounding box of their geometry, so that rows near each other in the
+//! file are near each other in space, and the statistics of pages and row
+//! groups cover small areas.
+//!
+//! The rows of each whole file are sorted by their curve values with the
+//! external merge sort of [`crate::key_sort`].
+
+use crate::geoparquet::{is_wkb_type, wkb_center, wkb_values};
+use arrow::array::{ArrayRef, RecordBatch, UInt64Array};
+use clap::ValueEnum;
+use spatialbench::spatial::crs;
+use std::sync::Arc;
+
+/// The space filling curve to sort rows along
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum SpatialOrder {
+    /// Hilbert curve, which preserves locality better than Z-order
+    Hilbert,
+    /// Z-order (Morton) curve, which interleaves the bits of the coordinates
+    Zorder,
+}
+
+impl SpatialOrder {
+    /// Return the value along the curve of a point within `bounds`, the
+    /// extent of the coordinates
+    fn value(&self, (x, y): (f64, f64), bounds: &[f64; 4]) -> u64 {
+        let x = quantize(x, bounds[0], bounds[2]);
+        let y = quantize(y, bounds[1], bounds[3]);
+        match self {
+            SpatialOrder::Hilbert => hilbert_index(x, y),
+            SpatialOrder::Zorder => zorder_index(x, y),
+        }
+    }
+}
+
+/// Map `value` in `[min, max]` to the full range of a `u32`, clamping values
+/// outside the range
+fn quantize(value: f64, min: f64, max: f64) -> u32 {
+    let scaled = (value - min) / (max - min) * u32::MAX as f64;
+    // `as` saturates, and maps NaN to 0
+    scaled as u32
+}
+
+/// Return the index of `(x, y)` along the Hilbert curve filling the
+/// `2^32` x `2^32` grid
+pub fn hilbert_index(x: u32, y: u32) -> u64 {
+    const N: u64 = 1 << 32;
+    let (mut x, mut y) = (x as u64, y as u64);
+    let mut index = 0;
+    let mut s = N / 2;
+    while s > 0 {
+        let rx = (x & s != 0) as u64;
+        let ry = (y & s != 0) as u64;
+        index += s * s * ((3 * rx) ^ ry);
+        // rotate the quadrant so the curve within it has the right orientation
+        if ry == 0 {
+            if rx == 1 {
+                x = N - 1 - x;
+                y = N - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+/// Return the index of `(x, y)` along the Z-order curve, which interleaves
+/// the bits of `x` (even bits) and `y` (odd bits)
+pub fn zorder_index(x: u32, y: u32) -> u64 {
+    /// Spread the bits of `v` to the even bits of the result
+    fn spread(v: u32) -> u64 {
+        let mut v = v as u64;
+        v = (v | (v << 16)) & 0x0000_FFFF_0000_FFFF;
+        v = (v | (v << 8)) & 0x00FF_00FF_00FF_00FF;
+        v = (v | (v << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+        v = (v | (v << 2)) & 0x3333_3333_3333_3333;
+        v = (v | (v << 1)) & 0x5555_5555_5555_5555;
+        v
+    }
+    spread(x) | (spread(y) << 1)
+}
+
+/// Return the value along the curve `order` of the center of the first
+/// geometry column of each row of `batch`
+///
+/// Rows with null or empty geometries have the largest value, so they are
+/// sorted last. If the batch has no geometry column, all the values are 0.
+pub fn curve_values(batch: &RecordBatch, order: SpatialOrder) -> ArrayRef {
+    let column = batch
+        .schema()
+        .fields()
+        .iter()
+        .position(|field| is_wkb_type(field.data_type()));
+    let Some(column) = column else {
+        return Arc::new(UInt64Array::from(vec![0; batch.num_rows()]));
+    };
+    let bounds = crs::crs().bounds();
+    let values: UInt64Array = wkb_values(batch.column(column))
+        .map(|wkb| {
+            let value = wkb
+                .and_then(wkb_center)
+                .map(|point| order.value(point, &bounds))
+                .unwrap_or(u64::MAX);
+            Some(value)
+        })
+        .collect();
+    Arc::new(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curves() {
+        assert_eq!(zorder_index(1, 0), 1);
+        assert_eq!(zorder_index(0, 1), 2);
+        assert_eq!(zorder_index(3, 3), 15);
+        assert_eq!(zorder_index(u32::MAX, u32::MAX), u64::MAX);
+
+        // visiting the cells of a 4 x 4 grid in Hilbert order only ever
+        // moves to an adjacent cell
+        let mut cells: Vec<(u32, u32)> = (0..16).map(|i| (i % 4, i / 4)).collect();
+        cells.sort_by_key(|&(x, y)| hilbert_index(x << 30, y << 30));
+        assert_eq!(cells[0], (0, 0));
+        for pair in cells.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1, "{a:?} -> {b:?}");
+        }
+    }
+}
@@ -18,8 +18,11 @@
 //! Implementations of [`Source`] for generating data in TBL format
 
 use super::generate::Source;
+use super::throttle::throttle;
 use spatialbench::generators::{
-    BuildingGenerator, CustomerGenerator, DriverGenerator, TripGenerator, VehicleGenerator,
+    BuildingGenerator, CityGenerator, CountryGenerator, CustomerGenerator, DriverGenerator,
+    LandmassGenerator, PoiGenerator, PointCloudGenerator, RasterGenerator, RegionGenerator,
+    RoadGenerator, TrajectoryGenerator, TripGenerator, VehicleGenerator,
 };
 use std::io::Write;
 
@@ -44,6 +47,7 @@ macro_rules! define_tbl_source {
 
             fn create(self, mut buffer: Vec<u8>) -> Vec<u8> {
                 for item in self.inner.iter() {
+                    throttle(1);
                     // The default Display impl writes TBL format
                     writeln!(&mut buffer, "{item}").expect("writing to memory is infallible");
                 }
@@ -59,3 +63,12 @@ define_tbl_source!(DriverTblSource, DriverGenerator<'static>);
 define_tbl_source!(CustomerTblSource, CustomerGenerator<'static>);
 define_tbl_source!(TripTblSource, TripGenerator);
 define_tbl_source!(BuildingTblSource, BuildingGenerator<'static>);
+define_tbl_source!(TrajectoryTblSource, TrajectoryGenerator);
+define_tbl_source!(RoadTblSource, RoadGenerator);
+define_tbl_source!(LandmassTblSource, LandmassGenerator);
+define_tbl_source!(CountryTblSource, CountryGenerator);
+define_tbl_source!(RegionTblSource, RegionGenerator);
+define_tbl_source!(CityTblSource, CityGenerator);
+define_tbl_source!(PoiTblSource, PoiGenerator);
+define_tbl_source!(RasterTblSource, RasterGenerator);
+define_tbl_source!(PointCloudTblSource, PointCloudGenerator);
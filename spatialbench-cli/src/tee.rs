@@ -0,0 +1,191 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Write each output file to several locations at once (`--mirror`)
+//!
+//! * [`Mirror`]: a copy of an output file in another location
+//! * [`Tee`]: a writer or [`Sink`] copying everything written to it to mirrors
+
+use crate::generate::Sink;
+use crate::object_store_writer::{ObjectStoreWriter, UploadOptions};
+use crate::output_plan::{OutputLocation, OutputPlan};
+use crate::parquet::IntoSize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+/// A copy of an output file being written to another location
+pub enum Mirror {
+    /// Written to `temp_path`, and renamed to `path` once complete
+    File {
+        writer: BufWriter<File>,
+        temp_path: PathBuf,
+        path: PathBuf,
+    },
+    /// Uploaded to an object store, where it is visible once complete
+    ObjectStore(Box<ObjectStoreWriter>),
+}
+
+impl Mirror {
+    /// Start writing a copy to `location`
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn try_new(location: &OutputLocation, options: UploadOptions) -> io::Result<Self> {
+        match location {
+            OutputLocation::File(path) => {
+                let temp_path = path.with_extension("inprogress");
+                let file = File::create(&temp_path).map_err(|err| {
+                    io::Error::other(format!("Failed to create {temp_path:?}: {err}"))
+                })?;
+                Ok(Self::File {
+                    writer: BufWriter::with_capacity(32 * 1024 * 1024, file), // 32MB buffer
+                    temp_path,
+                    path: path.clone(),
+                })
+            }
+            OutputLocation::ObjectStore(url) => Ok(Self::ObjectStore(Box::new(
+                ObjectStoreWriter::try_new(url, options)?,
+            ))),
+            location => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Can not mirror the output to {location}"),
+            )),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Self::File { writer, .. } => writer.write_all(buf),
+            Self::ObjectStore(writer) => writer.write_all(buf),
+        }
+    }
+
+    /// Complete the copy
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::File {
+                mut writer,
+                temp_path,
+                path,
+            } => {
+                writer.flush()?;
+                drop(writer);
+                std::fs::rename(&temp_path, &path).map_err(|e| {
+                    io::Error::other(format!(
+                        "Failed to rename {temp_path:?} to {path:?} file: {e}"
+                    ))
+                })
+            }
+            Self::ObjectStore(writer) => writer.into_size().map(|_| ()),
+        }
+    }
+}
+
+/// Start writing the copies of the output of `plan` to its mirrors
+pub fn mirrors(plan: &OutputPlan) -> io::Result<Vec<Mirror>> {
+    plan.mirrors()
+        .iter()
+        .map(|location| Mirror::try_new(location, plan.upload_options()))
+        .collect()
+}
+
+/// A writer or [`Sink`] that writes everything written to `inner` to
+/// [`Mirror`]s as well
+///
+/// The data is generated and encoded once, and the mirrors are completed
+/// when `inner` is.
+pub struct Tee<W> {
+    inner: W,
+    mirrors: Vec<Mirror>,
+}
+
+impl<W> Tee<W> {
+    /// Copy the output written to `inner` to `mirrors`
+    pub fn new(inner: W, mirrors: Vec<Mirror>) -> Self {
+        Self { inner, mirrors }
+    }
+
+    fn mirror(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.mirrors
+            .iter_mut()
+            .try_for_each(|mirror| mirror.write_all(buf))
+    }
+
+    fn finish_mirrors(mirrors: Vec<Mirror>) -> io::Result<()> {
+        mirrors.into_iter().try_for_each(Mirror::finish)
+    }
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.mirror(&buf[..written])?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + IntoSize> IntoSize for Tee<W> {
+    fn into_size(self) -> Result<usize, io::Error> {
+        let size = self.inner.into_size()?;
+        Self::finish_mirrors(self.mirrors)?;
+        Ok(size)
+    }
+}
+
+impl<S: Sink> Sink for Tee<S> {
+    fn sink(&mut self, buffer: &[u8]) -> Result<(), io::Error> {
+        self.inner.sink(buffer)?;
+        self.mirror(buffer)
+    }
+
+    fn flush(self) -> Result<(), io::Error> {
+        self.inner.flush()?;
+        Self::finish_mirrors(self.mirrors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tee() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        let mirrors = ["a.tbl", "b.tbl"]
+            .map(|name| {
+                let location = OutputLocation::File(path(name));
+                Mirror::try_new(&location, UploadOptions::default()).unwrap()
+            })
+            .into();
+        let out = BufWriter::new(File::create(path("out.tbl")).unwrap());
+        let mut tee = Tee::new(out, mirrors);
+        tee.write_all(b"hello ").unwrap();
+        tee.write_all(b"world").unwrap();
+        // the copies are only visible once complete
+        assert!(!path("a.tbl").exists());
+        tee.into_size().unwrap();
+        for name in ["out.tbl", "a.tbl", "b.tbl"] {
+            assert_eq!(std::fs::read_to_string(path(name)).unwrap(), "hello world");
+        }
+        assert!(!path("a.inprogress").exists());
+    }
+}
@@ -0,0 +1,153 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Rate limiting the generation to a number of rows per second
+//!
+//! With `--throttle`, the generator is a steady-state load driver rather
+//! than a bulk producer. All the worker threads share one [`Throttle`]:
+//! each of them reserves the rows it is about to emit, and waits until the
+//! rows reserved before them are due, so the total rate is the same for
+//! any number of threads.
+
+use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
+use spatialbench_arrow::RecordBatchIterator;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Paces rows to a fixed number of rows per second
+#[derive(Debug)]
+pub struct Throttle {
+    rows_per_sec: f64,
+    /// When the first rows were reserved
+    start: OnceLock<Instant>,
+    /// Number of rows reserved so far
+    reserved: AtomicU64,
+}
+
+impl Throttle {
+    /// Create a throttle emitting `rows_per_sec` rows per second
+    pub fn new(rows_per_sec: f64) -> Self {
+        assert!(rows_per_sec.is_finite() && rows_per_sec > 0.0);
+        Self {
+            rows_per_sec,
+            start: OnceLock::new(),
+            reserved: AtomicU64::new(0),
+        }
+    }
+
+    /// Block until `rows` more rows can be emitted
+    ///
+    /// The rows are due once all the rows reserved before them have had
+    /// their time, so the first rows are emitted immediately.
+    pub fn acquire(&self, rows: usize) {
+        let start = *self.start.get_or_init(Instant::now);
+        let before = self.reserved.fetch_add(rows as u64, Ordering::Relaxed);
+        let due = start + Duration::from_secs_f64(before as f64 / self.rows_per_sec);
+        let now = Instant::now();
+        if due > now {
+            std::thread::sleep(due - now);
+        }
+    }
+}
+
+static THROTTLE: OnceLock<Throttle> = OnceLock::new();
+
+/// Limit the generation to `rows_per_sec` rows per second
+///
+/// Only the first call has an effect.
+pub fn set_throttle(rows_per_sec: f64) {
+    let _ = THROTTLE.set(Throttle::new(rows_per_sec));
+}
+
+/// Block until `rows` more rows can be emitted, if throttled with
+/// [`set_throttle`]
+pub fn throttle(rows: usize) {
+    if let Some(throttle) = THROTTLE.get() {
+        throttle.acquire(rows);
+    }
+}
+
+/// Return the number of rows in a chunk of throttled output, if throttled
+///
+/// Chunks are written whole once they are generated, so a chunk of one
+/// second of rows keeps the output steady rather than in bursts of the
+/// default chunk size.
+pub fn chunk_rows() -> Option<i64> {
+    THROTTLE
+        .get()
+        .map(|throttle| throttle.rows_per_sec.ceil() as i64)
+}
+
+/// A [`RecordBatchIterator`] that paces the batches of the inner iterator
+/// with [`throttle`]
+pub struct Throttled<I> {
+    inner: I,
+}
+
+impl<I: RecordBatchIterator> Throttled<I> {
+    /// Pace the batches of `inner`
+    pub fn new(inner: I) -> Self {
+        Self { inner }
+    }
+}
+
+impl<I: RecordBatchIterator> Iterator for Throttled<I> {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = self.inner.next()?;
+        throttle(batch.num_rows());
+        Some(batch)
+    }
+}
+
+impl<I: RecordBatchIterator> RecordBatchIterator for Throttled<I> {
+    fn schema(&self) -> &SchemaRef {
+        self.inner.schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_acquire() {
+        let throttle = Arc::new(Throttle::new(1000.0));
+        let start = Instant::now();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let throttle = Arc::clone(&throttle);
+                std::thread::spawn(move || {
+                    for _ in 0..5 {
+                        throttle.acquire(10);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        // the last 10 of the 200 rows are due after 190 rows
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(190), "{elapsed:?}");
+        assert_eq!(throttle.reserved.load(Ordering::Relaxed), 200);
+    }
+}
@@ -0,0 +1,50 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Time window of the trip pickup times (`--time-start`, `--time-end`,
+//! `--time-pattern` and `--time-zone`)
+
+use log::info;
+use spatialbench::temporal::{set_time_window, TimePattern, TimeWindow};
+use std::io;
+
+/// Set the time window of the trips from the `--time-*` arguments
+///
+/// The trips keep the dates of the reference data unless one of the
+/// arguments is given. The window defaults to the dates of the reference
+/// data, uniformly in UTC.
+pub fn load_time_window(
+    start: Option<i64>,
+    end: Option<i64>,
+    pattern: Option<TimePattern>,
+    utc_offset: Option<i32>,
+) -> io::Result<()> {
+    if start.is_none() && end.is_none() && pattern.is_none() && utc_offset.is_none() {
+        return Ok(());
+    }
+    let default = TimeWindow::default();
+    let window = TimeWindow::try_new(
+        start.unwrap_or(default.start()),
+        end.unwrap_or(default.end()),
+    )
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+    .with_pattern(pattern.unwrap_or_default())
+    .with_utc_offset(utc_offset.unwrap_or_default());
+    info!("Generating the trip pickup times in {window:?}");
+    set_time_window(window);
+    Ok(())
+}
@@ -0,0 +1,524 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `verify` command: check a dataset against the data the generator creates
+//!
+//! For each table, [`TableSummary`] collects the row count, the range of the
+//! key column, an order independent checksum of each column and the number
+//! of valid geometries, both for the generated data and for the Parquet files
+//! of the dataset. Checksums do not depend on the order of the rows, so
+//! datasets split into parts, partitioned or sorted are verified the same way.
+
+use crate::generation::GenerationOptions;
+use crate::geoparquet::{is_wkb_type, wkb_values};
+use crate::manifest::{object_url, Checksummed, Manifest, MANIFEST_FILE};
+use crate::object_store_writer::{object_store_url, read_object};
+use arrow::array::{Array, ArrayRef, Int64Array, RecordBatch};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use clap::Args;
+use geo::{CoordsIter, Validation};
+use geozero::wkb::Wkb;
+use geozero::ToGeo;
+use log::info;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use spatialbench_arrow::{Table, TableGenerator};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::runtime::Handle;
+
+/// Check a generated dataset against the expected data
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// Directory of the dataset, as written by `spatialbench-cli --format parquet`
+    ///
+    /// Each table is read from `{table}.parquet` or from the Parquet files in
+    /// the `{table}` directory (parts, Hive and spatial partitions). With
+    /// `--checksums` it may also be an object store URL.
+    path: PathBuf,
+
+    #[command(flatten)]
+    generation: GenerationOptions,
+
+    /// Which tables to verify (default: all the tables found in the dataset)
+    ///
+    /// The zone table is built from downloaded data and is not verified.
+    #[arg(short = 'T', long = "tables", value_delimiter = ',')]
+    tables: Option<Vec<Table>>,
+
+    /// Check the sizes and SHA-256 checksums of the files of the dataset
+    /// against its `_spatialbench_manifest.json` instead of the generated
+    /// data
+    #[arg(long, default_value_t = false)]
+    checksums: bool,
+}
+
+impl VerifyArgs {
+    /// Verify the tables, printing a report to stdout, and return an error
+    /// if any table does not match
+    pub fn run(&self) -> io::Result<()> {
+        if self.checksums {
+            return verify_checksums(&self.path);
+        }
+        if !self.path.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a directory", self.path.display()),
+            ));
+        }
+        self.generation.apply()?;
+        let row_counts = self.generation.row_counts()?;
+
+        let tables: Vec<(Table, Vec<PathBuf>)> = match &self.tables {
+            Some(tables) => tables
+                .iter()
+                .map(|&table| Ok((table, table_files(&self.path, table.name())?)))
+                .collect::<io::Result<_>>()?,
+            None => Table::ALL
+                .into_iter()
+                .map(|table| Ok((table, table_files(&self.path, table.name())?)))
+                .filter(|files| !matches!(files, Ok((_, files)) if files.is_empty()))
+                .collect::<io::Result<_>>()?,
+        };
+        if tables.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("No Parquet files of any table in {}", self.path.display()),
+            ));
+        }
+
+        let mut failed = vec![];
+        for (table, files) in tables {
+            let start = Instant::now();
+            // generate the expected data while reading the files
+            let (expected, actual) = std::thread::scope(|scope| {
+                let generator = TableGenerator::new(table, self.generation.scale_factor)
+                    .with_seed(self.generation.seed)
+                    .with_row_counts(row_counts);
+                let expected = scope.spawn(move || TableSummary::generated(generator));
+                let actual = TableSummary::read(table, &files);
+                (
+                    expected.join().expect("generating the table panicked"),
+                    actual,
+                )
+            });
+            let problems = match actual {
+                Ok(actual) => expected.compare(&actual),
+                Err(e) => vec![format!("failed reading the files: {e}")],
+            };
+            info!("Verified {table} in {:?}", start.elapsed());
+
+            if problems.is_empty() {
+                println!("{table}: OK ({} rows)", expected.rows);
+            } else {
+                println!("{table}: FAILED");
+                for problem in problems {
+                    println!("  {problem}");
+                }
+                failed.push(table.name());
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Verification failed for {}", failed.join(", ")),
+            ))
+        }
+    }
+}
+
+/// Check the files of the dataset at `path` (a directory or an object store
+/// URL) against the checksums of its manifest, printing a report to stdout,
+/// and return an error if any file does not match
+fn verify_checksums(path: &Path) -> io::Result<()> {
+    let url = object_store_url(path);
+    let read = |name: &str, f: &mut dyn FnMut(&[u8])| -> io::Result<()> {
+        match &url {
+            // called from the async main, so block on a worker thread
+            Some(url) => {
+                let url = object_url(url, name)?;
+                tokio::task::block_in_place(|| Handle::current().block_on(read_object(&url, f)))
+            }
+            None => {
+                let mut file = File::open(path.join(name))?;
+                let mut buffer = vec![0; 1024 * 1024];
+                loop {
+                    let len = file.read(&mut buffer)?;
+                    if len == 0 {
+                        return Ok(());
+                    }
+                    f(&buffer[..len]);
+                }
+            }
+        }
+    };
+
+    let mut json = vec![];
+    read(MANIFEST_FILE, &mut |bytes| json.extend_from_slice(bytes)).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to read {MANIFEST_FILE} of {}: {e}", path.display()),
+        )
+    })?;
+    let manifest = Manifest::parse(&json)?;
+
+    let mut failed = vec![];
+    for (table, files) in manifest.tables() {
+        let start = Instant::now();
+        let mut problems = vec![];
+        for file in files {
+            let mut hasher = Checksummed::new(io::sink(), None);
+            match read(&file.path, &mut |bytes| {
+                hasher.write_all(bytes).expect("sink")
+            }) {
+                Ok(()) => {
+                    let actual = hasher.checksum();
+                    if actual.bytes != file.checksum.bytes {
+                        problems.push(format!(
+                            "{}: {} bytes, expected {}",
+                            file.path, actual.bytes, file.checksum.bytes
+                        ));
+                    } else if actual.sha256 != file.checksum.sha256 {
+                        problems.push(format!(
+                            "{}: SHA-256 {}, expected {}",
+                            file.path, actual.sha256, file.checksum.sha256
+                        ));
+                    }
+                }
+                Err(e) => problems.push(format!("{}: failed reading: {e}", file.path)),
+            }
+        }
+        info!("Verified the checksums of {table} in {:?}", start.elapsed());
+
+        if problems.is_empty() {
+            println!("{table}: OK ({} files)", files.len());
+        } else {
+            println!("{table}: FAILED");
+            for problem in problems {
+                println!("  {problem}");
+            }
+            failed.push(table);
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Verification failed for {}", failed.join(", ")),
+        ))
+    }
+}
+
+/// Return the Parquet files of the table `name` in the dataset at `path`
+pub fn table_files(path: &Path, name: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let file = path.join(format!("{name}.parquet"));
+    if file.is_file() {
+        files.push(file);
+    }
+    let dir = path.join(name);
+    if dir.is_dir() {
+        collect_parquet_files(&dir, &mut files)?;
+    }
+    Ok(files)
+}
+
+fn collect_parquet_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_parquet_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "parquet")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Order independent statistics of the rows of a table
+#[derive(Debug, Clone, PartialEq)]
+struct TableSummary {
+    /// Name of the key column of the table
+    key: String,
+    rows: usize,
+    /// Smallest and largest value of the key column
+    key_range: Option<(i64, i64)>,
+    /// Type and checksum of each column, by name
+    columns: BTreeMap<String, (DataType, u64)>,
+    /// Statistics of each WKB geometry column, by name
+    geometries: BTreeMap<String, GeometrySummary>,
+}
+
+/// Number of valid, invalid and null geometries in a column
+///
+/// Valid geometries can be parsed, pass the `geo` validity checks and have
+/// longitude / latitude coordinates.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct GeometrySummary {
+    valid: usize,
+    invalid: usize,
+    null: usize,
+}
+
+impl TableSummary {
+    /// Create an empty summary of `table`
+    fn new(table: Table) -> Self {
+        Self {
+            key: table.schema().field(0).name().clone(),
+            rows: 0,
+            key_range: None,
+            columns: BTreeMap::new(),
+            geometries: BTreeMap::new(),
+        }
+    }
+
+    /// Summarize the table of `generator`
+    fn generated(generator: TableGenerator) -> Self {
+        let mut summary = Self::new(generator.table());
+        for batch in generator.batches() {
+            summary.add(&batch);
+        }
+        summary
+    }
+
+    /// Summarize the rows of the Parquet `files`
+    fn read(table: Table, files: &[PathBuf]) -> Result<Self, String> {
+        if files.is_empty() {
+            return Err("no Parquet files found".into());
+        }
+        let mut summary = Self::new(table);
+        for path in files {
+            let error = |e: &dyn std::fmt::Display| format!("{}: {e}", path.display());
+            let file = File::open(path).map_err(|e| error(&e))?;
+            let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+                .and_then(|builder| builder.build())
+                .map_err(|e| error(&e))?;
+            for batch in reader {
+                summary.add(&batch.map_err(|e| error(&e))?);
+            }
+        }
+        Ok(summary)
+    }
+
+    fn add(&mut self, batch: &RecordBatch) {
+        self.rows += batch.num_rows();
+        let key = batch.column_by_name(&self.key).and_then(key_range);
+        if let Some((min, max)) = key {
+            let (old_min, old_max) = self.key_range.unwrap_or((min, max));
+            self.key_range = Some((old_min.min(min), old_max.max(max)));
+        }
+
+        let schema = batch.schema();
+        for (field, array) in schema.fields().iter().zip(batch.columns()) {
+            let (_, checksum) = self
+                .columns
+                .entry(field.name().clone())
+                .or_insert_with(|| (field.data_type().clone(), 0));
+            *checksum = checksum.wrapping_add(column_checksum(array));
+
+            if is_wkb_type(field.data_type()) {
+                let geometries = self.geometries.entry(field.name().clone()).or_default();
+                for wkb in wkb_values(array) {
+                    match wkb {
+                        None => geometries.null += 1,
+                        Some(wkb) if is_valid_wkb(wkb) => geometries.valid += 1,
+                        Some(_) => geometries.invalid += 1,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the differences between the `actual` data and this expected data
+    fn compare(&self, actual: &TableSummary) -> Vec<String> {
+        let mut problems = vec![];
+        if self.rows != actual.rows {
+            problems.push(format!(
+                "row count: expected {}, found {}",
+                self.rows, actual.rows
+            ));
+        }
+        // e.g. not in the projected `--columns`
+        if !actual.columns.contains_key(&self.key) {
+            info!("Key column {} is not in the files, not verified", self.key);
+        } else if self.key_range != actual.key_range {
+            problems.push(format!(
+                "key range: expected {}, found {}",
+                format_range(self.key_range),
+                format_range(actual.key_range)
+            ));
+        }
+
+        for (name, (data_type, checksum)) in &self.columns {
+            match actual.columns.get(name) {
+                // e.g. removed by Hive partitioning
+                None => info!("Column {name} is not in the files, not verified"),
+                // e.g. native GeoArrow geometries
+                Some((actual_type, _)) if is_wkb_type(data_type) && !is_wkb_type(actual_type) => {
+                    info!("Column {name} is not WKB encoded, not verified")
+                }
+                Some((_, actual_checksum)) if actual_checksum != checksum => {
+                    problems.push(format!("column {name}: checksum does not match"))
+                }
+                Some(_) => {}
+            }
+        }
+        for (name, expected) in &self.geometries {
+            if let Some(actual) = actual
+                .geometries
+                .get(name)
+                .filter(|actual| *actual != expected)
+            {
+                problems.push(format!(
+                    "column {name}: expected {} valid, {} invalid and {} null geometries, found {}, {} and {}",
+                    expected.valid, expected.invalid, expected.null, actual.valid, actual.invalid, actual.null
+                ));
+            }
+        }
+        problems
+    }
+}
+
+fn format_range(range: Option<(i64, i64)>) -> String {
+    match range {
+        Some((min, max)) => format!("{min}..={max}"),
+        None => "none".into(),
+    }
+}
+
+/// Return the smallest and largest values of an integer key column
+fn key_range(array: &ArrayRef) -> Option<(i64, i64)> {
+    if !array.data_type().is_integer() {
+        return None;
+    }
+    let keys = cast(array, &DataType::Int64).ok()?;
+    let keys = keys.as_any().downcast_ref::<Int64Array>()?;
+    Some((arrow::compute::min(keys)?, arrow::compute::max(keys)?))
+}
+
+/// Return the sum of the hashes of the values of `array`, so the checksum of
+/// a column does not depend on the order of its rows
+///
+/// WKB values are hashed as bytes, and other values as their display strings,
+/// which do not change when round tripping through Parquet.
+fn column_checksum(array: &ArrayRef) -> u64 {
+    let mut checksum = 0u64;
+    if is_wkb_type(array.data_type()) {
+        for value in wkb_values(array) {
+            checksum = checksum.wrapping_add(value.map_or(NULL_HASH, hash_bytes));
+        }
+        return checksum;
+    }
+
+    let options = FormatOptions::default();
+    let Ok(formatter) = ArrayFormatter::try_new(array.as_ref(), &options) else {
+        return checksum;
+    };
+    let mut value = String::new();
+    for i in 0..array.len() {
+        let hash = if array.is_null(i) {
+            NULL_HASH
+        } else {
+            value.clear();
+            write!(value, "{}", formatter.value(i)).expect("writing to a string");
+            hash_bytes(value.as_bytes())
+        };
+        checksum = checksum.wrapping_add(hash);
+    }
+    checksum
+}
+
+/// Hash of null values
+const NULL_HASH: u64 = 0x9E3779B97F4A7C15;
+
+/// FNV-1a, finished with the splitmix64 mixer so that sums of hashes of
+/// similar values are well distributed
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut z = bytes.iter().fold(0xcbf29ce484222325u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Return true if `wkb` is a valid geometry with longitude / latitude coordinates
+fn is_valid_wkb(wkb: &[u8]) -> bool {
+    let Ok(geometry) = Wkb(wkb).to_geo() else {
+        return false;
+    };
+    geometry.is_valid()
+        && geometry
+            .coords_iter()
+            .all(|c| (-180.0..=180.0).contains(&c.x) && (-90.0..=90.0).contains(&c.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let expected = TableSummary::generated(TableGenerator::new(Table::Building, 0.1));
+        assert_eq!(expected.rows, 1);
+        assert_eq!(expected.key_range, Some((1, 1)));
+        let geometries = expected.geometries["b_boundary"];
+        assert_eq!(geometries.valid + geometries.invalid, 1);
+
+        // the checksums do not depend on the order or batching of the rows
+        let batches: Vec<_> = TableGenerator::new(Table::Vehicle, 0.1)
+            .with_batch_size(3)
+            .batches()
+            .collect();
+        let mut reversed = TableSummary::new(Table::Vehicle);
+        for batch in batches.iter().rev() {
+            reversed.add(batch);
+        }
+        let expected = TableSummary::generated(TableGenerator::new(Table::Vehicle, 0.1));
+        assert_eq!(reversed, expected);
+        assert!(expected.compare(&reversed).is_empty());
+
+        // a missing batch is detected
+        let mut truncated = TableSummary::new(Table::Vehicle);
+        for batch in &batches[1..] {
+            truncated.add(batch);
+        }
+        let problems = expected.compare(&truncated);
+        assert!(
+            problems[0].starts_with("row count: expected 10, found 7"),
+            "{problems:?}"
+        );
+        assert!(problems
+            .iter()
+            .any(|p| p == "column v_vehiclekey: checksum does not match"));
+    }
+}
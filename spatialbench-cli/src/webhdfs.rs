@@ -0,0 +1,741 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! [`WebHdfs`]: an [`ObjectStore`] writing to HDFS through the WebHDFS REST
+//! API of the NameNode
+//!
+//! Selected by `hdfs://namenode/path`, `webhdfs://namenode:port/path` and
+//! `swebhdfs://namenode:port/path` URLs, so HDFS works everywhere the object
+//! stores do ([`ObjectStoreWriter`](crate::object_store_writer::ObjectStoreWriter),
+//! `--mirror`, partitioned output and the manifest). `hdfs://` URLs use
+//! the default HTTP port of the NameNode, [`DEFAULT_HTTP_PORT`], rather than
+//! the RPC port of the URL. Requests are made as the `HADOOP_USER_NAME` user
+//! with simple authentication; Kerberos (SPNEGO) is not supported.
+//!
+//! Files are written to `<path>.inprogress` (created with the first part and
+//! appended to with the next ones, in order) and renamed to `<path>` when
+//! complete, so incomplete files are never visible under their final name.
+//!
+//! Only writing, reading and deleting single files is supported.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use object_store::path::Path;
+use object_store::{
+    Attributes, GetOptions, GetResult, GetResultPayload, ListResult, MultipartUpload, ObjectMeta,
+    ObjectStore, PutMultipartOptions, PutOptions, PutPayload, PutResult, UploadPart,
+};
+use reqwest::{header, Client, Method, Response, StatusCode};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use tokio::sync::watch;
+use url::Url;
+
+/// The default HTTP port of the NameNode (Hadoop 3)
+pub const DEFAULT_HTTP_PORT: u16 = 9870;
+
+const STORE: &str = "WebHDFS";
+
+/// Returns true if `url` is written to with [`WebHdfs`]
+pub fn is_webhdfs_url(url: &Url) -> bool {
+    matches!(url.scheme(), "hdfs" | "webhdfs" | "swebhdfs")
+}
+
+/// Writes to HDFS with the WebHDFS REST API
+#[derive(Debug, Clone)]
+pub struct WebHdfs {
+    /// e.g. `http://namenode:9870/webhdfs/v1`
+    base: String,
+    /// The `user.name` of the requests
+    user: Option<String>,
+    client: Client,
+}
+
+impl Display for WebHdfs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WebHdfs({})", self.base)
+    }
+}
+
+impl WebHdfs {
+    /// Return the store and the path within it for a `hdfs://`,
+    /// `webhdfs://` or `swebhdfs://` URL
+    pub fn try_new(url: &Url) -> object_store::Result<(Self, Path)> {
+        let generic = |message: String| object_store::Error::Generic {
+            store: STORE,
+            source: message.into(),
+        };
+        let host = url
+            .host_str()
+            .ok_or_else(|| generic(format!("Missing NameNode in {url}")))?;
+        let (scheme, port) = match url.scheme() {
+            "hdfs" => ("http", DEFAULT_HTTP_PORT),
+            "webhdfs" => ("http", url.port().unwrap_or(DEFAULT_HTTP_PORT)),
+            "swebhdfs" => ("https", url.port().unwrap_or(9871)),
+            scheme => return Err(generic(format!("Not a WebHDFS scheme: {scheme}"))),
+        };
+        let client = Client::builder()
+            // the redirects to the DataNodes are followed explicitly, to send
+            // the data only to the DataNode
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| generic(e.to_string()))?;
+        let path = Path::from_url_path(url.path())?;
+        let store = Self {
+            base: format!("{scheme}://{host}:{port}/webhdfs/v1"),
+            user: std::env::var("HADOOP_USER_NAME").ok(),
+            client,
+        };
+        Ok((store, path))
+    }
+
+    /// Return the URL of operation `op` on `path`
+    fn url(&self, path: &Path, op: &str, params: &[(&str, &str)]) -> object_store::Result<Url> {
+        let mut url = Url::parse(&format!("{}/{}", self.base, path)).map_err(|e| {
+            object_store::Error::Generic {
+                store: STORE,
+                source: e.into(),
+            }
+        })?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("op", op);
+            if let Some(user) = &self.user {
+                query.append_pair("user.name", user);
+            }
+            query.extend_pairs(params);
+        }
+        Ok(url)
+    }
+
+    /// Send a request for operation `op` on `path`
+    async fn send(
+        &self,
+        method: Method,
+        path: &Path,
+        op: &str,
+        params: &[(&str, &str)],
+    ) -> object_store::Result<Response> {
+        let url = self.url(path, op, params)?;
+        self.client
+            .request(method, url)
+            .send()
+            .await
+            .map_err(|e| transport_error(path, op, e))
+    }
+
+    /// Send a request for operation `op` on `path`, returning the response
+    /// if it is successful
+    async fn request(
+        &self,
+        method: Method,
+        path: &Path,
+        op: &str,
+        params: &[(&str, &str)],
+    ) -> object_store::Result<Response> {
+        let response = self.send(method, path, op, params).await?;
+        check(path, op, response).await
+    }
+
+    /// Return the location a `response` redirects to
+    fn redirect(path: &Path, op: &str, response: &Response) -> object_store::Result<String> {
+        response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| request_error(path, op, "redirect without a location".into()))
+    }
+
+    /// Send a request for operation `op` on `path`, and the `data` to the
+    /// DataNode it redirects to
+    async fn send_data(
+        &self,
+        method: Method,
+        path: &Path,
+        op: &str,
+        params: &[(&str, &str)],
+        data: PutPayload,
+    ) -> object_store::Result<()> {
+        let response = self.send(method.clone(), path, op, params).await?;
+        if response.status() != StatusCode::TEMPORARY_REDIRECT {
+            check(path, op, response).await?;
+            return Err(request_error(path, op, "expected a redirect".into()));
+        }
+        let location = Self::redirect(path, op, &response)?;
+        let mut body = Vec::with_capacity(data.content_length());
+        for bytes in data.iter() {
+            body.extend_from_slice(bytes);
+        }
+        let response = self
+            .client
+            .request(method, location)
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| transport_error(path, op, e))?;
+        check(path, op, response).await.map(|_| ())
+    }
+
+    /// Create (or overwrite) `path` with `data`
+    async fn create(&self, path: &Path, data: PutPayload) -> object_store::Result<()> {
+        let params = [("overwrite", "true")];
+        self.send_data(Method::PUT, path, "CREATE", &params, data)
+            .await
+    }
+
+    /// Append `data` to `path`
+    async fn append(&self, path: &Path, data: PutPayload) -> object_store::Result<()> {
+        self.send_data(Method::POST, path, "APPEND", &[], data)
+            .await
+    }
+
+    /// Rename `from` to `to`, replacing `to`
+    async fn replace(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        // RENAME does not overwrite
+        match self.delete(to).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+        let destination = format!("/{to}");
+        let response = self
+            .request(
+                Method::PUT,
+                from,
+                "RENAME",
+                &[("destination", destination.as_str())],
+            )
+            .await?;
+        if boolean(from, "RENAME", response).await? {
+            Ok(())
+        } else {
+            Err(request_error(from, "RENAME", format!("failed to {to}")))
+        }
+    }
+
+    async fn status(&self, path: &Path) -> object_store::Result<ObjectMeta> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct FileStatus {
+            length: u64,
+            modification_time: i64,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Status {
+            file_status: FileStatus,
+        }
+        let response = self
+            .request(Method::GET, path, "GETFILESTATUS", &[])
+            .await?;
+        let status: Status = json(path, "GETFILESTATUS", response).await?;
+        Ok(ObjectMeta {
+            location: path.clone(),
+            last_modified: DateTime::<Utc>::from_timestamp_millis(
+                status.file_status.modification_time,
+            )
+            .unwrap_or_default(),
+            size: status.file_status.length,
+            e_tag: None,
+            version: None,
+        })
+    }
+}
+
+/// The source of the errors of failed requests
+#[derive(Debug)]
+struct RequestError {
+    message: String,
+    /// True if the request may succeed when retried
+    transient: bool,
+}
+
+impl Display for RequestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// Returns true if `e` is the error of a request that may succeed when
+/// retried: the connection failed, or the server responded with a 408, 429
+/// or 5xx status
+pub fn is_transient(e: &object_store::Error) -> bool {
+    match e {
+        object_store::Error::Generic { source, .. } => source
+            .downcast_ref::<RequestError>()
+            .is_some_and(|e| e.transient),
+        _ => false,
+    }
+}
+
+fn request_error(path: &Path, op: &str, message: String) -> object_store::Error {
+    error(path, op, message, false)
+}
+
+/// Return the error of a request that failed to be sent or received
+fn transport_error(path: &Path, op: &str, e: reqwest::Error) -> object_store::Error {
+    error(path, op, e.to_string(), true)
+}
+
+/// Return the error of a request the server responded to with `status`
+pub(crate) fn status_error(
+    path: &Path,
+    op: &str,
+    status: StatusCode,
+    message: &str,
+) -> object_store::Error {
+    let transient = status.is_server_error()
+        || matches!(
+            status,
+            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS
+        );
+    error(path, op, format!("{status} {message}"), transient)
+}
+
+fn error(path: &Path, op: &str, message: String, transient: bool) -> object_store::Error {
+    object_store::Error::Generic {
+        store: STORE,
+        source: Box::new(RequestError {
+            message: format!("{op} of {path} failed: {message}"),
+            transient,
+        }),
+    }
+}
+
+/// Return `response` if it is successful
+async fn check(path: &Path, op: &str, response: Response) -> object_store::Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response.text().await.unwrap_or_default();
+    Err(if status == StatusCode::NOT_FOUND {
+        object_store::Error::NotFound {
+            path: path.to_string(),
+            source: format!("{status} {message}").into(),
+        }
+    } else {
+        status_error(path, op, status, &message)
+    })
+}
+
+async fn json<T: for<'de> Deserialize<'de>>(
+    path: &Path,
+    op: &str,
+    response: Response,
+) -> object_store::Result<T> {
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| transport_error(path, op, e))?;
+    serde_json::from_slice(&bytes).map_err(|e| request_error(path, op, e.to_string()))
+}
+
+/// Return the `{"boolean": ...}` result of an operation
+async fn boolean(path: &Path, op: &str, response: Response) -> object_store::Result<bool> {
+    #[derive(Deserialize)]
+    struct Boolean {
+        boolean: bool,
+    }
+    Ok(json::<Boolean>(path, op, response).await?.boolean)
+}
+
+/// Return the path `path` is written to until it is complete
+fn in_progress(path: &Path) -> Path {
+    Path::from(format!("{path}.inprogress"))
+}
+
+fn not_implemented(op: &str) -> object_store::Error {
+    object_store::Error::NotSupported {
+        source: format!("{op} is not supported by {STORE}").into(),
+    }
+}
+
+#[async_trait]
+impl ObjectStore for WebHdfs {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        payload: PutPayload,
+        _opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        let temp = in_progress(location);
+        self.create(&temp, payload).await?;
+        self.replace(&temp, location).await?;
+        Ok(PutResult {
+            e_tag: None,
+            version: None,
+        })
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &Path,
+        _opts: PutMultipartOptions,
+    ) -> object_store::Result<Box<dyn MultipartUpload>> {
+        let (turn, _) = watch::channel(0);
+        Ok(Box::new(WebHdfsUpload {
+            store: Arc::new(self.clone()),
+            temp: in_progress(location),
+            location: location.clone(),
+            num_parts: 0,
+            turn: Arc::new(turn),
+        }))
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        if options.range.is_some() {
+            return Err(not_implemented("Range reads"));
+        }
+        let meta = self.status(location).await?;
+        let payload = if options.head {
+            stream::empty().boxed()
+        } else {
+            let mut response = self.send(Method::GET, location, "OPEN", &[]).await?;
+            // OPEN redirects to a DataNode
+            if response.status() == StatusCode::TEMPORARY_REDIRECT {
+                let url = Self::redirect(location, "OPEN", &response)?;
+                response = self
+                    .client
+                    .get(url)
+                    .send()
+                    .await
+                    .map_err(|e| transport_error(location, "OPEN", e))?;
+            }
+            let response = check(location, "OPEN", response).await?;
+            let path = location.clone();
+            response
+                .bytes_stream()
+                .map(move |bytes| bytes.map_err(|e| transport_error(&path, "OPEN", e)))
+                .boxed()
+        };
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(payload),
+            range: 0..meta.size,
+            meta,
+            attributes: Attributes::default(),
+        })
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        let response = self
+            .request(
+                Method::DELETE,
+                location,
+                "DELETE",
+                &[("recursive", "false")],
+            )
+            .await?;
+        if boolean(location, "DELETE", response).await? {
+            Ok(())
+        } else {
+            Err(object_store::Error::NotFound {
+                path: location.to_string(),
+                source: "DELETE returned false".into(),
+            })
+        }
+    }
+
+    fn list(&self, _prefix: Option<&Path>) -> BoxStream<'static, object_store::Result<ObjectMeta>> {
+        stream::once(async { Err(not_implemented("Listing")) }).boxed()
+    }
+
+    async fn list_with_delimiter(
+        &self,
+        _prefix: Option<&Path>,
+    ) -> object_store::Result<ListResult> {
+        Err(not_implemented("Listing"))
+    }
+
+    async fn copy(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(not_implemented("Copying"))
+    }
+
+    async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(not_implemented("Copying"))
+    }
+}
+
+/// A file written in parts: created with the first part, and appended to
+/// with the next ones
+///
+/// The parts are uploaded concurrently by
+/// [`ObjectStoreWriter`](crate::object_store_writer::ObjectStoreWriter), but
+/// HDFS files can only be appended to, so each part waits for its `turn`.
+#[derive(Debug)]
+struct WebHdfsUpload {
+    store: Arc<WebHdfs>,
+    temp: Path,
+    location: Path,
+    /// Number of parts started so far
+    num_parts: usize,
+    /// The number of parts written so far
+    turn: Arc<watch::Sender<usize>>,
+}
+
+impl WebHdfsUpload {
+    /// Wait until the first `parts` parts are written
+    async fn wait_for(turn: &watch::Sender<usize>, parts: usize) -> object_store::Result<()> {
+        turn.subscribe()
+            .wait_for(|written| *written >= parts)
+            .await
+            .map(|_| ())
+            .map_err(|e| object_store::Error::Generic {
+                store: STORE,
+                source: e.into(),
+            })
+    }
+}
+
+#[async_trait]
+impl MultipartUpload for WebHdfsUpload {
+    fn put_part(&mut self, data: PutPayload) -> UploadPart {
+        let part = self.num_parts;
+        self.num_parts += 1;
+        let store = Arc::clone(&self.store);
+        let temp = self.temp.clone();
+        let turn = Arc::clone(&self.turn);
+        Box::pin(async move {
+            Self::wait_for(&turn, part).await?;
+            if part == 0 {
+                store.create(&temp, data).await?;
+            } else {
+                store.append(&temp, data).await?;
+            }
+            turn.send_replace(part + 1);
+            Ok(())
+        })
+    }
+
+    async fn complete(&mut self) -> object_store::Result<PutResult> {
+        Self::wait_for(&self.turn, self.num_parts).await?;
+        if self.num_parts == 0 {
+            self.store.create(&self.temp, PutPayload::new()).await?;
+        }
+        self.store.replace(&self.temp, &self.location).await?;
+        Ok(PutResult {
+            e_tag: None,
+            version: None,
+        })
+    }
+
+    async fn abort(&mut self) -> object_store::Result<()> {
+        match self.store.delete(&self.temp).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// A NameNode (and DataNode) keeping the files in memory
+    async fn mock_namenode() -> (u16, Arc<Mutex<HashMap<String, Vec<u8>>>>) {
+        let files: Arc<Mutex<HashMap<String, Vec<u8>>>> = Arc::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::clone(&files);
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let files = Arc::clone(&state);
+                tokio::spawn(async move {
+                    let mut reader = BufReader::new(socket);
+                    loop {
+                        let mut request = String::new();
+                        if reader.read_line(&mut request).await.unwrap_or(0) == 0 {
+                            return;
+                        }
+                        let mut length = 0;
+                        loop {
+                            let mut line = String::new();
+                            reader.read_line(&mut line).await.unwrap();
+                            if line.trim().is_empty() {
+                                break;
+                            }
+                            let (name, value) = line.split_once(':').unwrap();
+                            if name.eq_ignore_ascii_case("content-length") {
+                                length = value.trim().parse().unwrap();
+                            }
+                        }
+                        let mut body = vec![0; length];
+                        reader.read_exact(&mut body).await.unwrap();
+                        let response = handle(&request, body, port, &files);
+                        reader.get_mut().write_all(&response).await.unwrap();
+                    }
+                });
+            }
+        });
+        (port, files)
+    }
+
+    fn handle(
+        request: &str,
+        body: Vec<u8>,
+        port: u16,
+        files: &Mutex<HashMap<String, Vec<u8>>>,
+    ) -> Vec<u8> {
+        let target = request.split_whitespace().nth(1).unwrap();
+        let url = Url::parse(&format!("http://localhost{target}")).unwrap();
+        let path = url.path().strip_prefix("/webhdfs/v1").unwrap().to_string();
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        let mut files = files.lock().unwrap();
+        let response = |status: &str, headers: &str, body: &[u8]| {
+            let mut response = format!(
+                "HTTP/1.1 {status}\r\n{headers}content-length: {}\r\n\r\n",
+                body.len()
+            )
+            .into_bytes();
+            response.extend_from_slice(body);
+            response
+        };
+        let redirect = || {
+            response(
+                "307 Temporary Redirect",
+                &format!("location: http://127.0.0.1:{port}{target}&datanode=true\r\n"),
+                b"",
+            )
+        };
+        let datanode = params.contains_key("datanode");
+        match params["op"].as_str() {
+            "CREATE" if !datanode => redirect(),
+            "CREATE" => {
+                files.insert(path, body);
+                response("201 Created", "", b"")
+            }
+            "APPEND" if !datanode => redirect(),
+            "APPEND" => match files.get_mut(&path) {
+                Some(file) => {
+                    file.extend(body);
+                    response("200 OK", "", b"")
+                }
+                None => response("404 Not Found", "", b"{}"),
+            },
+            "RENAME" => {
+                let file = files.remove(&path);
+                let found = file.is_some();
+                if let Some(file) = file {
+                    files.insert(params["destination"].clone(), file);
+                }
+                response("200 OK", "", format!("{{\"boolean\": {found}}}").as_bytes())
+            }
+            "DELETE" => {
+                let found = files.remove(&path).is_some();
+                response("200 OK", "", format!("{{\"boolean\": {found}}}").as_bytes())
+            }
+            "GETFILESTATUS" => match files.get(&path) {
+                Some(file) => response(
+                    "200 OK",
+                    "",
+                    format!(
+                        "{{\"FileStatus\": {{\"length\": {}, \"modificationTime\": 0}}}}",
+                        file.len()
+                    )
+                    .as_bytes(),
+                ),
+                None => response("404 Not Found", "", b"{}"),
+            },
+            "OPEN" => response("200 OK", "", files.get(&path).map_or(&[][..], |f| f)),
+            op => panic!("unexpected {op}"),
+        }
+    }
+
+    #[test]
+    fn test_try_new() {
+        for (url, base, path) in [
+            (
+                "hdfs://namenode:8020/data/sf1",
+                "http://namenode:9870/webhdfs/v1",
+                "data/sf1",
+            ),
+            (
+                "webhdfs://namenode:50070/data",
+                "http://namenode:50070/webhdfs/v1",
+                "data",
+            ),
+            (
+                "swebhdfs://namenode/data",
+                "https://namenode:9871/webhdfs/v1",
+                "data",
+            ),
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert!(is_webhdfs_url(&url));
+            let (store, location) = WebHdfs::try_new(&url).unwrap();
+            assert_eq!(store.base, base);
+            assert_eq!(location.as_ref(), path);
+        }
+        assert!(!is_webhdfs_url(&Url::parse("s3://bucket/data").unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_webhdfs() {
+        let (port, files) = mock_namenode().await;
+        let url = Url::parse(&format!("webhdfs://127.0.0.1:{port}/sf1")).unwrap();
+        let (store, _) = WebHdfs::try_new(&url).unwrap();
+
+        let small = Path::from("sf1/vehicle.csv");
+        store
+            .put(&small, PutPayload::from("v_vehiclekey\n1\n"))
+            .await
+            .unwrap();
+
+        let large = Path::from("sf1/trip.tbl");
+        let mut upload = store.put_multipart(&large).await.unwrap();
+        let parts: Vec<_> = ["first|", "second|", "third|"]
+            .into_iter()
+            .map(|part| upload.put_part(PutPayload::from(part)))
+            .collect();
+        // the parts are appended in order whatever order they are polled in
+        for part in parts.into_iter().rev() {
+            tokio::spawn(part);
+        }
+        upload.complete().await.unwrap();
+
+        {
+            let files = files.lock().unwrap();
+            assert_eq!(files["/sf1/vehicle.csv"], b"v_vehiclekey\n1\n");
+            assert_eq!(files["/sf1/trip.tbl"], b"first|second|third|");
+            assert!(!files.contains_key("/sf1/trip.tbl.inprogress"));
+        }
+
+        let meta = store.head(&large).await.unwrap();
+        assert_eq!(meta.size, 19);
+        let bytes = store.get(&small).await.unwrap().bytes().await.unwrap();
+        assert_eq!(bytes.as_ref(), b"v_vehiclekey\n1\n");
+        assert!(matches!(
+            store.head(&Path::from("sf1/missing")).await,
+            Err(object_store::Error::NotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,369 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Writing the geometries as other variants of WKB (`--wkb-flavor` and
+//! `--wkb-byte-order`)
+//!
+//! The geometries are generated as little endian ISO WKB. [`WkbEncoded`]
+//! rewrites the WKB columns of the batches written to Parquet and Arrow
+//! files as EWKB with the SRID of `--crs`, and / or in big endian, for the
+//! loaders that only read one of the variants.
+
+use crate::geoparquet::{is_wkb_type, wkb_values};
+use arrow::array::{BinaryArray, RecordBatch};
+use arrow::compute::cast;
+use arrow::datatypes::SchemaRef;
+use clap::ValueEnum;
+use spatialbench_arrow::RecordBatchIterator;
+use std::io;
+
+/// Flags of the EWKB geometry type
+const EWKB_Z: u32 = 0x8000_0000;
+const EWKB_M: u32 = 0x4000_0000;
+const EWKB_SRID: u32 = 0x2000_0000;
+
+/// How the type and dimensions of the geometries are written in WKB
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum)]
+pub enum WkbFlavor {
+    /// ISO WKB, with the dimensions in the geometry type, e.g. 1001 for a
+    /// point with a Z
+    #[default]
+    Iso,
+    /// PostGIS extended WKB, with the dimensions as flags of the geometry
+    /// type and the SRID of `--crs`
+    Ewkb,
+}
+
+/// Byte order of the numbers of the WKB geometries
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum)]
+pub enum WkbByteOrder {
+    /// Little endian (NDR)
+    #[default]
+    #[value(alias = "ndr")]
+    Little,
+    /// Big endian (XDR)
+    #[value(alias = "xdr")]
+    Big,
+}
+
+/// The variant of WKB to write the geometries as
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct WkbVariant {
+    pub flavor: WkbFlavor,
+    pub byte_order: WkbByteOrder,
+    /// SRID of the EWKB geometries
+    pub srid: u32,
+}
+
+impl WkbVariant {
+    /// Return true for little endian ISO WKB, as the geometries are generated
+    pub fn is_default(&self) -> bool {
+        self.flavor == WkbFlavor::Iso && self.byte_order == WkbByteOrder::Little
+    }
+
+    /// Append the ISO WKB or EWKB `wkb` to `buffer` in this variant
+    ///
+    /// Returns None, with `buffer` unchanged, if `wkb` is invalid.
+    pub fn encode(&self, wkb: &[u8], buffer: &mut Vec<u8>) -> Option<()> {
+        let len = buffer.len();
+        let mut transcoder = Transcoder {
+            input: wkb,
+            pos: 0,
+            output: buffer,
+            variant: *self,
+        };
+        let result = transcoder.geometry(true);
+        let complete = transcoder.pos == wkb.len();
+        if result.is_none() || !complete {
+            buffer.truncate(len);
+            return None;
+        }
+        Some(())
+    }
+
+    /// Rewrite the WKB columns of `batch` in this variant, keeping invalid
+    /// WKB as is
+    pub fn encode_batch(&self, batch: &RecordBatch) -> io::Result<RecordBatch> {
+        let mut columns = batch.columns().to_vec();
+        let mut buffer = Vec::new();
+        for (i, field) in batch.schema_ref().fields().iter().enumerate() {
+            if !is_wkb_type(field.data_type()) {
+                continue;
+            }
+            let encoded: BinaryArray = wkb_values(batch.column(i))
+                .map(|wkb| {
+                    wkb.map(|wkb| {
+                        buffer.clear();
+                        if self.encode(wkb, &mut buffer).is_none() {
+                            buffer.extend_from_slice(wkb);
+                        }
+                        buffer.clone()
+                    })
+                })
+                .collect();
+            columns[i] = cast(&encoded, field.data_type()).map_err(io::Error::other)?;
+        }
+        RecordBatch::try_new(batch.schema(), columns).map_err(io::Error::other)
+    }
+}
+
+/// Copies a WKB geometry from `input` to `output`, rewriting its headers and
+/// numbers in a [`WkbVariant`]
+struct Transcoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+    output: &'a mut Vec<u8>,
+    variant: WkbVariant,
+}
+
+impl Transcoder<'_> {
+    /// Copy a geometry, with the SRID if it is the `outer` geometry of EWKB
+    fn geometry(&mut self, outer: bool) -> Option<()> {
+        let little_endian = match self.read_bytes::<1>()? {
+            [0] => false,
+            [1] => true,
+            _ => return None,
+        };
+        let raw_type = self.read_u32(little_endian)?;
+        let mut has_z = raw_type & EWKB_Z != 0;
+        let mut has_m = raw_type & EWKB_M != 0;
+        if raw_type & EWKB_SRID != 0 {
+            self.read_u32(little_endian)?;
+        }
+        let iso_type = raw_type & 0x0FFF_FFFF;
+        match iso_type / 1000 {
+            0 => {}
+            1 => has_z = true,
+            2 => has_m = true,
+            3 => (has_z, has_m) = (true, true),
+            _ => return None,
+        }
+        let geometry_type = iso_type % 1000;
+
+        self.output.push(match self.variant.byte_order {
+            WkbByteOrder::Little => 1,
+            WkbByteOrder::Big => 0,
+        });
+        match self.variant.flavor {
+            WkbFlavor::Iso => {
+                let dimensions = has_z as u32 + 2 * has_m as u32;
+                self.write_u32(geometry_type + 1000 * dimensions);
+            }
+            WkbFlavor::Ewkb => {
+                let mut ewkb_type = geometry_type;
+                if has_z {
+                    ewkb_type |= EWKB_Z;
+                }
+                if has_m {
+                    ewkb_type |= EWKB_M;
+                }
+                if outer {
+                    ewkb_type |= EWKB_SRID;
+                }
+                self.write_u32(ewkb_type);
+                if outer {
+                    self.write_u32(self.variant.srid);
+                }
+            }
+        }
+
+        let ordinates = 2 + has_z as usize + has_m as usize;
+        match geometry_type {
+            1 => self.coords(1, ordinates, little_endian),
+            2 => {
+                let n = self.copy_u32(little_endian)?;
+                self.coords(n, ordinates, little_endian)
+            }
+            3 => {
+                for _ in 0..self.copy_u32(little_endian)? {
+                    let n = self.copy_u32(little_endian)?;
+                    self.coords(n, ordinates, little_endian)?;
+                }
+                Some(())
+            }
+            4..=7 => {
+                for _ in 0..self.copy_u32(little_endian)? {
+                    self.geometry(false)?;
+                }
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    /// Copy `n` coordinates of `ordinates` numbers each
+    fn coords(&mut self, n: usize, ordinates: usize, little_endian: bool) -> Option<()> {
+        for _ in 0..n * ordinates {
+            let bytes = self.read_bytes::<8>()?;
+            let value = if little_endian {
+                f64::from_le_bytes(bytes)
+            } else {
+                f64::from_be_bytes(bytes)
+            };
+            match self.variant.byte_order {
+                WkbByteOrder::Little => self.output.extend_from_slice(&value.to_le_bytes()),
+                WkbByteOrder::Big => self.output.extend_from_slice(&value.to_be_bytes()),
+            }
+        }
+        Some(())
+    }
+
+    /// Copy a count
+    fn copy_u32(&mut self, little_endian: bool) -> Option<usize> {
+        let value = self.read_u32(little_endian)?;
+        self.write_u32(value);
+        Some(value as usize)
+    }
+
+    fn read_bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.input.get(self.pos..self.pos + N)?;
+        self.pos += N;
+        bytes.try_into().ok()
+    }
+
+    fn read_u32(&mut self, little_endian: bool) -> Option<u32> {
+        let bytes = self.read_bytes::<4>()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        match self.variant.byte_order {
+            WkbByteOrder::Little => self.output.extend_from_slice(&value.to_le_bytes()),
+            WkbByteOrder::Big => self.output.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+}
+
+/// A [`RecordBatchIterator`] that writes the WKB columns of the batches of
+/// the inner iterator in a [`WkbVariant`]
+pub struct WkbEncoded<I> {
+    inner: I,
+    variant: WkbVariant,
+}
+
+impl<I: RecordBatchIterator> WkbEncoded<I> {
+    /// Rewrite the batches of `inner` in `variant`, unless it is the default
+    pub fn new(inner: I, variant: WkbVariant) -> Self {
+        Self { inner, variant }
+    }
+}
+
+impl<I: RecordBatchIterator> Iterator for WkbEncoded<I> {
+    type Item = RecordBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = self.inner.next()?;
+        if self.variant.is_default() {
+            return Some(batch);
+        }
+        let batch = self.variant.encode_batch(&batch).unwrap_or_else(|e| {
+            panic!("Failed encoding the geometries as {:?}: {e}", self.variant)
+        });
+        Some(batch)
+    }
+}
+
+impl<I: RecordBatchIterator> RecordBatchIterator for WkbEncoded<I> {
+    fn schema(&self) -> &SchemaRef {
+        self.inner.schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geoparquet::{wkb_bbox, wkb_ordinates};
+    use geo::{point, polygon, Geometry, MultiPolygon};
+    use geozero::wkb::{Ewkb, Wkb};
+    use geozero::ToGeo;
+    use spatialbench::spatial::{Crs, Dimensions};
+    use spatialbench_arrow::conversions::{geometry_to_wkb, geometry_to_wkb_with_m};
+
+    fn variant(flavor: WkbFlavor, byte_order: WkbByteOrder) -> WkbVariant {
+        WkbVariant {
+            flavor,
+            byte_order,
+            srid: 3857,
+        }
+    }
+
+    fn encode(variant: WkbVariant, wkb: &[u8]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        variant.encode(wkb, &mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_encode_point() {
+        let wkb = geometry_to_wkb(
+            &point!(x: 1.0, y: 2.0).into(),
+            Crs::Wgs84,
+            Dimensions::Xyz,
+            |_| 3.0,
+        );
+        assert_eq!(encode(WkbVariant::default(), &wkb), wkb);
+
+        let big = encode(variant(WkbFlavor::Iso, WkbByteOrder::Big), &wkb);
+        assert_eq!(&big[..5], &[0, 0, 0, 0x03, 0xe9]);
+        assert_eq!(&big[5..13], &1.0f64.to_be_bytes());
+        assert_eq!(&big[21..], &3.0f64.to_be_bytes());
+
+        let ewkb = encode(variant(WkbFlavor::Ewkb, WkbByteOrder::Little), &wkb);
+        assert_eq!(&ewkb[..9], &[1, 1, 0, 0, 0xa0, 0x11, 0x0f, 0, 0]);
+        assert_eq!(&ewkb[9..], &wkb[5..]);
+
+        // and back
+        let iso = variant(WkbFlavor::Iso, WkbByteOrder::Little);
+        assert_eq!(encode(iso, &big), wkb);
+        assert_eq!(encode(iso, &ewkb), wkb);
+    }
+
+    #[test]
+    fn test_encode_nested() {
+        let square =
+            polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 0.0)];
+        let geometry: Geometry = MultiPolygon::new(vec![square.clone(), square]).into();
+        let wkb = geometry_to_wkb_with_m(
+            &geometry,
+            Crs::Wgs84,
+            Dimensions::Xyzm,
+            |_| 5.0,
+            |i| i as f64,
+        );
+        for byte_order in [WkbByteOrder::Little, WkbByteOrder::Big] {
+            let big = encode(variant(WkbFlavor::Iso, byte_order), &wkb);
+            assert_eq!(Wkb(&big).to_geo().unwrap(), geometry);
+            assert_eq!(wkb_bbox(&big), wkb_bbox(&wkb));
+            assert_eq!(wkb_ordinates(&big), wkb_ordinates(&wkb));
+
+            let ewkb = encode(variant(WkbFlavor::Ewkb, byte_order), &wkb);
+            assert_eq!(Ewkb(&ewkb).to_geo().unwrap(), geometry);
+            assert_eq!(wkb_ordinates(&ewkb), wkb_ordinates(&wkb));
+        }
+
+        let mut buffer = vec![7];
+        let invalid = &wkb[..wkb.len() - 1];
+        assert!(variant(WkbFlavor::Ewkb, WkbByteOrder::Big)
+            .encode(invalid, &mut buffer)
+            .is_none());
+        assert_eq!(buffer, [7]);
+    }
+}
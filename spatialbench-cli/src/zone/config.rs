@@ -15,10 +15,24 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::geoarrow::GeometryEncoding;
+use crate::geoparquet::GeoParquetVersion;
+use crate::wkb_variant::WkbVariant;
 use anyhow::{anyhow, Result};
+use clap::ValueEnum;
 use parquet::basic::Compression as ParquetCompression;
 use std::path::PathBuf;
 
+/// Where the boundaries of the zones come from
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, ValueEnum)]
+pub enum ZoneSource {
+    /// The division areas of Overture Maps, downloaded from Hugging Face
+    #[default]
+    Overture,
+    /// A Voronoi tessellation of the world, generated from `--seed`
+    Tessellation,
+}
+
 #[derive(Clone)]
 pub struct ZoneDfArgs {
     pub scale_factor: f64,
@@ -28,6 +42,13 @@ pub struct ZoneDfArgs {
     pub output_file_size_mb: Option<f32>,
     pub parquet_row_group_bytes: i64,
     pub parquet_compression: ParquetCompression,
+    pub geoparquet_version: Option<GeoParquetVersion>,
+    pub geometry_encoding: GeometryEncoding,
+    pub wkb_variant: WkbVariant,
+    pub parquet_row_group_rows: Option<i64>,
+    pub parquet_data_page_bytes: Option<usize>,
+    pub source: ZoneSource,
+    pub seed: u64,
 }
 
 impl ZoneDfArgs {
@@ -48,9 +69,54 @@ impl ZoneDfArgs {
             output_file_size_mb,
             parquet_row_group_bytes,
             parquet_compression,
+            geoparquet_version: None,
+            geometry_encoding: GeometryEncoding::Wkb,
+            wkb_variant: WkbVariant::default(),
+            parquet_row_group_rows: None,
+            parquet_data_page_bytes: None,
+            source: ZoneSource::default(),
+            seed: 0,
         }
     }
 
+    pub fn with_geoparquet_version(
+        mut self,
+        geoparquet_version: Option<GeoParquetVersion>,
+    ) -> Self {
+        self.geoparquet_version = geoparquet_version;
+        self
+    }
+
+    pub fn with_geometry_encoding(mut self, geometry_encoding: GeometryEncoding) -> Self {
+        self.geometry_encoding = geometry_encoding;
+        self
+    }
+
+    pub fn with_wkb_variant(mut self, wkb_variant: WkbVariant) -> Self {
+        self.wkb_variant = wkb_variant;
+        self
+    }
+
+    pub fn with_parquet_row_group_rows(mut self, parquet_row_group_rows: Option<i64>) -> Self {
+        self.parquet_row_group_rows = parquet_row_group_rows;
+        self
+    }
+
+    pub fn with_parquet_data_page_bytes(mut self, parquet_data_page_bytes: Option<usize>) -> Self {
+        self.parquet_data_page_bytes = parquet_data_page_bytes;
+        self
+    }
+
+    pub fn with_source(mut self, source: ZoneSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
     pub fn validate(&self) -> Result<()> {
         if let (Some(part), Some(parts)) = (self.part, self.parts) {
             if part < 1 || part > parts {
@@ -15,12 +15,15 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::geoarrow::GeometryEncoding;
+use crate::geoparquet::GeoParquetVersion;
+use crate::wkb_variant::WkbVariant;
 use log::info;
 use parquet::basic::Compression as ParquetCompression;
 use std::io;
 use std::path::PathBuf;
 
-use super::config::ZoneDfArgs;
+use super::config::{ZoneDfArgs, ZoneSource};
 
 /// Generates zone table in the requested format
 #[allow(clippy::too_many_arguments)]
@@ -33,6 +36,13 @@ pub async fn generate_zone(
     max_file_size_mb: Option<f32>,
     parquet_row_group_bytes: i64,
     parquet_compression: ParquetCompression,
+    geoparquet_version: Option<GeoParquetVersion>,
+    geometry_encoding: GeometryEncoding,
+    wkb_variant: WkbVariant,
+    parquet_row_group_rows: Option<i64>,
+    parquet_data_page_bytes: Option<usize>,
+    source: ZoneSource,
+    seed: u64,
 ) -> io::Result<()> {
     match format {
         OutputFormat::Parquet => {
@@ -49,7 +59,14 @@ pub async fn generate_zone(
                     max_file_size_mb,
                     parquet_row_group_bytes,
                     parquet_compression,
-                );
+                )
+                .with_geoparquet_version(geoparquet_version)
+                .with_geometry_encoding(geometry_encoding)
+                .with_wkb_variant(wkb_variant)
+                .with_parquet_row_group_rows(parquet_row_group_rows)
+                .with_parquet_data_page_bytes(parquet_data_page_bytes)
+                .with_source(source)
+                .with_seed(seed);
                 super::generate_zone_parquet_single(args)
                     .await
                     .map_err(io::Error::other)
@@ -64,7 +81,14 @@ pub async fn generate_zone(
                     max_file_size_mb,
                     parquet_row_group_bytes,
                     parquet_compression,
-                );
+                )
+                .with_geoparquet_version(geoparquet_version)
+                .with_geometry_encoding(geometry_encoding)
+                .with_wkb_variant(wkb_variant)
+                .with_parquet_row_group_rows(parquet_row_group_rows)
+                .with_parquet_data_page_bytes(parquet_data_page_bytes)
+                .with_source(source)
+                .with_seed(seed);
                 super::generate_zone_parquet_multi(args)
                     .await
                     .map_err(io::Error::other)
@@ -82,4 +106,8 @@ pub enum OutputFormat {
     Tbl,
     Csv,
     Parquet,
+    Geojson,
+    Geojsonl,
+    Flatgeobuf,
+    Arrow,
 }
@@ -15,12 +15,14 @@
 // specific language governing permissions and limitations
 // under the License.
 
-//! Zone table generation module using DataFusion and remote Parquet files
+//! Zone table generation module using DataFusion and remote Parquet files,
+//! or a generated tessellation of the world
 
 mod config;
 mod datasource;
 mod partition;
 mod stats;
+mod tessellation;
 mod transform;
 mod writer;
 
@@ -29,7 +31,7 @@ pub mod main;
 use anyhow::Result;
 use std::sync::Arc;
 
-pub use config::ZoneDfArgs;
+pub use config::{ZoneDfArgs, ZoneSource};
 use datasource::ZoneDataSource;
 use partition::PartitionStrategy;
 use stats::ZoneTableStats;
@@ -41,14 +43,21 @@ pub async fn generate_zone_parquet_single(args: ZoneDfArgs) -> Result<()> {
     args.validate()?;
 
     let stats = ZoneTableStats::new(args.scale_factor, args.parts);
+    let partition =
+        PartitionStrategy::calculate(stats.estimated_total_rows(), args.parts, args.part);
+
+    if args.source == ZoneSource::Tessellation {
+        let batch = tessellation::record_batch(stats.estimated_total_rows() as usize, args.seed)?;
+        let batches = partition.apply_to_batches(&[batch])?;
+        let writer = ParquetWriter::new(&args, &stats, tessellation::schema());
+        return writer.write(&batches);
+    }
+
     let datasource = ZoneDataSource::new().await?;
     let ctx = datasource.create_context()?;
 
     let df = datasource.load_zone_data(&ctx, args.scale_factor).await?;
 
-    let partition =
-        PartitionStrategy::calculate(stats.estimated_total_rows(), args.parts, args.part);
-
     let df = partition.apply_to_dataframe(df)?;
 
     let transformer = ZoneTransformer::new(partition.offset());
@@ -67,18 +76,28 @@ pub async fn generate_zone_parquet_single(args: ZoneDfArgs) -> Result<()> {
 /// Generate all parts by collecting once and partitioning in memory
 pub async fn generate_zone_parquet_multi(args: ZoneDfArgs) -> Result<()> {
     let stats = ZoneTableStats::new(args.scale_factor, args.parts);
-    let datasource = ZoneDataSource::new().await?;
-    let ctx = datasource.create_context()?;
 
-    let df = datasource.load_zone_data(&ctx, args.scale_factor).await?;
+    let (schema, batches) = match args.source {
+        ZoneSource::Overture => {
+            let datasource = ZoneDataSource::new().await?;
+            let ctx = datasource.create_context()?;
 
-    // Transform without offset (we'll adjust per-part later)
-    let transformer = ZoneTransformer::new(0);
-    let df = transformer.transform(&ctx, df).await?;
+            let df = datasource.load_zone_data(&ctx, args.scale_factor).await?;
 
-    // Collect once
-    let schema = Arc::new(transformer.arrow_schema(&df)?);
-    let batches = df.collect().await?;
+            // Transform without offset (we'll adjust per-part later)
+            let transformer = ZoneTransformer::new(0);
+            let df = transformer.transform(&ctx, df).await?;
+
+            // Collect once
+            let schema = Arc::new(transformer.arrow_schema(&df)?);
+            (schema, df.collect().await?)
+        }
+        ZoneSource::Tessellation => {
+            let zones = stats.estimated_total_rows() as usize;
+            let batch = tessellation::record_batch(zones, args.seed)?;
+            (tessellation::schema(), vec![batch])
+        }
+    };
 
     // Calculate total rows
     let total_rows: i64 = batches.iter().map(|b| b.num_rows() as i64).sum();
@@ -103,7 +122,14 @@ pub async fn generate_zone_parquet_multi(args: ZoneDfArgs) -> Result<()> {
             args.output_file_size_mb,
             args.parquet_row_group_bytes,
             args.parquet_compression,
-        );
+        )
+        .with_geoparquet_version(args.geoparquet_version)
+        .with_geometry_encoding(args.geometry_encoding)
+        .with_wkb_variant(args.wkb_variant)
+        .with_parquet_row_group_rows(args.parquet_row_group_rows)
+        .with_parquet_data_page_bytes(args.parquet_data_page_bytes)
+        .with_source(args.source)
+        .with_seed(args.seed);
 
         let writer = ParquetWriter::new(&part_args, &stats, schema.clone());
         writer.write(&partitioned_batches)?;
@@ -0,0 +1,272 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Zone boundaries generated as a Voronoi tessellation of the world
+//!
+//! The seeds of the zones are placed on a jittered grid derived from the
+//! seed, and the Voronoi diagram is computed from their Delaunay
+//! triangulation. The seeds near the edges of the world are mirrored across
+//! them, so the bisectors with their mirrors clip the cells to the edges
+//! exactly. Each vertex of the diagram is the circumcenter of one triangle
+//! and is computed and rounded once, so neighbouring zones share identical
+//! vertices and the zones cover the world without gaps or overlaps.
+
+use anyhow::{anyhow, Result};
+use arrow_array::{ArrayRef, BinaryArray, Int64Array, RecordBatch, StringViewArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use geo::{Coord, Geometry, LineString, Polygon};
+use spade::{DelaunayTriangulation, Point2, Triangulation};
+use spatialbench::spatial::crs::Crs;
+use spatialbench::spatial::precision::snap_coordinates;
+use spatialbench::spatial::utils::spider_seed_for_index;
+use spatialbench::spatial::Dimensions;
+use spatialbench_arrow::conversions::geometry_to_wkb;
+use std::iter::repeat_n;
+use std::sync::Arc;
+
+/// Bounds of the tessellation: the world in longitude / latitude
+const MIN_X: f64 = -180.0;
+const MAX_X: f64 = 180.0;
+const MIN_Y: f64 = -90.0;
+const MAX_Y: f64 = 90.0;
+
+/// Seeds closer to an edge than this many cells are mirrored across it
+const MIRRORED_CELLS: f64 = 3.0;
+
+/// Subtype of the generated zones
+const SUBTYPE: &str = "tessellation";
+
+/// Return the schema of the zone table, as written from Overture Maps
+pub fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("z_zonekey", DataType::Int64, false),
+        Field::new("z_gersid", DataType::Utf8View, false),
+        Field::new("z_country", DataType::Utf8View, false),
+        Field::new("z_region", DataType::Utf8View, false),
+        Field::new("z_name", DataType::Utf8View, false),
+        Field::new("z_subtype", DataType::Utf8View, false),
+        Field::new("z_boundary", DataType::Binary, false),
+    ]))
+}
+
+/// Generate a zone table of `zones` rows tessellating the world
+///
+/// The zones have the keys `1..=zones` and are named `Zone#000000001` and
+/// so on, in the order of their seeds from south west to north east. The
+/// Overture Maps ids, countries and regions are empty.
+pub fn record_batch(zones: usize, seed: u64) -> Result<RecordBatch> {
+    let boundaries = tessellate(zones, seed)?;
+    let strings = |value: &str| -> ArrayRef {
+        Arc::new(StringViewArray::from_iter_values(repeat_n(value, zones)))
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from_iter_values(1..=zones as i64)),
+        strings(""),
+        strings(""),
+        strings(""),
+        Arc::new(StringViewArray::from_iter_values(
+            (1..=zones).map(|key| format!("Zone#{key:09}")),
+        )),
+        strings(SUBTYPE),
+        Arc::new(BinaryArray::from_iter_values(boundaries.into_iter().map(
+            |boundary| {
+                geometry_to_wkb(
+                    &Geometry::Polygon(boundary),
+                    Crs::Wgs84,
+                    Dimensions::Xy,
+                    |_| f64::NAN,
+                )
+            },
+        ))),
+    ];
+    Ok(RecordBatch::try_new(schema(), columns)?)
+}
+
+/// Generate the boundaries of `zones` zones tessellating the world
+///
+/// The exterior rings are counterclockwise, with the vertices rounded to
+/// the precision set with
+/// [`set_precision`](spatialbench::spatial::precision::set_precision).
+pub fn tessellate(zones: usize, seed: u64) -> Result<Vec<Polygon>> {
+    if zones == 0 {
+        return Ok(Vec::new());
+    }
+    let (seeds, margin) = seed_points(zones, seed);
+
+    let mut points = seeds.clone();
+    for p in &seeds {
+        if p.x - MIN_X < margin {
+            points.push(Point2::new(2.0 * MIN_X - p.x, p.y));
+        }
+        if MAX_X - p.x < margin {
+            points.push(Point2::new(2.0 * MAX_X - p.x, p.y));
+        }
+        if p.y - MIN_Y < margin {
+            points.push(Point2::new(p.x, 2.0 * MIN_Y - p.y));
+        }
+        if MAX_Y - p.y < margin {
+            points.push(Point2::new(p.x, 2.0 * MAX_Y - p.y));
+        }
+    }
+    // the stable bulk load keeps the seeds at the indices 0..zones
+    let num_points = points.len();
+    let triangulation = DelaunayTriangulation::<Point2<f64>>::bulk_load_stable(points)?;
+    if triangulation.num_vertices() != num_points {
+        return Err(anyhow!("the seeds of the zones are not distinct"));
+    }
+
+    // the vertices of the diagram, by the index of their triangle
+    let mut vertices = vec![Coord::zero(); triangulation.num_all_faces()];
+    for face in triangulation.inner_faces() {
+        let center = face.circumcenter();
+        let (x, y) = snap_coordinates(center.x, center.y);
+        vertices[face.fix().index()] = Coord {
+            x: x.clamp(MIN_X, MAX_X),
+            y: y.clamp(MIN_Y, MAX_Y),
+        };
+    }
+
+    triangulation
+        .vertices()
+        .take(zones)
+        .map(|vertex| {
+            let mut ring: Vec<Coord> = Vec::new();
+            // the out edges of a vertex, and their dual edges, are
+            // counterclockwise
+            for edge in vertex.as_voronoi_face().adjacent_edges() {
+                let face = edge
+                    .from()
+                    .as_delaunay_face()
+                    .ok_or_else(|| anyhow!("zone {} is not bounded", vertex.index() + 1))?;
+                let coord = vertices[face.fix().index()];
+                // cocircular seeds give several triangles with the same center
+                if ring.last() != Some(&coord) {
+                    ring.push(coord);
+                }
+            }
+            if ring.len() > 1 && ring.first() == ring.last() {
+                ring.pop();
+            }
+            Ok(Polygon::new(LineString::from(ring), vec![]))
+        })
+        .collect()
+}
+
+/// Return the seeds of `zones` zones, and the distance to the edges within
+/// which they are mirrored
+///
+/// The world is split in rows of cells about as wide as high, and each seed
+/// is placed at random in the middle half of its cell. A cell of the
+/// diagram then never reaches further than a couple of cells from its seed.
+fn seed_points(zones: usize, seed: u64) -> (Vec<Point2<f64>>, f64) {
+    let rows = ((zones as f64 / 2.0).sqrt().ceil() as usize).max(1);
+    let cell_height = (MAX_Y - MIN_Y) / rows as f64;
+
+    let mut points = Vec::with_capacity(zones);
+    for row in 0..rows {
+        let columns = zones * (row + 1) / rows - zones * row / rows;
+        let cell_width = (MAX_X - MIN_X) / columns as f64;
+        for column in 0..columns {
+            let (u, v) = jitter(seed, points.len() as u64);
+            points.push(Point2::new(
+                MIN_X + (column as f64 + u) * cell_width,
+                MIN_Y + (row as f64 + v) * cell_height,
+            ));
+        }
+    }
+
+    let widest_cell = (MAX_X - MIN_X) / (zones / rows).max(1) as f64;
+    (points, MIRRORED_CELLS * widest_cell.max(cell_height))
+}
+
+/// Return the position of the seed `index` in its cell, in `0.25..0.75`
+fn jitter(seed: u64, index: u64) -> (f64, f64) {
+    let u = spider_seed_for_index(seed ^ spider_seed_for_index(index, 0), 0);
+    let v = spider_seed_for_index(u, 0);
+    let unit = |value: u64| (value >> 11) as f64 / (1u64 << 53) as f64;
+    (0.25 + 0.5 * unit(u), 0.25 + 0.5 * unit(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Area, Contains, Point, Validation};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_tessellate_covers_the_world() {
+        let zones = tessellate(500, 42).unwrap();
+        assert_eq!(zones.len(), 500);
+
+        for zone in &zones {
+            assert!(zone.is_valid(), "{zone:?}");
+            assert!(zone.signed_area() > 0.0);
+        }
+        // no overlaps and no gaps
+        let area: f64 = zones.iter().map(|zone| zone.unsigned_area()).sum();
+        let world = (MAX_X - MIN_X) * (MAX_Y - MIN_Y);
+        assert!((area - world).abs() < 1e-6, "{area} != {world}");
+
+        for i in 0..1000 {
+            let (u, v) = jitter(7, i);
+            let point = Point::new(MIN_X + (u - 0.25) * 720.0, MIN_Y + (v - 0.25) * 360.0);
+            let containing = zones.iter().filter(|zone| zone.contains(&point)).count();
+            assert_eq!(containing, 1, "{point:?}");
+        }
+    }
+
+    #[test]
+    fn test_tessellate_shares_vertices() {
+        let zones = tessellate(300, 0).unwrap();
+
+        // every edge is used once in each direction by two neighbours, or
+        // once by a zone on the edge of the world
+        let key = |c: Coord| (c.x.to_bits(), c.y.to_bits());
+        let mut edges = HashMap::new();
+        for zone in &zones {
+            for line in zone.exterior().lines() {
+                *edges.entry((key(line.start), key(line.end))).or_insert(0) += 1;
+            }
+        }
+        for (&(start, end), &count) in &edges {
+            assert_eq!(count, 1);
+            let (x0, y0) = (f64::from_bits(start.0), f64::from_bits(start.1));
+            let (x1, y1) = (f64::from_bits(end.0), f64::from_bits(end.1));
+            let on_edge = (x0 == x1 && (x0 == MIN_X || x0 == MAX_X))
+                || (y0 == y1 && (y0 == MIN_Y || y0 == MAX_Y));
+            assert!(
+                on_edge || edges.contains_key(&(end, start)),
+                "({x0}, {y0}) -> ({x1}, {y1})"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tessellate_is_deterministic() {
+        assert_eq!(tessellate(50, 3).unwrap(), tessellate(50, 3).unwrap());
+        assert_ne!(tessellate(50, 3).unwrap(), tessellate(50, 4).unwrap());
+        assert_eq!(tessellate(1, 0).unwrap()[0].unsigned_area(), 360.0 * 180.0);
+        assert!(tessellate(0, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_batch() {
+        let batch = record_batch(10, 0).unwrap();
+        assert_eq!(batch.num_rows(), 10);
+        assert_eq!(batch.schema(), schema());
+    }
+}
@@ -15,11 +15,19 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::geoarrow::{GeoArrowEncoder, GeometryEncoding};
+use crate::geoparquet::{is_wkb_type, wkb_values, GeoParquetEncoder};
 use anyhow::Result;
-use arrow_array::RecordBatch;
+use arrow::compute::cast;
+use arrow_array::{BinaryArray, RecordBatch};
 use arrow_schema::SchemaRef;
+use geozero::wkb::Wkb;
+use geozero::ToGeo;
 use log::{debug, info};
 use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+use spatialbench::spatial::crs::{self, Crs};
+use spatialbench::spatial::Dimensions;
+use spatialbench_arrow::conversions::geometry_to_wkb;
 use std::{path::PathBuf, sync::Arc, time::Instant};
 
 use super::config::ZoneDfArgs;
@@ -34,13 +42,18 @@ pub struct ParquetWriter {
 
 impl ParquetWriter {
     pub fn new(args: &ZoneDfArgs, stats: &ZoneTableStats, schema: SchemaRef) -> Self {
-        let rows_per_group =
-            stats.compute_rows_per_group(args.parquet_row_group_bytes, 128 * 1024 * 1024);
+        let rows_per_group = match args.parquet_row_group_rows {
+            Some(rows) => rows as usize,
+            None => stats.compute_rows_per_group(args.parquet_row_group_bytes, 128 * 1024 * 1024),
+        };
 
-        let props = WriterProperties::builder()
+        let mut props = WriterProperties::builder()
             .set_compression(args.parquet_compression)
-            .set_max_row_group_size(rows_per_group)
-            .build();
+            .set_max_row_group_size(rows_per_group);
+        if let Some(data_page_bytes) = args.parquet_data_page_bytes {
+            props = props.set_data_page_size_limit(data_page_bytes);
+        }
+        let props = props.build();
 
         debug!("Using row group size: {} rows", rows_per_group);
 
@@ -71,15 +84,63 @@ impl ParquetWriter {
             return Ok(());
         }
 
+        let wkb_variant = self.args.wkb_variant;
+        let batches = batches
+            .iter()
+            .map(|batch| {
+                let batch = project_geometries(batch)?;
+                if wkb_variant.is_default() {
+                    return Ok(batch);
+                }
+                Ok(wkb_variant.encode_batch(&batch)?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let batches = batches.as_slice();
+
         // Write to temp file first
         let temp_path = self.output_path.with_extension("inprogress");
         let t0 = Instant::now();
         let file = std::fs::File::create(&temp_path)?;
-        let mut writer =
-            ArrowWriter::try_new(file, Arc::clone(&self.schema), Some(self.props.clone()))?;
-
+        let mut geoparquet_encoder = self
+            .args
+            .geoparquet_version
+            .map(|version| GeoParquetEncoder::new(&self.schema, version));
+        let schema = match &geoparquet_encoder {
+            Some(encoder) => Arc::clone(encoder.schema()),
+            None => Arc::clone(&self.schema),
+        };
+        // all batches are in memory, so infer the native geometry types from all of them
+        let geoarrow_encoder = match self.args.geometry_encoding {
+            GeometryEncoding::Wkb => None,
+            GeometryEncoding::Geoarrow => Some(GeoArrowEncoder::new(&schema, batches)),
+        };
+        let schema = match &geoarrow_encoder {
+            Some(encoder) => {
+                geoparquet_encoder = geoparquet_encoder
+                    .map(|geoparquet| geoparquet.with_native_types(encoder.geometry_types()));
+                Arc::clone(encoder.schema())
+            }
+            None => schema,
+        };
+        let mut writer = ArrowWriter::try_new(file, schema, Some(self.props.clone()))?;
+
+        let mut geo_statistics = geoparquet_encoder
+            .as_ref()
+            .map(|encoder| encoder.new_statistics())
+            .unwrap_or_default();
         for batch in batches {
-            writer.write(batch)?;
+            let batch = match &geoparquet_encoder {
+                Some(encoder) => encoder.encode(batch.clone(), &mut geo_statistics),
+                None => batch.clone(),
+            };
+            let batch = match &geoarrow_encoder {
+                Some(encoder) => encoder.encode(batch)?,
+                None => batch,
+            };
+            writer.write(&batch)?;
+        }
+        if let Some(encoder) = &geoparquet_encoder {
+            writer.append_key_value_metadata(encoder.metadata(&geo_statistics));
         }
 
         writer.close()?;
@@ -109,3 +170,32 @@ impl ParquetWriter {
         Ok(())
     }
 }
+
+/// Project the WKB geometry columns of `batch` to the CRS set with
+/// [`crs::set_crs`], as the zone boundaries are downloaded in longitude /
+/// latitude
+fn project_geometries(batch: &RecordBatch) -> Result<RecordBatch> {
+    let crs = crs::crs();
+    if crs == Crs::Wgs84 {
+        return Ok(batch.clone());
+    }
+    let mut columns = batch.columns().to_vec();
+    for (i, field) in batch.schema_ref().fields().iter().enumerate() {
+        if !is_wkb_type(field.data_type()) {
+            continue;
+        }
+        let projected = wkb_values(batch.column(i))
+            .map(|wkb| {
+                wkb.map(|wkb| {
+                    let geometry = Wkb(wkb).to_geo()?;
+                    Ok(geometry_to_wkb(&geometry, crs, Dimensions::Xy, |_| {
+                        f64::NAN
+                    }))
+                })
+                .transpose()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        columns[i] = cast(&BinaryArray::from_iter(projected), field.data_type())?;
+    }
+    Ok(RecordBatch::try_new(batch.schema(), columns)?)
+}
@@ -15,6 +15,9 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use arrow_array::cast::AsArray;
+use arrow_array::types::Int64Type;
+use arrow_array::Array;
 use arrow_array::RecordBatch;
 use assert_cmd::Command;
 use parquet::arrow::arrow_reader::{ArrowReaderOptions, ParquetRecordBatchReaderBuilder};
@@ -209,7 +212,7 @@ fn test_spatialbench_cli_parquet_no_overwrite() {
     run_command();
     let original_metadata =
         fs::metadata(&expected_file).expect("Failed to get metadata of generated file");
-    assert_eq!(original_metadata.len(), 412);
+    assert_eq!(original_metadata.len(), 1023);
 
     // Run the spatialbench-cli command again with the same parameters and expect the
     // file to not be overwritten
@@ -402,6 +405,41 @@ fn test_spatialbench_cli_parts_explicit() {
     verify_table(temp_dir.path(), "trip", num_parts, "v1");
 }
 
+/// Generate the dimension tables with one invocation per --part, as separate
+/// machines would, and verify the union of the parts has every row once
+#[test]
+fn test_spatialbench_cli_parts_explicit_all_tables() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    let num_parts = 3;
+    for part in 1..=num_parts {
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("--scale-factor")
+            .arg("0.51")
+            .arg("--format")
+            .arg("tbl")
+            .arg("--tables")
+            .arg("building,driver,vehicle,customer")
+            .arg("--output-dir")
+            .arg(temp_dir.path())
+            .arg("--parts")
+            .arg(num_parts.to_string())
+            .arg("--part")
+            .arg(part.to_string())
+            .assert()
+            .success();
+    }
+
+    verify_table(temp_dir.path(), "customer", num_parts, "v1");
+    // tables that are not split are only written by the first part
+    for table in ["building", "vehicle", "driver"] {
+        verify_table(temp_dir.path(), table, 1, "v1");
+        let other_part = temp_dir.path().join(table).join(format!("{table}.2.tbl"));
+        assert!(!other_part.exists(), "{other_part:?} should not exist");
+    }
+}
+
 /// Create all tables using --parts option and verify the output layouts
 #[test]
 fn test_spatialbench_cli_parts_all_tables() {
@@ -744,6 +782,122 @@ fn test_spatialbench_cli_zero_part_zero_parts() {
         ));
 }
 
+/// Test `--output -` writes the same bytes to stdout as to a file
+#[test]
+fn test_spatialbench_cli_output_stdout() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    for format in ["parquet", "csv", "geojsonl"] {
+        let output = Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("--scale-factor")
+            .arg("0.001")
+            .arg("--format")
+            .arg(format)
+            .arg("--tables")
+            .arg("trip")
+            .arg("--output")
+            .arg("-")
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+
+        let output_file = temp_dir.path().join(format!("trip_{format}"));
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("--scale-factor")
+            .arg("0.001")
+            .arg("--format")
+            .arg(format)
+            .arg("--tables")
+            .arg("trip")
+            .arg("--output")
+            .arg(&output_file)
+            .assert()
+            .success();
+
+        let expected = fs::read(&output_file).expect("Failed to read output file");
+        assert!(!expected.is_empty());
+        assert_eq!(output, expected, "stdout differs from file for {format}");
+    }
+}
+
+/// Test that --output - is rejected with several tables or parts
+#[test]
+fn test_spatialbench_cli_output_stdout_single_table() {
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip,building")
+        .arg("--output")
+        .arg("-")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--output requires a single table, e.g. --tables trip",
+        ));
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--parts")
+        .arg("4")
+        .arg("--output")
+        .arg("-")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--output writes a single part, use --part with --parts",
+        ));
+}
+
+/// Test the `queries` command prints the same queries for the same seed
+#[test]
+fn test_spatialbench_cli_queries() {
+    let run_command = |seed: &str| {
+        let output = Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("queries")
+            .arg("--dialect")
+            .arg("duckdb")
+            .arg("--scale-factor")
+            .arg("0.1")
+            .arg("--seed")
+            .arg(seed)
+            .arg("--queries")
+            .arg("Q1,Q12")
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(output).expect("queries are utf8")
+    };
+
+    let queries = run_command("42");
+    assert!(queries.starts_with("-- Q1: "));
+    assert!(queries.contains("\n-- Q12: "));
+    assert!(queries.contains("CROSS JOIN LATERAL"));
+    assert_eq!(queries.matches(';').count(), 2);
+    assert_eq!(queries, run_command("42"));
+    assert_ne!(queries, run_command("43"));
+
+    // without a seed, the published queries are printed
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("queries")
+        .arg("--queries")
+        .arg("Q1")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "ST_GeomFromText('POINT (-111.7610 34.8697)'), 0.45)",
+        ));
+}
+
 /// Test specifying parquet options even when writing tbl output
 #[tokio::test]
 async fn test_incompatible_options_warnings() {
@@ -885,6 +1039,162 @@ async fn test_customer_output_file_size() {
     assert!(file_count > 1, "Expected multiple files with 1MB limit");
 }
 
+/// Test that --target-file-size splits the tables into files of roughly that
+/// size, as --mb-per-file does
+#[test]
+fn test_spatialbench_cli_target_file_size() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    for (dir, option, size) in [
+        ("target", "--target-file-size", "2MB"),
+        ("mb", "--mb-per-file", "2"),
+    ] {
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("--format")
+            .arg("csv")
+            .arg("--scale-factor")
+            .arg("0.01")
+            .arg("--tables")
+            .arg("trip")
+            .arg("--output-dir")
+            .arg(temp_dir.path().join(dir))
+            .arg(option)
+            .arg(size)
+            .assert()
+            .success();
+    }
+
+    let files = |dir: &str| {
+        let mut files: Vec<_> = fs::read_dir(temp_dir.path().join(dir).join("trip"))
+            .expect("Failed to read trip directory")
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        files
+    };
+    let target_files = files("target");
+    assert!(target_files.len() > 1, "Expected multiple files with 2MB");
+    assert!(target_files.contains(&"trip.1.csv".to_string()));
+    assert_eq!(target_files, files("mb"));
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--target-file-size")
+        .arg("512MB")
+        .arg("--parts")
+        .arg("2")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("cannot be used with"));
+}
+
+/// Test that --merge writes one file per table with the rows of all the parts
+#[test]
+fn test_spatialbench_cli_merge() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    for (dir, parts, merge) in [("single", "1", false), ("merged", "3", true)] {
+        for format in ["parquet", "csv"] {
+            let mut command = Command::cargo_bin("spatialbench-cli").expect("Binary not found");
+            command
+                .arg("--format")
+                .arg(format)
+                .arg("--scale-factor")
+                .arg("0.01")
+                .arg("--tables")
+                .arg("trip,vehicle")
+                .arg("--output-dir")
+                .arg(temp_dir.path().join(dir))
+                .arg("--parts")
+                .arg(parts);
+            if merge {
+                command.arg("--merge");
+            }
+            command.assert().success();
+        }
+    }
+
+    let merged = temp_dir.path().join("merged");
+    assert!(!merged.join("trip").exists(), "the parts are removed");
+    // only the header of the first part is kept
+    let single = fs::read_to_string(temp_dir.path().join("single/trip/trip.1.csv")).unwrap();
+    let csv = fs::read_to_string(merged.join("trip.csv")).unwrap();
+    assert_eq!(csv, single);
+
+    let rows = |path: PathBuf| -> usize {
+        let file = File::open(path).expect("Failed to open parquet file");
+        ParquetRecordBatchReaderBuilder::try_new(file)
+            .expect("Failed to create ParquetRecordBatchReaderBuilder")
+            .build()
+            .expect("Failed to build reader")
+            .map(|batch| batch.unwrap().num_rows())
+            .sum()
+    };
+    let single_rows = rows(temp_dir.path().join("single/trip/trip.1.parquet"));
+    assert_eq!(rows(merged.join("trip.parquet")), single_rows);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--format")
+        .arg("flatgeobuf")
+        .arg("--parts")
+        .arg("2")
+        .arg("--merge")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--merge requires --format"));
+}
+
+/// Test that --path-template sets the paths of the files and the manifest
+#[test]
+fn test_spatialbench_cli_path_template() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--format")
+        .arg("csv")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("trip,vehicle")
+        .arg("--parts")
+        .arg("2")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .arg("--path-template")
+        .arg("sf={sf}/{table}/part-{part:03}.{ext}")
+        .assert()
+        .success();
+
+    for path in [
+        "sf=0.01/trip/part-001.csv",
+        "sf=0.01/trip/part-002.csv",
+        "sf=0.01/vehicle/part-001.csv",
+    ] {
+        assert!(temp_dir.path().join(path).exists(), "{path} not found");
+    }
+    assert!(!temp_dir.path().join("trip").exists());
+    let manifest = fs::read_to_string(temp_dir.path().join("_spatialbench_manifest.json"))
+        .expect("Failed to read the manifest");
+    assert!(manifest.contains("sf=0.01/trip/part-002.csv"), "{manifest}");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--parts")
+        .arg("2")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .arg("--path-template")
+        .arg("{table}.parquet")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("must contain {part}"));
+}
+
 #[tokio::test]
 async fn test_zone_file_size_conflicts_with_parts() {
     let temp_dir = tempdir().expect("Failed to create temporary directory");
@@ -969,3 +1279,3239 @@ fn expect_row_group_sizes(output_dir: &Path, expected_row_groups: Vec<RowGroups>
     let actual_row_groups = format!("{actual_row_groups:#?}");
     assert_eq!(actual_row_groups, expected_row_groups);
 }
+
+/// Test generating a stream of query parameters
+#[test]
+fn test_spatialbench_cli_params() {
+    let run_command = |format: &str| {
+        let output = Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("params")
+            .arg("--query")
+            .arg("Q3")
+            .arg("--count")
+            .arg("100")
+            .arg("--seed")
+            .arg("42")
+            .arg("--scale-factor")
+            .arg("0.1")
+            .arg("--format")
+            .arg(format)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(output).expect("parameters are utf8")
+    };
+
+    let params = run_command("jsonl");
+    assert_eq!(params.lines().count(), 100);
+    for (index, line) in params.lines().enumerate() {
+        let value: serde_json::Value = serde_json::from_str(line).expect("valid json");
+        assert_eq!(value["query"], "Q3");
+        assert_eq!(value["index"], index);
+        assert!(value["window"].as_str().unwrap().starts_with("POLYGON(("));
+        assert_eq!(value["buffer"], 5000.0);
+    }
+    assert_eq!(params, run_command("jsonl"));
+
+    let csv = run_command("csv");
+    assert!(csv.starts_with("query,index,window,buffer\n"));
+    assert_eq!(csv.lines().count(), 101);
+
+    // the selectivity sets the number of top trips of Q4
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("params")
+        .arg("--query")
+        .arg("Q4")
+        .arg("--selectivity")
+        .arg("0.001")
+        .assert()
+        .success()
+        .stdout("{\"query\":\"Q4\",\"index\":0,\"limit\":6000}\n");
+}
+
+/// Test generating query parameters for several selectivities
+#[test]
+fn test_spatialbench_cli_params_selectivities() {
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["params", "--query", "Q4", "--count", "2"])
+        .args(["--selectivity", "low,0.05,high"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let lines: Vec<serde_json::Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0]["selectivity"], 0.001);
+    assert_eq!(lines[0]["limit"], 6000);
+    assert_eq!(lines[3]["index"], 1);
+    assert_eq!(lines[3]["limit"], 300_000);
+    assert_eq!(lines[5]["selectivity"], 0.1);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["params", "--query", "Q4", "--selectivity", "huge"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("one of low, medium and high"));
+}
+
+/// Test generating kNN queries with their exact neighbors
+#[test]
+fn test_spatialbench_cli_knn() {
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["knn", "--table", "trip", "--count", "3", "--k", "4"])
+        .args(["--scale-factor", "0.001", "--ground-truth"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 3);
+    for (index, line) in output.lines().enumerate() {
+        let query: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(query["index"], index);
+        assert_eq!(query["k"], 4);
+        assert!(query["center"].as_str().unwrap().starts_with("POINT ("));
+        assert_eq!(query["neighbors"].as_array().unwrap().len(), 4);
+        let distances: Vec<f64> = query["distances"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d.as_f64().unwrap())
+            .collect();
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["knn", "--k", "3", "--format", "sql", "--dialect", "duckdb"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "FROM building\nORDER BY distance, b_buildingkey\nLIMIT 3;",
+        ));
+
+    // the neighbors are those of the trips generated with the seed
+    let neighbors = |seed: &str| {
+        let output = Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .args(["knn", "--table", "trip", "--k", "4", "--ground-truth"])
+            .args(["--scale-factor", "0.001", "--seed", seed])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let query: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        query["neighbors"].clone()
+    };
+    assert_ne!(neighbors("0"), neighbors("1"));
+
+    // the projection is not applied to the data in memory
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["knn", "--ground-truth", "--crs", "EPSG:3857"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--ground-truth can not be used with --crs",
+        ));
+}
+
+/// Test writing the expected answers of the queries
+#[test]
+fn test_spatialbench_cli_answers() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("answers")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--query-seed")
+        .arg("42")
+        .arg("--queries")
+        .arg("Q1,Q7,Q2")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Q2 uses the downloaded zone table",
+        ));
+
+    let q1 = fs::read_to_string(temp_dir.path().join("q1.sql")).expect("q1.sql is written");
+    assert!(q1.starts_with("-- Q1: "));
+    let q7 = fs::read_to_string(temp_dir.path().join("q7.csv")).expect("q7.csv is written");
+    assert!(q7.starts_with("t_tripkey,reported_distance_m,line_distance_m,detour_ratio\n"));
+    // one row per trip
+    assert_eq!(q7.lines().count(), 6001);
+    assert!(!temp_dir.path().join("q2.csv").exists());
+
+    // the answers are computed on the data of the options of the generation
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["answers", "--scale-factor", "0.001", "--queries", "Q7"])
+        .args(["--seed", "1", "--rows", "trip=10"])
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+    let q7 = fs::read_to_string(temp_dir.path().join("q7.csv")).expect("q7.csv is written");
+    assert_eq!(q7.lines().count(), 11);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["answers", "--null-rate", "trip.t_dropoffloc=0.1"])
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "answers can not be used with --null-rate and --empty-rate",
+        ));
+}
+
+/// Test writing the cardinalities of the joins, with a zone table
+#[test]
+fn test_spatialbench_cli_answers_cardinalities() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    // two zones covering the western and eastern hemispheres
+    let wkb = |min_x: f64, max_x: f64| {
+        let mut wkb = vec![1];
+        wkb.extend(3u32.to_le_bytes());
+        wkb.extend(1u32.to_le_bytes());
+        wkb.extend(5u32.to_le_bytes());
+        for (x, y) in [
+            (min_x, -90.0),
+            (max_x, -90.0),
+            (max_x, 90.0),
+            (min_x, 90.0),
+            (min_x, -90.0),
+        ] {
+            wkb.extend(f64::to_le_bytes(x));
+            wkb.extend(f64::to_le_bytes(y));
+        }
+        wkb
+    };
+    let (west, east) = (wkb(-180.0, 0.0), wkb(0.0, 180.0));
+    let batch = RecordBatch::try_from_iter([
+        (
+            "z_zonekey",
+            Arc::new(arrow_array::Int64Array::from(vec![1, 2])) as Arc<dyn Array>,
+        ),
+        (
+            "z_name",
+            Arc::new(arrow_array::StringArray::from(vec![
+                "Coconino County",
+                "East",
+            ])),
+        ),
+        (
+            "z_boundary",
+            Arc::new(arrow_array::BinaryArray::from(vec![
+                west.as_slice(),
+                east.as_slice(),
+            ])),
+        ),
+    ])
+    .unwrap();
+    let zones = temp_dir.path().join("zone.parquet");
+    let mut writer =
+        parquet::arrow::ArrowWriter::try_new(File::create(&zones).unwrap(), batch.schema(), None)
+            .unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+
+    let answers = temp_dir.path().join("answers");
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args([
+            "answers",
+            "--scale-factor",
+            "0.001",
+            "--queries",
+            "Q1,Q10,Q12",
+        ])
+        .arg("--zones")
+        .arg(&zones)
+        .arg("--output-dir")
+        .arg(&answers)
+        .assert()
+        .success();
+
+    let cardinalities = fs::read_to_string(answers.join("cardinalities.csv")).unwrap();
+    let lines: Vec<_> = cardinalities.lines().collect();
+    assert_eq!(lines[0], "query,join,pairs");
+    // every pickup is within one zone, and the single building is the
+    // nearest of every trip
+    assert_eq!(
+        lines[1],
+        "Q10,\"(zone, trip) pairs of pickups within zones\",6000"
+    );
+    assert_eq!(
+        lines[2],
+        "Q12,\"(trip, building) pairs of the nearest buildings\",6000"
+    );
+    assert_eq!(lines.len(), 3);
+    assert!(answers.join("q10.sql").exists());
+    assert!(!answers.join("q10.csv").exists());
+}
+
+/// Test writing the table of pathological geometries
+#[test]
+fn test_spatialbench_cli_edge_cases() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    for format in ["parquet", "csv"] {
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("edge-cases")
+            .arg("--format")
+            .arg(format)
+            .arg("--output-dir")
+            .arg(temp_dir.path())
+            .assert()
+            .success();
+    }
+
+    let file = File::open(temp_dir.path().join("edge_case.parquet")).expect("parquet is written");
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file).expect("Failed to create reader");
+    let geo = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|metadata| metadata.iter().find(|kv| kv.key == "geo"))
+        .and_then(|kv| kv.value.clone())
+        .expect("geo metadata is written");
+    assert!(geo.contains("GeometryCollection"), "{geo}");
+    let rows: usize = builder
+        .build()
+        .unwrap()
+        .map(|batch| batch.unwrap().num_rows())
+        .sum();
+
+    let csv = fs::read_to_string(temp_dir.path().join("edge_case.csv")).expect("csv is written");
+    assert!(csv.starts_with("e_casekey,e_category,e_name,e_description,e_geometry\n"));
+    assert_eq!(csv.lines().count(), rows + 1);
+    assert!(
+        csv.contains(",empty_point,\"empty point, with NaN coordinates in WKB\",\"POINT EMPTY\"")
+    );
+}
+
+/// Test that the `refresh` command writes the inserted and deleted trips of each refresh set
+#[test]
+fn test_spatialbench_cli_refresh() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    for format in ["parquet", "csv"] {
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("refresh")
+            .arg("--scale-factor")
+            .arg("0.01")
+            .arg("--count")
+            .arg("2")
+            .arg("--format")
+            .arg(format)
+            .arg("--output-dir")
+            .arg(temp_dir.path())
+            .assert()
+            .success();
+    }
+
+    // the second refresh set inserts the 60 trips after the first one
+    let file =
+        File::open(temp_dir.path().join("trip.insert.2.parquet")).expect("inserts are written");
+    let keys: Vec<i64> = ParquetRecordBatchReaderBuilder::try_new(file)
+        .expect("Failed to create reader")
+        .build()
+        .unwrap()
+        .flat_map(|batch| {
+            let batch = batch.unwrap();
+            let keys = batch
+                .column_by_name("t_tripkey")
+                .unwrap()
+                .as_primitive::<Int64Type>();
+            keys.values().to_vec()
+        })
+        .collect();
+    assert_eq!(keys, (60_061..=60_120).collect::<Vec<_>>());
+
+    let deletes =
+        fs::read_to_string(temp_dir.path().join("trip.delete.2.csv")).expect("deletes are written");
+    let deletes: Vec<_> = deletes.lines().collect();
+    assert_eq!(deletes.len(), 61);
+    assert_eq!(deletes[..3], ["t_tripkey", "2", "1002"]);
+    let inserts =
+        fs::read_to_string(temp_dir.path().join("trip.insert.1.csv")).expect("inserts are written");
+    assert!(inserts.lines().nth(1).unwrap().starts_with("60001,"));
+}
+
+/// Test that the `cdc` command writes the changes of the trips in ordered commits
+#[test]
+fn test_spatialbench_cli_cdc() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    for format in ["parquet", "jsonl"] {
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("cdc")
+            .arg("--scale-factor")
+            .arg("0.001")
+            .arg("--commit-size")
+            .arg("1000")
+            .arg("--format")
+            .arg(format)
+            .arg("--output-dir")
+            .arg(temp_dir.path())
+            .assert()
+            .success();
+    }
+
+    // 6000 inserts and updates, and 60 deletes, in 8 commits
+    let file = File::open(temp_dir.path().join("trip.cdc.parquet")).expect("parquet is written");
+    let mut ops = std::collections::BTreeMap::new();
+    let mut last_commit = 0;
+    for batch in ParquetRecordBatchReaderBuilder::try_new(file)
+        .expect("Failed to create reader")
+        .build()
+        .unwrap()
+    {
+        let batch = batch.unwrap();
+        let commits = batch
+            .column_by_name("_commit")
+            .unwrap()
+            .as_primitive::<Int64Type>();
+        let op = batch.column_by_name("_op").unwrap().as_string_view();
+        for row in 0..batch.num_rows() {
+            assert!(commits.value(row) >= last_commit);
+            last_commit = commits.value(row);
+            *ops.entry(op.value(row).to_string()).or_insert(0) += 1;
+        }
+    }
+    assert_eq!(last_commit, 8);
+    assert_eq!(ops["insert"], 6000);
+    assert_eq!(ops["update"], 6000);
+    assert_eq!(ops["delete"], 60);
+
+    let jsonl =
+        fs::read_to_string(temp_dir.path().join("trip.cdc.jsonl")).expect("jsonl is written");
+    assert_eq!(jsonl.lines().count(), 12_060);
+    let first: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+    assert_eq!(first["_op"], "insert");
+    assert_eq!(first["t_tripkey"], 1);
+    assert!(first["t_dropoffloc"].is_null());
+    assert_eq!(first["t_pickuploc"]["type"], "Point");
+}
+
+/// Test the options that can not be used with PostgreSQL output are
+/// rejected before connecting to the database
+#[test]
+fn test_spatialbench_cli_postgres_errors() {
+    let url = "postgres://bench@localhost:1/spatialbench";
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--scale-factor", "0.001", "--output", url])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "The zone table can not be written to PostgreSQL",
+        ));
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--tables", "trip", "--format", "csv", "--output", url])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--format can not be used with PostgreSQL output",
+        ));
+}
+
+/// Test --delta writes Delta tables with a transaction log adding their files
+#[test]
+fn test_spatialbench_cli_delta() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--scale-factor", "0.001", "--tables", "vehicle,customer"])
+        .args(["--partition-by", "c_region", "--delta", "--output-dir"])
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let actions = |table: &str| -> Vec<serde_json::Value> {
+        let log = temp_dir
+            .path()
+            .join(table)
+            .join("_delta_log/00000000000000000000.json");
+        fs::read_to_string(log)
+            .expect("log is written")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    };
+    let vehicle = actions("vehicle");
+    assert_eq!(vehicle[1]["protocol"]["minReaderVersion"], 1);
+    assert_eq!(vehicle[3]["add"]["path"], "part-0.parquet");
+
+    let customer = actions("customer");
+    assert_eq!(
+        customer[2]["metaData"]["partitionColumns"],
+        serde_json::json!(["c_region"])
+    );
+    let adds: Vec<_> = customer
+        .iter()
+        .filter_map(|action| action.get("add"))
+        .collect();
+    assert_eq!(adds.len(), 5);
+    assert_eq!(adds[4]["path"], "c_region=MIDDLE%20EAST/part-0.parquet");
+    assert_eq!(adds[4]["partitionValues"]["c_region"], "MIDDLE EAST");
+    let records: i64 = adds
+        .iter()
+        .map(|add| {
+            let stats: serde_json::Value =
+                serde_json::from_str(add["stats"].as_str().unwrap()).unwrap();
+            stats["numRecords"].as_i64().unwrap()
+        })
+        .sum();
+    assert_eq!(records, 30);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--tables", "vehicle", "--format", "csv", "--delta"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--delta requires --format parquet",
+        ));
+}
+
+/// Test --throttle paces the rows without changing them
+#[test]
+fn test_spatialbench_cli_throttle() {
+    let generate = |throttle: Option<&str>| {
+        let mut command = Command::cargo_bin("spatialbench-cli").expect("Binary not found");
+        command.args([
+            "--scale-factor",
+            "0.001",
+            "--tables",
+            "trip",
+            "--format",
+            "csv",
+        ]);
+        command.args(["--output", "-"]);
+        if let Some(throttle) = throttle {
+            command.args(["--throttle", throttle]);
+        }
+        let start = std::time::Instant::now();
+        let output = command.assert().success().get_output().stdout.clone();
+        (output, start.elapsed())
+    };
+
+    let (expected, _) = generate(None);
+    // the last of the 6000 trips is due after 5999 / 3000 seconds
+    let (throttled, elapsed) = generate(Some("3000"));
+    assert_eq!(throttled, expected);
+    assert!(
+        elapsed >= std::time::Duration::from_millis(1900),
+        "{elapsed:?}"
+    );
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--tables", "trip", "--output", "-", "--throttle", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--throttle must be positive"));
+}
+
+/// Test --seed generates the same rows when split into parts, and other
+/// rows than the default seed
+#[test]
+fn test_spatialbench_cli_seed() {
+    let generate = |seed: &str, parts: Option<&str>| {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut command = Command::cargo_bin("spatialbench-cli").expect("Binary not found");
+        command
+            .arg("--scale-factor")
+            .arg("0.001")
+            .arg("--format")
+            .arg("tbl")
+            .arg("--tables")
+            .arg("driver,customer,trip")
+            .arg("--seed")
+            .arg(seed)
+            .arg("--output-dir")
+            .arg(temp_dir.path());
+        if let Some(parts) = parts {
+            command.arg("--parts").arg(parts);
+        }
+        command.assert().success();
+        ["driver", "customer", "trip"].map(|table| {
+            let read = |path: PathBuf| fs::read_to_string(path).expect("table is written");
+            match parts {
+                None => read(temp_dir.path().join(format!("{table}.tbl"))),
+                // the driver table is too small to fill every part
+                Some(_) => (1..=3)
+                    .map(|part| {
+                        temp_dir
+                            .path()
+                            .join(table)
+                            .join(format!("{table}.{part}.tbl"))
+                    })
+                    .filter(|path| path.exists())
+                    .map(read)
+                    .collect(),
+            }
+        })
+    };
+    // the country code of a phone number follows the nation, so only compare
+    // the local number
+    let phones = |rows: &str| -> Vec<String> {
+        rows.lines()
+            .map(|row| {
+                let phone = row.split('|').nth(5).unwrap();
+                phone.split_once('-').unwrap().1.to_string()
+            })
+            .collect()
+    };
+
+    let [drivers, customers, trips] = generate("42", None);
+    assert_eq!(
+        [&drivers, &customers, &trips],
+        generate("42", Some("3")).each_ref()
+    );
+    let [default_drivers, default_customers, default_trips] = generate("0", None);
+    assert_ne!(trips, default_trips);
+    assert_ne!(phones(&drivers), phones(&default_drivers));
+    assert_ne!(phones(&customers), phones(&default_customers));
+}
+
+/// Test that --rows overrides the number of rows of the tables
+#[test]
+fn test_spatialbench_cli_rows() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--format")
+        .arg("tbl")
+        .arg("--tables")
+        .arg("customer,trip")
+        .arg("--rows")
+        .arg("customer=20")
+        .arg("--rows")
+        .arg("trip=1_000")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let customers = fs::read_to_string(temp_dir.path().join("customer.tbl")).unwrap();
+    assert_eq!(customers.lines().count(), 20);
+    let trips = fs::read_to_string(temp_dir.path().join("trip.tbl")).unwrap();
+    assert_eq!(trips.lines().count(), 1000);
+    for trip in trips.lines() {
+        let customer_key: i64 = trip.split('|').nth(1).unwrap().parse().unwrap();
+        assert!((1..=20).contains(&customer_key), "{trip}");
+    }
+}
+
+/// Test generating the user defined tables of --custom-tables
+#[test]
+fn test_spatialbench_cli_custom_tables() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let spec = temp_dir.path().join("spec.yaml");
+    fs::write(
+        &spec,
+        "tables:
+  - name: sensor
+    rows: { base: 10, per_sf: 1000 }
+    columns:
+      - { name: s_sensorkey, type: key }
+      - { name: s_customerkey, type: foreign_key, references: customer }
+      - { name: s_location, type: geometry, kind: point, distribution: clusters }
+",
+    )
+    .unwrap();
+    let output_dir = temp_dir.path().join("output");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("customer")
+        .arg("--rows")
+        .arg("customer=5")
+        .arg("--custom-tables")
+        .arg(&spec)
+        .arg("--output-dir")
+        .arg(&output_dir)
+        .assert()
+        .success();
+
+    let file = File::open(output_dir.join("sensor.parquet")).unwrap();
+    let batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(file)
+        .unwrap()
+        .build()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+    assert_eq!(rows, 20);
+    for batch in &batches {
+        let customers = batch.column(1).as_primitive::<Int64Type>();
+        assert!(customers.iter().all(|key| (1..=5).contains(&key.unwrap())));
+    }
+    let manifest = fs::read_to_string(output_dir.join("_spatialbench_manifest.json")).unwrap();
+    assert!(manifest.contains("sensor.parquet"), "{manifest}");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--format")
+        .arg("csv")
+        .arg("--custom-tables")
+        .arg(&spec)
+        .arg("--output-dir")
+        .arg(&output_dir)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("requires the parquet format"));
+}
+
+/// Test that --config and --profile set the options, with the command line taking precedence
+#[test]
+fn test_spatialbench_cli_config_profiles() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let config = temp_dir.path().join("bench.yml");
+    fs::write(
+        &config,
+        format!(
+            "generate:
+  scale_factor: 0.01
+  format: tbl
+  tables: [customer, vehicle]
+  rows: [customer=30]
+  output_dir: {}
+profiles:
+  tiny:
+    rows: [customer=3]
+    no_manifest: true
+",
+            temp_dir.path().join("output").display()
+        ),
+    )
+    .unwrap();
+    let customers = |output_dir: &str| {
+        let path = temp_dir.path().join(output_dir).join("customer.tbl");
+        fs::read_to_string(path).unwrap().lines().count()
+    };
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--config")
+        .arg(&config)
+        .assert()
+        .success();
+    assert_eq!(customers("output"), 30);
+    assert!(temp_dir.path().join("output/vehicle.tbl").exists());
+
+    // the command line overrides the profile, which overrides the file
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--config")
+        .arg(&config)
+        .arg("--profile")
+        .arg("tiny")
+        .arg("--tables")
+        .arg("customer")
+        .arg("--output-dir")
+        .arg(temp_dir.path().join("tiny"))
+        .assert()
+        .success();
+    assert_eq!(customers("tiny"), 3);
+    assert!(!temp_dir.path().join("tiny/vehicle.tbl").exists());
+    assert!(!temp_dir
+        .path()
+        .join("tiny/_spatialbench_manifest.json")
+        .exists());
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--config")
+        .arg(&config)
+        .arg("--profile")
+        .arg("large")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "no profile large, expected one of: tiny",
+        ));
+}
+
+/// Test that --mirror writes the same files to every output directory
+#[test]
+fn test_spatialbench_cli_mirror() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let dirs = ["output", "mirror1", "mirror2"].map(|dir| temp_dir.path().join(dir));
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("driver,trip")
+        .arg("--parts")
+        .arg("2")
+        .arg("--output-dir")
+        .arg(&dirs[0])
+        .arg("--mirror")
+        .arg(&dirs[1])
+        .arg("--mirror")
+        .arg(&dirs[2])
+        .assert()
+        .success();
+
+    for file in [
+        "driver/driver.1.parquet",
+        "trip/trip.1.parquet",
+        "trip/trip.2.parquet",
+        "_spatialbench_manifest.json",
+    ] {
+        let expected = fs::read(dirs[0].join(file)).unwrap();
+        for mirror in &dirs[1..] {
+            assert_eq!(fs::read(mirror.join(file)).unwrap(), expected, "{file}");
+        }
+    }
+    assert!(!dirs[1].join("trip/trip.1.inprogress").exists());
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("zone")
+        .arg("--output-dir")
+        .arg(&dirs[0])
+        .arg("--mirror")
+        .arg(&dirs[1])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--mirror can not be used with the zone table",
+        ));
+}
+
+/// Test that the --s3-* options are validated
+#[test]
+fn test_spatialbench_cli_s3_options() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let run = |args: &[&str]| {
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("--tables")
+            .arg("vehicle")
+            .arg("--output-dir")
+            .arg(temp_dir.path())
+            .args(args)
+            .assert()
+            .failure()
+    };
+
+    run(&["--s3-sse", "AES256"]).stderr(predicates::str::contains(
+        "The --s3-* options require an s3:// --output-dir or --mirror",
+    ));
+    run(&["--s3-sse", "AES256", "--s3-sse-kms-key-id", "key"]).stderr(predicates::str::contains(
+        "--s3-sse-kms-key-id requires --s3-sse aws:kms",
+    ));
+    run(&["--s3-acl", "everyone"]).stderr(predicates::str::contains("invalid value 'everyone'"));
+    run(&["--s3-tag", "team"]).stderr(predicates::str::contains("expected KEY=VALUE"));
+}
+
+/// Test that --distribution changes the pickup locations but not the number of trips
+#[test]
+fn test_spatialbench_cli_distribution() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let trips = |distribution: &str| {
+        let output_dir = temp_dir.path().join(distribution);
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("--scale-factor")
+            .arg("0.001")
+            .arg("--format")
+            .arg("csv")
+            .arg("--tables")
+            .arg("trip")
+            .arg("--distribution")
+            .arg(format!("trip={distribution}"))
+            .arg("--output-dir")
+            .arg(&output_dir)
+            .assert()
+            .success();
+        fs::read_to_string(output_dir.join("trip.csv")).unwrap()
+    };
+    let uniform = trips("uniform");
+    let coastline = trips("coastline");
+    assert_eq!(uniform.lines().count(), coastline.lines().count());
+    assert_ne!(uniform, coastline);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--distribution")
+        .arg("trip=uniform")
+        .arg("--distribution")
+        .arg("trip=hotspots")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--distribution is given more than once for trip",
+        ));
+}
+
+/// Test that --density-raster only picks up the trips in the weighted cells
+#[test]
+fn test_spatialbench_cli_density_raster() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    // all the weight in the north west cell of a 0.1 degree grid around New York
+    let raster = temp_dir.path().join("population.asc");
+    fs::write(
+        &raster,
+        "ncols 2\nnrows 2\nxllcorner -74.1\nyllcorner 40.6\ncellsize 0.1\n5 0\n0 0\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--format")
+        .arg("csv")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--density-raster")
+        .arg(format!("trip={}", raster.display()))
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let trips = fs::read_to_string(temp_dir.path().join("trip.csv")).unwrap();
+    let mut rows = 0;
+    for line in trips.lines().skip(1) {
+        let pickup = line.split("\"POINT(").nth(1).unwrap();
+        let (x, y) = pickup.split_once(')').unwrap().0.split_once(' ').unwrap();
+        let (x, y): (f64, f64) = (x.parse().unwrap(), y.parse().unwrap());
+        assert!(
+            (-74.1..=-74.0).contains(&x) && (40.7..=40.8).contains(&y),
+            "{line}"
+        );
+        rows += 1;
+    }
+    assert!(rows > 0);
+}
+
+/// Test that the --time-* options keep the pickup times in the window
+#[test]
+fn test_spatialbench_cli_time_window() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--format")
+        .arg("csv")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--time-start")
+        .arg("2024-03-01")
+        .arg("--time-end")
+        .arg("2024-03-08")
+        .arg("--time-pattern")
+        .arg("diurnal")
+        .arg("--time-zone")
+        .arg("+01:00")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // the window is in local time, the pickup times in UTC
+    let trips = fs::read_to_string(temp_dir.path().join("trip.csv")).unwrap();
+    for line in trips.lines().skip(1) {
+        let pickup = line.split(',').nth(4).unwrap();
+        assert!(
+            ("2024-02-29 23:00:00".."2024-03-07 23:00:00").contains(&pickup),
+            "{line}"
+        );
+    }
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--time-zone")
+        .arg("America/New_York")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "named time zones are not supported",
+        ));
+}
+
+/// Test that the trajectory table has one path per trip
+#[test]
+fn test_spatialbench_cli_trajectory() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--format")
+        .arg("csv")
+        .arg("--tables")
+        .arg("trip,trajectory")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // one trajectory per trip, from the pickup time to the dropoff time
+    let trips = fs::read_to_string(temp_dir.path().join("trip.csv")).unwrap();
+    let trajectories = fs::read_to_string(temp_dir.path().join("trajectory.csv")).unwrap();
+    assert_eq!(
+        trajectories.lines().next().unwrap(),
+        "tr_tripkey,tr_starttime,tr_endtime,tr_numpoints,tr_path"
+    );
+    assert_eq!(trajectories.lines().count(), trips.lines().count());
+    for (trajectory, trip) in trajectories.lines().zip(trips.lines()).skip(1) {
+        let trajectory: Vec<_> = trajectory.splitn(5, ',').collect();
+        let trip: Vec<_> = trip.split(',').collect();
+        assert_eq!(trajectory[..2], [trip[0], trip[4]]);
+        assert!(trajectory[4].starts_with("\"LINESTRING("), "{trajectory:?}");
+    }
+}
+
+/// Test that --snap-to-roads starts and ends the trips on the road network
+#[test]
+fn test_spatialbench_cli_snap_to_roads() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--format")
+        .arg("tbl")
+        .arg("--tables")
+        .arg("trip,road")
+        .arg("--snap-to-roads")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // a single town at this scale factor
+    let roads = fs::read_to_string(temp_dir.path().join("road.tbl")).unwrap();
+    assert_eq!(roads.lines().count(), 42);
+    assert!(roads.starts_with("1|1|primary|LINESTRING("), "{roads}");
+
+    // the trips start and end at the intersections of the streets
+    let intersections: String = roads.lines().collect();
+    let trips = fs::read_to_string(temp_dir.path().join("trip.tbl")).unwrap();
+    for trip in trips.lines() {
+        let fields: Vec<_> = trip.split('|').collect();
+        for location in [fields[10], fields[11]] {
+            let coords = location.trim_start_matches("POINT(").trim_end_matches(')');
+            assert!(intersections.contains(coords), "{trip}");
+        }
+    }
+}
+
+/// Test that the landmass table has one row per continent and that --on-land changes the trips
+#[test]
+fn test_spatialbench_cli_on_land() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--format")
+        .arg("parquet")
+        .arg("--tables")
+        .arg("trip,landmass")
+        .arg("--on-land")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // one row per continent, whatever the scale factor
+    let file = File::open(temp_dir.path().join("landmass.parquet")).unwrap();
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+    assert_eq!(reader.metadata().file_metadata().num_rows(), 8);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("verify")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip,landmass")
+        .arg("--on-land")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // the trips are not the same as in the water
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("verify")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip")
+        .arg(temp_dir.path())
+        .assert()
+        .failure();
+}
+
+/// Test that the country, region and city tables form a hierarchy
+#[test]
+fn test_spatialbench_cli_admin_hierarchy() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--format")
+        .arg("tbl")
+        .arg("--tables")
+        .arg("country,region,city")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // the same hierarchy at every scale factor
+    for (table, rows) in [("country", 32), ("region", 288), ("city", 4608)] {
+        let path = temp_dir.path().join(format!("{table}.tbl"));
+        let contents = fs::read_to_string(path).unwrap();
+        assert_eq!(contents.lines().count(), rows, "{table}");
+    }
+    let cities = fs::read_to_string(temp_dir.path().join("city.tbl")).unwrap();
+    let last = cities.lines().last().unwrap();
+    assert!(
+        last.starts_with("4608|288|City#000004608|POLYGON(("),
+        "{last}"
+    );
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("city")
+        .arg("--rows")
+        .arg("city=10")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "the number of rows of table city is fixed",
+        ));
+}
+
+/// Test that the POIs reference the cities and the categories
+#[test]
+fn test_spatialbench_cli_poi() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--format")
+        .arg("tbl")
+        .arg("--tables")
+        .arg("poi")
+        .arg("--rows")
+        .arg("poi=500")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(temp_dir.path().join("poi.tbl")).unwrap();
+    assert_eq!(contents.lines().count(), 500);
+    for line in contents.lines() {
+        let fields: Vec<&str> = line.split('|').collect();
+        let city: i64 = fields[1].parse().unwrap();
+        let category: i64 = fields[2].parse().unwrap();
+        assert!((1..=4608).contains(&city), "{line}");
+        assert!((1..=1000).contains(&category), "{line}");
+        assert!(fields[4].starts_with("POINT("), "{line}");
+    }
+}
+
+/// Test that --realistic-text makes up the addresses and the city names
+#[test]
+fn test_spatialbench_cli_realistic_text() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--format")
+        .arg("csv")
+        .arg("--tables")
+        .arg("customer,city")
+        .arg("--realistic-text")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let contents = fs::read_to_string(temp_dir.path().join("customer.csv")).unwrap();
+    for line in contents.lines().skip(1) {
+        // the quoted address ends with a town and a postcode
+        let address = line.split('"').nth(1).unwrap();
+        let (_, postcode) = address.rsplit_once(' ').unwrap();
+        assert!(postcode.parse::<u32>().is_ok(), "{line}");
+        assert!(address.contains(", "), "{line}");
+    }
+    let contents = fs::read_to_string(temp_dir.path().join("city.csv")).unwrap();
+    assert!(contents.lines().skip(1).all(|line| !line.contains("City#")));
+}
+
+/// Test that --key-skew concentrates the references on a fraction of the keys
+#[test]
+fn test_spatialbench_cli_key_skew() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--format")
+        .arg("tbl")
+        .arg("--tables")
+        .arg("poi")
+        .arg("--rows")
+        .arg("poi=10000")
+        .arg("--key-skew")
+        .arg("p_citykey=hotspot:0.2,0.8")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // 20% of the 4608 cities have 80% of the POIs
+    let contents = fs::read_to_string(temp_dir.path().join("poi.tbl")).unwrap();
+    let hot = contents
+        .lines()
+        .filter(|line| line.split('|').nth(1).unwrap().parse::<i64>().unwrap() <= 922)
+        .count();
+    assert!((7800..8200).contains(&hot), "{hot}");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--key-skew")
+        .arg("t_custkey=zipf:1")
+        .arg("--key-skew")
+        .arg("t_custkey=uniform")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--key-skew is given more than once for t_custkey",
+        ));
+}
+
+/// Test that --spatial-correlation makes the fares depend on the pickup locations
+#[test]
+fn test_spatialbench_cli_spatial_correlation() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--format")
+        .arg("tbl")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--spatial-correlation")
+        .arg("1,1")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // the fare per mile only depends on the pickup location, so trips
+    // picked up close to each other have about the same fare per mile, up
+    // to the rounding of the fares and distances, while the mean difference
+    // of independent uniform fares per mile would be 0.5
+    let contents = fs::read_to_string(temp_dir.path().join("trip.tbl")).unwrap();
+    let mut trips: Vec<(f64, f64, f64)> = contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('|').collect();
+            let fare: f64 = fields[6].parse().unwrap();
+            let distance: f64 = fields[9].parse().unwrap();
+            let pickup = fields[10]
+                .trim_start_matches("POINT(")
+                .trim_end_matches(')');
+            let (x, y) = pickup.split_once(' ').unwrap();
+            (distance >= 0.1).then(|| (x.parse().unwrap(), y.parse().unwrap(), fare / distance))
+        })
+        .collect();
+    trips.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let differences: Vec<f64> = trips
+        .windows(2)
+        .filter(|pair| (pair[1].0 - pair[0].0).abs() < 0.01 && (pair[1].1 - pair[0].1).abs() < 0.01)
+        .map(|pair| (pair[1].2 - pair[0].2).abs())
+        .collect();
+    assert!(differences.len() > 10, "{}", differences.len());
+    let mean = differences.iter().sum::<f64>() / differences.len() as f64;
+    assert!(mean < 0.2, "{mean}");
+}
+
+/// Test that --attribute-distribution changes the distribution of the POI categories
+#[test]
+fn test_spatialbench_cli_attribute_distribution() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--format")
+        .arg("tbl")
+        .arg("--tables")
+        .arg("poi")
+        .arg("--rows")
+        .arg("poi=2000")
+        .arg("--attribute-distribution")
+        .arg("category=normal:500,10")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // the categories are around 500 instead of the first ones of the Zipf
+    // distribution
+    let contents = fs::read_to_string(temp_dir.path().join("poi.tbl")).unwrap();
+    let categories: Vec<i64> = contents
+        .lines()
+        .map(|line| line.split('|').nth(2).unwrap().parse().unwrap())
+        .collect();
+    assert_eq!(categories.len(), 2000);
+    assert!(categories.iter().all(|c| (430..=570).contains(c)));
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("poi")
+        .arg("--attribute-distribution")
+        .arg("category=uniform")
+        .arg("--attribute-distribution")
+        .arg("category=zipf:2")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--attribute-distribution is given more than once for category",
+        ));
+}
+
+/// Test generating the cells of the raster table
+#[test]
+fn test_spatialbench_cli_raster() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--format")
+        .arg("csv")
+        .arg("--tables")
+        .arg("raster")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // 22 rows of 44 cells, with a header
+    let contents = fs::read_to_string(temp_dir.path().join("raster.csv")).unwrap();
+    assert_eq!(contents.lines().count(), 1 + 22 * 44);
+    assert!(contents.starts_with("rs_cellkey,rs_row,rs_column,"));
+    for line in contents.lines().skip(1) {
+        let (_, polygon) = line.split_once('"').unwrap();
+        assert!(polygon.starts_with("POLYGON(("), "{line}");
+    }
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("raster")
+        .arg("--rows")
+        .arg("raster=10")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "only depends on the scale factor",
+        ));
+}
+
+/// Test generating the points of the pointcloud table
+#[test]
+fn test_spatialbench_cli_pointcloud() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--format")
+        .arg("parquet")
+        .arg("--tables")
+        .arg("pointcloud")
+        .arg("--rows")
+        .arg("pointcloud=250000")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let file = File::open(temp_dir.path().join("pointcloud.parquet")).unwrap();
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+    let columns: Vec<String> = reader
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| field.name().to_string())
+        .collect();
+    assert_eq!(
+        columns,
+        [
+            "pc_pointkey",
+            "pc_x",
+            "pc_y",
+            "pc_z",
+            "pc_intensity",
+            "pc_classification"
+        ]
+    );
+    let rows: usize = reader
+        .build()
+        .unwrap()
+        .map(|batch| batch.unwrap().num_rows())
+        .sum();
+    assert_eq!(rows, 250_000);
+}
+
+/// Test that --dims xyz writes the buildings with the Z of their roof
+#[test]
+fn test_spatialbench_cli_dims_xyz() {
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--format")
+        .arg("geojsonl")
+        .arg("--tables")
+        .arg("building")
+        .arg("--dims")
+        .arg("xyz")
+        .arg("--output")
+        .arg("-")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    // the vertices of the boundary have the same Z, the roof
+    let output = String::from_utf8(output).unwrap();
+    let start = output.find("[[[").unwrap() + 3;
+    let end = output.find("]]]").unwrap();
+    let roofs: Vec<_> = output[start..end]
+        .split("],[")
+        .map(|position| position.split(',').nth(2).unwrap())
+        .collect();
+    assert!(roofs.len() >= 4, "{output}");
+    assert!(roofs.iter().all(|z| *z == roofs[0]), "{output}");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--format")
+        .arg("csv")
+        .arg("--dims")
+        .arg("xyz")
+        .arg("--stdout")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--dims xyz is not supported for tbl and csv files",
+        ));
+}
+
+/// Test that --dims xyzm writes the trajectories with the times as M
+#[test]
+fn test_spatialbench_cli_dims_xyzm() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trajectory")
+        .arg("--dims")
+        .arg("xyzm")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // the paths are ISO WKB LineString ZM, with increasing times as M
+    let file =
+        File::open(temp_dir.path().join("trajectory.parquet")).expect("trajectory is written");
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file).expect("Failed to create reader");
+    let batch = builder
+        .build()
+        .expect("Failed to build reader")
+        .next()
+        .expect("trajectory has rows")
+        .expect("Failed to read batch");
+    let paths = batch
+        .column_by_name("tr_path")
+        .expect("tr_path is written")
+        .as_binary::<i32>();
+    let path = paths.value(0);
+    assert_eq!(u32::from_le_bytes(path[1..5].try_into().unwrap()), 3002);
+    let times: Vec<_> = path[9..]
+        .chunks_exact(8)
+        .skip(3)
+        .step_by(4)
+        .map(|m| f64::from_le_bytes(m.try_into().unwrap()))
+        .collect();
+    assert!(times.len() > 1);
+    assert!(times.windows(2).all(|w| w[0] < w[1]), "{times:?}");
+}
+
+/// Test that --crs projects the geometries and records the CRS in the metadata
+#[test]
+fn test_spatialbench_cli_crs() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--geoparquet-version")
+        .arg("1.1")
+        .arg("--crs")
+        .arg("EPSG:3857")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // the GeoParquet metadata has the PROJJSON of the CRS, and the bbox is
+    // in meters
+    let file = File::open(temp_dir.path().join("trip.parquet")).expect("trip is written");
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file).expect("Failed to create reader");
+    let geo = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|metadata| metadata.iter().find(|kv| kv.key == "geo"))
+        .and_then(|kv| kv.value.clone())
+        .expect("geo metadata is written");
+    let geo: serde_json::Value = serde_json::from_str(&geo).unwrap();
+    let column = &geo["columns"]["t_pickuploc"];
+    assert_eq!(column["crs"]["id"]["code"], 3857, "{geo}");
+    let xmax = column["bbox"][2].as_f64().unwrap();
+    assert!(xmax > 180.0 && xmax <= 20_037_508.343, "{geo}");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("verify")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--crs")
+        .arg("EPSG:3857")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--format")
+        .arg("geojson")
+        .arg("--crs")
+        .arg("EPSG:3857")
+        .arg("--stdout")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--crs EPSG:3857 is not supported for tbl, csv and GeoJSON files",
+        ));
+}
+
+/// Test that --geography records spherical edges in the GeoParquet metadata
+#[test]
+fn test_spatialbench_cli_geography() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip,building")
+        .arg("--geoparquet-version")
+        .arg("1.1")
+        .arg("--geography")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // the GeoParquet metadata records spherical edges
+    let file = File::open(temp_dir.path().join("building.parquet")).expect("building is written");
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file).expect("Failed to create reader");
+    let geo = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|metadata| metadata.iter().find(|kv| kv.key == "geo"))
+        .and_then(|kv| kv.value.clone())
+        .expect("geo metadata is written");
+    let geo: serde_json::Value = serde_json::from_str(&geo).unwrap();
+    assert_eq!(geo["columns"]["b_boundary"]["edges"], "spherical", "{geo}");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("verify")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip,building")
+        .arg("--geography")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--geography")
+        .arg("--crs")
+        .arg("EPSG:3857")
+        .arg("--stdout")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--geography is not supported with --crs EPSG:3857",
+        ));
+}
+
+/// Test that --geometry-mix writes the given fraction of multi polygons
+#[test]
+fn test_spatialbench_cli_geometry_mix() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("1")
+        .arg("--format")
+        .arg("csv")
+        .arg("--tables")
+        .arg("building")
+        .arg("--geometry-mix")
+        .arg("building=polygon:0.7,multipolygon:0.3")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let buildings = fs::read_to_string(temp_dir.path().join("building.csv")).unwrap();
+    let multi = buildings.matches("MULTIPOLYGON").count();
+    assert!((5_900..=6_100).contains(&multi), "{multi} of 20000");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("building")
+        .arg("--geometry-mix")
+        .arg("zone=polygon:0.5,multipolygon:0.5")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "the zone boundaries are downloaded and can not be mixed",
+        ));
+}
+
+/// Test that --invalid-rate flags the given fraction of the boundaries invalid
+#[test]
+fn test_spatialbench_cli_invalid_rate() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("1")
+        .arg("--tables")
+        .arg("building")
+        .arg("--invalid-rate")
+        .arg("0.01")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // 1% of the boundaries are flagged invalid
+    let file = File::open(temp_dir.path().join("building.parquet")).expect("building is written");
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .expect("Failed to create reader")
+        .build()
+        .unwrap();
+    let mut invalid = 0;
+    for batch in reader {
+        let batch = batch.unwrap();
+        let flags = batch
+            .column_by_name("b_isvalid")
+            .expect("b_isvalid is written");
+        invalid += flags.as_boolean().false_count();
+    }
+    assert!((199..=201).contains(&invalid), "{invalid} of 20000");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("verify")
+        .arg("--scale-factor")
+        .arg("1")
+        .arg("--tables")
+        .arg("building")
+        .arg("--invalid-rate")
+        .arg("0.01")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("building")
+        .arg("--format")
+        .arg("csv")
+        .arg("--invalid-rate")
+        .arg("0.01")
+        .arg("--stdout")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--invalid-rate is not supported for tbl and csv files",
+        ));
+}
+
+/// Test that --add-cell-index adds the cell columns of the points
+#[test]
+fn test_spatialbench_cli_add_cell_index() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip,poi")
+        .arg("--add-cell-index")
+        .arg("h3:9")
+        .arg("--add-cell-index")
+        .arg("s2:13")
+        .arg("--add-cell-index")
+        .arg("geohash:7")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let file = File::open(temp_dir.path().join("trip.parquet")).expect("trip is written");
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .expect("Failed to create reader")
+        .build()
+        .unwrap();
+    for batch in reader {
+        let batch = batch.unwrap();
+        for location in ["t_pickuploc", "t_dropoffloc"] {
+            let h3 = batch
+                .column_by_name(&format!("{location}_h3_9"))
+                .expect("the H3 cells are written")
+                .as_primitive::<Int64Type>();
+            let s2 = batch
+                .column_by_name(&format!("{location}_s2_13"))
+                .expect("the S2 cells are written")
+                .as_primitive::<Int64Type>();
+            let geohash = batch
+                .column_by_name(&format!("{location}_geohash_7"))
+                .expect("the geohashes are written")
+                .as_string::<i32>();
+            for row in 0..batch.num_rows() {
+                // the resolution is in bits 52 to 55 of an H3 cell, and the
+                // level in the lowest bit set of an S2 cell
+                assert_eq!((h3.value(row) >> 52) & 0xF, 9);
+                assert_eq!(s2.value(row).trailing_zeros(), 2 * (30 - 13));
+                assert_eq!(geohash.value(row).len(), 7);
+            }
+        }
+    }
+    let file = File::open(temp_dir.path().join("poi.parquet")).expect("poi is written");
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file).expect("Failed to create reader");
+    let columns: Vec<_> = reader
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| field.name().clone())
+        .collect();
+    assert_eq!(
+        columns[5..],
+        [
+            "p_location_h3_9",
+            "p_location_s2_13",
+            "p_location_geohash_7"
+        ]
+    );
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("poi")
+        .arg("--format")
+        .arg("csv")
+        .arg("--add-cell-index")
+        .arg("h3:9")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--add-cell-index is not supported for tbl and csv files",
+        ));
+}
+
+/// Test that --stats writes the column statistics of every part
+#[test]
+fn test_spatialbench_cli_stats() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip,building")
+        .arg("--parts")
+        .arg("2")
+        .arg("--null-rate")
+        .arg("trip.t_tip=0.1")
+        .arg("--stats")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let read_stats = |table: &str| -> serde_json::Value {
+        let path = temp_dir.path().join(format!("_stats/{table}.json"));
+        serde_json::from_str(&fs::read_to_string(path).expect("stats are written")).unwrap()
+    };
+    // the statistics of both parts
+    let trip = read_stats("trip");
+    assert_eq!(trip["table"], "trip");
+    assert_eq!(trip["rows"], 6000);
+    let columns = trip["columns"].as_array().unwrap();
+    assert_eq!(columns.len(), 12);
+    let tripkey = &columns[0];
+    assert_eq!(tripkey["name"], "t_tripkey");
+    assert_eq!(tripkey["type"], "Int64");
+    assert_eq!(
+        (&tripkey["min"], &tripkey["max"]),
+        (&1.into(), &6000.into())
+    );
+    let ndv = tripkey["ndv"].as_u64().unwrap();
+    assert!((5900..6100).contains(&ndv), "{ndv}");
+    let tip = &columns[7];
+    assert_eq!(tip["nullable"], true);
+    let nulls = tip["nulls"].as_u64().unwrap();
+    assert!((450..750).contains(&nulls), "{nulls}");
+    let pickuploc = &columns[10];
+    assert_eq!(pickuploc["mean_vertices"], 1.0);
+    assert_eq!(pickuploc["bbox"].as_array().unwrap().len(), 4);
+
+    let building = read_stats("building");
+    let boundary = &building["columns"][2];
+    assert_eq!(boundary["name"], "b_boundary");
+    assert!(boundary["mean_vertices"].as_f64().unwrap() >= 4.0);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--format")
+        .arg("csv")
+        .arg("--stats")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--stats is not supported for tbl and csv files",
+        ));
+}
+
+/// Test that --spatial-index writes the bounding boxes of the row groups
+#[test]
+fn test_spatialbench_cli_spatial_index() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip,vehicle")
+        .arg("--parts")
+        .arg("2")
+        .arg("--parquet-row-group-bytes")
+        .arg("50000")
+        .arg("--spatial-index")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let index_dir = temp_dir.path().join("_index");
+    let items: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(index_dir.join("trip.json")).unwrap()).unwrap();
+    assert_eq!(items["column"], "t_pickuploc");
+    let items = items["items"].as_array().unwrap();
+    // several row groups in each part
+    assert!(items.len() > 2, "{}", items.len());
+    assert_eq!(items[0]["path"], "trip/trip.1.parquet");
+    assert_eq!(items[0]["row_group"], 0);
+    assert_eq!(items.last().unwrap()["path"], "trip/trip.2.parquet");
+    let rows: i64 = items
+        .iter()
+        .map(|item| item["rows"].as_i64().unwrap())
+        .sum();
+    assert_eq!(rows, 6000);
+
+    let index = fs::read(index_dir.join("trip.flatbush")).unwrap();
+    assert_eq!(&index[..4], [0xfb, 0x38, 16, 0]);
+    assert_eq!(
+        u32::from_le_bytes(index[4..8].try_into().unwrap()) as usize,
+        items.len()
+    );
+    // the vehicle table has no geometries
+    assert!(!index_dir.join("vehicle.flatbush").exists());
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--format")
+        .arg("csv")
+        .arg("--spatial-index")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--spatial-index requires --format parquet",
+        ));
+}
+
+/// Test that --spatial-sort groups nearby pickups into the same row groups
+#[test]
+fn test_spatialbench_cli_spatial_sort() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--parquet-row-group-bytes")
+        .arg("500000")
+        .arg("--spatial-sort")
+        .arg("hilbert")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // the bounding box of the pickup locations of each row group
+    let path = temp_dir.path().join("trip.parquet");
+    let num_row_groups = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+        .expect("Failed to create reader")
+        .metadata()
+        .num_row_groups();
+    assert!(num_row_groups > 1);
+    let bounds: Vec<[f64; 4]> = (0..num_row_groups)
+        .map(|row_group| {
+            let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+                .expect("Failed to create reader")
+                .with_row_groups(vec![row_group])
+                .build()
+                .unwrap();
+            let mut bounds = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+            for batch in reader {
+                let batch = batch.unwrap();
+                let pickups = batch.column_by_name("t_pickuploc").unwrap();
+                for wkb in pickups.as_binary::<i32>().iter().flatten() {
+                    let x = f64::from_le_bytes(wkb[5..13].try_into().unwrap());
+                    let y = f64::from_le_bytes(wkb[13..21].try_into().unwrap());
+                    bounds = [
+                        bounds[0].min(x),
+                        bounds[1].min(y),
+                        bounds[2].max(x),
+                        bounds[3].max(y),
+                    ];
+                }
+            }
+            bounds
+        })
+        .collect();
+    // the file is sorted across its row groups, so together they cover about
+    // the extent of the trips rather than each the whole extent
+    let area = |b: &[f64; 4]| (b[2] - b[0]) * (b[3] - b[1]);
+    let extent = bounds.iter().fold(bounds[0], |e, b| {
+        [
+            e[0].min(b[0]),
+            e[1].min(b[1]),
+            e[2].max(b[2]),
+            e[3].max(b[3]),
+        ]
+    });
+    let covered: f64 = bounds.iter().map(area).sum();
+    assert!(
+        covered < 2.0 * area(&extent),
+        "{num_row_groups} row groups cover {covered} of {}",
+        area(&extent)
+    );
+}
+
+/// Test that --sort-by sorts the rows across the row groups
+#[test]
+fn test_spatialbench_cli_sort_by() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--parquet-row-group-bytes")
+        .arg("500000")
+        .arg("--sort-by")
+        .arg("trip:t_pickuptime")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let file = File::open(temp_dir.path().join("trip.parquet")).expect("trip is written");
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file).expect("Failed to create reader");
+    // the file is sorted across its row groups
+    assert!(reader.metadata().num_row_groups() > 1);
+    let times: Vec<i64> = reader
+        .build()
+        .unwrap()
+        .flat_map(|batch| {
+            let batch = batch.unwrap();
+            let column = batch.column_by_name("t_pickuptime").unwrap();
+            column
+                .as_primitive::<arrow_array::types::TimestampMillisecondType>()
+                .values()
+                .to_vec()
+        })
+        .collect();
+    assert_eq!(times.len(), 60_000);
+    assert!(times.is_sorted());
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--sort-by")
+        .arg("trip:t_pickuptime")
+        .arg("--spatial-sort")
+        .arg("hilbert")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--sort-by can not be used with --spatial-sort",
+        ));
+}
+
+/// Test that --shuffle writes the rows in an order that only depends on the seed
+#[test]
+fn test_spatialbench_cli_shuffle() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let trip_keys = |dir: &str| -> Vec<i64> {
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("--scale-factor")
+            .arg("0.01")
+            .arg("--tables")
+            .arg("trip")
+            .arg("--seed")
+            .arg("3")
+            .arg("--parquet-row-group-bytes")
+            .arg("500000")
+            .arg("--shuffle")
+            .arg("--output-dir")
+            .arg(temp_dir.path().join(dir))
+            .assert()
+            .success();
+        let file =
+            File::open(temp_dir.path().join(dir).join("trip.parquet")).expect("trip is written");
+        ParquetRecordBatchReaderBuilder::try_new(file)
+            .expect("Failed to create reader")
+            .build()
+            .unwrap()
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let column = batch.column_by_name("t_tripkey").unwrap();
+                column
+                    .as_primitive::<arrow_array::types::Int64Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect()
+    };
+
+    let keys = trip_keys("first");
+    assert!(!keys.is_sorted());
+    // the same seed writes the same order
+    assert_eq!(trip_keys("second"), keys);
+    let mut sorted = keys;
+    sorted.sort();
+    assert_eq!(sorted, (1..=60_000).collect::<Vec<i64>>());
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--shuffle")
+        .arg("--format")
+        .arg("csv")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--shuffle can not be used with tbl and csv files",
+        ));
+}
+
+/// Test that --wkb-flavor and --wkb-byte-order change the encoding of the geometries
+#[test]
+fn test_spatialbench_cli_wkb_variant() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--crs")
+        .arg("EPSG:3857")
+        .arg("--wkb-flavor")
+        .arg("ewkb")
+        .arg("--wkb-byte-order")
+        .arg("big")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let file = File::open(temp_dir.path().join("trip.parquet")).expect("trip is written");
+    let batch = ParquetRecordBatchReaderBuilder::try_new(file)
+        .expect("Failed to create reader")
+        .build()
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let pickups = batch
+        .column_by_name("t_pickuploc")
+        .unwrap()
+        .as_binary::<i32>();
+    for wkb in pickups.iter().flatten() {
+        // big endian point with the SRID flag, SRID 3857 and two coordinates
+        assert_eq!(&wkb[..9], &[0, 0x20, 0, 0, 1, 0, 0, 0x0f, 0x11]);
+        assert_eq!(wkb.len(), 25);
+    }
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--wkb-flavor")
+        .arg("ewkb")
+        .arg("--geoparquet-version")
+        .arg("1.1")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--wkb-flavor and --wkb-byte-order can not be used with --geoparquet-version",
+        ));
+}
+
+/// Test that --coordinate-precision and --grid-size round the coordinates
+#[test]
+fn test_spatialbench_cli_coordinate_precision() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let pickups = |option: &str, value: &str, dir: &str| -> Vec<[f64; 2]> {
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .arg("--scale-factor")
+            .arg("0.001")
+            .arg("--tables")
+            .arg("trip")
+            .arg(option)
+            .arg(value)
+            .arg("--output-dir")
+            .arg(temp_dir.path().join(dir))
+            .assert()
+            .success();
+        let file = File::open(temp_dir.path().join(dir).join("trip.parquet")).unwrap();
+        ParquetRecordBatchReaderBuilder::try_new(file)
+            .expect("Failed to create reader")
+            .build()
+            .unwrap()
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let column = batch.column_by_name("t_pickuploc").unwrap();
+                column
+                    .as_binary::<i32>()
+                    .iter()
+                    .flatten()
+                    .map(|wkb| {
+                        let x = f64::from_le_bytes(wkb[5..13].try_into().unwrap());
+                        let y = f64::from_le_bytes(wkb[13..21].try_into().unwrap());
+                        [x, y]
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    };
+
+    for [x, y] in pickups("--coordinate-precision", "2", "digits") {
+        assert_eq!(x, (x * 100.0).round() / 100.0);
+        assert_eq!(y, (y * 100.0).round() / 100.0);
+    }
+    let snapped = pickups("--grid-size", "0.5", "grid");
+    assert_eq!(snapped.len(), 6000);
+    for [x, y] in snapped {
+        assert_eq!((x * 2.0).fract(), 0.0);
+        assert_eq!((y * 2.0).fract(), 0.0);
+    }
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--coordinate-precision")
+        .arg("10")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure();
+}
+
+/// Test generating a part of the zones with --zone-source tessellation
+#[test]
+fn test_spatialbench_cli_zone_tessellation() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let zones = |seed: &str, dir: &str| -> Vec<RecordBatch> {
+        Command::cargo_bin("spatialbench-cli")
+            .expect("Binary not found")
+            .args(["--tables", "zone", "--zone-source", "tessellation"])
+            .args(["--parts", "2", "--part", "2", "--seed", seed])
+            .arg("--output-dir")
+            .arg(temp_dir.path().join(dir))
+            .assert()
+            .success();
+        let file = File::open(temp_dir.path().join(dir).join("zone/zone.2.parquet")).unwrap();
+        ParquetRecordBatchReaderBuilder::try_new(file)
+            .expect("Failed to create reader")
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    };
+
+    // the second half of the zones of scale factor 1
+    let batches = zones("0", "seed0");
+    assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 78047);
+    let keys = batches[0].column_by_name("z_zonekey").unwrap();
+    assert_eq!(keys.as_primitive::<Int64Type>().value(0), 78049);
+    let boundaries = |batches: &[RecordBatch]| -> Vec<Vec<u8>> {
+        batches
+            .iter()
+            .flat_map(|batch| {
+                let column = batch.column_by_name("z_boundary").unwrap();
+                column
+                    .as_binary::<i32>()
+                    .iter()
+                    .flatten()
+                    .map(|wkb| wkb.to_vec())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    };
+    let wkb = boundaries(&batches);
+    assert!(wkb.iter().all(|wkb| wkb[1..5] == 3u32.to_le_bytes()));
+    assert_eq!(wkb, boundaries(&zones("0", "again")));
+    assert_ne!(wkb, boundaries(&zones("1", "seed1")));
+}
+
+/// Test that --add-rowgen adds the lineage of the rows, whatever the part
+#[test]
+fn test_spatialbench_cli_add_rowgen() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let read_trips = |path: PathBuf| -> Vec<RecordBatch> {
+        let file = File::open(path).expect("trip is written");
+        ParquetRecordBatchReaderBuilder::try_new(file)
+            .expect("Failed to create reader")
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap())
+            .collect()
+    };
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--seed")
+        .arg("5")
+        .arg("--parts")
+        .arg("3")
+        .arg("--add-rowgen")
+        .arg("--output-dir")
+        .arg(temp_dir.path().join("parts"))
+        .assert()
+        .success();
+
+    // the lineage only depends on the key, whatever the part
+    let mut rows = vec![];
+    for part in 1..=3 {
+        for batch in read_trips(
+            temp_dir
+                .path()
+                .join(format!("parts/trip/trip.{part}.parquet")),
+        ) {
+            assert_eq!(batch.schema().fields().last().unwrap().name(), "_rowgen");
+            let keys = batch.column(0).as_primitive::<Int64Type>();
+            let rowgen = batch.column_by_name("_rowgen").unwrap().as_string::<i32>();
+            for i in 0..batch.num_rows() {
+                let key = keys.value(i) - 1;
+                let expected = format!("{}:{}:5", key / 100_000, key % 100_000);
+                assert_eq!(rowgen.value(i), expected);
+                rows.push(batch.slice(i, 1));
+            }
+        }
+    }
+
+    // regenerate one row on its own from its lineage, as the part of the
+    // table of its key with as many parts as rows
+    let lineage = rows[43_210]
+        .column_by_name("_rowgen")
+        .unwrap()
+        .as_string::<i32>()
+        .value(0)
+        .to_string();
+    let fields: Vec<i64> = lineage.split(':').map(|f| f.parse().unwrap()).collect();
+    let key = fields[0] * 100_000 + fields[1] + 1;
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--seed")
+        .arg(fields[2].to_string())
+        .arg("--parts")
+        .arg(rows.len().to_string())
+        .arg("--part")
+        .arg(key.to_string())
+        .arg("--add-rowgen")
+        .arg("--output-dir")
+        .arg(temp_dir.path().join("row"))
+        .assert()
+        .success();
+    let regenerated = read_trips(temp_dir.path().join(format!("row/trip/trip.{key}.parquet")));
+    assert_eq!(regenerated, vec![rows[43_210].clone()]);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--format")
+        .arg("tbl")
+        .arg("--add-rowgen")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--add-rowgen is not supported for tbl and csv files",
+        ));
+}
+
+/// Test that --duplicate-rate duplicates the given fraction of the buildings
+#[test]
+fn test_spatialbench_cli_duplicate_rate() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("1")
+        .arg("--tables")
+        .arg("building")
+        .arg("--duplicate-rate")
+        .arg("0.02")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    // 2% of the buildings point to the building before them
+    let file = File::open(temp_dir.path().join("building.parquet")).expect("building is written");
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .expect("Failed to create reader")
+        .build()
+        .unwrap();
+    let (mut exact, mut near) = (0, 0);
+    for batch in reader {
+        let batch = batch.unwrap();
+        let keys = batch
+            .column_by_name("b_buildingkey")
+            .unwrap()
+            .as_primitive::<Int64Type>();
+        let sources = batch
+            .column_by_name("b_duplicateof")
+            .expect("b_duplicateof is written")
+            .as_primitive::<Int64Type>();
+        let kinds = batch
+            .column_by_name("b_duplicatekind")
+            .unwrap()
+            .as_string::<i32>();
+        for row in 0..batch.num_rows() {
+            if sources.is_null(row) {
+                assert!(kinds.is_null(row));
+                continue;
+            }
+            assert_eq!(sources.value(row), keys.value(row) - 1);
+            match kinds.value(row) {
+                "exact" => exact += 1,
+                "near" => near += 1,
+                kind => panic!("unexpected kind {kind}"),
+            }
+        }
+    }
+    assert!(
+        (399..=401).contains(&(exact + near)),
+        "{exact} + {near} of 20000"
+    );
+    assert!(exact > 150 && near > 150, "{exact} exact and {near} near");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("verify")
+        .arg("--scale-factor")
+        .arg("1")
+        .arg("--tables")
+        .arg("building")
+        .arg("--duplicate-rate")
+        .arg("0.02")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("building")
+        .arg("--duplicate-rate")
+        .arg("0.5")
+        .arg("--stdout")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--duplicate-rate must be in [0, 0.25], got 0.5",
+        ));
+}
+
+/// Test that --null-rate and --empty-rate make the given fraction of the values null or empty
+#[test]
+fn test_spatialbench_cli_null_rate() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let rates = [
+        "--null-rate",
+        "trip.t_tip=0.1",
+        "--null-rate",
+        "trip.t_dropoffloc=0.02",
+        "--empty-rate",
+        "trip.t_dropoffloc=0.03",
+    ];
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("trip")
+        .args(rates)
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let file = File::open(temp_dir.path().join("trip.parquet")).expect("trip is written");
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .expect("Failed to create reader")
+        .build()
+        .unwrap();
+    let (mut tips, mut dropoffs, mut empty) = (0, 0, 0);
+    for batch in reader {
+        let batch = batch.unwrap();
+        tips += batch.column_by_name("t_tip").unwrap().null_count();
+        let column = batch.column_by_name("t_dropoffloc").unwrap();
+        dropoffs += column.null_count();
+        // empty points have NaN coordinates
+        empty += column
+            .as_binary::<i32>()
+            .iter()
+            .flatten()
+            .filter(|wkb| f64::from_le_bytes(wkb[5..13].try_into().unwrap()).is_nan())
+            .count();
+        assert_eq!(batch.column_by_name("t_fare").unwrap().null_count(), 0);
+    }
+    assert!((5500..=6500).contains(&tips), "{tips} of 60000");
+    assert!((1000..=1400).contains(&dropoffs), "{dropoffs} of 60000");
+    assert!((1600..=2000).contains(&empty), "{empty} of 60000");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("verify")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("trip")
+        .args(rates)
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--null-rate")
+        .arg("trip.t_tripkey=0.1")
+        .arg("--stdout")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "t_tripkey is the key of table trip and can not be null",
+        ));
+}
+
+/// Test that the cells of the null and empty points are null
+#[test]
+fn test_spatialbench_cli_null_rate_cell_index() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--add-cell-index")
+        .arg("geohash:7")
+        .arg("--null-rate")
+        .arg("trip.t_pickuploc=0.1")
+        .arg("--empty-rate")
+        .arg("trip.t_pickuploc=0.1")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let file = File::open(temp_dir.path().join("trip.parquet")).expect("trip is written");
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .expect("Failed to create reader")
+        .build()
+        .unwrap();
+    let mut missing = 0;
+    for batch in reader {
+        let batch = batch.unwrap();
+        let pickups = batch
+            .column_by_name("t_pickuploc")
+            .unwrap()
+            .as_binary::<i32>();
+        let cells = batch.column_by_name("t_pickuploc_geohash_7").unwrap();
+        for (row, pickup) in pickups.iter().enumerate() {
+            // the cells of null and empty points are null
+            let is_missing = pickup
+                .is_none_or(|wkb| f64::from_le_bytes(wkb[5..13].try_into().unwrap()).is_nan());
+            assert_eq!(cells.is_null(row), is_missing, "row {row}");
+            missing += is_missing as usize;
+        }
+        let dropoffs = batch.column_by_name("t_dropoffloc_geohash_7").unwrap();
+        assert_eq!(dropoffs.null_count(), 0);
+    }
+    assert!((1000..=1400).contains(&missing), "{missing} of 6000");
+}
+
+/// Test that --columns only writes the given columns, in the given order
+#[test]
+fn test_spatialbench_cli_columns() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.001")
+        .arg("--tables")
+        .arg("trip")
+        .arg("--columns")
+        .arg("trip:t_pickuploc,t_tripkey")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let file = File::open(temp_dir.path().join("trip.parquet")).expect("trip is written");
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file).expect("Failed to create reader");
+    let names: Vec<_> = builder
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().clone())
+        .collect();
+    assert_eq!(names, ["t_pickuploc", "t_tripkey"]);
+    let reader = builder.build().expect("Failed to build reader");
+    let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+    assert_eq!(rows, 6000);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--format")
+        .arg("csv")
+        .arg("--columns")
+        .arg("trip:t_tripkey")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--columns"));
+}
+
+/// Test verifying a generated dataset
+#[test]
+fn test_spatialbench_cli_verify() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("trip,vehicle")
+        .arg("--parts")
+        .arg("2")
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("verify")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout("vehicle: OK (1 rows)\ntrip: OK (60000 rows)\n");
+
+    // a truncated file is detected
+    let file = temp_dir.path().join("trip").join("trip.2.parquet");
+    let data = fs::read(&file).expect("trip.2.parquet is written");
+    fs::write(&file, &data[..data.len() / 2]).expect("Failed to truncate file");
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("verify")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("trip")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stdout(predicates::str::starts_with("trip: FAILED\n"));
+}
+
+/// Test the ddl command prints the table definitions with spatial indexes
+#[test]
+fn test_spatialbench_cli_ddl() {
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["ddl", "--dialect", "duckdb", "--tables", "trip,zone"])
+        .assert()
+        .success()
+        .stdout(predicates::str::starts_with("INSTALL spatial;"))
+        .stdout(predicates::str::contains(
+            "CREATE TABLE trip (\n    t_tripkey BIGINT NOT NULL,",
+        ))
+        .stdout(predicates::str::contains(
+            "CREATE INDEX zone_z_boundary_idx ON zone USING RTREE (z_boundary);",
+        ));
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["ddl", "--dialect", "bigquery", "--crs", "EPSG:3857"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--crs EPSG:3857 is not supported",
+        ));
+}
+
+/// Test the schema command describes the columns and keys of the tables
+#[test]
+fn test_spatialbench_cli_schema() {
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["schema", "--table", "trip", "--format", "json"])
+        .output()
+        .expect("Failed to run schema");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let trip = &json["tables"][0];
+    assert_eq!(trip["name"], "trip");
+    assert_eq!(trip["primary_key"], "t_tripkey");
+    let pickup = trip["columns"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|column| column["name"] == "t_pickuploc")
+        .unwrap();
+    assert_eq!(pickup["geometry"]["encoding"], "WKB");
+    assert_eq!(pickup["geometry"]["crs"], "EPSG:4326");
+
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["schema", "--table", "building", "--format", "arrow"])
+        .output()
+        .expect("Failed to run schema");
+    assert!(output.status.success());
+    let reader = arrow::ipc::reader::StreamReader::try_new(output.stdout.as_slice(), None).unwrap();
+    let schema = reader.schema();
+    assert_eq!(
+        schema.metadata()["spatialbench:primary_key"],
+        "b_buildingkey"
+    );
+    assert!(schema.field_with_name("b_boundary").is_ok());
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["schema", "--format", "arrow"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("describes a single table"));
+}
+
+/// Test generating a dataset writes a manifest of its tables and files
+#[test]
+fn test_spatialbench_cli_manifest() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--scale-factor", "0.001", "--tables", "vehicle,trip"])
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    let manifest = fs::read_to_string(temp_dir.path().join("_spatialbench_manifest.json"))
+        .expect("Failed to read manifest");
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest["scale_factor"], 0.001);
+    assert_eq!(manifest["format"], "parquet");
+    assert_eq!(manifest["crs"], "EPSG:4326");
+
+    let trip = &manifest["tables"]["trip"];
+    assert_eq!(trip["rows"], 6000);
+    let file = &trip["files"][0];
+    assert_eq!(file["path"], "trip.parquet");
+    let bytes = fs::metadata(temp_dir.path().join("trip.parquet"))
+        .unwrap()
+        .len();
+    assert_eq!(file["bytes"], bytes);
+    assert_eq!(file["sha256"].as_str().unwrap().len(), 64);
+    assert_eq!(trip["bbox"]["t_pickuploc"].as_array().unwrap().len(), 4);
+    // vehicles have no geometries
+    assert!(manifest["tables"]["vehicle"].get("bbox").is_none());
+
+    // no manifest with --no-manifest
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args([
+            "--scale-factor",
+            "0.001",
+            "--tables",
+            "vehicle",
+            "--no-manifest",
+        ])
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+    assert!(!temp_dir.path().join("_spatialbench_manifest.json").exists());
+}
+
+/// Test verifying the checksums of the manifest of a dataset
+#[test]
+fn test_spatialbench_cli_verify_checksums() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--scale-factor", "0.001", "--tables", "vehicle,trip"])
+        .args(["--format", "csv", "--parts", "2"])
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .assert()
+        .success();
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["verify", "--checksums"])
+        .arg(temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("trip: OK (2 files)"))
+        .stdout(predicates::str::contains("vehicle: OK"));
+
+    // a changed file fails the verification of its table only
+    let path = temp_dir.path().join("trip").join("trip.2.csv");
+    let mut data = fs::read(&path).expect("Failed to read trip file");
+    let last = data.len() - 2;
+    data[last] = if data[last] == b'0' { b'1' } else { b'0' };
+    fs::write(&path, data).expect("Failed to write trip file");
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["verify", "--checksums"])
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("trip: FAILED"))
+        .stdout(predicates::str::contains("trip/trip.2.csv: SHA-256"))
+        .stdout(predicates::str::contains("vehicle: OK"));
+}
+
+/// Test the JSON progress lines
+#[test]
+fn test_spatialbench_cli_progress_json() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--scale-factor", "0.001", "--tables", "vehicle,trip"])
+        .args(["--format", "csv", "--progress", "json"])
+        .arg("--output-dir")
+        .arg(temp_dir.path())
+        .output()
+        .expect("Failed to run spatialbench-cli");
+    assert!(output.status.success());
+
+    // the last line of each table reports it complete
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("JSON progress line"))
+        .collect();
+    let last = |table: &str| {
+        lines
+            .iter()
+            .rfind(|line| line["table"] == table)
+            .unwrap_or_else(|| panic!("no progress of {table}"))
+            .clone()
+    };
+    let trip = last("trip");
+    assert_eq!(trip["done"], true);
+    assert_eq!(trip["rows"], 6000);
+    assert_eq!(trip["total_rows"], 6000);
+    assert_eq!(trip["percent"], 100.0);
+    let bytes = fs::metadata(temp_dir.path().join("trip.csv"))
+        .unwrap()
+        .len();
+    assert_eq!(trip["bytes"], bytes);
+    assert_eq!(last("vehicle")["done"], true);
+}
+
+/// Test estimating the size of a run
+#[test]
+fn test_spatialbench_cli_estimate() {
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["estimate", "--scale-factor", "0.01", "--parts", "2"])
+        .args(["--tables", "trip,building", "--format", "json"])
+        .args(["--sample-rows", "1000"])
+        .output()
+        .expect("Failed to run estimate");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let trip = &json["tables"][0];
+    assert_eq!(trip["name"], "trip");
+    assert_eq!(trip["rows"], 60_000);
+    assert_eq!(trip["files"], 2);
+    // small files are uploaded with a single request each
+    assert_eq!(trip["put_requests"], 2);
+    let bytes = &trip["parquet_bytes"];
+    assert!(bytes["zstd"].as_u64().unwrap() < bytes["uncompressed"].as_u64().unwrap());
+    assert_eq!(trip["bytes"], bytes["snappy"]);
+    // the building table is not split
+    assert_eq!(json["tables"][1]["files"], 1);
+    assert_eq!(
+        json["total"]["rows"],
+        60_000 + json["tables"][1]["rows"].as_i64().unwrap()
+    );
+}
+
+/// Test previewing the first rows of a table
+#[test]
+fn test_spatialbench_cli_preview() {
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["preview", "--table", "building", "--rows", "3"])
+        .args(["--scale-factor", "1", "--max-width", "30"])
+        .output()
+        .expect("Failed to run preview");
+    assert!(output.status.success());
+    let output = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(
+        lines[0].split_whitespace().collect::<Vec<_>>(),
+        ["b_buildingkey", "b_name", "b_boundary"]
+    );
+    let offset = lines[0].find("b_boundary").unwrap();
+    for (key, line) in (1..).zip(&lines[2..]) {
+        assert!(line.starts_with(&format!("{key:>13}  ")));
+        let boundary = &line[offset..];
+        assert!(boundary.starts_with("POLYGON(("));
+        assert!(boundary.chars().count() <= 30);
+    }
+}
+
+/// Test running the queries with a command, checking their rows
+#[test]
+fn test_spatialbench_cli_run() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let answers = temp_dir.path().join("answers");
+    fs::create_dir(&answers).unwrap();
+    fs::write(answers.join("q1.csv"), "a,b\n1,2\n3,4\n").unwrap();
+    fs::write(answers.join("q3.csv"), "a\n1\n").unwrap();
+    let results = temp_dir.path().join("results.json");
+
+    // a client printing two rows for every query
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["run", "--command", "cat > /dev/null; echo 1; echo 2"])
+        .args(["--queries", "Q1,Q3,Q4", "--iterations", "2"])
+        .arg("--answers")
+        .arg(&answers)
+        .arg("--output")
+        .arg(&results)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Q3 failed or returned unexpected rows",
+        ));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&results).unwrap()).unwrap();
+    assert_eq!(json["dialect"], "duckdb");
+    let queries = json["queries"].as_array().unwrap();
+    assert_eq!(queries[0]["query"], "Q1");
+    assert_eq!(queries[0]["latencies_ms"].as_array().unwrap().len(), 2);
+    assert_eq!(queries[0]["rows"], 2);
+    assert_eq!(queries[0]["expected_rows"], 2);
+    assert_eq!(queries[1]["expected_rows"], 1);
+    // the queries of the zone table have no answers
+    assert!(queries[2].get("expected_rows").is_none());
+}
+
+/// Test scoring the results of a run
+#[test]
+fn test_spatialbench_cli_report() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let results = temp_dir.path().join("results.json");
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["run", "--command", "cat > /dev/null; echo 1"])
+        .args(["--queries", "Q1,Q3", "--warmup", "0"])
+        .arg("--output")
+        .arg(&results)
+        .assert()
+        .success();
+
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["report", "--format", "json"])
+        .arg(&results)
+        .output()
+        .expect("Failed to run report");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let run = &json[0];
+    assert_eq!(run["valid_queries"], 2);
+    assert!(run["geomean_ms"].as_f64().unwrap() > 0.0);
+    assert!(run["power"].as_f64().unwrap() > 0.0);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["report", "--format", "markdown"])
+        .arg(&results)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("| Valid queries | 2 / 2 |"));
+}
+
+/// Test running the queries in concurrent streams
+#[test]
+fn test_spatialbench_cli_run_streams() {
+    let output = Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["run", "--command", "cat > /dev/null; echo 1"])
+        .args(["--queries", "Q1,Q3", "--streams", "3", "--seed", "7"])
+        .args(["--warmup", "0", "--iterations", "2"])
+        .output()
+        .expect("Failed to run run");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let streams = json["streams"].as_array().unwrap();
+    assert_eq!(streams.len(), 3);
+    assert_eq!(streams[0]["seed"], 7);
+    assert_eq!(streams[2]["seed"], 9);
+    // the streams run the queries in different orders
+    assert_eq!(streams[0]["queries"][0]["query"], "Q1");
+    assert_eq!(streams[1]["queries"][0]["query"], "Q3");
+    // all the latencies of a query, over the streams
+    assert_eq!(
+        json["queries"][0]["latencies_ms"].as_array().unwrap().len(),
+        6
+    );
+    let throughput = &json["throughput"];
+    assert_eq!(throughput["queries"], 12);
+    assert!(throughput["queries_per_second"].as_f64().unwrap() > 0.0);
+    assert!(throughput["p99_ms"].as_f64().unwrap() >= throughput["p50_ms"].as_f64().unwrap());
+}
+
+/// Test that --output null generates the tables without writing anything
+#[test]
+fn test_spatialbench_cli_null_output() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .current_dir(temp_dir.path())
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("vehicle,driver,trip")
+        .arg("--parts")
+        .arg("2")
+        .arg("--output")
+        .arg("null")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("trip: 60000 rows in"))
+        .stderr(predicates::str::contains("rows/s per thread"));
+
+    // nothing is written
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+}
+
+/// Test that --memory-limit writes the same data as without a limit
+#[test]
+fn test_spatialbench_cli_memory_limit() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+
+    for (dir, limit) in [("unlimited", None), ("limited", Some("1MB"))] {
+        for format in ["tbl", "parquet"] {
+            let mut command = Command::cargo_bin("spatialbench-cli").expect("Binary not found");
+            command
+                .arg("--scale-factor")
+                .arg("0.01")
+                .arg("--tables")
+                .arg("trip,building")
+                .arg("--num-threads")
+                .arg("4")
+                .arg("--format")
+                .arg(format)
+                .arg("--parquet-row-group-bytes")
+                .arg("100000")
+                .arg("--output-dir")
+                .arg(temp_dir.path().join(dir));
+            if let Some(limit) = limit {
+                command.arg("--memory-limit").arg(limit);
+            }
+            command.assert().success();
+        }
+    }
+    for file in [
+        "trip.tbl",
+        "building.tbl",
+        "trip.parquet",
+        "building.parquet",
+    ] {
+        assert_eq!(
+            fs::read(temp_dir.path().join("unlimited").join(file)).unwrap(),
+            fs::read(temp_dir.path().join("limited").join(file)).unwrap(),
+            "{file} differs"
+        );
+    }
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--memory-limit")
+        .arg("4 apples")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("invalid size unit"));
+}
+
+/// Test that --staging-dir writes through the staging directory to an
+/// object store URL and leaves it empty
+#[test]
+fn test_spatialbench_cli_staging_dir() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let staging_dir = temp_dir.path().join("staging");
+    let output_dir = temp_dir.path().join("out");
+    fs::create_dir(&output_dir).unwrap();
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--scale-factor")
+        .arg("0.01")
+        .arg("--tables")
+        .arg("vehicle")
+        .arg("--format")
+        .arg("csv")
+        .arg("--output-dir")
+        .arg(format!("file://{}", output_dir.display()))
+        .arg("--staging-dir")
+        .arg(&staging_dir)
+        .assert()
+        .success();
+    assert!(fs::metadata(output_dir.join("vehicle.csv")).unwrap().len() > 0);
+    assert_eq!(fs::read_dir(&staging_dir).unwrap().count(), 0);
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .arg("--tables")
+        .arg("vehicle")
+        .arg("--output-dir")
+        .arg(&output_dir)
+        .arg("--staging-dir")
+        .arg(&staging_dir)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--staging-dir requires an object store --output-dir or --mirror",
+        ));
+}
+
+/// Test that the zone table is left out of the default tables written to an
+/// object store, and can not be requested
+#[test]
+fn test_spatialbench_cli_object_store_default_tables() {
+    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let url = format!("file://{}", temp_dir.path().display());
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--scale-factor", "0.001", "--format", "csv"])
+        .arg("--output-dir")
+        .arg(&url)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Warning: not generating the zone table",
+        ));
+    assert!(temp_dir.path().join("trip.csv").exists());
+    assert!(!temp_dir.path().join("zone.parquet").exists());
+
+    Command::cargo_bin("spatialbench-cli")
+        .expect("Binary not found")
+        .args(["--tables", "zone", "--output-dir"])
+        .arg(&url)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "The zone table can not be written to an object store",
+        ));
+}
@@ -0,0 +1,259 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Python bindings for the Spatial Bench data generator
+//!
+//! The generators are exposed as `pyarrow.RecordBatchReader`s, so the data is
+//! generated lazily as batches are read, and passed to Python without copies
+//! using the Arrow C stream interface.
+//!
+//! ```python
+//! import spatialbench
+//!
+//! for batch in spatialbench.trip(0.01):
+//!     print(batch.num_rows)
+//!
+//! spatialbench.generate("trip", 1.0, "trip.parquet")
+//! ```
+
+use arrow::array::RecordBatch;
+use arrow::error::ArrowError;
+use arrow::pyarrow::PyArrowType;
+use arrow::record_batch::RecordBatchReader;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use spatialbench_arrow::{RecordBatchIterator, Table, TableGenerator, DEFAULT_BATCH_SIZE};
+use std::fs::File;
+
+/// The batches of a generator, as a `pyarrow.RecordBatchReader`
+type PyRecordBatchReader = PyArrowType<Box<dyn RecordBatchReader + Send>>;
+
+/// Create a [`TableGenerator`], validating the arguments from Python
+fn table_generator(
+    table: &str,
+    scale_factor: f64,
+    part: i32,
+    parts: i32,
+    seed: u64,
+    batch_size: usize,
+) -> PyResult<TableGenerator> {
+    let table: Table = table.parse().map_err(PyValueError::new_err)?;
+    if !(scale_factor.is_finite() && scale_factor > 0.0) {
+        return Err(PyValueError::new_err(format!(
+            "scale_factor must be positive, got {scale_factor}"
+        )));
+    }
+    if parts < 1 || part < 1 || part > parts {
+        return Err(PyValueError::new_err(format!(
+            "part must be between 1 and parts ({parts}), got {part}"
+        )));
+    }
+    if batch_size == 0 {
+        return Err(PyValueError::new_err("batch_size must be positive"));
+    }
+    Ok(TableGenerator::new(table, scale_factor)
+        .with_part(part, parts)
+        .with_seed(seed)
+        .with_batch_size(batch_size))
+}
+
+/// Adapts the batches of a [`TableGenerator`] to a [`RecordBatchReader`]
+struct GeneratorReader(Box<dyn RecordBatchIterator>);
+
+impl Iterator for GeneratorReader {
+    type Item = Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Ok)
+    }
+}
+
+impl RecordBatchReader for GeneratorReader {
+    fn schema(&self) -> arrow::datatypes::SchemaRef {
+        std::sync::Arc::clone(self.0.schema())
+    }
+}
+
+/// Return the names of the tables that can be generated
+#[pyfunction]
+fn tables() -> Vec<&'static str> {
+    Table::ALL.iter().map(|table| table.name()).collect()
+}
+
+/// Return a `pyarrow.RecordBatchReader` that generates part `part` of
+/// `parts` of `table` at `scale_factor`
+///
+/// Geometry columns are WKB encoded binary columns. All random values are
+/// derived from `seed`; the default `0` generates the reference data.
+#[pyfunction]
+#[pyo3(signature = (table, scale_factor, part = 1, parts = 1, seed = 0, batch_size = DEFAULT_BATCH_SIZE))]
+fn generator(
+    table: &str,
+    scale_factor: f64,
+    part: i32,
+    parts: i32,
+    seed: u64,
+    batch_size: usize,
+) -> PyResult<PyRecordBatchReader> {
+    let generator = table_generator(table, scale_factor, part, parts, seed, batch_size)?;
+    let reader: Box<dyn RecordBatchReader + Send> = Box::new(GeneratorReader(generator.batches()));
+    Ok(PyArrowType(reader))
+}
+
+/// Define a function that returns the generator of one table, such as
+/// `spatialbench.trip(scale_factor)`
+macro_rules! define_table_function {
+    ($FUN_NAME:ident, $TABLE:literal, $DOC:literal) => {
+        #[doc = $DOC]
+        #[pyfunction]
+        #[pyo3(signature = (scale_factor, part = 1, parts = 1, seed = 0, batch_size = DEFAULT_BATCH_SIZE))]
+        fn $FUN_NAME(
+            scale_factor: f64,
+            part: i32,
+            parts: i32,
+            seed: u64,
+            batch_size: usize,
+        ) -> PyResult<PyRecordBatchReader> {
+            generator($TABLE, scale_factor, part, parts, seed, batch_size)
+        }
+    };
+}
+
+define_table_function!(
+    vehicle,
+    "vehicle",
+    "Return a generator of the vehicle table"
+);
+define_table_function!(driver, "driver", "Return a generator of the driver table");
+define_table_function!(
+    customer,
+    "customer",
+    "Return a generator of the customer table"
+);
+define_table_function!(trip, "trip", "Return a generator of the trip table");
+define_table_function!(
+    building,
+    "building",
+    "Return a generator of the building table"
+);
+define_table_function!(
+    trajectory,
+    "trajectory",
+    "Return a generator of the trajectory table, the paths of the trips"
+);
+define_table_function!(
+    road,
+    "road",
+    "Return a generator of the road table, the streets of the road network"
+);
+define_table_function!(
+    landmass,
+    "landmass",
+    "Return a generator of the landmass table, the continents and islands of the land mask"
+);
+define_table_function!(
+    country,
+    "country",
+    "Return a generator of the country table, the top level of the administrative hierarchy"
+);
+define_table_function!(
+    region,
+    "region",
+    "Return a generator of the region table, the countries subdivided into regions"
+);
+define_table_function!(
+    city,
+    "city",
+    "Return a generator of the city table, the regions subdivided into cities"
+);
+define_table_function!(
+    poi,
+    "poi",
+    "Return a generator of the POI table, points of interest in the cities"
+);
+define_table_function!(
+    raster,
+    "raster",
+    "Return a generator of the raster table, the cells of a grid of elevation and temperature"
+);
+define_table_function!(
+    pointcloud,
+    "pointcloud",
+    "Return a generator of the point cloud table, the returns of a LiDAR style survey"
+);
+
+/// Write part `part` of `parts` of `table` at `scale_factor` to the Parquet
+/// file at `path`, returning the number of rows written
+///
+/// The GIL is released while generating, so other Python threads can run.
+#[pyfunction]
+#[pyo3(signature = (table, scale_factor, path, part = 1, parts = 1, seed = 0))]
+fn generate(
+    py: Python<'_>,
+    table: &str,
+    scale_factor: f64,
+    path: std::path::PathBuf,
+    part: i32,
+    parts: i32,
+    seed: u64,
+) -> PyResult<usize> {
+    let generator = table_generator(table, scale_factor, part, parts, seed, DEFAULT_BATCH_SIZE)?;
+    py.allow_threads(move || {
+        let file = File::create(&path)
+            .map_err(|e| PyIOError::new_err(format!("Failed to create {path:?}: {e}")))?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let parquet_error = |e: parquet::errors::ParquetError| PyIOError::new_err(e.to_string());
+        let mut writer =
+            ArrowWriter::try_new(file, generator.schema(), Some(props)).map_err(parquet_error)?;
+        let mut num_rows = 0;
+        for batch in generator.batches() {
+            num_rows += batch.num_rows();
+            writer.write(&batch).map_err(parquet_error)?;
+        }
+        writer.close().map_err(parquet_error)?;
+        Ok(num_rows)
+    })
+}
+
+/// The `spatialbench` Python module
+#[pymodule]
+fn spatialbench(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(tables, m)?)?;
+    m.add_function(wrap_pyfunction!(generator, m)?)?;
+    m.add_function(wrap_pyfunction!(vehicle, m)?)?;
+    m.add_function(wrap_pyfunction!(driver, m)?)?;
+    m.add_function(wrap_pyfunction!(customer, m)?)?;
+    m.add_function(wrap_pyfunction!(trip, m)?)?;
+    m.add_function(wrap_pyfunction!(building, m)?)?;
+    m.add_function(wrap_pyfunction!(trajectory, m)?)?;
+    m.add_function(wrap_pyfunction!(road, m)?)?;
+    m.add_function(wrap_pyfunction!(landmass, m)?)?;
+    m.add_function(wrap_pyfunction!(country, m)?)?;
+    m.add_function(wrap_pyfunction!(region, m)?)?;
+    m.add_function(wrap_pyfunction!(city, m)?)?;
+    m.add_function(wrap_pyfunction!(poi, m)?)?;
+    m.add_function(wrap_pyfunction!(raster, m)?)?;
+    m.add_function(wrap_pyfunction!(pointcloud, m)?)?;
+    m.add_function(wrap_pyfunction!(generate, m)?)?;
+    Ok(())
+}
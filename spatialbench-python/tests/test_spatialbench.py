@@ -0,0 +1,65 @@
+# Licensed to the Apache Software Foundation (ASF) under one
+# or more contributor license agreements.  See the NOTICE file
+# distributed with this work for additional information
+# regarding copyright ownership.  The ASF licenses this file
+# to you under the Apache License, Version 2.0 (the
+# "License"); you may not use this file except in compliance
+# with the License.  You may obtain a copy of the License at
+#
+#   http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing,
+# software distributed under the License is distributed on an
+# "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+# KIND, either express or implied.  See the License for the
+# specific language governing permissions and limitations
+# under the License.
+
+import pyarrow as pa
+import pyarrow.parquet as pq
+import pytest
+
+import spatialbench
+
+
+def test_generators():
+    assert spatialbench.tables() == [
+        "vehicle",
+        "driver",
+        "customer",
+        "trip",
+        "building",
+        "trajectory",
+        "road",
+        "landmass",
+        "country",
+        "region",
+        "city",
+        "poi",
+        "raster",
+        "pointcloud",
+    ]
+
+    trips = spatialbench.trip(0.001, batch_size=1000).read_all()
+    assert trips.num_rows == 6000
+    assert trips.schema.field("t_pickuploc").type == pa.binary()
+
+    # the parts of a table have the same rows as the whole table
+    parts = [spatialbench.generator("trip", 0.001, part=part, parts=3) for part in (1, 2, 3)]
+    assert pa.concat_tables([part.read_all() for part in parts]).equals(trips)
+
+    # seeds generate other, reproducible data
+    seeded = spatialbench.trip(0.001, seed=42).read_all()
+    assert seeded.equals(spatialbench.trip(0.001, seed=42).read_all())
+    assert not seeded.equals(trips)
+
+    with pytest.raises(ValueError):
+        spatialbench.generator("lineitem", 1.0)
+    with pytest.raises(ValueError):
+        spatialbench.trip(1.0, part=4, parts=3)
+
+
+def test_generate(tmp_path):
+    path = tmp_path / "vehicle.parquet"
+    assert spatialbench.generate("vehicle", 1.0, path) == 100
+    assert pq.read_table(path).equals(spatialbench.vehicle(1.0).read_all())
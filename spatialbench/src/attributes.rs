@@ -0,0 +1,547 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Configurable distributions of the attribute columns
+//!
+//! The fare per mile and the tip percentage of the trips are uniform and the
+//! categories of the POIs follow a Zipf distribution by default. A
+//! [`ValueDistribution`] draws the values of an [`Attribute`] from another
+//! distribution instead, to vary the selectivity of filters and the skew of
+//! group-bys in experiments. The values stay in the range of the attribute.
+//!
+//! With a [`SpatialCorrelation`], the fares and tips of nearby trips are
+//! similar, for benchmarks of spatial regression and hotspot functions with
+//! a known ground truth.
+
+use crate::spatial::utils::{build_cdf_from_weights, hash_to_unit_u64, pick_from_cdf};
+use once_cell::sync::OnceCell;
+use std::f64::consts::PI;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// The attribute columns with a configurable distribution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    /// The fare per mile of the trips (`t_fare`), in cents
+    Fare,
+    /// The tip of the trips (`t_tip`), in percent of the fare
+    Tip,
+    /// The category of the POIs (`p_category`)
+    Category,
+}
+
+impl Attribute {
+    /// All the attributes
+    pub const ALL: [Attribute; 3] = [Attribute::Fare, Attribute::Tip, Attribute::Category];
+
+    /// Return the name of the attribute, such as `fare`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Attribute::Fare => "fare",
+            Attribute::Tip => "tip",
+            Attribute::Category => "category",
+        }
+    }
+
+    /// Return the values of the attribute
+    pub fn range(&self) -> RangeInclusive<i64> {
+        match self {
+            Attribute::Fare => 150..=300,
+            Attribute::Tip => 0..=30,
+            Attribute::Category => 1..=1000,
+        }
+    }
+
+    /// Return whether the attribute can be correlated with the location of
+    /// its row, see [`SpatialCorrelation`]
+    pub fn is_spatial(&self) -> bool {
+        matches!(self, Attribute::Fare | Attribute::Tip)
+    }
+
+    /// Return the distribution of the attribute when none is configured
+    pub fn default_distribution(&self) -> ValueDistribution {
+        match self {
+            Attribute::Fare | Attribute::Tip => ValueDistribution::Uniform,
+            Attribute::Category => ValueDistribution::Zipf { s: 1.0 },
+        }
+    }
+
+    /// Return the salt of the random values of the attribute
+    fn salt(&self) -> u64 {
+        match self {
+            Attribute::Fare => 0xFA7E_D157,
+            Attribute::Tip => 0x7100_D157,
+            Attribute::Category => 0xCA7E_D157,
+        }
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Attribute {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Attribute::ALL
+            .into_iter()
+            .find(|attribute| attribute.name() == s.trim().to_ascii_lowercase())
+            .ok_or_else(|| format!("Invalid attribute {s}, expected fare, tip or category"))
+    }
+}
+
+/// A distribution of the values of an [`Attribute`]
+///
+/// The values are integers in the range of the attribute: the normal and
+/// log-normal values are rounded and clamped to the range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueDistribution {
+    /// Every value of the range is equally likely
+    Uniform,
+    /// The `k`-th value of the range has a frequency proportional to
+    /// `1 / k^s`, so the first values are the most common
+    Zipf { s: f64 },
+    /// Normal distribution of mean `mu` and standard deviation `sigma`
+    Normal { mu: f64, sigma: f64 },
+    /// The exponential of a normal distribution of mean `mu` and standard
+    /// deviation `sigma`, a long tail of large values
+    LogNormal { mu: f64, sigma: f64 },
+}
+
+impl fmt::Display for ValueDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueDistribution::Uniform => write!(f, "uniform"),
+            ValueDistribution::Zipf { s } => write!(f, "zipf:{s}"),
+            ValueDistribution::Normal { mu, sigma } => write!(f, "normal:{mu},{sigma}"),
+            ValueDistribution::LogNormal { mu, sigma } => write!(f, "lognormal:{mu},{sigma}"),
+        }
+    }
+}
+
+impl FromStr for ValueDistribution {
+    type Err = String;
+
+    /// Parse a distribution such as `uniform`, `zipf:1.2`, `normal:225,30`
+    /// or `lognormal:5.4,0.2`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid distribution {s}, expected uniform, zipf:<s>, normal:<mu>,<sigma> or lognormal:<mu>,<sigma>"
+            )
+        };
+        let (name, params) = s.trim().split_once(':').unwrap_or((s.trim(), ""));
+        let params: Vec<f64> = if params.is_empty() {
+            vec![]
+        } else {
+            params
+                .split(',')
+                .map(|param| param.trim().parse().map_err(|_| invalid()))
+                .collect::<Result<_, _>>()?
+        };
+        let distribution = match (name.to_ascii_lowercase().as_str(), &params[..]) {
+            ("uniform", []) => ValueDistribution::Uniform,
+            ("zipf", &[s]) => ValueDistribution::Zipf { s },
+            ("normal", &[mu, sigma]) => ValueDistribution::Normal { mu, sigma },
+            ("lognormal", &[mu, sigma]) => ValueDistribution::LogNormal { mu, sigma },
+            _ => return Err(invalid()),
+        };
+        match distribution {
+            ValueDistribution::Zipf { s: exponent }
+                if !(exponent >= 0.0 && exponent.is_finite()) =>
+            {
+                Err(format!(
+                    "Invalid distribution {s}, the Zipf exponent must be at least 0"
+                ))
+            }
+            ValueDistribution::Normal { mu, sigma }
+            | ValueDistribution::LogNormal { mu, sigma }
+                if !(sigma > 0.0 && sigma.is_finite() && mu.is_finite()) =>
+            {
+                Err(format!(
+                    "Invalid distribution {s}, the standard deviation must be positive"
+                ))
+            }
+            distribution => Ok(distribution),
+        }
+    }
+}
+
+/// Spatial autocorrelation of the attributes: nearby rows have similar
+/// values
+///
+/// The value of a row is drawn from a standard normal variable `Z`, mapped to
+/// the distribution of the attribute, with
+/// `Z = strength * F(x, y) + sqrt(1 - strength^2) * E`, where `F` is a
+/// smooth standard normal [`field`](Self::field) of the location of the row
+/// and `E` a standard normal noise of the row. The distribution of the
+/// values is unchanged, and `strength` is the correlation of `Z` with the
+/// field, the ground truth of spatial regressions. The field varies over
+/// about `range` degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialCorrelation {
+    /// From 0 (no correlation) to 1 (the values only depend on the location)
+    pub strength: f64,
+    /// Distance between the independent values of the field, in degrees
+    pub range: f64,
+}
+
+impl SpatialCorrelation {
+    /// Default range, about 10 kilometers, the size of a city
+    pub const DEFAULT_RANGE: f64 = 0.1;
+
+    /// Return the standard normal field of `attribute` at `x`, `y`
+    ///
+    /// The field is the interpolation of independent standard normal values
+    /// on a lattice of [`Self::range`] degrees, scaled so that it is
+    /// standard normal at every location.
+    pub fn field(&self, attribute: Attribute, x: f64, y: f64, seed: u64) -> f64 {
+        let (x, y) = (x / self.range, y / self.range);
+        let (x0, y0) = (x.floor(), y.floor());
+        let salt = attribute.salt().rotate_left(16) ^ seed;
+        let value = |i: f64, j: f64| {
+            let key = ((i as i64 as u64) << 32) ^ (j as i64 as u64 & 0xFFFF_FFFF);
+            let u = hash_to_unit_u64(key, salt);
+            let v = hash_to_unit_u64(key, salt.rotate_left(32));
+            (-2.0 * (1.0 - u).ln()).sqrt() * (2.0 * PI * v).cos()
+        };
+        // smoothstep weights of the 4 lattice values around the location
+        let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+        let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+        let weights = [
+            ((1.0 - tx) * (1.0 - ty), value(x0, y0)),
+            (tx * (1.0 - ty), value(x0 + 1.0, y0)),
+            ((1.0 - tx) * ty, value(x0, y0 + 1.0)),
+            (tx * ty, value(x0 + 1.0, y0 + 1.0)),
+        ];
+        let sum: f64 = weights.iter().map(|(w, value)| w * value).sum();
+        let norm: f64 = weights.iter().map(|(w, _)| w * w).sum::<f64>().sqrt();
+        sum / norm
+    }
+}
+
+impl fmt::Display for SpatialCorrelation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.strength, self.range)
+    }
+}
+
+impl FromStr for SpatialCorrelation {
+    type Err = String;
+
+    /// Parse a strength, and optionally a range in degrees, such as `0.8` or
+    /// `0.8,0.05`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid spatial correlation {s}, expected e.g. 0.8 or 0.8,0.05");
+        let (strength, range) = match s.split_once(',') {
+            Some((strength, range)) => (strength, Some(range)),
+            None => (s, None),
+        };
+        let strength: f64 = strength.trim().parse().map_err(|_| invalid())?;
+        let range: f64 = match range {
+            Some(range) => range.trim().parse().map_err(|_| invalid())?,
+            None => Self::DEFAULT_RANGE,
+        };
+        if !(0.0..=1.0).contains(&strength) {
+            return Err(format!(
+                "Invalid spatial correlation {s}, the strength must be in 0..=1"
+            ));
+        }
+        if !(range > 0.0 && range.is_finite()) {
+            return Err(format!(
+                "Invalid spatial correlation {s}, the range must be positive"
+            ));
+        }
+        Ok(SpatialCorrelation { strength, range })
+    }
+}
+
+/// Draws the values of an [`Attribute`] from a [`ValueDistribution`]
+#[derive(Debug, Clone)]
+pub struct AttributeSampler {
+    attribute: Attribute,
+    distribution: ValueDistribution,
+    /// The cumulative frequencies of the values, for the Zipf distribution
+    cdf: Vec<f64>,
+    correlation: Option<SpatialCorrelation>,
+}
+
+impl AttributeSampler {
+    pub fn new(attribute: Attribute, distribution: ValueDistribution) -> Self {
+        let cdf = match distribution {
+            ValueDistribution::Zipf { s } => {
+                let count = attribute.range().count();
+                build_cdf_from_weights((1..=count).map(|k| 1.0 / (k as f64).powf(s)).collect())
+            }
+            _ => vec![],
+        };
+        Self {
+            attribute,
+            distribution,
+            cdf,
+            correlation: None,
+        }
+    }
+
+    /// Correlates the values with the location of the rows, see
+    /// [`Self::sample_at`]
+    pub fn with_spatial_correlation(mut self, correlation: Option<SpatialCorrelation>) -> Self {
+        self.correlation = correlation;
+        self
+    }
+
+    /// Return the value of the row with `key`
+    ///
+    /// The value only depends on the key and the seed, so the parts of a
+    /// table can be generated independently.
+    pub fn sample(&self, key: u64, seed: u64) -> i64 {
+        let salt = self.attribute.salt() ^ seed;
+        let u = hash_to_unit_u64(key, salt);
+        // Box-Muller, with a second uniform value
+        let normal = || {
+            let v = hash_to_unit_u64(key, salt.rotate_left(32));
+            (-2.0 * (1.0 - u).ln()).sqrt() * (2.0 * PI * v).cos()
+        };
+        self.value(u, normal)
+    }
+
+    /// Return the value of the row with `key` at `x`, `y`, correlated with
+    /// the values of the rows nearby if the sampler has a
+    /// [`SpatialCorrelation`], otherwise the same as [`Self::sample`]
+    pub fn sample_at(&self, key: u64, seed: u64, x: f64, y: f64) -> i64 {
+        let Some(correlation) = self.correlation else {
+            return self.sample(key, seed);
+        };
+        let salt = self.attribute.salt() ^ seed;
+        let u = hash_to_unit_u64(key, salt);
+        let v = hash_to_unit_u64(key, salt.rotate_left(32));
+        let noise = (-2.0 * (1.0 - u).ln()).sqrt() * (2.0 * PI * v).cos();
+        let strength = correlation.strength;
+        let z = strength * correlation.field(self.attribute, x, y, seed)
+            + (1.0 - strength * strength).sqrt() * noise;
+        self.value(normal_cdf(z), || z)
+    }
+
+    /// Return the value of the quantile `u` of the distribution, or of the
+    /// standard normal value `normal` for the normal distributions
+    fn value(&self, u: f64, normal: impl FnOnce() -> f64) -> i64 {
+        let range = self.attribute.range();
+        let (min, max) = (*range.start(), *range.end());
+        let value = match self.distribution {
+            ValueDistribution::Uniform => min + (u * (max - min + 1) as f64) as i64,
+            ValueDistribution::Zipf { .. } => min + pick_from_cdf(&self.cdf, u) as i64,
+            ValueDistribution::Normal { mu, sigma } => (mu + sigma * normal()).round() as i64,
+            ValueDistribution::LogNormal { mu, sigma } => {
+                (mu + sigma * normal()).exp().round() as i64
+            }
+        };
+        value.clamp(min, max)
+    }
+}
+
+/// Return the cumulative distribution function of the standard normal
+/// distribution at `z`
+///
+/// With the approximation 7.1.26 of the error function of Abramowitz and
+/// Stegun, to 1.5e-7.
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+/// The distributions of the attributes that are not the default one
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AttributeDistributions {
+    pub fare: Option<ValueDistribution>,
+    pub tip: Option<ValueDistribution>,
+    pub category: Option<ValueDistribution>,
+    /// Correlates the values of the [`Attribute::is_spatial`] attributes
+    /// with the location of the rows
+    pub spatial_correlation: Option<SpatialCorrelation>,
+}
+
+impl AttributeDistributions {
+    /// Return the distribution of `attribute`, if it is not the default one
+    pub fn get(&self, attribute: Attribute) -> Option<ValueDistribution> {
+        match attribute {
+            Attribute::Fare => self.fare,
+            Attribute::Tip => self.tip,
+            Attribute::Category => self.category,
+        }
+    }
+
+    /// Set the distribution of `attribute`
+    pub fn set(&mut self, attribute: Attribute, distribution: ValueDistribution) {
+        let slot = match attribute {
+            Attribute::Fare => &mut self.fare,
+            Attribute::Tip => &mut self.tip,
+            Attribute::Category => &mut self.category,
+        };
+        *slot = Some(distribution);
+    }
+
+    /// Return the sampler of `attribute`, if its distribution is not the
+    /// default one or it is spatially correlated
+    pub fn sampler(&self, attribute: Attribute) -> Option<AttributeSampler> {
+        let correlation = self.spatial_correlation.filter(|_| attribute.is_spatial());
+        let distribution = match (self.get(attribute), correlation) {
+            (Some(distribution), _) => distribution,
+            (None, Some(_)) => attribute.default_distribution(),
+            (None, None) => return None,
+        };
+        Some(AttributeSampler::new(attribute, distribution).with_spatial_correlation(correlation))
+    }
+}
+
+static ATTRIBUTE_DISTRIBUTIONS: OnceCell<AttributeDistributions> = OnceCell::new();
+
+/// Set the distributions of the attributes of the generators created
+/// afterwards
+///
+/// Only the first call has an effect.
+pub fn set_attribute_distributions(distributions: AttributeDistributions) {
+    let _ = ATTRIBUTE_DISTRIBUTIONS.set(distributions);
+}
+
+/// Return the distributions set with [`set_attribute_distributions`], or
+/// the defaults
+pub fn attribute_distributions() -> AttributeDistributions {
+    ATTRIBUTE_DISTRIBUTIONS.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_value_distribution() {
+        for s in ["uniform", "zipf:1.2", "normal:225,30", "lognormal:5.4,0.2"] {
+            let distribution: ValueDistribution = s.parse().unwrap();
+            assert_eq!(distribution.to_string(), s);
+        }
+        for (s, error) in [
+            ("zipf", "expected uniform"),
+            ("normal:1", "expected uniform"),
+            ("pareto:1", "expected uniform"),
+            ("normal:1,0", "must be positive"),
+            ("zipf:-1", "at least 0"),
+        ] {
+            let err = s.parse::<ValueDistribution>().unwrap_err();
+            assert!(err.contains(error), "{s}: {err}");
+        }
+        assert_eq!("Tip".parse::<Attribute>(), Ok(Attribute::Tip));
+        assert!("tags".parse::<Attribute>().is_err());
+    }
+
+    #[test]
+    fn test_attribute_sampler() {
+        let histogram = |distribution: &str| {
+            let sampler = AttributeSampler::new(Attribute::Tip, distribution.parse().unwrap());
+            let mut counts = [0; 31];
+            for key in 0..100_000 {
+                counts[sampler.sample(key, 0) as usize] += 1;
+            }
+            counts
+        };
+        // every value of the range is drawn
+        let uniform = histogram("uniform");
+        assert!(uniform.iter().all(|&count| (2800..3700).contains(&count)));
+        // the first values are the most common
+        let zipf = histogram("zipf:1.5");
+        assert!(zipf[..10].windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(zipf[0] > 35_000);
+        // around the mean, clamped to the range
+        let normal = histogram("normal:15,3");
+        assert!(normal[15] > normal[10] && normal[15] > normal[20]);
+        assert_eq!(normal[0] + normal[30], 0);
+        let clamped = histogram("normal:40,5");
+        assert!(clamped[30] > 95_000);
+        let lognormal = histogram("lognormal:1.5,0.5");
+        assert!(lognormal[3] > lognormal[10] && lognormal[10] > lognormal[20]);
+
+        // the same for a key and a seed
+        let sampler = AttributeSampler::new(Attribute::Fare, ValueDistribution::Uniform);
+        assert_eq!(sampler.sample(7, 1), sampler.sample(7, 1));
+        assert!((0..100).any(|key| sampler.sample(key, 0) != sampler.sample(key, 1)));
+    }
+
+    #[test]
+    fn test_spatial_correlation() {
+        let correlation: SpatialCorrelation = "0.9".parse().unwrap();
+        assert_eq!(correlation.range, SpatialCorrelation::DEFAULT_RANGE);
+        assert_eq!("0.5,1".parse::<SpatialCorrelation>().unwrap().range, 1.0);
+        for (s, error) in [
+            ("high", "expected e.g. 0.8"),
+            ("1.5", "must be in 0..=1"),
+            ("0.5,0", "must be positive"),
+        ] {
+            let err = s.parse::<SpatialCorrelation>().unwrap_err();
+            assert!(err.contains(error), "{s}: {err}");
+        }
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((normal_cdf(-1.0) - 0.158655).abs() < 1e-5);
+
+        // the field is standard normal and smooth
+        let points: Vec<(f64, f64)> = (0..20_000)
+            .map(|i| (i as f64 * 0.0371 % 50.0, i as f64 * 0.0173 % 30.0))
+            .collect();
+        let field: Vec<f64> = points
+            .iter()
+            .map(|&(x, y)| correlation.field(Attribute::Fare, x, y, 0))
+            .collect();
+        let mean = field.iter().sum::<f64>() / field.len() as f64;
+        let variance = field.iter().map(|z| (z - mean).powi(2)).sum::<f64>() / field.len() as f64;
+        assert!(
+            mean.abs() < 0.1 && (variance - 1.0).abs() < 0.15,
+            "{mean} {variance}"
+        );
+        let step = correlation.field(Attribute::Fare, 10.001, 20.0, 0)
+            - correlation.field(Attribute::Fare, 10.0, 20.0, 0);
+        assert!(step.abs() < 0.1);
+
+        // the fares of nearby trips are close, with a standard deviation
+        // much smaller than the one of all the fares (43.6), and still
+        // uniform
+        let sampler = AttributeSampler::new(Attribute::Fare, ValueDistribution::Uniform)
+            .with_spatial_correlation(Some(correlation));
+        let near: Vec<f64> = (0..1000)
+            .map(|key| sampler.sample_at(key, 0, 10.0, 20.0) as f64)
+            .collect();
+        let mean = near.iter().sum::<f64>() / near.len() as f64;
+        let deviation = (near.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / 1000.0).sqrt();
+        assert!(deviation < 25.0, "{deviation}");
+        let mut counts = [0; 151];
+        for (key, &(x, y)) in points.iter().enumerate() {
+            counts[(sampler.sample_at(key as u64, 0, x, y) - 150) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&count| (60..220).contains(&count)));
+    }
+}
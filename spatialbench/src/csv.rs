@@ -17,10 +17,16 @@
 
 //! CSV formatting support for the row struct objects generated by the library.
 
-use crate::generators::{Building, Customer, Driver, Trip, Vehicle};
+use crate::generators::{
+    Building, City, Country, Customer, Driver, Landmass, Poi, PointCloudPoint, Raster, Region,
+    Road, Trajectory, Trip, Vehicle,
+};
 use core::fmt;
 use std::fmt::Display;
 
+/// The default field delimiter
+pub const DEFAULT_DELIMITER: char = ',';
+
 /// Write [`Vehicle`]s in CSV format.
 ///
 /// # Example
@@ -46,11 +52,33 @@ use std::fmt::Display;
 /// ```
 pub struct VehicleCsv<'a> {
     inner: Vehicle<'a>,
+    delimiter: char,
 }
 
 impl<'a> VehicleCsv<'a> {
     pub fn new(inner: Vehicle<'a>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    ///
+    /// # Example
+    /// ```
+    /// # use spatialbench::generators::VehicleGenerator;
+    /// # use spatialbench::csv::VehicleCsv;
+    /// // Output the first row in TSV format
+    /// let vehicle = VehicleGenerator::new(1.0, 1, 1).iter().next().unwrap();
+    /// assert_eq!(
+    ///   VehicleCsv::new(vehicle).with_delimiter('\t').to_string(),
+    ///   "1\tManufacturer#1\tBrand#13\tPROMO BURNISHED COPPER\t\"ly. slyly ironi\""
+    /// );
+    /// ```
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
     }
 
     /// Returns the CSV header for the Vehicle table
@@ -61,10 +89,11 @@ impl<'a> VehicleCsv<'a> {
 
 impl Display for VehicleCsv<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
         write!(
             f,
             // note must quote the comment field as it may contain commas
-            "{},{},{},{},\"{}\"",
+            "{}{d}{}{d}{}{d}{}{d}\"{}\"",
             self.inner.v_vehiclekey,
             self.inner.v_mfgr,
             self.inner.v_brand,
@@ -99,11 +128,21 @@ impl Display for VehicleCsv<'_> {
 /// ```
 pub struct DriverCsv {
     inner: Driver,
+    delimiter: char,
 }
 
 impl DriverCsv {
     pub fn new(inner: Driver) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
     }
 
     /// Returns the CSV header for the Driver table
@@ -114,10 +153,11 @@ impl DriverCsv {
 
 impl Display for DriverCsv {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
         write!(
             f,
             // note must quote the address and comment fields as they may contain commas
-            "{},{},\"{}\",{},{},{}",
+            "{}{d}{}{d}\"{}\"{d}{}{d}{}{d}{}",
             self.inner.d_driverkey,
             self.inner.d_name,
             self.inner.d_address,
@@ -153,11 +193,21 @@ impl Display for DriverCsv {
 /// ```
 pub struct CustomerCsv<'a> {
     inner: Customer<'a>,
+    delimiter: char,
 }
 
 impl<'a> CustomerCsv<'a> {
     pub fn new(inner: Customer<'a>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
     }
 
     /// Returns the CSV header for the Customer table
@@ -168,10 +218,11 @@ impl<'a> CustomerCsv<'a> {
 
 impl Display for CustomerCsv<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
         write!(
             f,
             // note must quote the address and comment fields as they may contain commas
-            "{},{},\"{}\",{},{},{}",
+            "{}{d}{}{d}\"{}\"{d}{}{d}{}{d}{}",
             self.inner.c_custkey,
             self.inner.c_name,
             self.inner.c_address,
@@ -200,25 +251,37 @@ impl Display for CustomerCsv<'_> {
 /// ```
 pub struct TripCsv {
     inner: Trip,
+    delimiter: char,
 }
 
 impl TripCsv {
     pub fn new(inner: Trip) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
     }
 
     /// Returns the CSV header for the Trip table
     pub fn header() -> &'static str {
-        "t_tripkey,t_custkey,t_driverkey,t_vehiclekey,t_pickuptime,t_dropofftime,t_fare,t_tip,t_totalamount,t_distance,t_pickupx,t_pickupy"
+        "t_tripkey,t_custkey,t_driverkey,t_vehiclekey,t_pickuptime,t_dropofftime,t_fare,t_tip,t_totalamount,t_distance,t_pickuploc,t_dropoffloc"
     }
 }
 
 impl Display for TripCsv {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
         write!(
             f,
-            // note must quote location and comment fields as they may contain commas
-            "{},{},{},{},{},{},{},{},{},{},\"{:?}\",\"{:?}\"",
+            // note must quote location and comment fields as they may contain commas.
+            // Locations are written as WKT (the `Debug` format of geo types)
+            "{}{d}{}{d}{}{d}{}{d}{}{d}{}{d}{}{d}{}{d}{}{d}{}{d}\"{:?}\"{d}\"{:?}\"",
             self.inner.t_tripkey,
             self.inner.t_custkey,
             self.inner.t_driverkey,
@@ -235,6 +298,509 @@ impl Display for TripCsv {
     }
 }
 
+/// Write [`Trajectory`]s in CSV format.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::TrajectoryGenerator;
+/// # use spatialbench::csv::TrajectoryCsv;
+/// # use std::fmt::Write;
+/// // Output the first 3 rows in CSV format
+/// let generator = TrajectoryGenerator::new(0.01, 1, 1);
+/// let mut csv = String::new();
+/// writeln!(&mut csv, "{}", TrajectoryCsv::header()).unwrap(); // write header
+/// for line in generator.iter().take(3) {
+///   // write line using CSV formatter
+///   writeln!(&mut csv, "{}", TrajectoryCsv::new(line)).unwrap();
+/// }
+/// ```
+pub struct TrajectoryCsv {
+    inner: Trajectory,
+    delimiter: char,
+}
+
+impl TrajectoryCsv {
+    pub fn new(inner: Trajectory) -> Self {
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the CSV header for the Trajectory table
+    pub fn header() -> &'static str {
+        "tr_tripkey,tr_starttime,tr_endtime,tr_numpoints,tr_path"
+    }
+}
+
+impl Display for TrajectoryCsv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
+        write!(
+            f,
+            // note must quote the path as it contains commas. The path is
+            // written as WKT (the `Debug` format of geo types)
+            "{}{d}{}{d}{}{d}{}{d}\"{:?}\"",
+            self.inner.tr_tripkey,
+            self.inner.tr_starttime,
+            self.inner.tr_endtime,
+            self.inner.tr_numpoints,
+            self.inner.tr_path,
+        )
+    }
+}
+
+/// Write [`Road`]s in CSV format.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::RoadGenerator;
+/// # use spatialbench::csv::RoadCsv;
+/// # use std::fmt::Write;
+/// // Output the first 3 rows in CSV format
+/// let generator = RoadGenerator::new(0.01, 1, 1);
+/// let mut csv = String::new();
+/// writeln!(&mut csv, "{}", RoadCsv::header()).unwrap(); // write header
+/// for line in generator.iter().take(3) {
+///   // write line using CSV formatter
+///   writeln!(&mut csv, "{}", RoadCsv::new(line)).unwrap();
+/// }
+/// ```
+pub struct RoadCsv {
+    inner: Road,
+    delimiter: char,
+}
+
+impl RoadCsv {
+    pub fn new(inner: Road) -> Self {
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the CSV header for the Road table
+    pub fn header() -> &'static str {
+        "r_roadkey,r_townkey,r_class,r_line"
+    }
+}
+
+impl Display for RoadCsv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
+        write!(
+            f,
+            // note must quote the line as it contains commas
+            "{}{d}{}{d}{}{d}\"{:?}\"",
+            self.inner.r_roadkey, self.inner.r_townkey, self.inner.r_class, self.inner.r_line,
+        )
+    }
+}
+
+/// Write [`Country`]s in CSV format.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::CountryGenerator;
+/// # use spatialbench::csv::CountryCsv;
+/// # use std::fmt::Write;
+/// // Output the first 3 rows in CSV format
+/// let generator = CountryGenerator::new(1.0, 1, 1);
+/// let mut csv = String::new();
+/// writeln!(&mut csv, "{}", CountryCsv::header()).unwrap(); // write header
+/// for line in generator.iter().take(3) {
+///   // write line using CSV formatter
+///   writeln!(&mut csv, "{}", CountryCsv::new(line)).unwrap();
+/// }
+/// ```
+pub struct CountryCsv {
+    inner: Country,
+    delimiter: char,
+}
+
+impl CountryCsv {
+    pub fn new(inner: Country) -> Self {
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the CSV header for the Country table
+    pub fn header() -> &'static str {
+        "co_countrykey,co_name,co_boundary"
+    }
+}
+
+impl Display for CountryCsv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
+        write!(
+            f,
+            // note must quote the boundary as it contains commas
+            "{}{d}{}{d}\"{:?}\"",
+            self.inner.co_countrykey, self.inner.co_name, self.inner.co_boundary,
+        )
+    }
+}
+
+/// Write [`Region`]s in CSV format.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::RegionGenerator;
+/// # use spatialbench::csv::RegionCsv;
+/// # use std::fmt::Write;
+/// // Output the first 3 rows in CSV format
+/// let generator = RegionGenerator::new(1.0, 1, 1);
+/// let mut csv = String::new();
+/// writeln!(&mut csv, "{}", RegionCsv::header()).unwrap(); // write header
+/// for line in generator.iter().take(3) {
+///   // write line using CSV formatter
+///   writeln!(&mut csv, "{}", RegionCsv::new(line)).unwrap();
+/// }
+/// ```
+pub struct RegionCsv {
+    inner: Region,
+    delimiter: char,
+}
+
+impl RegionCsv {
+    pub fn new(inner: Region) -> Self {
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the CSV header for the Region table
+    pub fn header() -> &'static str {
+        "re_regionkey,re_countrykey,re_name,re_boundary"
+    }
+}
+
+impl Display for RegionCsv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
+        write!(
+            f,
+            // note must quote the boundary as it contains commas
+            "{}{d}{}{d}{}{d}\"{:?}\"",
+            self.inner.re_regionkey,
+            self.inner.re_countrykey,
+            self.inner.re_name,
+            self.inner.re_boundary,
+        )
+    }
+}
+
+/// Write [`City`]s in CSV format.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::CityGenerator;
+/// # use spatialbench::csv::CityCsv;
+/// # use std::fmt::Write;
+/// // Output the first 3 rows in CSV format
+/// let generator = CityGenerator::new(1.0, 1, 1);
+/// let mut csv = String::new();
+/// writeln!(&mut csv, "{}", CityCsv::header()).unwrap(); // write header
+/// for line in generator.iter().take(3) {
+///   // write line using CSV formatter
+///   writeln!(&mut csv, "{}", CityCsv::new(line)).unwrap();
+/// }
+/// ```
+pub struct CityCsv {
+    inner: City,
+    delimiter: char,
+}
+
+impl CityCsv {
+    pub fn new(inner: City) -> Self {
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the CSV header for the City table
+    pub fn header() -> &'static str {
+        "ci_citykey,ci_regionkey,ci_name,ci_boundary"
+    }
+}
+
+impl Display for CityCsv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
+        write!(
+            f,
+            // note must quote the boundary as it contains commas
+            "{}{d}{}{d}{}{d}\"{:?}\"",
+            self.inner.ci_citykey,
+            self.inner.ci_regionkey,
+            self.inner.ci_name,
+            self.inner.ci_boundary,
+        )
+    }
+}
+
+/// Write the cells of the [`Raster`] in CSV format.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::RasterGenerator;
+/// # use spatialbench::csv::RasterCsv;
+/// # use std::fmt::Write;
+/// // Output the first 3 rows in CSV format
+/// let generator = RasterGenerator::new(0.01, 1, 1);
+/// let mut csv = String::new();
+/// writeln!(&mut csv, "{}", RasterCsv::header()).unwrap(); // write header
+/// for line in generator.iter().take(3) {
+///   // write line using CSV formatter
+///   writeln!(&mut csv, "{}", RasterCsv::new(line)).unwrap();
+/// }
+/// ```
+pub struct RasterCsv {
+    inner: Raster,
+    delimiter: char,
+}
+
+impl RasterCsv {
+    pub fn new(inner: Raster) -> Self {
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the CSV header for the Raster table
+    pub fn header() -> &'static str {
+        "rs_cellkey,rs_row,rs_column,rs_elevation,rs_temperature,rs_cell"
+    }
+}
+
+impl Display for RasterCsv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
+        write!(
+            f,
+            // note must quote the cell as it contains commas
+            "{}{d}{}{d}{}{d}{}{d}{}{d}\"{:?}\"",
+            self.inner.rs_cellkey,
+            self.inner.rs_row,
+            self.inner.rs_column,
+            self.inner.rs_elevation,
+            self.inner.rs_temperature,
+            self.inner.rs_cell,
+        )
+    }
+}
+
+/// Write [`PointCloudPoint`]s in CSV format.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::PointCloudGenerator;
+/// # use spatialbench::csv::PointCloudPointCsv;
+/// # use std::fmt::Write;
+/// // Output the first 3 rows in CSV format
+/// let generator = PointCloudGenerator::new(0.01, 1, 1);
+/// let mut csv = String::new();
+/// writeln!(&mut csv, "{}", PointCloudPointCsv::header()).unwrap(); // write header
+/// for line in generator.iter().take(3) {
+///   // write line using CSV formatter
+///   writeln!(&mut csv, "{}", PointCloudPointCsv::new(line)).unwrap();
+/// }
+/// ```
+pub struct PointCloudPointCsv {
+    inner: PointCloudPoint,
+    delimiter: char,
+}
+
+impl PointCloudPointCsv {
+    pub fn new(inner: PointCloudPoint) -> Self {
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the CSV header for the PointCloud table
+    pub fn header() -> &'static str {
+        "pc_pointkey,pc_x,pc_y,pc_z,pc_intensity,pc_classification"
+    }
+}
+
+impl Display for PointCloudPointCsv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
+        write!(
+            f,
+            "{}{d}{}{d}{}{d}{}{d}{}{d}{}",
+            self.inner.pc_pointkey,
+            self.inner.pc_x,
+            self.inner.pc_y,
+            self.inner.pc_z,
+            self.inner.pc_intensity,
+            self.inner.pc_classification,
+        )
+    }
+}
+
+/// Write [`Landmass`]es in CSV format.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::LandmassGenerator;
+/// # use spatialbench::csv::LandmassCsv;
+/// # use std::fmt::Write;
+/// // Output the first 3 rows in CSV format
+/// let generator = LandmassGenerator::new(1.0, 1, 1);
+/// let mut csv = String::new();
+/// writeln!(&mut csv, "{}", LandmassCsv::header()).unwrap(); // write header
+/// for line in generator.iter().take(3) {
+///   // write line using CSV formatter
+///   writeln!(&mut csv, "{}", LandmassCsv::new(line)).unwrap();
+/// }
+/// ```
+pub struct LandmassCsv {
+    inner: Landmass,
+    delimiter: char,
+}
+
+impl LandmassCsv {
+    pub fn new(inner: Landmass) -> Self {
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the CSV header for the Landmass table
+    pub fn header() -> &'static str {
+        "l_landmasskey,l_name,l_islands,l_boundary"
+    }
+}
+
+impl Display for LandmassCsv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
+        write!(
+            f,
+            // note must quote the boundary as it contains commas
+            "{}{d}{}{d}{}{d}\"{:?}\"",
+            self.inner.l_landmasskey,
+            self.inner.l_name,
+            self.inner.l_islands,
+            self.inner.l_boundary,
+        )
+    }
+}
+
+/// Write [`Poi`]s in CSV format.
+///
+/// # Example
+/// ```
+/// # use spatialbench::generators::PoiGenerator;
+/// # use spatialbench::csv::PoiCsv;
+/// # use std::fmt::Write;
+/// // Output the first 3 rows in CSV format
+/// let generator = PoiGenerator::new(0.01, 1, 1);
+/// let mut csv = String::new();
+/// writeln!(&mut csv, "{}", PoiCsv::header()).unwrap(); // write header
+/// for line in generator.iter().take(3) {
+///   // write line using CSV formatter
+///   writeln!(&mut csv, "{}", PoiCsv::new(line)).unwrap();
+/// }
+/// ```
+pub struct PoiCsv {
+    inner: Poi,
+    delimiter: char,
+}
+
+impl PoiCsv {
+    pub fn new(inner: Poi) -> Self {
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the CSV header for the Poi table
+    pub fn header() -> &'static str {
+        "p_poikey,p_citykey,p_category,p_name,p_location"
+    }
+}
+
+impl Display for PoiCsv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
+        write!(
+            f,
+            "{}{d}{}{d}{}{d}{}{d}\"{:?}\"",
+            self.inner.p_poikey,
+            self.inner.p_citykey,
+            self.inner.p_category,
+            self.inner.p_name,
+            self.inner.p_location,
+        )
+    }
+}
+
 /// Write [`Building`]s in CSV format.
 ///
 /// # Example
@@ -253,11 +819,21 @@ impl Display for TripCsv {
 /// ```
 pub struct BuildingCsv<'a> {
     inner: Building<'a>,
+    delimiter: char,
 }
 
 impl<'a> BuildingCsv<'a> {
     pub fn new(inner: Building<'a>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            delimiter: DEFAULT_DELIMITER,
+        }
+    }
+
+    /// Separate fields with `delimiter` instead of [`DEFAULT_DELIMITER`]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
     }
 
     /// Returns the CSV header for the Building table
@@ -268,10 +844,12 @@ impl<'a> BuildingCsv<'a> {
 
 impl Display for BuildingCsv<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.delimiter;
         write!(
             f,
-            // note must quote the comment field as it may contain commas
-            "{},{},\"{:?}\"",
+            // note must quote the boundary as it may contain commas. The
+            // boundary is written as WKT (the `Debug` format of geo types)
+            "{}{d}{}{d}\"{:?}\"",
             self.inner.b_buildingkey, self.inner.b_name, self.inner.b_boundary,
         )
     }
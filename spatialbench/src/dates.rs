@@ -56,6 +56,29 @@ static JULIAN_DATE: LazyLock<Vec<i32>> = LazyLock::new(|| {
 pub struct GenerateUtils;
 
 impl GenerateUtils {
+    /// Returns the total number of rows of a table for a scale factor
+    ///
+    /// Below scale factor 1 the count is rounded to the nearest row, and every
+    /// table has at least one row. From scale factor 1 it is truncated, as in
+    /// the published datasets. Every generator, as well as the foreign keys
+    /// that reference other tables, derives counts from this function so that
+    /// the keys of trips always refer to rows that exist.
+    ///
+    /// With `log_scale` the table grows with `1 + log2(scale_factor)` (for
+    /// buildings), so scale factors of 0.5 and below have a single row.
+    pub fn total_row_count(scale_base: i32, scale_factor: f64, log_scale: bool) -> i64 {
+        let rows = if log_scale {
+            scale_base as f64 * (1.0 + scale_factor.log2())
+        } else {
+            scale_base as f64 * scale_factor
+        };
+        if scale_factor < 1.0 {
+            (rows.round() as i64).max(1)
+        } else {
+            rows as i64
+        }
+    }
+
     /// Calculates row count with linear scaling (original behavior)
     pub fn calculate_row_count(
         scale_base: i32,
@@ -84,12 +107,12 @@ impl GenerateUtils {
         part_count: i32,
         log_scale: bool,
     ) -> i64 {
-        let total_row_count = if log_scale {
-            (scale_base as f64 * (1.0 + scale_factor.log2())) as i64
-        } else {
-            (scale_base as f64 * scale_factor) as i64
-        };
+        let total_row_count = Self::total_row_count(scale_base, scale_factor, log_scale);
+        Self::part_row_count(total_row_count, part, part_count)
+    }
 
+    /// Returns the number of rows in a part of a table with `total_row_count` rows
+    pub fn part_row_count(total_row_count: i64, part: i32, part_count: i32) -> i64 {
         let rows_per_part = total_row_count / part_count as i64;
 
         if part == part_count {
@@ -107,7 +130,34 @@ impl GenerateUtils {
         part: i32,
         part_count: i32,
     ) -> i64 {
-        let total_row_count = (scale_base as f64 * scale_factor) as i64;
+        Self::calculate_scaled_start_index(scale_base, scale_factor, part, part_count, false)
+    }
+
+    /// Calculates start index for a specific part of the data with logarithmic
+    /// scaling (for buildings)
+    pub fn calculate_logarithmic_start_index(
+        scale_base: i32,
+        scale_factor: f64,
+        part: i32,
+        part_count: i32,
+    ) -> i64 {
+        Self::calculate_scaled_start_index(scale_base, scale_factor, part, part_count, true)
+    }
+
+    fn calculate_scaled_start_index(
+        scale_base: i32,
+        scale_factor: f64,
+        part: i32,
+        part_count: i32,
+        log_scale: bool,
+    ) -> i64 {
+        let total_row_count = Self::total_row_count(scale_base, scale_factor, log_scale);
+        Self::part_start_index(total_row_count, part, part_count)
+    }
+
+    /// Returns the index of the first row of a part of a table with
+    /// `total_row_count` rows
+    pub fn part_start_index(total_row_count: i64, part: i32, part_count: i32) -> i64 {
         let rows_per_part = total_row_count / part_count as i64;
         rows_per_part * (part as i64 - 1)
     }
@@ -176,11 +226,15 @@ pub struct TPCHDate {
 
 impl Display for TPCHDate {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {:02}:{:02}:{:02}",
-            &DATE_TO_STRING[self.date_index as usize], self.hour, self.minute, self.second
-        )
+        match DATE_TO_STRING.get(self.date_index as usize) {
+            Some(date) => write!(f, "{date}")?,
+            // dates of a time window outside of the generated range
+            None => {
+                let (y, m, dy) = civil_from_days(self.to_unix_epoch() as i64);
+                write!(f, "{:04}-{:02}-{:02}", y, m, dy)?
+            }
+        }
+        write!(f, " {:02}:{:02}:{:02}", self.hour, self.minute, self.second)
     }
 }
 
@@ -204,6 +258,21 @@ impl TPCHDate {
         }
     }
 
+    /// Create a new date from the number of seconds since the Unix epoch
+    ///
+    /// The date may be outside of the range of the data generator, e.g. for
+    /// a [`TimeWindow`](crate::temporal::TimeWindow).
+    pub fn from_unix_epoch_seconds(seconds: i64) -> Self {
+        let days = seconds.div_euclid(24 * 60 * 60);
+        let time = seconds.rem_euclid(24 * 60 * 60);
+        Self {
+            date_index: days as i32 - Self::UNIX_EPOCH_OFFSET,
+            hour: (time / 3600) as u8,
+            minute: (time % 3600 / 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+
     /// Create a new date with a given day value and time components including seconds
     pub fn new_with_time(day_value: i32, (hour, minute, second): (u8, u8, u8)) -> Self {
         TPCHDate::new(day_value, hour, minute, second)
@@ -217,7 +286,12 @@ impl TPCHDate {
     /// * month: `2`
     /// * day: `3`
     pub fn to_ymd(&self) -> (i32, i32, i32) {
-        to_ymd(self.date_index + 1)
+        if (0..TOTAL_DATE_RANGE).contains(&self.date_index) {
+            to_ymd(self.date_index + 1)
+        } else {
+            let (y, m, dy) = civil_from_days(self.to_unix_epoch() as i64);
+            (y as i32 - 1900, m as i32, dy as i32)
+        }
     }
 
     /// Return the inner date index
@@ -293,6 +367,35 @@ fn format_ymd(y: i32, m: i32, dy: i32) -> String {
     format!("{:04}-{:02}-{:02}", 1900 + y, m, dy)
 }
 
+/// Returns the number of days since the Unix epoch of a date of the
+/// proleptic Gregorian calendar
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the (year, month, day) of a number of days since the Unix epoch,
+/// the inverse of [`days_from_civil`]
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
 /// Helpers duplicated to avoid circular references
 const fn julian(date: i32) -> i32 {
     let mut offset = date - MIN_GENERATE_DATE;
@@ -363,4 +466,65 @@ mod test {
         assert_eq!(date.to_string(), "1995-05-19 00:00:00");
         assert_eq!(date.to_unix_epoch(), 9269);
     }
+
+    #[test]
+    fn test_dates_outside_of_the_generated_range() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(
+            days_from_civil(1992, 1, 1),
+            TPCHDate::UNIX_EPOCH_OFFSET as i64
+        );
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+
+        let date = TPCHDate::from_unix_epoch_seconds(1_709_195_415);
+        assert_eq!(date.to_string(), "2024-02-29 08:30:15");
+        assert_eq!(date.to_unix_epoch_seconds(), 1_709_195_415);
+        assert_eq!(date.to_ymd(), (124, 2, 29));
+        // dates in the generated range are unchanged
+        let date = TPCHDate::new(MIN_GENERATE_DATE + 1234, 10, 30, 45);
+        assert_eq!(
+            TPCHDate::from_unix_epoch_seconds(date.to_unix_epoch_seconds()),
+            date
+        );
+    }
+
+    #[test]
+    fn test_fractional_row_counts() {
+        // 100 * 0.29 is 28.999999999999996 in floating point
+        assert_eq!(GenerateUtils::total_row_count(100, 0.29, false), 29);
+        assert_eq!(
+            GenerateUtils::total_row_count(6_000_000, 0.01, false),
+            60_000
+        );
+        // tiny tables still have one row
+        assert_eq!(GenerateUtils::total_row_count(100, 0.001, false), 1);
+        assert_eq!(GenerateUtils::total_row_count(20_000, 0.01, true), 1);
+        assert_eq!(GenerateUtils::total_row_count(20_000, 0.51, true), 571);
+        // standard scale factors keep the truncated counts
+        assert_eq!(GenerateUtils::total_row_count(20_000, 5.0, true), 66_438);
+        assert_eq!(GenerateUtils::total_row_count(20_000, 10.0, true), 86_438);
+
+        // the parts cover all the rows exactly once
+        for log_scale in [false, true] {
+            let total = GenerateUtils::total_row_count(20_000, 3.3, log_scale);
+            let mut next_start = 0;
+            for part in 1..=7 {
+                let (start, count) = if log_scale {
+                    (
+                        GenerateUtils::calculate_logarithmic_start_index(20_000, 3.3, part, 7),
+                        GenerateUtils::calculate_logarithmic_row_count(20_000, 3.3, part, 7),
+                    )
+                } else {
+                    (
+                        GenerateUtils::calculate_start_index(20_000, 3.3, part, 7),
+                        GenerateUtils::calculate_row_count(20_000, 3.3, part, 7),
+                    )
+                };
+                assert_eq!(start, next_start);
+                next_start = start + count;
+            }
+            assert_eq!(next_start, total);
+        }
+    }
 }
@@ -16,27 +16,191 @@
 // under the License.
 
 //! Generators for each Spatial Bench Tables
+use crate::attributes::{self, Attribute, AttributeDistributions, AttributeSampler};
 use crate::dates;
 use crate::dates::{GenerateUtils, TPCHDate};
 use crate::decimal::TPCHDecimal;
 use crate::distribution::Distribution;
 use crate::distribution::Distributions;
+use crate::names::{self, TextSynthesizer, TextValue};
+use crate::random::derive_seed;
 use crate::random::RandomPhoneNumber;
 use crate::random::RowRandomInt;
 use crate::random::{PhoneNumberInstance, RandomBoundedLong, StringSequenceInstance};
 use crate::random::{RandomAlphaNumeric, RandomAlphaNumericInstance};
 use crate::random::{RandomBoundedInt, RandomString, RandomStringSequence, RandomText};
+use crate::skew::{self, ForeignKey, KeySampler, KeySkews};
+use crate::spatial::admin::{AdminHierarchy, AdminLevel};
+use crate::spatial::geography;
+use crate::spatial::landmass::{self, LandMask};
 use crate::spatial::overrides as spatial_overrides;
+use crate::spatial::point_cloud::{CloudPoint, POINTS_PER_TILE};
+use crate::spatial::precision::snap_coordinates;
+use crate::spatial::road::{self, RoadNetwork};
+use crate::spatial::trajectory::{route_path, trajectory_path};
 use crate::spatial::utils::continent::{build_continent_cdf, WeightedTarget};
-use crate::spatial::utils::{hash_to_unit_u64, spider_seed_for_index, wrap_around_longitude};
-use crate::spatial::{ContinentAffines, SpatialDefaults, SpatialGenerator};
+use crate::spatial::utils::{
+    build_cdf_from_weights, hash_to_unit_u64, pick_from_cdf, spider_seed_for_index,
+    wrap_around_longitude,
+};
+use crate::spatial::{ContinentAffines, PointCloud, RasterGrid, SpatialDefaults, SpatialGenerator};
+use crate::temporal::{self, TimeWindow};
 use crate::text::TextPool;
-use geo::Point;
+use geo::{
+    BoundingRect, Centroid, Contains, InteriorPoint, LineString, MultiPolygon, Point, Polygon,
+};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use std::convert::TryInto;
 use std::fmt;
 use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+
+/// Overrides of the number of rows of the generated tables
+///
+/// By default the row count of every table is derived from the scale factor.
+/// An override only changes the size of its table, for example to generate
+/// many buildings and few trips for skew experiments. Trips reference the
+/// keys of the overridden customer, driver and vehicle tables, so the foreign
+/// keys still refer to rows that exist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RowCounts {
+    /// Number of rows of the vehicle table
+    pub vehicle: Option<i64>,
+    /// Number of rows of the driver table
+    pub driver: Option<i64>,
+    /// Number of rows of the customer table
+    pub customer: Option<i64>,
+    /// Number of rows of the trip table
+    pub trip: Option<i64>,
+    /// Number of rows of the building table
+    pub building: Option<i64>,
+    /// Number of rows of the POI table
+    pub poi: Option<i64>,
+    /// Number of rows of the point cloud table
+    pub pointcloud: Option<i64>,
+}
+
+impl RowCounts {
+    /// Return the number of rows of the vehicle table
+    pub fn vehicle_count(&self, scale_factor: f64) -> i64 {
+        self.vehicle.unwrap_or_else(|| {
+            GenerateUtils::total_row_count(VehicleGenerator::SCALE_BASE, scale_factor, false)
+        })
+    }
+
+    /// Return the number of rows of the driver table
+    pub fn driver_count(&self, scale_factor: f64) -> i64 {
+        self.driver.unwrap_or_else(|| {
+            GenerateUtils::total_row_count(DriverGenerator::SCALE_BASE, scale_factor, false)
+        })
+    }
+
+    /// Return the number of rows of the customer table
+    pub fn customer_count(&self, scale_factor: f64) -> i64 {
+        self.customer.unwrap_or_else(|| {
+            GenerateUtils::total_row_count(CustomerGenerator::SCALE_BASE, scale_factor, false)
+        })
+    }
+
+    /// Return the number of rows of the trip table
+    pub fn trip_count(&self, scale_factor: f64) -> i64 {
+        self.trip.unwrap_or_else(|| {
+            GenerateUtils::total_row_count(TripGenerator::SCALE_BASE, scale_factor, false)
+        })
+    }
+
+    /// Return the number of rows of the building table
+    pub fn building_count(&self, scale_factor: f64) -> i64 {
+        self.building.unwrap_or_else(|| {
+            GenerateUtils::total_row_count(BuildingGenerator::SCALE_BASE, scale_factor, true)
+        })
+    }
+
+    /// Return the number of rows of the POI table
+    pub fn poi_count(&self, scale_factor: f64) -> i64 {
+        self.poi.unwrap_or_else(|| {
+            GenerateUtils::total_row_count(PoiGenerator::SCALE_BASE, scale_factor, false)
+        })
+    }
+
+    /// Return the number of rows of the point cloud table
+    pub fn pointcloud_count(&self, scale_factor: f64) -> i64 {
+        self.pointcloud.unwrap_or_else(|| {
+            GenerateUtils::total_row_count(PointCloudGenerator::SCALE_BASE, scale_factor, false)
+        })
+    }
+
+    /// Return the number of drivers the trips reference (`t_driverkey`)
+    ///
+    /// The trips of the reference data only reference the first drivers, as
+    /// many as there are vehicles at the scale factor. With an overridden
+    /// driver count, trips reference all the drivers.
+    pub fn referenced_driver_count(&self, scale_factor: f64) -> i64 {
+        self.driver.unwrap_or_else(|| {
+            GenerateUtils::total_row_count(VehicleGenerator::SCALE_BASE, scale_factor, false)
+        })
+    }
+}
+
+/// Lists the overridden row counts, e.g. `building=10000000,trip=1000000`
+impl Display for RowCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = [
+            ("vehicle", self.vehicle),
+            ("driver", self.driver),
+            ("customer", self.customer),
+            ("trip", self.trip),
+            ("building", self.building),
+            ("poi", self.poi),
+        ];
+        let mut first = true;
+        for (table, count) in counts {
+            if let Some(count) = count {
+                if !first {
+                    write!(f, ",")?;
+                }
+                write!(f, "{table}={count}")?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The columns of a table to generate, by their position in its rows
+///
+/// The generator iterators skip the attributes of the other columns, which
+/// are left empty, without changing the values of the projected columns.
+/// By default all the columns are generated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Projection {
+    /// The positions of the columns, or `None` for all of them
+    columns: Option<Vec<usize>>,
+}
+
+impl Projection {
+    /// Only the columns at the `columns` positions
+    pub fn new(columns: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            columns: Some(columns.into_iter().collect()),
+        }
+    }
+
+    /// Return true if the column at position `column` is generated
+    pub fn contains(&self, column: usize) -> bool {
+        self.columns
+            .as_ref()
+            .is_none_or(|columns| columns.contains(&column))
+    }
+
+    /// Return true if any of the columns at the `columns` positions is
+    /// generated
+    pub fn contains_any(&self, columns: impl IntoIterator<Item = usize>) -> bool {
+        columns.into_iter().any(|column| self.contains(column))
+    }
+}
 
 /// A Vehicle Manufacturer, formatted as `"Manufacturer#<n>"`
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -109,6 +273,8 @@ pub struct VehicleGenerator<'a> {
     scale_factor: f64,
     part: i32,
     part_count: i32,
+    seed: u64,
+    row_counts: RowCounts,
     distributions: &'a Distributions,
     text_pool: &'a TextPool,
 }
@@ -154,11 +320,39 @@ impl<'a> VehicleGenerator<'a> {
             scale_factor,
             part,
             part_count,
+            seed: 0,
+            row_counts: RowCounts::default(),
             distributions,
             text_pool,
         }
     }
 
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// The default seed `0` generates the reference data. See
+    /// [`derive_seed`] for details.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Overrides the number of rows of the tables, see [`RowCounts`]
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Return the number of rows of this part, including overrides
+    pub fn row_count(&self) -> i64 {
+        let total_row_count = self.row_counts.vehicle_count(self.scale_factor);
+        GenerateUtils::part_row_count(total_row_count, self.part, self.part_count)
+    }
+
     /// Return the row count for the given scale factor and generator part count
     pub fn calculate_row_count(scale_factor: f64, part: i32, part_count: i32) -> i64 {
         GenerateUtils::calculate_row_count(Self::SCALE_BASE, scale_factor, part, part_count)
@@ -169,13 +363,13 @@ impl<'a> VehicleGenerator<'a> {
         VehicleGeneratorIterator::new(
             self.distributions,
             self.text_pool,
-            GenerateUtils::calculate_start_index(
-                Self::SCALE_BASE,
-                self.scale_factor,
+            GenerateUtils::part_start_index(
+                self.row_counts.vehicle_count(self.scale_factor),
                 self.part,
                 self.part_count,
             ),
-            Self::calculate_row_count(self.scale_factor, self.part, self.part_count),
+            self.row_count(),
+            self.seed,
         )
     }
 }
@@ -199,6 +393,7 @@ pub struct VehicleGeneratorIterator<'a> {
     size_random: RandomBoundedInt,
     container_random: RandomString<'a>,
     comment_random: RandomText<'a>,
+    projection: Projection,
 
     start_index: i64,
     row_count: i64,
@@ -211,31 +406,36 @@ impl<'a> VehicleGeneratorIterator<'a> {
         text_pool: &'a TextPool,
         start_index: i64,
         row_count: i64,
+        seed: u64,
     ) -> Self {
         let mut name_random = RandomStringSequence::new(
-            709314158,
+            derive_seed(709314158, seed),
             VehicleGenerator::NAME_WORDS,
             distributions.part_colors(),
         );
         let mut manufacturer_random = RandomBoundedInt::new(
-            1,
+            derive_seed(1, seed),
             VehicleGenerator::MANUFACTURER_MIN,
             VehicleGenerator::MANUFACTURER_MAX,
         );
         let mut brand_random = RandomBoundedInt::new(
-            46831694,
+            derive_seed(46831694, seed),
             VehicleGenerator::BRAND_MIN,
             VehicleGenerator::BRAND_MAX,
         );
-        let mut type_random = RandomString::new(1841581359, distributions.part_types());
+        let mut type_random =
+            RandomString::new(derive_seed(1841581359, seed), distributions.part_types());
         let mut size_random = RandomBoundedInt::new(
-            1193163244,
+            derive_seed(1193163244, seed),
             VehicleGenerator::SIZE_MIN,
             VehicleGenerator::SIZE_MAX,
         );
-        let mut container_random = RandomString::new(727633698, distributions.part_containers());
+        let mut container_random = RandomString::new(
+            derive_seed(727633698, seed),
+            distributions.part_containers(),
+        );
         let mut comment_random = RandomText::new(
-            804159733,
+            derive_seed(804159733, seed),
             text_pool,
             VehicleGenerator::COMMENT_AVERAGE_LENGTH as f64,
         );
@@ -257,12 +457,19 @@ impl<'a> VehicleGeneratorIterator<'a> {
             size_random,
             container_random,
             comment_random,
+            projection: Projection::default(),
             start_index,
             row_count,
             index: 0,
         }
     }
 
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
     /// Creates a vehicle with the given key
     fn make_vehicle(&mut self, vehicle_key: i64) -> Vehicle<'a> {
         let manufacturer = self.manufacturer_random.next_value();
@@ -272,8 +479,16 @@ impl<'a> VehicleGeneratorIterator<'a> {
             v_vehiclekey: vehicle_key,
             v_mfgr: VehicleManufacturerName::new(manufacturer),
             v_brand: VehicleBrandName::new(brand),
-            v_type: self.type_random.next_value(),
-            v_license: self.comment_random.next_value(),
+            v_type: if self.projection.contains(3) {
+                self.type_random.next_value()
+            } else {
+                ""
+            },
+            v_license: if self.projection.contains(4) {
+                self.comment_random.next_value()
+            } else {
+                ""
+            },
         }
     }
 
@@ -346,7 +561,7 @@ pub struct Driver {
     /// Driver name.
     pub d_name: DriverName,
     /// Driver address
-    pub d_address: RandomAlphaNumericInstance,
+    pub d_address: TextValue<RandomAlphaNumericInstance>,
     /// Region name
     pub d_region: String,
     /// Nation name
@@ -376,6 +591,9 @@ pub struct DriverGenerator<'a> {
     scale_factor: f64,
     part: i32,
     part_count: i32,
+    seed: u64,
+    row_counts: RowCounts,
+    realistic_text: bool,
     distributions: &'a Distributions,
     text_pool: &'a TextPool,
 }
@@ -429,11 +647,49 @@ impl<'a> DriverGenerator<'a> {
             scale_factor,
             part,
             part_count,
+            seed: 0,
+            row_counts: RowCounts::default(),
+            realistic_text: names::realistic_text(),
             distributions,
             text_pool,
         }
     }
 
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// The default seed `0` generates the reference data. See
+    /// [`derive_seed`] for details.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Overrides the number of rows of the tables, see [`RowCounts`]
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Makes up realistic addresses, such as `1428 Cedar Lane, Brenford 40213`, instead of
+    /// random characters
+    ///
+    /// Defaults to the mode set with [`names::set_realistic_text`].
+    pub fn with_realistic_text(mut self, realistic_text: bool) -> Self {
+        self.realistic_text = realistic_text;
+        self
+    }
+
+    /// Return the number of rows of this part, including overrides
+    pub fn row_count(&self) -> i64 {
+        let total_row_count = self.row_counts.driver_count(self.scale_factor);
+        GenerateUtils::part_row_count(total_row_count, self.part, self.part_count)
+    }
+
     /// Return the row count for the given scale factor and generator part count
     pub fn calculate_row_count(scale_factor: f64, part: i32, part_count: i32) -> i64 {
         GenerateUtils::calculate_row_count(Self::SCALE_BASE, scale_factor, part, part_count)
@@ -444,13 +700,14 @@ impl<'a> DriverGenerator<'a> {
         DriverGeneratorIterator::new(
             self.distributions,
             self.text_pool,
-            GenerateUtils::calculate_start_index(
-                Self::SCALE_BASE,
-                self.scale_factor,
+            GenerateUtils::part_start_index(
+                self.row_counts.driver_count(self.scale_factor),
                 self.part,
                 self.part_count,
             ),
-            Self::calculate_row_count(self.scale_factor, self.part, self.part_count),
+            self.row_count(),
+            self.seed,
+            self.realistic_text,
         )
     }
 }
@@ -468,6 +725,8 @@ impl<'a> IntoIterator for DriverGenerator<'a> {
 #[derive(Debug)]
 pub struct DriverGeneratorIterator<'a> {
     address_random: RandomAlphaNumeric,
+    /// Makes up the addresses instead of `address_random`
+    names: Option<TextSynthesizer>,
     nation_key_random: RandomBoundedInt,
     phone_random: RandomPhoneNumber,
     account_balance_random: RandomBoundedInt,
@@ -480,6 +739,7 @@ pub struct DriverGeneratorIterator<'a> {
     // Add references to distributions
     nations: &'a Distribution,
     regions: &'a Distribution,
+    projection: Projection,
 
     start_index: i64,
     row_count: i64,
@@ -492,27 +752,34 @@ impl<'a> DriverGeneratorIterator<'a> {
         text_pool: &'a TextPool,
         start_index: i64,
         row_count: i64,
+        seed: u64,
+        realistic_text: bool,
     ) -> Self {
-        let mut address_random =
-            RandomAlphaNumeric::new(706178559, DriverGenerator::ADDRESS_AVERAGE_LENGTH);
-        let mut nation_key_random =
-            RandomBoundedInt::new(110356601, 0, (distributions.nations().size() - 1) as i32);
-        let mut phone_random = RandomPhoneNumber::new(884434366);
+        let mut address_random = RandomAlphaNumeric::new(
+            derive_seed(706178559, seed),
+            DriverGenerator::ADDRESS_AVERAGE_LENGTH,
+        );
+        let mut nation_key_random = RandomBoundedInt::new(
+            derive_seed(110356601, seed),
+            0,
+            (distributions.nations().size() - 1) as i32,
+        );
+        let mut phone_random = RandomPhoneNumber::new(derive_seed(884434366, seed));
         let mut account_balance_random = RandomBoundedInt::new(
-            962338209,
+            derive_seed(962338209, seed),
             DriverGenerator::ACCOUNT_BALANCE_MIN,
             DriverGenerator::ACCOUNT_BALANCE_MAX,
         );
         let mut comment_random = RandomText::new(
-            1341315363,
+            derive_seed(1341315363, seed),
             text_pool,
             DriverGenerator::COMMENT_AVERAGE_LENGTH as f64,
         );
         let mut bbb_comment_random =
-            RandomBoundedInt::new(202794285, 1, DriverGenerator::SCALE_BASE);
-        let mut bbb_junk_random = RowRandomInt::new(263032577, 1);
-        let mut bbb_offset_random = RowRandomInt::new(715851524, 1);
-        let mut bbb_type_random = RandomBoundedInt::new(753643799, 0, 100);
+            RandomBoundedInt::new(derive_seed(202794285, seed), 1, DriverGenerator::SCALE_BASE);
+        let mut bbb_junk_random = RowRandomInt::new(derive_seed(263032577, seed), 1);
+        let mut bbb_offset_random = RowRandomInt::new(derive_seed(715851524, seed), 1);
+        let mut bbb_type_random = RandomBoundedInt::new(derive_seed(753643799, seed), 0, 100);
 
         // Advance all generators to the starting position
         address_random.advance_rows(start_index);
@@ -527,6 +794,7 @@ impl<'a> DriverGeneratorIterator<'a> {
 
         DriverGeneratorIterator {
             address_random,
+            names: realistic_text.then(|| TextSynthesizer::new(seed, "driver")),
             nation_key_random,
             phone_random,
             account_balance_random,
@@ -539,6 +807,7 @@ impl<'a> DriverGeneratorIterator<'a> {
             // Initialize the new fields
             nations: distributions.nations(),
             regions: distributions.regions(),
+            projection: Projection::default(),
 
             start_index,
             row_count,
@@ -546,6 +815,12 @@ impl<'a> DriverGeneratorIterator<'a> {
         }
     }
 
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
     /// Creates a Driver with the given key
     fn make_driver(&mut self, driver_key: i64) -> Driver {
         let nation_key = self.nation_key_random.next_value();
@@ -554,21 +829,33 @@ impl<'a> DriverGeneratorIterator<'a> {
             .regions
             .get_value(self.nations.get_weight(nation_key as usize) as usize);
 
+        // the names are only copied for the projected columns
+        let text = |column: usize, value: &str| {
+            if self.projection.contains(column) {
+                value.to_string()
+            } else {
+                String::new()
+            }
+        };
         Driver {
             d_driverkey: driver_key,
             d_name: DriverName::new(driver_key),
-            d_address: self.address_random.next_value(),
-            d_region: region.to_string(), // Convert &str to String
-            d_nation: nation.to_string(), // Convert &str to String
+            d_address: match &self.names {
+                _ if !self.projection.contains(2) => TextValue::Realistic(String::new()),
+                Some(names) => TextValue::Realistic(names.address(driver_key as u64)),
+                None => TextValue::Default(self.address_random.next_value()),
+            },
+            d_region: text(3, region),
+            d_nation: text(4, nation),
             d_phone: self.phone_random.next_value(nation_key as i64),
         }
     }
 
-    /// Selects a driver for a vehicle, with drivers table 5x the size of vehicles table
-    pub fn select_driver(vehicle_key: i64, driver_number: i64, scale_factor: f64) -> i64 {
-        // Use supplier generator's scale base
-        let mut driver_count = (VehicleGenerator::SCALE_BASE as f64 * scale_factor) as i64;
-        driver_count = driver_count.max(1);
+    /// Selects one of the first `driver_count` drivers for a vehicle
+    ///
+    /// See [`RowCounts::referenced_driver_count`] for the number of drivers
+    /// trips reference.
+    pub fn select_driver(vehicle_key: i64, driver_number: i64, driver_count: i64) -> i64 {
         ((vehicle_key
             + (driver_number
                 * ((driver_count / DriverGenerator::DRIVERS_PER_VEHICLE as i64)
@@ -637,7 +924,7 @@ pub struct Customer<'a> {
     /// Customer name
     pub c_name: CustomerName,
     /// Customer address
-    pub c_address: RandomAlphaNumericInstance,
+    pub c_address: TextValue<RandomAlphaNumericInstance>,
     /// Region name
     pub c_region: &'a str,
     /// Nation name
@@ -662,6 +949,9 @@ pub struct CustomerGenerator<'a> {
     scale_factor: f64,
     part: i32,
     part_count: i32,
+    seed: u64,
+    row_counts: RowCounts,
+    realistic_text: bool,
     distributions: &'a Distributions,
     text_pool: &'a TextPool,
 }
@@ -703,11 +993,49 @@ impl<'a> CustomerGenerator<'a> {
             scale_factor,
             part,
             part_count,
+            seed: 0,
+            row_counts: RowCounts::default(),
+            realistic_text: names::realistic_text(),
             distributions,
             text_pool,
         }
     }
 
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// The default seed `0` generates the reference data. See
+    /// [`derive_seed`] for details.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Overrides the number of rows of the tables, see [`RowCounts`]
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Makes up realistic addresses, such as `1428 Cedar Lane, Brenford 40213`, instead of
+    /// random characters
+    ///
+    /// Defaults to the mode set with [`names::set_realistic_text`].
+    pub fn with_realistic_text(mut self, realistic_text: bool) -> Self {
+        self.realistic_text = realistic_text;
+        self
+    }
+
+    /// Return the number of rows of this part, including overrides
+    pub fn row_count(&self) -> i64 {
+        let total_row_count = self.row_counts.customer_count(self.scale_factor);
+        GenerateUtils::part_row_count(total_row_count, self.part, self.part_count)
+    }
+
     /// Return the row count for the given scale factor and generator part count
     pub fn calculate_row_count(scale_factor: f64, part: i32, part_count: i32) -> i64 {
         GenerateUtils::calculate_row_count(Self::SCALE_BASE, scale_factor, part, part_count)
@@ -718,13 +1046,14 @@ impl<'a> CustomerGenerator<'a> {
         CustomerGeneratorIterator::new(
             self.distributions,
             self.text_pool,
-            GenerateUtils::calculate_start_index(
-                Self::SCALE_BASE,
-                self.scale_factor,
+            GenerateUtils::part_start_index(
+                self.row_counts.customer_count(self.scale_factor),
                 self.part,
                 self.part_count,
             ),
-            Self::calculate_row_count(self.scale_factor, self.part, self.part_count),
+            self.row_count(),
+            self.seed,
+            self.realistic_text,
         )
     }
 }
@@ -742,8 +1071,11 @@ impl<'a> IntoIterator for CustomerGenerator<'a> {
 #[derive(Debug)]
 pub struct CustomerGeneratorIterator<'a> {
     address_random: RandomAlphaNumeric,
+    /// Makes up the addresses instead of `address_random`
+    names: Option<TextSynthesizer>,
     nation_key_random: RandomBoundedInt,
     phone_random: RandomPhoneNumber,
+    projection: Projection,
 
     start_index: i64,
     row_count: i64,
@@ -758,21 +1090,30 @@ impl<'a> CustomerGeneratorIterator<'a> {
         text_pool: &'a TextPool,
         start_index: i64,
         row_count: i64,
+        seed: u64,
+        realistic_text: bool,
     ) -> Self {
-        let mut address_random =
-            RandomAlphaNumeric::new(881155353, CustomerGenerator::ADDRESS_AVERAGE_LENGTH);
-        let mut nation_key_random =
-            RandomBoundedInt::new(1489529863, 0, (distributions.nations().size() - 1) as i32);
-        let mut phone_random = RandomPhoneNumber::new(1521138112);
+        let mut address_random = RandomAlphaNumeric::new(
+            derive_seed(881155353, seed),
+            CustomerGenerator::ADDRESS_AVERAGE_LENGTH,
+        );
+        let mut nation_key_random = RandomBoundedInt::new(
+            derive_seed(1489529863, seed),
+            0,
+            (distributions.nations().size() - 1) as i32,
+        );
+        let mut phone_random = RandomPhoneNumber::new(derive_seed(1521138112, seed));
         let mut account_balance_random = RandomBoundedInt::new(
-            298370230,
+            derive_seed(298370230, seed),
             CustomerGenerator::ACCOUNT_BALANCE_MIN,
             CustomerGenerator::ACCOUNT_BALANCE_MAX,
         );
-        let mut market_segment_random =
-            RandomString::new(1140279430, distributions.market_segments());
+        let mut market_segment_random = RandomString::new(
+            derive_seed(1140279430, seed),
+            distributions.market_segments(),
+        );
         let mut comment_random = RandomText::new(
-            1335826707,
+            derive_seed(1335826707, seed),
             text_pool,
             CustomerGenerator::COMMENT_AVERAGE_LENGTH as f64,
         );
@@ -787,16 +1128,24 @@ impl<'a> CustomerGeneratorIterator<'a> {
 
         CustomerGeneratorIterator {
             address_random,
+            names: realistic_text.then(|| TextSynthesizer::new(seed, "customer")),
             phone_random,
             nation_key_random,
             regions: distributions.regions(),
             nations: distributions.nations(),
+            projection: Projection::default(),
             start_index,
             row_count,
             index: 0,
         }
     }
 
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
     /// Creates a customer with the given key
     fn make_customer(&mut self, customer_key: i64) -> Customer<'a> {
         let nation_key = self.nation_key_random.next_value() as i64;
@@ -804,7 +1153,11 @@ impl<'a> CustomerGeneratorIterator<'a> {
         Customer {
             c_custkey: customer_key,
             c_name: CustomerName::new(customer_key),
-            c_address: self.address_random.next_value(),
+            c_address: match &self.names {
+                _ if !self.projection.contains(2) => TextValue::Realistic(String::new()),
+                Some(names) => TextValue::Realistic(names.address(customer_key as u64)),
+                None => TextValue::Default(self.address_random.next_value()),
+            },
             c_region: self.regions.get_value(region_key as usize),
             c_nation: self.nations.get_value(nation_key as usize),
             c_phone: self.phone_random.next_value(nation_key),
@@ -896,11 +1249,20 @@ pub struct TripGenerator {
     scale_factor: f64,
     part: i32,
     part_count: i32,
+    seed: u64,
+    row_counts: RowCounts,
     distributions: Distributions,
     text_pool: TextPool,
     distance_kde: crate::kde::DistanceKDE,
     spatial_gen: SpatialGenerator,
     continent_cdf: Vec<WeightedTarget>,
+    time_window: Option<TimeWindow>,
+    snap_to_roads: bool,
+    on_land: bool,
+    attributes: AttributeDistributions,
+    key_skews: KeySkews,
+    /// The first and last key generated instead of the rows of the part
+    keys: Option<(i64, i64)>,
 }
 
 impl TripGenerator {
@@ -938,23 +1300,127 @@ impl TripGenerator {
         distance_kde: crate::kde::DistanceKDE,
         spatial_gen: SpatialGenerator,
     ) -> TripGenerator {
-        let continent_cdf = {
-            let affines = ContinentAffines::default();
-            build_continent_cdf(&affines)
-                .into_iter()
-                .map(|(_name, m, cdf)| WeightedTarget { m, cdf })
-                .collect()
-        };
+        let continent_cdf = trip_continent_cdf();
 
         TripGenerator {
             scale_factor,
             part,
             part_count,
+            seed: 0,
+            row_counts: RowCounts::default(),
             distributions: distributions.clone(),
             text_pool: text_pool.clone(),
             distance_kde,
             spatial_gen,
             continent_cdf,
+            time_window: temporal::time_window(),
+            snap_to_roads: road::snap_to_roads(),
+            on_land: landmass::on_land(),
+            attributes: attributes::attribute_distributions(),
+            key_skews: skew::key_skews(),
+            keys: None,
+        }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// The default seed `0` generates the reference data. See
+    /// [`derive_seed`] for details.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Overrides the number of rows of the tables, see [`RowCounts`]
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Draws the pickup times from `time_window` instead of the dates of the
+    /// reference data
+    ///
+    /// Defaults to the window set with [`temporal::set_time_window`], if any.
+    pub fn with_time_window(mut self, time_window: TimeWindow) -> Self {
+        self.time_window = Some(time_window);
+        self
+    }
+
+    /// Snaps the pickup and dropoff locations to the intersections of the
+    /// [`RoadGenerator`] road network, with the length of the shortest route
+    /// between them as the distance
+    ///
+    /// Defaults to the mode set with [`road::set_snap_to_roads`].
+    pub fn with_snap_to_roads(mut self, snap_to_roads: bool) -> Self {
+        self.snap_to_roads = snap_to_roads;
+        self
+    }
+
+    /// Keeps the pickup and dropoff locations on the land of the
+    /// [`LandmassGenerator`] land mask
+    ///
+    /// A pickup location in the water is drawn again from the spatial
+    /// distribution, and as a last resort moved inland. A dropoff location
+    /// in the water is moved towards the pickup location, halving the
+    /// distance of the trip each time. Defaults to the mode set with
+    /// [`landmass::set_on_land`].
+    pub fn with_on_land(mut self, on_land: bool) -> Self {
+        self.on_land = on_land;
+        self
+    }
+
+    /// Draws the fare per mile and the tip percentage from `attributes`
+    /// instead of uniformly
+    ///
+    /// Defaults to the distributions set with
+    /// [`attributes::set_attribute_distributions`].
+    pub fn with_attribute_distributions(mut self, attributes: AttributeDistributions) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// References the customers, drivers and vehicles with `key_skews`
+    /// instead of uniformly
+    ///
+    /// A skewed driver is drawn on its own rather than from the vehicle.
+    /// Defaults to the skews set with [`skew::set_key_skews`].
+    pub fn with_key_skews(mut self, key_skews: KeySkews) -> Self {
+        self.key_skews = key_skews;
+        self
+    }
+
+    /// Generates the trips with `keys` instead of the rows of the part
+    ///
+    /// The keys may be beyond the rows of the table, such as the trips
+    /// inserted by a [`RefreshSet`](crate::refresh::RefreshSet).
+    pub fn with_keys(mut self, keys: RangeInclusive<i64>) -> Self {
+        self.keys = Some((*keys.start(), *keys.end()));
+        self
+    }
+
+    /// Return the number of rows of this part, including overrides
+    pub fn row_count(&self) -> i64 {
+        if let Some((first, last)) = self.keys {
+            return (last - first + 1).max(0);
+        }
+        let total_row_count = self.row_counts.trip_count(self.scale_factor);
+        GenerateUtils::part_row_count(total_row_count, self.part, self.part_count)
+    }
+
+    /// Return the index of the first row of this part
+    fn start_index(&self) -> i64 {
+        match self.keys {
+            Some((first, _)) => first - 1,
+            None => GenerateUtils::part_start_index(
+                self.row_counts.trip_count(self.scale_factor),
+                self.part,
+                self.part_count,
+            ),
         }
     }
 
@@ -969,25 +1435,148 @@ impl TripGenerator {
             &self.distributions,
             &self.text_pool,
             self.scale_factor,
-            GenerateUtils::calculate_start_index(
-                Self::SCALE_BASE,
-                self.scale_factor,
-                self.part,
-                self.part_count,
-            ),
-            GenerateUtils::calculate_row_count(
-                Self::SCALE_BASE,
-                self.scale_factor,
-                self.part,
-                self.part_count,
-            ),
+            self.row_counts,
+            self.start_index(),
+            self.row_count(),
             self.distance_kde.clone(), // Add the KDE model
-            self.spatial_gen.clone(),
+            self.spatial_gen.with_seed(self.seed),
             self.continent_cdf.clone(),
+            self.time_window,
+            self.snap_to_roads.then(|| {
+                road_network(
+                    self.scale_factor,
+                    self.seed,
+                    &self.spatial_gen,
+                    &self.continent_cdf,
+                    self.on_land,
+                )
+            }),
+            self.on_land.then(|| landmass::land_mask(self.seed)),
+            &self.attributes,
+            &self.key_skews,
+            self.seed,
+        )
+    }
+}
+
+/// Generates the pickup locations (`t_pickuploc`) of trips from their key
+///
+/// The pickup location of a trip only depends on its key, so this can look
+/// up the locations of a few trips, for example to choose query parameters,
+/// without creating a [`TripGenerator`] (which needs the text pool).
+#[derive(Debug, Clone)]
+pub struct TripPickupGenerator {
+    spatial_gen: SpatialGenerator,
+    continent_cdf: Vec<WeightedTarget>,
+    land: Option<Arc<LandMask>>,
+}
+
+impl TripPickupGenerator {
+    /// Creates a generator with the same spatial distribution as [`TripGenerator::new`]
+    pub fn new() -> Self {
+        Self {
+            spatial_gen: spatial_overrides::trip_or_default(SpatialDefaults::trip_default),
+            continent_cdf: trip_continent_cdf(),
+            land: landmass::on_land().then(|| landmass::land_mask(0)),
+        }
+    }
+
+    /// Returns the pickup location of the trip with the given key
+    pub fn pickup_location(&self, trip_key: i64) -> Point {
+        trip_pickup_location(
+            &self.spatial_gen,
+            &self.continent_cdf,
+            self.land.as_deref(),
+            0,
+            trip_key,
         )
     }
 }
 
+impl Default for TripPickupGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the cumulative weights of the continents trips are generated in
+fn trip_continent_cdf() -> Vec<WeightedTarget> {
+    let affines = ContinentAffines::default();
+    build_continent_cdf(&affines)
+        .into_iter()
+        .map(|(_name, m, cdf)| WeightedTarget { m, cdf })
+        .collect()
+}
+
+/// Mixes the global seed of a generator into a hash seed, keeping the
+/// reference hashes for the default seed `0`
+fn seeded_hash(value: u64, seed: u64) -> u64 {
+    if seed == 0 {
+        value
+    } else {
+        spider_seed_for_index(value, seed)
+    }
+}
+
+/// Number of times a pickup location in the water is drawn again before
+/// moving it inland, see [`TripGenerator::with_on_land`]
+const MAX_PICKUP_ATTEMPTS: u64 = 16;
+/// Number of times the distance of a trip is halved to bring its dropoff
+/// location on land, see [`TripGenerator::with_on_land`]
+const MAX_DROPOFF_HALVINGS: usize = 8;
+
+/// Returns the pickup location of the trip with the given key, on the land
+/// of `land` if any
+fn trip_pickup_location(
+    spatial_gen: &SpatialGenerator,
+    continent_cdf: &[WeightedTarget],
+    land: Option<&LandMask>,
+    seed: u64,
+    trip_key: i64,
+) -> Point {
+    // Select continent based on trip_key and generate pickup location
+    let u = hash_to_unit_u64(trip_key as u64, seeded_hash(0xC0DEC0DE, seed));
+    let idx = continent_cdf
+        .iter()
+        .position(|t| u <= t.cdf)
+        .unwrap_or(continent_cdf.len() - 1);
+    let continent_affine = &continent_cdf[idx].m;
+    let generate = |index: u64| -> Point {
+        spatial_gen
+            .generate(index, continent_affine)
+            .try_into()
+            .expect("Failed to convert to point")
+    };
+
+    let pickup = generate(trip_key as u64);
+    let Some(land) = land else {
+        return pickup;
+    };
+    // the continents of the land mask are in the same order
+    let continent = &land.continents()[idx];
+    if continent.contains(pickup) {
+        return pickup;
+    }
+    (1..MAX_PICKUP_ATTEMPTS)
+        .map(|attempt| generate(spider_seed_for_index(trip_key as u64, attempt)))
+        .find(|&location| continent.contains(location))
+        .unwrap_or_else(|| continent.inland(pickup))
+}
+
+/// Returns the dropoff location `offset` away from `pickup`
+fn dropoff_location(pickup: Point, offset: (f64, f64)) -> Point {
+    let mut dropoff_x = pickup.x() + offset.0;
+    dropoff_x = wrap_around_longitude(dropoff_x);
+
+    let mut dropoff_y = pickup.y() + offset.1;
+
+    // Hard code coordinate precision to 8 decimal places - millimeter level precision for WGS 84
+    dropoff_x = (dropoff_x * 100_000_000.0).round() / 100_000_000.0;
+    dropoff_y = (dropoff_y * 100_000_000.0).round() / 100_000_000.0;
+
+    Point::new(dropoff_x, dropoff_y)
+}
+
 impl IntoIterator for TripGenerator {
     type Item = Trip;
     type IntoIter = TripGeneratorIterator;
@@ -1008,14 +1597,28 @@ pub struct TripGeneratorIterator {
     fare_per_mile_random: RandomBoundedInt,
     tip_percent_random: RandomBoundedInt,
     trip_minutes_per_mile_random: RandomBoundedInt,
+    /// The configured distributions of the fare per mile and the tip
+    /// percentage, instead of the uniform random streams
+    fare_sampler: Option<AttributeSampler>,
+    tip_sampler: Option<AttributeSampler>,
+    /// The configured skews of the foreign keys, instead of the uniform
+    /// random streams
+    customer_sampler: Option<KeySampler>,
+    driver_sampler: Option<KeySampler>,
+    vehicle_sampler: Option<KeySampler>,
     distance_kde: crate::kde::DistanceKDE,
     spatial_gen: SpatialGenerator,
     continent_cdf: Vec<WeightedTarget>,
+    time_window: Option<TimeWindow>,
+    roads: Option<Arc<RoadNetwork>>,
+    land: Option<Arc<LandMask>>,
+    seed: u64,
+    projection: Projection,
 
-    scale_factor: f64,
     start_index: i64,
     row_count: i64,
     max_customer_key: i64,
+    referenced_driver_count: i64,
 
     index: i64,
     trip_number: i64,
@@ -1027,45 +1630,70 @@ impl TripGeneratorIterator {
         _distributions: &Distributions,
         _text_pool: &TextPool,
         scale_factor: f64,
+        row_counts: RowCounts,
         start_index: i64,
         row_count: i64,
         distance_kde: crate::kde::DistanceKDE,
         spatial_gen: SpatialGenerator,
         continent_cdf: Vec<WeightedTarget>,
+        time_window: Option<TimeWindow>,
+        roads: Option<Arc<RoadNetwork>>,
+        land: Option<Arc<LandMask>>,
+        attributes: &AttributeDistributions,
+        key_skews: &KeySkews,
+        seed: u64,
     ) -> Self {
         // Create all the randomizers
-        let max_customer_key = (CustomerGenerator::SCALE_BASE as f64 * scale_factor) as i64;
-        let max_driver_key = (DriverGenerator::SCALE_BASE as f64 * scale_factor) as i64;
-        let max_vehicle_key = (VehicleGenerator::SCALE_BASE as f64 * scale_factor) as i64;
-
-        let mut customer_key_random =
-            RandomBoundedLong::new(921591341, scale_factor >= 30000.0, 1, max_customer_key);
-        let mut driver_key_random =
-            RandomBoundedLong::new(572982913, scale_factor >= 30000.0, 1, max_driver_key);
-        let mut vehicle_key_random =
-            RandomBoundedLong::new(135497281, scale_factor >= 30000.0, 1, max_vehicle_key);
+        let max_customer_key = row_counts.customer_count(scale_factor);
+        let max_driver_key = row_counts.driver_count(scale_factor);
+        let max_vehicle_key = row_counts.vehicle_count(scale_factor);
+        // overridden row counts may not fit in 32 bits at small scale factors
+        let use_64bits = |max_key: i64| scale_factor >= 30000.0 || max_key > i32::MAX as i64;
+
+        let mut customer_key_random = RandomBoundedLong::new(
+            derive_seed(921591341, seed),
+            use_64bits(max_customer_key),
+            1,
+            max_customer_key,
+        );
+        let mut driver_key_random = RandomBoundedLong::new(
+            derive_seed(572982913, seed),
+            use_64bits(max_driver_key),
+            1,
+            max_driver_key,
+        );
+        let mut vehicle_key_random = RandomBoundedLong::new(
+            derive_seed(135497281, seed),
+            use_64bits(max_vehicle_key),
+            1,
+            max_vehicle_key,
+        );
 
         let mut pickup_date_random = RandomBoundedInt::new(
-            831649288,
+            derive_seed(831649288, seed),
             dates::MIN_GENERATE_DATE,
             dates::MIN_GENERATE_DATE + dates::TOTAL_DATE_RANGE - 1,
         );
-        let mut pickup_time_random = dates::RandomTimeOfDay::new(123456789);
+        let mut pickup_time_random =
+            dates::RandomTimeOfDay::new(derive_seed(123456789, seed) as u64);
 
         let mut fare_per_mile_random = RandomBoundedInt::new(
-            109837462,
+            derive_seed(109837462, seed),
             TripGenerator::FARE_MIN_PER_MILE,
             TripGenerator::FARE_MAX_PER_MILE,
         );
 
         let mut tip_percent_random = RandomBoundedInt::new(
-            483912756,
+            derive_seed(483912756, seed),
             TripGenerator::TIP_PERCENT_MIN,
             TripGenerator::TIP_PERCENT_MAX,
         );
 
-        let mut trip_minutes_per_mile_random =
-            RandomBoundedInt::new(748219567, 1, TripGenerator::TRIP_DURATION_MAX_PER_MILE);
+        let mut trip_minutes_per_mile_random = RandomBoundedInt::new(
+            derive_seed(748219567, seed),
+            1,
+            TripGenerator::TRIP_DURATION_MAX_PER_MILE,
+        );
 
         // Advance all generators to the starting position
         customer_key_random.advance_rows(start_index);
@@ -1086,24 +1714,64 @@ impl TripGeneratorIterator {
             fare_per_mile_random,
             tip_percent_random,
             trip_minutes_per_mile_random,
+            fare_sampler: attributes.sampler(Attribute::Fare),
+            tip_sampler: attributes.sampler(Attribute::Tip),
+            customer_sampler: key_skews.sampler(ForeignKey::Customer, max_customer_key),
+            driver_sampler: key_skews.sampler(
+                ForeignKey::Driver,
+                row_counts.referenced_driver_count(scale_factor),
+            ),
+            vehicle_sampler: key_skews.sampler(ForeignKey::Vehicle, max_vehicle_key),
             distance_kde,
             spatial_gen,
             continent_cdf,
+            time_window,
+            roads,
+            land,
+            seed,
+            projection: Projection::default(),
 
-            scale_factor,
             start_index,
             row_count,
             max_customer_key,
+            referenced_driver_count: row_counts.referenced_driver_count(scale_factor),
 
             index: 0,
             trip_number: 0,
         }
     }
 
-    /// Creates a trip with the given key
-    fn make_trip(&mut self, trip_key: i64) -> Trip {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Return true if the projected columns depend on the locations: the
+    /// locations themselves, the distance and the columns derived from it
+    /// when the trips stay on land or follow the roads, and the fare and tip
+    /// when they are sampled at the pickup location
+    fn needs_locations(&self) -> bool {
+        let moved = self.land.is_some() || self.roads.is_some();
+        let sampled = self.fare_sampler.is_some() || self.tip_sampler.is_some();
+        self.projection.contains_any([10, 11])
+            || (moved && self.projection.contains_any(5..=9))
+            || (sampled && self.projection.contains_any(6..=8))
+    }
+
+    /// Return the uniform value of the foreign key of `salt` of the trip
+    fn key_unit(&self, trip_key: i64, salt: u64) -> f64 {
+        hash_to_unit_u64(trip_key as u64, seeded_hash(salt, self.seed))
+    }
+
+    /// Creates a trip with the given key, and its route if the trips snap
+    /// to the roads
+    fn make_trip(&mut self, trip_key: i64) -> (Trip, Option<LineString>) {
         // generate customer key, taking into account customer mortality rate
         let mut customer_key = self.customer_key_random.next_value();
+        if let Some(sampler) = &self.customer_sampler {
+            customer_key = sampler.key(self.key_unit(trip_key, 0xC057_0E1C));
+        }
         let mut delta = 1;
         while customer_key % TripGenerator::CUSTOMER_MORTALITY as i64 == 0 {
             customer_key += delta;
@@ -1111,59 +1779,61 @@ impl TripGeneratorIterator {
             delta *= -1;
         }
 
-        let vehicle_key = self.vehicle_key_random.next_value();
-        let driver_key = DriverGeneratorIterator::select_driver(
-            vehicle_key,
-            self.trip_number,
-            self.scale_factor,
-        );
+        let mut vehicle_key = self.vehicle_key_random.next_value();
+        if let Some(sampler) = &self.vehicle_sampler {
+            vehicle_key = sampler.key(self.key_unit(trip_key, 0x7E81_C1E0));
+        }
+        let driver_key = match &self.driver_sampler {
+            Some(sampler) => sampler.key(self.key_unit(trip_key, 0xD21_7E20)),
+            None => DriverGeneratorIterator::select_driver(
+                vehicle_key,
+                self.trip_number,
+                self.referenced_driver_count,
+            ),
+        };
 
         let pickup_date_value = self.pickup_date_random.next_value();
         let pickup_time = self.pickup_time_random.next_value();
-        let pickup_date = TPCHDate::new_with_time(pickup_date_value, pickup_time);
+        let pickup_date = match &self.time_window {
+            Some(window) => window.timestamp(trip_key as u64, self.seed),
+            None => TPCHDate::new_with_time(pickup_date_value, pickup_time),
+        };
 
         // Get distance from KDE model (in miles with decimal precision)
-        let mut distance_value = self.distance_kde.generate(trip_key as u64);
+        let mut distance_value = self
+            .distance_kde
+            .generate(seeded_hash(trip_key as u64, self.seed));
         // Hard code distance precision to 8 decimal places
         distance_value = (distance_value * 100_000_000.0).round() / 100_000_000.0;
-        let distance = TPCHDecimal((distance_value * 100.0) as i64);
-
-        // Select continent based on trip_key and generate pickup location
-        let u = hash_to_unit_u64(trip_key as u64, 0xC0DEC0DE);
-        let idx = self
-            .continent_cdf
-            .iter()
-            .position(|t| u <= t.cdf)
-            .unwrap_or(self.continent_cdf.len() - 1);
-        let continent_affine = &self.continent_cdf[idx].m;
-
-        // Pickup
-        let pickuploc_geom = self.spatial_gen.generate(trip_key as u64, continent_affine);
-        let pickuploc: Point = pickuploc_geom
-            .try_into()
-            .expect("Failed to convert to point");
-
-        // Generate dropoff using angle and distance
-        let angle_seed = spider_seed_for_index(trip_key as u64, 1234);
-        let mut angle_rng = StdRng::seed_from_u64(angle_seed);
-        let angle: f64 = angle_rng.gen::<f64>() * std::f64::consts::TAU;
-
-        let mut dropoff_x = pickuploc.x() + distance_value * angle.cos();
-        dropoff_x = wrap_around_longitude(dropoff_x);
-
-        let mut dropoff_y = pickuploc.y() + distance_value * angle.sin();
-
-        // Hard code coordinate precision to 8 decimal places - millimeter level precision for WGS 84
-        dropoff_x = (dropoff_x * 100_000_000.0).round() / 100_000_000.0;
-        dropoff_y = (dropoff_y * 100_000_000.0).round() / 100_000_000.0;
-
-        let dropoffloc = Point::new(dropoff_x, dropoff_y);
+        let mut distance = TPCHDecimal((distance_value * 100.0) as i64);
+
+        let (pickuploc, dropoffloc, route) = if self.needs_locations() {
+            let (pickuploc, dropoffloc, route, moved_distance) =
+                self.make_locations(trip_key, distance_value);
+            distance_value = moved_distance;
+            distance = TPCHDecimal((distance_value * 100.0) as i64);
+            (pickuploc, dropoffloc, route)
+        } else {
+            (Point::default(), Point::default(), None)
+        };
 
-        let fare_per_mile = self.fare_per_mile_random.next_value() as f64;
+        // the random streams always advance, so the other columns do not
+        // depend on the distributions
+        let mut fare_per_mile = self.fare_per_mile_random.next_value() as i64;
+        if let Some(sampler) = &self.fare_sampler {
+            fare_per_mile =
+                sampler.sample_at(trip_key as u64, self.seed, pickuploc.x(), pickuploc.y());
+        }
+        let fare_per_mile = fare_per_mile as f64;
         let fare_value = (distance_value * fare_per_mile) / 100.0;
         let fare = TPCHDecimal((fare_value * 100.0) as i64); // Use 100.0 (float) instead of 100 (int)
 
-        let tip_percent = self.tip_percent_random.next_value() as f64; // Convert to f64
+        let mut tip_percent = self.tip_percent_random.next_value() as i64;
+        if let Some(sampler) = &self.tip_sampler {
+            tip_percent =
+                sampler.sample_at(trip_key as u64, self.seed, pickuploc.x(), pickuploc.y());
+        }
+        let tip_percent = tip_percent as f64;
         let tip_value = (fare_value * tip_percent) / 100.0; // Use 100.0 instead of 100
         let tip = TPCHDecimal((tip_value * 100.0) as i64); // Use 100.0 instead of 100
 
@@ -1174,31 +1844,38 @@ impl TripGeneratorIterator {
         let seconds_per_degree = 180000;
         let duration_seconds = (distance_value * seconds_per_degree as f64).round() as i32;
 
-        // Get hours and minutes from pickup time
-        let (pickup_hour, pickup_minute, pickup_second) = pickup_time;
-        let total_seconds = (pickup_hour as i32) * 3600
-            + (pickup_minute as i32) * 60
-            + (pickup_second as i32)
-            + duration_seconds;
-        let dropoff_hour = ((total_seconds / 3600) % 24) as u8;
-        let dropoff_minute = ((total_seconds % 3600) / 60) as u8;
-        let dropoff_second = (total_seconds % 60) as u8;
-        let day_delta = total_seconds / (24 * 3600);
-        let dropoff_day = pickup_date_value + day_delta;
-
-        // Ensure the dropoff day doesn't exceed the maximum date value
-        let bounded_dropoff_day = std::cmp::min(
-            dropoff_day,
-            dates::MIN_GENERATE_DATE + dates::TOTAL_DATE_RANGE - 1,
-        );
-        let dropoff_date = TPCHDate::new(
-            bounded_dropoff_day,
-            dropoff_hour,
-            dropoff_minute,
-            dropoff_second,
-        );
+        let dropoff_date = if self.time_window.is_some() {
+            // time windows are not bounded by the dates of the reference data
+            TPCHDate::from_unix_epoch_seconds(
+                pickup_date.to_unix_epoch_seconds() + duration_seconds as i64,
+            )
+        } else {
+            // Get hours and minutes from pickup time
+            let (pickup_hour, pickup_minute, pickup_second) = pickup_time;
+            let total_seconds = (pickup_hour as i32) * 3600
+                + (pickup_minute as i32) * 60
+                + (pickup_second as i32)
+                + duration_seconds;
+            let dropoff_hour = ((total_seconds / 3600) % 24) as u8;
+            let dropoff_minute = ((total_seconds % 3600) / 60) as u8;
+            let dropoff_second = (total_seconds % 60) as u8;
+            let day_delta = total_seconds / (24 * 3600);
+            let dropoff_day = pickup_date_value + day_delta;
+
+            // Ensure the dropoff day doesn't exceed the maximum date value
+            let bounded_dropoff_day = std::cmp::min(
+                dropoff_day,
+                dates::MIN_GENERATE_DATE + dates::TOTAL_DATE_RANGE - 1,
+            );
+            TPCHDate::new(
+                bounded_dropoff_day,
+                dropoff_hour,
+                dropoff_minute,
+                dropoff_second,
+            )
+        };
 
-        Trip {
+        let trip = Trip {
             t_tripkey: trip_key,
             t_custkey: customer_key,
             t_driverkey: driver_key,
@@ -1211,14 +1888,81 @@ impl TripGeneratorIterator {
             t_distance: distance,
             t_pickuploc: pickuploc,
             t_dropoffloc: dropoffloc,
-        }
+        };
+        (trip, route)
     }
-}
 
-impl Iterator for TripGeneratorIterator {
-    type Item = Trip;
+    /// Creates the pickup and dropoff locations of the trip with the given
+    /// key and distance, and its route if the trips snap to the roads
+    ///
+    /// Returns the distance moved to keep the dropoff on land or along the
+    /// route.
+    fn make_locations(
+        &self,
+        trip_key: i64,
+        mut distance_value: f64,
+    ) -> (Point, Point, Option<LineString>, f64) {
+        // Pickup
+        let mut pickuploc = trip_pickup_location(
+            &self.spatial_gen,
+            &self.continent_cdf,
+            self.land.as_deref(),
+            self.seed,
+            trip_key,
+        );
 
-    fn next(&mut self) -> Option<Self::Item> {
+        // Generate dropoff using angle and distance
+        let angle_seed = spider_seed_for_index(trip_key as u64, seeded_hash(1234, self.seed));
+        let mut angle_rng = StdRng::seed_from_u64(angle_seed);
+        let angle: f64 = angle_rng.gen::<f64>() * std::f64::consts::TAU;
+
+        let on_sphere = geography::geography();
+        let mut offset = (distance_value * angle.cos(), distance_value * angle.sin());
+        if on_sphere {
+            // the same distance on the ground in every direction, with the
+            // angle counterclockwise from east
+            let dropoff =
+                geography::destination(pickuploc.0, 90.0 - angle.to_degrees(), distance_value);
+            offset = (dropoff.x - pickuploc.x(), dropoff.y - pickuploc.y());
+        }
+
+        let mut dropoffloc = dropoff_location(pickuploc, offset);
+
+        // Move the dropoff towards the pickup until it is on land
+        if let Some(land) = &self.land {
+            for _ in 0..MAX_DROPOFF_HALVINGS {
+                if land.contains(dropoffloc) {
+                    break;
+                }
+                offset = (offset.0 / 2.0, offset.1 / 2.0);
+                distance_value = (distance_value * 50_000_000.0).round() / 100_000_000.0;
+                dropoffloc = dropoff_location(pickuploc, offset);
+            }
+        }
+
+        // Follow the roads from the intersection nearest to the pickup
+        let route = self
+            .roads
+            .as_ref()
+            .map(|roads| roads.route(pickuploc, offset));
+        if let Some(route) = &route {
+            pickuploc = Point(route.0[0]);
+            dropoffloc = Point(route.0[route.0.len() - 1]);
+            distance_value = if on_sphere {
+                geography::length(route)
+            } else {
+                route
+                    .lines()
+                    .map(|line| line.delta().x.hypot(line.delta().y))
+                    .sum()
+            };
+            distance_value = (distance_value * 100_000_000.0).round() / 100_000_000.0;
+        }
+        (pickuploc, dropoffloc, route, distance_value)
+    }
+
+    /// Returns the next trip, and its route if the trips snap to the roads
+    fn next_with_route(&mut self) -> Option<(Trip, Option<LineString>)> {
         if self.index >= self.row_count {
             return None;
         }
@@ -1241,22 +1985,1809 @@ impl Iterator for TripGeneratorIterator {
     }
 }
 
-/// Represents a building in the dataset
+impl Iterator for TripGeneratorIterator {
+    type Item = Trip;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_route().map(|(trip, _route)| trip)
+    }
+}
+
+/// The TRAJECTORY table: the path of the vehicle of each trip
+///
+/// A trajectory has a position every [`TrajectoryGenerator::SAMPLE_SECONDS`]
+/// seconds from the pickup to the dropoff of its trip, with at least the
+/// pickup and dropoff and at most [`TrajectoryGenerator::MAX_POINTS`]
+/// positions. The time of position `i` of `n` is
+/// `tr_starttime + i * (tr_endtime - tr_starttime) / (n - 1)`.
+///
+/// The Display trait is implemented to format the trajectory data as a
+/// string in the default SpatialBench 'tbl' format.
+///
+/// ```text
+/// 1|1997-07-24 06:58:22|1997-07-24 13:59:54|2|LINESTRING(21.2 8.0,21.1 7.8)|
+/// ```
 #[derive(Debug, Clone, PartialEq)]
-pub struct Building<'a> {
-    /// Unique identifier for the building
-    pub b_buildingkey: i64,
-    /// Name of the building
-    pub b_name: StringSequenceInstance<'a>,
-    /// WKT representation of the building's polygon
-    pub b_boundary: geo::Polygon,
+pub struct Trajectory {
+    /// Primary key, and foreign key to TRIP
+    pub tr_tripkey: i64,
+    /// Time of the first position, the pickup time of the trip
+    pub tr_starttime: TPCHDate,
+    /// Time of the last position, the dropoff time of the trip
+    pub tr_endtime: TPCHDate,
+    /// Number of positions
+    pub tr_numpoints: i32,
+    /// Positions from the pickup to the dropoff location of the trip
+    pub tr_path: LineString,
 }
 
-impl Display for Building<'_> {
+impl Trajectory {
+    /// Return the time of each position of the path, in seconds since the
+    /// Unix epoch
+    ///
+    /// The positions are sampled at regular times, from the start to the
+    /// end of the trip.
+    pub fn times(&self) -> impl Iterator<Item = i64> + '_ {
+        let start = self.tr_starttime.to_unix_epoch_seconds();
+        let seconds = self.tr_endtime.to_unix_epoch_seconds() - start;
+        let intervals = (self.tr_path.0.len() as i64 - 1).max(1);
+        (0..self.tr_path.0.len() as i64).map(move |i| start + seconds * i / intervals)
+    }
+}
+
+impl Display for Trajectory {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "{}|{}|{:?}|",
+            "{}|{}|{}|{}|{:?}|",
+            self.tr_tripkey, self.tr_starttime, self.tr_endtime, self.tr_numpoints, self.tr_path,
+        )
+    }
+}
+
+/// Generator for the [`Trajectory`]s of the trips of a [`TripGenerator`]
+///
+/// The trajectory table has one row per trip, in the same parts.
+#[derive(Debug, Clone)]
+pub struct TrajectoryGenerator {
+    trips: TripGenerator,
+}
+
+impl TrajectoryGenerator {
+    /// Seconds between the positions of a trajectory
+    pub const SAMPLE_SECONDS: i64 = 60;
+    /// Largest number of positions of a trajectory
+    pub const MAX_POINTS: i64 = 1000;
+
+    /// Creates a new TrajectoryGenerator with the given scale factor
+    pub fn new(scale_factor: f64, part: i32, part_count: i32) -> TrajectoryGenerator {
+        Self::from_trips(TripGenerator::new(scale_factor, part, part_count))
+    }
+
+    /// Creates a generator of the trajectories of the trips of `trips`
+    pub fn from_trips(trips: TripGenerator) -> TrajectoryGenerator {
+        Self { trips }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// See [`TripGenerator::with_seed`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.trips = self.trips.with_seed(seed);
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.trips.seed()
+    }
+
+    /// Overrides the number of rows of the tables, see [`RowCounts`]
+    ///
+    /// There is one trajectory per trip.
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.trips = self.trips.with_row_counts(row_counts);
+        self
+    }
+
+    /// Return the number of rows of this part, including overrides
+    pub fn row_count(&self) -> i64 {
+        self.trips.row_count()
+    }
+
+    /// Returns an iterator over the trajectory rows
+    pub fn iter(&self) -> TrajectoryGeneratorIterator {
+        TrajectoryGeneratorIterator {
+            seed: self.trips.seed,
+            trips: self.trips.iter(),
+            projection: Projection::default(),
+        }
+    }
+}
+
+impl IntoIterator for TrajectoryGenerator {
+    type Item = Trajectory;
+    type IntoIter = TrajectoryGeneratorIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator that generates Trajectory rows
+#[derive(Debug)]
+pub struct TrajectoryGeneratorIterator {
+    trips: TripGeneratorIterator,
+    seed: u64,
+    projection: Projection,
+}
+
+impl TrajectoryGeneratorIterator {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        // the key and times of the trips, and their locations for the path
+        let trips = if projection.contains(4) {
+            Projection::new([0, 4, 5, 10, 11])
+        } else {
+            Projection::new([0, 4, 5])
+        };
+        self.trips = self.trips.with_projection(trips);
+        self.projection = projection;
+        self
+    }
+
+    /// Creates the trajectory of `trip`, along its route if any
+    fn make_trajectory(&self, trip: Trip, route: Option<LineString>) -> Trajectory {
+        let start = trip.t_pickuptime.to_unix_epoch_seconds();
+        // dropoffs on the last generated day may wrap around to its start
+        let seconds = (trip.t_dropofftime.to_unix_epoch_seconds() - start).max(0);
+        let points = (seconds / TrajectoryGenerator::SAMPLE_SECONDS + 1)
+            .clamp(2, TrajectoryGenerator::MAX_POINTS);
+        let seed =
+            spider_seed_for_index(trip.t_tripkey as u64, seeded_hash(0x7E4C_0001, self.seed));
+        Trajectory {
+            tr_tripkey: trip.t_tripkey,
+            tr_starttime: trip.t_pickuptime,
+            tr_endtime: TPCHDate::from_unix_epoch_seconds(start + seconds),
+            tr_numpoints: points as i32,
+            tr_path: match route {
+                _ if !self.projection.contains(4) => LineString::new(vec![]),
+                Some(route) => route_path(&route, points as usize, seed),
+                None => trajectory_path(trip.t_pickuploc, trip.t_dropoffloc, points as usize, seed),
+            },
+        }
+    }
+}
+
+impl Iterator for TrajectoryGeneratorIterator {
+    type Item = Trajectory;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (trip, route) = self.trips.next_with_route()?;
+        Some(self.make_trajectory(trip, route))
+    }
+}
+
+/// The ROAD table: the streets of a synthetic road network
+///
+/// The network is a set of towns, each a grid of
+/// [`TOWN_BLOCKS`](road::TOWN_BLOCKS) blocks along each side with a street
+/// for every row and column of intersections, see [`RoadNetwork`]. The towns
+/// are centered on the pickup locations of the first trips, so the roads
+/// follow the spatial distribution of the trips.
+///
+/// The Display trait is implemented to format the road data as a string in
+/// the default SpatialBench 'tbl' format.
+///
+/// ```text
+/// 1|1|primary|LINESTRING(21.178513175 7.973649664,21.181977453 7.973570967,...)|
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Road {
+    /// Primary key
+    pub r_roadkey: i64,
+    /// Key of the town of the road, from 1
+    pub r_townkey: i64,
+    /// `primary` or `residential`
+    pub r_class: &'static str,
+    /// The street, through all the intersections with the cross streets
+    pub r_line: LineString,
+}
+
+impl Display for Road {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{:?}|",
+            self.r_roadkey, self.r_townkey, self.r_class, self.r_line,
+        )
+    }
+}
+
+/// Generator for the [`Road`] table
+///
+/// The trips snap to the same network with
+/// [`TripGenerator::with_snap_to_roads`].
+#[derive(Debug, Clone)]
+pub struct RoadGenerator {
+    scale_factor: f64,
+    part: i32,
+    part_count: i32,
+    seed: u64,
+    spatial_gen: SpatialGenerator,
+    continent_cdf: Vec<WeightedTarget>,
+}
+
+impl RoadGenerator {
+    /// Number of towns per scale factor, before skipping the towns that
+    /// would overlap other towns
+    pub const TOWNS_PER_SCALE_FACTOR: f64 = 1000.0;
+
+    /// Creates a new RoadGenerator with the given scale factor
+    pub fn new(scale_factor: f64, part: i32, part_count: i32) -> RoadGenerator {
+        RoadGenerator {
+            scale_factor,
+            part,
+            part_count,
+            seed: 0,
+            spatial_gen: spatial_overrides::trip_or_default(SpatialDefaults::trip_default),
+            continent_cdf: trip_continent_cdf(),
+        }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// See [`TripGenerator::with_seed`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Accepts the row count overrides of the other tables
+    ///
+    /// The number of roads only depends on the scale factor and the seed.
+    pub fn with_row_counts(self, _row_counts: RowCounts) -> Self {
+        self
+    }
+
+    /// Return the road network of the scale factor and seed
+    ///
+    /// The towns are on land in the mode set with [`landmass::set_on_land`],
+    /// like the trips.
+    pub fn network(&self) -> Arc<RoadNetwork> {
+        road_network(
+            self.scale_factor,
+            self.seed,
+            &self.spatial_gen,
+            &self.continent_cdf,
+            landmass::on_land(),
+        )
+    }
+
+    /// Return the number of rows of the whole table
+    pub fn road_count(&self) -> i64 {
+        self.network().street_count() as i64
+    }
+
+    /// Return the number of rows of this part
+    pub fn row_count(&self) -> i64 {
+        GenerateUtils::part_row_count(self.road_count(), self.part, self.part_count)
+    }
+
+    /// Returns an iterator over the road rows
+    pub fn iter(&self) -> RoadGeneratorIterator {
+        let network = self.network();
+        let start = GenerateUtils::part_start_index(
+            network.street_count() as i64,
+            self.part,
+            self.part_count,
+        );
+        RoadGeneratorIterator {
+            network,
+            index: start,
+            end: start + self.row_count(),
+            projection: Projection::default(),
+        }
+    }
+}
+
+impl IntoIterator for RoadGenerator {
+    type Item = Road;
+    type IntoIter = RoadGeneratorIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator that generates Road rows
+#[derive(Debug)]
+pub struct RoadGeneratorIterator {
+    network: Arc<RoadNetwork>,
+    index: i64,
+    end: i64,
+    projection: Projection,
+}
+
+impl RoadGeneratorIterator {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+}
+
+impl Iterator for RoadGeneratorIterator {
+    type Item = Road;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        let street = self.index as usize;
+        self.index += 1;
+        Some(Road {
+            r_roadkey: self.index,
+            r_townkey: (street / road::STREETS_PER_TOWN) as i64 + 1,
+            r_class: RoadNetwork::street_class(street),
+            r_line: if self.projection.contains(3) {
+                self.network.street(street)
+            } else {
+                LineString::new(vec![])
+            },
+        })
+    }
+}
+
+/// Returns the road network at `scale_factor` and `seed`
+///
+/// The towns are centered on the pickup locations of the first trips, on
+/// land if `on_land`. The last network is kept, as all the parts of the trip
+/// and road tables use it.
+fn road_network(
+    scale_factor: f64,
+    seed: u64,
+    spatial_gen: &SpatialGenerator,
+    continent_cdf: &[WeightedTarget],
+    on_land: bool,
+) -> Arc<RoadNetwork> {
+    /// The scale factor (as bits), seed and on land mode of a network
+    type Key = (u64, u64, bool);
+    static LAST: Mutex<Option<(Key, Arc<RoadNetwork>)>> = Mutex::new(None);
+    let key = (scale_factor.to_bits(), seed, on_land);
+    let mut last = LAST.lock().unwrap();
+    if let Some((last_key, network)) = last.as_ref() {
+        if *last_key == key {
+            return Arc::clone(network);
+        }
+    }
+    let towns = (RoadGenerator::TOWNS_PER_SCALE_FACTOR * scale_factor)
+        .round()
+        .max(1.0) as i64;
+    let spatial_gen = spatial_gen.with_seed(seed);
+    let land = on_land.then(|| landmass::land_mask(seed));
+    let centers: Vec<_> = (1..=towns)
+        .map(|trip_key| {
+            trip_pickup_location(&spatial_gen, continent_cdf, land.as_deref(), seed, trip_key)
+        })
+        .collect();
+    let network = Arc::new(RoadNetwork::new(&centers, seed));
+    *last = Some((key, Arc::clone(&network)));
+    network
+}
+
+/// The LANDMASS table: the land of the continents, a synthetic land/water
+/// mask
+///
+/// Each continent has a mainland filling most of its bounding box and 3 to
+/// [`MAX_ISLANDS`](landmass::MAX_ISLANDS) islands around it, with procedural
+/// coastlines, see [`LandMask`]. The trips stay on land with
+/// [`TripGenerator::with_on_land`].
+///
+/// The Display trait is implemented to format the landmass data as a string
+/// in the default SpatialBench 'tbl' format.
+///
+/// ```text
+/// 1|africa|8|MULTIPOLYGON(((48.44787009 -1.232502,...)))|
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Landmass {
+    /// Primary key
+    pub l_landmasskey: i64,
+    /// Name of the continent, e.g. `africa`
+    pub l_name: &'static str,
+    /// Number of islands, the polygons of the boundary after the mainland
+    pub l_islands: i64,
+    /// The mainland and the islands
+    pub l_boundary: MultiPolygon,
+}
+
+impl Display for Landmass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{:?}|",
+            self.l_landmasskey, self.l_name, self.l_islands, self.l_boundary,
+        )
+    }
+}
+
+/// Generator for the [`Landmass`] table
+#[derive(Debug, Clone)]
+pub struct LandmassGenerator {
+    part: i32,
+    part_count: i32,
+    seed: u64,
+}
+
+impl LandmassGenerator {
+    /// Creates a new LandmassGenerator
+    ///
+    /// The table has one row per continent at every scale factor.
+    pub fn new(_scale_factor: f64, part: i32, part_count: i32) -> LandmassGenerator {
+        LandmassGenerator {
+            part,
+            part_count,
+            seed: 0,
+        }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// See [`TripGenerator::with_seed`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Accepts the row count overrides of the other tables
+    ///
+    /// The number of landmasses is fixed.
+    pub fn with_row_counts(self, _row_counts: RowCounts) -> Self {
+        self
+    }
+
+    /// Return the land mask of the seed
+    pub fn land_mask(&self) -> Arc<LandMask> {
+        landmass::land_mask(self.seed)
+    }
+
+    /// Return the number of rows of the whole table
+    pub fn landmass_count() -> i64 {
+        landmass::CONTINENT_COUNT as i64
+    }
+
+    /// Return the number of rows of this part
+    pub fn row_count(&self) -> i64 {
+        GenerateUtils::part_row_count(Self::landmass_count(), self.part, self.part_count)
+    }
+
+    /// Returns an iterator over the landmass rows
+    pub fn iter(&self) -> LandmassGeneratorIterator {
+        let start =
+            GenerateUtils::part_start_index(Self::landmass_count(), self.part, self.part_count);
+        LandmassGeneratorIterator {
+            land: self.land_mask(),
+            index: start,
+            end: start + self.row_count(),
+            projection: Projection::default(),
+        }
+    }
+}
+
+impl IntoIterator for LandmassGenerator {
+    type Item = Landmass;
+    type IntoIter = LandmassGeneratorIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator that generates Landmass rows
+#[derive(Debug)]
+pub struct LandmassGeneratorIterator {
+    land: Arc<LandMask>,
+    index: i64,
+    end: i64,
+    projection: Projection,
+}
+
+impl LandmassGeneratorIterator {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+}
+
+impl Iterator for LandmassGeneratorIterator {
+    type Item = Landmass;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        let continent = &self.land.continents()[self.index as usize];
+        self.index += 1;
+        Some(Landmass {
+            l_landmasskey: self.index,
+            l_name: continent.name(),
+            l_islands: continent.island_count() as i64,
+            l_boundary: if self.projection.contains(3) {
+                continent.boundary()
+            } else {
+                MultiPolygon::new(vec![])
+            },
+        })
+    }
+}
+
+/// A Country name, formatted as `"Country#<n>"`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CountryName(i64);
+
+impl CountryName {
+    /// Creates a new CountryName with the given value
+    pub fn new(value: i64) -> Self {
+        CountryName(value)
+    }
+}
+
+impl Display for CountryName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Country#{:09}", self.0)
+    }
+}
+
+/// The COUNTRY table: the top level of the administrative hierarchy
+///
+/// The bounding box of each continent is split into 2 by 2 countries, which
+/// do not overlap, see [`AdminHierarchy`].
+///
+/// The Display trait is implemented to format the country data as a string
+/// in the default SpatialBench 'tbl' format.
+///
+/// ```text
+/// 1|Country#000000001|POLYGON((-20.062752 37.579421,...))|
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Country {
+    /// Primary key
+    pub co_countrykey: i64,
+    /// Name of the country, e.g. `Country#000000001`
+    pub co_name: TextValue<CountryName>,
+    /// Boundary of the country
+    pub co_boundary: Polygon,
+}
+
+impl Display for Country {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{:?}|",
+            self.co_countrykey, self.co_name, self.co_boundary,
+        )
+    }
+}
+
+/// Generator for [`Country`]s
+#[derive(Debug, Clone)]
+pub struct CountryGenerator {
+    part: i32,
+    part_count: i32,
+    seed: u64,
+    realistic_text: bool,
+}
+
+impl CountryGenerator {
+    /// Creates a new CountryGenerator
+    ///
+    /// The number of countries is the same at every scale factor.
+    pub fn new(_scale_factor: f64, part: i32, part_count: i32) -> CountryGenerator {
+        CountryGenerator {
+            part,
+            part_count,
+            seed: 0,
+            realistic_text: names::realistic_text(),
+        }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// See [`TripGenerator::with_seed`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Accepts the row count overrides of the other tables
+    ///
+    /// The number of countries is fixed.
+    pub fn with_row_counts(self, _row_counts: RowCounts) -> Self {
+        self
+    }
+
+    /// Makes up realistic names, such as `Dovria`, instead of
+    /// `Country#000000001`
+    ///
+    /// Defaults to the mode set with [`names::set_realistic_text`].
+    pub fn with_realistic_text(mut self, realistic_text: bool) -> Self {
+        self.realistic_text = realistic_text;
+        self
+    }
+
+    /// Return the number of rows of the whole table
+    pub fn country_count() -> i64 {
+        AdminHierarchy::new(0).count(AdminLevel::Country) as i64
+    }
+
+    /// Return the number of rows of this part
+    pub fn row_count(&self) -> i64 {
+        GenerateUtils::part_row_count(Self::country_count(), self.part, self.part_count)
+    }
+
+    /// Returns an iterator over the country rows
+    pub fn iter(&self) -> CountryGeneratorIterator {
+        let start =
+            GenerateUtils::part_start_index(Self::country_count(), self.part, self.part_count);
+        CountryGeneratorIterator {
+            hierarchy: AdminHierarchy::new(self.seed),
+            names: self
+                .realistic_text
+                .then(|| TextSynthesizer::new(self.seed, "country")),
+            index: start,
+            end: start + self.row_count(),
+            projection: Projection::default(),
+        }
+    }
+}
+
+impl IntoIterator for CountryGenerator {
+    type Item = Country;
+    type IntoIter = CountryGeneratorIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator that generates Country rows
+#[derive(Debug)]
+pub struct CountryGeneratorIterator {
+    hierarchy: AdminHierarchy,
+    /// Makes up the names instead of numbering them
+    names: Option<TextSynthesizer>,
+    index: i64,
+    end: i64,
+    projection: Projection,
+}
+
+impl CountryGeneratorIterator {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+}
+
+impl Iterator for CountryGeneratorIterator {
+    type Item = Country;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        let index = self.index as usize;
+        self.index += 1;
+        Some(Country {
+            co_countrykey: self.index,
+            co_name: match &self.names {
+                _ if !self.projection.contains(1) => TextValue::Realistic(String::new()),
+                Some(names) => TextValue::Realistic(names.country_name(self.index as u64)),
+                None => TextValue::Default(CountryName::new(self.index)),
+            },
+            co_boundary: if self.projection.contains(2) {
+                self.hierarchy.boundary(AdminLevel::Country, index)
+            } else {
+                Polygon::new(LineString::new(vec![]), vec![])
+            },
+        })
+    }
+}
+
+/// A Region name, formatted as `"Region#<n>"`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionName(i64);
+
+impl RegionName {
+    /// Creates a new RegionName with the given value
+    pub fn new(value: i64) -> Self {
+        RegionName(value)
+    }
+}
+
+impl Display for RegionName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Region#{:09}", self.0)
+    }
+}
+
+/// The REGION table: the countries subdivided into regions
+///
+/// Each country has 3 by 3 regions, and the union of the regions of a
+/// country is exactly the country, see [`AdminHierarchy`].
+///
+/// The Display trait is implemented to format the region data as a string
+/// in the default SpatialBench 'tbl' format.
+///
+/// ```text
+/// 1|1|Region#000000001|POLYGON((-20.062752 37.579421,...))|
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    /// Primary key
+    pub re_regionkey: i64,
+    /// Foreign key to the Country table, the country the region is part of
+    pub re_countrykey: i64,
+    /// Name of the region, e.g. `Region#000000001`
+    pub re_name: TextValue<RegionName>,
+    /// Boundary of the region
+    pub re_boundary: Polygon,
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{:?}|",
+            self.re_regionkey, self.re_countrykey, self.re_name, self.re_boundary,
+        )
+    }
+}
+
+/// Generator for [`Region`]s
+#[derive(Debug, Clone)]
+pub struct RegionGenerator {
+    part: i32,
+    part_count: i32,
+    seed: u64,
+    realistic_text: bool,
+}
+
+impl RegionGenerator {
+    /// Creates a new RegionGenerator
+    ///
+    /// The number of regions is the same at every scale factor.
+    pub fn new(_scale_factor: f64, part: i32, part_count: i32) -> RegionGenerator {
+        RegionGenerator {
+            part,
+            part_count,
+            seed: 0,
+            realistic_text: names::realistic_text(),
+        }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// See [`TripGenerator::with_seed`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Accepts the row count overrides of the other tables
+    ///
+    /// The number of regions is fixed.
+    pub fn with_row_counts(self, _row_counts: RowCounts) -> Self {
+        self
+    }
+
+    /// Makes up realistic names, such as `Port Talimo`, instead of
+    /// `Region#000000001`
+    ///
+    /// Defaults to the mode set with [`names::set_realistic_text`].
+    pub fn with_realistic_text(mut self, realistic_text: bool) -> Self {
+        self.realistic_text = realistic_text;
+        self
+    }
+
+    /// Return the number of rows of the whole table
+    pub fn region_count() -> i64 {
+        AdminHierarchy::new(0).count(AdminLevel::Region) as i64
+    }
+
+    /// Return the number of rows of this part
+    pub fn row_count(&self) -> i64 {
+        GenerateUtils::part_row_count(Self::region_count(), self.part, self.part_count)
+    }
+
+    /// Returns an iterator over the region rows
+    pub fn iter(&self) -> RegionGeneratorIterator {
+        let start =
+            GenerateUtils::part_start_index(Self::region_count(), self.part, self.part_count);
+        RegionGeneratorIterator {
+            hierarchy: AdminHierarchy::new(self.seed),
+            names: self
+                .realistic_text
+                .then(|| TextSynthesizer::new(self.seed, "region")),
+            index: start,
+            end: start + self.row_count(),
+            projection: Projection::default(),
+        }
+    }
+}
+
+impl IntoIterator for RegionGenerator {
+    type Item = Region;
+    type IntoIter = RegionGeneratorIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator that generates Region rows
+#[derive(Debug)]
+pub struct RegionGeneratorIterator {
+    hierarchy: AdminHierarchy,
+    /// Makes up the names instead of numbering them
+    names: Option<TextSynthesizer>,
+    index: i64,
+    end: i64,
+    projection: Projection,
+}
+
+impl RegionGeneratorIterator {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+}
+
+impl Iterator for RegionGeneratorIterator {
+    type Item = Region;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        let index = self.index as usize;
+        self.index += 1;
+        Some(Region {
+            re_regionkey: self.index,
+            re_countrykey: self
+                .hierarchy
+                .parent(AdminLevel::Region, index)
+                .expect("regions have a country") as i64
+                + 1,
+            re_name: match &self.names {
+                _ if !self.projection.contains(2) => TextValue::Realistic(String::new()),
+                Some(names) => TextValue::Realistic(names.place_name(self.index as u64)),
+                None => TextValue::Default(RegionName::new(self.index)),
+            },
+            re_boundary: if self.projection.contains(3) {
+                self.hierarchy.boundary(AdminLevel::Region, index)
+            } else {
+                Polygon::new(LineString::new(vec![]), vec![])
+            },
+        })
+    }
+}
+
+/// A City name, formatted as `"City#<n>"`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CityName(i64);
+
+impl CityName {
+    /// Creates a new CityName with the given value
+    pub fn new(value: i64) -> Self {
+        CityName(value)
+    }
+}
+
+impl Display for CityName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "City#{:09}", self.0)
+    }
+}
+
+/// The CITY table: the regions subdivided into cities
+///
+/// Each region has 4 by 4 cities, irregular quadrilaterals, and the union of
+/// the cities of a region is exactly the region, see [`AdminHierarchy`].
+///
+/// The Display trait is implemented to format the city data as a string in
+/// the default SpatialBench 'tbl' format.
+///
+/// ```text
+/// 1|1|City#000000001|POLYGON((-20.062752 37.579421,...))|
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct City {
+    /// Primary key
+    pub ci_citykey: i64,
+    /// Foreign key to the Region table, the region the city is part of
+    pub ci_regionkey: i64,
+    /// Name of the city, e.g. `City#000000001`
+    pub ci_name: TextValue<CityName>,
+    /// Boundary of the city
+    pub ci_boundary: Polygon,
+}
+
+impl Display for City {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{:?}|",
+            self.ci_citykey, self.ci_regionkey, self.ci_name, self.ci_boundary,
+        )
+    }
+}
+
+/// Generator for [`City`]s
+#[derive(Debug, Clone)]
+pub struct CityGenerator {
+    part: i32,
+    part_count: i32,
+    seed: u64,
+    realistic_text: bool,
+}
+
+impl CityGenerator {
+    /// Creates a new CityGenerator
+    ///
+    /// The number of cities is the same at every scale factor.
+    pub fn new(_scale_factor: f64, part: i32, part_count: i32) -> CityGenerator {
+        CityGenerator {
+            part,
+            part_count,
+            seed: 0,
+            realistic_text: names::realistic_text(),
+        }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// See [`TripGenerator::with_seed`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Accepts the row count overrides of the other tables
+    ///
+    /// The number of cities is fixed.
+    pub fn with_row_counts(self, _row_counts: RowCounts) -> Self {
+        self
+    }
+
+    /// Makes up realistic names, such as `Brenford`, instead of
+    /// `City#000000001`
+    ///
+    /// Defaults to the mode set with [`names::set_realistic_text`].
+    pub fn with_realistic_text(mut self, realistic_text: bool) -> Self {
+        self.realistic_text = realistic_text;
+        self
+    }
+
+    /// Return the number of rows of the whole table
+    pub fn city_count() -> i64 {
+        AdminHierarchy::new(0).count(AdminLevel::City) as i64
+    }
+
+    /// Return the number of rows of this part
+    pub fn row_count(&self) -> i64 {
+        GenerateUtils::part_row_count(Self::city_count(), self.part, self.part_count)
+    }
+
+    /// Returns an iterator over the city rows
+    pub fn iter(&self) -> CityGeneratorIterator {
+        let start = GenerateUtils::part_start_index(Self::city_count(), self.part, self.part_count);
+        CityGeneratorIterator {
+            hierarchy: AdminHierarchy::new(self.seed),
+            names: self
+                .realistic_text
+                .then(|| TextSynthesizer::new(self.seed, "city")),
+            index: start,
+            end: start + self.row_count(),
+            projection: Projection::default(),
+        }
+    }
+}
+
+impl IntoIterator for CityGenerator {
+    type Item = City;
+    type IntoIter = CityGeneratorIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator that generates City rows
+#[derive(Debug)]
+pub struct CityGeneratorIterator {
+    hierarchy: AdminHierarchy,
+    /// Makes up the names instead of numbering them
+    names: Option<TextSynthesizer>,
+    index: i64,
+    end: i64,
+    projection: Projection,
+}
+
+impl CityGeneratorIterator {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+}
+
+impl Iterator for CityGeneratorIterator {
+    type Item = City;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        let index = self.index as usize;
+        self.index += 1;
+        Some(City {
+            ci_citykey: self.index,
+            ci_regionkey: self
+                .hierarchy
+                .parent(AdminLevel::City, index)
+                .expect("cities have a region") as i64
+                + 1,
+            ci_name: match &self.names {
+                _ if !self.projection.contains(2) => TextValue::Realistic(String::new()),
+                Some(names) => TextValue::Realistic(names.place_name(self.index as u64)),
+                None => TextValue::Default(CityName::new(self.index)),
+            },
+            ci_boundary: if self.projection.contains(3) {
+                self.hierarchy.boundary(AdminLevel::City, index)
+            } else {
+                Polygon::new(LineString::new(vec![]), vec![])
+            },
+        })
+    }
+}
+
+/// A point of interest name, formatted as `"Poi#<n>"`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoiName(i64);
+
+impl PoiName {
+    /// Creates a new PoiName with the given value
+    pub fn new(value: i64) -> Self {
+        PoiName(value)
+    }
+}
+
+impl Display for PoiName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Poi#{:09}", self.0)
+    }
+}
+
+/// The POI table: points of interest clustered around the centers of the
+/// cities
+///
+/// Each POI is in one of the cities of the [`City`] table, normally
+/// distributed around its center, and has one of
+/// [`PoiGenerator::CATEGORY_COUNT`] categories, Zipf distributed so that a
+/// few categories are very common and most are rare.
+///
+/// The Display trait is implemented to format the POI data as a string in
+/// the default SpatialBench 'tbl' format.
+///
+/// ```text
+/// 1|1289|10|Poi#000000001|POINT(-66.52702035 -17.750612874)|
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Poi {
+    /// Primary key
+    pub p_poikey: i64,
+    /// Foreign key to the City table, the city the POI is in
+    pub p_citykey: i64,
+    /// Category code, from 1 (the most common) to
+    /// [`PoiGenerator::CATEGORY_COUNT`]
+    pub p_category: i64,
+    /// Name of the POI, e.g. `Poi#000000001`
+    pub p_name: TextValue<PoiName>,
+    /// Location of the POI
+    pub p_location: Point,
+}
+
+impl Display for Poi {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{}|{:?}|",
+            self.p_poikey, self.p_citykey, self.p_category, self.p_name, self.p_location,
+        )
+    }
+}
+
+/// Generator for [`Poi`]s
+#[derive(Debug, Clone)]
+pub struct PoiGenerator {
+    scale_factor: f64,
+    part: i32,
+    part_count: i32,
+    seed: u64,
+    row_counts: RowCounts,
+    attributes: AttributeDistributions,
+    key_skews: KeySkews,
+    realistic_text: bool,
+}
+
+impl PoiGenerator {
+    /// Base scale for POI generation
+    const SCALE_BASE: i32 = 100_000;
+    /// Number of categories of the POIs
+    pub const CATEGORY_COUNT: usize = 1000;
+    /// Exponent of the Zipf distribution of the categories, the frequency of
+    /// category `k` is proportional to `1 / k^s`
+    const CATEGORY_EXPONENT: f64 = 1.0;
+    /// Standard deviation of the distance of a POI from the center of its
+    /// city, relative to the size of the city
+    const CLUSTER_SIGMA: f64 = 0.125;
+    /// Number of locations drawn before falling back to a point of the city
+    const MAX_LOCATION_ATTEMPTS: u64 = 8;
+
+    /// Creates a new PoiGenerator with the given scale factor
+    pub fn new(scale_factor: f64, part: i32, part_count: i32) -> PoiGenerator {
+        PoiGenerator {
+            scale_factor,
+            part,
+            part_count,
+            seed: 0,
+            row_counts: RowCounts::default(),
+            attributes: attributes::attribute_distributions(),
+            key_skews: skew::key_skews(),
+            realistic_text: names::realistic_text(),
+        }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// See [`TripGenerator::with_seed`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Overrides the number of rows of the tables, see [`RowCounts`]
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Draws the categories from `attributes` instead of the Zipf
+    /// distribution of exponent 1
+    ///
+    /// Defaults to the distributions set with
+    /// [`attributes::set_attribute_distributions`].
+    pub fn with_attribute_distributions(mut self, attributes: AttributeDistributions) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Makes up realistic names, such as `Cedar Bakery`, instead of
+    /// `Poi#000000001`
+    ///
+    /// Defaults to the mode set with [`names::set_realistic_text`].
+    pub fn with_realistic_text(mut self, realistic_text: bool) -> Self {
+        self.realistic_text = realistic_text;
+        self
+    }
+
+    /// Places the POIs in the cities with the skew of
+    /// [`ForeignKey::City`] of `key_skews` instead of uniformly
+    ///
+    /// Defaults to the skews set with [`skew::set_key_skews`].
+    pub fn with_key_skews(mut self, key_skews: KeySkews) -> Self {
+        self.key_skews = key_skews;
+        self
+    }
+
+    /// Return the number of rows of this part, including overrides
+    pub fn row_count(&self) -> i64 {
+        let total_row_count = self.row_counts.poi_count(self.scale_factor);
+        GenerateUtils::part_row_count(total_row_count, self.part, self.part_count)
+    }
+
+    /// Returns an iterator over the POI rows
+    pub fn iter(&self) -> PoiGeneratorIterator {
+        let start = GenerateUtils::part_start_index(
+            self.row_counts.poi_count(self.scale_factor),
+            self.part,
+            self.part_count,
+        );
+        let weights = (1..=Self::CATEGORY_COUNT)
+            .map(|k| 1.0 / (k as f64).powf(Self::CATEGORY_EXPONENT))
+            .collect();
+        PoiGeneratorIterator {
+            hierarchy: AdminHierarchy::new(self.seed),
+            names: self
+                .realistic_text
+                .then(|| TextSynthesizer::new(self.seed, "poi")),
+            category_cdf: build_cdf_from_weights(weights),
+            category_sampler: self.attributes.sampler(Attribute::Category),
+            city_sampler: self
+                .key_skews
+                .sampler(ForeignKey::City, CityGenerator::city_count()),
+            seed: self.seed,
+            index: start,
+            end: start + self.row_count(),
+            projection: Projection::default(),
+        }
+    }
+}
+
+impl IntoIterator for PoiGenerator {
+    type Item = Poi;
+    type IntoIter = PoiGeneratorIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator that generates Poi rows
+#[derive(Debug)]
+pub struct PoiGeneratorIterator {
+    hierarchy: AdminHierarchy,
+    /// Makes up the names instead of numbering them
+    names: Option<TextSynthesizer>,
+    category_cdf: Vec<f64>,
+    /// The configured distribution of the categories, instead of the
+    /// default Zipf distribution
+    category_sampler: Option<AttributeSampler>,
+    /// The configured skew of the cities, instead of uniform
+    city_sampler: Option<KeySampler>,
+    seed: u64,
+    index: i64,
+    end: i64,
+    projection: Projection,
+}
+
+impl PoiGeneratorIterator {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Creates the POI with the given key
+    fn make_poi(&self, poi_key: i64) -> Poi {
+        let key = poi_key as u64;
+        let cities = self.hierarchy.count(AdminLevel::City);
+        let u = hash_to_unit_u64(key, seeded_hash(0x9011_C17E, self.seed));
+        let city = match &self.city_sampler {
+            Some(sampler) => sampler.key(u) as usize - 1,
+            None => ((u * cities as f64) as usize).min(cities - 1),
+        };
+        let u = hash_to_unit_u64(key, seeded_hash(0xCA7E_6021, self.seed));
+        let category = match &self.category_sampler {
+            Some(sampler) => sampler.sample(key, self.seed),
+            None => pick_from_cdf(&self.category_cdf, u) as i64 + 1,
+        };
+
+        let location = if self.projection.contains(4) {
+            self.locate(key, city)
+        } else {
+            Point::default()
+        };
+
+        Poi {
+            p_poikey: poi_key,
+            p_citykey: city as i64 + 1,
+            p_category: category,
+            p_name: match &self.names {
+                _ if !self.projection.contains(3) => TextValue::Realistic(String::new()),
+                Some(names) => TextValue::Realistic(names.poi_name(poi_key as u64)),
+                None => TextValue::Default(PoiName::new(poi_key)),
+            },
+            p_location: location,
+        }
+    }
+
+    /// Draws the location of the POI with the given key in `city`
+    fn locate(&self, key: u64, city: usize) -> Point {
+        // normally distributed around the center of the city, drawn again
+        // if outside of the city
+        let boundary = self.hierarchy.boundary(AdminLevel::City, city);
+        let rect = boundary.bounding_rect().expect("cities are not empty");
+        let center = boundary.centroid().expect("cities are not empty");
+        let (sigma_x, sigma_y) = (
+            rect.width() * PoiGenerator::CLUSTER_SIGMA,
+            rect.height() * PoiGenerator::CLUSTER_SIGMA,
+        );
+        (0..PoiGenerator::MAX_LOCATION_ATTEMPTS)
+            .map(|attempt| {
+                let salt = seeded_hash(0x9015_0000 + 2 * attempt, self.seed);
+                let u1 = 1.0 - hash_to_unit_u64(key, salt);
+                let u2 = hash_to_unit_u64(key, salt + 1);
+                let r = (-2.0 * u1.ln()).sqrt();
+                let angle = std::f64::consts::TAU * u2;
+                let (x, y) = snap_coordinates(
+                    center.x() + sigma_x * r * angle.cos(),
+                    center.y() + sigma_y * r * angle.sin(),
+                );
+                Point::new(x, y)
+            })
+            .find(|location| boundary.contains(location))
+            .unwrap_or_else(|| boundary.interior_point().expect("cities are not empty"))
+    }
+}
+
+impl Iterator for PoiGeneratorIterator {
+    type Item = Poi;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        self.index += 1;
+        Some(self.make_poi(self.index))
+    }
+}
+
+/// The RASTER table: a synthetic raster of elevation and temperature, one
+/// row per cell
+///
+/// The cells are the squares of a [`RasterGrid`] covering the world,
+/// numbered by row from the north west corner, for raster-vector joins of
+/// the other tables with the cells.
+///
+/// The Display trait is implemented to format the cell data as a string in
+/// the default SpatialBench 'tbl' format.
+///
+/// ```text
+/// 1|0|0|1114.43|-31.69|POLYGON((-180.0 89.196428571,-179.196428571 89.196428571,...))|
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raster {
+    /// Primary key
+    pub rs_cellkey: i64,
+    /// Row of the cell, from 0 at the north
+    pub rs_row: i64,
+    /// Column of the cell, from 0 at the antimeridian
+    pub rs_column: i64,
+    /// Elevation of the terrain at the center of the cell, in meters
+    pub rs_elevation: f64,
+    /// Temperature at the center of the cell, in degrees Celsius
+    pub rs_temperature: f64,
+    /// Square of the cell
+    pub rs_cell: Polygon,
+}
+
+impl Display for Raster {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{}|{}|{:?}|",
+            self.rs_cellkey,
+            self.rs_row,
+            self.rs_column,
+            self.rs_elevation,
+            self.rs_temperature,
+            self.rs_cell,
+        )
+    }
+}
+
+/// Generator for [`Raster`] cells
+#[derive(Debug, Clone)]
+pub struct RasterGenerator {
+    grid: RasterGrid,
+    part: i32,
+    part_count: i32,
+    seed: u64,
+}
+
+impl RasterGenerator {
+    /// Creates a new RasterGenerator with the given scale factor
+    pub fn new(scale_factor: f64, part: i32, part_count: i32) -> RasterGenerator {
+        RasterGenerator {
+            grid: RasterGrid::new(scale_factor),
+            part,
+            part_count,
+            seed: 0,
+        }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// See [`TripGenerator::with_seed`]. Only the temperatures depend on the
+    /// seed, the elevation is the same for every seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Accepts the row count overrides of the other tables
+    ///
+    /// The number of cells only depends on the scale factor.
+    pub fn with_row_counts(self, _row_counts: RowCounts) -> Self {
+        self
+    }
+
+    /// Return the number of rows of the whole table at `scale_factor`
+    pub fn raster_count(scale_factor: f64) -> i64 {
+        RasterGrid::new(scale_factor).cell_count() as i64
+    }
+
+    /// Return the number of rows of this part
+    pub fn row_count(&self) -> i64 {
+        let total_row_count = self.grid.cell_count() as i64;
+        GenerateUtils::part_row_count(total_row_count, self.part, self.part_count)
+    }
+
+    /// Returns an iterator over the raster rows
+    pub fn iter(&self) -> RasterGeneratorIterator {
+        let start = GenerateUtils::part_start_index(
+            self.grid.cell_count() as i64,
+            self.part,
+            self.part_count,
+        );
+        RasterGeneratorIterator {
+            grid: self.grid,
+            seed: self.seed,
+            index: start,
+            end: start + self.row_count(),
+            projection: Projection::default(),
+        }
+    }
+}
+
+impl IntoIterator for RasterGenerator {
+    type Item = Raster;
+    type IntoIter = RasterGeneratorIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator that generates Raster rows
+#[derive(Debug)]
+pub struct RasterGeneratorIterator {
+    grid: RasterGrid,
+    seed: u64,
+    index: i64,
+    end: i64,
+    projection: Projection,
+}
+
+impl RasterGeneratorIterator {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+}
+
+impl Iterator for RasterGeneratorIterator {
+    type Item = Raster;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        let index = self.index as usize;
+        self.index += 1;
+        let (row, column) = self.grid.position(index);
+        Some(Raster {
+            rs_cellkey: self.index,
+            rs_row: row as i64,
+            rs_column: column as i64,
+            rs_elevation: if self.projection.contains(3) {
+                self.grid.elevation(index)
+            } else {
+                0.0
+            },
+            rs_temperature: if self.projection.contains(4) {
+                self.grid.temperature(index, self.seed)
+            } else {
+                0.0
+            },
+            rs_cell: if self.projection.contains(5) {
+                self.grid.cell(index)
+            } else {
+                Polygon::new(LineString::new(vec![]), vec![])
+            },
+        })
+    }
+}
+
+/// The POINTCLOUD table: the returns of a synthetic LiDAR survey, with only
+/// the attributes of a point cloud
+///
+/// The points are in tiles of about a kilometer on the continents, see
+/// [`PointCloud`]. The coordinates are separate columns, as point clouds
+/// are usually loaded, rather than a geometry.
+///
+/// The Display trait is implemented to format the point data as a string in
+/// the default SpatialBench 'tbl' format.
+///
+/// ```text
+/// 1|-2.397813806|49.656005468|512.94|2183|2|
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointCloudPoint {
+    /// Primary key
+    pub pc_pointkey: i64,
+    /// Longitude
+    pub pc_x: f64,
+    /// Latitude
+    pub pc_y: f64,
+    /// Elevation, in meters
+    pub pc_z: f64,
+    /// Strength of the return, from 0 to 65535
+    pub pc_intensity: i32,
+    /// ASPRS classification: 2 ground, 3 low vegetation, 5 high vegetation,
+    /// 6 building or 9 water
+    pub pc_classification: i16,
+}
+
+impl Display for PointCloudPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{}|{}|{}|",
+            self.pc_pointkey,
+            self.pc_x,
+            self.pc_y,
+            self.pc_z,
+            self.pc_intensity,
+            self.pc_classification,
+        )
+    }
+}
+
+/// Generator for [`PointCloudPoint`]s
+#[derive(Debug, Clone)]
+pub struct PointCloudGenerator {
+    scale_factor: f64,
+    part: i32,
+    part_count: i32,
+    seed: u64,
+    row_counts: RowCounts,
+}
+
+impl PointCloudGenerator {
+    /// Base scale for the point cloud generation, 10 million points per
+    /// scale factor
+    pub const SCALE_BASE: i32 = 10_000_000;
+
+    /// Creates a new PointCloudGenerator with the given scale factor
+    pub fn new(scale_factor: f64, part: i32, part_count: i32) -> PointCloudGenerator {
+        PointCloudGenerator {
+            scale_factor,
+            part,
+            part_count,
+            seed: 0,
+            row_counts: RowCounts::default(),
+        }
+    }
+
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// See [`TripGenerator::with_seed`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Overrides the number of rows of the table, see [`RowCounts`]
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Return the number of rows of this part
+    pub fn row_count(&self) -> i64 {
+        let total_row_count = self.row_counts.pointcloud_count(self.scale_factor);
+        GenerateUtils::part_row_count(total_row_count, self.part, self.part_count)
+    }
+
+    /// Returns an iterator over the point rows
+    pub fn iter(&self) -> PointCloudGeneratorIterator {
+        let start = GenerateUtils::part_start_index(
+            self.row_counts.pointcloud_count(self.scale_factor),
+            self.part,
+            self.part_count,
+        );
+        PointCloudGeneratorIterator {
+            cloud: PointCloud::new(self.seed),
+            tile: None,
+            index: start,
+            end: start + self.row_count(),
+            projection: Projection::default(),
+        }
+    }
+}
+
+impl IntoIterator for PointCloudGenerator {
+    type Item = PointCloudPoint;
+    type IntoIter = PointCloudGeneratorIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The columns of consecutive points, see
+/// [`PointCloudGeneratorIterator::next_columns`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointCloudColumns {
+    pub pc_pointkey: Vec<i64>,
+    pub pc_x: Vec<f64>,
+    pub pc_y: Vec<f64>,
+    pub pc_z: Vec<f64>,
+    pub pc_intensity: Vec<i32>,
+    pub pc_classification: Vec<i16>,
+}
+
+impl PointCloudColumns {
+    /// Return the number of points
+    pub fn len(&self) -> usize {
+        self.pc_pointkey.len()
+    }
+
+    /// Return true if there are no points
+    pub fn is_empty(&self) -> bool {
+        self.pc_pointkey.is_empty()
+    }
+}
+
+/// Iterator that generates PointCloudPoint rows
+#[derive(Debug)]
+pub struct PointCloudGeneratorIterator {
+    cloud: PointCloud,
+    /// The tile of the last point and its origin
+    tile: Option<(usize, (f64, f64))>,
+    index: i64,
+    end: i64,
+    projection: Projection,
+}
+
+impl PointCloudGeneratorIterator {
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Return the key and the point of the next row
+    fn next_point(&mut self) -> Option<(i64, CloudPoint)> {
+        if self.index >= self.end {
+            return None;
+        }
+        let index = self.index as usize;
+        self.index += 1;
+        let tile = index / POINTS_PER_TILE;
+        let origin = match self.tile {
+            Some((current, origin)) if current == tile => origin,
+            _ => {
+                let origin = self.cloud.tile_origin(index);
+                self.tile = Some((tile, origin));
+                origin
+            }
+        };
+        let with_z = self.projection.contains(3);
+        Some((self.index, self.cloud.point_with_z(index, origin, with_z)))
+    }
+
+    /// Return the columns of the next `max_rows` points, or fewer at the
+    /// end of the part
+    ///
+    /// This is the fast path of the Arrow generator: the points are written
+    /// straight to the columns, without building the rows.
+    pub fn next_columns(&mut self, max_rows: usize) -> Option<PointCloudColumns> {
+        let rows = (self.end - self.index).clamp(0, max_rows as i64) as usize;
+        if rows == 0 {
+            return None;
+        }
+        let mut columns = PointCloudColumns {
+            pc_pointkey: Vec::with_capacity(rows),
+            pc_x: Vec::with_capacity(rows),
+            pc_y: Vec::with_capacity(rows),
+            pc_z: Vec::with_capacity(rows),
+            pc_intensity: Vec::with_capacity(rows),
+            pc_classification: Vec::with_capacity(rows),
+        };
+        for _ in 0..rows {
+            let (key, point) = self.next_point()?;
+            columns.pc_pointkey.push(key);
+            columns.pc_x.push(point.x);
+            columns.pc_y.push(point.y);
+            columns.pc_z.push(point.z);
+            columns.pc_intensity.push(point.intensity);
+            columns.pc_classification.push(point.classification);
+        }
+        Some(columns)
+    }
+}
+
+impl Iterator for PointCloudGeneratorIterator {
+    type Item = PointCloudPoint;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, point) = self.next_point()?;
+        Some(PointCloudPoint {
+            pc_pointkey: key,
+            pc_x: point.x,
+            pc_y: point.y,
+            pc_z: point.z,
+            pc_intensity: point.intensity,
+            pc_classification: point.classification,
+        })
+    }
+}
+
+/// Represents a building in the dataset
+#[derive(Debug, Clone, PartialEq)]
+pub struct Building<'a> {
+    /// Unique identifier for the building
+    pub b_buildingkey: i64,
+    /// Name of the building
+    pub b_name: StringSequenceInstance<'a>,
+    /// Boundary of the building, a polygon unless converted by a
+    /// [`GeometryMix`](crate::spatial::GeometryMix)
+    pub b_boundary: geo::Geometry,
+}
+
+impl Display for Building<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{:?}|",
             self.b_buildingkey, self.b_name, self.b_boundary,
         )
     }
@@ -1268,6 +3799,8 @@ pub struct BuildingGenerator<'a> {
     scale_factor: f64,
     part: i32,
     part_count: i32,
+    seed: u64,
+    row_counts: RowCounts,
     distributions: &'a Distributions,
     text_pool: &'a TextPool,
     spatial_gen: SpatialGenerator,
@@ -1316,6 +3849,8 @@ impl<'a> BuildingGenerator<'a> {
             scale_factor,
             part,
             part_count,
+            seed: 0,
+            row_counts: RowCounts::default(),
             distributions,
             text_pool,
             spatial_gen,
@@ -1323,6 +3858,37 @@ impl<'a> BuildingGenerator<'a> {
         }
     }
 
+    /// Sets the global seed the random streams of the rows are derived from
+    ///
+    /// The default seed `0` generates the reference data. See
+    /// [`derive_seed`] for details.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Overrides the number of rows of the tables, see [`RowCounts`]
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Return the number of rows of this part, including overrides
+    pub fn row_count(&self) -> i64 {
+        let total_row_count = self.row_counts.building_count(self.scale_factor);
+        GenerateUtils::part_row_count(total_row_count, self.part, self.part_count)
+    }
+
+    /// Return the generator of the building boundaries
+    pub fn spatial_generator(&self) -> &SpatialGenerator {
+        &self.spatial_gen
+    }
+
+    /// Return the global seed, see [`Self::with_seed`]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
     /// Return the row count for the given scale factor and generator part count
     pub fn calculate_row_count(scale_factor: f64, part: i32, part_count: i32) -> i64 {
         GenerateUtils::calculate_logarithmic_row_count(
@@ -1338,16 +3904,32 @@ impl<'a> BuildingGenerator<'a> {
         BuildingGeneratorIterator::new(
             self.distributions,
             self.text_pool,
-            GenerateUtils::calculate_start_index(
-                Self::SCALE_BASE,
-                self.scale_factor,
+            GenerateUtils::part_start_index(
+                self.row_counts.building_count(self.scale_factor),
                 self.part,
                 self.part_count,
             ),
-            Self::calculate_row_count(self.scale_factor, self.part, self.part_count),
-            self.spatial_gen.clone(),
+            self.row_count(),
+            self.spatial_gen.with_seed(self.seed),
+            self.continent_cdf.clone(),
+            self.seed,
+        )
+    }
+
+    /// Returns the building with `key`, the same as in the part that
+    /// generates it
+    pub fn building(&self, key: i64) -> Building<'a> {
+        BuildingGeneratorIterator::new(
+            self.distributions,
+            self.text_pool,
+            key - 1,
+            1,
+            self.spatial_gen.with_seed(self.seed),
             self.continent_cdf.clone(),
+            self.seed,
         )
+        .next()
+        .expect("the iterator has one row")
     }
 }
 
@@ -1366,6 +3948,8 @@ pub struct BuildingGeneratorIterator<'a> {
     name_random: RandomStringSequence<'a>,
     spatial_gen: SpatialGenerator,
     continent_cdf: Vec<WeightedTarget>,
+    seed: u64,
+    projection: Projection,
 
     start_index: i64,
     row_count: i64,
@@ -1380,14 +3964,15 @@ impl<'a> BuildingGeneratorIterator<'a> {
         row_count: i64,
         spatial_gen: SpatialGenerator,
         continent_cdf: Vec<WeightedTarget>,
+        seed: u64,
     ) -> Self {
         let mut name_random = RandomStringSequence::new(
-            709314158,
+            derive_seed(709314158, seed),
             BuildingGenerator::NAME_WORDS,
             distributions.part_colors(),
         );
         let mut wkt_random = RandomText::new(
-            804159733,
+            derive_seed(804159733, seed),
             text_pool,
             BuildingGenerator::COMMENT_AVERAGE_LENGTH as f64,
         );
@@ -1400,6 +3985,8 @@ impl<'a> BuildingGeneratorIterator<'a> {
             name_random,
             spatial_gen,
             continent_cdf,
+            seed,
+            projection: Projection::default(),
 
             start_index,
             row_count,
@@ -1408,12 +3995,36 @@ impl<'a> BuildingGeneratorIterator<'a> {
         }
     }
 
+    /// Only generate the columns of `projection`, see [`Projection`]
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
     /// Creates a part with the given key
     fn make_building(&mut self, building_key: i64) -> Building<'a> {
-        let name = self.name_random.next_value();
+        let name = if self.projection.contains(1) {
+            self.name_random.next_value()
+        } else {
+            StringSequenceInstance::default()
+        };
+        let boundary = if self.projection.contains(2) {
+            self.make_boundary(building_key)
+        } else {
+            Polygon::new(LineString::new(vec![]), vec![]).into()
+        };
+
+        Building {
+            b_buildingkey: building_key,
+            b_name: name,
+            b_boundary: boundary,
+        }
+    }
 
+    /// Creates the boundary of the building with the given key
+    fn make_boundary(&self, building_key: i64) -> geo::Geometry {
         // Select continent based on building_key
-        let u = hash_to_unit_u64(building_key as u64, 0xC0DEC0DE);
+        let u = hash_to_unit_u64(building_key as u64, seeded_hash(0xC0DEC0DE, self.seed));
         let idx = self
             .continent_cdf
             .iter()
@@ -1422,16 +4033,8 @@ impl<'a> BuildingGeneratorIterator<'a> {
         let continent_affine = &self.continent_cdf[idx].m;
 
         // Generate point in unit space [0,1]
-        let geom = self
-            .spatial_gen
-            .generate(building_key as u64, continent_affine);
-        let polygon: geo::Polygon = geom.try_into().expect("Failed to convert to polygon");
-
-        Building {
-            b_buildingkey: building_key,
-            b_name: name,
-            b_boundary: polygon,
-        }
+        self.spatial_gen
+            .generate(building_key as u64, continent_affine)
     }
 }
 
@@ -1456,6 +4059,7 @@ impl<'a> Iterator for BuildingGeneratorIterator<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use geo::CoordsIter;
     #[test]
     fn test_vehicle_generation() {
         // Create a generator with a small scale factor
@@ -1492,6 +4096,63 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_key_skews() {
+        let mut skews = KeySkews::default();
+        skews.set(ForeignKey::Customer, "hotspot:0.2,0.8".parse().unwrap());
+        skews.set(ForeignKey::City, "zipf:1.5".parse().unwrap());
+        let trips: Vec<_> = TripGenerator::new(0.01, 1, 1)
+            .with_key_skews(skews)
+            .iter()
+            .collect();
+        // 20% of the customers have about 80% of the trips
+        let hot = RowCounts::default().customer_count(0.01) / 5;
+        let hot_trips = trips.iter().filter(|trip| trip.t_custkey <= hot).count();
+        let share = hot_trips as f64 / trips.len() as f64;
+        assert!((0.78..0.82).contains(&share), "{share}");
+        // the other keys stay uniform
+        let default = TripGenerator::new(0.01, 1, 1).iter().next().unwrap();
+        assert_eq!(trips[0].t_vehiclekey, default.t_vehiclekey);
+
+        let pois: Vec<_> = PoiGenerator::new(0.01, 1, 1)
+            .with_key_skews(skews)
+            .iter()
+            .collect();
+        let first = pois.iter().filter(|poi| poi.p_citykey == 1).count();
+        assert!(first > pois.len() / 4, "{first}");
+    }
+
+    #[test]
+    fn test_realistic_text() {
+        let drivers: Vec<_> = DriverGenerator::new(0.01, 1, 1)
+            .with_realistic_text(true)
+            .iter()
+            .collect();
+        let default = DriverGenerator::new(0.01, 1, 1).iter().next().unwrap();
+        // only the address changes
+        assert_eq!(drivers[0].d_phone, default.d_phone);
+        assert_ne!(drivers[0].d_address, default.d_address);
+        assert!(drivers[0].d_address.to_string().contains(", "));
+
+        // the names only depend on the key
+        let cities: Vec<_> = CityGenerator::new(1.0, 1, 1)
+            .with_realistic_text(true)
+            .iter()
+            .collect();
+        let second_part: Vec<_> = CityGenerator::new(1.0, 2, 2)
+            .with_realistic_text(true)
+            .iter()
+            .collect();
+        assert_eq!(cities[cities.len() - 1], second_part[second_part.len() - 1]);
+        assert!(!cities[0].ci_name.to_string().starts_with("City#"));
+        let poi = PoiGenerator::new(0.01, 1, 1)
+            .with_realistic_text(true)
+            .iter()
+            .next()
+            .unwrap();
+        assert!(!poi.p_name.to_string().starts_with("Poi#"));
+    }
+
     #[test]
     fn test_customer_generation() {
         // Create a generator with a small scale factor
@@ -1564,6 +4225,39 @@ mod tests {
         let first = &trips[1];
         assert_eq!(first.t_tripkey, 2);
         assert_eq!(first.to_string(), "2|172|1|1|1997-12-24 08:47:14|1997-12-24 09:28:57|0.03|0.00|0.04|0.01|POINT(94.423867952 29.887250009)|POINT(94.43760277 29.88940658)|");
+
+        // The pickup location can be computed from the key alone
+        let pickups = TripPickupGenerator::new();
+        for trip in trips.iter().step_by(997) {
+            assert_eq!(pickups.pickup_location(trip.t_tripkey), trip.t_pickuploc);
+        }
+
+        // Any keys can be generated, also after the table
+        let keys = TripGenerator::new(0.01, 1, 1).with_keys(101..=200);
+        assert_eq!(keys.row_count(), 100);
+        assert_eq!(keys.iter().collect::<Vec<_>>(), trips[100..200]);
+        let after = TripGenerator::new(0.01, 1, 1).with_keys(60_001..=60_010);
+        let after: Vec<_> = after.iter().map(|trip| trip.t_tripkey).collect();
+        assert_eq!(after, (60_001..=60_010).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_fractional_scale_factor_referential_integrity() {
+        for scale_factor in [0.001, 0.0037, 0.011] {
+            let customers = CustomerGenerator::calculate_row_count(scale_factor, 1, 1);
+            let drivers = DriverGenerator::calculate_row_count(scale_factor, 1, 1);
+            let vehicles = VehicleGenerator::calculate_row_count(scale_factor, 1, 1);
+            assert!(vehicles >= 1 && drivers >= 1 && customers >= 1);
+
+            // trips generated in parts reference the same keys
+            for part in 1..=3 {
+                for trip in TripGenerator::new(scale_factor, part, 3).iter() {
+                    assert!((1..=customers).contains(&trip.t_custkey), "{trip}");
+                    assert!((1..=drivers).contains(&trip.t_driverkey), "{trip}");
+                    assert!((1..=vehicles).contains(&trip.t_vehiclekey), "{trip}");
+                }
+            }
+        }
     }
 
     #[test]
@@ -1589,6 +4283,434 @@ mod tests {
         // Check first Building
         let first = &buildings[1];
         assert_eq!(first.b_buildingkey, 2);
-        assert_eq!(first.to_string(), "2|blush|POLYGON((124.218033476 10.538071565,124.217919324 10.539075339,124.212486371 10.539913704,124.214352934 10.536014944,124.215762091 10.536069114,124.218033476 10.538071565))|")
+        assert_eq!(first.to_string(), "2|blush|POLYGON((124.218033476 10.538071565,124.217919324 10.539075339,124.212486371 10.539913704,124.214352934 10.536014944,124.215762091 10.536069114,124.218033476 10.538071565))|");
+
+        // any building can be generated on its own
+        assert_eq!(&generator.building(2), first);
+        assert_eq!(generator.building(571), buildings[570]);
+    }
+
+    #[test]
+    fn test_polygon_vertices() {
+        let spatial_gen = SpatialDefaults::building_default().with_polygon_vertices(40..=50);
+        let generator = BuildingGenerator::new_with_distributions_and_text_pool(
+            1.0,
+            1,
+            1,
+            Distributions::static_default(),
+            TextPool::get_or_init_default(),
+            spatial_gen,
+        );
+        let mut vertices = vec![];
+        for building in generator.iter().take(200) {
+            // the ring is closed by repeating the first vertex
+            vertices.push(building.b_boundary.coords_count() - 1);
+        }
+        assert!(
+            vertices.iter().all(|v| (40..=50).contains(v)),
+            "{vertices:?}"
+        );
+        assert_eq!(vertices.iter().min(), Some(&40));
+        assert_eq!(vertices.iter().max(), Some(&50));
+
+        // a single vertex count
+        let spatial_gen = SpatialDefaults::building_default().with_polygon_vertices(4..=4);
+        let generator = BuildingGenerator::new_with_distributions_and_text_pool(
+            1.0,
+            1,
+            1,
+            Distributions::static_default(),
+            TextPool::get_or_init_default(),
+            spatial_gen,
+        );
+        for building in generator.iter().take(20) {
+            assert_eq!(building.b_boundary.coords_count(), 5, "{building}");
+        }
+    }
+
+    #[test]
+    fn test_geometry_mix() {
+        let mix = "polygon:0.5,multipolygon:0.5".parse().unwrap();
+        let spatial_gen = SpatialDefaults::building_default().with_geometry_mix(mix);
+        let generator = BuildingGenerator::new_with_distributions_and_text_pool(
+            1.0,
+            1,
+            1,
+            Distributions::static_default(),
+            TextPool::get_or_init_default(),
+            spatial_gen,
+        );
+        let mut multi = 0;
+        for (building, reference) in generator
+            .iter()
+            .zip(BuildingGenerator::new(1.0, 1, 1).iter())
+        {
+            match &building.b_boundary {
+                // the first part is the polygon generated without a mix
+                geo::Geometry::MultiPolygon(polygons) => {
+                    assert_eq!(
+                        geo::Geometry::Polygon(polygons.0[0].clone()),
+                        reference.b_boundary
+                    );
+                    multi += 1;
+                }
+                boundary => assert_eq!(boundary, &reference.b_boundary),
+            }
+        }
+        assert!((9_990..=10_010).contains(&multi), "{multi}");
+    }
+
+    #[test]
+    fn test_seed() {
+        let trips = |seed, part, part_count| -> Vec<String> {
+            TripGenerator::new(0.001, part, part_count)
+                .with_seed(seed)
+                .iter()
+                .map(|trip| trip.to_string())
+                .collect()
+        };
+
+        // seed 0 generates the reference data
+        assert_eq!(
+            trips(0, 1, 1),
+            TripGenerator::new(0.001, 1, 1)
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+        );
+        // the same seed generates the same data, independent of the parts
+        let seeded = trips(42, 1, 1);
+        assert_eq!(
+            seeded,
+            [trips(42, 1, 3), trips(42, 2, 3), trips(42, 3, 3)].concat()
+        );
+        // other seeds generate other data
+        assert_ne!(seeded, trips(0, 1, 1));
+        assert_ne!(seeded, trips(43, 1, 1));
+
+        let buildings = |seed| -> Vec<String> {
+            BuildingGenerator::new(1.0, 1, 1)
+                .with_seed(seed)
+                .iter()
+                .take(100)
+                .map(|building| building.to_string())
+                .collect()
+        };
+        assert_eq!(buildings(7), buildings(7));
+        assert_ne!(buildings(7), buildings(0));
+    }
+
+    #[test]
+    fn test_row_counts() {
+        let row_counts = RowCounts {
+            customer: Some(7),
+            driver: Some(5000),
+            trip: Some(1000),
+            building: Some(30),
+            ..Default::default()
+        };
+        assert_eq!(
+            row_counts.to_string(),
+            "driver=5000,customer=7,trip=1000,building=30"
+        );
+        assert_eq!(row_counts.vehicle_count(0.1), 10);
+
+        // the parts of the trips add up to the override and reference existing keys
+        let mut max_driver_key = 0;
+        let mut trip_count = 0;
+        for part in 1..=3 {
+            let generator = TripGenerator::new(0.1, part, 3).with_row_counts(row_counts);
+            let trips: Vec<_> = generator.iter().collect();
+            assert_eq!(trips.len() as i64, generator.row_count());
+            for trip in trips {
+                assert!((1..=7).contains(&trip.t_custkey), "{trip}");
+                assert!((1..=5000).contains(&trip.t_driverkey), "{trip}");
+                assert!((1..=10).contains(&trip.t_vehiclekey), "{trip}");
+                max_driver_key = max_driver_key.max(trip.t_driverkey);
+                trip_count += 1;
+            }
+        }
+        assert_eq!(trip_count, 1000);
+        assert!(max_driver_key > 10, "trips reference all the drivers");
+
+        let buildings = BuildingGenerator::new(0.1, 1, 1).with_row_counts(row_counts);
+        assert_eq!(buildings.iter().count(), 30);
+
+        // the default row counts generate the reference data
+        let trips = |row_counts| -> Vec<String> {
+            TripGenerator::new(0.01, 1, 1)
+                .with_row_counts(row_counts)
+                .iter()
+                .take(100)
+                .map(|trip| trip.to_string())
+                .collect()
+        };
+        assert_eq!(
+            trips(RowCounts::default()),
+            trips(RowCounts {
+                trip: Some(200),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_time_window() {
+        let start = temporal::parse_timestamp("2024-03-01").unwrap();
+        let end = temporal::parse_timestamp("2024-03-08").unwrap();
+        let window = TimeWindow::try_new(start, end)
+            .unwrap()
+            .with_pattern(temporal::TimePattern::Weekly);
+        let trips: Vec<_> = TripGenerator::new(0.001, 1, 1)
+            .with_time_window(window)
+            .iter()
+            .collect();
+        for trip in &trips {
+            let pickup = trip.t_pickuptime.to_unix_epoch_seconds();
+            assert!((start..end).contains(&pickup), "{trip}");
+            assert!(trip.t_pickuptime <= trip.t_dropofftime, "{trip}");
+        }
+        assert!(trips[0].to_string().contains("|2024-03-0"));
+
+        // the other columns are unchanged
+        let reference = TripGenerator::new(0.001, 1, 1).iter().next().unwrap();
+        assert_eq!(trips[0].t_pickuploc, reference.t_pickuploc);
+        assert_eq!(trips[0].t_fare, reference.t_fare);
+    }
+
+    #[test]
+    fn test_trajectory_generation() {
+        let trips: Vec<_> = TripGenerator::new(0.001, 1, 1).iter().collect();
+        let trajectories: Vec<_> = TrajectoryGenerator::new(0.001, 1, 1).iter().collect();
+        assert_eq!(trajectories.len(), trips.len());
+        for (trajectory, trip) in trajectories.iter().zip(&trips) {
+            assert_eq!(trajectory.tr_tripkey, trip.t_tripkey);
+            assert_eq!(trajectory.tr_starttime, trip.t_pickuptime);
+            assert_eq!(trajectory.tr_numpoints as usize, trajectory.tr_path.0.len());
+            assert_eq!(trajectory.tr_path.0[0], trip.t_pickuploc.0);
+            assert_eq!(trajectory.tr_path.0.last(), Some(&trip.t_dropoffloc.0));
+        }
+
+        // one position a minute
+        let trajectory = &trajectories[0];
+        let seconds = trajectory.tr_endtime.to_unix_epoch_seconds()
+            - trajectory.tr_starttime.to_unix_epoch_seconds();
+        assert_eq!(trajectory.tr_numpoints as i64, seconds / 60 + 1);
+        let times: Vec<_> = trajectory.times().collect();
+        assert_eq!(times.len(), trajectory.tr_path.0.len());
+        assert!((60..120).contains(&(times[1] - times[0])), "{times:?}");
+        assert_eq!(
+            times.last(),
+            Some(&trajectory.tr_endtime.to_unix_epoch_seconds())
+        );
+
+        // the parts have the same trajectories
+        let part: Vec<_> = TrajectoryGenerator::new(0.001, 2, 3).iter().collect();
+        let start = (part[0].tr_tripkey - 1) as usize;
+        assert_eq!(part, trajectories[start..start + part.len()]);
+    }
+
+    #[test]
+    fn test_snap_to_roads() {
+        let roads: Vec<_> = RoadGenerator::new(0.01, 1, 1).iter().collect();
+        assert_eq!(roads.len() % road::STREETS_PER_TOWN, 0);
+        assert_eq!(roads[0].r_roadkey, 1);
+        let part: Vec<_> = RoadGenerator::new(0.01, 2, 3).iter().collect();
+        let start = (part[0].r_roadkey - 1) as usize;
+        assert_eq!(part, roads[start..start + part.len()]);
+
+        // the trips start and end at intersections
+        let intersections: std::collections::HashSet<_> = roads
+            .iter()
+            .flat_map(|road| road.r_line.0.iter())
+            .map(|c| (c.x.to_bits(), c.y.to_bits()))
+            .collect();
+        let on_roads =
+            |point: Point| intersections.contains(&(point.x().to_bits(), point.y().to_bits()));
+        let generator = TripGenerator::new(0.01, 1, 100).with_snap_to_roads(true);
+        for trip in generator.iter() {
+            assert!(on_roads(trip.t_pickuploc), "{trip}");
+            assert!(on_roads(trip.t_dropoffloc), "{trip}");
+        }
+
+        // and the trajectories follow the roads
+        let trajectory = TrajectoryGenerator::from_trips(generator)
+            .iter()
+            .next()
+            .unwrap();
+        let reference = TrajectoryGenerator::new(0.01, 1, 100)
+            .iter()
+            .next()
+            .unwrap();
+        assert_ne!(trajectory.tr_path, reference.tr_path);
+        assert!(on_roads(Point(trajectory.tr_path.0[0])));
+    }
+
+    #[test]
+    fn test_on_land() {
+        let landmasses: Vec<_> = LandmassGenerator::new(1.0, 1, 1).iter().collect();
+        assert_eq!(landmasses.len() as i64, LandmassGenerator::landmass_count());
+        for (i, landmass) in landmasses.iter().enumerate() {
+            assert_eq!(landmass.l_landmasskey, i as i64 + 1);
+            assert_eq!(
+                landmass.l_boundary.0.len() as i64,
+                landmass.l_islands + 1,
+                "{landmass}"
+            );
+        }
+        assert_eq!(
+            LandmassGenerator::new(1.0, 1, 1).with_seed(1).iter().next(),
+            LandmassGenerator::new(1.0, 1, 1).with_seed(1).iter().next()
+        );
+
+        // the trips start on land, and all but a few end on land
+        let land = LandmassGenerator::new(1.0, 1, 1).land_mask();
+        let trips: Vec<_> = TripGenerator::new(0.01, 1, 10)
+            .with_on_land(true)
+            .iter()
+            .collect();
+        assert!(trips.iter().all(|trip| land.contains(trip.t_pickuploc)));
+        let on_land = trips
+            .iter()
+            .filter(|trip| land.contains(trip.t_dropoffloc))
+            .count();
+        assert!(
+            on_land * 100 >= trips.len() * 99,
+            "{on_land} of {}",
+            trips.len()
+        );
+        let reference = TripGenerator::new(0.01, 1, 10)
+            .iter()
+            .filter(|trip| land.contains(trip.t_pickuploc))
+            .count();
+        assert!(reference < trips.len(), "{reference}");
+    }
+
+    #[test]
+    fn test_admin_tables() {
+        let countries: Vec<_> = CountryGenerator::new(1.0, 1, 1).iter().collect();
+        let regions: Vec<_> = RegionGenerator::new(0.01, 1, 1).iter().collect();
+        let cities: Vec<_> = CityGenerator::new(1.0, 1, 1).iter().collect();
+        assert_eq!(countries.len() as i64, CountryGenerator::country_count());
+        assert_eq!(regions.len() as i64, RegionGenerator::region_count());
+        assert_eq!(cities.len() as i64, CityGenerator::city_count());
+        assert_eq!(countries[0].to_string()[..19], *"1|Country#000000001");
+
+        // every parent has the same number of children, in key order
+        for region in &regions {
+            assert_eq!(region.re_countrykey, (region.re_regionkey - 1) / 9 + 1);
+        }
+        for city in &cities {
+            assert_eq!(city.ci_regionkey, (city.ci_citykey - 1) / 16 + 1);
+        }
+
+        // the parts have the rows of the whole table
+        let part: Vec<_> = CityGenerator::new(1.0, 2, 3).iter().collect();
+        let start = (part[0].ci_citykey - 1) as usize;
+        assert_eq!(part, cities[start..start + part.len()]);
+        let seeded = CountryGenerator::new(1.0, 1, 1).with_seed(1).iter().next();
+        assert_ne!(seeded.as_ref(), countries.first());
+    }
+
+    #[test]
+    fn test_poi() {
+        let pois: Vec<_> = PoiGenerator::new(0.01, 1, 1).iter().collect();
+        assert_eq!(pois.len(), 1000);
+        assert_eq!(pois[0].to_string()[..17], *"1|1289|10|Poi#000");
+
+        // the POIs are in their city
+        let hierarchy = AdminHierarchy::new(0);
+        for poi in &pois {
+            let city = hierarchy.boundary(AdminLevel::City, poi.p_citykey as usize - 1);
+            assert!(city.contains(&poi.p_location), "{poi}");
+        }
+
+        // the first category is the most common
+        let mut counts = vec![0; PoiGenerator::CATEGORY_COUNT + 1];
+        for poi in &pois {
+            counts[poi.p_category as usize] += 1;
+        }
+        assert_eq!(counts[0], 0);
+        assert!(counts[2..].iter().all(|&count| count < counts[1]));
+
+        // the parts have the rows of the whole table
+        let part: Vec<_> = PoiGenerator::new(0.01, 2, 3).iter().collect();
+        let start = (part[0].p_poikey - 1) as usize;
+        assert_eq!(part, pois[start..start + part.len()]);
+        let row_counts = RowCounts {
+            poi: Some(10),
+            ..Default::default()
+        };
+        let pois = PoiGenerator::new(0.01, 1, 1).with_row_counts(row_counts);
+        assert_eq!(pois.iter().count(), 10);
+    }
+
+    #[test]
+    fn test_raster() {
+        let cells: Vec<_> = RasterGenerator::new(0.01, 1, 1).iter().collect();
+        assert_eq!(cells.len() as i64, RasterGenerator::raster_count(0.01));
+        assert_eq!(cells.len(), 71 * 142);
+        assert_eq!((cells[0].rs_row, cells[0].rs_column), (0, 0));
+        assert_eq!((cells[142].rs_row, cells[142].rs_column), (1, 0));
+
+        // the parts have the rows of the whole table
+        let part: Vec<_> = RasterGenerator::new(0.01, 2, 3).iter().collect();
+        let start = (part[0].rs_cellkey - 1) as usize;
+        assert_eq!(part, cells[start..start + part.len()]);
+        let parts: usize = (1..=3)
+            .map(|part| RasterGenerator::new(0.01, part, 3).iter().count())
+            .sum();
+        assert_eq!(parts, cells.len());
+
+        // the seed changes the temperature, not the cells or the elevation
+        let seeded: Vec<_> = RasterGenerator::new(0.01, 1, 1)
+            .with_seed(7)
+            .iter()
+            .collect();
+        assert!(cells
+            .iter()
+            .zip(&seeded)
+            .all(|(a, b)| a.rs_cell == b.rs_cell && a.rs_elevation == b.rs_elevation));
+        assert!(cells
+            .iter()
+            .zip(&seeded)
+            .any(|(a, b)| a.rs_temperature != b.rs_temperature));
+    }
+
+    #[test]
+    fn test_point_cloud() {
+        let generator = PointCloudGenerator::new(0.01, 1, 1);
+        let points: Vec<_> = generator.iter().collect();
+        assert_eq!(points.len(), 100_000);
+        assert_eq!(points[0].pc_pointkey, 1);
+
+        // the columns of the fast path are the rows
+        let mut iter = generator.iter();
+        let columns = iter.next_columns(1000).unwrap();
+        assert_eq!(columns.len(), 1000);
+        let rows: Vec<_> = (0..1000)
+            .map(|row| PointCloudPoint {
+                pc_pointkey: columns.pc_pointkey[row],
+                pc_x: columns.pc_x[row],
+                pc_y: columns.pc_y[row],
+                pc_z: columns.pc_z[row],
+                pc_intensity: columns.pc_intensity[row],
+                pc_classification: columns.pc_classification[row],
+            })
+            .collect();
+        assert_eq!(rows, points[..1000]);
+        assert_eq!(iter.next(), Some(points[1000]));
+
+        // the parts have the rows of the whole table
+        let part: Vec<_> = PointCloudGenerator::new(0.01, 2, 3).iter().collect();
+        let start = (part[0].pc_pointkey - 1) as usize;
+        assert_eq!(part, points[start..start + part.len()]);
+        let row_counts = RowCounts {
+            pointcloud: Some(10),
+            ..Default::default()
+        };
+        let mut iter = generator.with_row_counts(row_counts).iter();
+        assert_eq!(iter.next_columns(1000).unwrap().len(), 10);
+        assert_eq!(iter.next_columns(1000), None);
     }
 }
@@ -68,13 +68,19 @@
 //!
 //! If you want an easy way to generate the SpatialBench dataset for usage with external
 //! systems you can use CLI tool instead.
+pub mod attributes;
 pub mod csv;
 pub mod dates;
 pub mod decimal;
 pub mod distribution;
 pub mod generators;
 pub mod kde;
+pub mod names;
 pub mod q_and_a;
 pub mod random;
+pub mod refresh;
+pub mod skew;
 pub mod spatial;
+pub mod temporal;
 pub mod text;
+pub mod workload;
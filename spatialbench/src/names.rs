@@ -0,0 +1,363 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Realistic looking place names, street names and addresses
+//!
+//! The name columns of the tables are numbered, such as `City#000000001`,
+//! and the addresses random characters by default, which makes `LIKE`,
+//! regular expression and tokenization benchmarks unrealistic. A
+//! [`TextSynthesizer`] makes up names such as `Brenford`, `Port Talimo`,
+//! `Cedar Lane` or `Dovria` from syllables and word lists instead. Each name
+//! only depends on the key of its row and the seed, so the parts of a table
+//! can be generated independently.
+
+use crate::spatial::utils::hash_to_unit_u64;
+use once_cell::sync::OnceCell;
+use std::fmt;
+use std::fmt::Display;
+
+/// Consonants starting the syllables
+const ONSETS: [&str; 24] = [
+    "b", "br", "c", "ch", "d", "dr", "f", "g", "gr", "h", "k", "l", "m", "n", "p", "r", "s", "sh",
+    "st", "t", "th", "tr", "v", "w",
+];
+/// Vowels of the syllables
+const VOWELS: [&str; 10] = ["a", "e", "i", "o", "u", "a", "e", "o", "ai", "ou"];
+/// Consonants ending the last syllable, often none
+const CODAS: [&str; 12] = ["", "", "", "", "n", "r", "l", "s", "m", "nd", "rt", "x"];
+/// Endings of the town names
+const PLACE_SUFFIXES: [&str; 16] = [
+    "ton", "ford", "field", "ville", "burg", "dale", "wood", "port", "mouth", "bridge", "stead",
+    "ham", "by", "wick", "haven", "mont",
+];
+/// Words before the town names
+const PLACE_PREFIXES: [&str; 5] = ["New", "Port", "Lake", "Saint", "Fort"];
+/// Words after the town names
+const PLACE_QUALIFIERS: [&str; 5] = ["Springs", "Falls", "Heights", "Harbor", "Junction"];
+/// Endings of the country names
+const COUNTRY_SUFFIXES: [&str; 6] = ["ia", "land", "stan", "ora", "mark", "ania"];
+/// Common street names
+const STREET_WORDS: [&str; 26] = [
+    "Main", "High", "Church", "Mill", "Park", "Oak", "Maple", "Cedar", "Elm", "Pine", "Willow",
+    "Birch", "Lake", "River", "Hill", "Spring", "Station", "Market", "Sunset", "Highland",
+    "Meadow", "Forest", "Orchard", "Bridge", "Harbor", "Valley",
+];
+/// Types of the streets
+const STREET_TYPES: [&str; 10] = [
+    "Street",
+    "Avenue",
+    "Road",
+    "Lane",
+    "Drive",
+    "Boulevard",
+    "Way",
+    "Court",
+    "Place",
+    "Terrace",
+];
+/// Directions before some street names
+const DIRECTIONS: [&str; 4] = ["North", "South", "East", "West"];
+/// Kinds of the points of interest
+const POI_KINDS: [&str; 20] = [
+    "Cafe", "Market", "Pharmacy", "Bakery", "Hotel", "Museum", "Park", "Station", "Library",
+    "Clinic", "Garage", "Books", "Diner", "Gallery", "School", "Pub", "Grill", "Cinema",
+    "Pizzeria", "Gym",
+];
+/// Number of towns the addresses are in, so that the towns repeat like in
+/// real addresses
+const ADDRESS_TOWNS: u64 = 1000;
+
+/// Makes up the names of the rows of a table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextSynthesizer {
+    salt: u64,
+}
+
+impl TextSynthesizer {
+    /// Create the synthesizer of `table`, the names depend on the table and
+    /// `seed`
+    pub fn new(seed: u64, table: &str) -> Self {
+        // FNV-1a of the table name, so that the rows of the same key of two
+        // tables have different names
+        let table = table.bytes().fold(0xCBF2_9CE4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+        });
+        Self { salt: table ^ seed }
+    }
+
+    /// Return the name of a town or region, such as `Brenford`,
+    /// `Port Talimo` or `Drouvel Springs`
+    pub fn place_name(&self, key: u64) -> String {
+        place_name(&mut self.draws(key, 0x91AC))
+    }
+
+    /// Return the name of a country, such as `Dovria`
+    pub fn country_name(&self, key: u64) -> String {
+        let mut draws = self.draws(key, 0xC0_4E7);
+        let syllables = 1 + draws.index(2);
+        let mut name = stem(&mut draws, syllables);
+        name.push_str(draws.pick(&COUNTRY_SUFFIXES));
+        name
+    }
+
+    /// Return the name of a street, such as `Cedar Lane` or
+    /// `North Talimo Avenue`
+    pub fn street_name(&self, key: u64) -> String {
+        street_name(&mut self.draws(key, 0x57_12EE7))
+    }
+
+    /// Return a postal address, such as
+    /// `1428 Cedar Lane, Apt 12, Brenford 40213`
+    pub fn address(&self, key: u64) -> String {
+        let mut draws = self.draws(key, 0xADD2E55);
+        let number = draws.range(1, 9999);
+        let street = street_name(&mut draws);
+        let unit = if draws.chance(0.2) {
+            format!(", Apt {}", draws.range(1, 40))
+        } else {
+            String::new()
+        };
+        // the town of the address from a small set of towns
+        let town = self.place_name(draws.range(1, ADDRESS_TOWNS));
+        let postcode = draws.range(10000, 99999);
+        format!("{number} {street}{unit}, {town} {postcode}")
+    }
+
+    /// Return the name of a point of interest, such as `Cedar Bakery` or
+    /// `Talimo Books`
+    pub fn poi_name(&self, key: u64) -> String {
+        let mut draws = self.draws(key, 0x9017);
+        let name = if draws.chance(0.5) {
+            draws.pick(&STREET_WORDS).to_string()
+        } else {
+            root(&mut draws, 2)
+        };
+        format!("{name} {}", draws.pick(&POI_KINDS))
+    }
+
+    fn draws(&self, key: u64, salt: u64) -> Draws {
+        Draws {
+            key,
+            salt: self.salt ^ salt,
+            count: 0,
+        }
+    }
+}
+
+/// The random values of the name of a key
+struct Draws {
+    key: u64,
+    salt: u64,
+    count: u64,
+}
+
+impl Draws {
+    /// Upper bound of the number of values drawn for a name
+    const PER_KEY: u64 = 64;
+
+    fn unit(&mut self) -> f64 {
+        let value = self
+            .key
+            .wrapping_mul(Self::PER_KEY)
+            .wrapping_add(self.count);
+        self.count += 1;
+        hash_to_unit_u64(value, self.salt)
+    }
+
+    fn index(&mut self, len: usize) -> usize {
+        ((self.unit() * len as f64) as usize).min(len - 1)
+    }
+
+    fn pick(&mut self, words: &[&'static str]) -> &'static str {
+        words[self.index(words.len())]
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        self.unit() < probability
+    }
+
+    fn range(&mut self, min: u64, max: u64) -> u64 {
+        min + self.index((max - min + 1) as usize) as u64
+    }
+}
+
+/// Return a capitalized word of `syllables` syllables, a consonant and a
+/// vowel each, to add a suffix to
+fn stem(draws: &mut Draws, syllables: usize) -> String {
+    let mut word = String::new();
+    for _ in 0..syllables {
+        word.push_str(draws.pick(&ONSETS));
+        word.push_str(draws.pick(&VOWELS));
+    }
+    capitalize(&word)
+}
+
+/// Return a [`stem`], often with a final consonant
+fn root(draws: &mut Draws, syllables: usize) -> String {
+    let mut word = stem(draws, syllables);
+    word.push_str(draws.pick(&CODAS));
+    word
+}
+
+fn place_name(draws: &mut Draws) -> String {
+    let kind = draws.unit();
+    if kind < 0.5 {
+        let syllables = 1 + draws.index(2);
+        let mut name = stem(draws, syllables);
+        name.push_str(draws.pick(&PLACE_SUFFIXES));
+        name
+    } else if kind < 0.65 {
+        format!("{} {}", draws.pick(&PLACE_PREFIXES), root(draws, 2))
+    } else if kind < 0.8 {
+        format!("{} {}", root(draws, 2), draws.pick(&PLACE_QUALIFIERS))
+    } else {
+        let syllables = 2 + draws.index(2);
+        root(draws, syllables)
+    }
+}
+
+fn street_name(draws: &mut Draws) -> String {
+    let direction = if draws.chance(0.1) {
+        format!("{} ", draws.pick(&DIRECTIONS))
+    } else {
+        String::new()
+    };
+    let name = match draws.index(4) {
+        0 => root(draws, 2),
+        1 => ordinal(draws.range(1, 99)),
+        _ => draws.pick(&STREET_WORDS).to_string(),
+    };
+    format!("{direction}{name} {}", draws.pick(&STREET_TYPES))
+}
+
+/// Return `n` as an ordinal such as `1st` or `12th`
+fn ordinal(n: u64) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The value of a text column: the default one, or a realistic one made up
+/// by a [`TextSynthesizer`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextValue<T> {
+    Default(T),
+    Realistic(String),
+}
+
+impl<T: Display> Display for TextValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextValue::Default(value) => value.fmt(f),
+            TextValue::Realistic(text) => f.write_str(text),
+        }
+    }
+}
+
+static REALISTIC_TEXT: OnceCell<bool> = OnceCell::new();
+
+/// Make up realistic names and addresses in the generators created
+/// afterwards, unless they override it with `with_realistic_text`, see
+/// [`TextSynthesizer`]
+///
+/// Returns an error if it is already set to the other mode.
+pub fn set_realistic_text(realistic_text: bool) -> Result<(), String> {
+    if *REALISTIC_TEXT.get_or_init(|| realistic_text) != realistic_text {
+        return Err("the realistic text is already set to the other mode".to_string());
+    }
+    Ok(())
+}
+
+/// Return whether the names and addresses are realistic, see
+/// [`set_realistic_text`]
+pub fn realistic_text() -> bool {
+    REALISTIC_TEXT.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_text_synthesizer() {
+        let names = TextSynthesizer::new(0, "city");
+        assert_eq!(names.place_name(1), names.place_name(1));
+        assert_ne!(
+            TextSynthesizer::new(1, "city").place_name(1),
+            names.place_name(1)
+        );
+        assert_ne!(
+            TextSynthesizer::new(0, "region").place_name(1),
+            names.place_name(1)
+        );
+
+        // mostly distinct, capitalized words
+        let places: HashSet<String> = (0..1000).map(|key| names.place_name(key)).collect();
+        assert!(places.len() > 950, "{}", places.len());
+        for place in &places {
+            assert!(place
+                .split(' ')
+                .all(|word| word.chars().next().is_some_and(|c| c.is_ascii_uppercase())));
+        }
+
+        for key in 0..1000 {
+            let street = names.street_name(key);
+            assert!(
+                STREET_TYPES.iter().any(|kind| street.ends_with(kind)),
+                "{street}"
+            );
+            // number, street, town and postcode
+            let address = names.address(key);
+            let (number, _) = address.split_once(' ').unwrap();
+            assert!((1..=9999).contains(&number.parse::<u32>().unwrap()));
+            let (_, postcode) = address.rsplit_once(' ').unwrap();
+            assert!(
+                postcode.len() == 5 && postcode.parse::<u32>().is_ok(),
+                "{address}"
+            );
+            assert!(POI_KINDS
+                .iter()
+                .any(|kind| names.poi_name(key).ends_with(kind)));
+        }
+        assert_eq!(
+            [1, 2, 3, 4, 11, 12, 13, 21, 22, 23, 101].map(ordinal),
+            ["1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "23rd", "101st"]
+        );
+    }
+
+    #[test]
+    fn test_set_realistic_text() {
+        set_realistic_text(false).unwrap();
+        set_realistic_text(false).unwrap();
+        assert!(set_realistic_text(true).is_err());
+        assert!(!realistic_text());
+    }
+}
@@ -17,9 +17,23 @@
 
 //! Implementation of the core random number generators.
 
+use crate::spatial::utils::spider_seed_for_index;
 use crate::{distribution::Distribution, text::TextPool};
 use std::fmt::Display;
 
+/// Derives the seed of a random stream from its reference seed and a global seed
+///
+/// The global seed `0` keeps the reference seed, so the default data matches
+/// the reference data. Other global seeds give each stream an unrelated seed
+/// in `[1, 2^31 - 2]`, the valid seeds of [`RowRandomInt`].
+pub fn derive_seed(seed: i64, global_seed: u64) -> i64 {
+    if global_seed == 0 {
+        return seed;
+    }
+    let modulus = RowRandomInt::MODULUS as u64;
+    1 + (spider_seed_for_index(seed as u64, global_seed) % (modulus - 1)) as i64
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RowRandomInt {
     seed: i64,
@@ -0,0 +1,115 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Refresh sets of the trip table, like the refresh functions of TPC-H
+//!
+//! Each refresh set inserts new trips and deletes existing ones, about
+//! 0.1% of the trip table each, as the RF1 and RF2 functions of TPC-H
+//! insert and delete orders. The inserted trips have the keys after the
+//! table and the previous refresh sets, and the deleted keys are spread
+//! over the table and never deleted twice, so the refresh sets can be
+//! applied in order to benchmark incremental ingestion.
+
+use std::ops::RangeInclusive;
+
+/// Number of trips of the table for each trip inserted or deleted by a
+/// refresh set
+pub const REFRESH_DIVISOR: i64 = 1000;
+
+/// Largest number of refresh sets, which together delete each trip of the
+/// table at most once
+pub const MAX_REFRESHES: i64 = REFRESH_DIVISOR;
+
+/// The keys of the trips inserted and deleted by a refresh set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefreshSet {
+    /// Number of rows of the trip table
+    trip_count: i64,
+    /// Number of the refresh set, from 1
+    refresh: i64,
+}
+
+impl RefreshSet {
+    /// Create the refresh set `refresh` (from 1 to [`MAX_REFRESHES`]) of a
+    /// trip table of `trip_count` rows
+    ///
+    /// # Panics
+    /// If `refresh` is out of range
+    pub fn new(trip_count: i64, refresh: i64) -> Self {
+        assert!(
+            (1..=MAX_REFRESHES).contains(&refresh),
+            "refresh {refresh} out of 1..={MAX_REFRESHES}"
+        );
+        Self {
+            trip_count,
+            refresh,
+        }
+    }
+
+    /// Return the number of trips inserted, and deleted, by each refresh set
+    pub fn size(&self) -> i64 {
+        (self.trip_count / REFRESH_DIVISOR).max(1)
+    }
+
+    /// Return the keys of the inserted trips, after the keys of the table
+    /// and of the trips inserted by the previous refresh sets
+    pub fn inserted_keys(&self) -> RangeInclusive<i64> {
+        let first = self.trip_count + (self.refresh - 1) * self.size() + 1;
+        first..=first + self.size() - 1
+    }
+
+    /// Return the keys of the deleted trips of the table, every
+    /// [`REFRESH_DIVISOR`]th key from the number of the refresh set
+    pub fn deleted_keys(&self) -> impl Iterator<Item = i64> {
+        let (trip_count, refresh) = (self.trip_count, self.refresh);
+        (0..self.size())
+            .map(move |i| refresh + i * REFRESH_DIVISOR)
+            .filter(move |&key| key <= trip_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_refresh_sets() {
+        let sets: Vec<_> = (1..=MAX_REFRESHES)
+            .map(|refresh| RefreshSet::new(60_000, refresh))
+            .collect();
+        assert_eq!(sets[0].size(), 60);
+        assert_eq!(sets[0].inserted_keys(), 60_001..=60_060);
+        assert_eq!(sets[1].inserted_keys(), 60_061..=60_120);
+
+        // every trip of the table is deleted at most once
+        let mut deleted = HashSet::new();
+        for set in &sets {
+            let keys: Vec<_> = set.deleted_keys().collect();
+            assert_eq!(keys.len(), 60);
+            for key in keys {
+                assert!((1..=60_000).contains(&key));
+                assert!(deleted.insert(key), "{key} deleted twice");
+            }
+        }
+        assert_eq!(deleted.len(), 60_000);
+
+        let small = RefreshSet::new(10, 20);
+        assert_eq!(small.size(), 1);
+        assert_eq!(small.deleted_keys().count(), 0);
+    }
+}
@@ -0,0 +1,329 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Skew of the foreign keys
+//!
+//! The trips reference their customers, drivers and vehicles, and the POIs
+//! their cities, uniformly by default. A [`KeySkew`] makes some keys referenced
+//! much more often than others, such as 20% of the cities with 80% of the
+//! POIs, to benchmark how joins handle skew. The most referenced keys are
+//! the first ones, so the skew is known from the keys alone.
+
+use once_cell::sync::OnceCell;
+use std::fmt;
+use std::str::FromStr;
+
+/// The foreign key columns with a configurable skew
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignKey {
+    /// The customer of the trips (`t_custkey`)
+    Customer,
+    /// The driver of the trips (`t_driverkey`)
+    Driver,
+    /// The vehicle of the trips (`t_vehiclekey`)
+    Vehicle,
+    /// The city of the POIs (`p_citykey`)
+    City,
+}
+
+impl ForeignKey {
+    /// All the foreign keys
+    pub const ALL: [ForeignKey; 4] = [
+        ForeignKey::Customer,
+        ForeignKey::Driver,
+        ForeignKey::Vehicle,
+        ForeignKey::City,
+    ];
+
+    /// Return the name of the column, such as `t_custkey`
+    pub fn column(&self) -> &'static str {
+        match self {
+            ForeignKey::Customer => "t_custkey",
+            ForeignKey::Driver => "t_driverkey",
+            ForeignKey::Vehicle => "t_vehiclekey",
+            ForeignKey::City => "p_citykey",
+        }
+    }
+}
+
+impl fmt::Display for ForeignKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.column())
+    }
+}
+
+impl FromStr for ForeignKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ForeignKey::ALL
+            .into_iter()
+            .find(|key| key.column() == s.trim().to_ascii_lowercase())
+            .ok_or_else(|| {
+                format!(
+                    "Invalid foreign key {s}, expected t_custkey, t_driverkey, t_vehiclekey or p_citykey"
+                )
+            })
+    }
+}
+
+/// How often each key of the referenced table is referenced
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeySkew {
+    /// Every key is equally likely
+    Uniform,
+    /// The `k`-th key is referenced with a frequency proportional to
+    /// `1 / k^s`
+    Zipf { s: f64 },
+    /// The first `keys` fraction of the keys receive the `rows` fraction of
+    /// the references, such as 20% of the keys with 80% of the rows
+    Hotspot { keys: f64, rows: f64 },
+}
+
+impl fmt::Display for KeySkew {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySkew::Uniform => write!(f, "uniform"),
+            KeySkew::Zipf { s } => write!(f, "zipf:{s}"),
+            KeySkew::Hotspot { keys, rows } => write!(f, "hotspot:{keys},{rows}"),
+        }
+    }
+}
+
+impl FromStr for KeySkew {
+    type Err = String;
+
+    /// Parse a skew such as `uniform`, `zipf:1.1` or `hotspot:0.2,0.8`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || format!("Invalid key skew {s}, expected uniform, zipf:<s> or hotspot:<keys>,<rows>");
+        let (name, params) = s.trim().split_once(':').unwrap_or((s.trim(), ""));
+        let params: Vec<f64> = if params.is_empty() {
+            vec![]
+        } else {
+            params
+                .split(',')
+                .map(|param| param.trim().parse().map_err(|_| invalid()))
+                .collect::<Result<_, _>>()?
+        };
+        let skew = match (name.to_ascii_lowercase().as_str(), &params[..]) {
+            ("uniform", []) => KeySkew::Uniform,
+            ("zipf", &[s]) => KeySkew::Zipf { s },
+            ("hotspot", &[keys, rows]) => KeySkew::Hotspot { keys, rows },
+            _ => return Err(invalid()),
+        };
+        match skew {
+            KeySkew::Zipf { s: exponent } if !(exponent >= 0.0 && exponent.is_finite()) => Err(
+                format!("Invalid key skew {s}, the Zipf exponent must be at least 0"),
+            ),
+            KeySkew::Hotspot { keys, rows }
+                if !(keys > 0.0 && keys < 1.0 && rows > 0.0 && rows < 1.0) =>
+            {
+                Err(format!(
+                    "Invalid key skew {s}, the fractions of the keys and rows must be between 0 and 1"
+                ))
+            }
+            skew => Ok(skew),
+        }
+    }
+}
+
+/// The skew of one foreign key, parsed from `<column>=<skew>` such as
+/// `t_custkey=hotspot:0.2,0.8`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForeignKeySkew {
+    pub key: ForeignKey,
+    pub skew: KeySkew,
+}
+
+impl FromStr for ForeignKeySkew {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((key, skew)) = s.split_once('=') else {
+            return Err(format!(
+                "expected <column>=<skew>, e.g. t_custkey=hotspot:0.2,0.8, got '{s}'"
+            ));
+        };
+        Ok(ForeignKeySkew {
+            key: key.parse()?,
+            skew: skew.parse()?,
+        })
+    }
+}
+
+/// Draws the keys of `count` rows with a [`KeySkew`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeySampler {
+    skew: KeySkew,
+    count: i64,
+}
+
+impl KeySampler {
+    pub fn new(skew: KeySkew, count: i64) -> Self {
+        Self {
+            skew,
+            count: count.max(1),
+        }
+    }
+
+    /// Return the key, from 1 to the count, of the uniform value `u` in
+    /// [0, 1)
+    ///
+    /// The Zipf keys are drawn from the continuous approximation of the
+    /// distribution, so no table of the frequencies of the keys is needed
+    /// for the millions of customers of the large scale factors.
+    pub fn key(&self, u: f64) -> i64 {
+        let n = self.count as f64;
+        let rank = match self.skew {
+            KeySkew::Uniform => u * n,
+            KeySkew::Zipf { s } if (s - 1.0).abs() < 1e-9 => (n + 1.0).powf(u) - 1.0,
+            // the inverse of the cumulative frequency of 1 / x^s from 1 to
+            // n + 1
+            KeySkew::Zipf { s } => {
+                let e = 1.0 - s;
+                (1.0 + u * ((n + 1.0).powf(e) - 1.0)).powf(1.0 / e) - 1.0
+            }
+            KeySkew::Hotspot { keys, rows } => {
+                let hot = (keys * n).ceil().clamp(1.0, n);
+                if u < rows {
+                    u / rows * hot
+                } else {
+                    hot + (u - rows) / (1.0 - rows) * (n - hot)
+                }
+            }
+        };
+        (rank as i64).clamp(0, self.count - 1) + 1
+    }
+}
+
+/// The skews of the foreign keys that are not uniform
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KeySkews {
+    pub customer: Option<KeySkew>,
+    pub driver: Option<KeySkew>,
+    pub vehicle: Option<KeySkew>,
+    pub city: Option<KeySkew>,
+}
+
+impl KeySkews {
+    /// Return the skew of `key`, if it is configured
+    pub fn get(&self, key: ForeignKey) -> Option<KeySkew> {
+        match key {
+            ForeignKey::Customer => self.customer,
+            ForeignKey::Driver => self.driver,
+            ForeignKey::Vehicle => self.vehicle,
+            ForeignKey::City => self.city,
+        }
+    }
+
+    /// Set the skew of `key`
+    pub fn set(&mut self, key: ForeignKey, skew: KeySkew) {
+        let slot = match key {
+            ForeignKey::Customer => &mut self.customer,
+            ForeignKey::Driver => &mut self.driver,
+            ForeignKey::Vehicle => &mut self.vehicle,
+            ForeignKey::City => &mut self.city,
+        };
+        *slot = Some(skew);
+    }
+
+    /// Return the sampler of `key` among `count` rows, if its skew is
+    /// configured
+    pub fn sampler(&self, key: ForeignKey, count: i64) -> Option<KeySampler> {
+        self.get(key).map(|skew| KeySampler::new(skew, count))
+    }
+}
+
+static KEY_SKEWS: OnceCell<KeySkews> = OnceCell::new();
+
+/// Set the skews of the foreign keys of the generators created afterwards,
+/// unless they override them with `with_key_skews`
+///
+/// Returns an error if they are already set to other skews.
+pub fn set_key_skews(skews: KeySkews) -> Result<(), String> {
+    if *KEY_SKEWS.get_or_init(|| skews) != skews {
+        return Err("the key skews are already set to other skews".to_string());
+    }
+    Ok(())
+}
+
+/// Return the skews set with [`set_key_skews`], or none
+pub fn key_skews() -> KeySkews {
+    KEY_SKEWS.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_skew() {
+        for s in ["uniform", "zipf:1.1", "hotspot:0.2,0.8"] {
+            assert_eq!(s.parse::<KeySkew>().unwrap().to_string(), s);
+        }
+        let skew: ForeignKeySkew = "p_citykey=hotspot:0.2,0.8".parse().unwrap();
+        assert_eq!(skew.key, ForeignKey::City);
+        for (s, error) in [
+            ("t_custkey", "expected <column>=<skew>"),
+            ("c_custkey=uniform", "Invalid foreign key c_custkey"),
+            ("t_custkey=zipf", "expected uniform"),
+            ("t_custkey=zipf:-1", "at least 0"),
+            ("t_custkey=hotspot:0.2,1", "between 0 and 1"),
+        ] {
+            let err = s.parse::<ForeignKeySkew>().unwrap_err();
+            assert!(err.contains(error), "{s}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_key_sampler() {
+        let histogram = |skew: &str| {
+            let sampler = KeySampler::new(skew.parse().unwrap(), 100);
+            let mut counts = [0; 101];
+            for i in 0..100_000 {
+                counts[sampler.key((i as f64 + 0.5) / 100_000.0) as usize] += 1;
+            }
+            assert_eq!(counts[0], 0);
+            counts
+        };
+        let uniform = histogram("uniform");
+        assert!(uniform[1..].iter().all(|&count| count == 1000));
+        // 20% of the keys have 80% of the references
+        let hotspot = histogram("hotspot:0.2,0.8");
+        assert_eq!(hotspot[1..=20].iter().sum::<i32>(), 80_000);
+        assert!(hotspot[1..=20].iter().all(|&count| count == 4000));
+        // the frequencies fall with the rank
+        let zipf = histogram("zipf:1");
+        assert!(zipf[1] > 10 * zipf[20] && zipf[20] > zipf[100]);
+        let steep = histogram("zipf:2");
+        assert!(steep[1] > 40_000 && steep[1] > 3 * steep[2]);
+        assert_eq!(histogram("zipf:0"), uniform);
+        // a single key
+        assert_eq!(KeySampler::new(KeySkew::Zipf { s: 1.5 }, 1).key(0.99), 1);
+    }
+
+    #[test]
+    fn test_set_key_skews() {
+        set_key_skews(KeySkews::default()).unwrap();
+        set_key_skews(KeySkews::default()).unwrap();
+        let mut skews = KeySkews::default();
+        skews.set(ForeignKey::City, "zipf:1.1".parse().unwrap());
+        assert!(set_key_skews(skews).is_err());
+        assert_eq!(key_skews(), KeySkews::default());
+    }
+}
@@ -0,0 +1,243 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A synthetic administrative hierarchy: countries subdivided into regions
+//! subdivided into cities
+//!
+//! The bounding box of each continent is split into a grid of cities, whose
+//! vertices are moved randomly, so the cities are irregular quadrilaterals.
+//! A region is a block of cities and a country a block of regions, with the
+//! boundary following the vertices of the cities along the edge of the
+//! block. So the areas of each level are valid, do not overlap and cover
+//! the continents, and each area is exactly the union of its children.
+
+use crate::spatial::precision::snap_coordinates;
+use crate::spatial::utils::continent::build_continent_cdf;
+use crate::spatial::utils::{apply_affine, hash_to_unit_u64};
+use crate::spatial::ContinentAffines;
+use geo::{Coord, LineString, Polygon};
+
+/// Number of countries along each side of a continent
+pub const COUNTRIES_PER_SIDE: usize = 2;
+/// Number of regions along each side of a country
+pub const REGIONS_PER_SIDE: usize = 3;
+/// Number of cities along each side of a region
+pub const CITIES_PER_SIDE: usize = 4;
+
+/// Number of cities along each side of a continent
+const GRID: usize = COUNTRIES_PER_SIDE * REGIONS_PER_SIDE * CITIES_PER_SIDE;
+/// Largest distance a vertex is moved, relative to the side of a city.
+/// Less than half the side, so the cities stay simple polygons.
+const JITTER: f64 = 0.3;
+
+/// A level of the hierarchy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminLevel {
+    Country,
+    Region,
+    City,
+}
+
+impl AdminLevel {
+    /// Return the number of areas of the level on each continent
+    fn per_continent(&self) -> usize {
+        let countries = COUNTRIES_PER_SIDE * COUNTRIES_PER_SIDE;
+        let regions = countries * REGIONS_PER_SIDE * REGIONS_PER_SIDE;
+        match self {
+            AdminLevel::Country => countries,
+            AdminLevel::Region => regions,
+            AdminLevel::City => regions * CITIES_PER_SIDE * CITIES_PER_SIDE,
+        }
+    }
+
+    /// Return the number of children of an area of the level along each side
+    fn children_per_side(&self) -> usize {
+        match self {
+            AdminLevel::Country => REGIONS_PER_SIDE,
+            AdminLevel::Region => CITIES_PER_SIDE,
+            AdminLevel::City => 1,
+        }
+    }
+
+    /// Return the number of cities along each side of an area of the level
+    fn side(&self) -> usize {
+        match self {
+            AdminLevel::Country => REGIONS_PER_SIDE * CITIES_PER_SIDE,
+            AdminLevel::Region => CITIES_PER_SIDE,
+            AdminLevel::City => 1,
+        }
+    }
+
+    /// Return the column and row of the city at the lower left corner of
+    /// the area at `index` of the level, in the grid of its continent
+    fn origin(&self, index: usize) -> (usize, usize) {
+        let index = index % self.per_continent();
+        match self.parent() {
+            None => {
+                let (column, row) = (index % COUNTRIES_PER_SIDE, index / COUNTRIES_PER_SIDE);
+                (column * self.side(), row * self.side())
+            }
+            Some(parent) => {
+                let children = parent.children_per_side();
+                let (column, row) = parent.origin(index / (children * children));
+                let index = index % (children * children);
+                (
+                    column + index % children * self.side(),
+                    row + index / children * self.side(),
+                )
+            }
+        }
+    }
+
+    /// Return the parent level, if any
+    fn parent(&self) -> Option<AdminLevel> {
+        match self {
+            AdminLevel::Country => None,
+            AdminLevel::Region => Some(AdminLevel::Country),
+            AdminLevel::City => Some(AdminLevel::Region),
+        }
+    }
+}
+
+/// The administrative areas of all the continents
+#[derive(Debug, Clone)]
+pub struct AdminHierarchy {
+    /// Maps the unit square to the bounding box of each continent, in the
+    /// order of the continents the trips and buildings are generated in
+    affines: Vec<[f64; 6]>,
+    seed: u64,
+}
+
+impl AdminHierarchy {
+    /// Create the hierarchy, the boundaries only depend on `seed`
+    pub fn new(seed: u64) -> Self {
+        let affines = build_continent_cdf(&ContinentAffines::default())
+            .into_iter()
+            .map(|(_name, affine, _cdf)| affine)
+            .collect();
+        Self { affines, seed }
+    }
+
+    /// Return the number of areas of `level`
+    pub fn count(&self, level: AdminLevel) -> usize {
+        self.affines.len() * level.per_continent()
+    }
+
+    /// Return the index of the parent of the area at `index` of `level`, if
+    /// any
+    ///
+    /// The areas of each parent are consecutive, so the areas of a level are
+    /// in the order of their parents.
+    pub fn parent(&self, level: AdminLevel, index: usize) -> Option<usize> {
+        let parent = level.parent()?;
+        let children = parent.children_per_side();
+        Some(index / (children * children))
+    }
+
+    /// Return the boundary of the area at `index` of `level`
+    pub fn boundary(&self, level: AdminLevel, index: usize) -> Polygon {
+        let continent = index / level.per_continent();
+        let (column, row) = level.origin(index);
+        let side = level.side();
+        // the vertices along the edges of the block of cities,
+        // counterclockwise in the unit square
+        let ring = (0..side)
+            .map(|i| (column + i, row))
+            .chain((0..side).map(|j| (column + side, row + j)))
+            .chain((0..side).map(|i| (column + side - i, row + side)))
+            .chain((0..side).map(|j| (column, row + side - j)));
+        let affine = &self.affines[continent];
+        let mut coords: Vec<Coord> = ring
+            .map(|(i, j)| self.vertex(continent, i, j))
+            .map(|(x, y)| {
+                let (x, y) = apply_affine(x, y, affine);
+                let (x, y) = snap_coordinates(x, y);
+                Coord { x, y }
+            })
+            .collect();
+        coords.push(coords[0]);
+        // the affines of the continents flip the y axis
+        if affine[0] * affine[4] - affine[1] * affine[3] < 0.0 {
+            coords.reverse();
+        }
+        Polygon::new(LineString::new(coords), vec![])
+    }
+
+    /// Return the position of vertex `i`, `j` of the grid of cities of
+    /// `continent`, in the unit square of the continent
+    ///
+    /// The vertices on the edges of the continent only move along the edge,
+    /// so the areas cover the whole bounding box of the continent.
+    fn vertex(&self, continent: usize, i: usize, j: usize) -> (f64, f64) {
+        let key = ((continent * (GRID + 1) + i) * (GRID + 1) + j) as u64;
+        let jitter = |salt: u64, edge: bool| {
+            if edge {
+                0.0
+            } else {
+                JITTER * (2.0 * hash_to_unit_u64(2 * key + salt, self.seed ^ 0xAD_A1) - 1.0)
+            }
+        };
+        let x = (i as f64 + jitter(0, i == 0 || i == GRID)) / GRID as f64;
+        let y = (j as f64 + jitter(1, j == 0 || j == GRID)) / GRID as f64;
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Area, BooleanOps, MultiPolygon, Validation};
+
+    #[test]
+    fn test_admin_hierarchy() {
+        let hierarchy = AdminHierarchy::new(0);
+        assert_eq!(hierarchy.count(AdminLevel::Country), 32);
+        assert_eq!(hierarchy.count(AdminLevel::Region), 288);
+        assert_eq!(hierarchy.count(AdminLevel::City), 4608);
+        assert_eq!(hierarchy.parent(AdminLevel::Country, 5), None);
+        assert_eq!(hierarchy.parent(AdminLevel::Region, 9), Some(1));
+        assert_eq!(hierarchy.parent(AdminLevel::City, 15), Some(0));
+        assert_eq!(hierarchy.parent(AdminLevel::City, 16), Some(1));
+
+        // each area is the union of its children, and the children do not
+        // overlap
+        for (level, index) in [
+            (AdminLevel::Region, 0),
+            (AdminLevel::Region, 287),
+            (AdminLevel::Country, 13),
+        ] {
+            let child_level = match level {
+                AdminLevel::Country => AdminLevel::Region,
+                _ => AdminLevel::City,
+            };
+            let boundary = hierarchy.boundary(level, index);
+            assert!(boundary.is_valid());
+            assert!(boundary.signed_area() > 0.0, "counterclockwise");
+            let children: Vec<_> = (0..hierarchy.count(child_level))
+                .filter(|&child| hierarchy.parent(child_level, child) == Some(index))
+                .map(|child| hierarchy.boundary(child_level, child))
+                .collect();
+            let area: f64 = children.iter().map(|child| child.unsigned_area()).sum();
+            let union = children
+                .iter()
+                .fold(MultiPolygon::new(vec![]), |union, child| union.union(child));
+            assert!((union.unsigned_area() - area).abs() < 1e-6 * area);
+            assert!((boundary.unsigned_area() - area).abs() < 1e-6 * area);
+            assert!((union.xor(&boundary).unsigned_area()) < 1e-6 * area);
+        }
+    }
+}
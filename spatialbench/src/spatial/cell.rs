@@ -0,0 +1,298 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Cell indexes of the generated points: H3, S2 and geohash
+//!
+//! Benchmarks of cell based joins need the cell of each point. With a
+//! [`CellIndex`], the point columns of the trip and POI tables gain a column
+//! with the cell of each point at a resolution, computed from the longitude
+//! and latitude of the point while generating, whatever the CRS the points
+//! are written in.
+
+use h3o::{LatLng, Resolution};
+use once_cell::sync::OnceCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// The systems of cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CellSystem {
+    /// Uber H3 hexagons, resolutions 0 to 15
+    H3,
+    /// Google S2 cells, levels 0 to 30
+    S2,
+    /// Geohash strings, 1 to 12 characters
+    Geohash,
+}
+
+impl CellSystem {
+    /// Return the name of the system, such as `h3`
+    pub fn name(&self) -> &'static str {
+        match self {
+            CellSystem::H3 => "h3",
+            CellSystem::S2 => "s2",
+            CellSystem::Geohash => "geohash",
+        }
+    }
+
+    /// Return the resolutions of the system
+    fn resolutions(&self) -> std::ops::RangeInclusive<u8> {
+        match self {
+            CellSystem::H3 => 0..=15,
+            CellSystem::S2 => 0..=MAX_S2_LEVEL,
+            CellSystem::Geohash => 1..=12,
+        }
+    }
+}
+
+/// A cell index column: the cells of a system at a resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CellIndex {
+    pub system: CellSystem,
+    /// The H3 resolution, the S2 level or the number of characters of the
+    /// geohash
+    pub resolution: u8,
+}
+
+impl CellIndex {
+    /// Return the name of the column with the cells of `column`, such as
+    /// `t_pickuploc_h3_9`
+    pub fn column_name(&self, column: &str) -> String {
+        format!("{column}_{}_{}", self.system.name(), self.resolution)
+    }
+
+    /// Return the id of the H3 or S2 cell of the point at `lon`, `lat`
+    ///
+    /// The ids are the 64 bits of the cell ids of H3 and S2, as signed
+    /// integers for the engines without unsigned integers, so the S2 cells
+    /// of the faces 4 and 5 are negative.
+    ///
+    /// # Panics
+    /// If the system is [`CellSystem::Geohash`], whose cells are strings
+    pub fn cell_id(&self, lon: f64, lat: f64) -> i64 {
+        match self.system {
+            CellSystem::H3 => h3_cell(lon, lat, self.resolution) as i64,
+            CellSystem::S2 => s2_cell(lon, lat, self.resolution) as i64,
+            CellSystem::Geohash => panic!("geohashes are strings, not ids"),
+        }
+    }
+}
+
+impl fmt::Display for CellIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.system.name(), self.resolution)
+    }
+}
+
+impl FromStr for CellIndex {
+    type Err = String;
+
+    /// Parse a system and a resolution, such as `h3:9`, `s2:13` or
+    /// `geohash:7`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid cell index {s}, expected e.g. h3:9, s2:13 or geohash:7");
+        let (system, resolution) = s.trim().split_once(':').ok_or_else(invalid)?;
+        let system = match system.to_ascii_lowercase().as_str() {
+            "h3" => CellSystem::H3,
+            "s2" => CellSystem::S2,
+            "geohash" => CellSystem::Geohash,
+            _ => return Err(invalid()),
+        };
+        let resolution: u8 = resolution.parse().map_err(|_| invalid())?;
+        let resolutions = system.resolutions();
+        if !resolutions.contains(&resolution) {
+            return Err(format!(
+                "Invalid cell index {s}, the resolution of {} must be in {}..={}",
+                system.name(),
+                resolutions.start(),
+                resolutions.end()
+            ));
+        }
+        Ok(CellIndex { system, resolution })
+    }
+}
+
+/// Return the id of the H3 cell of the point at `lon`, `lat` at
+/// `resolution`
+pub fn h3_cell(lon: f64, lat: f64, resolution: u8) -> u64 {
+    let resolution = Resolution::try_from(resolution).expect("H3 resolutions are 0 to 15");
+    let point = LatLng::new(lat, lon).expect("points have finite coordinates");
+    u64::from(point.to_cell(resolution))
+}
+
+/// Largest S2 level, of the leaf cells
+const MAX_S2_LEVEL: u8 = 30;
+
+/// Position of the child cells of an S2 cell along the Hilbert curve, for
+/// each orientation of the curve and `i` and `j` bits of the child
+const S2_IJ_TO_POS: [[u64; 4]; 4] = [[0, 1, 3, 2], [0, 3, 1, 2], [2, 3, 1, 0], [2, 1, 3, 0]];
+/// The orientation of the curve is swapped (1) and inverted (2) in
+/// the child cell at each position
+const S2_POS_TO_ORIENTATION: [usize; 4] = [1, 0, 0, 3];
+
+/// Return the id of the S2 cell of the point at `lon`, `lat` at `level`
+pub fn s2_cell(lon: f64, lat: f64, level: u8) -> u64 {
+    let (lon, lat) = (lon.to_radians(), lat.to_radians());
+    let p = [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()];
+    // the face of the cube the point projects to, and its coordinates on
+    // the face
+    let axis = if p[0].abs() > p[1].abs() {
+        if p[0].abs() > p[2].abs() {
+            0
+        } else {
+            2
+        }
+    } else if p[1].abs() > p[2].abs() {
+        1
+    } else {
+        2
+    };
+    let face = if p[axis] < 0.0 { axis + 3 } else { axis };
+    let (u, v) = match face {
+        0 => (p[1] / p[0], p[2] / p[0]),
+        1 => (-p[0] / p[1], p[2] / p[1]),
+        2 => (-p[0] / p[2], -p[1] / p[2]),
+        3 => (p[2] / p[0], p[1] / p[0]),
+        4 => (p[2] / p[1], -p[0] / p[1]),
+        _ => (-p[1] / p[2], -p[0] / p[2]),
+    };
+    // the quadratic projection, which makes the cells of about equal area
+    let st = |u: f64| {
+        if u >= 0.0 {
+            0.5 * (1.0 + 3.0 * u).sqrt()
+        } else {
+            1.0 - 0.5 * (1.0 - 3.0 * u).sqrt()
+        }
+    };
+    let limit = 1u64 << MAX_S2_LEVEL;
+    let ij = |s: f64| ((limit as f64 * s).floor().max(0.0) as u64).min(limit - 1);
+    let (i, j) = (ij(st(u)), ij(st(v)));
+
+    // the position of the leaf cell along the Hilbert curve of the face
+    let mut orientation = face & 1;
+    let mut pos = 0;
+    for k in (0..MAX_S2_LEVEL).rev() {
+        let child = (((i >> k) & 1) << 1 | ((j >> k) & 1)) as usize;
+        let child_pos = S2_IJ_TO_POS[orientation][child];
+        orientation ^= S2_POS_TO_ORIENTATION[child_pos as usize];
+        pos = pos << 2 | child_pos;
+    }
+    let leaf = (face as u64) << 61 | pos << 1 | 1;
+    // the ancestor at `level`: the bits of the position below the level
+    // are replaced by a 1 followed by zeros
+    let lsb = 1u64 << (2 * (MAX_S2_LEVEL - level) as u32);
+    (leaf & lsb.wrapping_neg()) | lsb
+}
+
+/// The characters of the geohashes
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Return the geohash of the point at `lon`, `lat` with `length` characters
+pub fn geohash(lon: f64, lat: f64, length: u8) -> String {
+    let (mut lon_range, mut lat_range) = ((-180.0, 180.0), (-90.0, 90.0));
+    let mut hash = String::with_capacity(length as usize);
+    // the bits bisect the longitude and latitude in turn, from the
+    // longitude
+    let mut even = true;
+    for _ in 0..length {
+        let mut index = 0;
+        for _ in 0..5 {
+            let (range, value): (&mut (f64, f64), f64) = if even {
+                (&mut lon_range, lon)
+            } else {
+                (&mut lat_range, lat)
+            };
+            let middle = (range.0 + range.1) / 2.0;
+            index <<= 1;
+            if value >= middle {
+                index |= 1;
+                range.0 = middle;
+            } else {
+                range.1 = middle;
+            }
+            even = !even;
+        }
+        hash.push(GEOHASH_ALPHABET[index] as char);
+    }
+    hash
+}
+
+static CELL_INDEXES: OnceCell<Vec<CellIndex>> = OnceCell::new();
+
+/// Set the cell indexes of the points written by the Arrow generators
+///
+/// A repeated index is only added once. Only the first call has an effect.
+pub fn set_cell_indexes(mut indexes: Vec<CellIndex>) {
+    let mut seen = HashSet::new();
+    indexes.retain(|index| seen.insert(*index));
+    let _ = CELL_INDEXES.set(indexes);
+}
+
+/// Return the cell indexes set with [`set_cell_indexes`], or none
+pub fn cell_indexes() -> &'static [CellIndex] {
+    CELL_INDEXES.get().map_or(&[], Vec::as_slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cell_index() {
+        let index: CellIndex = "h3:9".parse().unwrap();
+        assert_eq!(
+            index,
+            CellIndex {
+                system: CellSystem::H3,
+                resolution: 9
+            }
+        );
+        assert_eq!(index.to_string(), "h3:9");
+        assert_eq!(index.column_name("p_location"), "p_location_h3_9");
+        assert_eq!("S2:30".parse::<CellIndex>().unwrap().to_string(), "s2:30");
+        for (s, error) in [
+            ("h3", "expected e.g. h3:9"),
+            ("quadkey:9", "expected e.g. h3:9"),
+            ("h3:16", "must be in 0..=15"),
+            ("geohash:0", "must be in 1..=12"),
+        ] {
+            let err = s.parse::<CellIndex>().unwrap_err();
+            assert!(err.contains(error), "{s}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_cells() {
+        // reference values of the H3, S2 and geohash libraries
+        assert_eq!(h3_cell(-122.4194, 37.7749, 9), 0x89283082803ffff);
+        assert_eq!(geohash(10.40744, 57.64911, 11), "u4pruydqqvj");
+        assert_eq!(geohash(-5.6, 42.6, 5), "ezs42");
+        assert_eq!(s2_cell(0.0, 0.0, 0), 0x1000000000000000);
+        assert_eq!(s2_cell(-122.4194, 37.7749, 13), 0x8085809c00000000);
+
+        // the cells of a point are nested
+        let (lon, lat) = (2.3522, 48.8566);
+        let leaf = s2_cell(lon, lat, 30);
+        for level in 0..30 {
+            let cell = s2_cell(lon, lat, level);
+            let lsb = cell & cell.wrapping_neg();
+            assert!(leaf > cell - lsb && leaf < cell + lsb, "level {level}");
+        }
+        assert!(geohash(lon, lat, 9).starts_with(&geohash(lon, lat, 5)));
+    }
+}
@@ -24,6 +24,7 @@ pub enum DistributionType {
     Bit,
     Thomas,
     HierarchicalThomas,
+    Coastline,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -74,6 +75,10 @@ pub enum DistributionParams {
         pareto_alpha_sub: f64,
         pareto_xm_sub: f64,
     },
+    Coastline {
+        // spread of the points on both sides of the coastline
+        sigma: f64,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -87,7 +92,9 @@ pub struct SpatialConfig {
     pub width: f64,
     pub height: f64,
 
-    // Polygon-specific
+    // Polygon-specific: the number of vertices is uniform in minseg..=maxseg
+    // (at least 3)
+    pub minseg: i32,
     pub maxseg: i32,
     pub polysize: f64,
 
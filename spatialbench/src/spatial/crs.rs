@@ -0,0 +1,415 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Coordinate reference system of the written geometries
+//!
+//! The generators produce WGS84 longitude / latitude coordinates. With a
+//! projected [`Crs`], the coordinates are projected when the geometries are
+//! written, so the spatial distributions are the same in every CRS.
+//!
+//! The projections are implemented here rather than with PROJ, so only the
+//! common projections of WGS84 are supported: Web Mercator, World Mercator
+//! and the UTM zones.
+
+use crate::spatial::utils::round_coordinate;
+use geo::Coord;
+use once_cell::sync::OnceCell;
+use std::collections::BTreeMap;
+use std::f64::consts::FRAC_PI_4;
+use std::fmt;
+use std::str::FromStr;
+
+/// Semi-major axis of the WGS84 ellipsoid, in meters
+const SEMI_MAJOR_AXIS: f64 = 6_378_137.0;
+
+/// Flattening of the WGS84 ellipsoid
+const FLATTENING: f64 = 1.0 / 298.257_223_563;
+
+/// Latitude beyond which Mercator projections are clamped, so that the
+/// Web Mercator world is a square
+const MAX_MERCATOR_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// Scale factor on the central meridian of the UTM zones
+const UTM_SCALE_FACTOR: f64 = 0.9996;
+
+/// Half the width of the UTM zones, in degrees of longitude
+const UTM_HALF_WIDTH: f64 = 3.0;
+
+/// False easting of the UTM zones, in meters
+const UTM_FALSE_EASTING: f64 = 500_000.0;
+
+/// False northing of the southern UTM zones, in meters
+const UTM_SOUTH_FALSE_NORTHING: f64 = 10_000_000.0;
+
+/// Projected coordinates are rounded to millimeters
+const PROJECTED_PRECISION: f64 = 1000.0;
+
+/// The coordinate reference system of the written geometries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Crs {
+    /// WGS84 longitude / latitude (`EPSG:4326` with longitude first)
+    #[default]
+    Wgs84,
+    /// Web Mercator (`EPSG:3857`), in meters on a sphere
+    WebMercator,
+    /// World Mercator (`EPSG:3395`), in meters on the WGS84 ellipsoid
+    WorldMercator,
+    /// A WGS84 UTM zone (`EPSG:326xx` north, `EPSG:327xx` south), in meters
+    Utm { zone: u8, south: bool },
+}
+
+impl Crs {
+    /// Return the EPSG code of this CRS
+    pub fn epsg_code(&self) -> u32 {
+        match self {
+            Crs::Wgs84 => 4326,
+            Crs::WebMercator => 3857,
+            Crs::WorldMercator => 3395,
+            Crs::Utm { zone, south: false } => 32600 + *zone as u32,
+            Crs::Utm { zone, south: true } => 32700 + *zone as u32,
+        }
+    }
+
+    /// Return the EPSG name of this CRS
+    pub fn name(&self) -> String {
+        match self {
+            Crs::Wgs84 => "WGS 84".to_string(),
+            Crs::WebMercator => "WGS 84 / Pseudo-Mercator".to_string(),
+            Crs::WorldMercator => "WGS 84 / World Mercator".to_string(),
+            Crs::Utm { zone, south } => {
+                format!("WGS 84 / UTM zone {zone}{}", if *south { 'S' } else { 'N' })
+            }
+        }
+    }
+
+    /// Return whether the coordinates are longitude / latitude degrees
+    pub fn is_geographic(&self) -> bool {
+        *self == Crs::Wgs84
+    }
+
+    /// Return the longitude of the central meridian of the projection
+    pub fn central_meridian(&self) -> f64 {
+        match self {
+            Crs::Utm { zone, .. } => *zone as f64 * 6.0 - 183.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Project the longitude / latitude `coord` to this CRS
+    ///
+    /// With a UTM zone, the longitudes outside the zone are clamped to its
+    /// edges: the transverse Mercator projection diverges 90° away from the
+    /// central meridian, so global data can not be projected to one zone.
+    pub fn project(&self, coord: Coord) -> Coord {
+        let (x, y) = match self {
+            Crs::Wgs84 => return coord,
+            Crs::WebMercator => {
+                let lat = coord.y.clamp(-MAX_MERCATOR_LATITUDE, MAX_MERCATOR_LATITUDE);
+                let y = (FRAC_PI_4 + lat.to_radians() / 2.0).tan().ln();
+                (coord.x.to_radians(), y)
+            }
+            Crs::WorldMercator => {
+                let lat = coord.y.clamp(-MAX_MERCATOR_LATITUDE, MAX_MERCATOR_LATITUDE);
+                (coord.x.to_radians(), isometric_latitude(lat.to_radians()))
+            }
+            Crs::Utm { south, .. } => {
+                let lon = (coord.x - self.central_meridian() + 540.0).rem_euclid(360.0) - 180.0;
+                let lon = lon.clamp(-UTM_HALF_WIDTH, UTM_HALF_WIDTH).to_radians();
+                let (x, y) = transverse_mercator(lon, coord.y.to_radians());
+                let false_northing = if *south {
+                    UTM_SOUTH_FALSE_NORTHING
+                } else {
+                    0.0
+                };
+                let x = UTM_FALSE_EASTING + UTM_SCALE_FACTOR * x;
+                let y = false_northing + UTM_SCALE_FACTOR * y;
+                return Coord {
+                    x: round_projected(x),
+                    y: round_projected(y),
+                };
+            }
+        };
+        Coord {
+            x: round_projected(SEMI_MAJOR_AXIS * x),
+            y: round_projected(SEMI_MAJOR_AXIS * y),
+        }
+    }
+
+    /// Return the extent of the coordinates in this CRS, as `[xmin, ymin,
+    /// xmax, ymax]`
+    ///
+    /// For the UTM zones, this is the extent of the zone, from 80°S to 84°N.
+    pub fn bounds(&self) -> [f64; 4] {
+        let corner = |x, y| self.project(Coord { x, y });
+        match self {
+            Crs::Wgs84 => [-180.0, -90.0, 180.0, 90.0],
+            Crs::WebMercator | Crs::WorldMercator => {
+                let max = corner(180.0, 90.0);
+                [-max.x, -max.y, max.x, max.y]
+            }
+            Crs::Utm { south, .. } => {
+                let lon = self.central_meridian();
+                let (xmin, xmax) = (
+                    corner(lon - UTM_HALF_WIDTH, 0.0).x,
+                    corner(lon + UTM_HALF_WIDTH, 0.0).x,
+                );
+                if *south {
+                    [xmin, corner(lon, -80.0).y, xmax, UTM_SOUTH_FALSE_NORTHING]
+                } else {
+                    [xmin, 0.0, xmax, corner(lon, 84.0).y]
+                }
+            }
+        }
+    }
+}
+
+/// Round a projected coordinate to millimeters
+fn round_projected(value: f64) -> f64 {
+    // adding zero turns the -0 of small negative values into 0
+    round_coordinate(value, PROJECTED_PRECISION) + 0.0
+}
+
+/// Return the isometric latitude of `lat` (radians) on the WGS84 ellipsoid
+fn isometric_latitude(lat: f64) -> f64 {
+    let e = (FLATTENING * (2.0 - FLATTENING)).sqrt();
+    let sin = lat.sin();
+    sin.atanh() - e * (e * sin).atanh()
+}
+
+/// Project `lon` (radians from the central meridian) and `lat` (radians)
+/// with the transverse Mercator projection of the WGS84 ellipsoid, with a
+/// scale factor of 1
+///
+/// Uses the series of Krüger in the third flattening, to the fourth order,
+/// which is accurate to less than a millimeter within the UTM zones.
+fn transverse_mercator(lon: f64, lat: f64) -> (f64, f64) {
+    let n = FLATTENING / (2.0 - FLATTENING);
+    let (n2, n3, n4) = (n * n, n * n * n, n * n * n * n);
+    let rectifying_radius = SEMI_MAJOR_AXIS / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0);
+    let alpha = [
+        n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0,
+        13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0,
+        61.0 * n3 / 240.0 - 103.0 * n4 / 140.0,
+        49561.0 * n4 / 161280.0,
+    ];
+
+    // conformal latitude, then the spherical transverse Mercator
+    let t = isometric_latitude(lat).sinh();
+    let xi = t.atan2(lon.cos());
+    let eta = (lon.sin() / (1.0 + t * t).sqrt()).atanh();
+
+    let (mut x, mut y) = (eta, xi);
+    for (j, alpha) in alpha.iter().enumerate() {
+        let k = 2.0 * (j + 1) as f64;
+        x += alpha * (k * xi).cos() * (k * eta).sinh();
+        y += alpha * (k * xi).sin() * (k * eta).cosh();
+    }
+    (rectifying_radius * x, rectifying_radius * y)
+}
+
+impl fmt::Display for Crs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EPSG:{}", self.epsg_code())
+    }
+}
+
+impl FromStr for Crs {
+    type Err = String;
+
+    /// Parse an `EPSG:code` (or `OGC:CRS84`) authority code, or a proj
+    /// string such as `+proj=utm +zone=33 +datum=WGS84`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with('+') {
+            return parse_proj_string(s);
+        }
+        let unsupported = || {
+            format!(
+                "Unsupported CRS {s}, expected EPSG:4326, EPSG:3857, EPSG:3395, a UTM zone \
+                 (EPSG:32601 to EPSG:32660 or EPSG:32701 to EPSG:32760) or a proj string"
+            )
+        };
+        if s.eq_ignore_ascii_case("OGC:CRS84") || s.eq_ignore_ascii_case("CRS84") {
+            return Ok(Crs::Wgs84);
+        }
+        let (authority, code) = s.split_once(':').ok_or_else(unsupported)?;
+        if !authority.eq_ignore_ascii_case("EPSG") {
+            return Err(unsupported());
+        }
+        match code.parse::<u32>().map_err(|_| unsupported())? {
+            4326 => Ok(Crs::Wgs84),
+            3857 => Ok(Crs::WebMercator),
+            3395 => Ok(Crs::WorldMercator),
+            code @ 32601..=32660 => Ok(Crs::Utm {
+                zone: (code - 32600) as u8,
+                south: false,
+            }),
+            code @ 32701..=32760 => Ok(Crs::Utm {
+                zone: (code - 32700) as u8,
+                south: true,
+            }),
+            _ => Err(unsupported()),
+        }
+    }
+}
+
+/// Parse a proj string of one of the supported projections
+fn parse_proj_string(s: &str) -> Result<Crs, String> {
+    let mut params = BTreeMap::new();
+    for token in s.split_whitespace() {
+        let token = token
+            .strip_prefix('+')
+            .ok_or_else(|| format!("Invalid proj string {s}: {token} does not start with +"))?;
+        let (key, value) = token.split_once('=').unwrap_or((token, ""));
+        params.insert(key, value);
+    }
+
+    for (&key, &value) in &params {
+        let default = match key {
+            "proj" | "zone" | "south" | "no_defs" | "type" | "wktext" | "nadgrids" | "a" | "b"
+            | "R" => continue,
+            "datum" | "ellps" if value == "WGS84" => continue,
+            "units" if value == "m" => continue,
+            "towgs84" if value.split(',').all(|v| v.parse() == Ok(0.0)) => continue,
+            "lat_0" | "lon_0" | "lat_ts" | "x_0" | "y_0" => 0.0,
+            "k" | "k_0" => 1.0,
+            _ => return Err(format!("Unsupported proj string {s}: +{key}={value}")),
+        };
+        if value.parse::<f64>() != Ok(default) {
+            return Err(format!(
+                "Unsupported proj string {s}: +{key} must be {default}"
+            ));
+        }
+    }
+
+    let radius = |key| params.get(key).map(|value| value.parse::<f64>());
+    let sphere = match (radius("R"), radius("a"), radius("b")) {
+        (None, None, None) => false,
+        (Some(Ok(SEMI_MAJOR_AXIS)), None, None)
+        | (None, Some(Ok(SEMI_MAJOR_AXIS)), Some(Ok(SEMI_MAJOR_AXIS))) => true,
+        _ => {
+            return Err(format!(
+                "Unsupported proj string {s}: only the WGS84 ellipsoid and the Web Mercator \
+                 sphere are supported"
+            ))
+        }
+    };
+
+    match params.get("proj").copied() {
+        Some("longlat" | "latlong" | "lonlat" | "latlon") if !sphere => Ok(Crs::Wgs84),
+        Some("webmerc") if !sphere => Ok(Crs::WebMercator),
+        Some("merc") if sphere => Ok(Crs::WebMercator),
+        Some("merc") => Ok(Crs::WorldMercator),
+        Some("utm") if !sphere => {
+            let zone = params
+                .get("zone")
+                .and_then(|zone| zone.parse::<u8>().ok())
+                .filter(|zone| (1..=60).contains(zone))
+                .ok_or_else(|| format!("Invalid proj string {s}: +zone must be 1 to 60"))?;
+            Ok(Crs::Utm {
+                zone,
+                south: params.contains_key("south"),
+            })
+        }
+        _ => Err(format!(
+            "Unsupported proj string {s}, expected +proj=longlat, +proj=merc, +proj=webmerc \
+             or +proj=utm"
+        )),
+    }
+}
+
+static CRS: OnceCell<Crs> = OnceCell::new();
+
+/// Set the CRS of the geometries written by the Arrow generators
+///
+/// Only the first call has an effect.
+pub fn set_crs(crs: Crs) {
+    let _ = CRS.set(crs);
+}
+
+/// Return the CRS set with [`set_crs`], or [`Crs::Wgs84`]
+pub fn crs() -> Crs {
+    CRS.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(crs: &str, x: f64, y: f64) -> (f64, f64) {
+        let coord = crs.parse::<Crs>().unwrap().project(Coord { x, y });
+        (coord.x, coord.y)
+    }
+
+    #[test]
+    fn test_project() {
+        assert_eq!(project("EPSG:4326", 12.5, -45.25), (12.5, -45.25));
+        assert_eq!(project("EPSG:3857", 180.0, 0.0), (20_037_508.343, 0.0));
+        assert_eq!(project("EPSG:3857", -74.0, 40.7).1, 4_968_191.93);
+        assert_eq!(project("EPSG:3395", 0.0, 45.0).1, 5_591_295.919);
+        // on the central meridian and the equator of zone 32N
+        assert_eq!(project("EPSG:32632", 9.0, 0.0), (500_000.0, 0.0));
+        assert_eq!(project("EPSG:32632", 9.0, 45.0), (500_000.0, 4_982_950.4));
+        assert_eq!(project("EPSG:32631", 0.0, 0.0), (166_021.443, 0.0));
+        assert_eq!(project("EPSG:32731", 3.0, 0.0), (500_000.0, 10_000_000.0));
+        // outside the zone, the longitudes are clamped to its edges
+        assert_eq!(
+            project("EPSG:32632", 99.0, 45.0),
+            project("EPSG:32632", 12.0, 45.0)
+        );
+        assert_eq!(
+            project("EPSG:32632", -171.0, 0.0),
+            project("EPSG:32632", 6.0, 0.0)
+        );
+        assert_eq!(
+            project("EPSG:32601", 179.0, 0.0),
+            project("EPSG:32601", -180.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("epsg:3857".parse(), Ok(Crs::WebMercator));
+        assert_eq!("OGC:CRS84".parse(), Ok(Crs::Wgs84));
+        assert_eq!(
+            "+proj=merc +a=6378137 +b=6378137 +lat_ts=0 +lon_0=0 +x_0=0 +y_0=0 +k=1 +units=m \
+             +nadgrids=@null +wktext +no_defs"
+                .parse(),
+            Ok(Crs::WebMercator)
+        );
+        assert_eq!(
+            "+proj=utm +zone=33 +south +datum=WGS84 +units=m +no_defs".parse(),
+            Ok(Crs::Utm {
+                zone: 33,
+                south: true
+            })
+        );
+        assert_eq!("+proj=longlat +datum=WGS84".parse(), Ok(Crs::Wgs84));
+        assert_eq!(
+            Crs::Utm {
+                zone: 33,
+                south: true
+            }
+            .to_string(),
+            "EPSG:32733"
+        );
+        assert!("EPSG:2154".parse::<Crs>().is_err());
+        assert!("+proj=utm +zone=61".parse::<Crs>().is_err());
+        assert!("+proj=merc +lon_0=10".parse::<Crs>().is_err());
+        assert!("+proj=longlat +datum=NAD27".parse::<Crs>().is_err());
+    }
+}
@@ -51,6 +51,7 @@ impl SpatialDefaults {
             height: 0.0,
 
             // geometry = polygon
+            minseg: 0,
             maxseg: 0,
             polysize: 0.0,
 
@@ -83,6 +84,7 @@ impl SpatialDefaults {
             height: 0.0,
 
             // geometry = polygon
+            minseg: 3,
             maxseg: 7,
             polysize: 0.000039,
 
@@ -0,0 +1,220 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Coordinate dimensions of the generated geometries, and their Z values
+//!
+//! The generators produce 2D geometries. With a Z dimension, the geometries
+//! are written with a Z value for each coordinate: the [`elevation`] of the
+//! terrain, or for buildings the height of their roof above it. With an M
+//! dimension, the trajectories are written with the time of each position
+//! as its measure.
+
+use crate::spatial::utils::{hash_to_unit_u64, round_coordinate};
+use once_cell::sync::OnceCell;
+use std::fmt;
+use std::str::FromStr;
+
+/// Size of the cells (degrees) and amplitude (meters) of the octaves of
+/// the terrain, from the largest
+const OCTAVES: [(f64, f64); 4] = [(8.0, 1500.0), (2.0, 600.0), (0.5, 240.0), (0.125, 96.0)];
+
+/// Z values are rounded to centimeters
+const Z_PRECISION: f64 = 100.0;
+
+/// Height of a floor of a building, in meters
+const FLOOR_HEIGHT: f64 = 3.5;
+
+/// Largest number of floors of a building
+const MAX_FLOORS: f64 = 60.0;
+
+/// The dimensions of the coordinates of the written geometries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dimensions {
+    /// Longitude and latitude
+    #[default]
+    Xy,
+    /// Longitude, latitude and elevation in meters
+    Xyz,
+    /// Longitude, latitude and a measure
+    Xym,
+    /// Longitude, latitude, elevation in meters and a measure
+    Xyzm,
+}
+
+impl Dimensions {
+    /// Return the dimensions with a Z if `has_z` and an M if `has_m`
+    pub fn new(has_z: bool, has_m: bool) -> Self {
+        match (has_z, has_m) {
+            (false, false) => Dimensions::Xy,
+            (true, false) => Dimensions::Xyz,
+            (false, true) => Dimensions::Xym,
+            (true, true) => Dimensions::Xyzm,
+        }
+    }
+
+    /// Return whether the coordinates have a Z value
+    pub fn has_z(&self) -> bool {
+        matches!(self, Dimensions::Xyz | Dimensions::Xyzm)
+    }
+
+    /// Return whether the coordinates have an M value
+    pub fn has_m(&self) -> bool {
+        matches!(self, Dimensions::Xym | Dimensions::Xyzm)
+    }
+
+    /// Return these dimensions without M, for the geometries that have no
+    /// measure
+    pub fn without_m(&self) -> Self {
+        Dimensions::new(self.has_z(), false)
+    }
+}
+
+impl fmt::Display for Dimensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dimensions::Xy => write!(f, "xy"),
+            Dimensions::Xyz => write!(f, "xyz"),
+            Dimensions::Xym => write!(f, "xym"),
+            Dimensions::Xyzm => write!(f, "xyzm"),
+        }
+    }
+}
+
+impl FromStr for Dimensions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "xy" => Ok(Dimensions::Xy),
+            "xyz" => Ok(Dimensions::Xyz),
+            "xym" => Ok(Dimensions::Xym),
+            "xyzm" => Ok(Dimensions::Xyzm),
+            _ => Err(format!(
+                "Invalid dimensions {s}, expected xy, xyz, xym or xyzm"
+            )),
+        }
+    }
+}
+
+/// Return the elevation in meters of the terrain at longitude `x` and
+/// latitude `y`
+///
+/// The terrain is a sum of octaves of smooth noise, from 0 to about 2400
+/// meters. Like a real terrain, it is the same for every seed.
+pub fn elevation(x: f64, y: f64) -> f64 {
+    let z: f64 = OCTAVES
+        .iter()
+        .enumerate()
+        .map(|(octave, &(cell, amplitude))| {
+            amplitude * value_noise(x / cell, y / cell, octave as u64)
+        })
+        .sum();
+    round_coordinate(z, Z_PRECISION)
+}
+
+/// Return the height in meters of the building `buildingkey` above the
+/// ground
+///
+/// Buildings have a whole number of floors, most of them a few and some
+/// of them dozens.
+pub fn building_height(buildingkey: i64, seed: u64) -> f64 {
+    let u = hash_to_unit_u64(buildingkey as u64, seed ^ 0xB1D6_4E16);
+    let floors = 1.0 + ((1.0 - u).ln() / 0.75f64.ln()).floor();
+    floors.min(MAX_FLOORS) * FLOOR_HEIGHT
+}
+
+/// Return noise in `[0, 1)` at `x`, `y` in units of the lattice, smoothly
+/// interpolated between random values at the lattice points
+fn value_noise(x: f64, y: f64, salt: u64) -> f64 {
+    let (i, j) = (x.floor(), y.floor());
+    let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+    let (fx, fy) = (smooth(x - i), smooth(y - j));
+    let value = |di: i64, dj: i64| {
+        let key =
+            ((i as i64 + di) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (j as i64 + dj) as u64;
+        hash_to_unit_u64(key, 0xE1E7_A710 ^ (salt << 32))
+    };
+    let bottom = value(0, 0) + fx * (value(1, 0) - value(0, 0));
+    let top = value(0, 1) + fx * (value(1, 1) - value(0, 1));
+    bottom + fy * (top - bottom)
+}
+
+static DIMENSIONS: OnceCell<Dimensions> = OnceCell::new();
+
+/// Set the dimensions of the geometries written by the Arrow generators
+///
+/// Only the first call has an effect.
+pub fn set_dimensions(dimensions: Dimensions) {
+    let _ = DIMENSIONS.set(dimensions);
+}
+
+/// Return the dimensions set with [`set_dimensions`], or [`Dimensions::Xy`]
+pub fn dimensions() -> Dimensions {
+    DIMENSIONS.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elevation() {
+        let mut previous = elevation(10.0, 45.0);
+        let (mut min, mut max) = (f64::MAX, f64::MIN);
+        for i in 1..=1000 {
+            let z = elevation(10.0 + i as f64 * 0.001, 45.0);
+            // the terrain is smooth
+            assert!((z - previous).abs() < 10.0, "{previous} to {z}");
+            (min, max) = (min.min(z), max.max(z));
+            previous = z;
+        }
+        assert!(min >= 0.0 && max <= 2436.0, "{min}..{max}");
+        assert!(max > min);
+        assert_eq!(elevation(10.5, 45.0), elevation(10.5, 45.0));
+    }
+
+    #[test]
+    fn test_dimensions() {
+        for dimensions in ["xy", "xyz", "xym", "xyzm"] {
+            let parsed: Dimensions = dimensions.parse().unwrap();
+            assert_eq!(parsed.to_string(), dimensions);
+        }
+        assert_eq!(Dimensions::Xyzm.without_m(), Dimensions::Xyz);
+        assert_eq!(Dimensions::Xym.without_m(), Dimensions::Xy);
+        assert_eq!(Dimensions::new(true, true), Dimensions::Xyzm);
+        assert!("xyzt".parse::<Dimensions>().is_err());
+    }
+
+    #[test]
+    fn test_building_height() {
+        let heights: Vec<_> = (1..=1000).map(|key| building_height(key, 0)).collect();
+        assert!(heights
+            .iter()
+            .all(|h| (FLOOR_HEIGHT..=MAX_FLOORS * FLOOR_HEIGHT).contains(h)));
+        let low = heights.iter().filter(|&&h| h <= 3.0 * FLOOR_HEIGHT).count();
+        assert!(
+            (500..=700).contains(&low),
+            "{low} buildings of 3 floors or less"
+        );
+        assert_ne!(
+            heights,
+            (1..=1000)
+                .map(|key| building_height(key, 1))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -25,8 +25,25 @@ use crate::spatial::{DistributionParams, SpatialConfig};
 use geo::Geometry;
 use rand::rngs::StdRng;
 use rand::Rng;
+use std::f64::consts::PI;
+use std::fmt;
 use std::sync::OnceLock;
 
+/// A distribution of the geometries of a table, in addition to the built-in
+/// [`DistributionType`](crate::spatial::DistributionType)s
+///
+/// Set it with [`SpatialGenerator::with_distribution`]. The geometry of a row
+/// must only depend on its `index` and the `config`, so parts of a table can
+/// be generated independently. Implementations typically choose a center in
+/// the unit square and pass it to [`emit_geom`], which creates the
+/// configured type of geometry and places it with the continent affine `m`.
+///
+/// [`SpatialGenerator::with_distribution`]: crate::spatial::SpatialGenerator::with_distribution
+pub trait SpatialDistribution: fmt::Debug + Send + Sync {
+    /// Generate the geometry of the row with `index`
+    fn generate(&self, index: u64, config: &SpatialConfig, m: &[f64; 6]) -> Geometry;
+}
+
 pub fn generate_uniform(index: u64, config: &SpatialConfig, m: &[f64; 6]) -> Geometry {
     let seed = spider_seed_for_index(index, config.seed as u64);
     let mut rng = seeded_rng(seed);
@@ -114,6 +131,36 @@ pub fn generate_sierpinski(index: u64, config: &SpatialConfig, m: &[f64; 6]) ->
     emit_geom((x, y), config.geom_type, config, &mut rng, m)
 }
 
+/// Points spread on both sides of a synthetic coastline crossing the unit
+/// square from west to east
+///
+/// The coastline is a sum of sines of doubling frequencies and halving
+/// amplitudes with seeded phases, so it is rough at all scales. It is not
+/// derived from real coastlines.
+pub fn generate_coastline(index: u64, config: &SpatialConfig, m: &[f64; 6]) -> Geometry {
+    let sigma = match config.params {
+        DistributionParams::Coastline { sigma } => sigma.max(1e-6),
+        _ => panic!("Expected Coastline params, got {:?}", config.params),
+    };
+    let seed = spider_seed_for_index(index, config.seed as u64);
+    let mut rng = seeded_rng(seed);
+    let x: f64 = rng.gen();
+    let y = coastline(x, config.seed as u64) + rand_normal(&mut rng, 0.0, sigma);
+    emit_geom((x, unit_clamp(y)), config.geom_type, config, &mut rng, m)
+}
+
+/// Return the latitude of the coastline at `x`, in the unit square
+fn coastline(x: f64, seed: u64) -> f64 {
+    (0..8)
+        .map(|octave| {
+            let phase = u01_from_seed(spider_seed_for_index(octave, seed ^ 0xC0A57));
+            let frequency = (1u64 << octave) as f64;
+            0.15 / frequency * (2.0 * PI * (frequency * x + phase)).sin()
+        })
+        .sum::<f64>()
+        + 0.5
+}
+
 pub fn generate_thomas(
     index: u64,
     config: &SpatialConfig,
@@ -340,3 +387,68 @@ fn spider_bit(rng: &mut StdRng, prob: f64, digits: u32) -> f64 {
         })
         .sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial::{GeomType, SpatialDefaults};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_coastline() {
+        let mut config = SpatialDefaults::trip_default().config;
+        config.dist_type = crate::spatial::DistributionType::Coastline;
+        config.params = DistributionParams::Coastline { sigma: 0.01 };
+        config.geom_type = GeomType::Point;
+        // the identity affine keeps the unit square
+        let identity = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let mut far = 0;
+        for index in 0..1000 {
+            let Geometry::Point(point) = generate_coastline(index, &config, &identity) else {
+                panic!("expected a point");
+            };
+            assert!((0.0..=1.0).contains(&point.x()) && (0.0..=1.0).contains(&point.y()));
+            if (point.y() - coastline(point.x(), config.seed as u64)).abs() > 0.03 {
+                far += 1;
+            }
+        }
+        // 3 sigma
+        assert!(far < 10, "{far} points far from the coastline");
+    }
+
+    /// Places all the geometries on the diagonal of the unit square
+    #[derive(Debug)]
+    struct OnDiagonal;
+
+    impl SpatialDistribution for OnDiagonal {
+        fn generate(&self, index: u64, config: &SpatialConfig, m: &[f64; 6]) -> Geometry {
+            let v = (index % 100) as f64 / 100.0;
+            let mut rng = seeded_rng(index);
+            emit_geom((v, v), config.geom_type, config, &mut rng, m)
+        }
+    }
+
+    #[test]
+    fn test_custom_distribution() {
+        let identity = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let generator = SpatialDefaults::trip_default().with_distribution(Arc::new(OnDiagonal));
+        for index in 0..100 {
+            let Geometry::Point(point) = generator.generate(index, &identity) else {
+                panic!("expected a point");
+            };
+            assert_eq!(point.x(), point.y());
+        }
+
+        // a built-in distribution replaces the custom one
+        let generator = generator.with_distribution_type(
+            crate::spatial::DistributionType::Uniform,
+            DistributionParams::None,
+        );
+        assert!((0..100).any(|index| {
+            let Geometry::Point(point) = generator.generate(index, &identity) else {
+                panic!("expected a point");
+            };
+            point.x() != point.y()
+        }));
+    }
+}
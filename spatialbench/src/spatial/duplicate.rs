@@ -0,0 +1,172 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Injection of duplicate geometries
+//!
+//! The generated buildings are distinct. With a duplicate rate, a fraction
+//! of the buildings are copies of the building before them, either exact
+//! or moved by less than [`NEAR_DUPLICATE_DISTANCE`], and point to it, to
+//! benchmark deduplication and `ST_Equals` or `ST_DWithin` joins with a
+//! known number of matches. The duplicate rows are chosen from their index
+//! with a low discrepancy sequence, so any range of rows has close to the
+//! rate of them.
+
+use crate::spatial::precision::snap_coordinates;
+use crate::spatial::utils::{hash_to_unit_u64, spider_seed_for_index, weyl_unit};
+use geo::{Geometry, MapCoords};
+use once_cell::sync::OnceCell;
+use std::f64::consts::TAU;
+use std::fmt;
+
+/// Largest distance (degrees) a near duplicate is moved by
+pub const NEAR_DUPLICATE_DISTANCE: f64 = 1e-6;
+
+/// Largest duplicate rate
+///
+/// Consecutive rows of the low discrepancy sequence are at least
+/// `1 - 1 / φ ≈ 0.38` apart, so below this rate the row a duplicate copies
+/// is never a duplicate itself.
+pub const MAX_DUPLICATE_RATE: f64 = 0.25;
+
+/// The ways a row duplicates the row before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKind {
+    /// The same values and geometry
+    Exact,
+    /// The same values, and the geometry moved by at least half of
+    /// [`NEAR_DUPLICATE_DISTANCE`] and less than all of it
+    Near,
+}
+
+impl DuplicateKind {
+    pub const ALL: [DuplicateKind; 2] = [DuplicateKind::Exact, DuplicateKind::Near];
+
+    /// Return how the row with `index` duplicates the row with `index - 1`,
+    /// or `None` if it does not, for a fraction `rate` of duplicate rows
+    ///
+    /// The first row has no row before it and is never a duplicate.
+    pub fn of_row(index: u64, seed: u64, rate: f64) -> Option<DuplicateKind> {
+        if rate <= 0.0 || index <= 1 {
+            return None;
+        }
+        let u = weyl_unit(index, seed, 0xD0_B1E);
+        (u < rate.min(MAX_DUPLICATE_RATE)).then(|| {
+            let kind = spider_seed_for_index(index, seed ^ 0xD0_B1E) % 2;
+            Self::ALL[kind as usize]
+        })
+    }
+
+    /// Return the duplicate of `geometry` for the row with `index`
+    pub fn apply(&self, geometry: Geometry, index: u64, seed: u64) -> Geometry {
+        match self {
+            DuplicateKind::Exact => geometry,
+            DuplicateKind::Near => {
+                let angle = TAU * hash_to_unit_u64(index, seed ^ 0xA6_61E);
+                let distance = NEAR_DUPLICATE_DISTANCE
+                    * (0.5 + 0.45 * hash_to_unit_u64(index, seed ^ 0xD1_57A));
+                let (dx, dy) = (distance * angle.cos(), distance * angle.sin());
+                geometry.map_coords(|c| {
+                    let (x, y) = snap_coordinates(c.x + dx, c.y + dy);
+                    geo::coord! { x: x, y: y }
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for DuplicateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DuplicateKind::Exact => write!(f, "exact"),
+            DuplicateKind::Near => write!(f, "near"),
+        }
+    }
+}
+
+static DUPLICATE_RATE: OnceCell<f64> = OnceCell::new();
+
+/// Set the fraction of buildings that duplicate the building before them
+///
+/// Only the first call has an effect.
+pub fn set_duplicate_rate(rate: f64) {
+    let _ = DUPLICATE_RATE.set(rate);
+}
+
+/// Return the rate set with [`set_duplicate_rate`], or 0
+pub fn duplicate_rate() -> f64 {
+    DUPLICATE_RATE.get().copied().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{polygon, Distance, Euclidean, Polygon};
+
+    #[test]
+    fn test_of_row() {
+        let duplicates: Vec<_> = (0..10_000)
+            .filter(|&index| DuplicateKind::of_row(index, 3, 0.02).is_some())
+            .collect();
+        assert!(
+            (199..=201).contains(&duplicates.len()),
+            "{}",
+            duplicates.len()
+        );
+        // the rows before the duplicates are not duplicates
+        for rate in [0.02, MAX_DUPLICATE_RATE, 1.0] {
+            assert!((2..10_000).all(|index| {
+                DuplicateKind::of_row(index, 3, rate).is_none()
+                    || DuplicateKind::of_row(index - 1, 3, rate).is_none()
+            }));
+        }
+        let kinds: Vec<_> = duplicates
+            .iter()
+            .filter_map(|&index| DuplicateKind::of_row(index, 3, 0.02))
+            .collect();
+        for kind in DuplicateKind::ALL {
+            assert!(kinds.contains(&kind), "{kind}");
+        }
+        assert_eq!(DuplicateKind::of_row(1, 3, 1.0), None);
+        assert_eq!(DuplicateKind::of_row(5, 3, 0.0), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let square: Polygon = polygon![
+            (x: 10.0, y: 20.0),
+            (x: 10.001, y: 20.0),
+            (x: 10.001, y: 20.001),
+            (x: 10.0, y: 20.001),
+        ];
+        let geometry = Geometry::Polygon(square.clone());
+        assert_eq!(DuplicateKind::Exact.apply(geometry.clone(), 7, 3), geometry);
+        for index in 0..100 {
+            let Geometry::Polygon(near) = DuplicateKind::Near.apply(geometry.clone(), index, 3)
+            else {
+                panic!("not a polygon");
+            };
+            assert_ne!(near, square);
+            for (a, b) in square.exterior().points().zip(near.exterior().points()) {
+                let distance = Euclidean.distance(a, b);
+                assert!(
+                    (NEAR_DUPLICATE_DISTANCE * 0.49..NEAR_DUPLICATE_DISTANCE).contains(&distance),
+                    "{distance}"
+                );
+            }
+        }
+    }
+}
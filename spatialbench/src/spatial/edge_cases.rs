@@ -0,0 +1,393 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Pathological geometries, to test the robustness of engines
+//!
+//! The generated tables only have well behaved geometries. The edge cases
+//! are a fixed set of longitude / latitude geometries that engines often get
+//! wrong: crossing the antimeridian, around the poles, empty, without area,
+//! touching themselves, very large, or nearly duplicated. They are the same
+//! for every scale factor and seed.
+
+use geo::{
+    coord, Coord, Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint,
+    MultiPolygon, Point, Polygon,
+};
+use std::f64::consts::TAU;
+
+/// Number of vertices of the huge polygon
+const HUGE_POLYGON_VERTICES: usize = 100_000;
+
+/// Number of parts of the huge multipolygon along each side of its grid
+const HUGE_MULTIPOLYGON_SIDE: usize = 100;
+
+/// A pathological geometry
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeCase {
+    /// Kind of edge case, e.g. `antimeridian`
+    pub category: &'static str,
+    /// Name of the edge case, unique among all of them
+    pub name: &'static str,
+    /// What is pathological about the geometry
+    pub description: &'static str,
+    pub geometry: Geometry,
+}
+
+/// Return all the edge cases, by category
+pub fn edge_cases() -> Vec<EdgeCase> {
+    let case = |category, name, description, geometry| EdgeCase {
+        category,
+        name,
+        description,
+        geometry,
+    };
+    vec![
+        // antimeridian
+        case(
+            "antimeridian",
+            "antimeridian_polygon",
+            "2 degree box across the antimeridian, or 358 degrees wide in the plane",
+            polygon(&[(179.0, -1.0), (-179.0, -1.0), (-179.0, 1.0), (179.0, 1.0)]),
+        ),
+        case(
+            "antimeridian",
+            "antimeridian_linestring",
+            "1 degree line across the antimeridian, or 359 degrees long in the plane",
+            line(&[(179.5, 10.0), (-179.5, 10.0)]),
+        ),
+        case(
+            "antimeridian",
+            "antimeridian_split_multipolygon",
+            "box split into two parts touching the antimeridian from either side",
+            Geometry::MultiPolygon(MultiPolygon(vec![
+                polygon_ring(&[(179.0, 20.0), (180.0, 20.0), (180.0, 21.0), (179.0, 21.0)]),
+                polygon_ring(&[
+                    (-180.0, 20.0),
+                    (-179.0, 20.0),
+                    (-179.0, 21.0),
+                    (-180.0, 21.0),
+                ]),
+            ])),
+        ),
+        case(
+            "antimeridian",
+            "longitude_out_of_range_polygon",
+            "box across the antimeridian with longitudes beyond 180",
+            polygon(&[
+                (179.0, -30.0),
+                (181.0, -30.0),
+                (181.0, -29.0),
+                (179.0, -29.0),
+            ]),
+        ),
+        case(
+            "antimeridian",
+            "antimeridian_points",
+            "the same point at longitude 180 and -180",
+            Geometry::MultiPoint(MultiPoint::from(vec![(180.0, 0.0), (-180.0, 0.0)])),
+        ),
+        // poles
+        case(
+            "pole",
+            "north_pole_cap",
+            "cap north of 80 degrees, closed along the antimeridian and the pole",
+            polygon(&[
+                (-180.0, 80.0),
+                (-90.0, 80.0),
+                (0.0, 80.0),
+                (90.0, 80.0),
+                (180.0, 80.0),
+                (180.0, 90.0),
+                (-180.0, 90.0),
+            ]),
+        ),
+        case(
+            "pole",
+            "south_pole_ring",
+            "ring around the south pole along a parallel, collinear in the plane",
+            polygon(&[(0.0, -80.0), (90.0, -80.0), (180.0, -80.0), (-90.0, -80.0)]),
+        ),
+        case(
+            "pole",
+            "north_pole_point",
+            "the north pole",
+            Geometry::Point(Point::new(0.0, 90.0)),
+        ),
+        case(
+            "pole",
+            "south_pole_point",
+            "the south pole, at an arbitrary longitude",
+            Geometry::Point(Point::new(123.4, -90.0)),
+        ),
+        case(
+            "pole",
+            "over_the_pole_linestring",
+            "line over the north pole between opposite longitudes",
+            line(&[(10.0, 89.0), (10.0, 90.0), (-170.0, 89.0)]),
+        ),
+        // empty
+        case(
+            "empty",
+            "empty_point",
+            "empty point, with NaN coordinates in WKB",
+            Geometry::Point(Point::new(f64::NAN, f64::NAN)),
+        ),
+        case(
+            "empty",
+            "empty_linestring",
+            "linestring without coordinates",
+            Geometry::LineString(LineString::new(vec![])),
+        ),
+        case(
+            "empty",
+            "empty_polygon",
+            "polygon without rings",
+            Geometry::Polygon(Polygon::new(LineString::new(vec![]), vec![])),
+        ),
+        case(
+            "empty",
+            "empty_multipoint",
+            "multipoint without points",
+            Geometry::MultiPoint(MultiPoint(vec![])),
+        ),
+        case(
+            "empty",
+            "empty_multilinestring",
+            "multilinestring without lines",
+            Geometry::MultiLineString(MultiLineString(vec![])),
+        ),
+        case(
+            "empty",
+            "empty_multipolygon",
+            "multipolygon without polygons",
+            Geometry::MultiPolygon(MultiPolygon(vec![])),
+        ),
+        case(
+            "empty",
+            "empty_geometrycollection",
+            "geometry collection without geometries",
+            Geometry::GeometryCollection(GeometryCollection(vec![])),
+        ),
+        // zero area
+        case(
+            "zero_area",
+            "collinear_polygon",
+            "polygon with all its vertices on a line",
+            polygon(&[(10.0, 10.0), (11.0, 11.0), (12.0, 12.0)]),
+        ),
+        case(
+            "zero_area",
+            "single_point_polygon",
+            "polygon with all its vertices at the same point",
+            polygon(&[(10.0, 10.0), (10.0, 10.0), (10.0, 10.0)]),
+        ),
+        case(
+            "zero_area",
+            "zero_length_linestring",
+            "linestring with both ends at the same point",
+            line(&[(10.0, 10.0), (10.0, 10.0)]),
+        ),
+        case(
+            "zero_area",
+            "spike_polygon",
+            "triangle with a spike going out and back along the same edge",
+            polygon(&[(0.0, 0.0), (1.0, 0.0), (3.0, 0.0), (1.0, 0.0), (1.0, 1.0)]),
+        ),
+        // self-touching
+        case(
+            "self_touching",
+            "inverted_hole_polygon",
+            "shell touching itself at a vertex, enclosing a hole",
+            polygon(&[
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (5.0, 5.0),
+                (7.0, 8.0),
+                (3.0, 8.0),
+                (5.0, 5.0),
+                (0.0, 10.0),
+            ]),
+        ),
+        case(
+            "self_touching",
+            "hole_touching_shell",
+            "hole touching its shell at a point",
+            Geometry::Polygon(Polygon::new(
+                ring(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]),
+                vec![ring(&[(5.0, 0.0), (7.0, 3.0), (3.0, 3.0)])],
+            )),
+        ),
+        case(
+            "self_touching",
+            "touching_multipolygon",
+            "two squares of a multipolygon touching at a corner",
+            Geometry::MultiPolygon(MultiPolygon(vec![
+                polygon_ring(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]),
+                polygon_ring(&[(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0)]),
+            ])),
+        ),
+        case(
+            "self_touching",
+            "self_touching_linestring",
+            "linestring passing twice through the same point",
+            line(&[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0), (1.0, 1.0)]),
+        ),
+        // huge
+        case(
+            "huge",
+            "huge_polygon",
+            "polygon with 100000 vertices",
+            Geometry::Polygon(circle(
+                coord! { x: 10.0, y: 50.0 },
+                5.0,
+                HUGE_POLYGON_VERTICES,
+            )),
+        ),
+        case(
+            "huge",
+            "huge_multipolygon",
+            "multipolygon of 10000 polygons of 10 vertices",
+            Geometry::MultiPolygon(MultiPolygon(
+                (0..HUGE_MULTIPOLYGON_SIDE * HUGE_MULTIPOLYGON_SIDE)
+                    .map(|i| {
+                        let (x, y) = (i % HUGE_MULTIPOLYGON_SIDE, i / HUGE_MULTIPOLYGON_SIDE);
+                        let center =
+                            coord! { x: -20.0 + x as f64 * 0.1, y: -10.0 + y as f64 * 0.1 };
+                        circle(center, 0.04, 10)
+                    })
+                    .collect(),
+            )),
+        ),
+        case(
+            "huge",
+            "world_polygon",
+            "polygon covering all longitudes and latitudes",
+            polygon(&[
+                (-180.0, -90.0),
+                (180.0, -90.0),
+                (180.0, 90.0),
+                (-180.0, 90.0),
+            ]),
+        ),
+        // precision
+        case(
+            "near_duplicate",
+            "adjacent_float_points",
+            "two points one representable double apart",
+            Geometry::MultiPoint(MultiPoint::from(vec![
+                (1.0, 1.0),
+                (f64::from_bits(1.0f64.to_bits() + 1), 1.0),
+            ])),
+        ),
+        case(
+            "near_duplicate",
+            "near_duplicate_vertices",
+            "square with consecutive vertices 1e-12 degrees apart",
+            polygon(&[
+                (20.0, 20.0),
+                (21.0, 20.0),
+                (21.0 + 1e-12, 20.0 + 1e-12),
+                (21.0, 21.0),
+                (20.0, 21.0),
+            ]),
+        ),
+        case(
+            "near_duplicate",
+            "repeated_vertices",
+            "square with each vertex repeated",
+            polygon(&[
+                (20.0, 20.0),
+                (20.0, 20.0),
+                (21.0, 20.0),
+                (21.0, 20.0),
+                (21.0, 21.0),
+                (21.0, 21.0),
+                (20.0, 21.0),
+                (20.0, 21.0),
+            ]),
+        ),
+        case(
+            "near_duplicate",
+            "near_duplicate_multipolygon",
+            "two squares of a multipolygon 1e-12 degrees apart, overlapping",
+            Geometry::MultiPolygon(MultiPolygon(vec![
+                polygon_ring(&[(30.0, 30.0), (31.0, 30.0), (31.0, 31.0), (30.0, 31.0)]),
+                polygon_ring(&[
+                    (30.0 + 1e-12, 30.0),
+                    (31.0 + 1e-12, 30.0),
+                    (31.0 + 1e-12, 31.0),
+                    (30.0 + 1e-12, 31.0),
+                ]),
+            ])),
+        ),
+    ]
+}
+
+fn ring(coords: &[(f64, f64)]) -> LineString {
+    // closes the ring
+    Polygon::new(LineString::from(coords.to_vec()), vec![])
+        .into_inner()
+        .0
+}
+
+fn polygon_ring(coords: &[(f64, f64)]) -> Polygon {
+    Polygon::new(ring(coords), vec![])
+}
+
+fn polygon(coords: &[(f64, f64)]) -> Geometry {
+    Geometry::Polygon(polygon_ring(coords))
+}
+
+fn line(coords: &[(f64, f64)]) -> Geometry {
+    Geometry::LineString(LineString::from(coords.to_vec()))
+}
+
+/// Return a regular polygon of `vertices` vertices, counterclockwise
+fn circle(center: Coord, radius: f64, vertices: usize) -> Polygon {
+    let coords: Vec<Coord> = (0..vertices)
+        .map(|i| {
+            let angle = TAU * i as f64 / vertices as f64;
+            coord! { x: center.x + radius * angle.cos(), y: center.y + radius * angle.sin() }
+        })
+        .collect();
+    Polygon::new(LineString::new(coords), vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::CoordsIter;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_edge_cases() {
+        let cases = edge_cases();
+        let names: HashSet<_> = cases.iter().map(|case| case.name).collect();
+        assert_eq!(names.len(), cases.len(), "the names are unique");
+        let categories: HashSet<_> = cases.iter().map(|case| case.category).collect();
+        assert_eq!(categories.len(), 7, "{categories:?}");
+
+        let case = |name| cases.iter().find(|case| case.name == name).unwrap();
+        assert_eq!(case("huge_polygon").geometry.coords_count(), 100_001);
+        assert_eq!(case("empty_polygon").geometry.coords_count(), 0);
+        let Geometry::Polygon(polygon) = &case("antimeridian_polygon").geometry else {
+            panic!("not a polygon");
+        };
+        // the rings are closed
+        assert_eq!(polygon.exterior().0.first(), polygon.exterior().0.last());
+    }
+}
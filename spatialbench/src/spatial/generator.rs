@@ -17,15 +17,20 @@
 
 use crate::spatial::cache::{HierThomasCache, ThomasCache};
 use crate::spatial::distributions::*;
-use crate::spatial::{DistributionType, SpatialConfig};
+use crate::spatial::utils::spider_seed_for_index;
+use crate::spatial::{DistributionParams, DistributionType, GeometryMix, SpatialConfig};
 use geo::Geometry;
-use std::sync::OnceLock;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, OnceLock};
 
 #[derive(Clone, Debug)]
 pub struct SpatialGenerator {
     pub config: SpatialConfig,
     pub thomas_cache: OnceLock<ThomasCache>,
     pub hier_cache: OnceLock<HierThomasCache>,
+    pub geometry_mix: GeometryMix,
+    /// Replaces the distribution of the config, if set
+    pub distribution: Option<Arc<dyn SpatialDistribution>>,
 }
 
 impl SpatialGenerator {
@@ -38,10 +43,89 @@ impl SpatialGenerator {
             config,
             thomas_cache,
             hier_cache,
+            geometry_mix: GeometryMix::default(),
+            distribution: None,
         }
     }
 
+    /// Returns a generator whose config seed is derived from the global `seed`
+    ///
+    /// The global seed `0` returns the generator unchanged.
+    pub fn with_seed(&self, seed: u64) -> Self {
+        if seed == 0 {
+            return self.clone();
+        }
+        let mut config = self.config.clone();
+        config.seed = spider_seed_for_index(config.seed as u64, seed) as u32;
+        // the caches depend on the seed
+        Self {
+            config,
+            thomas_cache: OnceLock::new(),
+            hier_cache: OnceLock::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a generator with the built-in distribution `dist_type`
+    ///
+    /// The geometries, seed and other settings of the config are kept.
+    pub fn with_distribution_type(
+        &self,
+        dist_type: DistributionType,
+        params: DistributionParams,
+    ) -> Self {
+        let mut config = self.config.clone();
+        config.dist_type = dist_type;
+        config.params = params;
+        // the caches depend on the distribution
+        Self {
+            config,
+            thomas_cache: OnceLock::new(),
+            hier_cache: OnceLock::new(),
+            distribution: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a generator placing the geometries with `distribution` instead
+    /// of the distribution of the config
+    pub fn with_distribution(&self, distribution: Arc<dyn SpatialDistribution>) -> Self {
+        let mut generator = self.clone();
+        generator.distribution = Some(distribution);
+        generator
+    }
+
+    /// Returns a generator of polygons with `vertices` vertices
+    ///
+    /// The number of vertices of each polygon is uniform in the range (and at
+    /// least 3). Points and boxes are not affected.
+    pub fn with_polygon_vertices(&self, vertices: RangeInclusive<i32>) -> Self {
+        let mut generator = self.clone();
+        generator.config.minseg = *vertices.start();
+        generator.config.maxseg = *vertices.end();
+        generator
+    }
+
+    /// Returns a generator of polygons converted to the types of `mix`
+    ///
+    /// Points are not affected.
+    pub fn with_geometry_mix(&self, mix: GeometryMix) -> Self {
+        let mut generator = self.clone();
+        generator.geometry_mix = mix;
+        generator
+    }
+
     pub fn generate(&self, index: u64, continent_affine: &[f64; 6]) -> Geometry {
+        let geometry = match &self.distribution {
+            Some(distribution) => distribution.generate(index, &self.config, continent_affine),
+            None => self.generate_builtin(index, continent_affine),
+        };
+        self.geometry_mix
+            .apply(index, self.config.seed as u64, geometry)
+    }
+
+    /// Generate a geometry with the built-in distribution of the config
+    fn generate_builtin(&self, index: u64, continent_affine: &[f64; 6]) -> Geometry {
         match self.config.dist_type {
             DistributionType::Uniform => generate_uniform(index, &self.config, continent_affine),
             DistributionType::Normal => generate_normal(index, &self.config, continent_affine),
@@ -59,6 +143,9 @@ impl SpatialGenerator {
                 &self.hier_cache,
                 continent_affine,
             ),
+            DistributionType::Coastline => {
+                generate_coastline(index, &self.config, continent_affine)
+            }
         }
     }
 }
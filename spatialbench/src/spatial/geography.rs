@@ -0,0 +1,170 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Generation on the sphere
+//!
+//! By default the geometries are generated with planar math on longitude
+//! and latitude: a degree of longitude is as long at the poles as at the
+//! equator, and the geometries that cross the antimeridian are clamped to
+//! it. With geography, the placements, sizes and distances are computed on
+//! the sphere instead: the geometries are spread by area, keep their size
+//! and shape at any latitude, and wrap across the antimeridian and around
+//! the poles. Distances are central angles in degrees, a degree being about
+//! 111 km on the WGS84 spheroid.
+
+use geo::{coord, Coord, LineString};
+use once_cell::sync::OnceCell;
+
+/// Return the latitude at `t` in `[0, 1]` from the latitude `lat0` to
+/// `lat1`, so that equal steps of `t` cover equal areas
+///
+/// Values of `t` outside of `[0, 1]` are clamped at the poles.
+pub fn equal_area_latitude(t: f64, lat0: f64, lat1: f64) -> f64 {
+    let sin = |lat: f64| lat.clamp(-90.0, 90.0).to_radians().sin();
+    let (s0, s1) = (sin(lat0), sin(lat1));
+    (s0 + t * (s1 - s0)).clamp(-1.0, 1.0).asin().to_degrees()
+}
+
+/// Return the great-circle distance between `a` and `b`, in degrees
+pub fn distance(a: Coord, b: Coord) -> f64 {
+    let (lat1, lat2) = (a.y.to_radians(), b.y.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.x - a.x).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * h.sqrt().min(1.0).asin().to_degrees()
+}
+
+/// Return the length of `line` along great circles, in degrees
+pub fn length(line: &LineString) -> f64 {
+    line.lines().map(|l| distance(l.start, l.end)).sum()
+}
+
+/// Return the initial bearing from `a` to `b`, in degrees clockwise from
+/// north
+pub fn bearing(a: Coord, b: Coord) -> f64 {
+    let (lat1, lat2) = (a.y.to_radians(), b.y.to_radians());
+    let dlon = (b.x - a.x).to_radians();
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    y.atan2(x).to_degrees()
+}
+
+/// Return the point at `distance` degrees from `start` along the great
+/// circle with the initial `bearing`
+///
+/// The longitude is within 180 degrees of the longitude of `start`, so that
+/// the points around a start stay continuous across the antimeridian; wrap
+/// it once the geometry is built.
+pub fn destination(start: Coord, bearing: f64, distance: f64) -> Coord {
+    let (lat1, bearing, d) = (
+        start.y.to_radians(),
+        bearing.to_radians(),
+        distance.to_radians(),
+    );
+    let lat2 = (lat1.sin() * d.cos() + lat1.cos() * d.sin() * bearing.cos())
+        .clamp(-1.0, 1.0)
+        .asin();
+    let dlon = (bearing.sin() * d.sin() * lat1.cos()).atan2(d.cos() - lat1.sin() * lat2.sin());
+    coord! { x: start.x + dlon.to_degrees(), y: lat2.to_degrees() }
+}
+
+/// Return the point at `fraction` of the way from `a` to `b` along the
+/// great circle between them
+///
+/// Like [`destination`], the longitude stays continuous with `a`.
+pub fn interpolate(a: Coord, b: Coord, fraction: f64) -> Coord {
+    let d = distance(a, b).to_radians();
+    if d < 1e-12 {
+        return a;
+    }
+    let to_vector = |c: Coord| {
+        let (lon, lat) = (c.x.to_radians(), c.y.to_radians());
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+    };
+    let (va, vb) = (to_vector(a), to_vector(b));
+    let (wa, wb) = (
+        ((1.0 - fraction) * d).sin() / d.sin(),
+        (fraction * d).sin() / d.sin(),
+    );
+    let v: Vec<f64> = (0..3).map(|i| wa * va[i] + wb * vb[i]).collect();
+    let lat = v[2].atan2(v[0].hypot(v[1])).to_degrees();
+    let lon = v[1].atan2(v[0]).to_degrees();
+    // keep the longitude within 180 degrees of a
+    let x = a.x + (lon - a.x + 540.0).rem_euclid(360.0) - 180.0;
+    coord! { x: x, y: lat }
+}
+
+static GEOGRAPHY: OnceCell<bool> = OnceCell::new();
+
+/// Set whether the geometries are generated on the sphere
+///
+/// Only the first call has an effect.
+pub fn set_geography(geography: bool) {
+    let _ = GEOGRAPHY.set(geography);
+}
+
+/// Return whether [`set_geography`] set the generation on the sphere
+pub fn geography() -> bool {
+    GEOGRAPHY.get().copied().unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_distance_and_destination() {
+        // a degree of longitude is shorter away from the equator
+        let at = |x, y| coord! { x: x, y: y };
+        assert_close(distance(at(0.0, 0.0), at(1.0, 0.0)), 1.0);
+        assert!(distance(at(0.0, 60.0), at(1.0, 60.0)) < 0.51);
+        assert_close(
+            distance(at(179.5, 10.0), at(-179.5, 10.0)),
+            distance(at(0.0, 10.0), at(1.0, 10.0)),
+        );
+        assert_close(bearing(at(0.0, 0.0), at(0.0, 10.0)), 0.0);
+
+        // east across the antimeridian, continuous with the start
+        let east = destination(at(179.5, 0.0), 90.0, 1.0);
+        assert_close(east.x, 180.5);
+        assert_close(east.y, 0.0);
+
+        // north over the pole, to the other side of the globe
+        let over = destination(at(10.0, 89.0), 0.0, 2.0);
+        assert_close(over.y, 89.0);
+        assert_close((over.x - 10.0).abs(), 180.0);
+        assert_close(distance(at(10.0, 89.0), over), 2.0);
+
+        let half = interpolate(at(179.0, 0.0), at(-179.0, 0.0), 0.5);
+        assert_close(half.x, 180.0);
+        assert_close(half.y, 0.0);
+    }
+
+    #[test]
+    fn test_equal_area_latitude() {
+        assert_close(equal_area_latitude(0.0, -90.0, 90.0), -90.0);
+        assert_close(equal_area_latitude(0.5, -90.0, 90.0), 0.0);
+        assert_close(equal_area_latitude(1.0, 0.0, 90.0), 90.0);
+        // half of the area of a hemisphere is below 30 degrees
+        assert_close(equal_area_latitude(0.5, 0.0, 90.0), 30.0);
+        assert_close(equal_area_latitude(1.5, 0.0, 90.0), 90.0);
+    }
+}
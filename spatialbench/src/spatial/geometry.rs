@@ -15,17 +15,20 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::spatial::geography::{self, destination, equal_area_latitude};
+use crate::spatial::precision::snap_coordinates;
 use crate::spatial::utils::{
-    apply_affine, clamp_polygon_to_dateline, crosses_dateline, round_coordinates,
-    wrap_around_longitude,
+    apply_affine, clamp_polygon_to_dateline, crosses_dateline, wrap_around_longitude,
 };
 use crate::spatial::{GeomType, SpatialConfig};
 use geo::orient::Direction;
-use geo::{coord, Geometry, LineString, Orient, Point, Polygon};
+use geo::{coord, Coord, Geometry, LineString, MapCoords, Orient, Point, Polygon};
 use rand::rngs::StdRng;
 use rand::Rng;
 use std::f64::consts::PI;
 
+/// Default precision of the coordinates, 9 decimal digits, see
+/// [`precision`](crate::spatial::precision)
 pub const GEOMETRY_PRECISION: f64 = 1_000_000_000.0;
 
 pub fn emit_geom(
@@ -35,6 +38,13 @@ pub fn emit_geom(
     rng: &mut StdRng,
     m: &[f64; 6],
 ) -> Geometry {
+    if geography::geography() {
+        return match geom_type {
+            GeomType::Point => generate_point_geom_on_sphere(center01, m),
+            GeomType::Box => generate_box_geom_on_sphere(center01, config, rng, m),
+            GeomType::Polygon => generate_polygon_geom_on_sphere(center01, config, rng, m),
+        };
+    }
     match geom_type {
         GeomType::Point => generate_point_geom(center01, m),
         GeomType::Box => generate_box_geom(center01, config, rng, m),
@@ -45,7 +55,7 @@ pub fn emit_geom(
 pub fn generate_point_geom(center: (f64, f64), m: &[f64; 6]) -> Geometry {
     let (x, y) = apply_affine(center.0, center.1, m);
     let x = wrap_around_longitude(x);
-    let (x, y) = round_coordinates(x, y, GEOMETRY_PRECISION);
+    let (x, y) = snap_coordinates(x, y);
     Geometry::Point(Point::new(x, y))
 }
 
@@ -69,7 +79,7 @@ pub fn generate_box_geom(
     let coords: Vec<_> = corners
         .iter()
         .map(|&(x, y)| apply_affine(x, y, m))
-        .map(|(x, y)| round_coordinates(x, y, GEOMETRY_PRECISION))
+        .map(|(x, y)| snap_coordinates(x, y))
         .map(|(x, y)| coord! { x: x, y: y })
         .collect();
 
@@ -90,9 +100,9 @@ pub fn generate_polygon_geom(
     rng: &mut StdRng,
     m: &[f64; 6],
 ) -> Geometry {
-    let min_segs = 3;
-    let num_segments = if config.maxseg <= 3 {
-        3
+    let min_segs = config.minseg.max(3);
+    let num_segments = if config.maxseg <= min_segs {
+        min_segs
     } else {
         rng.gen_range(0..=(config.maxseg - min_segs)) + min_segs
     };
@@ -110,7 +120,7 @@ pub fn generate_polygon_geom(
                 center.1 + config.polysize * ang.sin(),
             );
             let (x1, y1) = apply_affine(x0, y0, m);
-            let (xr, yr) = round_coordinates(x1, y1, GEOMETRY_PRECISION);
+            let (xr, yr) = snap_coordinates(x1, y1);
             coord! { x: xr, y: yr }
         })
         .collect::<Vec<_>>();
@@ -129,3 +139,130 @@ pub fn generate_polygon_geom(
     polygon = polygon.orient(Direction::Default);
     Geometry::Polygon(polygon)
 }
+
+/// Return the center on the sphere of the unit `center`, spread by area
+/// between the latitudes covered by the affine `m`
+fn sphere_center(center: (f64, f64), m: &[f64; 6]) -> Coord {
+    let (x, _) = apply_affine(center.0, center.1, m);
+    let lat0 = m[3] * center.0 + m[5];
+    let y = equal_area_latitude(center.1, lat0, lat0 + m[4]);
+    coord! { x: x, y: y }
+}
+
+/// Return the size in degrees of the arc of a unit of the affine `m`
+fn sphere_scale(m: &[f64; 6]) -> f64 {
+    (m[0] * m[4] - m[1] * m[3]).abs().sqrt()
+}
+
+/// Orient the ring of a polygon built around a center, then wrap its
+/// longitudes across the antimeridian
+fn wrap_polygon(ring: Vec<Coord>) -> Geometry {
+    let polygon = Polygon::new(LineString::from(ring), vec![]).orient(Direction::Default);
+    Geometry::Polygon(polygon.map_coords(|c| {
+        let (x, y) = snap_coordinates(wrap_around_longitude(c.x), c.y);
+        coord! { x: x, y: y }
+    }))
+}
+
+pub fn generate_point_geom_on_sphere(center: (f64, f64), m: &[f64; 6]) -> Geometry {
+    let c = sphere_center(center, m);
+    let (x, y) = snap_coordinates(wrap_around_longitude(c.x), c.y);
+    Geometry::Point(Point::new(x, y))
+}
+
+pub fn generate_box_geom_on_sphere(
+    center: (f64, f64),
+    config: &SpatialConfig,
+    rng: &mut StdRng,
+    m: &[f64; 6],
+) -> Geometry {
+    let c = sphere_center(center, m);
+    let half_width = rng.gen::<f64>() * config.width / 2.0 * m[0].abs();
+    let half_height = rng.gen::<f64>() * config.height / 2.0 * m[4].abs();
+
+    // the box keeps its width on the ground, so spans more longitude
+    // toward the poles, up to all of it
+    let (south, north) = (
+        (c.y - half_height).max(-90.0),
+        (c.y + half_height).min(90.0),
+    );
+    let half_lon = (half_width / c.y.to_radians().cos().max(1e-9)).min(179.999_999);
+    let ring = vec![
+        coord! { x: c.x - half_lon, y: south },
+        coord! { x: c.x + half_lon, y: south },
+        coord! { x: c.x + half_lon, y: north },
+        coord! { x: c.x - half_lon, y: north },
+        coord! { x: c.x - half_lon, y: south },
+    ];
+    wrap_polygon(ring)
+}
+
+pub fn generate_polygon_geom_on_sphere(
+    center: (f64, f64),
+    config: &SpatialConfig,
+    rng: &mut StdRng,
+    m: &[f64; 6],
+) -> Geometry {
+    let min_segs = config.minseg.max(3);
+    let num_segments = if config.maxseg <= min_segs {
+        min_segs
+    } else {
+        rng.gen_range(0..=(config.maxseg - min_segs)) + min_segs
+    };
+
+    let mut angles: Vec<f64> = (0..num_segments)
+        .map(|_| rng.gen::<f64>() * 2.0 * PI)
+        .collect();
+    angles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    // the vertices are at the same distance from the center on the ground,
+    // counterclockwise from east like in the plane
+    let c = sphere_center(center, m);
+    let radius = config.polysize * sphere_scale(m);
+    let mut ring: Vec<Coord> = angles
+        .iter()
+        .map(|&ang| destination(c, 90.0 - ang.to_degrees(), radius))
+        .collect();
+
+    if let Some(first) = ring.first().copied() {
+        ring.push(first);
+    }
+    wrap_polygon(ring)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial::{DistributionParams, DistributionType};
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_polygon_on_sphere_across_antimeridian() {
+        let config = SpatialConfig {
+            dist_type: DistributionType::Uniform,
+            geom_type: GeomType::Polygon,
+            dim: 2,
+            seed: 7,
+            width: 0.0,
+            height: 0.0,
+            minseg: 8,
+            maxseg: 8,
+            polysize: 0.01,
+            params: DistributionParams::None,
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        // the center is on the antimeridian
+        let m = [360.0, 0.0, 0.0, 0.0, 90.0, 0.0];
+        let Geometry::Polygon(polygon) =
+            generate_polygon_geom_on_sphere((0.5, 1.0 / 3.0), &config, &mut rng, &m)
+        else {
+            panic!("not a polygon");
+        };
+        let coords: Vec<_> = polygon.exterior().coords().collect();
+        assert_eq!(coords.len(), 9);
+        // wrapped on both sides of the antimeridian rather than clamped
+        assert!(coords.iter().any(|c| c.x > 179.0));
+        assert!(coords.iter().any(|c| c.x < -179.0));
+        assert!(coords.iter().all(|c| c.x.abs() <= 180.0));
+    }
+}
@@ -0,0 +1,196 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A synthetic raster: a grid of square cells covering the world, with an
+//! elevation and a temperature in each cell
+//!
+//! The elevation is the [`elevation`] of the terrain at the center of the
+//! cell, the same as the Z of the 3D geometries. The temperature falls from
+//! the equator to the poles and by [`LAPSE_RATE`] degrees per kilometer of
+//! elevation, with smooth seeded noise, so neighbouring cells have close
+//! values like a real raster.
+
+use crate::spatial::dimensions::elevation;
+use crate::spatial::precision::snap_coordinates;
+use crate::spatial::utils::{hash_to_unit_u64, round_coordinate};
+use geo::{Coord, LineString, Polygon};
+
+/// Number of cells per scale factor, the grid has twice as many columns as
+/// rows, so the cells are square in degrees
+pub const CELLS_PER_SCALE_FACTOR: f64 = 1_000_000.0;
+
+/// Temperature at the equator at sea level, in degrees Celsius
+const EQUATOR_TEMPERATURE: f64 = 28.0;
+/// Temperature at the poles at sea level, in degrees Celsius
+const POLE_TEMPERATURE: f64 = -25.0;
+/// Decrease of the temperature with the elevation, in degrees per kilometer
+pub const LAPSE_RATE: f64 = 6.5;
+/// Amplitude of the noise of the temperature, in degrees
+const NOISE_AMPLITUDE: f64 = 3.0;
+/// Distance between the random values the noise is interpolated from, in
+/// degrees
+const NOISE_SPACING: f64 = 5.0;
+/// The values are rounded to hundredths
+const VALUE_PRECISION: f64 = 100.0;
+
+/// A grid of square cells covering the world, numbered by row from the
+/// north west corner like the pixels of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RasterGrid {
+    rows: usize,
+}
+
+impl RasterGrid {
+    /// Create the grid of about [`CELLS_PER_SCALE_FACTOR`] cells per scale
+    /// factor, with at least 2 cells
+    pub fn new(scale_factor: f64) -> Self {
+        let rows = (CELLS_PER_SCALE_FACTOR * scale_factor / 2.0).sqrt().round();
+        Self {
+            rows: (rows as usize).max(1),
+        }
+    }
+
+    /// Return the number of rows of cells
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Return the number of columns of cells
+    pub fn columns(&self) -> usize {
+        2 * self.rows
+    }
+
+    /// Return the number of cells
+    pub fn cell_count(&self) -> usize {
+        self.rows() * self.columns()
+    }
+
+    /// Return the size of the side of the cells, in degrees
+    pub fn cell_size(&self) -> f64 {
+        180.0 / self.rows as f64
+    }
+
+    /// Return the row and column of the cell at `index`
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        (index / self.columns(), index % self.columns())
+    }
+
+    /// Return the square of the cell at `index`
+    pub fn cell(&self, index: usize) -> Polygon {
+        let (row, column) = self.position(index);
+        let size = self.cell_size();
+        // from the row and column of each edge, so neighbouring cells share
+        // their edges exactly
+        let x = |column: usize| -180.0 + column as f64 * size;
+        let y = |row: usize| 90.0 - row as f64 * size;
+        let (west, east, north, south) = (x(column), x(column + 1), y(row), y(row + 1));
+        let coords: Vec<Coord> = [
+            (west, south),
+            (east, south),
+            (east, north),
+            (west, north),
+            (west, south),
+        ]
+        .into_iter()
+        .map(|(x, y)| {
+            let (x, y) = snap_coordinates(x, y);
+            Coord { x, y }
+        })
+        .collect();
+        Polygon::new(LineString::new(coords), vec![])
+    }
+
+    /// Return the longitude and latitude of the center of the cell at
+    /// `index`
+    pub fn center(&self, index: usize) -> (f64, f64) {
+        let (row, column) = self.position(index);
+        let size = self.cell_size();
+        (
+            -180.0 + (column as f64 + 0.5) * size,
+            90.0 - (row as f64 + 0.5) * size,
+        )
+    }
+
+    /// Return the elevation of the cell at `index`, in meters
+    pub fn elevation(&self, index: usize) -> f64 {
+        let (x, y) = self.center(index);
+        round_coordinate(elevation(x, y), VALUE_PRECISION)
+    }
+
+    /// Return the temperature of the cell at `index`, in degrees Celsius
+    pub fn temperature(&self, index: usize, seed: u64) -> f64 {
+        let (x, y) = self.center(index);
+        let cos = y.to_radians().cos();
+        let sea_level = POLE_TEMPERATURE + (EQUATOR_TEMPERATURE - POLE_TEMPERATURE) * cos * cos;
+        let temperature = sea_level - LAPSE_RATE * elevation(x, y) / 1000.0
+            + NOISE_AMPLITUDE * smooth_noise(x, y, seed);
+        round_coordinate(temperature, VALUE_PRECISION)
+    }
+}
+
+/// Return a value in [-1, 1] that changes smoothly with `x` and `y`: the
+/// interpolation of random values on a lattice of [`NOISE_SPACING`]
+fn smooth_noise(x: f64, y: f64, seed: u64) -> f64 {
+    let (x, y) = (x / NOISE_SPACING, y / NOISE_SPACING);
+    let (x0, y0) = (x.floor(), y.floor());
+    let value = |i: f64, j: f64| {
+        let key = ((i as i64 as u64) << 32) ^ (j as i64 as u64 & 0xFFFF_FFFF);
+        2.0 * hash_to_unit_u64(key, seed ^ 0x7E_4A7E) - 1.0
+    };
+    // smoothstep between the lattice values
+    let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+    let bottom = value(x0, y0) * (1.0 - tx) + value(x0 + 1.0, y0) * tx;
+    let top = value(x0, y0 + 1.0) * (1.0 - tx) + value(x0 + 1.0, y0 + 1.0) * tx;
+    bottom * (1.0 - ty) + top * ty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Area, BoundingRect};
+
+    #[test]
+    fn test_raster_grid() {
+        let grid = RasterGrid::new(1.0);
+        assert_eq!((grid.rows(), grid.columns()), (707, 1414));
+        assert_eq!(RasterGrid::new(1e-9).cell_count(), 2);
+
+        // the cells cover the world, from the north west corner
+        let grid = RasterGrid::new(0.001);
+        let area: f64 = (0..grid.cell_count())
+            .map(|index| grid.cell(index).signed_area())
+            .sum();
+        assert!((area - 360.0 * 180.0).abs() < 1e-6, "{area}");
+        let first = grid.cell(0).bounding_rect().unwrap();
+        assert_eq!((first.min().x, first.max().y), (-180.0, 90.0));
+        let last = grid.cell(grid.cell_count() - 1).bounding_rect().unwrap();
+        assert_eq!((last.max().x, last.min().y), (180.0, -90.0));
+
+        // warmer at the equator than at the poles, and the same for a seed
+        let grid = RasterGrid::new(1.0);
+        let equator = grid.rows() / 2 * grid.columns();
+        assert!(grid.temperature(equator, 0) > grid.temperature(0, 0) + 30.0);
+        assert_eq!(grid.temperature(equator, 1), grid.temperature(equator, 1));
+        assert_ne!(grid.temperature(equator, 0), grid.temperature(equator, 1));
+        // neighbouring cells have close temperatures
+        for index in equator..equator + 1000 {
+            let step = grid.temperature(index + 1, 0) - grid.temperature(index, 0);
+            assert!(step.abs() < 2.0, "{index}: {step}");
+        }
+    }
+}
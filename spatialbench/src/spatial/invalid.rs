@@ -0,0 +1,177 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Injection of invalid geometries
+//!
+//! The generated geometries are valid. With an invalid rate, a fraction of
+//! the building boundaries is made invalid in one of the ways real data
+//! often is, and flagged as such, to benchmark validation and repair. The
+//! invalid rows are chosen from their index with a low discrepancy
+//! sequence, so any range of rows has close to the rate of them.
+
+use crate::spatial::utils::{spider_seed_for_index, weyl_unit};
+use geo::{coord, BoundingRect, Geometry, LineString, MultiPolygon, Polygon};
+use once_cell::sync::OnceCell;
+use std::fmt;
+
+/// The ways a geometry is made invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidKind {
+    /// The polygon is replaced by a bowtie over its bounding box, a ring
+    /// crossing itself
+    Bowtie,
+    /// The rings are written without their closing coordinate
+    ///
+    /// The geometry types always close their rings, so the geometry is
+    /// unchanged and the rings are unclosed when written as WKB.
+    UnclosedRing,
+    /// The ring goes back to a vertex it already passed, so it touches
+    /// itself along an edge
+    DuplicateVertex,
+}
+
+impl InvalidKind {
+    pub const ALL: [InvalidKind; 3] = [
+        InvalidKind::Bowtie,
+        InvalidKind::UnclosedRing,
+        InvalidKind::DuplicateVertex,
+    ];
+
+    /// Return the kind of invalid geometry of the row with `index`, or
+    /// `None` if it stays valid, for a fraction `rate` of invalid rows
+    pub fn of_row(index: u64, seed: u64, rate: f64) -> Option<InvalidKind> {
+        if rate <= 0.0 {
+            return None;
+        }
+        let u = weyl_unit(index, seed, 0x1A_7A11D);
+        (u < rate).then(|| {
+            let kind = spider_seed_for_index(index, seed ^ 0x1A_7A11D) % 3;
+            Self::ALL[kind as usize]
+        })
+    }
+
+    /// Make `geometry` invalid in this way
+    ///
+    /// Only polygons are changed, or the first polygon of multi polygons;
+    /// other geometries are returned unchanged.
+    pub fn apply(&self, geometry: Geometry) -> Geometry {
+        match geometry {
+            Geometry::Polygon(polygon) => Geometry::Polygon(self.apply_polygon(polygon)),
+            Geometry::MultiPolygon(MultiPolygon(mut polygons)) if !polygons.is_empty() => {
+                polygons[0] = self.apply_polygon(polygons[0].clone());
+                Geometry::MultiPolygon(MultiPolygon(polygons))
+            }
+            geometry => geometry,
+        }
+    }
+
+    fn apply_polygon(&self, polygon: Polygon) -> Polygon {
+        match self {
+            InvalidKind::Bowtie => {
+                let Some(rect) = polygon.bounding_rect() else {
+                    return polygon;
+                };
+                let (min, max) = (rect.min(), rect.max());
+                let ring = vec![
+                    min,
+                    max,
+                    coord! { x: max.x, y: min.y },
+                    coord! { x: min.x, y: max.y },
+                ];
+                Polygon::new(LineString::new(ring), vec![])
+            }
+            InvalidKind::UnclosedRing => polygon,
+            InvalidKind::DuplicateVertex => {
+                let (exterior, interiors) = polygon.into_inner();
+                let mut ring = exterior.0;
+                if ring.len() < 4 {
+                    return Polygon::new(LineString::new(ring), interiors);
+                }
+                // v0 v1 v2 v1 v3 ...: back along the edge to v1
+                ring.insert(3, ring[1]);
+                Polygon::new(LineString::new(ring), interiors)
+            }
+        }
+    }
+}
+
+impl fmt::Display for InvalidKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidKind::Bowtie => write!(f, "bowtie"),
+            InvalidKind::UnclosedRing => write!(f, "unclosed ring"),
+            InvalidKind::DuplicateVertex => write!(f, "duplicate vertex"),
+        }
+    }
+}
+
+static INVALID_RATE: OnceCell<f64> = OnceCell::new();
+
+/// Set the fraction of building boundaries made invalid
+///
+/// Only the first call has an effect.
+pub fn set_invalid_rate(rate: f64) {
+    let _ = INVALID_RATE.set(rate);
+}
+
+/// Return the rate set with [`set_invalid_rate`], or 0
+pub fn invalid_rate() -> f64 {
+    INVALID_RATE.get().copied().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{polygon, Validation};
+
+    #[test]
+    fn test_of_row() {
+        let invalid: Vec<_> = (0..10_000)
+            .filter_map(|index| InvalidKind::of_row(index, 3, 0.01))
+            .collect();
+        assert!((99..=101).contains(&invalid.len()), "{}", invalid.len());
+        for kind in InvalidKind::ALL {
+            assert!(invalid.contains(&kind), "{kind}");
+        }
+        assert_eq!(InvalidKind::of_row(5, 3, 0.0), None);
+        assert!((0..100).all(|index| InvalidKind::of_row(index, 3, 1.0).is_some()));
+    }
+
+    #[test]
+    fn test_apply() {
+        let square = Geometry::Polygon(polygon![
+            (x: 0.0, y: 0.0),
+            (x: 1.0, y: 0.0),
+            (x: 1.0, y: 1.0),
+            (x: 0.0, y: 1.0),
+        ]);
+        assert!(square.is_valid());
+        assert!(!InvalidKind::Bowtie.apply(square.clone()).is_valid());
+        assert!(!InvalidKind::DuplicateVertex
+            .apply(square.clone())
+            .is_valid());
+        assert_eq!(InvalidKind::UnclosedRing.apply(square.clone()), square);
+
+        let Geometry::Polygon(triangle) = InvalidKind::DuplicateVertex.apply(Geometry::Polygon(
+            polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 0.0, y: 1.0)],
+        )) else {
+            panic!("not a polygon");
+        };
+        assert_eq!(triangle.exterior().0.len(), 5);
+        assert!(!triangle.is_valid());
+    }
+}
@@ -0,0 +1,364 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A synthetic land/water mask of continents and islands with procedural
+//! coastlines
+
+use crate::spatial::precision::snap_coordinates;
+use crate::spatial::utils::continent::build_continent_cdf;
+use crate::spatial::utils::{apply_affine, hash_to_unit_u64, spider_seed_for_index};
+use crate::spatial::ContinentAffines;
+use geo::{Coord, LineString, MultiPolygon, Point, Polygon};
+use once_cell::sync::OnceCell;
+use std::f64::consts::TAU;
+use std::sync::{Arc, Mutex};
+
+/// Number of vertices of the coastline of a mainland
+pub const MAINLAND_VERTICES: usize = 4096;
+/// Number of vertices of the coastline of an island
+pub const ISLAND_VERTICES: usize = 256;
+/// Largest number of islands of a continent
+pub const MAX_ISLANDS: usize = 12;
+
+/// Mean radius of a mainland, in the unit square of its continent
+const MAINLAND_RADIUS: f64 = 0.34;
+/// Mean radius of the largest islands
+const ISLAND_RADIUS: f64 = 0.04;
+/// Amplitude of the first octave of a coastline, relative to the mean
+/// radius. The amplitudes of all the octaves add up to less than twice this,
+/// so the coastline of a mainland is between 0.2 and 0.48 away from its
+/// center, and that of an island at most 0.056 away from its center.
+const ROUGHNESS: f64 = 0.2;
+/// Number of octaves of the coastline of a mainland, the highest has 8
+/// vertices per period
+const OCTAVES: u32 = 10;
+/// Smallest and largest distance of an island from the center of the
+/// mainland, so the islands never touch the mainland or each other
+const ISLAND_DISTANCE: (f64, f64) = (0.54, 0.64);
+
+/// A star shaped piece of land with a fractal coastline, in the unit square
+/// of its continent
+#[derive(Debug, Clone)]
+struct Shape {
+    center: (f64, f64),
+    /// The coastline, counterclockwise, vertex `j` at angle `TAU * j / n`
+    /// from the center
+    coastline: Vec<(f64, f64)>,
+    /// Largest distance of the coastline from the center
+    max_radius: f64,
+}
+
+impl Shape {
+    /// Create a shape of mean radius `radius` with `vertices` vertices
+    ///
+    /// The distance of the coastline from the center is a sum of sines of
+    /// doubling (integer) frequencies and halving amplitudes, like the
+    /// coastline of the [`Coastline`](crate::spatial::DistributionType::Coastline)
+    /// distribution, and so is continuous all around the shape.
+    fn new(center: (f64, f64), radius: f64, vertices: usize, seed: u64) -> Self {
+        let octaves = OCTAVES.min((vertices / 8).ilog2() + 1);
+        let phases: Vec<f64> = (0..octaves)
+            .map(|octave| hash_to_unit_u64(octave as u64, seed) * TAU)
+            .collect();
+        let mut max_radius: f64 = 0.0;
+        let coastline = (0..vertices)
+            .map(|j| {
+                let angle = TAU * j as f64 / vertices as f64;
+                let noise: f64 = phases
+                    .iter()
+                    .enumerate()
+                    .map(|(octave, phase)| {
+                        let frequency = (1u64 << octave) as f64;
+                        ROUGHNESS / frequency * (frequency * angle + phase).sin()
+                    })
+                    .sum();
+                let r = radius * (1.0 + noise);
+                max_radius = max_radius.max(r);
+                (center.0 + r * angle.cos(), center.1 + r * angle.sin())
+            })
+            .collect();
+        Self {
+            center,
+            coastline,
+            max_radius,
+        }
+    }
+
+    /// Return true if `x`, `y` is inside the coastline
+    ///
+    /// The shape is star shaped, so this only checks the edge of the
+    /// coastline in the direction of the point.
+    fn contains(&self, x: f64, y: f64) -> bool {
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        if dx.hypot(dy) > self.max_radius {
+            return false;
+        }
+        let n = self.coastline.len();
+        let angle = dy.atan2(dx).rem_euclid(TAU);
+        let j = ((angle / TAU * n as f64) as usize).min(n - 1);
+        let (a, b) = (self.coastline[j], self.coastline[(j + 1) % n]);
+        // the center is on the left of the counterclockwise edge
+        (b.0 - a.0) * (y - a.1) - (b.1 - a.1) * (x - a.0) >= 0.0
+    }
+
+    /// Return the coastline transformed by `affine`, as a counterclockwise
+    /// polygon
+    fn polygon(&self, affine: &[f64; 6]) -> Polygon {
+        let mut coords: Vec<Coord> = self
+            .coastline
+            .iter()
+            .map(|&(x, y)| {
+                let (x, y) = apply_affine(x, y, affine);
+                let (x, y) = snap_coordinates(x, y);
+                Coord { x, y }
+            })
+            .collect();
+        coords.push(coords[0]);
+        // the affines of the continents flip the y axis
+        if affine[0] * affine[4] - affine[1] * affine[3] < 0.0 {
+            coords.reverse();
+        }
+        Polygon::new(LineString::new(coords), vec![])
+    }
+}
+
+/// The land of a continent: a mainland filling most of the bounding box of
+/// the continent, and a few islands around it
+#[derive(Debug, Clone)]
+pub struct Continent {
+    name: &'static str,
+    /// Maps the unit square to the bounding box of the continent
+    affine: [f64; 6],
+    mainland: Shape,
+    islands: Vec<Shape>,
+}
+
+impl Continent {
+    fn new(name: &'static str, affine: [f64; 6], seed: u64) -> Self {
+        let mainland = Shape::new((0.5, 0.5), MAINLAND_RADIUS, MAINLAND_VERTICES, seed);
+        let random = |salt: u64| hash_to_unit_u64(salt, seed ^ 0x15_1A4D);
+        let count = 3 + (random(0) * (MAX_ISLANDS - 2) as f64) as usize;
+        let islands = (0..count)
+            .map(|i| {
+                let salt = 4 * (i as u64 + 1);
+                // spread around the mainland, at most a quarter of the
+                // angle between two islands away from an even spread
+                let angle = TAU * (i as f64 + 0.5 * random(salt) - 0.25) / count as f64;
+                let (min, max) = ISLAND_DISTANCE;
+                let distance = min + (max - min) * random(salt + 1);
+                let radius = ISLAND_RADIUS * (0.4 + 0.6 * random(salt + 2));
+                let center = (0.5 + distance * angle.cos(), 0.5 + distance * angle.sin());
+                Shape::new(center, radius, ISLAND_VERTICES, seed ^ salt)
+            })
+            .collect();
+        Self {
+            name,
+            affine,
+            mainland,
+            islands,
+        }
+    }
+
+    /// Return the name of the continent, e.g. `africa`
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Return the number of islands
+    pub fn island_count(&self) -> usize {
+        self.islands.len()
+    }
+
+    /// Return the land of the continent, the mainland first
+    pub fn boundary(&self) -> MultiPolygon {
+        std::iter::once(&self.mainland)
+            .chain(&self.islands)
+            .map(|shape| shape.polygon(&self.affine))
+            .collect()
+    }
+
+    /// Return true if `point` is on the land of the continent
+    pub fn contains(&self, point: Point) -> bool {
+        let (x, y) = self.to_unit(point);
+        std::iter::once(&self.mainland)
+            .chain(&self.islands)
+            .any(|shape| shape.contains(x, y))
+    }
+
+    /// Return `point` if it is on land, otherwise the point in the
+    /// direction of `point` from the center of the mainland that is closer
+    /// to the center than any of its coastline
+    pub fn inland(&self, point: Point) -> Point {
+        if self.contains(point) {
+            return point;
+        }
+        let (x, y) = self.to_unit(point);
+        let (cx, cy) = self.mainland.center;
+        let distance = (x - cx).hypot(y - cy).max(f64::EPSILON);
+        let scale = (1.0 - 2.0 * ROUGHNESS) * MAINLAND_RADIUS * 0.99 / distance;
+        let (x, y) = apply_affine(cx + (x - cx) * scale, cy + (y - cy) * scale, &self.affine);
+        let (x, y) = snap_coordinates(x, y);
+        Point::new(x, y)
+    }
+
+    /// Return the position of `point` in the unit square of the continent
+    fn to_unit(&self, point: Point) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.affine;
+        let (x, y) = (point.x() - c, point.y() - f);
+        let determinant = a * e - b * d;
+        ((e * x - b * y) / determinant, (a * y - d * x) / determinant)
+    }
+}
+
+/// The land of all the continents, in the order of the continents the trips
+/// and buildings are generated in
+#[derive(Debug, Clone)]
+pub struct LandMask {
+    continents: Vec<Continent>,
+}
+
+impl LandMask {
+    /// Create the land of the continents, the shapes only depend on `seed`
+    pub fn new(seed: u64) -> Self {
+        let affines = ContinentAffines::default();
+        let continents = build_continent_cdf(&affines)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, affine, _cdf))| {
+                let name = CONTINENT_NAMES
+                    .iter()
+                    .find(|&&known| known == name)
+                    .expect("known continent");
+                let seed = spider_seed_for_index(i as u64, seed ^ 0x1A4D_3A55);
+                Continent::new(name, affine, seed)
+            })
+            .collect();
+        Self { continents }
+    }
+
+    /// Return the continents
+    pub fn continents(&self) -> &[Continent] {
+        &self.continents
+    }
+
+    /// Return true if `point` is on the land of any continent
+    pub fn contains(&self, point: Point) -> bool {
+        self.continents
+            .iter()
+            .any(|continent| continent.contains(point))
+    }
+}
+
+/// Number of continents, each with its own land
+pub const CONTINENT_COUNT: usize = CONTINENT_NAMES.len();
+
+/// Names of the continents of [`ContinentAffines`]
+const CONTINENT_NAMES: [&str; 8] = [
+    "africa",
+    "europe",
+    "south_asia",
+    "north_asia",
+    "oceania",
+    "south_america",
+    "south_north_america",
+    "north_north_america",
+];
+
+/// Returns the land mask of `seed`
+///
+/// The last mask is kept, as all the parts of the landmass and trip tables
+/// use it.
+pub fn land_mask(seed: u64) -> Arc<LandMask> {
+    static LAST: Mutex<Option<(u64, Arc<LandMask>)>> = Mutex::new(None);
+    let mut last = LAST.lock().unwrap();
+    if let Some((last_seed, mask)) = last.as_ref() {
+        if *last_seed == seed {
+            return Arc::clone(mask);
+        }
+    }
+    let mask = Arc::new(LandMask::new(seed));
+    *last = Some((seed, Arc::clone(&mask)));
+    mask
+}
+
+static ON_LAND: OnceCell<bool> = OnceCell::new();
+
+/// Keep the trips generated by [`TripGenerator::new`] on land
+///
+/// Only the first call has an effect.
+///
+/// [`TripGenerator::new`]: crate::generators::TripGenerator::new
+pub fn set_on_land(on_land: bool) {
+    let _ = ON_LAND.set(on_land);
+}
+
+/// Return whether the trips are kept on land, see [`set_on_land`]
+pub fn on_land() -> bool {
+    ON_LAND.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Area, Contains, Validation};
+
+    #[test]
+    fn test_land_mask() {
+        let mask = LandMask::new(0);
+        assert_eq!(mask.continents().len(), 8);
+        for continent in mask.continents() {
+            let boundary = continent.boundary();
+            assert!(boundary.is_valid(), "{}", continent.name());
+            assert_eq!(boundary.0.len(), continent.island_count() + 1);
+            assert!((3..=MAX_ISLANDS).contains(&continent.island_count()));
+            assert_eq!(boundary.0[0].exterior().0.len(), MAINLAND_VERTICES + 1);
+            // counterclockwise
+            assert!(boundary.signed_area() > 0.0, "{}", continent.name());
+        }
+        // the shapes depend on the seed
+        let other = LandMask::new(1);
+        assert_ne!(
+            mask.continents()[0].boundary(),
+            other.continents()[0].boundary()
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let mask = LandMask::new(0);
+        for continent in mask.continents() {
+            let boundary = continent.boundary();
+            let [a, _, c, _, e, f] = continent.affine;
+            let mut on_land = 0;
+            for i in 0..2000 {
+                let (u, v) = (hash_to_unit_u64(i, 1), hash_to_unit_u64(i, 2));
+                let point = Point::new(c + a * u, f + e * v);
+                let contains = continent.contains(point);
+                assert_eq!(contains, boundary.contains(&point), "{point:?}");
+                on_land += contains as usize;
+
+                let inland = continent.inland(point);
+                assert!(continent.contains(inland), "{point:?} {inland:?}");
+                if contains {
+                    assert_eq!(inland, point);
+                }
+            }
+            // the mainland covers about a third of the bounding box
+            assert!((400..1200).contains(&on_land), "{on_land}");
+        }
+    }
+}
@@ -0,0 +1,246 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Mixes of geometry types in a generated geometry column
+
+use crate::spatial::precision::snap_coordinates;
+use crate::spatial::utils::{spider_seed_for_index, weyl_unit};
+use geo::{BoundingRect, Geometry, MapCoords, MultiPolygon, Polygon, Translate};
+use std::fmt;
+use std::str::FromStr;
+
+/// The types of geometries in a [`GeometryMix`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeometryKind {
+    Polygon,
+    MultiPolygon,
+}
+
+impl fmt::Display for GeometryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryKind::Polygon => write!(f, "polygon"),
+            GeometryKind::MultiPolygon => write!(f, "multipolygon"),
+        }
+    }
+}
+
+impl FromStr for GeometryKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "polygon" => Ok(GeometryKind::Polygon),
+            "multipolygon" => Ok(GeometryKind::MultiPolygon),
+            _ => Err(format!(
+                "Invalid geometry type {s}, expected polygon or multipolygon"
+            )),
+        }
+    }
+}
+
+/// The fraction of each type of geometry in a generated column, e.g.
+/// `polygon:0.7,multipolygon:0.3`
+///
+/// The type of each row is chosen from its index with a low discrepancy
+/// sequence, so any range of rows has close to the given fractions. Multi
+/// polygons are made of the generated polygon and 1 or 2 translated copies
+/// of it, next to each other and not overlapping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeometryMix {
+    /// The types with their cumulative fractions, ending with 1
+    cdf: Vec<(GeometryKind, f64)>,
+}
+
+impl Default for GeometryMix {
+    /// Only polygons, as generated without a mix
+    fn default() -> Self {
+        Self {
+            cdf: vec![(GeometryKind::Polygon, 1.0)],
+        }
+    }
+}
+
+impl GeometryMix {
+    /// Create a mix from the fraction of each type, which must add up to 1
+    pub fn try_new(fractions: &[(GeometryKind, f64)]) -> Result<Self, String> {
+        let mut cdf: Vec<(GeometryKind, f64)> = Vec::with_capacity(fractions.len());
+        let mut total = 0.0;
+        for &(kind, fraction) in fractions {
+            if !(0.0..=1.0).contains(&fraction) {
+                return Err(format!(
+                    "the fraction of {kind} must be in [0, 1], got {fraction}"
+                ));
+            }
+            if cdf.iter().any(|&(k, _)| k == kind) {
+                return Err(format!("{kind} is listed more than once"));
+            }
+            total += fraction;
+            cdf.push((kind, total));
+        }
+        if (total - 1.0).abs() > 1e-6 {
+            return Err(format!("the fractions must add up to 1, got {total}"));
+        }
+        if let Some(last) = cdf.last_mut() {
+            last.1 = 1.0;
+        }
+        Ok(Self { cdf })
+    }
+
+    /// Return the fraction of `kind` in the mix
+    pub fn fraction(&self, kind: GeometryKind) -> f64 {
+        let mut previous = 0.0;
+        for &(k, cumulative) in &self.cdf {
+            if k == kind {
+                return cumulative - previous;
+            }
+            previous = cumulative;
+        }
+        0.0
+    }
+
+    /// Return the type of the geometry of the row with `index`
+    pub fn kind(&self, index: u64, seed: u64) -> GeometryKind {
+        if let [(kind, _)] = self.cdf[..] {
+            return kind;
+        }
+        let u = weyl_unit(index, seed, 0x6E0_313C);
+        self.cdf
+            .iter()
+            .find(|&&(_, cumulative)| u < cumulative)
+            .map_or(self.cdf[self.cdf.len() - 1].0, |&(kind, _)| kind)
+    }
+
+    /// Convert the generated `geometry` of the row with `index` to its type
+    /// in the mix
+    ///
+    /// Only polygons are converted, other geometries are returned unchanged.
+    pub fn apply(&self, index: u64, seed: u64, geometry: Geometry) -> Geometry {
+        match (self.kind(index, seed), geometry) {
+            (GeometryKind::MultiPolygon, Geometry::Polygon(polygon)) => {
+                let parts = 2 + spider_seed_for_index(index, seed) % 2;
+                Geometry::MultiPolygon(multi_polygon(polygon, parts as usize))
+            }
+            (_, geometry) => geometry,
+        }
+    }
+}
+
+impl FromStr for GeometryMix {
+    type Err = String;
+
+    /// Parse a mix such as `polygon:0.7,multipolygon:0.3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fractions = vec![];
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((kind, fraction)) = entry.split_once(':') else {
+                return Err(format!(
+                    "expected <type>:<fraction>, e.g. multipolygon:0.3, got '{entry}'"
+                ));
+            };
+            let fraction: f64 = fraction
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid fraction '{}'", fraction.trim()))?;
+            fractions.push((kind.trim().parse()?, fraction));
+        }
+        Self::try_new(&fractions)
+    }
+}
+
+/// Return a multi polygon of `polygon` and `parts - 1` copies of it,
+/// translated next to each other along the longitude
+fn multi_polygon(polygon: Polygon, parts: usize) -> MultiPolygon {
+    let Some(rect) = polygon.bounding_rect() else {
+        return MultiPolygon::new(vec![polygon]);
+    };
+    // leave a gap of half the width between the parts, towards the east
+    // unless they would cross the antimeridian
+    let mut step = 1.5 * rect.width();
+    if rect.max().x + step * (parts - 1) as f64 > 180.0 {
+        step = -step;
+    }
+    let copies: Vec<_> = (1..parts)
+        .map(|i| {
+            polygon.translate(step * i as f64, 0.0).map_coords(|c| {
+                let (x, y) = snap_coordinates(c.x, c.y);
+                geo::coord! { x: x, y: y }
+            })
+        })
+        .collect();
+    MultiPolygon::new(std::iter::once(polygon).chain(copies).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Intersects, Validation};
+
+    #[test]
+    fn test_parse_geometry_mix() {
+        let mix: GeometryMix = "polygon:0.7, MultiPolygon:0.3".parse().unwrap();
+        assert!((mix.fraction(GeometryKind::MultiPolygon) - 0.3).abs() < 1e-9);
+        assert_eq!(GeometryMix::default().fraction(GeometryKind::Polygon), 1.0);
+
+        for (value, error) in [
+            ("polygon", "expected <type>:<fraction>"),
+            ("point:1", "Invalid geometry type point"),
+            ("polygon:x", "invalid fraction 'x'"),
+            ("polygon:1.5", "the fraction of polygon must be in [0, 1]"),
+            (
+                "polygon:0.5,polygon:0.5",
+                "polygon is listed more than once",
+            ),
+            (
+                "polygon:0.5,multipolygon:0.1",
+                "the fractions must add up to 1",
+            ),
+        ] {
+            let err = value.parse::<GeometryMix>().unwrap_err();
+            assert!(err.starts_with(error), "{value}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_geometry_mix_fractions() {
+        let mix: GeometryMix = "polygon:0.7,multipolygon:0.3".parse().unwrap();
+        // the fraction is close to 0.3 in any range of rows
+        for start in [0, 12_345, 1 << 40] {
+            let multi = (start..start + 1000)
+                .filter(|&i| mix.kind(i, 0) == GeometryKind::MultiPolygon)
+                .count();
+            assert!((290..=310).contains(&multi), "{start}: {multi}");
+        }
+    }
+
+    #[test]
+    fn test_multi_polygons_are_valid() {
+        let polygon = Polygon::new(
+            vec![(179.0, 0.0), (179.5, 0.0), (179.5, 1.0), (179.0, 0.0)].into(),
+            vec![],
+        );
+        let multi = multi_polygon(polygon, 3);
+        assert_eq!(multi.0.len(), 3);
+        assert!(multi.is_valid());
+        assert!(!multi.0[0].intersects(&multi.0[1]));
+        // translated to the west of the antimeridian
+        assert!(multi
+            .0
+            .iter()
+            .all(|p| p.bounding_rect().unwrap().max().x <= 180.0));
+    }
+}
@@ -15,15 +15,42 @@
 // specific language governing permissions and limitations
 // under the License.
 
+pub mod admin;
 pub mod cache;
+pub mod cell;
 pub mod config;
+pub mod crs;
 pub mod defaults;
+pub mod dimensions;
 pub mod distributions;
+pub mod duplicate;
+pub mod edge_cases;
 pub mod generator;
+pub mod geography;
 pub mod geometry;
+pub mod grid;
+pub mod invalid;
+pub mod landmass;
+pub mod mix;
 pub mod overrides;
+pub mod point_cloud;
+pub mod precision;
+pub mod raster;
+pub mod road;
+pub mod trajectory;
 pub mod utils;
 
+pub use admin::{AdminHierarchy, AdminLevel};
+pub use cell::CellIndex;
 pub use config::*;
+pub use crs::Crs;
 pub use defaults::*;
+pub use dimensions::Dimensions;
 pub use generator::SpatialGenerator;
+pub use grid::RasterGrid;
+pub use landmass::LandMask;
+pub use mix::{GeometryKind, GeometryMix};
+pub use point_cloud::PointCloud;
+pub use precision::CoordinatePrecision;
+pub use raster::DensityRaster;
+pub use road::RoadNetwork;
@@ -0,0 +1,182 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A synthetic point cloud, like the returns of an airborne LiDAR survey
+//!
+//! The points are grouped in square tiles of [`POINTS_PER_TILE`] points, as
+//! a survey is delivered, placed on the continents. Each point only depends
+//! on its index and the seed, with a few hashes and the [`elevation`] of the
+//! terrain, so the points are cheap to generate and any range of them can be
+//! generated on its own.
+
+use crate::spatial::dimensions::elevation;
+use crate::spatial::precision::snap_coordinates;
+use crate::spatial::utils::continent::build_continent_cdf;
+use crate::spatial::utils::{apply_affine, hash_to_unit_u64, pick_from_cdf, round_coordinate};
+use crate::spatial::ContinentAffines;
+
+/// Number of points of a tile
+pub const POINTS_PER_TILE: usize = 100_000;
+/// Side of a tile, in degrees, about a kilometer
+const TILE_SIZE: f64 = 0.01;
+/// Z values are rounded to centimeters
+const Z_PRECISION: f64 = 100.0;
+
+/// ASPRS classification of the ground returns
+pub const CLASS_GROUND: i16 = 2;
+/// ASPRS classification of the low vegetation returns
+pub const CLASS_LOW_VEGETATION: i16 = 3;
+/// ASPRS classification of the high vegetation returns
+pub const CLASS_HIGH_VEGETATION: i16 = 5;
+/// ASPRS classification of the building returns
+pub const CLASS_BUILDING: i16 = 6;
+/// ASPRS classification of the water returns
+pub const CLASS_WATER: i16 = 9;
+
+/// The classes of the points, the share of the points of each class, the
+/// largest height of the points above the ground and their intensity range
+const CLASSES: [(i16, f64, f64, (f64, f64)); 5] = [
+    (CLASS_GROUND, 0.55, 0.0, (800.0, 2500.0)),
+    (CLASS_LOW_VEGETATION, 0.1, 2.0, (400.0, 1500.0)),
+    (CLASS_HIGH_VEGETATION, 0.2, 25.0, (200.0, 1200.0)),
+    (CLASS_BUILDING, 0.1, 30.0, (1000.0, 4000.0)),
+    (CLASS_WATER, 0.05, 0.0, (0.0, 300.0)),
+];
+
+/// A point of the cloud
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CloudPoint {
+    /// Longitude
+    pub x: f64,
+    /// Latitude
+    pub y: f64,
+    /// Elevation, in meters
+    pub z: f64,
+    /// Strength of the return, from 0 to 65535 like in LAS files
+    pub intensity: i32,
+    /// ASPRS classification, such as [`CLASS_GROUND`]
+    pub classification: i16,
+}
+
+/// The tiles of points on the continents
+#[derive(Debug, Clone)]
+pub struct PointCloud {
+    /// Maps the unit square to the bounding box of each continent
+    affines: Vec<[f64; 6]>,
+    /// Cumulative share of the tiles on each continent, by area
+    cdf: Vec<f64>,
+    seed: u64,
+}
+
+impl PointCloud {
+    /// Create the point cloud, the tiles and points depend on `seed`
+    pub fn new(seed: u64) -> Self {
+        let (affines, cdf) = build_continent_cdf(&ContinentAffines::default())
+            .into_iter()
+            .map(|(_name, affine, cdf)| (affine, cdf))
+            .unzip();
+        Self { affines, cdf, seed }
+    }
+
+    /// Return the longitude and latitude of the south west corner of the
+    /// tile of the point at `index`
+    pub fn tile_origin(&self, index: usize) -> (f64, f64) {
+        let tile = (index / POINTS_PER_TILE) as u64;
+        let u = |salt: u64| hash_to_unit_u64(3 * tile + salt, self.seed ^ 0x7C_1D);
+        let affine = &self.affines[pick_from_cdf(&self.cdf, u(0))];
+        let (x, y) = apply_affine(u(1), u(2), affine);
+        // the tiles stay within the valid longitudes and latitudes
+        (
+            x.clamp(-180.0, 180.0 - TILE_SIZE),
+            y.clamp(-90.0, 90.0 - TILE_SIZE),
+        )
+    }
+
+    /// Return the point at `index`, in the tile at `origin`, the
+    /// [`Self::tile_origin`] of the point
+    ///
+    /// The origin is a parameter so it is only computed once for the points
+    /// of a tile.
+    pub fn point(&self, index: usize, origin: (f64, f64)) -> CloudPoint {
+        self.point_with_z(index, origin, true)
+    }
+
+    /// Return the point at `index` like [`Self::point`], with a zero
+    /// elevation unless `with_z`, which skips the elevation of the terrain
+    pub fn point_with_z(&self, index: usize, origin: (f64, f64), with_z: bool) -> CloudPoint {
+        let key = 4 * index as u64;
+        let u = |salt: u64| hash_to_unit_u64(key + salt, self.seed ^ 0x9017_C10D);
+        let (x, y) = snap_coordinates(origin.0 + TILE_SIZE * u(0), origin.1 + TILE_SIZE * u(1));
+        let class = u(2);
+        let mut cumulative = 0.0;
+        let (classification, _, max_height, (low, high)) = CLASSES
+            .iter()
+            .copied()
+            .find(|&(_, share, _, _)| {
+                cumulative += share;
+                class < cumulative
+            })
+            .unwrap_or(CLASSES[0]);
+        // the same hash gives the height and the intensity, the higher
+        // returns of a class are weaker
+        let v = u(3);
+        let z = if with_z {
+            round_coordinate(elevation(x, y) + max_height * v, Z_PRECISION)
+        } else {
+            0.0
+        };
+        CloudPoint {
+            x,
+            y,
+            z,
+            intensity: (high - (high - low) * v) as i32,
+            classification,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_cloud() {
+        let cloud = PointCloud::new(0);
+        let origin = cloud.tile_origin(0);
+        assert_eq!(cloud.tile_origin(POINTS_PER_TILE - 1), origin);
+        assert_ne!(cloud.tile_origin(POINTS_PER_TILE), origin);
+        assert_ne!(PointCloud::new(1).tile_origin(0), origin);
+
+        let points: Vec<_> = (0..10_000)
+            .map(|index| cloud.point(index, origin))
+            .collect();
+        let mut counts = [0; 10];
+        for point in &points {
+            // in the tile, above the ground
+            assert!(point.x >= origin.0 && point.x <= origin.0 + TILE_SIZE);
+            assert!(point.y >= origin.1 && point.y <= origin.1 + TILE_SIZE);
+            assert!(point.z >= elevation(point.x, point.y) - 0.01);
+            assert!((0..=u16::MAX as i32).contains(&point.intensity));
+            counts[point.classification as usize] += 1;
+        }
+        // the ground is the most common class
+        assert!(counts
+            .iter()
+            .all(|&count| count <= counts[CLASS_GROUND as usize]));
+        assert!(counts[CLASS_WATER as usize] > 0);
+    }
+}
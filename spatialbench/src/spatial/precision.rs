@@ -0,0 +1,137 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Precision of the longitude / latitude of the generated geometries
+//!
+//! The coordinates are rounded to 9 decimal digits as they are generated.
+//! With [`set_precision`], they are rounded to fewer digits or snapped to a
+//! grid instead, matching the precision an engine stores them with. The
+//! coordinates are snapped before anything is derived from them, so the
+//! duplicates, the written data and the answers computed from the
+//! generators all have the same coordinates.
+
+use crate::spatial::utils::round_coordinate;
+use once_cell::sync::OnceCell;
+use std::fmt;
+
+/// Largest number of decimal digits of the coordinates, as generated
+pub const MAX_DIGITS: u8 = 9;
+
+/// How the coordinates of the generated geometries are rounded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoordinatePrecision {
+    /// Rounded to a number of decimal digits
+    Digits(u8),
+    /// Snapped to the nearest multiple of a grid size, in degrees
+    Grid(f64),
+}
+
+impl Default for CoordinatePrecision {
+    fn default() -> Self {
+        CoordinatePrecision::Digits(MAX_DIGITS)
+    }
+}
+
+impl fmt::Display for CoordinatePrecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoordinatePrecision::Digits(digits) => write!(f, "{digits} digits"),
+            CoordinatePrecision::Grid(size) => write!(f, "grid {size}"),
+        }
+    }
+}
+
+impl CoordinatePrecision {
+    /// Return the precision of `--coordinate-precision` (`digits`) or
+    /// `--grid-size`, if either is set
+    pub fn from_options(digits: Option<u8>, grid_size: Option<f64>) -> Option<Self> {
+        match (digits, grid_size) {
+            (_, Some(size)) => Some(CoordinatePrecision::Grid(size)),
+            (Some(digits), None) => Some(CoordinatePrecision::Digits(digits)),
+            (None, None) => None,
+        }
+    }
+
+    /// Round a coordinate to this precision
+    #[inline]
+    pub fn snap(&self, value: f64) -> f64 {
+        match self {
+            CoordinatePrecision::Digits(digits) => {
+                round_coordinate(value, 10f64.powi(*digits as i32))
+            }
+            CoordinatePrecision::Grid(size) => (value / size).round() * size,
+        }
+    }
+}
+
+/// Parse a grid size in degrees, e.g. `0.0001`
+pub fn parse_grid_size(value: &str) -> Result<f64, String> {
+    let size: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("expected a grid size in degrees, e.g. 0.0001, got '{value}'"))?;
+    if !(size.is_finite() && size > 0.0) {
+        return Err(format!("the grid size must be positive, got {value}"));
+    }
+    Ok(size)
+}
+
+static PRECISION: OnceCell<CoordinatePrecision> = OnceCell::new();
+
+/// Set the precision of the coordinates of the generated geometries
+///
+/// Only the first call has an effect.
+pub fn set_precision(precision: CoordinatePrecision) {
+    let _ = PRECISION.set(precision);
+}
+
+/// Return the precision set with [`set_precision`], or 9 decimal digits
+pub fn precision() -> CoordinatePrecision {
+    PRECISION.get().copied().unwrap_or_default()
+}
+
+/// Round a generated coordinate to the precision set with [`set_precision`]
+#[inline]
+pub fn snap_coordinates(x: f64, y: f64) -> (f64, f64) {
+    let precision = precision();
+    (precision.snap(x), precision.snap(y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap() {
+        let default = CoordinatePrecision::default();
+        let value = 12.345_678_912_345;
+        assert_eq!(default.snap(value), round_coordinate(value, 1e9));
+        assert_eq!(CoordinatePrecision::Digits(3).snap(value), 12.346);
+        assert_eq!(CoordinatePrecision::Digits(0).snap(-value), -12.0);
+        assert_eq!(CoordinatePrecision::Grid(0.25).snap(value), 12.25);
+        assert_eq!(CoordinatePrecision::Grid(0.25).snap(-value), -12.25);
+        assert_eq!(CoordinatePrecision::Grid(2.0).snap(value), 12.0);
+    }
+
+    #[test]
+    fn test_parse_grid_size() {
+        assert_eq!(parse_grid_size("0.0001"), Ok(0.0001));
+        assert!(parse_grid_size("0").is_err());
+        assert!(parse_grid_size("-1").is_err());
+        assert!(parse_grid_size("fine").is_err());
+    }
+}
@@ -0,0 +1,240 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Place geometries in proportion to the weights of a density raster
+
+use crate::spatial::distributions::SpatialDistribution;
+use crate::spatial::geometry::emit_geom;
+use crate::spatial::utils::{hash_to_unit_u64, seeded_rng, spider_seed_for_index};
+use crate::spatial::SpatialConfig;
+use geo::Geometry;
+use rand::Rng;
+
+/// A grid of weights in longitude / latitude, such as population counts
+///
+/// As a [`SpatialDistribution`], each geometry is placed in a cell chosen
+/// in proportion to the weights and uniformly within the cell, so the
+/// geometries concentrate where the weights are high. The raster replaces
+/// the continents the geometries are otherwise placed in.
+///
+/// Rasters are read from the [ESRI ASCII grid] format, which GDAL writes
+/// from other formats such as GeoTIFF:
+///
+/// ```text
+/// gdal_translate -of AAIGrid -tr 0.1 0.1 population.tif population.asc
+/// ```
+///
+/// [ESRI ASCII grid]: https://desktop.arcgis.com/en/arcmap/latest/manage-data/raster-and-images/esri-ascii-raster-format.htm
+#[derive(Debug, Clone)]
+pub struct DensityRaster {
+    ncols: usize,
+    nrows: usize,
+    /// Longitude / latitude of the lower left corner of the grid
+    xll: f64,
+    yll: f64,
+    cellsize: f64,
+    /// Indices (row major, from the north) of the cells with a positive weight
+    cells: Vec<u32>,
+    /// Cumulative weights of `cells`, ending with 1
+    cdf: Vec<f64>,
+}
+
+impl DensityRaster {
+    /// Parse a raster in the ESRI ASCII grid format
+    ///
+    /// Cells with the `NODATA_value` or a negative weight have no
+    /// geometries.
+    pub fn from_ascii_grid(text: &str) -> Result<Self, String> {
+        let mut tokens = text.split_ascii_whitespace().peekable();
+        let (mut ncols, mut nrows, mut cellsize) = (None, None, None);
+        let (mut xll, mut yll, mut centered) = (None, None, false);
+        let mut nodata = None;
+        // the header lines start with a keyword, the values with a number
+        while let Some(key) = tokens.next_if(|t| t.starts_with(|c: char| c.is_ascii_alphabetic())) {
+            let value = tokens
+                .next()
+                .ok_or_else(|| format!("missing value of {key}"))?;
+            let number: f64 = value
+                .parse()
+                .map_err(|_| format!("invalid value of {key}: '{value}'"))?;
+            match key.to_ascii_lowercase().as_str() {
+                "ncols" => ncols = Some(number as usize),
+                "nrows" => nrows = Some(number as usize),
+                "xllcorner" => xll = Some(number),
+                "yllcorner" => yll = Some(number),
+                "xllcenter" => (xll, centered) = (Some(number), true),
+                "yllcenter" => (yll, centered) = (Some(number), true),
+                "cellsize" => cellsize = Some(number),
+                "nodata_value" => nodata = Some(number),
+                _ => return Err(format!("unknown header {key}")),
+            }
+        }
+        let missing = |name| format!("missing header {name}");
+        let ncols = ncols.ok_or_else(|| missing("ncols"))?;
+        let nrows = nrows.ok_or_else(|| missing("nrows"))?;
+        let cellsize = cellsize.ok_or_else(|| missing("cellsize"))?;
+        let mut xll = xll.ok_or_else(|| missing("xllcorner"))?;
+        let mut yll = yll.ok_or_else(|| missing("yllcorner"))?;
+        if !cellsize.is_finite() || cellsize <= 0.0 {
+            return Err(format!("cellsize must be positive, got {cellsize}"));
+        }
+        if centered {
+            xll -= cellsize / 2.0;
+            yll -= cellsize / 2.0;
+        }
+        if ncols * nrows > u32::MAX as usize {
+            return Err(format!("too many cells {ncols}x{nrows}"));
+        }
+
+        let (mut cells, mut cdf) = (vec![], vec![]);
+        let mut total = 0.0;
+        let mut count = 0;
+        for token in tokens {
+            let weight: f64 = token
+                .parse()
+                .map_err(|_| format!("invalid weight '{token}'"))?;
+            if weight > 0.0 && Some(weight) != nodata {
+                total += weight;
+                cells.push(count as u32);
+                cdf.push(total);
+            }
+            count += 1;
+        }
+        if count != ncols * nrows {
+            return Err(format!(
+                "expected {} weights for {ncols}x{nrows} cells, got {count}",
+                ncols * nrows
+            ));
+        }
+        if cells.is_empty() {
+            return Err("all the weights are zero".to_string());
+        }
+        cdf.iter_mut().for_each(|c| *c /= total);
+        *cdf.last_mut().unwrap() = 1.0;
+        Ok(Self {
+            ncols,
+            nrows,
+            xll,
+            yll,
+            cellsize,
+            cells,
+            cdf,
+        })
+    }
+
+    /// Return the longitude / latitude of the point at `x`, `y` in cell
+    /// `cell`
+    fn position(&self, cell: usize, x: f64, y: f64) -> (f64, f64) {
+        // rows start from the north
+        let (row, col) = (cell / self.ncols, cell % self.ncols);
+        (
+            self.xll + (col as f64 + x) * self.cellsize,
+            self.yll + (self.nrows - row) as f64 * self.cellsize - y * self.cellsize,
+        )
+    }
+}
+
+impl SpatialDistribution for DensityRaster {
+    /// The geometries keep the size and shape they have in the continent
+    /// `m`, but are centered on the chosen position
+    fn generate(&self, index: u64, config: &SpatialConfig, m: &[f64; 6]) -> Geometry {
+        let u = hash_to_unit_u64(index, (config.seed as u64) ^ 0xDE75_1770);
+        let i = self
+            .cdf
+            .partition_point(|&c| c < u)
+            .min(self.cells.len() - 1);
+        let mut rng = seeded_rng(spider_seed_for_index(index, config.seed as u64));
+        let (lon, lat) = self.position(self.cells[i] as usize, rng.gen(), rng.gen());
+
+        // translate the center of the unit square to the position
+        let center = (0.5, 0.5);
+        let m = [
+            m[0],
+            m[1],
+            lon - (m[0] * center.0 + m[1] * center.1),
+            m[3],
+            m[4],
+            lat - (m[3] * center.0 + m[4] * center.1),
+        ];
+        emit_geom(center, config.geom_type, config, &mut rng, &m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial::SpatialDefaults;
+
+    const IDENTITY: [f64; 6] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+    const GRID: &str = "ncols 3
+nrows 2
+xllcorner 10
+yllcorner 40
+cellsize 0.5
+NODATA_value -9999
+0 3 -9999
+1 0 0
+";
+
+    #[test]
+    fn test_density_raster() {
+        let raster = DensityRaster::from_ascii_grid(GRID).unwrap();
+        let config = SpatialDefaults::trip_default().config;
+        let mut counts = [0; 2];
+        for index in 0..1000 {
+            let Geometry::Point(point) = raster.generate(index, &config, &IDENTITY) else {
+                panic!("expected a point");
+            };
+            if (10.5..=11.0).contains(&point.x()) && (40.5..=41.0).contains(&point.y()) {
+                counts[0] += 1;
+            } else if (10.0..=10.5).contains(&point.x()) && (40.0..=40.5).contains(&point.y()) {
+                counts[1] += 1;
+            } else {
+                panic!("{point:?} is in a cell without weight");
+            }
+        }
+        // weights 3 and 1
+        assert!((700..=800).contains(&counts[0]), "{counts:?}");
+    }
+
+    #[test]
+    fn test_invalid_raster() {
+        for (text, error) in [
+            (
+                "ncols 2\nnrows 1\nxllcorner 0\nyllcorner 0\n1 2",
+                "missing header cellsize",
+            ),
+            (
+                "ncols 2\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n1",
+                "expected 2 weights",
+            ),
+            (
+                "ncols 1\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n1.2.3",
+                "invalid weight '1.2.3'",
+            ),
+            (
+                "ncols 1\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n0",
+                "all the weights are zero",
+            ),
+            ("ncols 1\nbands 3", "unknown header bands"),
+        ] {
+            let err = DensityRaster::from_ascii_grid(text).unwrap_err();
+            assert!(err.starts_with(error), "{err}");
+        }
+    }
+}
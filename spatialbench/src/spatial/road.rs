@@ -0,0 +1,340 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A synthetic road network of towns with a grid of streets
+
+use crate::spatial::precision::snap_coordinates;
+use crate::spatial::utils::hash_to_unit_u64;
+use geo::{Coord, LineString, Point};
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+
+/// Number of blocks along each side of a town
+pub const TOWN_BLOCKS: usize = 20;
+/// Size of a block in degrees
+pub const BLOCK_SIZE: f64 = 0.004;
+/// Number of streets of a town, one for each row and column of intersections
+pub const STREETS_PER_TOWN: usize = 2 * (TOWN_BLOCKS + 1);
+
+/// Largest offset of an intersection from its place on a regular grid, in
+/// blocks. Below a quarter of a block the streets can only meet at the
+/// intersections.
+const JITTER: f64 = 0.2;
+/// Smallest distance of the centers of two towns in longitude or latitude,
+/// so that the towns do not overlap
+const TOWN_SPAN: f64 = (TOWN_BLOCKS + 1) as f64 * BLOCK_SIZE;
+
+/// An intersection of the streets of a town
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Intersection {
+    town: usize,
+    column: usize,
+    row: usize,
+}
+
+/// A road network of towns, each a grid of streets
+///
+/// The streets of a town are a connected planar graph: every row and column
+/// of intersections is a street, and the streets only meet at the
+/// intersections, which are slightly offset from a regular grid. The towns
+/// do not overlap and are not connected to each other.
+#[derive(Debug, Clone)]
+pub struct RoadNetwork {
+    /// Centers of the towns
+    towns: Vec<(f64, f64)>,
+    /// Size of the cells of `index` in degrees
+    cell_size: f64,
+    /// Towns by the cell of their center
+    index: HashMap<(i64, i64), Vec<usize>>,
+    seed: u64,
+}
+
+impl RoadNetwork {
+    /// Create a network with towns centered on `centers`
+    ///
+    /// A center is skipped if its town would overlap the town of an earlier
+    /// center, so the network has at most `centers.len()` and at least one
+    /// town. The positions of the intersections only depend on `seed`.
+    ///
+    /// # Panics
+    /// If `centers` is empty
+    pub fn new(centers: &[Point], seed: u64) -> Self {
+        assert!(
+            !centers.is_empty(),
+            "a road network needs at least one town"
+        );
+        // about one town per cell for uniformly placed towns
+        let cell_size = (180.0 / (centers.len() as f64).sqrt()).max(TOWN_SPAN);
+        let mut network = Self {
+            towns: vec![],
+            cell_size,
+            index: HashMap::new(),
+            seed,
+        };
+        let half = TOWN_SPAN / 2.0;
+        for center in centers {
+            // towns stay away from the poles and the antimeridian
+            let x = center.x().clamp(-180.0 + half, 180.0 - half);
+            let y = center.y().clamp(-90.0 + half, 90.0 - half);
+            let (cx, cy) = network.cell(x, y);
+            let overlaps = (cx - 1..=cx + 1)
+                .flat_map(|i| (cy - 1..=cy + 1).map(move |j| (i, j)))
+                .filter_map(|cell| network.index.get(&cell))
+                .flatten()
+                .any(|&town| {
+                    let (tx, ty) = network.towns[town];
+                    (tx - x).abs() < TOWN_SPAN && (ty - y).abs() < TOWN_SPAN
+                });
+            if !overlaps {
+                network
+                    .index
+                    .entry((cx, cy))
+                    .or_default()
+                    .push(network.towns.len());
+                network.towns.push((x, y));
+            }
+        }
+        network
+    }
+
+    /// Return the number of towns
+    pub fn town_count(&self) -> usize {
+        self.towns.len()
+    }
+
+    /// Return the number of streets of all the towns
+    pub fn street_count(&self) -> usize {
+        self.towns.len() * STREETS_PER_TOWN
+    }
+
+    /// Return the street `street` (0-based), the streets of town `t` are
+    /// `t * STREETS_PER_TOWN..(t + 1) * STREETS_PER_TOWN`
+    pub fn street(&self, street: usize) -> LineString {
+        let town = street / STREETS_PER_TOWN;
+        let line = street % STREETS_PER_TOWN;
+        let coords = (0..=TOWN_BLOCKS).map(|k| {
+            let (column, row) = if line <= TOWN_BLOCKS {
+                (k, line)
+            } else {
+                (line - TOWN_BLOCKS - 1, k)
+            };
+            self.position(Intersection { town, column, row })
+        });
+        LineString::new(coords.collect())
+    }
+
+    /// Return the class of street `street`, every fifth street of a town is
+    /// a `primary` road and the others are `residential`
+    pub fn street_class(street: usize) -> &'static str {
+        if (street % STREETS_PER_TOWN % (TOWN_BLOCKS + 1)).is_multiple_of(5) {
+            "primary"
+        } else {
+            "residential"
+        }
+    }
+
+    /// Return a shortest route along the streets from the intersection
+    /// nearest to `start` to the intersection nearest to `start + offset`
+    /// in the same town
+    ///
+    /// The route starts in the town nearest to `start`, which may be far
+    /// away if there are few towns, and ends at the edge of the town if the
+    /// offset leaves it. Among the shortest routes it follows the straight
+    /// line, and the route has at least two positions even if it starts and
+    /// ends at the same intersection.
+    pub fn route(&self, start: Point, offset: (f64, f64)) -> LineString {
+        let from = self.nearest_intersection(start);
+        let start = self.position(from);
+        let to = self.town_intersection(from.town, start.x + offset.0, start.y + offset.1);
+
+        let (columns, rows) = (to.column.abs_diff(from.column), to.row.abs_diff(from.row));
+        let mut coords = vec![start];
+        let (mut column_steps, mut row_steps) = (0, 0);
+        let mut at = from;
+        for _ in 0..columns + rows {
+            // step along the street that is behind the straight line
+            if row_steps == rows
+                || (column_steps < columns
+                    && (2 * column_steps + 1) * rows <= (2 * row_steps + 1) * columns)
+            {
+                column_steps += 1;
+                at.column = if to.column > from.column {
+                    at.column + 1
+                } else {
+                    at.column - 1
+                };
+            } else {
+                row_steps += 1;
+                at.row = if to.row > from.row {
+                    at.row + 1
+                } else {
+                    at.row - 1
+                };
+            }
+            coords.push(self.position(at));
+        }
+        if coords.len() == 1 {
+            coords.push(start);
+        }
+        LineString::new(coords)
+    }
+
+    /// Return the cell of `index` of the position `x`, `y`
+    fn cell(&self, x: f64, y: f64) -> (i64, i64) {
+        (
+            (x / self.cell_size).floor() as i64,
+            (y / self.cell_size).floor() as i64,
+        )
+    }
+
+    /// Return the intersection of the nearest town nearest to `point`
+    fn nearest_intersection(&self, point: Point) -> Intersection {
+        let (x, y) = point.x_y();
+        let (cx, cy) = self.cell(x, y);
+        let mut nearest: Option<(f64, usize)> = None;
+        // visit the cells in rings around the cell of the point, the towns
+        // in ring r are at least r - 1 cells away
+        let max_ring = (360.0 / self.cell_size).ceil() as i64 + 1;
+        for ring in 0..=max_ring {
+            if matches!(nearest, Some((distance, _)) if distance <= (ring - 1) as f64 * self.cell_size)
+            {
+                break;
+            }
+            for i in cx - ring..=cx + ring {
+                for j in cy - ring..=cy + ring {
+                    if (i - cx).abs() != ring && (j - cy).abs() != ring {
+                        continue;
+                    }
+                    for &town in self.index.get(&(i, j)).into_iter().flatten() {
+                        let (tx, ty) = self.towns[town];
+                        let distance = (tx - x).hypot(ty - y);
+                        if nearest.is_none_or(|(d, t)| (distance, town) < (d, t)) {
+                            nearest = Some((distance, town));
+                        }
+                    }
+                }
+            }
+        }
+        let (_, town) = nearest.expect("the network has at least one town");
+        self.town_intersection(town, x, y)
+    }
+
+    /// Return the intersection of `town` nearest to `x`, `y`, ignoring the
+    /// offsets of the intersections from the grid
+    fn town_intersection(&self, town: usize, x: f64, y: f64) -> Intersection {
+        let (tx, ty) = self.towns[town];
+        let index = |offset: f64| {
+            let index = (offset / BLOCK_SIZE + TOWN_BLOCKS as f64 / 2.0).round();
+            index.clamp(0.0, TOWN_BLOCKS as f64) as usize
+        };
+        Intersection {
+            town,
+            column: index(x - tx),
+            row: index(y - ty),
+        }
+    }
+
+    /// Return the position of `intersection`
+    fn position(&self, intersection: Intersection) -> Coord {
+        let Intersection { town, column, row } = intersection;
+        let (tx, ty) = self.towns[town];
+        let key = ((town as u64) << 16) | ((column as u64) << 8) | row as u64;
+        let jitter = |salt: u64| (hash_to_unit_u64(key, self.seed ^ salt) * 2.0 - 1.0) * JITTER;
+        let center = TOWN_BLOCKS as f64 / 2.0;
+        let (x, y) = snap_coordinates(
+            tx + (column as f64 - center + jitter(0x5742_EE70)) * BLOCK_SIZE,
+            ty + (row as f64 - center + jitter(0x5742_EE71)) * BLOCK_SIZE,
+        );
+        Coord { x, y }
+    }
+}
+
+static SNAP_TO_ROADS: OnceCell<bool> = OnceCell::new();
+
+/// Snap the trips generated by [`TripGenerator::new`] to the roads
+///
+/// Only the first call has an effect.
+///
+/// [`TripGenerator::new`]: crate::generators::TripGenerator::new
+pub fn set_snap_to_roads(snap_to_roads: bool) {
+    let _ = SNAP_TO_ROADS.set(snap_to_roads);
+}
+
+/// Return whether the trips snap to the roads, see [`set_snap_to_roads`]
+pub fn snap_to_roads() -> bool {
+    SNAP_TO_ROADS.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streets() {
+        let centers = [
+            Point::new(10.0, 20.0),
+            // overlaps the first town
+            Point::new(10.05, 20.05),
+            Point::new(10.1, 20.0),
+            Point::new(179.99, 0.0),
+        ];
+        let network = RoadNetwork::new(&centers, 1);
+        assert_eq!(network.town_count(), 3);
+        assert_eq!(network.street_count(), 3 * STREETS_PER_TOWN);
+
+        // the streets of a town meet at the intersections
+        let street = network.street(3);
+        let cross_street = network.street(TOWN_BLOCKS + 1 + 7);
+        assert_eq!(street.0.len(), TOWN_BLOCKS + 1);
+        assert_eq!(street.0[7], cross_street.0[3]);
+        assert_eq!(RoadNetwork::street_class(5), "primary");
+        assert_eq!(RoadNetwork::street_class(TOWN_BLOCKS + 2), "residential");
+
+        // the towns do not cross the antimeridian
+        let last = network.street(3 * STREETS_PER_TOWN - 1);
+        assert!(last.0.iter().all(|c| c.x < 180.0), "{last:?}");
+    }
+
+    #[test]
+    fn test_route() {
+        let network = RoadNetwork::new(&[Point::new(10.0, 20.0), Point::new(30.0, 20.0)], 1);
+        let route = network.route(Point::new(10.001, 20.0), (0.01, -0.006));
+        // 2 or 3 columns and 1 or 2 rows away
+        assert!((4..=6).contains(&route.0.len()), "{route:?}");
+        assert_eq!(
+            route,
+            network.route(Point::new(10.001, 20.0), (0.01, -0.006))
+        );
+        for pair in route.0.windows(2) {
+            let (dx, dy) = ((pair[1].x - pair[0].x).abs(), (pair[1].y - pair[0].y).abs());
+            assert!(dx < 0.5 * BLOCK_SIZE || dy < 0.5 * BLOCK_SIZE, "{pair:?}");
+        }
+
+        // starts in the nearest town, and stays in it
+        let route = network.route(Point::new(29.0, 21.0), (1.0, 0.0));
+        assert!(
+            route.0.iter().all(|c| (c.x - 30.0).abs() < TOWN_SPAN),
+            "{route:?}"
+        );
+        assert_eq!(route.0.len(), TOWN_BLOCKS + 1);
+
+        // a route to the same intersection
+        let route = network.route(Point::new(10.0, 20.0), (0.0, 0.0));
+        assert_eq!(route.0.len(), 2);
+        assert_eq!(route.0[0], route.0[1]);
+    }
+}
@@ -0,0 +1,219 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Paths of the vehicles between the pickup and dropoff of trips
+
+use crate::spatial::geography::{self, bearing, destination, distance, interpolate};
+use crate::spatial::precision::snap_coordinates;
+use crate::spatial::utils::{rand_normal, seeded_rng, wrap_around_longitude};
+use geo::{Coord, LineString, Point};
+use rand::Rng;
+
+/// Standard deviation of the distance of the middle of a path from the
+/// straight line, relative to the length of the line
+const WANDER: f64 = 0.1;
+
+/// Return a path of `points` positions from `pickup` to `dropoff`, sampled
+/// at regular times
+///
+/// The path wanders off the straight line with a Brownian bridge pinned at
+/// both ends, and the vehicle moves at a varying speed along it, so the
+/// positions are unevenly spaced. The path takes the short way across the
+/// antimeridian, and with geography follows the great circle between the
+/// ends. It only depends on `seed`.
+pub fn trajectory_path(pickup: Point, dropoff: Point, points: usize, seed: u64) -> LineString {
+    bridge_path(pickup, dropoff, points, seed, geography::geography())
+}
+
+fn bridge_path(
+    pickup: Point,
+    dropoff: Point,
+    points: usize,
+    seed: u64,
+    on_sphere: bool,
+) -> LineString {
+    let points = points.max(2);
+    let mut rng = seeded_rng(seed);
+    let (x0, y0) = pickup.x_y();
+    let mut dx = dropoff.x() - x0;
+    if dx > 180.0 {
+        dx -= 360.0;
+    } else if dx < -180.0 {
+        dx += 360.0;
+    }
+    let dy = dropoff.y() - y0;
+
+    // progress along the line, from the distance covered in each interval
+    let mut progress = vec![0.0; points];
+    // offset across the line, a random walk turned into a bridge below
+    let mut walk = vec![0.0; points];
+    for i in 1..points {
+        progress[i] = progress[i - 1] + rng.gen_range(0.2..1.8);
+        walk[i] = walk[i - 1] + rand_normal(&mut rng, 0.0, 1.0);
+    }
+    let total = progress[points - 1];
+    let last = walk[points - 1];
+    // the variance of the bridge is largest in the middle, at a quarter of
+    // the number of steps
+    let scale = WANDER * 2.0 / (points as f64 - 1.0).sqrt();
+    let arc = distance(pickup.0, dropoff.0);
+
+    let coords = (0..points).map(|i| {
+        if i == 0 {
+            return Coord { x: x0, y: y0 };
+        }
+        if i == points - 1 {
+            return dropoff.0;
+        }
+        let along = progress[i] / total;
+        let across = scale * (walk[i] - last * i as f64 / (points - 1) as f64);
+        let (x, y) = if on_sphere {
+            // off the great circle to the left, like the normal in the plane
+            let on_line = interpolate(pickup.0, dropoff.0, along);
+            let heading = bearing(on_line, dropoff.0);
+            destination(on_line, heading - 90.0, across * arc).x_y()
+        } else {
+            // the unit normal to the line scaled by its length is (-dy, dx)
+            (x0 + along * dx - across * dy, y0 + along * dy + across * dx)
+        };
+        let (x, y) = snap_coordinates(wrap_around_longitude(x), y);
+        Coord { x, y }
+    });
+    LineString::new(coords.collect())
+}
+
+/// Return a path of `points` positions along `route`, sampled at regular
+/// times
+///
+/// The vehicle moves at a varying speed as in [`trajectory_path`], but
+/// stays on the route, so the path has the turns of the route between the
+/// positions it passes at the sampled times. It only depends on `seed`.
+pub fn route_path(route: &LineString, points: usize, seed: u64) -> LineString {
+    let points = points.max(2);
+    let mut rng = seeded_rng(seed);
+    let mut progress = vec![0.0; points];
+    for i in 1..points {
+        progress[i] = progress[i - 1] + rng.gen_range(0.2..1.8);
+    }
+    let total = progress[points - 1];
+
+    // distance along the route at each of its positions
+    let on_sphere = geography::geography();
+    let mut lengths = vec![0.0];
+    for line in route.lines() {
+        let segment = if on_sphere {
+            distance(line.start, line.end)
+        } else {
+            let (dx, dy) = line.delta().x_y();
+            dx.hypot(dy)
+        };
+        lengths.push(lengths[lengths.len() - 1] + segment);
+    }
+    let length = lengths[lengths.len() - 1];
+
+    let coords = (0..points).map(|i| {
+        if i == 0 {
+            return route.0[0];
+        }
+        if i == points - 1 {
+            return route.0[route.0.len() - 1];
+        }
+        let along = progress[i] / total * length;
+        let k = lengths
+            .partition_point(|&l| l <= along)
+            .clamp(1, route.0.len() - 1);
+        let (a, b) = (route.0[k - 1], route.0[k]);
+        let segment = lengths[k] - lengths[k - 1];
+        let f = if segment > 0.0 {
+            (along - lengths[k - 1]) / segment
+        } else {
+            0.0
+        };
+        let (x, y) = snap_coordinates(a.x + f * (b.x - a.x), a.y + f * (b.y - a.y));
+        Coord { x, y }
+    });
+    LineString::new(coords.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Distance, Euclidean};
+
+    #[test]
+    fn test_trajectory_path() {
+        let (pickup, dropoff) = (Point::new(10.0, 20.0), Point::new(10.3, 20.4));
+        let path = trajectory_path(pickup, dropoff, 50, 7);
+        assert_eq!(path.0.len(), 50);
+        assert_eq!(path.0[0], pickup.0);
+        assert_eq!(path.0[49], dropoff.0);
+        assert_eq!(path, trajectory_path(pickup, dropoff, 50, 7));
+
+        // the path stays close to the line
+        let line = geo::Line::new(pickup, dropoff);
+        for &coord in &path.0 {
+            let distance = Euclidean.distance(&Point(coord), &line);
+            assert!(distance < 0.25, "{coord:?} is {distance} from the line");
+        }
+
+        // a trip of no time still has a start and an end
+        let path = trajectory_path(pickup, pickup, 0, 7);
+        assert_eq!(path.0, vec![pickup.0, pickup.0]);
+    }
+
+    #[test]
+    fn test_path_across_the_antimeridian() {
+        let path = trajectory_path(Point::new(179.9, 0.0), Point::new(-179.9, 0.0), 20, 1);
+        assert!(path.0.iter().all(|c| c.x.abs() > 179.0), "{path:?}");
+    }
+
+    #[test]
+    fn test_path_on_sphere() {
+        // over the north pole, rather than along the parallel
+        let (pickup, dropoff) = (Point::new(0.0, 80.0), Point::new(180.0, 80.0));
+        let path = bridge_path(pickup, dropoff, 40, 5, true);
+        assert_eq!(path.0.len(), 40);
+        assert_eq!(path.0[39], dropoff.0);
+        let highest = path.0.iter().map(|c| c.y).fold(f64::MIN, f64::max);
+        assert!(highest > 85.0, "{path:?}");
+        assert!(path.0.iter().all(|c| c.x.abs() <= 180.0 && c.y <= 90.0));
+    }
+
+    #[test]
+    fn test_route_path() {
+        let route = LineString::from(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 2.0)]);
+        let path = route_path(&route, 30, 3);
+        assert_eq!(path.0.len(), 30);
+        assert_eq!(path.0[0], route.0[0]);
+        assert_eq!(path.0[29], route.0[2]);
+        // the positions are on the route, in order
+        let mut along = 0.0;
+        for coord in &path.0 {
+            let position = if coord.y == 0.0 {
+                coord.x
+            } else {
+                1.0 + coord.y
+            };
+            assert!(
+                coord.y == 0.0 || coord.x == 1.0,
+                "{coord:?} is not on the route"
+            );
+            assert!(position >= along, "{path:?}");
+            along = position;
+        }
+    }
+}
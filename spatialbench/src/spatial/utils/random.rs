@@ -29,6 +29,7 @@ pub fn unit_clamp(v: f64) -> f64 {
     v.clamp(0.0, 1.0)
 }
 
+/// Mix `index` and `global_seed` into a seed (the SplitMix64 finalizer)
 pub fn spider_seed_for_index(index: u64, global_seed: u64) -> u64 {
     let mut z = index
         .wrapping_add(global_seed)
@@ -53,6 +54,19 @@ pub fn hash_to_unit_u64(x: u64, salt: u64) -> f64 {
     ((z >> 11) as f64) / ((1u64 << 53) as f64)
 }
 
+/// Return the `index`-th value in `[0, 1)` of a Weyl sequence with the
+/// golden ratio, starting at an offset derived from `seed` and `salt`
+///
+/// Unlike hashing each index, consecutive indices are evenly spread, so any
+/// run of rows has close to the expected fraction below a threshold.
+#[inline]
+pub fn weyl_unit(index: u64, seed: u64, salt: u64) -> f64 {
+    let z = index
+        .wrapping_mul(0x9E3779B97F4A7C15)
+        .wrapping_add(spider_seed_for_index(seed, salt));
+    ((z >> 11) as f64) / ((1u64 << 53) as f64)
+}
+
 #[inline]
 pub fn u01_from_seed(seed: u64) -> f64 {
     let z = spider_seed_for_index(seed, 0xA1CE_CAFE);
@@ -0,0 +1,347 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Time windows and seasonal patterns of the generated trip timestamps
+//!
+//! By default the pickup times of trips are uniform over the dates of the
+//! reference data (1992 to 1998). A [`TimeWindow`] draws them from another
+//! range instead, optionally with the daily and weekly seasonality of real
+//! traffic, for spatio-temporal queries that filter on busy periods.
+
+use crate::dates::{days_from_civil, TPCHDate};
+use crate::spatial::utils::{seeded_rng, spider_seed_for_index};
+use once_cell::sync::OnceCell;
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Relative number of trips in each hour of the day, in local time, with a
+/// morning and a larger evening rush hour
+const HOURLY_WEIGHTS: [f64; 24] = [
+    0.55, 0.40, 0.30, 0.22, 0.18, 0.20, 0.40, 0.70, 0.90, 0.90, 0.85, 0.85, //
+    0.90, 0.90, 0.95, 1.00, 1.00, 1.05, 1.10, 1.05, 0.95, 0.90, 0.85, 0.70,
+];
+
+/// Relative number of trips on each day of the week, from Monday
+const DAILY_WEIGHTS: [f64; 7] = [0.90, 0.95, 1.00, 1.05, 1.15, 1.10, 0.85];
+
+/// The distribution of the timestamps in a [`TimeWindow`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimePattern {
+    /// Uniform over the window
+    #[default]
+    Uniform,
+    /// Fewer trips at night and rush hours in the morning and evening
+    Diurnal,
+    /// The diurnal pattern, with busier Fridays and quieter Sundays
+    Weekly,
+}
+
+impl TimePattern {
+    /// Return the relative number of trips at `seconds` since the epoch, in
+    /// local time
+    fn weight(&self, seconds: i64) -> f64 {
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let hour = (seconds.rem_euclid(SECONDS_PER_DAY) / 3600) as usize;
+        match self {
+            TimePattern::Uniform => 1.0,
+            TimePattern::Diurnal => HOURLY_WEIGHTS[hour],
+            // 1970-01-01 is a Thursday
+            TimePattern::Weekly => {
+                HOURLY_WEIGHTS[hour] * DAILY_WEIGHTS[(days + 3).rem_euclid(7) as usize]
+            }
+        }
+    }
+
+    /// Return the largest weight of the pattern
+    fn max_weight(&self) -> f64 {
+        let max = |weights: &[f64]| weights.iter().copied().fold(0.0, f64::max);
+        match self {
+            TimePattern::Uniform => 1.0,
+            TimePattern::Diurnal => max(&HOURLY_WEIGHTS),
+            TimePattern::Weekly => max(&HOURLY_WEIGHTS) * max(&DAILY_WEIGHTS),
+        }
+    }
+}
+
+impl fmt::Display for TimePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimePattern::Uniform => write!(f, "uniform"),
+            TimePattern::Diurnal => write!(f, "diurnal"),
+            TimePattern::Weekly => write!(f, "weekly"),
+        }
+    }
+}
+
+impl FromStr for TimePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "uniform" => Ok(TimePattern::Uniform),
+            "diurnal" => Ok(TimePattern::Diurnal),
+            "weekly" => Ok(TimePattern::Weekly),
+            _ => Err(format!(
+                "Invalid time pattern {s}, expected uniform, diurnal or weekly"
+            )),
+        }
+    }
+}
+
+/// The range and distribution of the pickup times of trips
+///
+/// The window and the pattern are in the local time of a fixed UTC offset,
+/// so that rush hours are in the morning and evening wherever the trips
+/// are, and the timestamps are generated in UTC.
+///
+/// # Example
+/// ```
+/// # use spatialbench::temporal::{parse_timestamp, TimePattern, TimeWindow};
+/// let window = TimeWindow::try_new(
+///     parse_timestamp("2024-01-01").unwrap(),
+///     parse_timestamp("2024-02-01").unwrap(),
+/// )
+/// .unwrap()
+/// .with_pattern(TimePattern::Weekly)
+/// .with_utc_offset(-5 * 3600);
+/// let pickup = window.timestamp(1, 0);
+/// assert!(pickup.to_string().starts_with("2024-01"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    /// Seconds since the epoch of the first timestamp, in local time
+    start: i64,
+    /// Seconds since the epoch after the last timestamp, in local time
+    end: i64,
+    pattern: TimePattern,
+    /// Seconds to add to UTC to get the local time
+    utc_offset: i32,
+}
+
+impl Default for TimeWindow {
+    /// The dates of the reference data, from 1992-01-01 to 1998-12-31, in
+    /// UTC
+    fn default() -> Self {
+        Self {
+            start: days_from_civil(1992, 1, 1) * SECONDS_PER_DAY,
+            end: days_from_civil(1999, 1, 1) * SECONDS_PER_DAY,
+            pattern: TimePattern::Uniform,
+            utc_offset: 0,
+        }
+    }
+}
+
+impl TimeWindow {
+    /// Create a uniform window from `start` (included) to `end` (excluded),
+    /// in seconds since the epoch
+    pub fn try_new(start: i64, end: i64) -> Result<Self, String> {
+        if start >= end {
+            return Err(format!(
+                "the time window must end after it starts, got {} to {}",
+                TPCHDate::from_unix_epoch_seconds(start),
+                TPCHDate::from_unix_epoch_seconds(end)
+            ));
+        }
+        Ok(Self {
+            start,
+            end,
+            ..Self::default()
+        })
+    }
+
+    /// Returns a window with the distribution `pattern`
+    pub fn with_pattern(mut self, pattern: TimePattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    /// Returns a window in the local time of `utc_offset` seconds east of UTC
+    pub fn with_utc_offset(mut self, utc_offset: i32) -> Self {
+        self.utc_offset = utc_offset;
+        self
+    }
+
+    /// Return the first second of the window, in local time
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Return the second after the window, in local time
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+
+    /// Return the distribution of the timestamps
+    pub fn pattern(&self) -> TimePattern {
+        self.pattern
+    }
+
+    /// Return the timestamp in UTC of the row with `index`
+    ///
+    /// The timestamp only depends on the index and the seed, and is drawn
+    /// by rejection sampling of the pattern.
+    pub fn timestamp(&self, index: u64, seed: u64) -> TPCHDate {
+        let mut rng = seeded_rng(spider_seed_for_index(index, seed ^ 0x7140_E000));
+        let range = (self.end - self.start) as u64;
+        let max_weight = self.pattern.max_weight();
+        let mut local = self.start;
+        // the weights are at least a sixth of the largest one, so this
+        // almost never runs out of attempts
+        for _ in 0..64 {
+            local = self.start + rng.gen_range(0..range) as i64;
+            if rng.gen::<f64>() * max_weight < self.pattern.weight(local) {
+                break;
+            }
+        }
+        TPCHDate::from_unix_epoch_seconds(local - self.utc_offset as i64)
+    }
+}
+
+/// Parse a timestamp such as `2024-01-01` or `2024-01-01 08:30:00` to
+/// seconds since the epoch
+pub fn parse_timestamp(value: &str) -> Result<i64, String> {
+    let invalid = || format!("invalid timestamp '{value}', expected YYYY-MM-DD[ HH:MM[:SS]]");
+    let value = value.trim();
+    let (date, time) = match value.split_once([' ', 'T']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let fields = |text: &str, separator: char| -> Result<Vec<i64>, String> {
+        text.split(separator)
+            .map(|f| f.parse().map_err(|_| invalid()))
+            .collect()
+    };
+    let [year, month, day] = fields(date, '-')?[..] else {
+        return Err(invalid());
+    };
+    let (hour, minute, second) = match time.map(|t| fields(t, ':')).transpose()?.as_deref() {
+        None => (0, 0, 0),
+        Some(&[hour, minute]) => (hour, minute, 0),
+        Some(&[hour, minute, second]) => (hour, minute, second),
+        Some(_) => return Err(invalid()),
+    };
+    if !(1..=12).contains(&month) {
+        return Err(invalid());
+    }
+    let next_month = days_from_civil(year + month / 12, month % 12 + 1, 1);
+    if !(1..=next_month - days_from_civil(year, month, 1)).contains(&day)
+        || !(0..24).contains(&hour)
+        || !(0..60).contains(&minute)
+        || !(0..60).contains(&second)
+    {
+        return Err(invalid());
+    }
+    Ok(days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second)
+}
+
+/// Parse a UTC offset such as `UTC`, `+05:30` or `-08` to seconds east of
+/// UTC
+///
+/// Named time zones are not supported since their offsets change with
+/// daylight saving time.
+pub fn parse_utc_offset(value: &str) -> Result<i32, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("utc") || value == "Z" {
+        return Ok(0);
+    }
+    let invalid = || {
+        format!("invalid UTC offset '{value}', expected UTC or +HH:MM, named time zones are not supported")
+    };
+    let offset = value.strip_prefix("UTC").unwrap_or(value).replace(':', "");
+    let (sign, digits) = match offset.split_at_checked(1) {
+        Some(("+", digits)) => (1, digits),
+        Some(("-", digits)) => (-1, digits),
+        _ => return Err(invalid()),
+    };
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let (hours, minutes): (i32, i32) = match digits.len() {
+        1 | 2 => (digits.parse().map_err(|_| invalid())?, 0),
+        4 => (
+            digits[..2].parse().map_err(|_| invalid())?,
+            digits[2..].parse().map_err(|_| invalid())?,
+        ),
+        _ => return Err(invalid()),
+    };
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+    Ok(sign * (hours * 3600 + minutes * 60))
+}
+
+static TIME_WINDOW: OnceCell<TimeWindow> = OnceCell::new();
+
+/// Set the time window of the trips generated by [`TripGenerator::new`]
+///
+/// Only the first call has an effect.
+///
+/// [`TripGenerator::new`]: crate::generators::TripGenerator::new
+pub fn set_time_window(window: TimeWindow) {
+    let _ = TIME_WINDOW.set(window);
+}
+
+/// Return the time window set with [`set_time_window`], if any
+pub fn time_window() -> Option<TimeWindow> {
+    TIME_WINDOW.get().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-02"), Ok(SECONDS_PER_DAY));
+        assert_eq!(parse_timestamp("2024-02-29 08:30"), Ok(1_709_195_400),);
+        assert_eq!(parse_timestamp("2024-02-29T08:30:15"), Ok(1_709_195_415));
+        for value in ["2023-02-29", "2024-13-01", "2024-01-01 24:00", "yesterday"] {
+            let err = parse_timestamp(value).unwrap_err();
+            assert!(err.starts_with("invalid timestamp"), "{value}: {err}");
+        }
+
+        assert_eq!(parse_utc_offset("UTC"), Ok(0));
+        assert_eq!(parse_utc_offset("+05:30"), Ok(19_800));
+        assert_eq!(parse_utc_offset("UTC-8"), Ok(-28_800));
+        let err = parse_utc_offset("America/New_York").unwrap_err();
+        assert!(err.contains("named time zones are not supported"), "{err}");
+    }
+
+    #[test]
+    fn test_time_pattern() {
+        let start = parse_timestamp("2024-01-01").unwrap();
+        let end = parse_timestamp("2024-03-01").unwrap();
+        let window = TimeWindow::try_new(start, end)
+            .unwrap()
+            .with_pattern(TimePattern::Diurnal)
+            .with_utc_offset(3600);
+        let mut hours = [0; 24];
+        for index in 0..10_000 {
+            let seconds = window.timestamp(index, 0).to_unix_epoch_seconds();
+            // the window is in local time
+            assert!((start - 3600..end - 3600).contains(&seconds));
+            hours[((seconds + 3600) % SECONDS_PER_DAY / 3600) as usize] += 1;
+        }
+        // rush hour is busier than the middle of the night
+        assert!(hours[18] > 4 * hours[4], "{hours:?}");
+
+        assert!(TimeWindow::try_new(end, start).is_err());
+    }
+}
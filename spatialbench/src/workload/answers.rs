@@ -0,0 +1,615 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Reference answers of the benchmark queries, see [`Answer`]
+
+use super::{Dataset, ParameterValue, Query, QueryParameters, DEGREES_PER_METER};
+use crate::dates::TPCHDate;
+use crate::generators::{CustomerName, Trip};
+use geo::{
+    Area, BooleanOps, BoundingRect, ConvexHull, Distance, Euclidean, Geometry, Intersects,
+    MultiPoint, MultiPolygon, Point,
+};
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{RTree, AABB};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+
+/// A value in a row of an [`Answer`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnswerValue {
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Null,
+}
+
+impl Display for AnswerValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnswerValue::Int(value) => write!(f, "{value}"),
+            AnswerValue::Float(value) => write!(f, "{value}"),
+            AnswerValue::Text(value) if value.contains([',', '"', '\n']) => {
+                write!(f, "\"{}\"", value.replace('"', "\"\""))
+            }
+            AnswerValue::Text(value) => write!(f, "{value}"),
+            AnswerValue::Null => Ok(()),
+        }
+    }
+}
+
+/// The expected result of a query, computed from the generated data
+///
+/// Answers are computed directly from the trip and building generators with
+/// planar geometry (distances and areas in degrees, as in the `GEOMETRY`
+/// dialects; `radius` and `buffer` parameters are converted from meters), so
+/// engine implementers can check their results at small scale factors, like
+/// the answer sets of TPC-H.
+///
+/// The rows are in the order of the `ORDER BY` of the query, with ties broken
+/// by the remaining columns so the answers are deterministic. In particular,
+/// the kNN join (`Q12`) picks the buildings with the smallest keys among
+/// buildings at the same distance. Timestamps are formatted like the `tbl`
+/// output, durations are in seconds and locations are WKT.
+///
+/// The zone table is built from downloaded data, so the queries that use it
+/// (`Q2`, `Q4`, `Q6`, `Q10` and `Q11`) have no answers.
+///
+/// # Example
+/// ```
+/// # use spatialbench::workload::{Answer, Query, QueryParameters};
+/// let params = QueryParameters::generate(Query::Q1, 0.001, 42);
+/// let answer = Answer::compute(&params, 0.001).unwrap();
+/// assert_eq!(answer.columns()[0], "t_tripkey");
+///
+/// assert!(Answer::compute(&QueryParameters::reference(Query::Q2), 0.001).is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    query: Query,
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<AnswerValue>>,
+}
+
+impl Answer {
+    /// Compute the answer of the query with `params` on `dataset`, or `None`
+    /// if the query uses the zone table
+    pub fn compute(params: &QueryParameters, dataset: impl Into<Dataset>) -> Option<Self> {
+        let dataset = &dataset.into();
+        let (columns, rows) = match params.query() {
+            Query::Q1 => q1(params, dataset),
+            Query::Q3 => q3(params, dataset),
+            Query::Q5 => q5(dataset),
+            Query::Q7 => q7(dataset),
+            Query::Q8 => q8(params, dataset),
+            Query::Q9 => q9(dataset),
+            Query::Q12 => q12(params, dataset),
+            Query::Q2 | Query::Q4 | Query::Q6 | Query::Q10 | Query::Q11 => return None,
+        };
+        Some(Self {
+            query: params.query(),
+            columns,
+            rows,
+        })
+    }
+
+    /// Return the query this is the answer of
+    pub fn query(&self) -> Query {
+        self.query
+    }
+
+    /// Return the names of the columns
+    pub fn columns(&self) -> &[&'static str] {
+        &self.columns
+    }
+
+    /// Return the rows, in order
+    pub fn rows(&self) -> &[Vec<AnswerValue>] {
+        &self.rows
+    }
+
+    /// Write the answer as CSV with a header to `out`
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{}", self.columns.join(","))?;
+        for row in &self.rows {
+            for (i, value) in row.iter().enumerate() {
+                if i > 0 {
+                    write!(out, ",")?;
+                }
+                write!(out, "{value}")?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Columns and rows of an answer
+type Rows = (Vec<&'static str>, Vec<Vec<AnswerValue>>);
+
+/// Keys, names and boundaries of the buildings, with an R-tree of their
+/// bounding boxes
+pub(super) struct Buildings {
+    pub(super) keys: Vec<i64>,
+    names: Vec<String>,
+    pub(super) boundaries: Vec<MultiPolygon>,
+    pub(super) tree: RTree<GeomWithData<Rectangle<[f64; 2]>, usize>>,
+}
+
+impl Buildings {
+    pub(super) fn new(dataset: &Dataset) -> Self {
+        let (mut keys, mut names, mut boundaries) = (vec![], vec![], vec![]);
+        for building in dataset.buildings() {
+            keys.push(building.b_buildingkey);
+            names.push(building.b_name.to_string());
+            // multipolygons with a geometry mix
+            boundaries.push(match building.b_boundary {
+                Geometry::Polygon(polygon) => MultiPolygon::new(vec![polygon]),
+                Geometry::MultiPolygon(multi_polygon) => multi_polygon,
+                _ => unreachable!("building boundaries are polygons"),
+            });
+        }
+        let rects = boundaries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, boundary)| {
+                let rect = boundary.bounding_rect()?;
+                let (min, max) = (rect.min(), rect.max());
+                Some(GeomWithData::new(
+                    Rectangle::from_corners([min.x, min.y], [max.x, max.y]),
+                    i,
+                ))
+            })
+            .collect();
+        Self {
+            keys,
+            names,
+            boundaries,
+            tree: RTree::bulk_load(rects),
+        }
+    }
+
+    /// Return the buildings whose bounding boxes are within `distance` of `point`
+    pub(super) fn near(&self, point: Point, distance: f64) -> impl Iterator<Item = usize> + '_ {
+        let envelope = AABB::from_corners(
+            [point.x() - distance, point.y() - distance],
+            [point.x() + distance, point.y() + distance],
+        );
+        self.tree
+            .locate_in_envelope_intersecting(&envelope)
+            .map(|rect| rect.data)
+    }
+
+    /// Set `nearest` to the (distance, key, index) of the `k` buildings
+    /// nearest to `point`, ordered by distance and key
+    pub(super) fn nearest(&self, point: Point, k: usize, nearest: &mut Vec<(f64, i64, usize)>) {
+        nearest.clear();
+        if k == 0 {
+            return;
+        }
+        let candidates = self
+            .tree
+            .nearest_neighbor_iter_with_distance_2(&[point.x(), point.y()]);
+        for (rect, bounding_distance_2) in candidates {
+            // the distance to a bounding box is at most the distance to the building
+            if nearest.len() == k && bounding_distance_2.sqrt() > nearest[k - 1].0 {
+                break;
+            }
+            let i = rect.data;
+            let candidate = (
+                Euclidean.distance(&point, &self.boundaries[i]),
+                self.keys[i],
+                i,
+            );
+            let position = nearest.partition_point(|&(distance, key, _)| {
+                (distance, key) < (candidate.0, candidate.1)
+            });
+            nearest.insert(position, candidate);
+            nearest.truncate(k);
+        }
+    }
+}
+
+fn distance_degrees(params: &QueryParameters, name: &str) -> f64 {
+    match params.get(name) {
+        Some(ParameterValue::Distance(meters)) => meters * DEGREES_PER_METER,
+        _ => 0.0,
+    }
+}
+
+/// Truncate `date` to the month, e.g. `1992-03-01 00:00:00`
+fn month(date: TPCHDate) -> String {
+    format!("{}-01 00:00:00", &date.to_string()[..7])
+}
+
+fn duration_seconds(trip: &Trip) -> i64 {
+    trip.t_dropofftime.to_unix_epoch_seconds() - trip.t_pickuptime.to_unix_epoch_seconds()
+}
+
+fn float(value: &AnswerValue) -> f64 {
+    match value {
+        AnswerValue::Int(value) => *value as f64,
+        AnswerValue::Float(value) => *value,
+        _ => f64::NAN,
+    }
+}
+
+/// Compare values of the same column
+fn compare(a: &AnswerValue, b: &AnswerValue) -> Ordering {
+    match (a, b) {
+        (AnswerValue::Text(a), AnswerValue::Text(b)) => a.cmp(b),
+        (AnswerValue::Null, AnswerValue::Null) => Ordering::Equal,
+        (AnswerValue::Null, _) => Ordering::Greater,
+        (_, AnswerValue::Null) => Ordering::Less,
+        (a, b) => float(a).total_cmp(&float(b)),
+    }
+}
+
+/// Sort `rows` by the columns in `order`, where a negative column sorts
+/// descending (with nulls last), then by all columns
+fn sort_rows(rows: &mut [Vec<AnswerValue>], order: &[isize]) {
+    rows.sort_by(|a, b| {
+        let by_order = order.iter().map(|&column| {
+            let i = column.unsigned_abs() - 1;
+            match (column < 0, &a[i], &b[i]) {
+                (true, AnswerValue::Null, AnswerValue::Null) => Ordering::Equal,
+                (true, AnswerValue::Null, _) => Ordering::Greater,
+                (true, _, AnswerValue::Null) => Ordering::Less,
+                (true, a, b) => compare(b, a),
+                (false, a, b) => compare(a, b),
+            }
+        });
+        let by_columns = a.iter().zip(b).map(|(a, b)| compare(a, b));
+        by_order
+            .chain(by_columns)
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Trips within a radius of a point, ordered by distance
+fn q1(params: &QueryParameters, dataset: &Dataset) -> Rows {
+    let Some(ParameterValue::Point(center)) = params.get("center") else {
+        return (vec![], vec![]);
+    };
+    let radius = distance_degrees(params, "radius");
+    let mut rows: Vec<_> = dataset
+        .trips()
+        .filter_map(|trip| {
+            let distance = Euclidean.distance(trip.t_pickuploc, center);
+            (distance <= radius).then(|| {
+                vec![
+                    AnswerValue::Int(trip.t_tripkey),
+                    AnswerValue::Float(trip.t_pickuploc.x()),
+                    AnswerValue::Float(trip.t_pickuploc.y()),
+                    AnswerValue::Text(trip.t_pickuptime.to_string()),
+                    AnswerValue::Float(distance),
+                ]
+            })
+        })
+        .collect();
+    sort_rows(&mut rows, &[5, 1]);
+    let columns = vec![
+        "t_tripkey",
+        "pickup_lon",
+        "pickup_lat",
+        "t_pickuptime",
+        "distance_to_center",
+    ];
+    (columns, rows)
+}
+
+/// Monthly statistics of the trips within a distance of a window
+fn q3(params: &QueryParameters, dataset: &Dataset) -> Rows {
+    let Some(ParameterValue::Window(window)) = params.get("window") else {
+        return (vec![], vec![]);
+    };
+    let (window, buffer) = (window.to_polygon(), distance_degrees(params, "buffer"));
+
+    // month -> (trips, distance, duration, fare)
+    let mut months: BTreeMap<String, (i64, f64, f64, f64)> = BTreeMap::new();
+    for trip in dataset.trips() {
+        if Euclidean.distance(&trip.t_pickuploc, &window) <= buffer {
+            let stats = months.entry(month(trip.t_pickuptime)).or_default();
+            stats.0 += 1;
+            stats.1 += trip.t_distance.as_f64();
+            stats.2 += duration_seconds(&trip) as f64;
+            stats.3 += trip.t_fare.as_f64();
+        }
+    }
+    let rows = months
+        .into_iter()
+        .map(|(month, (count, distance, duration, fare))| {
+            let count_f64 = count as f64;
+            vec![
+                AnswerValue::Text(month),
+                AnswerValue::Int(count),
+                AnswerValue::Float(distance / count_f64),
+                AnswerValue::Float(duration / count_f64),
+                AnswerValue::Float(fare / count_f64),
+            ]
+        })
+        .collect();
+    let columns = vec![
+        "pickup_month",
+        "total_trips",
+        "avg_distance",
+        "avg_duration",
+        "avg_fare",
+    ];
+    (columns, rows)
+}
+
+/// Monthly convex hull areas of the dropoffs of repeat customers
+fn q5(dataset: &Dataset) -> Rows {
+    let mut groups: HashMap<(i64, String), Vec<Point>> = HashMap::new();
+    for trip in dataset.trips() {
+        groups
+            .entry((trip.t_custkey, month(trip.t_pickuptime)))
+            .or_default()
+            .push(trip.t_dropoffloc);
+    }
+    let mut rows: Vec<_> = groups
+        .into_iter()
+        .filter(|(_, dropoffs)| dropoffs.len() > 5)
+        .map(|((custkey, month), dropoffs)| {
+            let count = dropoffs.len() as i64;
+            let hull = MultiPoint::new(dropoffs).convex_hull();
+            vec![
+                AnswerValue::Int(custkey),
+                AnswerValue::Text(CustomerName::new(custkey).to_string()),
+                AnswerValue::Text(month),
+                AnswerValue::Float(hull.unsigned_area()),
+                AnswerValue::Int(count),
+            ]
+        })
+        .collect();
+    sort_rows(&mut rows, &[-5, 1]);
+    let columns = vec![
+        "c_custkey",
+        "customer_name",
+        "pickup_month",
+        "monthly_travel_hull_area",
+        "dropoff_count",
+    ];
+    (columns, rows)
+}
+
+/// Ratio of the reported and straight line distances of the trips
+fn q7(dataset: &Dataset) -> Rows {
+    let mut rows: Vec<_> = dataset
+        .trips()
+        .map(|trip| {
+            let reported = trip.t_distance.as_f64();
+            let line = Euclidean.distance(trip.t_pickuploc, trip.t_dropoffloc) / DEGREES_PER_METER;
+            vec![
+                AnswerValue::Int(trip.t_tripkey),
+                AnswerValue::Float(reported),
+                AnswerValue::Float(line),
+                if line == 0.0 {
+                    AnswerValue::Null
+                } else {
+                    AnswerValue::Float(reported / line)
+                },
+            ]
+        })
+        .collect();
+    sort_rows(&mut rows, &[-4, -2, 1]);
+    let columns = vec![
+        "t_tripkey",
+        "reported_distance_m",
+        "line_distance_m",
+        "detour_ratio",
+    ];
+    (columns, rows)
+}
+
+/// Number of pickups within a radius of each building
+fn q8(params: &QueryParameters, dataset: &Dataset) -> Rows {
+    let radius = distance_degrees(params, "radius");
+    let buildings = Buildings::new(dataset);
+    let mut counts = vec![0i64; buildings.keys.len()];
+    for trip in dataset.trips() {
+        for i in buildings.near(trip.t_pickuploc, radius) {
+            if Euclidean.distance(&trip.t_pickuploc, &buildings.boundaries[i]) <= radius {
+                counts[i] += 1;
+            }
+        }
+    }
+    let mut rows: Vec<_> = counts
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, count)| count > 0)
+        .map(|(i, count)| {
+            vec![
+                AnswerValue::Int(buildings.keys[i]),
+                AnswerValue::Text(buildings.names[i].clone()),
+                AnswerValue::Int(count),
+            ]
+        })
+        .collect();
+    sort_rows(&mut rows, &[-3, 1]);
+    (vec!["b_buildingkey", "b_name", "nearby_pickup_count"], rows)
+}
+
+/// Intersection over union of the pairs of intersecting buildings
+fn q9(dataset: &Dataset) -> Rows {
+    let buildings = Buildings::new(dataset);
+    let mut rows = vec![];
+    for (i, boundary) in buildings.boundaries.iter().enumerate() {
+        let Some(rect) = boundary.bounding_rect() else {
+            continue;
+        };
+        let envelope =
+            AABB::from_corners([rect.min().x, rect.min().y], [rect.max().x, rect.max().y]);
+        for other in buildings.tree.locate_in_envelope_intersecting(&envelope) {
+            let j = other.data;
+            let other_boundary = &buildings.boundaries[j];
+            if buildings.keys[i] >= buildings.keys[j] || !boundary.intersects(other_boundary) {
+                continue;
+            }
+            let (area1, area2) = (boundary.unsigned_area(), other_boundary.unsigned_area());
+            let overlap = boundary.intersection(other_boundary).unsigned_area();
+            let iou = if overlap == 0.0 {
+                0.0
+            } else if area1 + area2 - overlap == 0.0 {
+                1.0
+            } else {
+                overlap / (area1 + area2 - overlap)
+            };
+            rows.push(vec![
+                AnswerValue::Int(buildings.keys[i]),
+                AnswerValue::Int(buildings.keys[j]),
+                AnswerValue::Float(area1),
+                AnswerValue::Float(area2),
+                AnswerValue::Float(overlap),
+                AnswerValue::Float(iou),
+            ]);
+        }
+    }
+    sort_rows(&mut rows, &[-6, 1, 2]);
+    let columns = vec![
+        "building_1",
+        "building_2",
+        "area1",
+        "area2",
+        "overlap_area",
+        "iou",
+    ];
+    (columns, rows)
+}
+
+/// The k nearest buildings of each trip pickup
+fn q12(params: &QueryParameters, dataset: &Dataset) -> Rows {
+    let k = match params.get("k") {
+        Some(ParameterValue::Count(k)) => k.max(0) as usize,
+        _ => 0,
+    };
+    let buildings = Buildings::new(dataset);
+    let mut rows = vec![];
+    let mut nearest = Vec::with_capacity(k + 1);
+    for trip in dataset.trips() {
+        let pickup = trip.t_pickuploc;
+        buildings.nearest(pickup, k, &mut nearest);
+        for &(distance, key, i) in &nearest {
+            rows.push(vec![
+                AnswerValue::Int(trip.t_tripkey),
+                AnswerValue::Text(format!("POINT ({} {})", pickup.x(), pickup.y())),
+                AnswerValue::Int(key),
+                AnswerValue::Text(buildings.names[i].clone()),
+                AnswerValue::Float(distance),
+            ]);
+        }
+    }
+    sort_rows(&mut rows, &[5, 3, 1]);
+    let columns = vec![
+        "t_tripkey",
+        "t_pickuploc",
+        "b_buildingkey",
+        "building_name",
+        "distance_to_building",
+    ];
+    (columns, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::{RowCounts, TripGenerator};
+
+    #[test]
+    fn test_trip_answers() {
+        let scale_factor = 0.001;
+        let params = QueryParameters::generate(Query::Q1, scale_factor, 3);
+        let answer = Answer::compute(&params, scale_factor).unwrap();
+        assert!(!answer.rows().is_empty());
+        let distances: Vec<f64> = answer.rows().iter().map(|row| float(&row[4])).collect();
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(distances
+            .iter()
+            .all(|&d| d <= distance_degrees(&params, "radius")));
+
+        // every trip has a detour ratio, ordered descending with nulls last
+        let answer = Answer::compute(&QueryParameters::reference(Query::Q7), scale_factor).unwrap();
+        let trip_count = TripGenerator::calculate_row_count(scale_factor, 1, 1) as usize;
+        assert_eq!(answer.rows().len(), trip_count);
+        let ratios: Vec<&AnswerValue> = answer.rows().iter().map(|row| &row[3]).collect();
+        assert!(ratios
+            .windows(2)
+            .all(|pair| { pair[1] == &AnswerValue::Null || float(pair[0]) >= float(pair[1]) }));
+
+        let mut csv = vec![];
+        answer.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("t_tripkey,reported_distance_m,line_distance_m,detour_ratio\n"));
+        assert_eq!(csv.lines().count(), trip_count + 1);
+
+        // the answers are computed on the trips of the seed and row counts
+        let distances = |seed| {
+            let dataset = Dataset::new(scale_factor)
+                .with_seed(seed)
+                .with_row_counts(RowCounts {
+                    trip: Some(10),
+                    ..Default::default()
+                });
+            let answer = Answer::compute(&QueryParameters::reference(Query::Q7), dataset).unwrap();
+            let mut distances: Vec<f64> = answer.rows().iter().map(|row| float(&row[1])).collect();
+            let mut expected: Vec<f64> = dataset.trips().map(|t| t.t_distance.as_f64()).collect();
+            distances.sort_by(f64::total_cmp);
+            expected.sort_by(f64::total_cmp);
+            assert_eq!(distances, expected);
+            distances
+        };
+        assert_ne!(distances(0), distances(1));
+    }
+
+    #[test]
+    fn test_building_answers() {
+        // there is a single building below scale factor 1
+        let scale_factor = 0.001;
+        let params = QueryParameters::reference(Query::Q12);
+        let answer = Answer::compute(&params, scale_factor).unwrap();
+        let trip_count = TripGenerator::calculate_row_count(scale_factor, 1, 1) as usize;
+        assert_eq!(answer.rows().len(), trip_count);
+
+        // the nearest buildings match a brute force search
+        let buildings = Buildings::new(&Dataset::new(1.0));
+        let mut nearest = vec![];
+        for trip in Dataset::new(scale_factor).trips().take(20) {
+            let mut expected: Vec<(f64, i64, usize)> = buildings
+                .boundaries
+                .iter()
+                .enumerate()
+                .map(|(i, boundary)| {
+                    let distance = Euclidean.distance(&trip.t_pickuploc, boundary);
+                    (distance, buildings.keys[i], i)
+                })
+                .collect();
+            expected.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            buildings.nearest(trip.t_pickuploc, 5, &mut nearest);
+            assert_eq!(nearest, expected[..5]);
+        }
+
+        let answer = Answer::compute(&QueryParameters::reference(Query::Q9), scale_factor).unwrap();
+        for row in answer.rows() {
+            assert!(float(&row[0]) < float(&row[1]));
+            assert!((0.0..=1.0).contains(&float(&row[5])));
+        }
+    }
+}
@@ -0,0 +1,348 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Exact cardinalities of the spatial joins of the queries, see
+//! [`JoinCardinality`]
+
+use super::answers::Buildings;
+use super::{Dataset, ParameterValue, Query, QueryParameters, DEGREES_PER_METER};
+use crate::generators::Trip;
+use geo::{BoundingRect, Contains, Distance, Euclidean, Intersects, MultiPolygon, Point, Rect};
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{RTree, AABB};
+
+/// Name of the zone of `Q2`
+const COCONINO_COUNTY: &str = "Coconino County";
+
+/// The zone table, to compute the cardinalities of the joins with zones
+///
+/// The zones are built from downloaded data rather than generated, so they
+/// are read from the generated zone table by the caller.
+pub struct Zones {
+    keys: Vec<i64>,
+    names: Vec<String>,
+    boundaries: Vec<MultiPolygon>,
+    tree: RTree<GeomWithData<Rectangle<[f64; 2]>, usize>>,
+}
+
+impl Zones {
+    /// Index the zones with their keys, names and boundaries
+    pub fn new(zones: impl IntoIterator<Item = (i64, String, MultiPolygon)>) -> Self {
+        let (mut keys, mut names, mut boundaries) = (vec![], vec![], vec![]);
+        for (key, name, boundary) in zones {
+            keys.push(key);
+            names.push(name);
+            boundaries.push(boundary);
+        }
+        let rects = boundaries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, boundary)| {
+                let rect = boundary.bounding_rect()?;
+                let (min, max) = (rect.min(), rect.max());
+                Some(GeomWithData::new(
+                    Rectangle::from_corners([min.x, min.y], [max.x, max.y]),
+                    i,
+                ))
+            })
+            .collect();
+        Self {
+            keys,
+            names,
+            boundaries,
+            tree: RTree::bulk_load(rects),
+        }
+    }
+
+    /// Return the number of zones
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Return true if there are no zones
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Return the zones `point` is within (`ST_Within`)
+    fn containing(&self, point: Point) -> impl Iterator<Item = usize> + '_ {
+        self.tree
+            .locate_all_at_point(&[point.x(), point.y()])
+            .map(|rect| rect.data)
+            .filter(move |&i| self.boundaries[i].contains(&point))
+    }
+
+    /// Return the number of (zone, trip) pairs of trips within zones
+    fn trip_pairs(&self, pickups: impl Iterator<Item = Point>) -> u64 {
+        pickups
+            .map(|pickup| self.containing(pickup).count() as u64)
+            .sum()
+    }
+}
+
+/// The exact number of pairs of rows matched by the spatial join of a query
+///
+/// This is the cardinality of the join itself, before any aggregation or
+/// limit of the query, computed from the generated data with an R-tree and
+/// planar predicates, as the [`super::Answer`]s are. It lets implementers
+/// check their joins without a trusted reference engine:
+///
+/// * `Q2`: trips intersecting the Coconino County zone
+/// * `Q4`: (zone, trip) pairs of the top trips by tip within zones
+/// * `Q6`: (zone, trip) pairs of trips within zones intersecting the window
+/// * `Q8`: (building, trip) pairs of pickups within the radius of buildings
+/// * `Q9`: pairs of distinct intersecting buildings
+/// * `Q10`: (zone, trip) pairs of pickups within zones
+/// * `Q11`: (pickup zone, dropoff zone) pairs of different zones of trips
+/// * `Q12`: (trip, building) pairs of the k nearest buildings of trips
+///
+/// The zone joins need the [`Zones`].
+///
+/// # Example
+/// ```
+/// # use spatialbench::workload::{JoinCardinality, Query, QueryParameters};
+/// let params = QueryParameters::reference(Query::Q12);
+/// let cardinality = JoinCardinality::compute(&params, 0.001, None).unwrap();
+/// // there is a single building below scale factor 1, the nearest of each trip
+/// assert_eq!(cardinality.pairs, 6000);
+///
+/// assert!(JoinCardinality::compute(&QueryParameters::reference(Query::Q1), 0.001, None).is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinCardinality {
+    pub query: Query,
+    /// Description of the pairs of the join
+    pub join: &'static str,
+    pub pairs: u64,
+}
+
+impl JoinCardinality {
+    /// Compute the cardinality of the join of the query with `params` on
+    /// `dataset`
+    ///
+    /// Returns `None` for the queries without a spatial join, and for the
+    /// zone joins without `zones`.
+    pub fn compute(
+        params: &QueryParameters,
+        dataset: impl Into<Dataset>,
+        zones: Option<&Zones>,
+    ) -> Option<Self> {
+        let (query, dataset) = (params.query(), dataset.into());
+        let trips = || dataset.trips();
+        let (join, pairs) = match (query, zones) {
+            (Query::Q2, Some(zones)) => {
+                let pairs = match zones.names.iter().position(|n| n == COCONINO_COUNTY) {
+                    Some(i) => {
+                        let zone = &zones.boundaries[i];
+                        trips().filter(|t| zone.intersects(&t.t_pickuploc)).count() as u64
+                    }
+                    None => 0,
+                };
+                ("trips intersecting the Coconino County zone", pairs)
+            }
+            (Query::Q4, Some(zones)) => {
+                let limit = match params.get("limit") {
+                    Some(ParameterValue::Count(limit)) => limit.max(0) as usize,
+                    _ => 0,
+                };
+                let mut top: Vec<Trip> = trips().collect();
+                top.sort_by(|a, b| b.t_tip.cmp(&a.t_tip).then(a.t_tripkey.cmp(&b.t_tripkey)));
+                top.truncate(limit);
+                let pairs = zones.trip_pairs(top.iter().map(|t| t.t_pickuploc));
+                ("(zone, trip) pairs of the top trips within zones", pairs)
+            }
+            (Query::Q6, Some(zones)) => {
+                let Some(ParameterValue::Window(window)) = params.get("window") else {
+                    return None;
+                };
+                let pairs = trips()
+                    .map(|t| {
+                        zones
+                            .containing(t.t_pickuploc)
+                            .filter(|&i| window_intersects(window, &zones.boundaries[i]))
+                            .count() as u64
+                    })
+                    .sum();
+                (
+                    "(zone, trip) pairs of trips within zones intersecting the window",
+                    pairs,
+                )
+            }
+            (Query::Q8, _) => {
+                let radius = match params.get("radius") {
+                    Some(ParameterValue::Distance(meters)) => meters * DEGREES_PER_METER,
+                    _ => 0.0,
+                };
+                let buildings = Buildings::new(&dataset);
+                let pairs = trips()
+                    .map(|t| {
+                        buildings
+                            .near(t.t_pickuploc, radius)
+                            .filter(|&i| {
+                                Euclidean.distance(&t.t_pickuploc, &buildings.boundaries[i])
+                                    <= radius
+                            })
+                            .count() as u64
+                    })
+                    .sum();
+                ("(building, trip) pairs within the radius", pairs)
+            }
+            (Query::Q9, _) => {
+                let buildings = Buildings::new(&dataset);
+                let mut pairs = 0;
+                for (i, boundary) in buildings.boundaries.iter().enumerate() {
+                    let Some(rect) = boundary.bounding_rect() else {
+                        continue;
+                    };
+                    let envelope = AABB::from_corners(
+                        [rect.min().x, rect.min().y],
+                        [rect.max().x, rect.max().y],
+                    );
+                    pairs += buildings
+                        .tree
+                        .locate_in_envelope_intersecting(&envelope)
+                        .filter(|other| {
+                            let j = other.data;
+                            buildings.keys[i] < buildings.keys[j]
+                                && boundary.intersects(&buildings.boundaries[j])
+                        })
+                        .count() as u64;
+                }
+                ("pairs of intersecting buildings", pairs)
+            }
+            (Query::Q10, Some(zones)) => {
+                let pairs = zones.trip_pairs(trips().map(|t| t.t_pickuploc));
+                ("(zone, trip) pairs of pickups within zones", pairs)
+            }
+            (Query::Q11, Some(zones)) => {
+                let pairs = trips()
+                    .map(|t| {
+                        let dropoffs: Vec<usize> = zones.containing(t.t_dropoffloc).collect();
+                        zones
+                            .containing(t.t_pickuploc)
+                            .map(|pickup| {
+                                dropoffs
+                                    .iter()
+                                    .filter(|&&dropoff| zones.keys[dropoff] != zones.keys[pickup])
+                                    .count() as u64
+                            })
+                            .sum::<u64>()
+                    })
+                    .sum();
+                (
+                    "(pickup zone, dropoff zone) pairs of trips across zones",
+                    pairs,
+                )
+            }
+            (Query::Q12, _) => {
+                let k = match params.get("k") {
+                    Some(ParameterValue::Count(k)) => k.max(0) as u64,
+                    _ => 0,
+                };
+                let buildings = Buildings::new(&dataset).keys.len() as u64;
+                let trips = dataset.trip_count() as u64;
+                (
+                    "(trip, building) pairs of the nearest buildings",
+                    trips * k.min(buildings),
+                )
+            }
+            _ => return None,
+        };
+        Some(Self { query, join, pairs })
+    }
+}
+
+/// Return true if the query `window` intersects `boundary`
+fn window_intersects(window: Rect, boundary: &MultiPolygon) -> bool {
+    window.to_polygon().intersects(boundary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::TripGenerator;
+    use crate::workload::Answer;
+    use geo::polygon;
+
+    #[test]
+    fn test_building_joins() {
+        let scale_factor = 0.001;
+        // the pairs of Q8 are the sum of its counts
+        let params = QueryParameters::generate(Query::Q8, scale_factor, 3);
+        let answer = Answer::compute(&params, scale_factor).unwrap();
+        let counts: i64 = answer
+            .rows()
+            .iter()
+            .map(|row| match row[2] {
+                crate::workload::AnswerValue::Int(count) => count,
+                _ => 0,
+            })
+            .sum();
+        let cardinality = JoinCardinality::compute(&params, scale_factor, None).unwrap();
+        assert_eq!(cardinality.pairs, counts as u64);
+
+        // the pairs of Q9 are its rows
+        let params = QueryParameters::reference(Query::Q9);
+        let answer = Answer::compute(&params, scale_factor).unwrap();
+        let cardinality = JoinCardinality::compute(&params, scale_factor, None).unwrap();
+        assert_eq!(cardinality.pairs, answer.rows().len() as u64);
+    }
+
+    #[test]
+    fn test_zone_joins() {
+        let scale_factor = 0.001;
+        // two zones covering the whole world, one of them twice
+        let west = polygon![(x: -180.0, y: -90.0), (x: 0.0, y: -90.0), (x: 0.0, y: 90.0), (x: -180.0, y: 90.0)];
+        let east = polygon![(x: 0.0, y: -90.0), (x: 180.0, y: -90.0), (x: 180.0, y: 90.0), (x: 0.0, y: 90.0)];
+        let zones = Zones::new([
+            (
+                1,
+                COCONINO_COUNTY.to_string(),
+                MultiPolygon::new(vec![west.clone()]),
+            ),
+            (2, "East".to_string(), MultiPolygon::new(vec![east])),
+        ]);
+        let trips: Vec<Trip> = TripGenerator::new(scale_factor, 1, 1).iter().collect();
+        let count = |predicate: &dyn Fn(&Trip) -> bool| {
+            trips.iter().filter(|t| predicate(t)).count() as u64
+        };
+        let west_of = |p: Point| p.x() < 0.0;
+
+        let pairs = |query| {
+            JoinCardinality::compute(
+                &QueryParameters::reference(query),
+                scale_factor,
+                Some(&zones),
+            )
+            .unwrap()
+            .pairs
+        };
+        assert_eq!(pairs(Query::Q2), count(&|t| west_of(t.t_pickuploc)));
+        assert_eq!(pairs(Query::Q10), trips.len() as u64);
+        assert_eq!(
+            pairs(Query::Q11),
+            count(&|t| west_of(t.t_pickuploc) != west_of(t.t_dropoffloc))
+        );
+        assert_eq!(pairs(Query::Q4), 1000);
+        // the window of Q6 is in Arizona
+        assert_eq!(pairs(Query::Q6), count(&|t| west_of(t.t_pickuploc)));
+
+        // the zone joins need the zones
+        let params = QueryParameters::reference(Query::Q10);
+        assert!(JoinCardinality::compute(&params, scale_factor, None).is_none());
+    }
+}
@@ -0,0 +1,307 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Standalone kNN queries with their exact neighbors, see [`KnnStream`]
+
+use super::answers::Buildings;
+use super::{Dataset, Dialect, ParameterValue, TRIP_PICKUPS};
+use crate::generators::{TripGenerator, TripPickupGenerator};
+use crate::spatial::utils::{hash_to_unit_u64, spider_seed_for_index};
+use geo::Point;
+use rstar::primitives::GeomWithData;
+use rstar::RTree;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Smallest and largest `k` of the queries when it is not fixed
+const K_RANGE: (usize, usize) = (1, 100);
+
+/// Table a kNN query searches
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KnnTable {
+    /// The trip pickup locations
+    Trip,
+    /// The building boundaries
+    Building,
+}
+
+impl KnnTable {
+    /// Return the name of the table
+    pub fn name(&self) -> &'static str {
+        match self {
+            KnnTable::Trip => "trip",
+            KnnTable::Building => "building",
+        }
+    }
+
+    /// Return the key and geometry columns of the table
+    fn columns(&self) -> (&'static str, &'static str) {
+        match self {
+            KnnTable::Trip => ("t_tripkey", "t_pickuploc"),
+            KnnTable::Building => ("b_buildingkey", "b_boundary"),
+        }
+    }
+}
+
+impl Display for KnnTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for KnnTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "trip" => Ok(KnnTable::Trip),
+            "building" => Ok(KnnTable::Building),
+            _ => Err(format!("Invalid kNN table {s}, expected trip or building")),
+        }
+    }
+}
+
+/// A kNN query: the `k` rows of a table nearest to a center point
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct KnnQuery {
+    pub table: KnnTable,
+    /// Longitude / latitude, rounded to the 4 decimals of the SQL
+    pub center: Point,
+    pub k: usize,
+}
+
+impl KnnQuery {
+    /// Return the query in `dialect`, selecting the keys of the neighbors and
+    /// their distances, ordered by distance and key
+    ///
+    /// The neighbors of the `GEOGRAPHY` dialects are nearest on the sphere,
+    /// so they may differ from the [`KnnGroundTruth`] at equal planar
+    /// distances.
+    pub fn sql(&self, dialect: Dialect) -> String {
+        let (key, geometry) = self.table.columns();
+        let center = ParameterValue::Point(self.center).to_sql(dialect);
+        let (from_wkb, distance) = match dialect {
+            Dialect::Bigquery => ("ST_GEOGFROMWKB", "ST_DISTANCE"),
+            _ => ("ST_GeomFromWKB", "ST_Distance"),
+        };
+        let geometry = format!("{from_wkb}({geometry})");
+        // the `<->` operator uses the GiST index of PostGIS
+        let order = match dialect {
+            Dialect::Postgis => format!("{geometry} <-> {center}, {key}"),
+            _ => format!("distance, {key}"),
+        };
+        format!(
+            "-- kNN: the {k} {table} rows nearest to {point}\n\
+             SELECT {key}, {distance}({geometry}, {center}) AS distance\n\
+             FROM {table}\n\
+             ORDER BY {order}\n\
+             LIMIT {k};\n",
+            k = self.k,
+            table = self.table,
+            point = ParameterValue::Point(self.center),
+        )
+    }
+}
+
+/// Iterator over reproducible kNN queries on a table
+///
+/// The center of each query is the pickup location of a trip chosen by the
+/// seed, so the queries follow the skew of the data, and `k` is drawn
+/// between 1 and 100 unless fixed with [`Self::with_k`]. The `n`-th query is
+/// always the same for the same table, scale factor and seed.
+///
+/// # Example
+/// ```
+/// # use spatialbench::workload::{KnnGroundTruth, KnnStream, KnnTable};
+/// let queries: Vec<_> = KnnStream::new(KnnTable::Trip, 0.001, 42)
+///     .with_k(3)
+///     .take(2)
+///     .collect();
+/// let truth = KnnGroundTruth::new(KnnTable::Trip, 0.001);
+/// assert_eq!(truth.neighbors(&queries[0]).len(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct KnnStream {
+    table: KnnTable,
+    scale_factor: f64,
+    seed: u64,
+    k: Option<usize>,
+    index: u64,
+}
+
+impl KnnStream {
+    /// Create a stream of kNN queries on `table` at `scale_factor`
+    pub fn new(table: KnnTable, scale_factor: f64, seed: u64) -> Self {
+        Self {
+            table,
+            scale_factor,
+            seed,
+            k: None,
+            index: 0,
+        }
+    }
+
+    /// Use the same `k` for every query
+    pub fn with_k(mut self, k: usize) -> Self {
+        self.k = Some(k);
+        self
+    }
+}
+
+impl Iterator for KnnStream {
+    type Item = KnnQuery;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let seed = spider_seed_for_index(self.index, spider_seed_for_index(self.seed, 1));
+        self.index += 1;
+
+        let trip_count = TripGenerator::calculate_row_count(self.scale_factor, 1, 1);
+        let trip = 1 + (hash_to_unit_u64(seed, 0) * trip_count as f64) as i64;
+        let pickup = TRIP_PICKUPS
+            .get_or_init(TripPickupGenerator::new)
+            .pickup_location(trip.min(trip_count));
+        let round = |degrees: f64| (degrees * 10_000.0).round() / 10_000.0;
+        let k = self.k.unwrap_or_else(|| {
+            let (min, max) = K_RANGE;
+            min + (hash_to_unit_u64(seed, 1) * (max - min + 1) as f64) as usize
+        });
+        Some(KnnQuery {
+            table: self.table,
+            center: Point::new(round(pickup.x()), round(pickup.y())),
+            k,
+        })
+    }
+}
+
+/// The exact neighbors of kNN queries, with planar distances in degrees
+///
+/// The table of the [`Dataset`] is generated in memory and indexed, so this
+/// is intended for small scale factors. Rows at the same distance are ordered by key, as in
+/// the `ORDER BY` of [`KnnQuery::sql`].
+pub struct KnnGroundTruth {
+    index: Index,
+}
+
+enum Index {
+    Trip(RTree<GeomWithData<[f64; 2], i64>>),
+    Building(Buildings),
+}
+
+impl KnnGroundTruth {
+    /// Generate and index `table` of `dataset`
+    pub fn new(table: KnnTable, dataset: impl Into<Dataset>) -> Self {
+        let dataset = dataset.into();
+        let index = match table {
+            KnnTable::Trip => {
+                let pickups = dataset
+                    .trips()
+                    .map(|trip| {
+                        let pickup = trip.t_pickuploc;
+                        GeomWithData::new([pickup.x(), pickup.y()], trip.t_tripkey)
+                    })
+                    .collect();
+                Index::Trip(RTree::bulk_load(pickups))
+            }
+            KnnTable::Building => Index::Building(Buildings::new(&dataset)),
+        };
+        Self { index }
+    }
+
+    /// Return the keys of the neighbors of `query` with their distances,
+    /// ordered by distance and key
+    pub fn neighbors(&self, query: &KnnQuery) -> Vec<(i64, f64)> {
+        let center = query.center;
+        match &self.index {
+            Index::Trip(tree) => {
+                let mut nearest: Vec<(f64, i64)> = vec![];
+                for pickup in tree.nearest_neighbor_iter(&[center.x(), center.y()]) {
+                    let [x, y] = *pickup.geom();
+                    let distance = (x - center.x()).hypot(y - center.y());
+                    // keep the pickups tied with the k-th, to order them by key
+                    if nearest.len() >= query.k && distance > nearest[query.k - 1].0 {
+                        break;
+                    }
+                    nearest.push((distance, pickup.data));
+                }
+                nearest.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+                nearest.truncate(query.k);
+                nearest.into_iter().map(|(d, key)| (key, d)).collect()
+            }
+            Index::Building(buildings) => {
+                let mut nearest = Vec::with_capacity(query.k + 1);
+                buildings.nearest(center, query.k, &mut nearest);
+                nearest.into_iter().map(|(d, key, _)| (key, d)).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_knn_stream() {
+        let stream = || KnnStream::new(KnnTable::Trip, 0.01, 42);
+        let queries: Vec<_> = stream().take(10).collect();
+        assert_eq!(queries, stream().take(10).collect::<Vec<_>>());
+        assert!(queries.iter().all(|q| (1..=100).contains(&q.k)));
+        assert_ne!(queries[0].center, queries[1].center);
+        assert!(stream().with_k(7).take(10).all(|q| q.k == 7));
+    }
+
+    #[test]
+    fn test_trip_ground_truth() {
+        let scale_factor = 0.001;
+        for seed in [0, 42] {
+            let dataset = Dataset::new(scale_factor).with_seed(seed);
+            let truth = KnnGroundTruth::new(KnnTable::Trip, dataset);
+            let trips: Vec<_> = dataset.trips().collect();
+            for query in KnnStream::new(KnnTable::Trip, scale_factor, 1).take(5) {
+                // a brute force search
+                let mut expected: Vec<(i64, f64)> = trips
+                    .iter()
+                    .map(|trip| {
+                        let (x, y) = (trip.t_pickuploc.x(), trip.t_pickuploc.y());
+                        let distance = (x - query.center.x()).hypot(y - query.center.y());
+                        (trip.t_tripkey, distance)
+                    })
+                    .collect();
+                expected.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+                expected.truncate(query.k);
+                assert_eq!(truth.neighbors(&query), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sql() {
+        let query = KnnQuery {
+            table: KnnTable::Building,
+            center: Point::new(-111.761, 34.8697),
+            k: 5,
+        };
+        let sql = query.sql(Dialect::Postgis);
+        assert!(sql.contains(
+            "ORDER BY ST_GeomFromWKB(b_boundary) <-> ST_GeomFromText('POINT (-111.7610 34.8697)'), b_buildingkey"
+        ));
+        assert!(sql.ends_with("LIMIT 5;\n"));
+        let sql = query.sql(Dialect::Bigquery);
+        assert!(sql
+            .contains("ST_DISTANCE(ST_GEOGFROMWKB(b_boundary), ST_GEOGPOINT(-111.7610, 34.8697))"));
+    }
+}
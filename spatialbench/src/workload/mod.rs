@@ -0,0 +1,565 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Spatial Bench query workload: [`Query`], [`Dialect`], [`QueryParameters`]
+//! and [`ParameterStream`]
+//!
+//! The benchmark queries (range queries, kNN, distance joins and spatial
+//! joins) are templates with named substitution parameters, such as the
+//! center point of a range query or the `k` of a kNN join.
+//! [`QueryParameters::reference`] returns the parameters of the published
+//! queries, and [`QueryParameters::generate`] derives other parameters from a
+//! seed, so benchmark runs can use different but reproducible queries.
+//! [`ParameterStream`] generates any number of them, optionally with filters
+//! that select a given fraction of the trips. [`KnnStream`] generates
+//! standalone kNN queries, whose exact neighbors [`KnnGroundTruth`] computes.
+//! [`Answer`] and [`JoinCardinality`] compute the expected results and join
+//! cardinalities of the queries, on the [`Dataset`] they are run against.
+//!
+//! # Example
+//! ```
+//! # use spatialbench::workload::{Dialect, Query, QueryParameters};
+//! let params = QueryParameters::generate(Query::Q12, 1.0, 42);
+//! assert_eq!(params, QueryParameters::generate(Query::Q12, 1.0, 42));
+//!
+//! let sql = QueryParameters::reference(Query::Q1).sql(Dialect::Postgis);
+//! assert!(sql.contains("ST_DWithin(ST_GeomFromWKB(t.t_pickuploc), ST_GeomFromText('POINT (-111.7610 34.8697)'), 0.45)"));
+//! ```
+
+mod answers;
+mod cardinality;
+mod knn;
+mod params;
+mod sql;
+
+pub use answers::{Answer, AnswerValue};
+pub use cardinality::{JoinCardinality, Zones};
+pub use knn::{KnnGroundTruth, KnnQuery, KnnStream, KnnTable};
+pub use params::ParameterStream;
+
+use crate::generators::{
+    Building, BuildingGenerator, RowCounts, Trip, TripGenerator, TripPickupGenerator,
+};
+use crate::spatial::utils::hash_to_unit_u64;
+use geo::{coord, Point, Rect};
+use std::fmt::{self, Display, Write};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Degrees per meter, used to express distances for planar (`GEOMETRY`)
+/// dialects, matching `t_distance`
+const DEGREES_PER_METER: f64 = 0.000009;
+
+/// Center of the reference range queries (Sedona, Arizona)
+const SEDONA_CENTER: (f64, f64) = (-111.7610, 34.8697);
+
+/// Shared so the cache of its spatial distribution is only built once
+static TRIP_PICKUPS: OnceLock<TripPickupGenerator> = OnceLock::new();
+
+/// The generated data the queries are run against
+///
+/// Converts from a scale factor for the reference data, generated with the
+/// default seed and row counts. The other options of the generation, such as
+/// `--on-land`, are global settings of the generators.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dataset {
+    pub scale_factor: f64,
+    pub seed: u64,
+    pub row_counts: RowCounts,
+}
+
+impl Dataset {
+    /// Create the reference data at `scale_factor`
+    pub fn new(scale_factor: f64) -> Self {
+        Self {
+            scale_factor,
+            seed: 0,
+            row_counts: RowCounts::default(),
+        }
+    }
+
+    /// Set the seed the data is generated with
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the row counts the data is generated with
+    pub fn with_row_counts(mut self, row_counts: RowCounts) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Return the number of trips
+    fn trip_count(&self) -> i64 {
+        self.row_counts.trip_count(self.scale_factor)
+    }
+
+    /// Generate the trips
+    fn trips(&self) -> impl Iterator<Item = Trip> {
+        TripGenerator::new(self.scale_factor, 1, 1)
+            .with_seed(self.seed)
+            .with_row_counts(self.row_counts)
+            .iter()
+    }
+
+    /// Generate the buildings
+    fn buildings(&self) -> impl Iterator<Item = Building<'static>> {
+        BuildingGenerator::new(self.scale_factor, 1, 1)
+            .with_seed(self.seed)
+            .with_row_counts(self.row_counts)
+            .iter()
+    }
+}
+
+impl From<f64> for Dataset {
+    fn from(scale_factor: f64) -> Self {
+        Self::new(scale_factor)
+    }
+}
+
+/// The Spatial Bench queries
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Query {
+    Q1,
+    Q2,
+    Q3,
+    Q4,
+    Q5,
+    Q6,
+    Q7,
+    Q8,
+    Q9,
+    Q10,
+    Q11,
+    Q12,
+}
+
+impl Query {
+    /// All the queries, in order
+    pub const ALL: [Query; 12] = [
+        Query::Q1,
+        Query::Q2,
+        Query::Q3,
+        Query::Q4,
+        Query::Q5,
+        Query::Q6,
+        Query::Q7,
+        Query::Q8,
+        Query::Q9,
+        Query::Q10,
+        Query::Q11,
+        Query::Q12,
+    ];
+
+    /// Return the number of the query, e.g. `3` for [`Query::Q3`]
+    pub fn number(&self) -> i32 {
+        *self as i32 + 1
+    }
+
+    /// Return true if the size of the filter of the query can be chosen to
+    /// select a fraction of the trips, see [`ParameterStream::with_selectivity`]
+    pub fn has_selectivity(&self) -> bool {
+        matches!(self, Query::Q1 | Query::Q3 | Query::Q4 | Query::Q6)
+    }
+
+    /// Return a one line description of the query
+    pub fn description(&self) -> &'static str {
+        match self {
+            Query::Q1 => "Find trips starting within a radius of a point, ordered by distance",
+            Query::Q2 => "Count trips starting within Coconino County (Arizona) zone",
+            Query::Q3 => "Monthly trip statistics within a distance of a query window",
+            Query::Q4 => "Zone distribution of the top trips by tip amount",
+            Query::Q5 => "Monthly convex hull of the dropoff locations of repeat customers",
+            Query::Q6 => "Zone statistics for trips in zones intersecting a query window",
+            Query::Q7 => "Detect route detours by comparing reported and straight line distances",
+            Query::Q8 => "Count nearby pickups for each building within a radius",
+            Query::Q9 => "Building conflation (duplicate/overlap detection via IoU)",
+            Query::Q10 => "Zone statistics for trips starting within each zone",
+            Query::Q11 => "Count trips that cross between different zones",
+            Query::Q12 => "Find the k nearest buildings to each trip pickup location (KNN join)",
+        }
+    }
+}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Q{}", self.number())
+    }
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    /// Returns the query with the given name, such as `Q3`, `q3` or `3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.strip_prefix(['Q', 'q']).unwrap_or(s);
+        Query::ALL
+            .into_iter()
+            .find(|query| number.parse() == Ok(query.number()))
+            .ok_or_else(|| format!("Invalid query {s}, expected Q1 to Q12"))
+    }
+}
+
+/// SQL dialects the queries can be written in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Dialect {
+    /// Apache Sedona (Spark SQL), the reference dialect
+    Sedona,
+    /// PostgreSQL with PostGIS
+    Postgis,
+    /// DuckDB with the spatial extension
+    Duckdb,
+    /// Google BigQuery, which uses `GEOGRAPHY` so distances and areas are
+    /// in meters instead of degrees
+    Bigquery,
+}
+
+impl Dialect {
+    /// All the dialects
+    pub const ALL: [Dialect; 4] = [
+        Dialect::Sedona,
+        Dialect::Postgis,
+        Dialect::Duckdb,
+        Dialect::Bigquery,
+    ];
+
+    /// Return the name of the dialect, such as `postgis`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dialect::Sedona => "sedona",
+            Dialect::Postgis => "postgis",
+            Dialect::Duckdb => "duckdb",
+            Dialect::Bigquery => "bigquery",
+        }
+    }
+
+    /// Return true if the dialect uses spherical `GEOGRAPHY` types
+    fn is_geography(&self) -> bool {
+        matches!(self, Dialect::Bigquery)
+    }
+}
+
+impl Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    /// Returns the dialect with the given name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Dialect::ALL
+            .into_iter()
+            .find(|dialect| dialect.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("Invalid dialect {s}, expected one of sedona, postgis, duckdb, bigquery")
+            })
+    }
+}
+
+/// The value of a query substitution parameter
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ParameterValue {
+    /// A longitude / latitude point
+    Point(Point),
+    /// A longitude / latitude query window
+    Window(Rect),
+    /// A distance in meters
+    Distance(f64),
+    /// A number of rows, such as the `k` of a kNN join
+    Count(i64),
+}
+
+impl ParameterValue {
+    /// Return the value as a SQL expression in `dialect`
+    fn to_sql(self, dialect: Dialect) -> String {
+        match (self, dialect.is_geography()) {
+            (ParameterValue::Point(point), false) => {
+                format!("ST_GeomFromText('{}')", point_wkt(point))
+            }
+            (ParameterValue::Point(point), true) => {
+                format!("ST_GEOGPOINT({:.4}, {:.4})", point.x(), point.y())
+            }
+            (ParameterValue::Window(rect), false) => {
+                format!("ST_GeomFromText('{}')", window_wkt(rect))
+            }
+            // the edges of the window are lines of constant latitude
+            (ParameterValue::Window(rect), true) => {
+                format!("ST_GEOGFROMTEXT('{}', planar => TRUE)", window_wkt(rect))
+            }
+            (ParameterValue::Distance(meters), false) => format_number(meters * DEGREES_PER_METER),
+            (ParameterValue::Distance(meters), true) => format_number(meters),
+            (ParameterValue::Count(count), _) => count.to_string(),
+        }
+    }
+}
+
+impl Display for ParameterValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterValue::Point(point) => write!(f, "{}", point_wkt(*point)),
+            ParameterValue::Window(rect) => write!(f, "{}", window_wkt(*rect)),
+            ParameterValue::Distance(meters) => write!(f, "{}m", format_number(*meters)),
+            ParameterValue::Count(count) => write!(f, "{count}"),
+        }
+    }
+}
+
+fn point_wkt(point: Point) -> String {
+    format!("POINT ({:.4} {:.4})", point.x(), point.y())
+}
+
+fn window_wkt(rect: Rect) -> String {
+    let (min, max) = (rect.min(), rect.max());
+    format!(
+        "POLYGON(({0:.4} {1:.4}, {2:.4} {1:.4}, {2:.4} {3:.4}, {0:.4} {3:.4}, {0:.4} {1:.4}))",
+        min.x, min.y, max.x, max.y
+    )
+}
+
+/// Format `value` with at most 6 decimal places, without trailing zeros
+fn format_number(value: f64) -> String {
+    let formatted = format!("{value:.6}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// The substitution parameters of one [`Query`]
+///
+/// Queries without parameters, such as [`Query::Q7`], have no values.
+///
+/// # Example
+/// ```
+/// # use spatialbench::workload::{Dialect, ParameterValue, Query, QueryParameters};
+/// let params = QueryParameters::reference(Query::Q12);
+/// assert_eq!(params.get("k"), Some(ParameterValue::Count(5)));
+///
+/// // parameters derived from a seed are different, but reproducible
+/// let params = QueryParameters::generate(Query::Q1, 10.0, 7);
+/// println!("{}", params.sql(Dialect::Duckdb));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryParameters {
+    query: Query,
+    values: Vec<(&'static str, ParameterValue)>,
+}
+
+impl QueryParameters {
+    /// Return the parameters of the published benchmark queries
+    pub fn reference(query: Query) -> Self {
+        let values = match query {
+            Query::Q1 => vec![
+                ("center", ParameterValue::Point(SEDONA_CENTER.into())),
+                ("radius", ParameterValue::Distance(50_000.0)),
+            ],
+            Query::Q3 => vec![
+                (
+                    "window",
+                    ParameterValue::Window(window(SEDONA_CENTER.into(), 0.145, 0.135)),
+                ),
+                ("buffer", ParameterValue::Distance(5_000.0)),
+            ],
+            Query::Q4 => vec![("limit", ParameterValue::Count(1000))],
+            Query::Q6 => vec![(
+                "window",
+                ParameterValue::Window(window(SEDONA_CENTER.into(), 0.45, 0.45)),
+            )],
+            Query::Q8 => vec![("radius", ParameterValue::Distance(500.0))],
+            Query::Q12 => vec![("k", ParameterValue::Count(5))],
+            Query::Q2 | Query::Q5 | Query::Q7 | Query::Q9 | Query::Q10 | Query::Q11 => vec![],
+        };
+        Self { query, values }
+    }
+
+    /// Return parameters derived from `seed` for the data at `scale_factor`
+    ///
+    /// The center points and query windows of range queries are moved to the
+    /// pickup location of a trip chosen by the seed, so they cover data with
+    /// the same skew as the generated trips, and the sizes of the windows are
+    /// kept. Row counts and join distances are drawn from ranges around the
+    /// reference values. The same seed and scale factor always return the
+    /// same parameters.
+    pub fn generate(query: Query, scale_factor: f64, seed: u64) -> Self {
+        // each query and parameter draws from an independent stream
+        let random = |index: u64| hash_to_unit_u64(seed, ((query.number() as u64) << 8) | index);
+        let between =
+            |index: u64, min: i64, max: i64| min + (random(index) * (max - min + 1) as f64) as i64;
+
+        let trip_count = TripGenerator::calculate_row_count(scale_factor, 1, 1);
+        let center = TRIP_PICKUPS
+            .get_or_init(TripPickupGenerator::new)
+            .pickup_location(between(0, 1, trip_count));
+
+        let mut params = Self::reference(query);
+        for (index, (name, value)) in params.values.iter_mut().enumerate() {
+            let index = index as u64 + 1;
+            *value = match (*name, *value) {
+                (_, ParameterValue::Point(_)) => ParameterValue::Point(center),
+                (_, ParameterValue::Window(rect)) => {
+                    ParameterValue::Window(window(center, rect.width() / 2.0, rect.height() / 2.0))
+                }
+                ("limit", _) => ParameterValue::Count(between(index, 500, 2000)),
+                ("k", _) => ParameterValue::Count(between(index, 1, 10)),
+                // distance joins, rounded to 10m
+                ("radius", _) if query == Query::Q8 => {
+                    ParameterValue::Distance(between(index, 25, 100) as f64 * 10.0)
+                }
+                (_, value) => value,
+            };
+        }
+        params
+    }
+
+    /// Return the query these are the parameters of
+    pub fn query(&self) -> Query {
+        self.query
+    }
+
+    /// Return the names and values of the parameters
+    pub fn values(&self) -> &[(&'static str, ParameterValue)] {
+        &self.values
+    }
+
+    /// Return the value of the parameter called `name`, if any
+    pub fn get(&self, name: &str) -> Option<ParameterValue> {
+        self.values
+            .iter()
+            .find(|(value_name, _)| *value_name == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Replace the value of the parameter called `name`, if any
+    pub(super) fn set(&mut self, name: &str, value: ParameterValue) {
+        if let Some((_, old)) = self
+            .values
+            .iter_mut()
+            .find(|(value_name, _)| *value_name == name)
+        {
+            *old = value;
+        }
+    }
+
+    /// Return the query in `dialect` with these parameters substituted
+    ///
+    /// The query starts with a comment describing it and its parameters, and
+    /// ends with a `;`.
+    pub fn sql(&self, dialect: Dialect) -> String {
+        let mut sql = format!("-- {}: {}\n", self.query, self.query.description());
+        if !self.values.is_empty() {
+            let values: Vec<_> = self
+                .values
+                .iter()
+                .map(|(name, value)| format!("{name} = {value}"))
+                .collect();
+            writeln!(sql, "-- Parameters: {}", values.join(", ")).expect("write to string");
+        }
+
+        let mut body = sql::template(self.query, dialect).trim().to_string();
+        for (name, value) in &self.values {
+            body = body.replace(&format!(":{name}"), &value.to_sql(dialect));
+        }
+        sql.push_str(&body);
+        sql.push_str(";\n");
+        sql
+    }
+}
+
+/// Return the window extending `half_width` and `half_height` degrees around
+/// `center`, clamped to valid longitudes and latitudes
+fn window(center: Point, half_width: f64, half_height: f64) -> Rect {
+    Rect::new(
+        coord! {
+            x: (center.x() - half_width).max(-180.0),
+            y: (center.y() - half_height).max(-90.0),
+        },
+        coord! {
+            x: (center.x() + half_width).min(180.0),
+            y: (center.y() + half_height).min(90.0),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_queries() {
+        let sql = QueryParameters::reference(Query::Q3).sql(Dialect::Sedona);
+        assert!(sql.starts_with("-- Q3: Monthly trip statistics"));
+        assert!(sql.contains("ST_GeomFromText('POLYGON((-111.9060 34.7347, -111.6160 34.7347, -111.6160 35.0047, -111.9060 35.0047, -111.9060 34.7347))'), 0.045)"));
+        assert!(sql.ends_with("ORDER BY pickup_month;\n"));
+
+        // distances are in meters for GEOGRAPHY
+        let sql = QueryParameters::reference(Query::Q8).sql(Dialect::Bigquery);
+        assert!(sql.contains("ST_GEOGFROMWKB(b.b_boundary), 500)"));
+
+        // every parameter is substituted in every dialect
+        for dialect in Dialect::ALL {
+            for query in Query::ALL {
+                let sql = QueryParameters::generate(query, 0.1, 1).sql(dialect);
+                let body = sql.lines().filter(|line| !line.starts_with("-- "));
+                for line in body {
+                    assert!(!line.contains(" :"), "{query} {dialect}: {line}");
+                }
+                assert_eq!(query.to_string().parse(), Ok(query));
+                assert_eq!(dialect.to_string().parse(), Ok(dialect));
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_parameters() {
+        for query in Query::ALL {
+            let params = QueryParameters::generate(query, 1.0, 42);
+            assert_eq!(params, QueryParameters::generate(query, 1.0, 42));
+            let names: Vec<_> = params.values().iter().map(|(name, _)| *name).collect();
+            let reference = QueryParameters::reference(query);
+            let reference_names: Vec<_> =
+                reference.values().iter().map(|(name, _)| *name).collect();
+            assert_eq!(names, reference_names);
+        }
+
+        // the center is the pickup location of a trip, and differs by seed
+        let center = |seed| QueryParameters::generate(Query::Q1, 1.0, seed).get("center");
+        assert_ne!(center(1), center(2));
+        let Some(ParameterValue::Point(point)) = center(1) else {
+            panic!("expected a point");
+        };
+        assert!((-180.0..=180.0).contains(&point.x()) && (-90.0..=90.0).contains(&point.y()));
+
+        // windows keep the size of the reference window
+        let params = QueryParameters::generate(Query::Q6, 1.0, 3);
+        let Some(ParameterValue::Window(rect)) = params.get("window") else {
+            panic!("expected a window");
+        };
+        assert!(rect.width() <= 0.9 + 1e-9 && rect.height() <= 0.9 + 1e-9);
+
+        for seed in 0..100 {
+            let params = QueryParameters::generate(Query::Q12, 1.0, seed);
+            let Some(ParameterValue::Count(k)) = params.get("k") else {
+                panic!("expected a count");
+            };
+            assert!((1..=10).contains(&k));
+        }
+    }
+}
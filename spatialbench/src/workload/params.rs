@@ -0,0 +1,244 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Streams of substitution parameters, see [`ParameterStream`]
+
+use super::{window, ParameterValue, Query, QueryParameters, DEGREES_PER_METER, TRIP_PICKUPS};
+use crate::generators::{TripGenerator, TripPickupGenerator};
+use crate::spatial::utils::spider_seed_for_index;
+use geo::Point;
+
+/// Number of trip pickup locations sampled to estimate selectivities
+const SAMPLE_SIZE: i64 = 10_000;
+
+/// Fewest sampled trips a distance is estimated from. Smaller selectivities
+/// are extrapolated assuming the trips are uniform close to the center.
+const MIN_SAMPLED_TRIPS: usize = 10;
+
+/// Iterator over reproducible substitution parameters for one query
+///
+/// Each item is [`QueryParameters::generate`]d from its own seed derived from
+/// the stream seed, so a benchmark harness can run thousands of variants of
+/// a query, and the `n`-th item is always the same for the same query, scale
+/// factor and seed.
+///
+/// With [`Self::with_selectivity`], the size of the filter of each variant is
+/// chosen so it selects about that fraction of the trip table:
+///
+/// * `Q1`: the radius around the center
+/// * `Q3`: the (square) query window, keeping the buffer distance
+/// * `Q6`: the (square) query window
+/// * `Q4`: the number of top trips
+///
+/// Selectivities are estimated from a sample of the trip pickup locations
+/// with planar distances, as used by the `GEOMETRY` dialects. The joins
+/// (`Q8`, `Q12`) and the queries without parameters have no selectivity to
+/// control, see [`Query::has_selectivity`].
+///
+/// # Example
+/// ```
+/// # use spatialbench::workload::{ParameterStream, ParameterValue, Query};
+/// let stream = ParameterStream::new(Query::Q4, 1.0, 42).with_selectivity(0.001);
+/// for params in stream.take(3) {
+///     assert_eq!(params.get("limit"), Some(ParameterValue::Count(6000)));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParameterStream {
+    query: Query,
+    scale_factor: f64,
+    seed: u64,
+    selectivity: Option<f64>,
+    /// Pickup locations of evenly spaced trips, sampled on first use
+    sample: Option<Vec<Point>>,
+    index: u64,
+}
+
+impl ParameterStream {
+    /// Create a stream of parameters for `query` on data at `scale_factor`
+    pub fn new(query: Query, scale_factor: f64, seed: u64) -> Self {
+        Self {
+            query,
+            scale_factor,
+            seed,
+            selectivity: None,
+            sample: None,
+            index: 0,
+        }
+    }
+
+    /// Choose filter sizes that select about `selectivity` of the trips
+    ///
+    /// `selectivity` is a fraction, clamped to `(0, 1]`. It has no effect
+    /// for queries without a selectivity.
+    pub fn with_selectivity(mut self, selectivity: f64) -> Self {
+        self.selectivity = Some(selectivity.clamp(f64::MIN_POSITIVE, 1.0));
+        self
+    }
+
+    /// Return the query the parameters are for
+    pub fn query(&self) -> Query {
+        self.query
+    }
+
+    fn trip_count(&self) -> i64 {
+        TripGenerator::calculate_row_count(self.scale_factor, 1, 1)
+    }
+
+    /// Return the distance (in degrees) from `center` within which about
+    /// `selectivity` of the trips are, using `distance` between points
+    fn distance_quantile(
+        &mut self,
+        center: Point,
+        selectivity: f64,
+        distance: impl Fn(Point, Point) -> f64,
+    ) -> f64 {
+        let trip_count = self.trip_count();
+        let sample = self.sample.get_or_insert_with(|| {
+            let pickups = TRIP_PICKUPS.get_or_init(TripPickupGenerator::new);
+            let step = (trip_count as f64 / SAMPLE_SIZE as f64).max(1.0);
+            (0..SAMPLE_SIZE.min(trip_count))
+                .map(|i| pickups.pickup_location(1 + (i as f64 * step) as i64))
+                .collect()
+        });
+        let mut distances: Vec<f64> = sample.iter().map(|&p| distance(center, p)).collect();
+
+        let wanted = selectivity * distances.len() as f64;
+        let rank = (wanted.ceil() as usize).clamp(MIN_SAMPLED_TRIPS, distances.len());
+        let (_, &mut quantile, _) = distances.select_nth_unstable_by(rank - 1, f64::total_cmp);
+        if wanted < rank as f64 {
+            // the number of trips within a distance grows with its square
+            quantile * (wanted / rank as f64).sqrt()
+        } else {
+            quantile
+        }
+    }
+
+    /// Resize the filters of `params` to select `selectivity` of the trips
+    fn apply_selectivity(&mut self, params: &mut QueryParameters, selectivity: f64) {
+        let chebyshev = |a: Point, b: Point| (a.x() - b.x()).abs().max((a.y() - b.y()).abs());
+        match self.query {
+            Query::Q1 => {
+                let Some(ParameterValue::Point(center)) = params.get("center") else {
+                    return;
+                };
+                let radius = self.distance_quantile(center, selectivity, |a, b| {
+                    (a.x() - b.x()).hypot(a.y() - b.y())
+                });
+                params.set(
+                    "radius",
+                    ParameterValue::Distance(radius / DEGREES_PER_METER),
+                );
+            }
+            Query::Q3 | Query::Q6 => {
+                let Some(ParameterValue::Window(rect)) = params.get("window") else {
+                    return;
+                };
+                // trips within the buffer of a window are selected as well
+                let buffer = match params.get("buffer") {
+                    Some(ParameterValue::Distance(meters)) => meters * DEGREES_PER_METER,
+                    _ => 0.0,
+                };
+                let center = rect.center().into();
+                let half_size = self.distance_quantile(center, selectivity, chebyshev) - buffer;
+                params.set(
+                    "window",
+                    ParameterValue::Window(window(center, half_size.max(0.0), half_size.max(0.0))),
+                );
+            }
+            Query::Q4 => {
+                let limit = (selectivity * self.trip_count() as f64).round().max(1.0);
+                params.set("limit", ParameterValue::Count(limit as i64));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Iterator for ParameterStream {
+    type Item = QueryParameters;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let seed = spider_seed_for_index(self.index, spider_seed_for_index(self.seed, 0));
+        self.index += 1;
+
+        let mut params = QueryParameters::generate(self.query, self.scale_factor, seed);
+        if let Some(selectivity) = self.selectivity {
+            self.apply_selectivity(&mut params, selectivity);
+        }
+        Some(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameter_stream() {
+        let stream = || ParameterStream::new(Query::Q3, 0.1, 42);
+        let first: Vec<_> = stream().take(5).collect();
+        assert_eq!(first, stream().take(5).collect::<Vec<_>>());
+        assert_ne!(first[0], first[1]);
+        // other seeds give other parameters
+        let other: Vec<_> = ParameterStream::new(Query::Q3, 0.1, 43).take(5).collect();
+        assert!(first.iter().all(|params| !other.contains(params)));
+    }
+
+    #[test]
+    fn test_selectivity() {
+        let pickups = TRIP_PICKUPS.get_or_init(TripPickupGenerator::new);
+        let trip_count = TripGenerator::calculate_row_count(0.1, 1, 1);
+        let trips: Vec<Point> = (1..=trip_count)
+            .map(|key| pickups.pickup_location(key))
+            .collect();
+
+        // the radius of Q1 selects about 5% of the trips
+        let stream = ParameterStream::new(Query::Q1, 0.1, 7).with_selectivity(0.05);
+        for params in stream.take(3) {
+            let (Some(ParameterValue::Point(center)), Some(ParameterValue::Distance(radius))) =
+                (params.get("center"), params.get("radius"))
+            else {
+                panic!("expected a center and radius");
+            };
+            let radius = radius * DEGREES_PER_METER;
+            let selected = trips
+                .iter()
+                .filter(|p| (p.x() - center.x()).hypot(p.y() - center.y()) <= radius)
+                .count();
+            let selectivity = selected as f64 / trip_count as f64;
+            assert!((0.04..0.06).contains(&selectivity), "{selectivity}");
+        }
+
+        // the window of Q6 selects about 1% of the trips
+        let stream = ParameterStream::new(Query::Q6, 0.1, 7).with_selectivity(0.01);
+        for params in stream.take(3) {
+            let Some(ParameterValue::Window(rect)) = params.get("window") else {
+                panic!("expected a window");
+            };
+            let selected = trips
+                .iter()
+                .filter(|p| {
+                    (rect.min().x..=rect.max().x).contains(&p.x())
+                        && (rect.min().y..=rect.max().y).contains(&p.y())
+                })
+                .count();
+            let selectivity = selected as f64 / trip_count as f64;
+            assert!((0.007..0.013).contains(&selectivity), "{selectivity}");
+        }
+    }
+}
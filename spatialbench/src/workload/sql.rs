@@ -0,0 +1,462 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! SQL templates of the benchmark queries in each [`Dialect`]
+//!
+//! The Sedona (Spark SQL) templates are the reference, and match
+//! `spatialbench-queries/print_queries.py`. The other dialects only override
+//! the queries that need to change, except for BigQuery whose `GEOGRAPHY`
+//! functions differ throughout. Substitution parameters are written as
+//! `:name`, see [`super::QueryParameters`].
+
+use super::{Dialect, Query};
+
+/// Return the template of `query` in `dialect`
+pub(super) fn template(query: Query, dialect: Dialect) -> &'static str {
+    match (dialect, query) {
+        (Dialect::Postgis, Query::Q5) => POSTGIS_Q5,
+        (Dialect::Postgis, Query::Q12) => POSTGIS_Q12,
+        (Dialect::Duckdb, Query::Q12) => DUCKDB_Q12,
+        (Dialect::Bigquery, query) => bigquery(query),
+        (_, query) => sedona(query),
+    }
+}
+
+fn sedona(query: Query) -> &'static str {
+    match query {
+        Query::Q1 => SEDONA_Q1,
+        Query::Q2 => SEDONA_Q2,
+        Query::Q3 => SEDONA_Q3,
+        Query::Q4 => SEDONA_Q4,
+        Query::Q5 => SEDONA_Q5,
+        Query::Q6 => SEDONA_Q6,
+        Query::Q7 => SEDONA_Q7,
+        Query::Q8 => SEDONA_Q8,
+        Query::Q9 => SEDONA_Q9,
+        Query::Q10 => SEDONA_Q10,
+        Query::Q11 => SEDONA_Q11,
+        Query::Q12 => SEDONA_Q12,
+    }
+}
+
+fn bigquery(query: Query) -> &'static str {
+    match query {
+        Query::Q1 => BIGQUERY_Q1,
+        Query::Q2 => BIGQUERY_Q2,
+        Query::Q3 => BIGQUERY_Q3,
+        Query::Q4 => BIGQUERY_Q4,
+        Query::Q5 => BIGQUERY_Q5,
+        Query::Q6 => BIGQUERY_Q6,
+        Query::Q7 => BIGQUERY_Q7,
+        Query::Q8 => BIGQUERY_Q8,
+        Query::Q9 => BIGQUERY_Q9,
+        Query::Q10 => BIGQUERY_Q10,
+        Query::Q11 => BIGQUERY_Q11,
+        Query::Q12 => BIGQUERY_Q12,
+    }
+}
+
+const SEDONA_Q1: &str = r#"
+SELECT
+   t.t_tripkey, ST_X(ST_GeomFromWKB(t.t_pickuploc)) AS pickup_lon, ST_Y(ST_GeomFromWKB(t.t_pickuploc)) AS pickup_lat, t.t_pickuptime,
+   ST_Distance(ST_GeomFromWKB(t.t_pickuploc), :center) AS distance_to_center
+FROM trip t
+WHERE ST_DWithin(ST_GeomFromWKB(t.t_pickuploc), :center, :radius)
+ORDER BY distance_to_center ASC, t.t_tripkey ASC
+"#;
+
+const SEDONA_Q2: &str = r#"
+SELECT COUNT(*) AS trip_count_in_coconino_county
+FROM trip t
+WHERE ST_Intersects(ST_GeomFromWKB(t.t_pickuploc), (SELECT ST_GeomFromWKB(z.z_boundary) FROM zone z WHERE z.z_name = 'Coconino County' LIMIT 1))
+"#;
+
+const SEDONA_Q3: &str = r#"
+SELECT
+   DATE_TRUNC('month', t.t_pickuptime) AS pickup_month, COUNT(t.t_tripkey) AS total_trips,
+   AVG(t.t_distance) AS avg_distance, AVG(t.t_dropofftime - t.t_pickuptime) AS avg_duration,
+   AVG(t.t_fare) AS avg_fare
+FROM trip t
+WHERE ST_DWithin(ST_GeomFromWKB(t.t_pickuploc), :window, :buffer)
+GROUP BY pickup_month
+ORDER BY pickup_month
+"#;
+
+const SEDONA_Q4: &str = r#"
+SELECT z.z_zonekey, z.z_name, COUNT(*) AS trip_count
+FROM
+   zone z
+       JOIN (
+       SELECT t.t_pickuploc
+       FROM trip t
+       ORDER BY t.t_tip DESC, t.t_tripkey ASC
+           LIMIT :limit
+   ) top_trips ON ST_Within(ST_GeomFromWKB(top_trips.t_pickuploc), ST_GeomFromWKB(z.z_boundary))
+GROUP BY z.z_zonekey, z.z_name
+ORDER BY trip_count DESC, z.z_zonekey ASC
+"#;
+
+const SEDONA_Q5: &str = r#"
+SELECT
+   c.c_custkey, c.c_name AS customer_name,
+   DATE_TRUNC('month', t.t_pickuptime) AS pickup_month,
+   ST_Area(ST_ConvexHull(ST_Collect(ARRAY_AGG(ST_GeomFromWKB(t.t_dropoffloc))))) AS monthly_travel_hull_area,
+   COUNT(*) as dropoff_count
+FROM trip t JOIN customer c ON t.t_custkey = c.c_custkey
+GROUP BY c.c_custkey, c.c_name, pickup_month
+HAVING dropoff_count > 5 -- Only include repeat customers for meaningful hulls
+ORDER BY dropoff_count DESC, c.c_custkey ASC
+"#;
+
+const SEDONA_Q6: &str = r#"
+SELECT
+   z.z_zonekey, z.z_name,
+   COUNT(t.t_tripkey) AS total_pickups, AVG(t.t_totalamount) AS avg_distance,
+   AVG(t.t_dropofftime - t.t_pickuptime) AS avg_duration
+FROM trip t, zone z
+WHERE ST_Intersects(:window, ST_GeomFromWKB(z.z_boundary))
+ AND ST_Within(ST_GeomFromWKB(t.t_pickuploc), ST_GeomFromWKB(z.z_boundary))
+GROUP BY z.z_zonekey, z.z_name
+ORDER BY total_pickups DESC, z.z_zonekey ASC
+"#;
+
+const SEDONA_Q7: &str = r#"
+WITH trip_lengths AS (
+   SELECT
+       t.t_tripkey,
+       t.t_distance AS reported_distance_m,
+       ST_Length(
+               ST_MakeLine(
+                       ST_GeomFromWKB(t.t_pickuploc),
+                       ST_GeomFromWKB(t.t_dropoffloc)
+               )
+       ) / 0.000009 AS line_distance_m -- 1 meter = 0.000009 degree
+   FROM trip t
+)
+SELECT
+   t.t_tripkey,
+   t.reported_distance_m,
+   t.line_distance_m,
+   t.reported_distance_m / NULLIF(t.line_distance_m, 0) AS detour_ratio
+FROM trip_lengths t
+ORDER BY detour_ratio DESC NULLS LAST, reported_distance_m DESC, t_tripkey ASC
+"#;
+
+const SEDONA_Q8: &str = r#"
+SELECT b.b_buildingkey, b.b_name, COUNT(*) AS nearby_pickup_count
+FROM trip t JOIN building b ON ST_DWithin(ST_GeomFromWKB(t.t_pickuploc), ST_GeomFromWKB(b.b_boundary), :radius)
+GROUP BY b.b_buildingkey, b.b_name
+ORDER BY nearby_pickup_count DESC, b.b_buildingkey ASC
+"#;
+
+const SEDONA_Q9: &str = r#"
+WITH b1 AS (
+   SELECT b_buildingkey AS id, ST_GeomFromWKB(b_boundary) AS geom
+   FROM building
+),
+    b2 AS (
+        SELECT b_buildingkey AS id, ST_GeomFromWKB(b_boundary) AS geom
+        FROM building
+    ),
+    pairs AS (
+        SELECT
+            b1.id AS building_1,
+            b2.id AS building_2,
+            ST_Area(b1.geom) AS area1,
+            ST_Area(b2.geom) AS area2,
+            ST_Area(ST_Intersection(b1.geom, b2.geom)) AS overlap_area
+        FROM b1
+                 JOIN b2
+                      ON b1.id < b2.id
+                          AND ST_Intersects(b1.geom, b2.geom)
+    )
+SELECT
+   building_1,
+   building_2,
+   area1,
+   area2,
+   overlap_area,
+   CASE
+       WHEN overlap_area = 0 THEN 0.0
+       WHEN (area1 + area2 - overlap_area) = 0 THEN 1.0
+       ELSE overlap_area / (area1 + area2 - overlap_area)
+       END AS iou
+FROM pairs
+ORDER BY iou DESC, building_1 ASC, building_2 ASC
+"#;
+
+const SEDONA_Q10: &str = r#"
+SELECT
+   z.z_zonekey, z.z_name AS pickup_zone, AVG(t.t_dropofftime - t.t_pickuptime) AS avg_duration,
+   AVG(t.t_distance) AS avg_distance, COUNT(t.t_tripkey) AS num_trips
+FROM zone z LEFT JOIN trip t ON ST_Within(ST_GeomFromWKB(t.t_pickuploc), ST_GeomFromWKB(z.z_boundary))
+GROUP BY z.z_zonekey, z.z_name
+ORDER BY avg_duration DESC NULLS LAST, z.z_zonekey ASC
+"#;
+
+const SEDONA_Q11: &str = r#"
+SELECT COUNT(*) AS cross_zone_trip_count
+FROM
+   trip t
+       JOIN zone pickup_zone ON ST_Within(ST_GeomFromWKB(t.t_pickuploc), ST_GeomFromWKB(pickup_zone.z_boundary))
+       JOIN zone dropoff_zone ON ST_Within(ST_GeomFromWKB(t.t_dropoffloc), ST_GeomFromWKB(dropoff_zone.z_boundary))
+WHERE pickup_zone.z_zonekey != dropoff_zone.z_zonekey
+"#;
+
+const SEDONA_Q12: &str = r#"
+WITH trip_with_geom AS (
+   SELECT t_tripkey, t_pickuploc, ST_GeomFromWKB(t_pickuploc) as pickup_geom
+   FROM trip
+),
+    building_with_geom AS (
+        SELECT b_buildingkey, b_name, b_boundary, ST_GeomFromWKB(b_boundary) as boundary_geom
+        FROM building
+    )
+SELECT
+   t.t_tripkey,
+   t.t_pickuploc,
+   b.b_buildingkey,
+   b.b_name AS building_name,
+   ST_Distance(t.pickup_geom, b.boundary_geom) AS distance_to_building
+FROM trip_with_geom t JOIN building_with_geom b
+                          ON ST_KNN(t.pickup_geom, b.boundary_geom, :k, FALSE)
+ORDER BY distance_to_building ASC, b.b_buildingkey ASC
+"#;
+
+// PostgreSQL does not allow output column aliases in HAVING
+const POSTGIS_Q5: &str = r#"
+SELECT
+   c.c_custkey, c.c_name AS customer_name,
+   DATE_TRUNC('month', t.t_pickuptime) AS pickup_month,
+   ST_Area(ST_ConvexHull(ST_Collect(ARRAY_AGG(ST_GeomFromWKB(t.t_dropoffloc))))) AS monthly_travel_hull_area,
+   COUNT(*) as dropoff_count
+FROM trip t JOIN customer c ON t.t_custkey = c.c_custkey
+GROUP BY c.c_custkey, c.c_name, pickup_month
+HAVING COUNT(*) > 5 -- Only include repeat customers for meaningful hulls
+ORDER BY dropoff_count DESC, c.c_custkey ASC
+"#;
+
+// No KNN join, using cross join lateral with the `<->` distance operator instead
+const POSTGIS_Q12: &str = r#"
+SELECT
+   t.t_tripkey,
+   t.t_pickuploc,
+   nb.b_buildingkey,
+   nb.building_name,
+   nb.distance_to_building
+FROM trip t
+        CROSS JOIN LATERAL (
+   SELECT
+       b.b_buildingkey,
+       b.b_name AS building_name,
+       ST_Distance(ST_GeomFromWKB(t.t_pickuploc), ST_GeomFromWKB(b.b_boundary)) AS distance_to_building
+   FROM building b
+   ORDER BY ST_GeomFromWKB(t.t_pickuploc) <-> ST_GeomFromWKB(b.b_boundary)
+       LIMIT :k
+) AS nb
+ORDER BY nb.distance_to_building, nb.b_buildingkey
+"#;
+
+// No KNN join, using cross join lateral instead
+const DUCKDB_Q12: &str = r#"
+SELECT
+   t.t_tripkey,
+   t.t_pickuploc,
+   nb.b_buildingkey,
+   nb.building_name,
+   nb.distance_to_building
+FROM trip t
+        CROSS JOIN LATERAL (
+   SELECT
+       b.b_buildingkey,
+       b.b_name AS building_name,
+       ST_Distance(ST_GeomFromWKB(t.t_pickuploc), ST_GeomFromWKB(b.b_boundary)) AS distance_to_building
+   FROM building b
+   ORDER BY distance_to_building
+       LIMIT :k
+) AS nb
+ORDER BY nb.distance_to_building, nb.b_buildingkey
+"#;
+
+const BIGQUERY_Q1: &str = r#"
+SELECT
+   t.t_tripkey, ST_X(ST_GEOGFROMWKB(t.t_pickuploc)) AS pickup_lon, ST_Y(ST_GEOGFROMWKB(t.t_pickuploc)) AS pickup_lat, t.t_pickuptime,
+   ST_DISTANCE(ST_GEOGFROMWKB(t.t_pickuploc), :center) AS distance_to_center
+FROM trip t
+WHERE ST_DWITHIN(ST_GEOGFROMWKB(t.t_pickuploc), :center, :radius)
+ORDER BY distance_to_center ASC, t.t_tripkey ASC
+"#;
+
+const BIGQUERY_Q2: &str = r#"
+SELECT COUNT(*) AS trip_count_in_coconino_county
+FROM trip t
+WHERE ST_INTERSECTS(ST_GEOGFROMWKB(t.t_pickuploc), (SELECT ST_GEOGFROMWKB(z.z_boundary) FROM zone z WHERE z.z_name = 'Coconino County' LIMIT 1))
+"#;
+
+const BIGQUERY_Q3: &str = r#"
+SELECT
+   TIMESTAMP_TRUNC(t.t_pickuptime, MONTH) AS pickup_month, COUNT(t.t_tripkey) AS total_trips,
+   AVG(t.t_distance) AS avg_distance, AVG(TIMESTAMP_DIFF(t.t_dropofftime, t.t_pickuptime, SECOND)) AS avg_duration,
+   AVG(t.t_fare) AS avg_fare
+FROM trip t
+WHERE ST_DWITHIN(ST_GEOGFROMWKB(t.t_pickuploc), :window, :buffer)
+GROUP BY pickup_month
+ORDER BY pickup_month
+"#;
+
+const BIGQUERY_Q4: &str = r#"
+SELECT z.z_zonekey, z.z_name, COUNT(*) AS trip_count
+FROM
+   zone z
+       JOIN (
+       SELECT t.t_pickuploc
+       FROM trip t
+       ORDER BY t.t_tip DESC, t.t_tripkey ASC
+           LIMIT :limit
+   ) top_trips ON ST_WITHIN(ST_GEOGFROMWKB(top_trips.t_pickuploc), ST_GEOGFROMWKB(z.z_boundary))
+GROUP BY z.z_zonekey, z.z_name
+ORDER BY trip_count DESC, z.z_zonekey ASC
+"#;
+
+const BIGQUERY_Q5: &str = r#"
+SELECT
+   c.c_custkey, c.c_name AS customer_name,
+   TIMESTAMP_TRUNC(t.t_pickuptime, MONTH) AS pickup_month,
+   ST_AREA(ST_CONVEXHULL(ST_UNION_AGG(ST_GEOGFROMWKB(t.t_dropoffloc)))) AS monthly_travel_hull_area,
+   COUNT(*) as dropoff_count
+FROM trip t JOIN customer c ON t.t_custkey = c.c_custkey
+GROUP BY c.c_custkey, c.c_name, pickup_month
+HAVING COUNT(*) > 5 -- Only include repeat customers for meaningful hulls
+ORDER BY dropoff_count DESC, c.c_custkey ASC
+"#;
+
+const BIGQUERY_Q6: &str = r#"
+SELECT
+   z.z_zonekey, z.z_name,
+   COUNT(t.t_tripkey) AS total_pickups, AVG(t.t_totalamount) AS avg_distance,
+   AVG(TIMESTAMP_DIFF(t.t_dropofftime, t.t_pickuptime, SECOND)) AS avg_duration
+FROM trip t, zone z
+WHERE ST_INTERSECTS(:window, ST_GEOGFROMWKB(z.z_boundary))
+ AND ST_WITHIN(ST_GEOGFROMWKB(t.t_pickuploc), ST_GEOGFROMWKB(z.z_boundary))
+GROUP BY z.z_zonekey, z.z_name
+ORDER BY total_pickups DESC, z.z_zonekey ASC
+"#;
+
+// GEOGRAPHY lengths are already in meters
+const BIGQUERY_Q7: &str = r#"
+WITH trip_lengths AS (
+   SELECT
+       t.t_tripkey,
+       t.t_distance AS reported_distance_m,
+       ST_LENGTH(
+               ST_MAKELINE(
+                       ST_GEOGFROMWKB(t.t_pickuploc),
+                       ST_GEOGFROMWKB(t.t_dropoffloc)
+               )
+       ) AS line_distance_m
+   FROM trip t
+)
+SELECT
+   t.t_tripkey,
+   t.reported_distance_m,
+   t.line_distance_m,
+   t.reported_distance_m / NULLIF(t.line_distance_m, 0) AS detour_ratio
+FROM trip_lengths t
+ORDER BY detour_ratio DESC NULLS LAST, reported_distance_m DESC, t_tripkey ASC
+"#;
+
+const BIGQUERY_Q8: &str = r#"
+SELECT b.b_buildingkey, b.b_name, COUNT(*) AS nearby_pickup_count
+FROM trip t JOIN building b ON ST_DWITHIN(ST_GEOGFROMWKB(t.t_pickuploc), ST_GEOGFROMWKB(b.b_boundary), :radius)
+GROUP BY b.b_buildingkey, b.b_name
+ORDER BY nearby_pickup_count DESC, b.b_buildingkey ASC
+"#;
+
+const BIGQUERY_Q9: &str = r#"
+WITH b1 AS (
+   SELECT b_buildingkey AS id, ST_GEOGFROMWKB(b_boundary) AS geom
+   FROM building
+),
+    b2 AS (
+        SELECT b_buildingkey AS id, ST_GEOGFROMWKB(b_boundary) AS geom
+        FROM building
+    ),
+    pairs AS (
+        SELECT
+            b1.id AS building_1,
+            b2.id AS building_2,
+            ST_AREA(b1.geom) AS area1,
+            ST_AREA(b2.geom) AS area2,
+            ST_AREA(ST_INTERSECTION(b1.geom, b2.geom)) AS overlap_area
+        FROM b1
+                 JOIN b2
+                      ON b1.id < b2.id
+                          AND ST_INTERSECTS(b1.geom, b2.geom)
+    )
+SELECT
+   building_1,
+   building_2,
+   area1,
+   area2,
+   overlap_area,
+   CASE
+       WHEN overlap_area = 0 THEN 0.0
+       WHEN (area1 + area2 - overlap_area) = 0 THEN 1.0
+       ELSE overlap_area / (area1 + area2 - overlap_area)
+       END AS iou
+FROM pairs
+ORDER BY iou DESC, building_1 ASC, building_2 ASC
+"#;
+
+const BIGQUERY_Q10: &str = r#"
+SELECT
+   z.z_zonekey, z.z_name AS pickup_zone, AVG(TIMESTAMP_DIFF(t.t_dropofftime, t.t_pickuptime, SECOND)) AS avg_duration,
+   AVG(t.t_distance) AS avg_distance, COUNT(t.t_tripkey) AS num_trips
+FROM zone z LEFT JOIN trip t ON ST_WITHIN(ST_GEOGFROMWKB(t.t_pickuploc), ST_GEOGFROMWKB(z.z_boundary))
+GROUP BY z.z_zonekey, z.z_name
+ORDER BY avg_duration DESC NULLS LAST, z.z_zonekey ASC
+"#;
+
+const BIGQUERY_Q11: &str = r#"
+SELECT COUNT(*) AS cross_zone_trip_count
+FROM
+   trip t
+       JOIN zone pickup_zone ON ST_WITHIN(ST_GEOGFROMWKB(t.t_pickuploc), ST_GEOGFROMWKB(pickup_zone.z_boundary))
+       JOIN zone dropoff_zone ON ST_WITHIN(ST_GEOGFROMWKB(t.t_dropoffloc), ST_GEOGFROMWKB(dropoff_zone.z_boundary))
+WHERE pickup_zone.z_zonekey != dropoff_zone.z_zonekey
+"#;
+
+// No KNN join, using a correlated array subquery instead
+const BIGQUERY_Q12: &str = r#"
+SELECT
+   t.t_tripkey,
+   t.t_pickuploc,
+   nb.b_buildingkey,
+   nb.building_name,
+   nb.distance_to_building
+FROM trip t,
+    UNNEST(ARRAY(
+   SELECT AS STRUCT
+       b.b_buildingkey,
+       b.b_name AS building_name,
+       ST_DISTANCE(ST_GEOGFROMWKB(t.t_pickuploc), ST_GEOGFROMWKB(b.b_boundary)) AS distance_to_building
+   FROM building b
+   ORDER BY distance_to_building
+       LIMIT :k
+)) AS nb
+ORDER BY nb.distance_to_building, nb.b_buildingkey
+"#;
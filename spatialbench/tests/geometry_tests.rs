@@ -97,7 +97,10 @@ mod geometry_tests {
 
         // Check that all building polygons have counter-clockwise winding
         for building in buildings.iter() {
-            let exterior = building.b_boundary.exterior();
+            let geo::Geometry::Polygon(polygon) = &building.b_boundary else {
+                panic!("Building {} is not a polygon", building.b_buildingkey);
+            };
+            let exterior = polygon.exterior();
             assert!(
                 exterior.is_ccw(),
                 "Building {} polygon should have counter-clockwise winding",
@@ -139,7 +142,9 @@ mod geometry_tests {
         let buildings: Vec<_> = generator.iter().collect();
 
         for building in buildings {
-            let polygon = &building.b_boundary;
+            let geo::Geometry::Polygon(polygon) = &building.b_boundary else {
+                panic!("Building {} is not a polygon", building.b_buildingkey);
+            };
 
             assert_eq!(
                 crosses_dateline(polygon),